@@ -1,5 +1,6 @@
 use plonky2::fri::reduction_strategies::FriReductionStrategy;
-use plonky2::fri::{FriConfig, FriParams};
+use plonky2::fri::{FriConfig, FriParams, FriPowStrategy};
+use plonky2::util::log_size::DegreeBits;
 
 /// A configuration containing the different parameters to be used by the STARK prover.
 pub struct StarkConfig {
@@ -30,14 +31,16 @@ impl StarkConfig {
             fri_config: FriConfig {
                 rate_bits: 1,
                 cap_height: 4,
-                proof_of_work_bits: 16,
+                pow_strategy: FriPowStrategy::Single(16),
                 reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
                 num_query_rounds: 84,
+                commit_final_poly: false,
             },
         }
     }
 
     pub(crate) fn fri_params(&self, degree_bits: usize) -> FriParams {
-        self.fri_config.fri_params(degree_bits, false)
+        self.fri_config
+            .fri_params(DegreeBits::new(degree_bits), false)
     }
 }
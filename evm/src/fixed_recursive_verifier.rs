@@ -24,6 +24,7 @@ use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
 use plonky2::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
 use plonky2::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
 use plonky2::recursion::dummy_circuit::cyclic_base_proof;
+use plonky2::util::log_size::DegreeBits;
 use plonky2::util::serialization::{
     Buffer, GateSerializer, IoResult, Read, WitnessGeneratorSerializer, Write,
 };
@@ -696,7 +697,7 @@ where
         );
 
         // Pad to match the root circuit's degree.
-        while log2_ceil(builder.num_gates()) < root.circuit.common.degree_bits() {
+        while log2_ceil(builder.num_gates()) < root.circuit.common.degree_bits().get() {
             builder.add_gate(NoopGate, vec![]);
         }
 
@@ -773,7 +774,7 @@ where
         // We need to adjust a few things, but it's easier than making a new CommonCircuitData.
         let expected_common_data = CommonCircuitData {
             fri_params: FriParams {
-                degree_bits: 14,
+                degree_bits: DegreeBits::new(14),
                 ..agg.circuit.common.fri_params.clone()
             },
             ..agg.circuit.common.clone()
@@ -1595,7 +1596,7 @@ where
                 .last()
                 .map(|wrapper: &PlonkWrapperCircuit<F, C, D>| &wrapper.circuit)
                 .unwrap_or(&initial_wrapper.circuit);
-            let last_degree_bits = last.common.degree_bits();
+            let last_degree_bits = last.common.degree_bits().get();
             assert!(last_degree_bits >= THRESHOLD_DEGREE_BITS);
             if last_degree_bits == THRESHOLD_DEGREE_BITS {
                 break;
@@ -1610,7 +1611,7 @@ where
             let circuit = builder.build::<C>();
 
             assert!(
-                circuit.common.degree_bits() < last_degree_bits,
+                circuit.common.degree_bits().get() < last_degree_bits,
                 "Couldn't shrink to expected recursion threshold of 2^{}; stalled at 2^{}",
                 THRESHOLD_DEGREE_BITS,
                 circuit.common.degree_bits()
@@ -5,6 +5,7 @@ use plonky2::hash::hash_types::RichField;
 use plonky2::iop::challenger::{Challenger, RecursiveChallenger};
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::util::log_size::DegreeBits;
 
 use crate::config::StarkConfig;
 use crate::cross_table_lookup::get_grand_product_challenge_set;
@@ -241,6 +242,7 @@ where
                     commit_phase_merkle_caps,
                     final_poly,
                     pow_witness,
+                    round_pow_witnesses,
                     ..
                 },
             ..
@@ -264,7 +266,8 @@ where
                 commit_phase_merkle_caps,
                 final_poly,
                 *pow_witness,
-                degree_bits,
+                round_pow_witnesses,
+                DegreeBits::new(degree_bits),
                 &config.fri_config,
             ),
         }
@@ -290,6 +293,7 @@ impl<const D: usize> StarkProofTarget<D> {
                     commit_phase_merkle_caps,
                     final_poly,
                     pow_witness,
+                    round_pow_witnesses,
                     ..
                 },
             ..
@@ -314,6 +318,7 @@ impl<const D: usize> StarkProofTarget<D> {
                 commit_phase_merkle_caps,
                 final_poly,
                 *pow_witness,
+                round_pow_witnesses,
                 &config.fri_config,
             ),
         }
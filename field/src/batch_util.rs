@@ -8,14 +8,16 @@ const fn pack_with_leftovers_split_point<P: PackedField>(slice: &[P::Scalar]) ->
     n - n_leftover
 }
 
-fn pack_slice_with_leftovers<P: PackedField>(slice: &[P::Scalar]) -> (&[P], &[P::Scalar]) {
+pub(crate) fn pack_slice_with_leftovers<P: PackedField>(
+    slice: &[P::Scalar],
+) -> (&[P], &[P::Scalar]) {
     let split_point = pack_with_leftovers_split_point::<P>(slice);
     let (slice_packable, slice_leftovers) = slice.split_at(split_point);
     let slice_packed = P::pack_slice(slice_packable);
     (slice_packed, slice_leftovers)
 }
 
-fn pack_slice_with_leftovers_mut<P: PackedField>(
+pub(crate) fn pack_slice_with_leftovers_mut<P: PackedField>(
     slice: &mut [P::Scalar],
 ) -> (&mut [P], &mut [P::Scalar]) {
     let split_point = pack_with_leftovers_split_point::<P>(slice);
@@ -95,6 +95,23 @@ pub trait FieldExtension<const D: usize>: Field {
 
     fn from_basefield(x: Self::BaseField) -> Self;
 
+    /// The inverse of `to_base_coeffs`. Panics unless `coeffs` has exactly `D` elements.
+    fn from_base_coeffs(coeffs: &[Self::BaseField]) -> Self {
+        let arr = coeffs.try_into().unwrap_or_else(|_| {
+            panic!(
+                "Expected {} base field coefficients, got {}",
+                D,
+                coeffs.len()
+            )
+        });
+        Self::from_basefield_array(arr)
+    }
+
+    /// The `D` base field coefficients of `self`, the constant coefficient first.
+    fn to_base_coeffs(self) -> [Self::BaseField; D] {
+        self.to_basefield_array()
+    }
+
     fn is_in_basefield(&self) -> bool {
         self.to_basefield_array()[1..].iter().all(|x| x.is_zero())
     }
@@ -129,9 +146,11 @@ pub fn flatten<F: Field, const D: usize>(l: &[F::Extension]) -> Vec<F>
 where
     F: Extendable<D>,
 {
-    l.iter()
-        .flat_map(|x| x.to_basefield_array().to_vec())
-        .collect()
+    let mut flattened = Vec::with_capacity(l.len() * D);
+    for x in l {
+        flattened.extend(x.to_base_coeffs());
+    }
+    flattened
 }
 
 /// Batch every D-sized chunks into extension field elements.
@@ -141,6 +160,6 @@ where
 {
     debug_assert_eq!(l.len() % D, 0);
     l.chunks_exact(D)
-        .map(|c| F::Extension::from_basefield_array(c.to_vec().try_into().unwrap()))
+        .map(F::Extension::from_base_coeffs)
         .collect()
 }
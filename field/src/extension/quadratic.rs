@@ -1,4 +1,4 @@
-use core::fmt::{self, Debug, Display, Formatter};
+use core::fmt::{self, Debug, Display, Formatter, LowerHex};
 use core::iter::{Product, Sum};
 use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
@@ -125,6 +125,18 @@ impl<F: Extendable<2>> Display for QuadraticExtension<F> {
     }
 }
 
+/// Formats the coordinates in hex, separated by colons.
+impl<F: Extendable<2> + LowerHex> LowerHex for QuadraticExtension<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        LowerHex::fmt(&self.0[0], f)?;
+        for x in &self.0[1..] {
+            f.write_str(":")?;
+            LowerHex::fmt(x, f)?;
+        }
+        Ok(())
+    }
+}
+
 impl<F: Extendable<2>> Debug for QuadraticExtension<F> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         Display::fmt(self, f)
@@ -246,5 +258,22 @@ mod tests {
                 crate::goldilocks_field::GoldilocksField,
             >
         );
+
+        #[test]
+        fn test_formatting() {
+            use crate::extension::quadratic::QuadraticExtension;
+            use crate::goldilocks_field::GoldilocksField;
+            use crate::types::Field64;
+
+            // The first coordinate is not in canonical form.
+            let x = QuadraticExtension([
+                GoldilocksField(GoldilocksField::ORDER + 1),
+                GoldilocksField(255),
+            ]);
+            assert_eq!(format!("{x}"), "1 + 255*a");
+            assert_eq!(format!("{x:x}"), "1:ff");
+            assert_eq!(format!("{x:#x}"), "0x1:0xff");
+            assert_eq!(format!("{:x}", -x), "ffffffff00000000:fffffffeffffff02");
+        }
     }
 }
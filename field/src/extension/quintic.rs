@@ -1,4 +1,4 @@
-use core::fmt::{self, Debug, Display, Formatter};
+use core::fmt::{self, Debug, Display, Formatter, LowerHex};
 use core::iter::{Product, Sum};
 use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
@@ -146,6 +146,18 @@ impl<F: Extendable<5>> Display for QuinticExtension<F> {
     }
 }
 
+/// Formats the coordinates in hex, separated by colons.
+impl<F: Extendable<5> + LowerHex> LowerHex for QuinticExtension<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        LowerHex::fmt(&self.0[0], f)?;
+        for x in &self.0[1..] {
+            f.write_str(":")?;
+            LowerHex::fmt(x, f)?;
+        }
+        Ok(())
+    }
+}
+
 impl<F: Extendable<5>> Debug for QuinticExtension<F> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         Display::fmt(self, f)
@@ -4,6 +4,7 @@ use core::cmp::{max, min};
 use plonky2_util::{log2_strict, reverse_index_bits_in_place};
 use unroll::unroll_for_loops;
 
+use crate::goldilocks_field::GoldilocksField;
 use crate::packable::Packable;
 use crate::packed::PackedField;
 use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
@@ -94,6 +95,44 @@ pub fn ifft_with_options<F: Field>(
     PolynomialCoeffs { coeffs: buffer }
 }
 
+/// An in-place radix-2 NTT over the Goldilocks field, with both input and output in natural order.
+///
+/// The forward transform maps the coefficients of a polynomial to its values on the subgroup
+/// generated by `primitive_root_of_unity(log2(n))`, matching [`fft`]. With `inverse` set, it maps
+/// such values back to coefficients, including the `1/n` scaling, so the two are inverse to each
+/// other. Panics if the length isn't a power of two.
+pub fn goldilocks_fft(coeffs: &mut [GoldilocksField], inverse: bool) {
+    type F = GoldilocksField;
+
+    let n = coeffs.len();
+    let lg_n = log2_strict(n);
+
+    reverse_index_bits_in_place(coeffs);
+    for lg_m in 1..=lg_n {
+        let half_m = 1 << (lg_m - 1);
+        let twiddles = if inverse {
+            F::twiddles_inverse(lg_m)
+        } else {
+            F::twiddles(lg_m)
+        };
+        for chunk in coeffs.chunks_exact_mut(2 * half_m) {
+            let (lo, hi) = chunk.split_at_mut(half_m);
+            for ((x, y), &w) in lo.iter_mut().zip(hi).zip(&twiddles) {
+                let t = *y * w;
+                *y = *x - t;
+                *x += t;
+            }
+        }
+    }
+
+    if inverse {
+        let n_inv = F::inverse_2exp(lg_n);
+        for c in coeffs.iter_mut() {
+            *c *= n_inv;
+        }
+    }
+}
+
 /// Generic FFT implementation that works with both scalar and packed inputs.
 #[unroll_for_loops]
 fn fft_classic_simd<P: PackedField>(
@@ -211,10 +250,10 @@ mod tests {
 
     use plonky2_util::{log2_ceil, log2_strict};
 
-    use crate::fft::{fft, fft_with_options, ifft};
+    use crate::fft::{fft, fft_with_options, goldilocks_fft, ifft};
     use crate::goldilocks_field::GoldilocksField;
     use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
-    use crate::types::Field;
+    use crate::types::{Field, Sample};
 
     #[test]
     fn fft_and_ifft() {
@@ -252,6 +291,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_goldilocks_fft_matches_naive_dft() {
+        type F = GoldilocksField;
+        for n in [2, 4, 8, 1024] {
+            let coeffs = F::rand_vec(n);
+            let mut values = coeffs.clone();
+            goldilocks_fft(&mut values, false);
+
+            let coeffs = PolynomialCoeffs::new(coeffs);
+            assert_eq!(values, evaluate_naive_power_of_2(&coeffs).values);
+            assert_eq!(values, fft(coeffs).values);
+        }
+    }
+
+    #[test]
+    fn test_goldilocks_fft_inverse_roundtrip() {
+        type F = GoldilocksField;
+        for lg_n in 0..=10 {
+            let coeffs = F::rand_vec(1 << lg_n);
+
+            let mut buffer = coeffs.clone();
+            goldilocks_fft(&mut buffer, false);
+            goldilocks_fft(&mut buffer, true);
+            assert_eq!(buffer, coeffs);
+
+            let mut buffer = coeffs.clone();
+            goldilocks_fft(&mut buffer, true);
+            assert_eq!(buffer, ifft(PolynomialValues::new(coeffs.clone())).coeffs);
+            goldilocks_fft(&mut buffer, false);
+            assert_eq!(buffer, coeffs);
+        }
+    }
+
     fn evaluate_naive<F: Field>(coefficients: &PolynomialCoeffs<F>) -> PolynomialValues<F> {
         let degree = coefficients.len();
         let degree_padded = 1 << log2_ceil(degree);
@@ -1,4 +1,6 @@
-use crate::extension::{Extendable, Frobenius};
+use alloc::vec;
+
+use crate::extension::{Extendable, FieldExtension, Frobenius};
 use crate::ops::Square;
 use crate::types::{Field, Sample};
 
@@ -112,6 +114,41 @@ macro_rules! test_field_arithmetic {
                 assert_eq!(x, x2);
                 assert_eq!(x1, x3);
             }
+
+            #[test]
+            fn signed_conversions() {
+                type F = $field;
+
+                let boundary = [0, 1, 2, i64::MAX - 1, i64::MAX];
+                for n in boundary {
+                    let x = F::from_noncanonical_i64(n);
+                    assert_eq!(x, F::from_noncanonical_i128(n as i128));
+                    assert_eq!(F::from_noncanonical_i64(-n), -x);
+                }
+                assert_eq!(
+                    F::from_noncanonical_i64(i64::MIN),
+                    -F::from_noncanonical_u64(1 << 63)
+                );
+                assert_eq!(
+                    F::from_noncanonical_i128(i128::MIN),
+                    -F::from_noncanonical_u128(1 << 127)
+                );
+
+                let mut rng = OsRng;
+                let random = (0..1000).map(|_| (rng.next_u64() as i64, rng.next_u64() as i64));
+                let pairs = boundary
+                    .into_iter()
+                    .chain(boundary.map(|n| -n))
+                    .chain([i64::MIN])
+                    .flat_map(|a| [a, i64::MIN, i64::MAX, -1].map(|b| (a, b)))
+                    .chain(random);
+                for (a, b) in pairs {
+                    assert_eq!(
+                        F::from_noncanonical_i64(a) + F::from_noncanonical_i64(b),
+                        F::from_noncanonical_i128(a as i128 + b as i128)
+                    );
+                }
+            }
         }
     };
 }
@@ -177,6 +214,21 @@ pub(crate) fn test_power_of_two_gen<BF: Extendable<D>, const D: usize>() {
     );
 }
 
+pub(crate) fn test_base_coeffs<BF: Extendable<D>, const D: usize>() {
+    let x = BF::Extension::rand();
+    let coeffs = <BF::Extension as FieldExtension<D>>::to_base_coeffs(x);
+    assert_eq!(coeffs, x.to_basefield_array());
+    assert_eq!(
+        <BF::Extension as FieldExtension<D>>::from_base_coeffs(&coeffs),
+        x
+    );
+}
+
+pub(crate) fn test_base_coeffs_wrong_length<BF: Extendable<D>, const D: usize>() {
+    let coeffs = vec![BF::ONE; D + 1];
+    <BF::Extension as FieldExtension<D>>::from_base_coeffs(&coeffs);
+}
+
 #[macro_export]
 macro_rules! test_field_extension {
     ($field:ty, $d:expr) => {
@@ -201,6 +253,15 @@ macro_rules! test_field_extension {
             fn test_power_of_two_gen() {
                 $crate::field_testing::test_power_of_two_gen::<$field, $d>();
             }
+            #[test]
+            fn test_base_coeffs() {
+                $crate::field_testing::test_base_coeffs::<$field, $d>();
+            }
+            #[test]
+            #[should_panic(expected = "base field coefficients")]
+            fn test_base_coeffs_wrong_length() {
+                $crate::field_testing::test_base_coeffs_wrong_length::<$field, $d>();
+            }
         }
     };
 }
@@ -1,4 +1,5 @@
-use core::fmt::{self, Debug, Display, Formatter};
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display, Formatter, LowerHex};
 use core::hash::{Hash, Hasher};
 use core::iter::{Product, Sum};
 use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
@@ -6,8 +7,11 @@ use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAss
 use num::{BigUint, Integer, ToPrimitive};
 use plonky2_util::{assume, branch_hint};
 use serde::{Deserialize, Serialize};
+use zeroize::DefaultIsZeroes;
 
+use crate::batch_util::{pack_slice_with_leftovers, pack_slice_with_leftovers_mut};
 use crate::ops::Square;
+use crate::packable::Packable;
 use crate::types::{Field, Field64, PrimeField, PrimeField64, Sample};
 
 const EPSILON: u64 = (1 << 32) - 1;
@@ -20,16 +24,149 @@ const EPSILON: u64 = (1 << 32) - 1;
 ///   = 2**64 - 2**32 + 1
 ///   = 2**32 * (2**32 - 1) + 1
 /// ```
-#[derive(Copy, Clone, Serialize, Deserialize)]
+///
+/// With the `serde-canonical` feature, elements are serialized through [`canonical_serde`].
+#[derive(Copy, Clone)]
+#[cfg_attr(not(feature = "serde-canonical"), derive(Serialize, Deserialize))]
 #[repr(transparent)]
 pub struct GoldilocksField(pub u64);
 
+/// Serde functions which serialize a [`GoldilocksField`] as its canonical value, and reject
+/// non-canonical values when deserializing.
+///
+/// The format is otherwise the same as the derived one, so serialized data is byte-stable
+/// regardless of the internal representations the prover happened to produce. Use as
+/// `#[serde(with = "canonical_serde")]`, or enable the `serde-canonical` feature to use it for all
+/// elements.
+pub mod canonical_serde {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::GoldilocksField;
+    use crate::types::{Field64, PrimeField64};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename = "GoldilocksField")]
+    struct Repr(u64);
+
+    pub fn serialize<S: Serializer>(x: &GoldilocksField, serializer: S) -> Result<S::Ok, S::Error> {
+        Repr(x.to_canonical_u64()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<GoldilocksField, D::Error> {
+        let Repr(x) = Repr::deserialize(deserializer)?;
+        if x >= GoldilocksField::ORDER {
+            return Err(D::Error::custom("Non-canonical field element"));
+        }
+        Ok(GoldilocksField(x))
+    }
+}
+
+#[cfg(feature = "serde-canonical")]
+impl Serialize for GoldilocksField {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        canonical_serde::serialize(self, serializer)
+    }
+}
+
+#[cfg(feature = "serde-canonical")]
+impl<'de> Deserialize<'de> for GoldilocksField {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        canonical_serde::deserialize(deserializer)
+    }
+}
+
+impl GoldilocksField {
+    /// Returns whether this element's internal representation is canonical, i.e. less than
+    /// `ORDER`.
+    #[inline]
+    pub const fn is_canonical(self) -> bool {
+        self.0 < Self::ORDER
+    }
+
+    /// Computes `self^7`, the S-box of Poseidon over Goldilocks, as `x^3 * x^4`. This takes four
+    /// multiplications, only three of which are sequential, against the three squarings and three
+    /// multiplications of `exp_u64(7)`.
+    #[inline(always)]
+    pub fn sbox(self) -> Self {
+        let x2 = self.square();
+        let x4 = x2.square();
+        let x3 = self * x2;
+        x3 * x4
+    }
+
+    /// Squares `self` `n` times, i.e. computes `self^(2^n)`. Each squaring is a single reduction
+    /// of the 128-bit square, in a loop with no other work, and `exp_power_of_2` defers to this.
+    #[inline]
+    pub fn square_n(self, n: usize) -> Self {
+        let mut x = self;
+        for _ in 0..n {
+            x = reduce128((x.0 as u128) * (x.0 as u128));
+        }
+        x
+    }
+
+    /// Returns the inverse of `primitive_root_of_unity(log_n)`. Since the root has order
+    /// `2^log_n`, its inverse is `root^(2^log_n - 1)`, the product of `root^(2^i)` for
+    /// `i < log_n`, which avoids a general inversion.
+    pub fn inverse_2adic_root(log_n: usize) -> Self {
+        let mut power = Self::primitive_root_of_unity(log_n);
+        let mut inverse = Self::ONE;
+        for _ in 0..log_n {
+            inverse *= power;
+            power = power.square();
+        }
+        inverse
+    }
+
+    /// Returns the twiddle factors of a radix-2 FFT of size `n = 2^log_n`, i.e. the first `n / 2`
+    /// powers of `primitive_root_of_unity(log_n)`, computed by successive multiplication.
+    pub fn twiddles(log_n: usize) -> Vec<Self> {
+        Self::primitive_root_of_unity(log_n)
+            .powers()
+            .take((1 << log_n) / 2)
+            .collect()
+    }
+
+    /// Like `twiddles`, but for the inverse FFT, i.e. the powers of `inverse_2adic_root(log_n)`.
+    pub fn twiddles_inverse(log_n: usize) -> Vec<Self> {
+        Self::inverse_2adic_root(log_n)
+            .powers()
+            .take((1 << log_n) / 2)
+            .collect()
+    }
+
+    /// Computes `acc[i] += alpha * x[i]` in place, in a single pass. Full vectors of the default
+    /// packing, e.g. `Avx2GoldilocksField` when compiled with AVX2, are processed with vector
+    /// instructions, and the leftovers as scalars. As with other arithmetic, the results may not
+    /// be canonical.
+    pub fn axpy(alpha: Self, x: &[Self], acc: &mut [Self]) {
+        assert_eq!(x.len(), acc.len(), "both arrays must have the same length");
+        type P = <GoldilocksField as Packable>::Packing;
+
+        let (acc_packed, acc_leftovers) = pack_slice_with_leftovers_mut::<P>(acc);
+        let (x_packed, x_leftovers) = pack_slice_with_leftovers::<P>(x);
+        let alpha_packed = P::from(alpha);
+        for (a, &x) in acc_packed.iter_mut().zip(x_packed) {
+            *a += alpha_packed * x;
+        }
+        for (a, &x) in acc_leftovers.iter_mut().zip(x_leftovers) {
+            *a += alpha * x;
+        }
+    }
+}
+
 impl Default for GoldilocksField {
     fn default() -> Self {
         Self::ZERO
     }
 }
 
+/// Lets buffers of field elements, such as a witness, be wiped with `zeroize`.
+impl DefaultIsZeroes for GoldilocksField {}
+
 impl PartialEq for GoldilocksField {
     fn eq(&self, other: &Self) -> bool {
         self.to_canonical_u64() == other.to_canonical_u64()
@@ -50,6 +187,12 @@ impl Display for GoldilocksField {
     }
 }
 
+impl LowerHex for GoldilocksField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        LowerHex::fmt(&self.to_canonical_u64(), f)
+    }
+}
+
 impl Debug for GoldilocksField {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         Debug::fmt(&self.to_canonical_u64(), f)
@@ -95,6 +238,10 @@ impl Field for GoldilocksField {
         Self::order()
     }
 
+    fn sum_slice(xs: &[Self]) -> Self {
+        sum_delayed(xs.iter().copied())
+    }
+
     /// Returns the inverse of the field element, using Fermat's little theorem.
     /// The inverse of `a` is computed as `a^(p-2)`, where `p` is the prime order of the field.
     ///
@@ -181,6 +328,11 @@ impl Field for GoldilocksField {
         })
     }
 
+    #[inline]
+    fn exp_power_of_2(&self, power_log: usize) -> Self {
+        self.square_n(power_log)
+    }
+
     #[inline]
     fn multiply_accumulate(&self, x: Self, y: Self) -> Self {
         // u64 + u64 * u64 cannot overflow.
@@ -192,6 +344,23 @@ impl PrimeField for GoldilocksField {
     fn to_canonical_biguint(&self) -> BigUint {
         self.to_canonical_u64().into()
     }
+
+    #[inline]
+    fn to_canonical_i64(&self) -> Option<i64> {
+        // Since `ORDER < 2^64`, both halves of the field fit in an `i64`.
+        let x = self.to_canonical_u64();
+        Some(if x <= Self::ORDER / 2 {
+            x as i64
+        } else {
+            -((Self::ORDER - x) as i64)
+        })
+    }
+
+    #[inline]
+    fn is_quadratic_residue(&self) -> bool {
+        // Euler's criterion, as in the default implementation, but with a `u64` exponent.
+        self.is_zero() || self.exp_u64((Self::ORDER - 1) / 2) == Self::ONE
+    }
 }
 
 impl Field64 for GoldilocksField {
@@ -234,11 +403,11 @@ impl Neg for GoldilocksField {
 
     #[inline]
     fn neg(self) -> Self {
-        if self.is_zero() {
-            Self::ZERO
-        } else {
-            Self(Self::ORDER - self.to_canonical_u64())
-        }
+        // `ORDER - 0` is the non-canonical `ORDER`, so zero is masked out rather than branched on,
+        // which would mispredict when negating many values.
+        let x = self.to_canonical_u64();
+        let mask = ((x != 0) as u64).wrapping_neg();
+        Self((Self::ORDER - x) & mask)
     }
 }
 
@@ -275,7 +444,13 @@ impl AddAssign for GoldilocksField {
 
 impl Sum for GoldilocksField {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-        iter.fold(Self::ZERO, |acc, x| acc + x)
+        sum_delayed(iter)
+    }
+}
+
+impl<'a> Sum<&'a Self> for GoldilocksField {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        sum_delayed(iter.copied())
     }
 }
 
@@ -398,6 +573,10 @@ fn reduce96((x_lo, x_hi): (u64, u32)) -> GoldilocksField {
 
 /// Reduces to a 64-bit value. The result might not be in canonical form; it could be in between the
 /// field order and `2^64`.
+///
+/// Writing `x = x_lo + 2^64 x_hi_lo + 2^96 x_hi_hi`, we have `2^64 = EPSILON` and `2^96 = -1` modulo
+/// the order, so `x = x_lo - x_hi_hi + EPSILON x_hi_lo`. No step relies on wrapping: the
+/// subtraction detects its borrow, and the addition its carry.
 #[inline]
 fn reduce128(x: u128) -> GoldilocksField {
     let (x_lo, x_hi) = split(x); // This is a no-op
@@ -407,8 +586,12 @@ fn reduce128(x: u128) -> GoldilocksField {
     let (mut t0, borrow) = x_lo.overflowing_sub(x_hi_hi);
     if borrow {
         branch_hint(); // A borrow is exceedingly rare. It is faster to branch.
+
+        // On a borrow, `t0 = x_lo - x_hi_hi + 2^64 >= 2^64 - 2^32 + 1`, and subtracting `EPSILON`
+        // is subtracting `2^64` modulo the order.
         t0 -= EPSILON; // Cannot underflow.
     }
+    // At most `(2^32 - 1)^2 < ORDER`, so `t0 + t1 < 2^64 + ORDER` as the addition requires.
     let t1 = x_hi_lo * EPSILON;
     let t2 = unsafe { add_no_canonicalize_trashing_input(t0, t1) };
     GoldilocksField(t2)
@@ -448,16 +631,348 @@ pub(crate) unsafe fn reduce160(x_lo: u128, x_hi: u32) -> GoldilocksField {
     GoldilocksField(t2)
 }
 
+/// Sums the given elements in a `u128` accumulator, reducing only once at the end.
+///
+/// Each term is below `2^64`, so the accumulator can only wrap after about `2^64` additions. If it
+/// does wrap, we add `2^128 mod P = P - 2^32` back in, which cannot wrap again since the wrapped
+/// accumulator is below `2^64`.
+#[inline]
+fn sum_delayed<I: Iterator<Item = GoldilocksField>>(iter: I) -> GoldilocksField {
+    const TWO_128_MOD_P: u128 = (GoldilocksField::ORDER - (1 << 32)) as u128;
+    let mut acc = 0u128;
+    for x in iter {
+        let (res, overflow) = acc.overflowing_add(x.0 as u128);
+        acc = res;
+        if overflow {
+            branch_hint();
+            acc += TWO_128_MOD_P;
+        }
+    }
+    reduce128(acc)
+}
+
 /// Squares the base N number of times and multiplies the result by the tail value.
 #[inline(always)]
 fn exp_acc<const N: usize>(base: GoldilocksField, tail: GoldilocksField) -> GoldilocksField {
-    base.exp_power_of_2(N) * tail
+    base.square_n(N) * tail
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
     use crate::{test_field_arithmetic, test_prime_field_arithmetic};
 
     test_prime_field_arithmetic!(crate::goldilocks_field::GoldilocksField);
     test_field_arithmetic!(crate::goldilocks_field::GoldilocksField);
+
+    #[test]
+    fn test_twiddles_match_exp() {
+        for log_n in 0..=12 {
+            let root = GoldilocksField::primitive_root_of_unity(log_n);
+            let root_inv = root.inverse();
+            let twiddles = GoldilocksField::twiddles(log_n);
+            let twiddles_inverse = GoldilocksField::twiddles_inverse(log_n);
+            assert_eq!(twiddles.len(), (1 << log_n) / 2);
+            assert_eq!(twiddles_inverse.len(), (1 << log_n) / 2);
+            for (i, (&w, &w_inv)) in twiddles.iter().zip(&twiddles_inverse).enumerate() {
+                assert_eq!(w, root.exp_u64(i as u64), "log_n = {}, i = {}", log_n, i);
+                assert_eq!(
+                    w_inv,
+                    root_inv.exp_u64(i as u64),
+                    "log_n = {}, i = {}",
+                    log_n,
+                    i
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_axpy_matches_two_passes() {
+        type F = GoldilocksField;
+        for len in [0, 1, 3, 4, 7, 8, 9, 4095, 4096] {
+            let alpha = F::rand();
+            let x = F::rand_vec(len);
+            let mut acc = F::rand_vec(len);
+
+            let scaled = x.iter().map(|&x| alpha * x).collect::<Vec<_>>();
+            let expected = acc
+                .iter()
+                .zip(&scaled)
+                .map(|(&a, &s)| a + s)
+                .collect::<Vec<_>>();
+            F::axpy(alpha, &x, &mut acc);
+            assert_eq!(acc, expected, "len = {len}");
+        }
+    }
+
+    #[test]
+    fn test_signed_conversions() {
+        const P: i128 = GoldilocksField::ORDER as i128;
+        let centered = |n: i128| {
+            let r = n.rem_euclid(P);
+            if r <= P / 2 {
+                r
+            } else {
+                r - P
+            }
+        };
+
+        let half = P / 2;
+        let boundary = [
+            0,
+            1,
+            -1,
+            half,
+            -half,
+            half + 1,
+            -half - 1,
+            i64::MIN as i128,
+            i64::MAX as i128,
+            i128::MIN,
+            i128::MAX,
+        ];
+        for n in boundary {
+            let x = GoldilocksField::from_noncanonical_i128(n);
+            assert_eq!(x.to_canonical_i64(), Some(centered(n) as i64), "{}", n);
+            assert_eq!(x.abs_centered(), centered(n).unsigned_abs() as u64, "{}", n);
+            if let Ok(n) = i64::try_from(n) {
+                assert_eq!(GoldilocksField::from_noncanonical_i64(n), x);
+            }
+        }
+        assert_eq!(GoldilocksField::NEG_ONE.to_canonical_i64(), Some(-1));
+        assert_eq!(GoldilocksField::NEG_ONE.abs_centered(), 1);
+
+        for x in GoldilocksField::rand_vec(1000) {
+            let n = x.to_canonical_i64().unwrap();
+            assert_eq!(GoldilocksField::from_noncanonical_i64(n), x);
+            assert_eq!(n.unsigned_abs(), x.abs_centered());
+        }
+    }
+
+    #[test]
+    fn test_neg_matches_branching_neg() {
+        fn branching_neg(x: GoldilocksField) -> GoldilocksField {
+            if x.is_zero() {
+                GoldilocksField::ZERO
+            } else {
+                GoldilocksField(GoldilocksField::ORDER - x.to_canonical_u64())
+            }
+        }
+
+        let boundary = [
+            0,
+            1,
+            2,
+            EPSILON - 1,
+            EPSILON,
+            EPSILON + 1,
+            GoldilocksField::ORDER - 2,
+            GoldilocksField::ORDER - 1,
+            // Non-canonical representations.
+            GoldilocksField::ORDER,
+            GoldilocksField::ORDER + 1,
+            u64::MAX - 1,
+            u64::MAX,
+        ]
+        .map(GoldilocksField);
+        let random = GoldilocksField::rand_vec(10_000);
+        for x in boundary.into_iter().chain(random) {
+            let neg = -x;
+            assert_eq!(neg, branching_neg(x));
+            assert_eq!(neg.0, branching_neg(x).0);
+            assert!(neg.is_canonical());
+        }
+    }
+
+    /// Operands at the boundaries of the limbs and of the field, including non-canonical ones.
+    const REDUCE_BOUNDARY: [u64; 8] = [
+        0,
+        1,
+        (1 << 32) - 1,
+        1 << 32,
+        (1 << 32) + 1,
+        GoldilocksField::ORDER - 1,
+        GoldilocksField::ORDER,
+        u64::MAX,
+    ];
+
+    fn reduce_reference(x: BigUint) -> u64 {
+        (x % GoldilocksField::ORDER).to_u64().unwrap()
+    }
+
+    #[test]
+    fn test_reduce128_boundary_products() {
+        for a in REDUCE_BOUNDARY {
+            for b in REDUCE_BOUNDARY {
+                let product = BigUint::from(a) * BigUint::from(b);
+                let expected = reduce_reference(product.clone());
+                let x = (a as u128) * (b as u128);
+                assert_eq!(reduce128(x).to_canonical_u64(), expected, "{} * {}", a, b);
+                assert_eq!(
+                    (GoldilocksField(a) * GoldilocksField(b)).to_canonical_u64(),
+                    expected,
+                    "{} * {}",
+                    a,
+                    b
+                );
+
+                for c in REDUCE_BOUNDARY {
+                    // `c + a * b <= 2^128 - 2^64` cannot overflow.
+                    let expected = reduce_reference(product.clone() + BigUint::from(c));
+                    let result = GoldilocksField(c)
+                        .multiply_accumulate(GoldilocksField(a), GoldilocksField(b));
+                    assert_eq!(result.to_canonical_u64(), expected, "{} + {} * {}", c, a, b);
+                }
+            }
+        }
+
+        for x in [u128::MAX, u128::MAX - 1, 1 << 96, (1 << 96) - 1, 1 << 64] {
+            assert_eq!(
+                reduce128(x).to_canonical_u64(),
+                reduce_reference(BigUint::from(x)),
+                "{}",
+                x
+            );
+        }
+    }
+
+    #[test]
+    fn test_reduce128_matches_reference() {
+        use rand::rngs::OsRng;
+        use rand::RngCore;
+
+        // A splitmix64 generator seeded from the OS, as drawing each sample from the OS is slow.
+        let mut state = OsRng.next_u64();
+        let mut next = || {
+            state = state.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+
+        for i in 0..10_000_000 {
+            let x = match i % 4 {
+                // Arbitrary products, as computed by multiplication.
+                0 | 1 => (next() as u128) * (next() as u128),
+                // Arbitrary 128-bit values.
+                2 => ((next() as u128) << 64) | next() as u128,
+                // A low limb below the top 32 bits, forcing a borrow.
+                _ => ((next() as u128) << 64) | (next() >> 33) as u128,
+            };
+            let expected = (x % GoldilocksField::ORDER as u128) as u64;
+            assert_eq!(reduce128(x).to_canonical_u64(), expected, "{}", x);
+        }
+    }
+
+    #[test]
+    fn test_sbox_matches_exp() {
+        let boundary = [
+            0,
+            1,
+            2,
+            EPSILON,
+            GoldilocksField::ORDER - 1,
+            // Non-canonical representations.
+            GoldilocksField::ORDER,
+            u64::MAX,
+        ]
+        .map(GoldilocksField);
+        let random = GoldilocksField::rand_vec(10_000);
+        for x in boundary.into_iter().chain(random) {
+            assert_eq!(x.sbox(), x.exp_u64(7));
+        }
+    }
+
+    #[test]
+    fn test_square_n_matches_repeated_square() {
+        let xs = [GoldilocksField::ORDER - 1, GoldilocksField::ORDER, u64::MAX]
+            .map(GoldilocksField)
+            .into_iter()
+            .chain(GoldilocksField::rand_vec(100));
+        for x in xs {
+            let mut expected = x;
+            for n in 0..=70 {
+                assert_eq!(x.square_n(n), expected, "{} squared {} times", x, n);
+                expected = expected.square();
+            }
+        }
+    }
+
+    #[test]
+    fn test_sum_slice_matches_eager_fold() {
+        let xs = GoldilocksField::rand_vec(1_000_000);
+        let expected = xs.iter().fold(GoldilocksField::ZERO, |acc, &x| acc + x);
+        assert_eq!(GoldilocksField::sum_slice(&xs), expected);
+        assert_eq!(xs.iter().sum::<GoldilocksField>(), expected);
+        assert_eq!(xs.iter().copied().sum::<GoldilocksField>(), expected);
+    }
+
+    #[test]
+    fn test_sum_slice_noncanonical() {
+        // Non-canonical inputs close to `2^64` exercise the final reduction.
+        let xs: Vec<_> = (0..1000).map(|i| GoldilocksField(u64::MAX - i)).collect();
+        let expected = xs.iter().fold(GoldilocksField::ZERO, |acc, &x| acc + x);
+        assert_eq!(GoldilocksField::sum_slice(&xs), expected);
+    }
+
+    #[test]
+    fn test_is_canonical() {
+        assert!(GoldilocksField::ONE.is_canonical());
+        assert!(GoldilocksField::NEG_ONE.is_canonical());
+        assert!(!GoldilocksField(GoldilocksField::ORDER).is_canonical());
+        assert!(!GoldilocksField(u64::MAX).is_canonical());
+    }
+
+    #[test]
+    fn test_canonical_serde() {
+        #[derive(Serialize, Deserialize)]
+        struct Wrapper(#[serde(with = "canonical_serde")] GoldilocksField);
+
+        let x = GoldilocksField(5);
+        let non_canonical = GoldilocksField(5 + GoldilocksField::ORDER);
+        assert_eq!(x, non_canonical);
+
+        let json = serde_json::to_string(&Wrapper(x)).unwrap();
+        assert_eq!(
+            serde_json::to_string(&Wrapper(non_canonical)).unwrap(),
+            json
+        );
+        // Canonical values serialize as with the derived implementation.
+        assert_eq!(json, "5");
+        assert_eq!(serde_json::from_str::<Wrapper>(&json).unwrap().0, x);
+
+        let json = (5 + GoldilocksField::ORDER).to_string();
+        assert!(serde_json::from_str::<Wrapper>(&json).is_err());
+    }
+
+    #[test]
+    fn test_inverse_2adic_root() {
+        for log_n in 0..=GoldilocksField::TWO_ADICITY {
+            let root = GoldilocksField::primitive_root_of_unity(log_n);
+            let inverse = GoldilocksField::inverse_2adic_root(log_n);
+            assert_eq!(root * inverse, GoldilocksField::ONE);
+            assert_eq!(inverse, root.inverse());
+        }
+    }
+
+    #[test]
+    fn test_is_quadratic_residue() {
+        assert!(GoldilocksField::ZERO.is_quadratic_residue());
+        assert!(!GoldilocksField::MULTIPLICATIVE_GROUP_GENERATOR.is_quadratic_residue());
+        for _ in 0..100 {
+            let x = GoldilocksField::rand();
+            assert!(x.square().is_quadratic_residue());
+            // Squares and non-squares are swapped by multiplying with a non-square.
+            let y = x * GoldilocksField::MULTIPLICATIVE_GROUP_GENERATOR;
+            assert_eq!(
+                y.is_quadratic_residue(),
+                x.is_zero() || !x.is_quadratic_residue()
+            );
+            assert_eq!(x.is_quadratic_residue(), x.sqrt().is_some());
+        }
+    }
 }
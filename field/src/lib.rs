@@ -3,6 +3,7 @@
 #![allow(clippy::type_complexity)]
 #![allow(clippy::len_without_is_empty)]
 #![allow(clippy::needless_range_loop)]
+#![feature(portable_simd)]
 #![feature(stdsimd)]
 #![feature(specialization)]
 #![cfg_attr(not(test), no_std)]
@@ -24,6 +25,7 @@ pub mod packed;
 pub mod polynomial;
 pub mod secp256k1_base;
 pub mod secp256k1_scalar;
+pub mod simd;
 pub mod types;
 pub mod zero_poly_coset;
 
@@ -0,0 +1,209 @@
+use alloc::vec::Vec;
+
+use plonky2_util::log2_strict;
+
+use crate::extension::{flatten, unflatten, Extendable, FieldExtension};
+use crate::fft::fft_root_table;
+use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
+use crate::types::Field;
+
+/// A polynomial over `F::Extension`, stored as the `D` base field components of each element
+/// side by side in a single vector, as in [`flatten`].
+///
+/// Like the vectors of [`PolynomialCoeffs`] and [`PolynomialValues`], the elements are either the
+/// coefficients of the polynomial or its values on a coset of a subgroup, and the in-place
+/// transforms convert between the two without allocating a second vector. As roots of unity lie in
+/// the base field, the transforms only ever multiply extension elements by base field elements.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FlatExtensionPolynomial<F: Field, const D: usize> {
+    pub elements: Vec<F>,
+}
+
+impl<F: Field + Extendable<D>, const D: usize> FlatExtensionPolynomial<F, D> {
+    pub fn new(elements: Vec<F>) -> Self {
+        assert_eq!(
+            elements.len() % D,
+            0,
+            "The length isn't a multiple of the extension degree"
+        );
+        Self { elements }
+    }
+
+    /// The number of extension field elements.
+    pub fn len(&self) -> usize {
+        self.elements.len() / D
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// The `i`th extension field element.
+    pub fn get(&self, i: usize) -> F::Extension {
+        F::Extension::from_basefield_array(self.elements[i * D..(i + 1) * D].try_into().unwrap())
+    }
+
+    pub fn into_coeffs(self) -> PolynomialCoeffs<F::Extension> {
+        PolynomialCoeffs::new(unflatten::<F, D>(&self.elements))
+    }
+
+    pub fn into_values(self) -> PolynomialValues<F::Extension> {
+        PolynomialValues::new(unflatten::<F, D>(&self.elements))
+    }
+
+    /// Pads the coefficients with zeros to `2^rate_bits` times their number, as in
+    /// [`PolynomialCoeffs::lde`].
+    pub fn lde(mut self, rate_bits: usize) -> Self {
+        self.elements
+            .resize(self.elements.len() << rate_bits, F::ZERO);
+        self
+    }
+
+    /// Permutes the extension field elements into bit-reversed order.
+    pub fn reverse_index_bits_in_place(&mut self) {
+        let n = self.len();
+        let lg_n = log2_strict(n);
+        for i in 0..n {
+            // `overflowing_shr` accommodates `lg_n == 0`, where the shift would be 64 bits.
+            let j = i
+                .reverse_bits()
+                .overflowing_shr(usize::BITS - lg_n as u32)
+                .0;
+            if i < j {
+                let (lo, hi) = self.elements.split_at_mut(j * D);
+                lo[i * D..(i + 1) * D].swap_with_slice(&mut hi[..D]);
+            }
+        }
+    }
+
+    /// Multiplies the `i`th element by `base^i`.
+    fn scale_by_powers(&mut self, base: F) {
+        for (element, power) in self.elements.chunks_exact_mut(D).zip(base.powers()) {
+            for c in element {
+                *c *= power;
+            }
+        }
+    }
+
+    /// Replaces coefficients by the values on the subgroup, in natural order, as in
+    /// [`PolynomialCoeffs::fft`].
+    pub fn fft_in_place(&mut self) {
+        let n = self.len();
+        let lg_n = log2_strict(n);
+        self.reverse_index_bits_in_place();
+        if lg_n == 0 {
+            return;
+        }
+
+        let root_table = fft_root_table::<F>(n);
+        for (lg_half_m, roots) in root_table.iter().enumerate() {
+            let half_m = 1 << lg_half_m;
+            for chunk in self.elements.chunks_exact_mut(2 * half_m * D) {
+                let (lo, hi) = chunk.split_at_mut(half_m * D);
+                for ((x, y), &w) in lo
+                    .chunks_exact_mut(D)
+                    .zip(hi.chunks_exact_mut(D))
+                    .zip(roots)
+                {
+                    for (x_c, y_c) in x.iter_mut().zip(y) {
+                        let t = *y_c * w;
+                        *y_c = *x_c - t;
+                        *x_c += t;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replaces values on the subgroup, in natural order, by the coefficients, as in
+    /// [`PolynomialValues::ifft`].
+    pub fn ifft_in_place(&mut self) {
+        let n = self.len();
+        let n_inv = F::inverse_2exp(log2_strict(n));
+        self.fft_in_place();
+
+        // As in `ifft`, the inverse transform is the forward one with all values but the first
+        // reversed, divided by `n`.
+        for c in &mut self.elements {
+            *c *= n_inv;
+        }
+        for i in 1..n / 2 {
+            let (lo, hi) = self.elements.split_at_mut((n - i) * D);
+            lo[i * D..(i + 1) * D].swap_with_slice(&mut hi[..D]);
+        }
+    }
+
+    /// Replaces coefficients by the values on the coset `shift*H`, as in
+    /// [`PolynomialCoeffs::coset_fft`].
+    pub fn coset_fft_in_place(&mut self, shift: F) {
+        self.scale_by_powers(shift);
+        self.fft_in_place();
+    }
+
+    /// Replaces values on the coset `shift*H` by the coefficients, as in
+    /// [`PolynomialValues::coset_ifft`].
+    pub fn coset_ifft_in_place(&mut self, shift: F) {
+        self.ifft_in_place();
+        self.scale_by_powers(shift.inverse());
+    }
+}
+
+impl<F: Field + Extendable<D>, const D: usize> From<PolynomialCoeffs<F::Extension>>
+    for FlatExtensionPolynomial<F, D>
+{
+    fn from(poly: PolynomialCoeffs<F::Extension>) -> Self {
+        Self::new(flatten::<F, D>(&poly.coeffs))
+    }
+}
+
+impl<F: Field + Extendable<D>, const D: usize> From<PolynomialValues<F::Extension>>
+    for FlatExtensionPolynomial<F, D>
+{
+    fn from(poly: PolynomialValues<F::Extension>) -> Self {
+        Self::new(flatten::<F, D>(&poly.values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::goldilocks_field::GoldilocksField;
+    use crate::types::Sample;
+
+    const D: usize = 2;
+    type F = GoldilocksField;
+    type FE = <F as Extendable<D>>::Extension;
+
+    #[test]
+    fn test_flat_fft_matches_extension_fft() {
+        let shift = F::MULTIPLICATIVE_GROUP_GENERATOR;
+        for lg_n in 0..=10 {
+            let coeffs = PolynomialCoeffs::new(FE::rand_vec(1 << lg_n));
+
+            let mut flat = FlatExtensionPolynomial::<F, D>::from(coeffs.clone());
+            assert_eq!(flat.len(), 1 << lg_n);
+            flat.fft_in_place();
+            assert_eq!(flat.clone().into_values(), coeffs.clone().fft());
+            flat.ifft_in_place();
+            assert_eq!(flat.into_coeffs(), coeffs);
+
+            let mut flat = FlatExtensionPolynomial::<F, D>::from(coeffs.clone());
+            flat.coset_fft_in_place(shift);
+            assert_eq!(flat.clone().into_values(), coeffs.coset_fft(shift.into()));
+            flat.coset_ifft_in_place(shift);
+            assert_eq!(flat.into_coeffs(), coeffs);
+        }
+    }
+
+    #[test]
+    fn test_flat_reverse_index_bits() {
+        let mut values = FE::rand_vec(16);
+        let mut flat = FlatExtensionPolynomial::<F, D>::from(PolynomialValues::new(values.clone()));
+        for (i, &v) in values.iter().enumerate() {
+            assert_eq!(flat.get(i), v);
+        }
+        flat.reverse_index_bits_in_place();
+        plonky2_util::reverse_index_bits_in_place(&mut values);
+        assert_eq!(flat.into_values().values, values);
+    }
+}
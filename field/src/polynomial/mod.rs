@@ -1,4 +1,5 @@
 pub(crate) mod division;
+mod flat_extension;
 
 use alloc::vec;
 use alloc::vec::Vec;
@@ -7,12 +8,13 @@ use core::iter::Sum;
 use core::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
 
 use anyhow::{ensure, Result};
+pub use flat_extension::FlatExtensionPolynomial;
 use itertools::Itertools;
 use plonky2_util::log2_strict;
 use serde::{Deserialize, Serialize};
 
 use crate::extension::{Extendable, FieldExtension};
-use crate::fft::{fft, fft_with_options, ifft, FftRootTable};
+use crate::fft::{fft, fft_with_options, ifft, ifft_with_options, FftRootTable};
 use crate::types::Field;
 
 /// A polynomial in point-value form.
@@ -61,7 +63,16 @@ impl<F: Field> PolynomialValues<F> {
 
     /// Returns the polynomial whose evaluation on the coset `shift*H` is `self`.
     pub fn coset_ifft(self, shift: F) -> PolynomialCoeffs<F> {
-        let mut shifted_coeffs = self.ifft();
+        self.coset_ifft_with_options(shift, None)
+    }
+
+    /// Like `coset_ifft`, but with a precomputed root table for `H`.
+    pub fn coset_ifft_with_options(
+        self,
+        shift: F,
+        root_table: Option<&FftRootTable<F>>,
+    ) -> PolynomialCoeffs<F> {
+        let mut shifted_coeffs = ifft_with_options(self, None, root_table);
         shifted_coeffs
             .coeffs
             .iter_mut()
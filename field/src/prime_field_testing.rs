@@ -166,6 +166,25 @@ macro_rules! test_prime_field_arithmetic {
                 assert_eq!(F::ZERO - x, F::NEG_ONE);
             }
 
+            #[test]
+            fn from_bits_le_checked() {
+                use $crate::types::PrimeField;
+
+                type F = $field;
+
+                assert_eq!(F::characteristic(), F::ORDER.into());
+                let bits = |n: u128| (0..65).map(|i| (n >> i) & 1 == 1).collect::<Vec<_>>();
+                for n in [0, 1, F::ORDER - 1] {
+                    assert_eq!(
+                        F::from_bits_le_checked(&bits(n as u128)),
+                        Some(F::from_canonical_u64(n))
+                    );
+                }
+                for n in [F::ORDER as u128, F::ORDER as u128 + 1, 1 << 64] {
+                    assert_eq!(F::from_bits_le_checked(&bits(n)), None);
+                }
+            }
+
             #[test]
             fn addition_double_wraparound() {
                 type F = $field;
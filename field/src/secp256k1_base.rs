@@ -265,7 +265,42 @@ impl DivAssign for Secp256K1Base {
 
 #[cfg(test)]
 mod tests {
+    use num::BigUint;
+
+    use crate::secp256k1_base::Secp256K1Base;
     use crate::test_field_arithmetic;
+    use crate::types::{Field, PrimeField};
 
     test_field_arithmetic!(crate::secp256k1_base::Secp256K1Base);
+
+    #[test]
+    fn test_from_bits_le_checked() {
+        type F = Secp256K1Base;
+
+        let bits = |n: &BigUint| (0..F::BITS as u64).map(|i| n.bit(i)).collect::<Vec<_>>();
+        let p = F::characteristic();
+        assert_eq!(
+            F::from_bits_le_checked(&bits(&(&p - 1u32))),
+            Some(F::NEG_ONE)
+        );
+        assert_eq!(F::from_bits_le_checked(&bits(&p)), None);
+        assert_eq!(F::from_bits_le_checked(&bits(&(&p + 1u32))), None);
+    }
+
+    #[test]
+    fn test_to_canonical_i64() {
+        type F = Secp256K1Base;
+
+        for n in [0, 1, -1, i64::MIN, i64::MAX] {
+            assert_eq!(F::from_noncanonical_i64(n).to_canonical_i64(), Some(n));
+        }
+        for n in [
+            i64::MIN as i128 - 1,
+            i64::MAX as i128 + 1,
+            i128::MIN,
+            i128::MAX,
+        ] {
+            assert_eq!(F::from_noncanonical_i128(n).to_canonical_i64(), None);
+        }
+    }
 }
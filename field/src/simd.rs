@@ -0,0 +1,291 @@
+//! Vectors of Goldilocks field elements built on portable SIMD, which the compiler lowers to the
+//! vector instructions enabled for the function it is inlined into, or to scalar code if there are
+//! none. Unlike the `PackedField` implementations in `arch`, which are selected at compile time,
+//! this lets a caller compile the same code for several targets and pick one at runtime.
+
+use core::fmt::{self, Debug, Formatter};
+use core::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+use core::simd::cmp::SimdPartialOrd;
+use core::simd::{LaneCount, Simd, SupportedLaneCount};
+
+use crate::goldilocks_field::GoldilocksField;
+use crate::types::Field64;
+
+const EPSILON: u64 = (1 << 32) - 1;
+
+/// `LANES` Goldilocks field elements. The arithmetic follows [`GoldilocksField`] step by step, so
+/// each lane of a sum, difference or product holds the same, possibly non-canonical, `u64` as the
+/// scalar operation would.
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct GoldilocksVector<const LANES: usize>(pub Simd<u64, LANES>)
+where
+    LaneCount<LANES>: SupportedLaneCount;
+
+impl<const LANES: usize> GoldilocksVector<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    #[inline(always)]
+    pub fn splat(x: GoldilocksField) -> Self {
+        Self(Simd::splat(x.0))
+    }
+
+    #[inline(always)]
+    pub fn from_array(xs: [GoldilocksField; LANES]) -> Self {
+        Self(Simd::from_array(xs.map(|x| x.0)))
+    }
+
+    #[inline(always)]
+    pub fn to_array(self) -> [GoldilocksField; LANES] {
+        self.0.to_array().map(GoldilocksField)
+    }
+
+    /// Loads the first `LANES` raw, possibly non-canonical, representations from `slice`.
+    #[inline(always)]
+    pub fn from_slice(slice: &[u64]) -> Self {
+        Self(Simd::from_slice(slice))
+    }
+
+    /// Stores the raw representations into the first `LANES` entries of `slice`.
+    #[inline(always)]
+    pub fn write_to_slice(self, slice: &mut [u64]) {
+        self.0.copy_to_slice(slice);
+    }
+
+    /// Reduces each lane to its canonical representation.
+    #[inline(always)]
+    pub fn to_canonical(self) -> Self {
+        let order = Simd::splat(GoldilocksField::ORDER);
+        let x = self.0;
+        Self(x.simd_ge(order).select(x - order, x))
+    }
+
+    /// Reduces the 128-bit values `x_hi * 2^64 + x_lo`, as `reduce128` does for a single value.
+    #[inline(always)]
+    pub fn reduce128(x_lo: Simd<u64, LANES>, x_hi: Simd<u64, LANES>) -> Self {
+        let epsilon = Simd::splat(EPSILON);
+        let x_hi_hi = x_hi >> Simd::splat(32);
+        let x_hi_lo = x_hi & epsilon;
+
+        let t0 = x_lo - x_hi_hi;
+        let borrow = x_lo.simd_lt(x_hi_hi);
+        let t0 = borrow.select(t0 - epsilon, t0);
+        let t1 = x_hi_lo * epsilon;
+        Self::add_no_canonicalize(t0, t1)
+    }
+
+    /// `x + y` modulo the order, assuming `x + y < 2^64 + ORDER`.
+    #[inline(always)]
+    fn add_no_canonicalize(x: Simd<u64, LANES>, y: Simd<u64, LANES>) -> Self {
+        let sum = x + y;
+        let carry = sum.simd_lt(x);
+        Self(carry.select(sum + Simd::splat(EPSILON), sum))
+    }
+
+    /// The full 128-bit products of the lanes of `x` and `y`, as `(lo, hi)` halves.
+    #[inline(always)]
+    fn widening_mul(
+        x: Simd<u64, LANES>,
+        y: Simd<u64, LANES>,
+    ) -> (Simd<u64, LANES>, Simd<u64, LANES>) {
+        let mask = Simd::splat(EPSILON);
+        let shift = Simd::splat(32);
+        let (x_lo, x_hi) = (x & mask, x >> shift);
+        let (y_lo, y_hi) = (y & mask, y >> shift);
+
+        let lo_lo = x_lo * y_lo;
+        let lo_hi = x_lo * y_hi;
+        let hi_lo = x_hi * y_lo;
+        let hi_hi = x_hi * y_hi;
+
+        let mid = lo_hi + hi_lo;
+        let mid_carry = mid
+            .simd_lt(lo_hi)
+            .select(Simd::splat(1 << 32), Simd::splat(0));
+        let lo = lo_lo + (mid << shift);
+        let lo_carry = lo.simd_lt(lo_lo).select(Simd::splat(1), Simd::splat(0));
+        let hi = hi_hi + (mid >> shift) + mid_carry + lo_carry;
+        (lo, hi)
+    }
+
+    /// `sum_i c_i x_i` for small constants, reduced once at the end: in each lane, the constants
+    /// must sum to less than `2^32`. Each `x_i` is split into 32-bit halves, whose weighted sums
+    /// then fit in 64 bits.
+    #[inline(always)]
+    pub fn linear_combination_small<const N: usize>(xs: [Self; N], cs: [Self; N]) -> Self {
+        let mask = Simd::splat(EPSILON);
+        let shift = Simd::splat(32);
+        let mut sum_lo = Simd::splat(0);
+        let mut sum_hi = Simd::splat(0);
+        for (x, c) in xs.into_iter().zip(cs) {
+            sum_lo += (x.0 & mask) * c.0;
+            sum_hi += (x.0 >> shift) * c.0;
+        }
+        // The sum is `sum_lo + 2^32 sum_hi`, which has at most 96 bits.
+        let lo = sum_lo + (sum_hi << shift);
+        let carry = lo.simd_lt(sum_lo).select(Simd::splat(1), Simd::splat(0));
+        Self::reduce128(lo, (sum_hi >> shift) + carry)
+    }
+
+    #[inline(always)]
+    pub fn square(self) -> Self {
+        self * self
+    }
+}
+
+impl<const LANES: usize> Debug for GoldilocksVector<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.to_array(), f)
+    }
+}
+
+impl<const LANES: usize> Add for GoldilocksVector<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        let epsilon = Simd::splat(EPSILON);
+        let zero = Simd::splat(0);
+        let sum = self.0 + rhs.0;
+        let over = sum.simd_lt(self.0);
+        let adjusted = sum + over.select(epsilon, zero);
+        // As in `GoldilocksField::add`, the adjustment itself overflows only if both inputs were
+        // non-canonical.
+        let over = adjusted.simd_lt(sum);
+        Self(adjusted + over.select(epsilon, zero))
+    }
+}
+
+impl<const LANES: usize> AddAssign for GoldilocksVector<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    #[inline(always)]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const LANES: usize> Sub for GoldilocksVector<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        let epsilon = Simd::splat(EPSILON);
+        let zero = Simd::splat(0);
+        let diff = self.0 - rhs.0;
+        let under = self.0.simd_lt(rhs.0);
+        let adjustment = under.select(epsilon, zero);
+        let adjusted = diff - adjustment;
+        let under = diff.simd_lt(adjustment);
+        Self(adjusted - under.select(epsilon, zero))
+    }
+}
+
+impl<const LANES: usize> SubAssign for GoldilocksVector<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    #[inline(always)]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<const LANES: usize> Mul for GoldilocksVector<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        let (lo, hi) = Self::widening_mul(self.0, rhs.0);
+        Self::reduce128(lo, hi)
+    }
+}
+
+impl<const LANES: usize> MulAssign for GoldilocksVector<LANES>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    #[inline(always)]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Field, PrimeField64, Sample};
+
+    type F = GoldilocksField;
+
+    /// Random elements, mixed with the edge cases of the arithmetic, including non-canonical ones.
+    fn inputs<const LANES: usize>() -> impl Iterator<Item = [F; LANES]> {
+        let edges = [
+            0,
+            1,
+            EPSILON - 1,
+            EPSILON,
+            F::ORDER - 1,
+            F::ORDER,
+            F::ORDER + 1,
+            u64::MAX,
+        ];
+        let mut values = edges.map(GoldilocksField).to_vec();
+        values.extend(F::rand_vec(64));
+        let n = values.len();
+        (0..n * n / LANES).map(move |i| core::array::from_fn(|j| values[(i * LANES + j) % n]))
+    }
+
+    fn check_ops<const LANES: usize>()
+    where
+        LaneCount<LANES>: SupportedLaneCount,
+    {
+        for (xs, ys) in inputs::<LANES>().zip(inputs::<LANES>().skip(3)) {
+            let x = GoldilocksVector::from_array(xs);
+            let y = GoldilocksVector::from_array(ys);
+            let raw = |v: GoldilocksVector<LANES>| v.to_array().map(|a| a.0);
+            assert_eq!(raw(x + y), core::array::from_fn(|i| (xs[i] + ys[i]).0));
+            assert_eq!(raw(x - y), core::array::from_fn(|i| (xs[i] - ys[i]).0));
+            assert_eq!(raw(x * y), core::array::from_fn(|i| (xs[i] * ys[i]).0));
+            assert_eq!(raw(x.to_canonical()), xs.map(|a| a.to_canonical_u64()));
+        }
+    }
+
+    #[test]
+    fn test_linear_combination_small() {
+        let xs: Vec<[F; 4]> = inputs::<4>().take(12).collect();
+        // Constants summing to `2^32 - 1` in the last lane.
+        let cs: [[u64; 4]; 12] = core::array::from_fn(|i| [17, i as u64, 0, EPSILON / 12 + 3]);
+        let result = GoldilocksVector::linear_combination_small(
+            core::array::from_fn(|i| GoldilocksVector::from_array(xs[i])),
+            cs.map(|c| GoldilocksVector::from_array(c.map(GoldilocksField))),
+        );
+        for lane in 0..4 {
+            let expected = (0..12)
+                .map(|i| xs[i][lane] * F::from_canonical_u64(cs[i][lane]))
+                .sum::<F>();
+            assert_eq!(result.to_array()[lane], expected);
+        }
+    }
+
+    #[test]
+    fn test_ops_match_scalar() {
+        check_ops::<1>();
+        check_ops::<4>();
+        check_ops::<8>();
+    }
+}
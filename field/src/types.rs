@@ -347,6 +347,16 @@ pub trait Field:
     /// Returns `n` as an element of this field.
     fn from_noncanonical_i64(n: i64) -> Self;
 
+    /// Returns `n` as an element of this field.
+    fn from_noncanonical_i128(n: i128) -> Self {
+        let f = Self::from_noncanonical_u128(n.unsigned_abs());
+        if n < 0 {
+            -f
+        } else {
+            f
+        }
+    }
+
     /// Returns `n % Self::characteristic()`. May be cheaper than from_noncanonical_u128 when we know
     /// that `n < 2 ** 96`.
     #[inline]
@@ -438,6 +448,12 @@ pub trait Field:
         Self::MULTIPLICATIVE_GROUP_GENERATOR
     }
 
+    /// Returns the sum of the given elements. Implementations may override this to delay modular
+    /// reduction, but the result must match an eager fold with `+`.
+    fn sum_slice(xs: &[Self]) -> Self {
+        xs.iter().copied().fold(Self::ZERO, |acc, x| acc + x)
+    }
+
     /// Equivalent to *self + x * y, but may be cheaper.
     #[inline]
     fn multiply_accumulate(&self, x: Self, y: Self) -> Self {
@@ -449,6 +465,33 @@ pub trait Field:
 pub trait PrimeField: Field {
     fn to_canonical_biguint(&self) -> BigUint;
 
+    /// Returns the centered representative of `self`, i.e. the `n` with `|n| <= (p - 1) / 2` such
+    /// that `self == n`, or `None` if it does not fit in an `i64`.
+    fn to_canonical_i64(&self) -> Option<i64> {
+        let n = self.to_canonical_biguint();
+        let order = Self::order();
+        if n <= (&order - 1u32) >> 1 {
+            n.to_i64()
+        } else {
+            (order - n).to_i128().and_then(|m| i64::try_from(-m).ok())
+        }
+    }
+
+    /// Returns the element whose canonical value has the little-endian bit decomposition `bits`,
+    /// or `None` if that value isn't less than the characteristic. Unlike summing the weighted bits
+    /// in the field, this rejects decompositions that would wrap around.
+    fn from_bits_le_checked(bits: &[bool]) -> Option<Self> {
+        let mut n = BigUint::zero();
+        for &bit in bits.iter().rev() {
+            n <<= 1;
+            if bit {
+                n += 1u32;
+            }
+        }
+        (n < Self::characteristic()).then(|| Self::from_noncanonical_biguint(n))
+    }
+
+    /// Whether `self` is a square, counting zero as one.
     fn is_quadratic_residue(&self) -> bool {
         if self.is_zero() {
             return true;
@@ -556,6 +599,14 @@ pub trait PrimeField64: PrimeField + Field64 {
 
     fn to_noncanonical_u64(&self) -> u64;
 
+    /// Returns the absolute value of the centered representative of `self`, i.e. `min(x, p - x)`
+    /// for the canonical `x`.
+    #[inline]
+    fn abs_centered(&self) -> u64 {
+        let x = self.to_canonical_u64();
+        x.min(Self::ORDER - x)
+    }
+
     #[inline(always)]
     fn to_canonical(&self) -> Self {
         Self::from_canonical_u64(self.to_canonical_u64())
@@ -1,11 +1,13 @@
 mod allocator;
 
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use plonky2::field::batch_util::batch_add_inplace;
 use plonky2::field::extension::quadratic::QuadraticExtension;
 use plonky2::field::extension::quartic::QuarticExtension;
 use plonky2::field::extension::quintic::QuinticExtension;
 use plonky2::field::goldilocks_field::GoldilocksField;
-use plonky2::field::types::Field;
+use plonky2::field::ops::Square;
+use plonky2::field::types::{Field, Sample};
 use tynm::type_name;
 
 pub(crate) fn bench_field<F: Field>(c: &mut Criterion) {
@@ -169,10 +171,120 @@ pub(crate) fn bench_field<F: Field>(c: &mut Criterion) {
             )
         },
     );
+
+    c.bench_function(&format!("sum_slice<{}>", type_name::<F>()), |b| {
+        b.iter_batched(
+            || F::rand_vec(1 << 16),
+            |x| F::sum_slice(&x),
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function(&format!("sum-eager-fold<{}>", type_name::<F>()), |b| {
+        b.iter_batched(
+            || F::rand_vec(1 << 16),
+            |x| x.iter().fold(F::ZERO, |acc, &y| acc + y),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+pub(crate) fn bench_goldilocks_sbox(c: &mut Criterion) {
+    c.bench_function("sbox-chain<GoldilocksField>", |b| {
+        b.iter_batched(
+            GoldilocksField::rand,
+            |mut x| {
+                for _ in 0..25 {
+                    x = x.sbox();
+                }
+                x
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    c.bench_function("sbox-exp_u64<GoldilocksField>", |b| {
+        b.iter_batched(
+            GoldilocksField::rand,
+            |mut x| {
+                for _ in 0..25 {
+                    x = x.exp_u64(7);
+                }
+                x
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_goldilocks_square_n(c: &mut Criterion) {
+    c.bench_function("square_n(32)<GoldilocksField>", |b| {
+        b.iter_batched(
+            GoldilocksField::rand,
+            |x| x.square_n(32),
+            BatchSize::SmallInput,
+        )
+    });
+
+    c.bench_function("repeated-square(32)<GoldilocksField>", |b| {
+        b.iter_batched(
+            GoldilocksField::rand,
+            |mut x| {
+                for _ in 0..32 {
+                    x = x.square();
+                }
+                x
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_goldilocks_axpy(c: &mut Criterion) {
+    const LEN: usize = 4096;
+    let mut group = c.benchmark_group("axpy<GoldilocksField>");
+
+    group.bench_function("two-pass", |b| {
+        b.iter_batched(
+            || {
+                (
+                    GoldilocksField::rand(),
+                    GoldilocksField::rand_vec(LEN),
+                    GoldilocksField::rand_vec(LEN),
+                )
+            },
+            |(alpha, x, mut acc)| {
+                let scaled = x.iter().map(|&x| alpha * x).collect::<Vec<_>>();
+                batch_add_inplace(&mut acc, &scaled);
+                acc
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("fused", |b| {
+        b.iter_batched(
+            || {
+                (
+                    GoldilocksField::rand(),
+                    GoldilocksField::rand_vec(LEN),
+                    GoldilocksField::rand_vec(LEN),
+                )
+            },
+            |(alpha, x, mut acc)| {
+                GoldilocksField::axpy(alpha, &x, &mut acc);
+                acc
+            },
+            BatchSize::LargeInput,
+        )
+    });
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
     bench_field::<GoldilocksField>(c);
+    bench_goldilocks_sbox(c);
+    bench_goldilocks_square_n(c);
+    bench_goldilocks_axpy(c);
     bench_field::<QuadraticExtension<GoldilocksField>>(c);
     bench_field::<QuarticExtension<GoldilocksField>>(c);
     bench_field::<QuinticExtension<GoldilocksField>>(c);
@@ -1,11 +1,12 @@
 mod allocator;
 
-use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
 use plonky2::field::goldilocks_field::GoldilocksField;
 use plonky2::field::types::Sample;
 use plonky2::hash::hash_types::{BytesHash, RichField};
 use plonky2::hash::keccak::KeccakHash;
 use plonky2::hash::poseidon::{Poseidon, SPONGE_WIDTH};
+use plonky2::hash::poseidon_simd::{set_simd_backend, simd_backend, SimdBackend};
 use plonky2::plonk::config::Hasher;
 use tynm::type_name;
 
@@ -32,8 +33,33 @@ pub(crate) fn bench_poseidon<F: Poseidon>(c: &mut Criterion) {
     );
 }
 
+/// Poseidon with each backend the CPU supports, and the one it detects, to check that the dispatch
+/// picks the fastest.
+pub(crate) fn bench_poseidon_backends(c: &mut Criterion) {
+    set_simd_backend(None).unwrap();
+    let detected = simd_backend();
+    let mut group = c.benchmark_group(format!(
+        "poseidon<GoldilocksField, {SPONGE_WIDTH}> backends (detected: {})",
+        detected.name()
+    ));
+    group.throughput(Throughput::Elements(1));
+    for backend in SimdBackend::ALL.into_iter().filter(|b| b.is_supported()) {
+        set_simd_backend(Some(backend)).unwrap();
+        group.bench_function(backend.name(), |b| {
+            b.iter_batched(
+                GoldilocksField::rand_array::<SPONGE_WIDTH>,
+                GoldilocksField::poseidon,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+    set_simd_backend(None).unwrap();
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     bench_poseidon::<GoldilocksField>(c);
+    bench_poseidon_backends(c);
     bench_keccak::<GoldilocksField>(c);
 }
 
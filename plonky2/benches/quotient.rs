@@ -0,0 +1,59 @@
+mod allocator;
+#[allow(dead_code)]
+#[path = "../tests/common/quotient.rs"]
+mod quotient;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use plonky2::field::types::Field;
+use plonky2::gates::noop::NoopGate;
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::prover::QUOTIENT_BLOCK_BITS;
+use quotient::{QuotientInputs, C, D, F};
+
+fn bench_quotient(c: &mut Criterion) {
+    let degree_bits = 20;
+
+    // A mix of a few hundred gates of different types, padded to the degree, so that every row's
+    // constraints are evaluated under several selectors.
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    let x = builder.add_virtual_public_input();
+    let mut acc = x;
+    for i in 0..100 {
+        acc = builder.mul_add(acc, x, acc);
+        let c = builder.constant(F::from_canonical_usize(i));
+        acc = builder.exp(acc, c, 8);
+        if i % 10 == 0 {
+            acc = builder
+                .hash_n_to_hash_no_pad::<PoseidonHash>(vec![acc, x])
+                .elements[0];
+        }
+    }
+    builder.register_public_input(acc);
+    while builder.num_gates() < 1 << degree_bits {
+        builder.add_gate(NoopGate, vec![]);
+    }
+    let inputs = QuotientInputs::new(builder.build::<C>());
+
+    let mut group = c.benchmark_group(format!("quotient-polys<2^{degree_bits}>"));
+    group.sample_size(10);
+
+    // Blocks of a single batch, the default blocks, and the whole domain in one block.
+    for block_bits in [5, QUOTIENT_BLOCK_BITS, usize::MAX] {
+        group.bench_with_input(
+            BenchmarkId::new("block-bits", block_bits),
+            &block_bits,
+            |b, &block_bits| {
+                b.iter(|| inputs.computation(block_bits).compute());
+            },
+        );
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    bench_quotient(c);
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,460 @@
+//! A byte-level entry point for validating proofs, for callers such as services or foreign language
+//! bindings which only handle serialized artifacts.
+//!
+//! Artifacts use the compact byte format of [`crate::util::serialization`], for circuits using
+//! [`PoseidonGoldilocksConfig`] over the quadratic extension. Each artifact is prefixed by the
+//! [artifact header](crate::util::serialization::header), so that passing the wrong blob, or a blob
+//! written by an incompatible release, is reported as such rather than as a failure to deserialize
+//! or verify. Proofs carry the full [`ProofHeader`], binding them to their common circuit data.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+use std::time::{Duration, Instant};
+
+use crate::field::types::PrimeField64;
+use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
+use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use crate::plonk::proof::ProofWithPublicInputs;
+use crate::plonk::proof_header::ProofHeader;
+use crate::plonk::validate_shape::validate_proof_with_pis_shape;
+use crate::plonk::verifier::verify;
+use crate::util::serialization::header::{ArtifactHeader, HeaderError};
+use crate::util::serialization::{Buffer, DefaultGateSerializer, IoResult, Read, Remaining, Write};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+pub use crate::util::serialization::header::{ArtifactKind, ARTIFACT_HEADER_LEN, ARTIFACT_MAGIC};
+
+/// The reasons [`validate_proof_bytes`] can reject its inputs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ValidationError {
+    /// The artifact is too short to hold a header.
+    MissingHeader { artifact: ArtifactKind },
+    /// The artifact doesn't start with [`ARTIFACT_MAGIC`].
+    BadMagic { artifact: ArtifactKind },
+    /// The header names another kind of artifact, or an unknown kind `found`.
+    WrongArtifactKind { expected: ArtifactKind, found: u8 },
+    /// The artifact was written with an encoding this release can't read.
+    UnsupportedFormatVersion {
+        artifact: ArtifactKind,
+        version: u16,
+    },
+    /// The artifact's body couldn't be deserialized, e.g. because it is truncated or was written
+    /// for another circuit.
+    Malformed {
+        artifact: ArtifactKind,
+        reason: String,
+    },
+    /// The proof doesn't have the shape the common circuit data prescribes.
+    Shape(String),
+    /// The proof is well-formed, but invalid.
+    Verification(String),
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingHeader { artifact } => {
+                write!(f, "The {artifact} is too short to hold an artifact header")
+            }
+            Self::BadMagic { artifact } => {
+                write!(f, "The {artifact} doesn't start with the artifact magic")
+            }
+            Self::WrongArtifactKind { expected, found } => match ArtifactKind::from_tag(*found) {
+                Some(found) => write!(f, "Expected the {expected}, but got the {found}"),
+                None => write!(
+                    f,
+                    "Expected the {expected}, but got an unknown artifact {found}"
+                ),
+            },
+            Self::UnsupportedFormatVersion { artifact, version } => write!(
+                f,
+                "The {artifact} has format version {version}, but only version {} is supported",
+                artifact.format_version()
+            ),
+            Self::Malformed { artifact, reason } => write!(f, "Malformed {artifact}: {reason}"),
+            Self::Shape(reason) => write!(f, "The proof has the wrong shape: {reason}"),
+            Self::Verification(reason) => write!(f, "The proof is invalid: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// The size, in bytes, of each part of a serialized proof, excluding its header.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProofSizeBreakdown {
+    /// The public inputs, with their count.
+    pub public_inputs: usize,
+    /// The Merkle caps of the wires, challenge-dependent columns, `Z`s and quotient polynomials.
+    pub merkle_caps: usize,
+    /// The openings of all polynomials at the challenge point.
+    pub openings: usize,
+    /// The Merkle caps of FRI's commit phase.
+    pub fri_commit_phase_caps: usize,
+    /// FRI's query rounds, which usually dominate.
+    pub fri_query_rounds: usize,
+    /// FRI's final polynomial and proof-of-work witnesses.
+    pub fri_final_poly_and_pow: usize,
+}
+
+impl ProofSizeBreakdown {
+    pub const fn total(&self) -> usize {
+        self.public_inputs
+            + self.merkle_caps
+            + self.openings
+            + self.fri_commit_phase_caps
+            + self.fri_query_rounds
+            + self.fri_final_poly_and_pow
+    }
+
+    fn of(proof_with_pis: &ProofWithPublicInputs<F, C, D>) -> IoResult<Self> {
+        let proof = &proof_with_pis.proof;
+        let fri_proof = &proof.opening_proof;
+        let size = |write: &dyn Fn(&mut Vec<u8>) -> IoResult<()>| -> IoResult<usize> {
+            let mut buffer = Vec::new();
+            write(&mut buffer)?;
+            Ok(buffer.len())
+        };
+        Ok(Self {
+            public_inputs: size(&|b| {
+                b.write_usize(proof_with_pis.public_inputs.len())?;
+                b.write_field_vec(&proof_with_pis.public_inputs)
+            })?,
+            merkle_caps: size(&|b| {
+                b.write_merkle_cap(&proof.wires_cap)?;
+                b.write_merkle_cap(&proof.challenge_dependent_cap)?;
+                b.write_merkle_cap(&proof.plonk_zs_partial_products_cap)?;
+                b.write_merkle_cap(&proof.quotient_polys_cap)
+            })?,
+            openings: size(&|b| b.write_opening_set(&proof.openings))?,
+            fri_commit_phase_caps: size(&|b| {
+                for cap in &fri_proof.commit_phase_merkle_caps {
+                    b.write_merkle_cap(cap)?;
+                }
+                Ok(())
+            })?,
+            fri_query_rounds: size(&|b| {
+                b.write_fri_query_rounds::<F, C, D>(&fri_proof.query_round_proofs)
+            })?,
+            fri_final_poly_and_pow: size(&|b| {
+                b.write_field_ext_vec::<F, D>(&fri_proof.final_poly.coeffs)?;
+                b.write_field(fri_proof.pow_witness)?;
+                b.write_field_vec(&fri_proof.round_pow_witnesses)
+            })?,
+        })
+    }
+}
+
+/// How long each phase of [`validate_proof_bytes`] took.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ValidationTiming {
+    pub deserialization: Duration,
+    pub structural_validation: Duration,
+    pub verification: Duration,
+}
+
+impl ValidationTiming {
+    pub fn total(&self) -> Duration {
+        self.deserialization + self.structural_validation + self.verification
+    }
+}
+
+/// A summary of a successfully validated proof.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidationReport {
+    /// The proof's public inputs, in canonical form.
+    pub public_inputs: Vec<u64>,
+    pub proof_size: ProofSizeBreakdown,
+    /// The conjectured security of the circuit's config, which may exceed the security it targets.
+    pub security_bits: usize,
+    pub timing: ValidationTiming,
+}
+
+/// Encodes verifier data as an artifact for [`validate_proof_bytes`].
+pub fn encode_verifier_data(verifier_data: &VerifierOnlyCircuitData<C, D>) -> IoResult<Vec<u8>> {
+    let mut bytes = header(ArtifactKind::VerifierData);
+    bytes.write_verifier_only_circuit_data(verifier_data)?;
+    Ok(bytes)
+}
+
+/// Encodes common circuit data as an artifact for [`validate_proof_bytes`]. Gates are encoded with
+/// the [`DefaultGateSerializer`].
+pub fn encode_common_data(common_data: &CommonCircuitData<F, D>) -> IoResult<Vec<u8>> {
+    let mut bytes = header(ArtifactKind::CommonData);
+    bytes.write_common_circuit_data(common_data, &DefaultGateSerializer)?;
+    Ok(bytes)
+}
+
+/// Encodes a proof as an artifact for [`validate_proof_bytes`], that is with its [`ProofHeader`].
+pub fn encode_proof(
+    proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Vec<u8> {
+    proof_with_pis.to_bytes_with_header(common_data)
+}
+
+/// Deserializes the given artifacts, as written by [`encode_verifier_data`],
+/// [`encode_common_data`] and [`encode_proof`], checks the proof's shape and then verifies it.
+pub fn validate_proof_bytes(
+    verifier_data: &[u8],
+    common_data: &[u8],
+    proof: &[u8],
+) -> Result<ValidationReport, ValidationError> {
+    let start = Instant::now();
+    let verifier_data = read_artifact(verifier_data, ArtifactKind::VerifierData, |buffer| {
+        buffer.read_verifier_only_circuit_data()
+    })?;
+    let common_data = read_artifact(common_data, ArtifactKind::CommonData, |buffer| {
+        buffer.read_common_circuit_data::<F, D>(&DefaultGateSerializer)
+    })?;
+    split_header(proof, ArtifactKind::Proof)?;
+    let proof_body = ProofHeader::split(proof)
+        .and_then(|(header, body)| {
+            header.check::<F, C, D>(false, &common_data)?;
+            Ok(body)
+        })
+        .map_err(|e| ValidationError::Malformed {
+            artifact: ArtifactKind::Proof,
+            reason: e.to_string(),
+        })?;
+    // The common data has already been used to infer the proof's shape while reading it, so the
+    // remaining bytes are also checked to catch a proof for a larger circuit.
+    let proof_with_pis = read_body(proof_body, ArtifactKind::Proof, |buffer| {
+        buffer.read_proof_with_public_inputs(&common_data)
+    })?;
+    let deserialization = start.elapsed();
+
+    let start = Instant::now();
+    validate_proof_with_pis_shape(&proof_with_pis, &common_data)
+        .map_err(|e| ValidationError::Shape(e.to_string()))?;
+    let structural_validation = start.elapsed();
+
+    let public_inputs = proof_with_pis
+        .public_inputs
+        .iter()
+        .map(|x| x.to_canonical_u64())
+        .collect();
+    let proof_size =
+        ProofSizeBreakdown::of(&proof_with_pis).expect("Writing to a byte-vector cannot fail.");
+
+    let start = Instant::now();
+    verify::<F, C, D>(proof_with_pis, &verifier_data, &common_data)
+        .map_err(|e| ValidationError::Verification(e.to_string()))?;
+    let verification = start.elapsed();
+
+    Ok(ValidationReport {
+        public_inputs,
+        proof_size,
+        security_bits: common_data.config.conjectured_security_bits::<F, D>(),
+        timing: ValidationTiming {
+            deserialization,
+            structural_validation,
+            verification,
+        },
+    })
+}
+
+fn header(kind: ArtifactKind) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(ARTIFACT_HEADER_LEN);
+    ArtifactHeader::current(kind).write(&mut bytes);
+    bytes
+}
+
+/// Checks the header of `bytes`, and returns the artifact's body.
+fn split_header(bytes: &[u8], kind: ArtifactKind) -> Result<&[u8], ValidationError> {
+    ArtifactHeader::split_kind(bytes, kind).map_err(|e| match e {
+        HeaderError::Missing => ValidationError::MissingHeader { artifact: kind },
+        HeaderError::BadMagic => ValidationError::BadMagic { artifact: kind },
+        HeaderError::UnknownKind(found) => ValidationError::WrongArtifactKind {
+            expected: kind,
+            found,
+        },
+        HeaderError::WrongKind { expected, found } => ValidationError::WrongArtifactKind {
+            expected,
+            found: found.tag(),
+        },
+        HeaderError::UnsupportedFormatVersion { kind, version } => {
+            ValidationError::UnsupportedFormatVersion {
+                artifact: kind,
+                version,
+            }
+        }
+    })
+}
+
+/// Checks the header of `bytes`, then reads its body, which must be consumed entirely.
+fn read_artifact<T>(
+    bytes: &[u8],
+    kind: ArtifactKind,
+    read: impl FnOnce(&mut Buffer) -> IoResult<T>,
+) -> Result<T, ValidationError> {
+    read_body(split_header(bytes, kind)?, kind, read)
+}
+
+/// Reads the body of an artifact, which must be consumed entirely.
+fn read_body<T>(
+    body: &[u8],
+    kind: ArtifactKind,
+    read: impl FnOnce(&mut Buffer) -> IoResult<T>,
+) -> Result<T, ValidationError> {
+    let mut buffer = Buffer::new(body);
+    let value = read(&mut buffer).map_err(|_| ValidationError::Malformed {
+        artifact: kind,
+        reason: "the body is truncated or invalid".to_string(),
+    })?;
+    if !buffer.is_empty() {
+        return Err(ValidationError::Malformed {
+            artifact: kind,
+            reason: alloc::format!("{} trailing bytes", buffer.remaining()),
+        });
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::iop::target::Target;
+    use crate::plonk::circuit_data::CircuitData;
+    use crate::util::test_fixtures::{
+        self, fixture_config, fixture_fri_config, prove_fixture, write_fixture, FIXTURE_X,
+    };
+
+    const VERIFIER_DATA: &[u8] = include_bytes!("fixtures/square_plus_3_verifier_data.bin");
+    const COMMON_DATA: &[u8] = include_bytes!("fixtures/square_plus_3_common_data.bin");
+    const PROOF: &[u8] = include_bytes!("fixtures/square_plus_3_proof.bin");
+    /// The verifier data of a circuit with the same common data, proving `y = x^2 + 5` instead.
+    const OTHER_VERIFIER_DATA: &[u8] = include_bytes!("fixtures/square_plus_5_verifier_data.bin");
+
+    fn fixture_circuit(c: u64) -> (CircuitData<F, C, D>, Target) {
+        test_fixtures::fixture_circuit(fixture_config(false, fixture_fri_config()), c)
+    }
+
+    #[test]
+    fn test_fixtures_match_circuits() -> Result<()> {
+        let (data, _) = fixture_circuit(3);
+        assert_eq!(
+            encode_verifier_data(&data.verifier_only).unwrap(),
+            VERIFIER_DATA
+        );
+        assert_eq!(encode_common_data(&data.common).unwrap(), COMMON_DATA);
+        let (other_data, _) = fixture_circuit(5);
+        assert_eq!(
+            encode_verifier_data(&other_data.verifier_only).unwrap(),
+            OTHER_VERIFIER_DATA
+        );
+        assert_eq!(other_data.common, data.common);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_proof_bytes() -> Result<()> {
+        let report = validate_proof_bytes(VERIFIER_DATA, COMMON_DATA, PROOF)?;
+        assert_eq!(report.public_inputs, [FIXTURE_X, FIXTURE_X * FIXTURE_X + 3]);
+        let (_, body) = ProofHeader::split(PROOF)?;
+        assert_eq!(report.proof_size.total(), body.len());
+        assert_eq!(report.security_bits, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_proof() {
+        for len in [PROOF.len() - 1, ARTIFACT_HEADER_LEN + 10] {
+            assert!(matches!(
+                validate_proof_bytes(VERIFIER_DATA, COMMON_DATA, &PROOF[..len]),
+                Err(ValidationError::Malformed {
+                    artifact: ArtifactKind::Proof,
+                    ..
+                })
+            ));
+        }
+        assert_eq!(
+            validate_proof_bytes(VERIFIER_DATA, COMMON_DATA, &PROOF[..3]),
+            Err(ValidationError::MissingHeader {
+                artifact: ArtifactKind::Proof
+            })
+        );
+
+        let mut extended = PROOF.to_vec();
+        extended.push(0);
+        assert!(matches!(
+            validate_proof_bytes(VERIFIER_DATA, COMMON_DATA, &extended),
+            Err(ValidationError::Malformed {
+                artifact: ArtifactKind::Proof,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_wrong_circuit_proof() {
+        assert!(matches!(
+            validate_proof_bytes(OTHER_VERIFIER_DATA, COMMON_DATA, PROOF),
+            Err(ValidationError::Verification(_))
+        ));
+    }
+
+    #[test]
+    fn test_mismatched_headers() {
+        assert_eq!(
+            validate_proof_bytes(COMMON_DATA, VERIFIER_DATA, PROOF),
+            Err(ValidationError::WrongArtifactKind {
+                expected: ArtifactKind::VerifierData,
+                found: ArtifactKind::CommonData.tag(),
+            })
+        );
+
+        let mut bad_magic = PROOF.to_vec();
+        bad_magic[0] ^= 1;
+        assert_eq!(
+            validate_proof_bytes(VERIFIER_DATA, COMMON_DATA, &bad_magic),
+            Err(ValidationError::BadMagic {
+                artifact: ArtifactKind::Proof
+            })
+        );
+
+        let mut future_version = COMMON_DATA.to_vec();
+        future_version[ARTIFACT_HEADER_LEN - 2..ARTIFACT_HEADER_LEN]
+            .copy_from_slice(&(ArtifactKind::CommonData.format_version() + 1).to_le_bytes());
+        assert_eq!(
+            validate_proof_bytes(VERIFIER_DATA, &future_version, PROOF),
+            Err(ValidationError::UnsupportedFormatVersion {
+                artifact: ArtifactKind::CommonData,
+                version: ArtifactKind::CommonData.format_version() + 1,
+            })
+        );
+    }
+
+    /// Regenerates the fixtures, after a deliberate change of the artifact format.
+    #[test]
+    #[ignore]
+    fn regenerate_entrypoint_fixtures() -> Result<()> {
+        let (data, x) = fixture_circuit(3);
+        let proof = prove_fixture(&data, x)?;
+        write_fixture(
+            "entrypoint/fixtures/square_plus_3_verifier_data.bin",
+            encode_verifier_data(&data.verifier_only).unwrap(),
+        )?;
+        write_fixture(
+            "entrypoint/fixtures/square_plus_3_common_data.bin",
+            encode_common_data(&data.common).unwrap(),
+        )?;
+        write_fixture(
+            "entrypoint/fixtures/square_plus_3_proof.bin",
+            encode_proof(&proof, &data.common),
+        )?;
+
+        let (other_data, _) = fixture_circuit(5);
+        write_fixture(
+            "entrypoint/fixtures/square_plus_5_verifier_data.bin",
+            encode_verifier_data(&other_data.verifier_only).unwrap(),
+        )?;
+        Ok(())
+    }
+}
@@ -1,15 +1,20 @@
+use alloc::vec::Vec;
+
 use crate::field::extension::Extendable;
 use crate::field::polynomial::PolynomialCoeffs;
-use crate::fri::proof::{FriChallenges, FriChallengesTarget};
+use crate::fri::proof::{FriChallenges, FriChallengesTarget, FriProof, FriProofTarget};
 use crate::fri::structure::{FriOpenings, FriOpeningsTarget};
-use crate::fri::FriConfig;
+use crate::fri::{FriConfig, FriParams};
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::hash::hash_types::{MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::challenger::{Challenger, RecursiveChallenger};
+use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use crate::recursion::variable_degree::VariableDegreeBits;
+use crate::util::log_size::DegreeBits;
 
 impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
     pub fn observe_openings<const D: usize>(&mut self, openings: &FriOpenings<F, D>)
@@ -21,47 +26,91 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
         }
     }
 
+    /// Observes every element of `proof`, in the order of `FriProof::to_field_elements`.
+    pub fn observe_fri_proof<const D: usize>(&mut self, proof: &FriProof<F, H, D>)
+    where
+        F: RichField + Extendable<D>,
+    {
+        self.observe_elements(&proof.to_field_elements());
+    }
+
     pub fn fri_challenges<C: GenericConfig<D, F = F>, const D: usize>(
         &mut self,
         commit_phase_merkle_caps: &[MerkleCap<F, C::Hasher>],
         final_poly: &PolynomialCoeffs<F::Extension>,
         pow_witness: F,
-        degree_bits: usize,
+        round_pow_witnesses: &[F],
+        degree_bits: DegreeBits,
         config: &FriConfig,
     ) -> FriChallenges<F, D>
     where
         F: RichField + Extendable<D>,
     {
         let num_fri_queries = config.num_query_rounds;
-        let lde_size = 1 << (degree_bits + config.rate_bits);
+        let lde_size = degree_bits.lde_bits(config.rate_bits).size();
         // Scaling factor to combine polynomials.
+        self.label(b"fri_alpha");
         let fri_alpha = self.get_extension_challenge::<D>();
 
-        // Recover the random betas used in the FRI reductions.
+        // Recover the random betas used in the FRI reductions, and the PoW responses of the rounds
+        // which are ground.
+        let mut fri_round_pow_responses = Vec::with_capacity(round_pow_witnesses.len());
         let fri_betas = commit_phase_merkle_caps
             .iter()
-            .map(|cap| {
+            .enumerate()
+            .map(|(i, cap)| {
+                self.label(b"fri_commit_phase_cap");
                 self.observe_cap::<C::Hasher>(cap);
+                if let Some(&round_pow_witness) = round_pow_witnesses.get(i) {
+                    self.label(b"fri_round_pow");
+                    self.observe_round_pow_separator(i);
+                    self.observe_element(round_pow_witness);
+                    fri_round_pow_responses.push(self.get_challenge());
+                }
+                self.label(b"fri_beta");
                 self.get_extension_challenge::<D>()
             })
             .collect();
 
+        self.label(b"fri_final_poly");
         self.observe_extension_elements(&final_poly.coeffs);
+        self.label(b"fri_pow");
+        self.observe_pow_context();
 
-        self.observe_element(pow_witness);
-        let fri_pow_response = self.get_challenge();
+        let fri_pow_response = if config.pow_strategy.final_bits().is_some() {
+            self.observe_element(pow_witness);
+            self.get_challenge()
+        } else {
+            F::ZERO
+        };
 
-        let fri_query_indices = (0..num_fri_queries)
-            .map(|_| self.get_challenge().to_canonical_u64() as usize % lde_size)
-            .collect();
+        self.label(b"fri_query_indices");
+        let fri_query_indices = self.sample_query_indices(lde_size, num_fri_queries);
 
         FriChallenges {
             fri_alpha,
             fri_betas,
             fri_pow_response,
+            fri_round_pow_responses,
             fri_query_indices,
         }
     }
+
+    /// Separates the grind of commit-phase round `round` from those of the other rounds.
+    pub(crate) fn observe_round_pow_separator(&mut self, round: usize) {
+        self.observe_element(F::from_canonical_usize(round));
+    }
+
+    /// Observes the PoW context, if any, prefixed by its length so that distinct contexts never
+    /// lead to the same transcript.
+    pub(crate) fn observe_pow_context(&mut self) {
+        if self.pow_context.is_empty() {
+            return;
+        }
+        let pow_context = self.pow_context.clone();
+        self.observe_element(F::from_canonical_usize(pow_context.len()));
+        self.observe_elements(&pow_context);
+    }
 }
 
 impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
@@ -73,41 +122,286 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         }
     }
 
+    /// Observes every element of `proof`, in the same order as `Challenger::observe_fri_proof`.
+    pub fn observe_fri_proof(&mut self, proof: &FriProofTarget<D>) {
+        self.observe_elements(&proof.to_targets());
+    }
+
     pub fn fri_challenges(
         &mut self,
         builder: &mut CircuitBuilder<F, D>,
         commit_phase_merkle_caps: &[MerkleCapTarget],
         final_poly: &PolynomialCoeffsExtTarget<D>,
         pow_witness: Target,
+        round_pow_witnesses: &[Target],
         inner_fri_config: &FriConfig,
     ) -> FriChallengesTarget<D> {
-        let num_fri_queries = inner_fri_config.num_query_rounds;
+        let (fri_alpha, fri_betas, fri_round_pow_responses) = self.fri_commit_phase_challenges(
+            builder,
+            commit_phase_merkle_caps,
+            round_pow_witnesses,
+        );
+
+        self.observe_extension_elements(&final_poly.0);
+
+        let (fri_pow_response, fri_query_indices) =
+            self.fri_query_phase_challenges(builder, pow_witness, inner_fri_config);
+
+        FriChallengesTarget {
+            fri_alpha,
+            fri_betas,
+            fri_pow_response,
+            fri_round_pow_responses,
+            fri_query_indices,
+        }
+    }
+
+    /// Like `fri_challenges`, for a proof padded to the shape of `inner_fri_params` whose actual
+    /// degree is the witnessed `degree_bits`. The native verifier of the proof only observes the
+    /// final polynomial up to its actual length, which depends on the degree, so the PoW response
+    /// and the query indices are derived from a copy of the challenger for each possible degree,
+    /// and those of the witnessed degree are selected.
+    pub(crate) fn fri_challenges_variable_degree(
+        mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        commit_phase_merkle_caps: &[MerkleCapTarget],
+        final_poly: &PolynomialCoeffsExtTarget<D>,
+        pow_witness: Target,
+        round_pow_witnesses: &[Target],
+        inner_fri_params: &FriParams,
+        degree_bits: &VariableDegreeBits,
+    ) -> FriChallengesTarget<D> {
+        let (fri_alpha, fri_betas, fri_round_pow_responses) = self.fri_commit_phase_challenges(
+            builder,
+            commit_phase_merkle_caps,
+            round_pow_witnesses,
+        );
+
+        let max_degree_bits = inner_fri_params.degree_bits.get();
+        let (pow_responses, query_indices): (Vec<_>, Vec<_>) = degree_bits
+            .range()
+            .map(|d| {
+                let final_poly_len = inner_fri_params.final_poly_len() >> (max_degree_bits - d);
+                let mut challenger = self.clone();
+                challenger.observe_extension_elements(&final_poly.0[..final_poly_len]);
+                challenger.fri_query_phase_challenges(
+                    builder,
+                    pow_witness,
+                    &inner_fri_params.config,
+                )
+            })
+            .unzip();
+
+        let fri_pow_response = degree_bits.select(builder, &pow_responses);
+        let fri_query_indices = (0..inner_fri_params.config.num_query_rounds)
+            .map(|i| {
+                let candidates = query_indices.iter().map(|q| q[i]).collect::<Vec<_>>();
+                degree_bits.select(builder, &candidates)
+            })
+            .collect();
+
+        FriChallengesTarget {
+            fri_alpha,
+            fri_betas,
+            fri_pow_response,
+            fri_round_pow_responses,
+            fri_query_indices,
+        }
+    }
+
+    /// Derives the FRI challenges which precede the final polynomial: the scaling factor `alpha`,
+    /// the reduction betas, and the responses to the PoW of the ground commit-phase rounds.
+    fn fri_commit_phase_challenges(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        commit_phase_merkle_caps: &[MerkleCapTarget],
+        round_pow_witnesses: &[Target],
+    ) -> (ExtensionTarget<D>, Vec<ExtensionTarget<D>>, Vec<Target>) {
         // Scaling factor to combine polynomials.
         let fri_alpha = self.get_extension_challenge(builder);
 
-        // Recover the random betas used in the FRI reductions.
+        // Recover the random betas used in the FRI reductions, and the PoW responses of the rounds
+        // which are ground.
+        let mut fri_round_pow_responses = Vec::with_capacity(round_pow_witnesses.len());
         let fri_betas = commit_phase_merkle_caps
             .iter()
-            .map(|cap| {
+            .enumerate()
+            .map(|(i, cap)| {
                 self.observe_cap(cap);
+                if let Some(&round_pow_witness) = round_pow_witnesses.get(i) {
+                    let separator = builder.constant(F::from_canonical_usize(i));
+                    self.observe_element(separator);
+                    self.observe_element(round_pow_witness);
+                    fri_round_pow_responses.push(self.get_challenge(builder));
+                }
                 self.get_extension_challenge(builder)
             })
             .collect();
 
-        self.observe_extension_elements(&final_poly.0);
+        (fri_alpha, fri_betas, fri_round_pow_responses)
+    }
 
-        self.observe_element(pow_witness);
-        let fri_pow_response = self.get_challenge(builder);
+    /// Derives the FRI challenges which follow the final polynomial: the PoW response, if any, and
+    /// the query indices.
+    fn fri_query_phase_challenges(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        pow_witness: Target,
+        inner_fri_config: &FriConfig,
+    ) -> (Target, Vec<Target>) {
+        let fri_pow_response = if inner_fri_config.pow_strategy.final_bits().is_some() {
+            self.observe_element(pow_witness);
+            self.get_challenge(builder)
+        } else {
+            builder.zero()
+        };
 
-        let fri_query_indices = (0..num_fri_queries)
+        let fri_query_indices = (0..inner_fri_config.num_query_rounds)
             .map(|_| self.get_challenge(builder))
             .collect();
 
-        FriChallengesTarget {
-            fri_alpha,
-            fri_betas,
-            fri_pow_response,
-            fri_query_indices,
+        (fri_pow_response, fri_query_indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::extension::FieldExtension;
+    use crate::field::types::Sample;
+    use crate::fri::reduction_strategies::FriReductionStrategy;
+    use crate::fri::witness_util::set_fri_proof_target;
+    use crate::gates::noop::NoopGate;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    #[test]
+    fn test_fri_challenges_in_circuit() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![1, 2, 1]);
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let inner = builder.build::<C>();
+        let proof = inner.prove(PartialWitness::new())?;
+        let fri_proof = &proof.proof.opening_proof;
+        let fri_config = &inner.common.config.fri_config;
+
+        let mut challenger = Challenger::<F, H>::new();
+        let expected = challenger.fri_challenges::<C, D>(
+            &fri_proof.commit_phase_merkle_caps,
+            &fri_proof.final_poly,
+            fri_proof.pow_witness,
+            &fri_proof.round_pow_witnesses,
+            inner.common.degree_bits(),
+            fri_config,
+        );
+        // Each extension challenge is `D` consecutive base challenges.
+        let mut challenger = Challenger::<F, H>::new();
+        let base_challenges = challenger.get_n_challenges(D);
+        assert_eq!(
+            expected.fri_alpha,
+            <<F as Extendable<D>>::Extension as FieldExtension<D>>::from_basefield_array(
+                base_challenges.try_into().unwrap()
+            )
+        );
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let leaf_lens = fri_proof.query_round_proofs[0]
+            .initial_trees_proof
+            .evals_proofs
+            .iter()
+            .map(|(leaf, _)| leaf.len())
+            .collect::<Vec<_>>();
+        let proof_target = builder.add_virtual_fri_proof(&leaf_lens, &inner.common.fri_params);
+        let mut recursive_challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+        let challenges = recursive_challenger.fri_challenges(
+            &mut builder,
+            &proof_target.commit_phase_merkle_caps,
+            &proof_target.final_poly,
+            proof_target.pow_witness,
+            &proof_target.round_pow_witnesses,
+            fri_config,
+        );
+        builder.register_public_inputs(&challenges.fri_alpha.0);
+        for beta in &challenges.fri_betas {
+            builder.register_public_inputs(&beta.0);
         }
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        set_fri_proof_target(&mut pw, &proof_target, fri_proof);
+        let outer_proof = data.prove(pw)?;
+        let expected_elements = [expected.fri_alpha]
+            .iter()
+            .chain(&expected.fri_betas)
+            .flat_map(FieldExtension::<D>::to_basefield_array)
+            .collect::<Vec<_>>();
+        assert_eq!(outer_proof.public_inputs, expected_elements);
+        data.verify(outer_proof)
+    }
+
+    #[test]
+    fn test_observe_fri_proof_in_circuit() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![1, 2, 1]);
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.constant(F::rand());
+        let y = builder.constant(F::rand());
+        builder.mul(x, y);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let inner = builder.build::<C>();
+        let proof = inner.prove(PartialWitness::new())?;
+        let fri_proof = &proof.proof.opening_proof;
+
+        let mut challenger = Challenger::<F, H>::new();
+        challenger.observe_fri_proof(fri_proof);
+        let expected = challenger.get_extension_challenge::<D>();
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let leaf_lens = fri_proof.query_round_proofs[0]
+            .initial_trees_proof
+            .evals_proofs
+            .iter()
+            .map(|(leaf, _)| leaf.len())
+            .collect::<Vec<_>>();
+        let proof_target = builder.add_virtual_fri_proof(&leaf_lens, &inner.common.fri_params);
+        assert_eq!(
+            proof_target.to_targets().len(),
+            fri_proof.to_field_elements().len()
+        );
+        let mut recursive_challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+        recursive_challenger.observe_fri_proof(&proof_target);
+        let challenge = recursive_challenger.get_extension_challenge(&mut builder);
+        builder.register_public_inputs(&challenge.0);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        set_fri_proof_target(&mut pw, &proof_target, fri_proof);
+        let outer_proof = data.prove(pw)?;
+        assert_eq!(
+            outer_proof.public_inputs,
+            FieldExtension::<D>::to_basefield_array(&expected)
+        );
+        data.verify(outer_proof)
     }
 }
@@ -0,0 +1,226 @@
+//! The FRI folding step, in the forms used by the prover and the verifier.
+//!
+//! With arity `r = 2^arity_bits`, a polynomial is split as `P(X) = sum_{i<r} X^i P_i(X^r)` and
+//! folded with the challenge `beta` into `P'(X) = sum_{i<r} beta^i P_i(X)`. If `P` is evaluated on
+//! the coset `shift * <w>`, then `P'` is evaluated on the coset `shift^r * <w^r>`.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use plonky2_maybe_rayon::*;
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::interpolation::{barycentric_weights, interpolate};
+use crate::field::polynomial::{FlatExtensionPolynomial, PolynomialCoeffs, PolynomialValues};
+use crate::field::types::Field;
+use crate::plonk::plonk_common::reduce_with_powers;
+use crate::util::{log2_strict, reverse_bits, reverse_index_bits_in_place};
+
+/// Folds `P`, given by its coefficients, into `P'(X) = sum_{i<r} beta^i P_i(X)`.
+pub fn fold_polynomial_coeffs<F: Field>(
+    coeffs: &PolynomialCoeffs<F>,
+    beta: F,
+    arity_bits: usize,
+) -> PolynomialCoeffs<F> {
+    PolynomialCoeffs::new(
+        coeffs
+            .coeffs
+            .par_chunks_exact(1 << arity_bits)
+            .map(|chunk| reduce_with_powers(chunk, beta))
+            .collect(),
+    )
+}
+
+/// Folds `P`, given by its values on `shift * <w>` in natural order, into the values of
+/// `P'(X) = sum_{i<r} beta^i P_i(X)` on `shift^r * <w^r>`, also in natural order.
+///
+/// The points `x * g^k`, where `g` is a primitive `r`th root of unity, all map to `x^r`. The
+/// folded value `P'(x^r)` is obtained by interpolating `P` over these points and evaluating the
+/// interpolant at `beta`.
+pub fn fold_polynomial_values<F: Field + Extendable<D>, const D: usize>(
+    values: &PolynomialValues<F::Extension>,
+    beta: F::Extension,
+    arity_bits: usize,
+    shift: F,
+) -> PolynomialValues<F::Extension> {
+    let arity = 1 << arity_bits;
+    let n = values.len();
+    let log_n = log2_strict(n);
+    assert!(
+        arity_bits <= log_n,
+        "Cannot fold {n} values with arity {arity}"
+    );
+    let folded_len = n >> arity_bits;
+
+    let g = F::primitive_root_of_unity(arity_bits);
+    let folded = F::primitive_root_of_unity(log_n)
+        .powers()
+        .take(folded_len)
+        .enumerate()
+        .map(|(j, w_j)| {
+            let x = shift * w_j;
+            // `x * g^k = shift * w^{j + k * folded_len}`.
+            let points = g
+                .powers()
+                .take(arity)
+                .enumerate()
+                .map(|(k, g_k)| ((x * g_k).into(), values.values[j + k * folded_len]))
+                .collect::<Vec<_>>();
+            let barycentric_weights = barycentric_weights(&points);
+            interpolate(&points, beta, &barycentric_weights)
+        })
+        .collect();
+    PolynomialValues::new(folded)
+}
+
+/// Like [`fold_polynomial_values`], but with the values of `P` and `P'` both in bit-reversed
+/// order, as committed by the prover, and stored flat.
+///
+/// Each run of `r` values is then a coset `x * <g>`, with `g` a primitive `r`th root of unity, and
+/// yields the value at `x^r`. Writing `e_k = P(x * g^k)`, the inverse DFT of the run gives
+/// `P_i(x^r) = x^{-i} / r * sum_k g^{-ik} e_k`, so no interpolation, and no inversion in the
+/// extension field, is needed.
+pub fn fold_committed_values<F: Field + Extendable<D>, const D: usize>(
+    values: &FlatExtensionPolynomial<F, D>,
+    beta: F::Extension,
+    arity_bits: usize,
+    shift: F,
+) -> FlatExtensionPolynomial<F, D> {
+    let arity = 1 << arity_bits;
+    let n = values.len();
+    let log_n = log2_strict(n);
+    assert!(
+        arity_bits <= log_n,
+        "Cannot fold {n} values with arity {arity}"
+    );
+    let folded_len = n >> arity_bits;
+
+    // The run at position `j` is the coset of `x_j = shift * w^rev(j)`.
+    let shift_inv = shift.inverse();
+    let mut x_invs: Vec<F> = F::primitive_root_of_unity(log_n)
+        .inverse()
+        .powers()
+        .take(folded_len)
+        .map(|w_inv| shift_inv * w_inv)
+        .collect();
+    reverse_index_bits_in_place(&mut x_invs);
+    let g_invs: Vec<F> = F::primitive_root_of_unity(arity_bits)
+        .inverse()
+        .powers()
+        .take(arity)
+        .collect();
+    let arity_inv = F::inverse_2exp(arity_bits);
+
+    let mut folded = vec![F::ZERO; folded_len * D];
+    folded
+        .par_chunks_exact_mut(D)
+        .zip(values.elements.par_chunks_exact(arity * D))
+        .zip(x_invs.par_iter())
+        .for_each(|((out, run), &x_inv)| {
+            // Within the run, `P(x * g^k)` is at position `rev(k)`.
+            let u = beta.scalar_mul(x_inv);
+            let mut sum = F::Extension::ZERO;
+            for i in (0..arity).rev() {
+                let mut d_i = [F::ZERO; D];
+                for k in 0..arity {
+                    let e_k = &run[reverse_bits(k, arity_bits) * D..][..D];
+                    let g_inv_ik = g_invs[(i * k) & (arity - 1)];
+                    for (d, &e) in d_i.iter_mut().zip(e_k) {
+                        *d += e * g_inv_ik;
+                    }
+                }
+                sum = sum * u + F::Extension::from_basefield_array(d_i);
+            }
+            out.copy_from_slice(&sum.scalar_mul(arity_inv).to_basefield_array());
+        });
+    FlatExtensionPolynomial::new(folded)
+}
+
+/// Computes P'(x^arity) from {P(x*g^i)}_(i=0..arity), where g is a `arity`-th root of unity
+/// and P' is the FRI reduced polynomial.
+///
+/// `evals` is a leaf of a commit-phase Merkle tree, i.e. a coset of the bit-reversed evaluation
+/// domain, and `x` sits at position `x_index_within_coset` in it.
+pub fn compute_evaluation<F: Field + Extendable<D>, const D: usize>(
+    x: F,
+    x_index_within_coset: usize,
+    arity_bits: usize,
+    evals: &[F::Extension],
+    beta: F::Extension,
+) -> F::Extension {
+    let arity = 1 << arity_bits;
+    debug_assert_eq!(evals.len(), arity);
+
+    let g = F::primitive_root_of_unity(arity_bits);
+
+    // The evaluation vector needs to be reordered first.
+    let mut evals = evals.to_vec();
+    reverse_index_bits_in_place(&mut evals);
+    let rev_x_index_within_coset = reverse_bits(x_index_within_coset, arity_bits);
+    let coset_start = x * g.exp_u64((arity - rev_x_index_within_coset) as u64);
+    // The answer is gotten by interpolating {(x*g^i, P(x*g^i))} and evaluating at beta.
+    let points = g
+        .powers()
+        .map(|y| (coset_start * y).into())
+        .zip(evals)
+        .collect::<Vec<_>>();
+    let barycentric_weights = barycentric_weights(&points);
+    interpolate(&points, beta, &barycentric_weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Sample;
+
+    const D: usize = 2;
+    type F = GoldilocksField;
+    type FE = <F as Extendable<D>>::Extension;
+
+    #[test]
+    fn test_fold_consistency() {
+        let log_n = 6;
+        let n = 1 << log_n;
+        let shift = F::MULTIPLICATIVE_GROUP_GENERATOR;
+
+        for arity_bits in 1..=4 {
+            let arity = 1 << arity_bits;
+            let coeffs = PolynomialCoeffs::new(FE::rand_vec(n));
+            let values = coeffs.coset_fft(shift.into());
+            let beta = FE::rand();
+
+            // Folding in coefficient and evaluation form agree.
+            let folded_coeffs = fold_polynomial_coeffs(&coeffs, beta, arity_bits);
+            let folded_values = fold_polynomial_values::<F, D>(&values, beta, arity_bits, shift);
+            let folded_shift = shift.exp_u64(arity as u64);
+            assert_eq!(folded_values, folded_coeffs.coset_fft(folded_shift.into()));
+
+            // Folding the committed, bit-reversed layout agrees as well.
+            let mut flat = FlatExtensionPolynomial::<F, D>::from(values.clone());
+            flat.reverse_index_bits_in_place();
+            let mut folded_flat = fold_committed_values(&flat, beta, arity_bits, shift);
+            folded_flat.reverse_index_bits_in_place();
+            assert_eq!(folded_flat.into_values(), folded_values);
+
+            // The prover commits to the bit-reversed values, `arity` per leaf. The verifier's
+            // evaluation from any position in any leaf matches the folded codeword.
+            let mut committed = values.values.clone();
+            reverse_index_bits_in_place(&mut committed);
+            let mut folded_committed = folded_values.values.clone();
+            reverse_index_bits_in_place(&mut folded_committed);
+            for x_index in 0..n {
+                let coset_index = x_index >> arity_bits;
+                let x_index_within_coset = x_index & (arity - 1);
+                let evals = &committed[coset_index * arity..(coset_index + 1) * arity];
+                let x = shift
+                    * F::primitive_root_of_unity(log_n)
+                        .exp_u64(reverse_bits(x_index, log_n) as u64);
+                assert_eq!(
+                    compute_evaluation::<F, D>(x, x_index_within_coset, arity_bits, evals, beta),
+                    folded_committed[coset_index]
+                );
+            }
+        }
+    }
+}
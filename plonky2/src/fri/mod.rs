@@ -3,20 +3,34 @@
 //! It provides both a native implementation and an in-circuit version
 //! of the FRI verifier for recursive proof composition.
 
+use alloc::vec;
 use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
 
+use itertools::Itertools;
 use serde::Serialize;
 
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+pub use crate::fri::folding::{compute_evaluation, fold_polynomial_coeffs, fold_polynomial_values};
 use crate::fri::reduction_strategies::FriReductionStrategy;
+use crate::hash::hash_types::RichField;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::transcript::{LEGACY_TRANSCRIPT_VERSION, TRANSCRIPT_VERSION};
+use crate::util::log_size::{DegreeBits, LdeBits};
 
 mod challenges;
+pub mod folding;
 pub mod oracle;
 pub mod proof;
 pub mod prover;
+pub mod query_index;
 pub mod recursive_verifier;
 pub mod reduction_strategies;
+pub mod streaming;
 pub mod structure;
-mod validate_shape;
+pub mod validate_shape;
 pub mod verifier;
 pub mod witness_util;
 
@@ -29,28 +43,114 @@ pub struct FriConfig {
     /// Height of Merkle tree caps.
     pub cap_height: usize,
 
-    /// Number of bits used for grinding.
-    pub proof_of_work_bits: u32,
+    /// How the prover grinds, i.e. performs proof-of-work.
+    pub pow_strategy: FriPowStrategy,
 
     /// The reduction strategy to be applied at each layer during the commit phase.
     pub reduction_strategy: FriReductionStrategy,
 
     /// Number of query rounds to perform.
     pub num_query_rounds: usize,
+
+    /// Whether to commit to the final polynomial rather than sending all of its coefficients.
+    ///
+    /// When set, the prover folds the final polynomial once more, committing to its evaluations
+    /// with an extra Merkle cap and sending only the much smaller remainder. The verifier checks
+    /// the final polynomial's evaluations at the query points through Merkle openings, which is
+    /// considerably cheaper in a recursive verifier than evaluating a long final polynomial.
+    pub commit_final_poly: bool,
+}
+
+/// Where, and how hard, the prover grinds during FRI.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub enum FriPowStrategy {
+    /// No proof-of-work.
+    None,
+
+    /// A single grind with the given number of bits after the commit phase, right before the query
+    /// indices are sampled.
+    Single(u32),
+
+    /// A grind before sampling the folding challenge of each commit-phase round, where the `i`th
+    /// entry gives the number of bits for round `i`. Rounds past the end of the vector are not
+    /// ground, and entries past the number of rounds are ignored.
+    PerRound(Vec<u32>),
 }
 
+impl FriPowStrategy {
+    /// The number of bits of the grind right before the query phase, if any.
+    pub const fn final_bits(&self) -> Option<u32> {
+        match self {
+            Self::Single(bits) => Some(*bits),
+            _ => None,
+        }
+    }
+
+    /// The number of bits of the grind in each of the `num_rounds` commit-phase rounds which are
+    /// ground.
+    pub fn round_bits(&self, num_rounds: usize) -> &[u32] {
+        match self {
+            Self::PerRound(bits) => &bits[..bits.len().min(num_rounds)],
+            _ => &[],
+        }
+    }
+
+    /// The bits of security added to the conjectured query-phase security.
+    ///
+    /// Only a grind right before the query indices are sampled counts. Grinding in the commit
+    /// phase makes it costlier to search for favourable folding challenges, but an adversary can
+    /// still grind the query indices for free, so it is conservatively not counted.
+    pub const fn query_security_bits(&self) -> usize {
+        match self {
+            Self::Single(bits) => *bits as usize,
+            _ => 0,
+        }
+    }
+}
+
+/// The largest arity, in bits, of the extra reduction applied when `commit_final_poly` is set.
+pub const COMMITTED_FINAL_POLY_MAX_ARITY_BITS: usize = 4;
+
 impl FriConfig {
     pub fn rate(&self) -> f64 {
         1.0 / ((1 << self.rate_bits) as f64)
     }
 
-    pub fn fri_params(&self, degree_bits: usize, hiding: bool) -> FriParams {
-        let reduction_arity_bits = self.reduction_strategy.reduction_arity_bits(
+    /// The conjectured security of the query phase, in bits, ignoring the size of the field the
+    /// challenges are drawn from; see the ethSTARK paper.
+    pub const fn conjectured_query_security_bits(&self) -> usize {
+        self.conjectured_query_security_bits_for_rounds(self.num_query_rounds)
+    }
+
+    /// Same as `conjectured_query_security_bits`, if only `num_query_rounds` query rounds were
+    /// checked.
+    pub const fn conjectured_query_security_bits_for_rounds(
+        &self,
+        num_query_rounds: usize,
+    ) -> usize {
+        num_query_rounds * self.rate_bits + self.pow_strategy.query_security_bits()
+    }
+
+    pub fn fri_params(&self, degree_bits: DegreeBits, hiding: bool) -> FriParams {
+        let mut reduction_arity_bits = self.reduction_strategy.reduction_arity_bits(
             degree_bits,
             self.rate_bits,
             self.cap_height,
             self.num_query_rounds,
         );
+        if self.commit_final_poly {
+            // Fold the final polynomial as far as allowed, keeping the committed tree at least as
+            // tall as the cap.
+            let final_poly_bits = degree_bits - reduction_arity_bits.iter().sum::<usize>();
+            let final_tree_bits = final_poly_bits + self.rate_bits;
+            let arity_bits = final_poly_bits
+                .get()
+                .min(COMMITTED_FINAL_POLY_MAX_ARITY_BITS)
+                .min(final_tree_bits.get().saturating_sub(self.cap_height));
+            if arity_bits > 0 {
+                reduction_arity_bits.push(arity_bits);
+            }
+        }
         FriParams {
             config: self.clone(),
             hiding,
@@ -75,7 +175,7 @@ pub struct FriParams {
     pub hiding: bool,
 
     /// The degree of the purported codeword, measured in bits.
-    pub degree_bits: usize,
+    pub degree_bits: DegreeBits,
 
     /// The arity of each FRI reduction step, expressed as the log2 of the actual arity.
     /// For example, `[3, 2, 1]` would describe a FRI reduction tree with 8-to-1 reduction, then
@@ -89,23 +189,152 @@ impl FriParams {
         self.reduction_arity_bits.iter().sum()
     }
 
+    /// Whether there are no commit-phase reductions, in which case the final polynomial is the
+    /// original one and query rounds have no steps.
+    pub fn is_trivial(&self) -> bool {
+        self.reduction_arity_bits.is_empty()
+    }
+
     pub(crate) fn max_arity_bits(&self) -> Option<usize> {
         self.reduction_arity_bits.iter().copied().max()
     }
 
-    pub const fn lde_bits(&self) -> usize {
-        self.degree_bits + self.config.rate_bits
+    pub const fn lde_bits(&self) -> LdeBits {
+        self.degree_bits.lde_bits(self.config.rate_bits)
     }
 
     pub const fn lde_size(&self) -> usize {
-        1 << self.lde_bits()
+        self.lde_bits().size()
+    }
+
+    /// The height, in bits, of an initial oracle's Merkle tree when that oracle is committed with
+    /// `oracle_rate_bits` rather than `config.rate_bits`.
+    pub const fn initial_tree_bits(&self, oracle_rate_bits: usize) -> LdeBits {
+        self.degree_bits.lde_bits(oracle_rate_bits)
     }
 
-    pub fn final_poly_bits(&self) -> usize {
+    /// The rate of each of `num_oracles` initial oracles when they all use `config.rate_bits`.
+    pub fn uniform_oracle_rate_bits(&self, num_oracles: usize) -> Vec<usize> {
+        vec![self.config.rate_bits; num_oracles]
+    }
+
+    pub fn final_poly_bits(&self) -> DegreeBits {
         self.degree_bits - self.total_arities()
     }
 
     pub fn final_poly_len(&self) -> usize {
-        1 << self.final_poly_bits()
+        self.final_poly_bits().size()
+    }
+
+    /// The number of bits of the grind in each commit-phase round which is ground.
+    pub fn round_pow_bits(&self) -> &[u32] {
+        self.config
+            .pow_strategy
+            .round_bits(self.reduction_arity_bits.len())
+    }
+
+    /// The total number of field elements the verifier's challenger observes for a proof of a
+    /// circuit with the given common data: the transcript version, the instance, the
+    /// commitments' caps, the openings, and the data of each FRI commit-phase round.
+    pub fn num_transcript_observations<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &self,
+        common: &CommonCircuitData<F, D>,
+    ) -> usize {
+        let hash_len = C::Hasher::hash_no_pad(&[]).to_vec().len();
+        let inner_hash_len = C::InnerHasher::hash_no_pad(&[]).to_vec().len();
+        let cap_len = (1 << self.config.cap_height) * hash_len;
+
+        let version = usize::from(TRANSCRIPT_VERSION != LEGACY_TRANSCRIPT_VERSION);
+        let instance = hash_len + inner_hash_len;
+        // Wires, partial products and quotient, plus the challenge-dependent columns if any.
+        let num_caps = 3 + usize::from(common.has_challenge_dependent_columns());
+        let openings: usize = common
+            .get_fri_instance(F::Extension::ZERO)
+            .batches
+            .iter()
+            .map(|batch| batch.polynomials.len() * D)
+            .sum();
+        // Each grinding round also observes its separator and witness.
+        let commit_phase =
+            self.reduction_arity_bits.len() * cap_len + 2 * self.round_pow_bits().len();
+        let final_poly = self.final_poly_len() * D;
+        let final_pow = usize::from(self.config.pow_strategy.final_bits().is_some());
+
+        version + instance + num_caps * cap_len + openings + commit_phase + final_poly + final_pow
+    }
+}
+
+/// A one-line summary of the parameters, e.g.
+/// `FRI[deg=2^20, rate=2^-3, cap=2^4, reductions=[4,4,4], queries=28, pow=16, ~100 bits]`.
+///
+/// The security shown is the conjectured query-phase security, which does not account for the
+/// size of the challenge field.
+impl Display for FriParams {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let FriConfig {
+            rate_bits,
+            cap_height,
+            ref pow_strategy,
+            num_query_rounds,
+            ..
+        } = self.config;
+        write!(
+            f,
+            "FRI[deg=2^{}, rate=2^-{rate_bits}, cap=2^{cap_height}, reductions=[{}], queries={num_query_rounds}, pow=",
+            self.degree_bits,
+            self.reduction_arity_bits.iter().join(","),
+        )?;
+        match pow_strategy {
+            FriPowStrategy::None => write!(f, "0")?,
+            FriPowStrategy::Single(bits) => write!(f, "{bits}")?,
+            FriPowStrategy::PerRound(_) => {
+                write!(f, "[{}]", self.round_pow_bits().iter().join(","))?
+            }
+        }
+        write!(
+            f,
+            ", ~{} bits]",
+            self.config.conjectured_query_security_bits()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::plonk::circuit_data::CircuitConfig;
+
+    #[test]
+    fn test_fri_params_display() {
+        let config = CircuitConfig::standard_recursion_config().fri_config;
+        let params = FriParams {
+            config: config.clone(),
+            hiding: false,
+            degree_bits: DegreeBits::new(20),
+            reduction_arity_bits: vec![4, 4, 4],
+        };
+        assert_eq!(
+            params.to_string(),
+            "FRI[deg=2^20, rate=2^-3, cap=2^4, reductions=[4,4,4], queries=28, pow=16, ~100 bits]"
+        );
+
+        let params = FriParams {
+            config: FriConfig {
+                pow_strategy: FriPowStrategy::PerRound(vec![8, 4, 2, 1]),
+                ..config
+            },
+            ..params
+        };
+        assert_eq!(
+            params.to_string(),
+            "FRI[deg=2^20, rate=2^-3, cap=2^4, reductions=[4,4,4], queries=28, pow=[8,4,2], ~84 bits]"
+        );
     }
 }
@@ -1,26 +1,31 @@
-use alloc::format;
 use alloc::vec::Vec;
+use alloc::{format, vec};
 
 use itertools::Itertools;
 use plonky2_field::types::Field;
 use plonky2_maybe_rayon::*;
+use zeroize::Zeroize;
 
 use crate::field::extension::Extendable;
 use crate::field::fft::FftRootTable;
 use crate::field::packed::PackedField;
-use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use crate::field::polynomial::{FlatExtensionPolynomial, PolynomialCoeffs, PolynomialValues};
 use crate::fri::proof::FriProof;
-use crate::fri::prover::fri_proof;
+use crate::fri::prover::fri_proof_from_values_with_zeroize;
 use crate::fri::structure::{FriBatchInfo, FriInstanceInfo};
 use crate::fri::FriParams;
 use crate::hash::hash_types::RichField;
+use crate::hash::hashing::expand_seed;
 use crate::hash::merkle_tree::MerkleTree;
 use crate::iop::challenger::Challenger;
-use crate::plonk::config::GenericConfig;
+use crate::plonk::config::{GenericConfig, Hasher};
 use crate::timed;
 use crate::util::reducing::ReducingFactor;
+use crate::util::sensitive::{wipe_vec, SensitiveData};
 use crate::util::timing::TimingTree;
-use crate::util::{log2_strict, reverse_bits, reverse_index_bits_in_place, transpose};
+use crate::util::{
+    log2_strict, reverse_bits, reverse_index_bits_in_place, transpose, transpose_into,
+};
 
 /// Four (~64 bit) field elements gives ~128 bit security.
 pub const SALT_SIZE: usize = 4;
@@ -50,6 +55,85 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> D
     }
 }
 
+/// The allocations of a [`PolynomialBatch`] which can be reused to commit to another batch of the
+/// same shape, see `ProverBuffers`. Committing fully overwrites them.
+#[derive(Clone, Debug)]
+pub(crate) struct BatchBuffers<F: RichField, H: Hasher<F>> {
+    /// The LDE of each polynomial, followed by the salts, i.e. the FFT scratch.
+    ldes: Vec<Vec<F>>,
+    /// The leaves of the Merkle tree, i.e. the rows of `ldes` in bit-reversed order.
+    leaves: Vec<Vec<F>>,
+    /// The digests of the Merkle tree.
+    digests: Vec<H::Hash>,
+}
+
+impl<F: RichField, H: Hasher<F>> Default for BatchBuffers<F, H> {
+    fn default() -> Self {
+        Self {
+            ldes: Vec::new(),
+            leaves: Vec::new(),
+            digests: Vec::new(),
+        }
+    }
+}
+
+impl<F: RichField, H: Hasher<F>> BatchBuffers<F, H> {
+    /// Allocates buffers for committing to `num_columns` columns, salts included, of `lde_size`
+    /// values with a Merkle cap of height `cap_height`.
+    pub(crate) fn new(num_columns: usize, lde_size: usize, cap_height: usize) -> Self {
+        Self {
+            ldes: vec![Vec::with_capacity(lde_size); num_columns],
+            leaves: vec![Vec::with_capacity(num_columns); lde_size],
+            digests: Vec::with_capacity(2 * (lde_size - (1 << cap_height))),
+        }
+    }
+
+    /// Takes back the allocations of the leaves and the digests of `tree`, once it is no longer
+    /// needed.
+    pub(crate) fn recycle(&mut self, tree: &mut MerkleTree<F, H>) {
+        self.leaves = core::mem::take(&mut tree.leaves);
+        self.digests = core::mem::take(&mut tree.digests);
+    }
+
+    /// Overwrites the LDEs and the leaves with zeros, keeping their allocations.
+    pub(crate) fn wipe(&mut self) {
+        for values in self.ldes.iter_mut().chain(&mut self.leaves) {
+            wipe_vec(values, F::ZERO);
+        }
+    }
+
+    /// Every LDE value and leaf held.
+    #[cfg(test)]
+    pub(crate) fn values(&self) -> impl Iterator<Item = &F> {
+        self.ldes.iter().chain(&self.leaves).flatten()
+    }
+}
+
+/// Wipes the polynomials and the leaves of the Merkle tree, i.e. their LDEs and the salts.
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Zeroize
+    for PolynomialBatch<F, C, D>
+{
+    fn zeroize(&mut self) {
+        for poly in &mut self.polynomials {
+            wipe_vec(&mut poly.coeffs, F::ZERO);
+        }
+        self.merkle_tree.zeroize();
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> SensitiveData
+    for PolynomialBatch<F, C, D>
+{
+    type Field = F;
+
+    fn for_each_value(&self, f: &mut dyn FnMut(F)) {
+        for poly in &self.polynomials {
+            poly.coeffs.iter().copied().for_each(&mut *f);
+        }
+        self.merkle_tree.for_each_value(f);
+    }
+}
+
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     PolynomialBatch<F, C, D>
 {
@@ -61,6 +145,63 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         cap_height: usize,
         timing: &mut TimingTree,
         fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        Self::from_values_with_salt_seed(
+            values,
+            rate_bits,
+            blinding,
+            cap_height,
+            timing,
+            fft_root_table,
+            None,
+            0,
+            false,
+        )
+    }
+
+    /// Like `from_values`, but if `salt_seed` is set, the salts are derived from it rather than
+    /// sampled, see `ProverOptions::salt_rng_seed`. Each oracle committed with the same seed must
+    /// have a distinct `oracle_index`. If `zeroize` is set, the intermediate LDEs are wiped once
+    /// the Merkle tree has been built, see `ProverOptions::zeroize_witness`.
+    pub fn from_values_with_salt_seed(
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        salt_seed: Option<u64>,
+        oracle_index: usize,
+        zeroize: bool,
+    ) -> Self {
+        Self::from_values_with_buffers(
+            values,
+            rate_bits,
+            blinding,
+            cap_height,
+            timing,
+            fft_root_table,
+            salt_seed,
+            oracle_index,
+            zeroize,
+            &mut BatchBuffers::default(),
+        )
+    }
+
+    /// Like `from_values_with_salt_seed`, but reuses the allocations of `buffers` for the LDEs,
+    /// the leaves and the digests. The latter two end up in the Merkle tree, see
+    /// `BatchBuffers::recycle`.
+    pub(crate) fn from_values_with_buffers(
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        salt_seed: Option<u64>,
+        oracle_index: usize,
+        zeroize: bool,
+        buffers: &mut BatchBuffers<F, C::Hasher>,
     ) -> Self {
         let coeffs = timed!(
             timing,
@@ -68,13 +209,17 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             values.into_par_iter().map(|v| v.ifft()).collect::<Vec<_>>()
         );
 
-        Self::from_coeffs(
+        Self::from_coeffs_with_buffers(
             coeffs,
             rate_bits,
             blinding,
             cap_height,
             timing,
             fft_root_table,
+            salt_seed,
+            oracle_index,
+            zeroize,
+            buffers,
         )
     }
 
@@ -86,20 +231,96 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         cap_height: usize,
         timing: &mut TimingTree,
         fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        Self::from_coeffs_with_salt_seed(
+            polynomials,
+            rate_bits,
+            blinding,
+            cap_height,
+            timing,
+            fft_root_table,
+            None,
+            0,
+            false,
+        )
+    }
+
+    /// Like `from_coeffs`, but with salts derived from `salt_seed`, see
+    /// `from_values_with_salt_seed`.
+    pub fn from_coeffs_with_salt_seed(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        salt_seed: Option<u64>,
+        oracle_index: usize,
+        zeroize: bool,
+    ) -> Self {
+        Self::from_coeffs_with_buffers(
+            polynomials,
+            rate_bits,
+            blinding,
+            cap_height,
+            timing,
+            fft_root_table,
+            salt_seed,
+            oracle_index,
+            zeroize,
+            &mut BatchBuffers::default(),
+        )
+    }
+
+    /// Like `from_coeffs_with_salt_seed`, but reuses the allocations of `buffers`, see
+    /// `from_values_with_buffers`.
+    pub(crate) fn from_coeffs_with_buffers(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        salt_seed: Option<u64>,
+        oracle_index: usize,
+        zeroize: bool,
+        buffers: &mut BatchBuffers<F, C::Hasher>,
     ) -> Self {
         let degree = polynomials[0].len();
-        let lde_values = timed!(
+        timed!(
             timing,
             "FFT + blinding",
-            Self::lde_values(&polynomials, rate_bits, blinding, fft_root_table)
+            Self::lde_values_into(
+                &polynomials,
+                rate_bits,
+                blinding,
+                fft_root_table,
+                salt_seed,
+                oracle_index,
+                &mut buffers.ldes,
+            )
         );
 
-        let mut leaves = timed!(timing, "transpose LDEs", transpose(&lde_values));
+        let mut leaves = core::mem::take(&mut buffers.leaves);
+        timed!(
+            timing,
+            "transpose LDEs",
+            transpose_into(&buffers.ldes, &mut leaves)
+        );
+        if zeroize {
+            for values in &mut buffers.ldes {
+                wipe_vec(values, F::ZERO);
+            }
+        }
         reverse_index_bits_in_place(&mut leaves);
         let merkle_tree = timed!(
             timing,
             "build Merkle tree",
-            MerkleTree::new(leaves, cap_height)
+            MerkleTree::new_reusing_digests(
+                leaves,
+                core::mem::take(&mut buffers.digests),
+                cap_height
+            )
         );
 
         Self {
@@ -111,31 +332,125 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         }
     }
 
+    /// Replaces the first `values.len()` polynomials of this unblinded batch with the polynomials
+    /// interpolating `values`, reusing the LDEs of the remaining polynomials rather than
+    /// recomputing them. The Merkle tree is rebuilt.
+    pub fn with_leading_values(
+        &self,
+        values: Vec<PolynomialValues<F>>,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        assert!(!self.blinding, "Cannot reuse the LDEs of a blinded batch");
+        let num_replaced = values.len();
+        assert!(num_replaced <= self.polynomials.len());
+        let coeffs = timed!(
+            timing,
+            "IFFT",
+            values.into_par_iter().map(|v| v.ifft()).collect::<Vec<_>>()
+        );
+        let lde_values = timed!(
+            timing,
+            "FFT",
+            Self::lde_values(&coeffs, self.rate_bits, false, fft_root_table, None, 0)
+        );
+
+        let mut rows = timed!(timing, "transpose LDEs", transpose(&lde_values));
+        reverse_index_bits_in_place(&mut rows);
+        let leaves = self
+            .merkle_tree
+            .leaves
+            .par_iter()
+            .zip(rows)
+            .map(|(leaf, row)| [row.as_slice(), &leaf[num_replaced..]].concat())
+            .collect();
+        let merkle_tree = timed!(
+            timing,
+            "build Merkle tree",
+            MerkleTree::new(leaves, cap_height)
+        );
+
+        let mut polynomials = coeffs;
+        polynomials.extend_from_slice(&self.polynomials[num_replaced..]);
+        Self {
+            polynomials,
+            merkle_tree,
+            degree_log: self.degree_log,
+            rate_bits: self.rate_bits,
+            blinding: false,
+        }
+    }
+
     fn lde_values(
         polynomials: &[PolynomialCoeffs<F>],
         rate_bits: usize,
         blinding: bool,
         fft_root_table: Option<&FftRootTable<F>>,
+        salt_seed: Option<u64>,
+        oracle_index: usize,
     ) -> Vec<Vec<F>> {
+        let mut ldes = Vec::new();
+        Self::lde_values_into(
+            polynomials,
+            rate_bits,
+            blinding,
+            fft_root_table,
+            salt_seed,
+            oracle_index,
+            &mut ldes,
+        );
+        ldes
+    }
+
+    /// Like `lde_values`, but writes the LDEs into `ldes`, reusing its allocations. The FFTs are
+    /// done in place.
+    fn lde_values_into(
+        polynomials: &[PolynomialCoeffs<F>],
+        rate_bits: usize,
+        blinding: bool,
+        fft_root_table: Option<&FftRootTable<F>>,
+        salt_seed: Option<u64>,
+        oracle_index: usize,
+        ldes: &mut Vec<Vec<F>>,
+    ) {
         let degree = polynomials[0].len();
 
         // If blinding, salt with two random elements to each leaf vector.
         let salt_size = if blinding { SALT_SIZE } else { 0 };
 
-        polynomials
-            .par_iter()
-            .map(|p| {
+        ldes.resize_with(polynomials.len() + salt_size, Vec::new);
+        let (poly_ldes, salt_ldes) = ldes.split_at_mut(polynomials.len());
+        poly_ldes
+            .par_iter_mut()
+            .zip(polynomials.par_iter())
+            .for_each(|(lde, p)| {
                 assert_eq!(p.len(), degree, "Polynomial degrees inconsistent");
-                p.lde(rate_bits)
-                    .coset_fft_with_options(F::coset_shift(), Some(rate_bits), fft_root_table)
-                    .values
-            })
-            .chain(
-                (0..salt_size)
-                    .into_par_iter()
-                    .map(|_| F::rand_vec(degree << rate_bits)),
-            )
-            .collect()
+                // The evaluations on the coset `shift * H` are those of `p(shift * X)`.
+                lde.clear();
+                lde.extend(
+                    F::coset_shift()
+                        .powers()
+                        .zip(&p.coeffs)
+                        .map(|(r, &c)| r * c),
+                );
+                lde.resize(degree << rate_bits, F::ZERO);
+                *lde = PolynomialCoeffs::new(core::mem::take(lde))
+                    .fft_with_options(Some(rate_bits), fft_root_table)
+                    .values;
+            });
+        salt_ldes.par_iter_mut().enumerate().for_each(|(i, lde)| {
+            *lde = match salt_seed {
+                Some(seed) => {
+                    let domain = [
+                        F::from_canonical_usize(oracle_index),
+                        F::from_canonical_usize(i),
+                    ];
+                    expand_seed(seed, &domain, degree << rate_bits)
+                }
+                None => F::rand_vec(degree << rate_bits),
+            };
+        });
     }
 
     /// Fetches LDE values at the `index * step`th point.
@@ -172,16 +487,15 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             .collect_vec()
     }
 
-    /// Produces a batch opening proof.
-    pub fn prove_openings(
+    /// Combines the polynomials opened by `instance` into the low-degree polynomial which goes into
+    /// FRI. Its evaluations are those which `fri_combine_evals` computes from the evaluations of the
+    /// opened polynomials.
+    pub(crate) fn combine_openings(
         instance: &FriInstanceInfo<F, D>,
         oracles: &[&Self],
-        challenger: &mut Challenger<F, C::Hasher>,
-        fri_params: &FriParams,
+        alpha: F::Extension,
         timing: &mut TimingTree,
-    ) -> FriProof<F, C::Hasher, D> {
-        assert!(D > 1, "Not implemented for D=1.");
-        let alpha = challenger.get_extension_challenge::<D>();
+    ) -> PolynomialCoeffs<F::Extension> {
         let mut alpha = ReducingFactor::new(alpha);
 
         // Final low-degree polynomial that goes into FRI.
@@ -210,22 +524,53 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             final_poly += quotient;
         }
 
-        let lde_final_poly = final_poly.lde(fri_params.config.rate_bits);
-        let lde_final_values = timed!(
+        final_poly
+    }
+
+    /// Produces a batch opening proof.
+    pub fn prove_openings(
+        instance: &FriInstanceInfo<F, D>,
+        oracles: &[&Self],
+        challenger: &mut Challenger<F, C::Hasher>,
+        fri_params: &FriParams,
+        timing: &mut TimingTree,
+    ) -> FriProof<F, C::Hasher, D> {
+        Self::prove_openings_with_zeroize(instance, oracles, challenger, fri_params, false, timing)
+    }
+
+    /// Like `prove_openings`, but if `zeroize` is set, the FRI codewords derived from the oracles
+    /// are wiped once folded, see `fri_proof_from_values_with_zeroize`.
+    pub(crate) fn prove_openings_with_zeroize(
+        instance: &FriInstanceInfo<F, D>,
+        oracles: &[&Self],
+        challenger: &mut Challenger<F, C::Hasher>,
+        fri_params: &FriParams,
+        zeroize: bool,
+        timing: &mut TimingTree,
+    ) -> FriProof<F, C::Hasher, D> {
+        assert!(D > 1, "Not implemented for D=1.");
+        challenger.label(b"fri_alpha");
+        let alpha = challenger.get_extension_challenge::<D>();
+        let final_poly = Self::combine_openings(instance, oracles, alpha, timing);
+
+        // The FFT is done in place, so that the coefficients and the values aren't both kept.
+        let mut lde_final_values =
+            FlatExtensionPolynomial::from(final_poly).lde(fri_params.config.rate_bits);
+        timed!(
             timing,
-            &format!("perform final FFT {}", lde_final_poly.len()),
-            lde_final_poly.coset_fft(F::coset_shift().into())
+            &format!("perform final FFT {}", lde_final_values.len()),
+            lde_final_values.coset_fft_in_place(F::coset_shift())
         );
 
-        let fri_proof = fri_proof::<F, C, D>(
+        let fri_proof = fri_proof_from_values_with_zeroize::<F, C, D>(
             &oracles
                 .par_iter()
                 .map(|c| &c.merkle_tree)
                 .collect::<Vec<_>>(),
-            lde_final_poly,
             lde_final_values,
             challenger,
             fri_params,
+            zeroize,
             timing,
         );
 
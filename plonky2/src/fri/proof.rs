@@ -1,23 +1,32 @@
+use alloc::collections::BTreeMap;
 use alloc::vec;
 use alloc::vec::Vec;
 
-use hashbrown::HashMap;
+use anyhow::ensure;
+use hashbrown::{HashMap, HashSet};
 use itertools::izip;
 use serde::{Deserialize, Serialize};
 
-use crate::field::extension::{flatten, unflatten, Extendable};
+use crate::field::extension::{flatten, unflatten, Extendable, FieldExtension};
 use crate::field::polynomial::PolynomialCoeffs;
+use crate::field::types::Field;
+use crate::fri::structure::{FriInstanceInfo, FriOracleInfo};
+use crate::fri::verifier::{fri_pow_response_bits, FriError};
 use crate::fri::FriParams;
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
-use crate::hash::hash_types::{MerkleCapTarget, RichField};
+use crate::hash::hash_types::{HashOut, MerkleCapTarget, RichField, NUM_HASH_OUT_ELTS};
 use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
 use crate::hash::merkle_tree::MerkleCap;
-use crate::hash::path_compression::{compress_merkle_proofs, decompress_merkle_proofs};
+use crate::hash::path_compression::{
+    compress_merkle_proofs, compressed_merkle_proofs_num_siblings, decompress_merkle_proofs,
+};
+use crate::iop::challenger::Challenger;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
-use crate::plonk::config::Hasher;
+use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
 use crate::plonk::plonk_common::salt_size;
 use crate::plonk::proof::{FriInferredElements, ProofChallenges};
+use crate::util::serialization::{Buffer, IoError, IoResult, Read, Remaining, Write};
 
 /// Evaluations and Merkle proof produced by the prover in a FRI query step.
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -50,6 +59,31 @@ impl<F: RichField, H: Hasher<F>> FriInitialTreeProof<F, H> {
         let evals = &self.evals_proofs[oracle_index].0;
         &evals[..evals.len() - salt_size(salted)]
     }
+
+    /// Checks that the opening of each oracle of `oracles` holds its `num_polys` evaluations
+    /// followed by `salt_size(oracle.blinding && hiding)` salt elements, so that stripping the salt
+    /// leaves exactly the oracle's polynomials.
+    pub fn validate_salt(&self, oracles: &[FriOracleInfo], hiding: bool) -> Result<(), FriError> {
+        if self.evals_proofs.len() != oracles.len() {
+            return Err(FriError::InitialOracleCountMismatch {
+                expected_len: oracles.len(),
+                len: self.evals_proofs.len(),
+            });
+        }
+        for (oracle_index, ((evals, _), oracle)) in
+            self.evals_proofs.iter().zip(oracles).enumerate()
+        {
+            let expected_len = oracle.num_polys + salt_size(oracle.blinding && hiding);
+            if evals.len() != expected_len {
+                return Err(FriError::SaltMismatch {
+                    oracle_index,
+                    expected_len,
+                    len: evals.len(),
+                });
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -88,15 +122,19 @@ pub struct FriQueryRoundTarget<const D: usize> {
 }
 
 /// Compressed proof of the FRI query rounds.
+///
+/// The openings are kept in `BTreeMap`s, so serialization lists them by increasing leaf index and
+/// two compressions of the same proof serialize to the same bytes. Maps are serialized as maps
+/// whatever their order, so data written when these were `HashMap`s still deserializes.
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[serde(bound = "")]
 pub struct CompressedFriQueryRounds<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> {
     /// Query indices.
     pub indices: Vec<usize>,
     /// Map from initial indices `i` to the `FriInitialProof` for the `i`th leaf.
-    pub initial_trees_proofs: HashMap<usize, FriInitialTreeProof<F, H>>,
+    pub initial_trees_proofs: BTreeMap<usize, FriInitialTreeProof<F, H>>,
     /// For each FRI query step, a map from indices `i` to the `FriQueryStep` for the `i`th leaf.
-    pub steps: Vec<HashMap<usize, FriQueryStep<F, H, D>>>,
+    pub steps: Vec<BTreeMap<usize, FriQueryStep<F, H, D>>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -108,8 +146,13 @@ pub struct FriProof<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>
     pub query_round_proofs: Vec<FriQueryRound<F, H, D>>,
     /// The final polynomial in coefficient form.
     pub final_poly: PolynomialCoeffs<F::Extension>,
-    /// Witness showing that the prover did PoW.
+    /// Witness showing that the prover did PoW. Only used with `FriPowStrategy::Single`, and zero
+    /// otherwise.
     pub pow_witness: F,
+    /// Witnesses showing that the prover did PoW in each commit-phase round, with
+    /// `FriPowStrategy::PerRound`.
+    #[serde(default)]
+    pub round_pow_witnesses: Vec<F>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -118,6 +161,39 @@ pub struct FriProofTarget<const D: usize> {
     pub query_round_proofs: Vec<FriQueryRoundTarget<D>>,
     pub final_poly: PolynomialCoeffsExtTarget<D>,
     pub pow_witness: Target,
+    pub round_pow_witnesses: Vec<Target>,
+}
+
+impl<const D: usize> FriProofTarget<D> {
+    /// The targets of every element of the proof, in the order of `FriProof::to_field_elements`.
+    pub fn to_targets(&self) -> Vec<Target> {
+        let FriProofTarget {
+            commit_phase_merkle_caps,
+            query_round_proofs,
+            final_poly,
+            pow_witness,
+            round_pow_witnesses,
+        } = self;
+
+        let mut targets = Vec::new();
+        for cap in commit_phase_merkle_caps {
+            targets.extend(cap.0.iter().flat_map(|h| h.elements));
+        }
+        for query_round in query_round_proofs {
+            for (leaf, merkle_proof) in &query_round.initial_trees_proof.evals_proofs {
+                targets.extend(leaf);
+                targets.extend(merkle_proof.siblings.iter().flat_map(|h| h.elements));
+            }
+            for step in &query_round.steps {
+                targets.extend(step.evals.iter().flat_map(|e| e.0));
+                targets.extend(step.merkle_proof.siblings.iter().flat_map(|h| h.elements));
+            }
+        }
+        targets.extend(final_poly.0.iter().flat_map(|e| e.0));
+        targets.push(*pow_witness);
+        targets.extend(round_pow_witnesses);
+        targets
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -131,9 +207,424 @@ pub struct CompressedFriProof<F: RichField + Extendable<D>, H: Hasher<F>, const
     pub final_poly: PolynomialCoeffs<F::Extension>,
     /// Witness showing that the prover did PoW.
     pub pow_witness: F,
+    /// Witnesses showing that the prover did PoW in each commit-phase round.
+    #[serde(default)]
+    pub round_pow_witnesses: Vec<F>,
 }
 
 impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H, D> {
+    /// The number of commit-phase reductions, inferred from the number of commit-phase Merkle caps.
+    pub fn inferred_num_reductions(&self) -> usize {
+        self.commit_phase_merkle_caps.len()
+    }
+
+    /// The commit-phase Merkle cap of the given reduction, or an error if the proof has none.
+    pub fn cap(&self, reduction: usize) -> Result<&MerkleCap<F, H>, FriError> {
+        self.commit_phase_merkle_caps
+            .get(reduction)
+            .ok_or(FriError::MissingCap(reduction))
+    }
+
+    /// Estimates the Shannon entropy per bit of the salts in the initial trees' openings, from `0`
+    /// for constant salts to `1` for uniformly random ones. This is a quick statistical check that
+    /// a hiding prover does not reuse salts; it is not a proof of randomness.
+    ///
+    /// The oracles are laid out as in Plonky2 proofs: all but the constants and sigmas oracle are
+    /// salted if `hiding`, with `salt_size(hiding)` elements at the end of each opening. The
+    /// estimate averages, over the bits of a field element, the binary entropy of the frequency of
+    /// ones at that bit. It is `0` if there are no salts.
+    #[cfg(feature = "std")]
+    pub fn salt_entropy_estimate(&self, hiding: bool) -> f64 {
+        use crate::plonk::plonk_common::PlonkOracle;
+
+        let salt_len = salt_size(hiding);
+        let mut ones = vec![0usize; F::BITS];
+        let mut num_salts = 0;
+        for round in &self.query_round_proofs {
+            let salted_openings = round
+                .initial_trees_proof
+                .evals_proofs
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i != PlonkOracle::CONSTANTS_SIGMAS.index)
+                .map(|(_, (evals, _))| evals);
+            for evals in salted_openings {
+                for salt in &evals[evals.len().saturating_sub(salt_len)..] {
+                    let salt = salt.to_canonical_u64();
+                    for (bit, count) in ones.iter_mut().enumerate() {
+                        *count += (salt >> bit) as usize & 1;
+                    }
+                    num_salts += 1;
+                }
+            }
+        }
+        if num_salts == 0 {
+            return 0.0;
+        }
+
+        let binary_entropy = |p: f64| {
+            if p == 0.0 || p == 1.0 {
+                0.0
+            } else {
+                -p * p.log2() - (1.0 - p) * (1.0 - p).log2()
+            }
+        };
+        let total: f64 = ones
+            .iter()
+            .map(|&count| binary_entropy(count as f64 / num_salts as f64))
+            .sum();
+        total / F::BITS as f64
+    }
+
+    /// The number of grinding bits achieved by this proof, i.e. the number of leading zero bits of
+    /// its PoW response. `challenges` must be the challenges derived from this proof, whose PoW
+    /// response is computed from `pow_witness`.
+    ///
+    /// This is meant for telemetry and does not check the proof of work.
+    pub fn pow_bits(&self, challenges: &FriChallenges<F, D>) -> u32 {
+        fri_pow_response_bits(challenges.fri_pow_response)
+    }
+
+    /// The number of query steps in each query round. For a well-formed proof, all entries are
+    /// equal to `inferred_num_reductions()`.
+    pub fn inferred_step_counts(&self) -> Vec<usize> {
+        self.query_round_proofs
+            .iter()
+            .map(|qrp| qrp.steps.len())
+            .collect()
+    }
+
+    /// Like `inferred_step_counts`, but returns the common per-round step count, or an error if
+    /// the query rounds disagree with each other or with the number of commit-phase caps.
+    pub fn try_inferred_step_count(&self) -> anyhow::Result<usize> {
+        let num_reductions = self.inferred_num_reductions();
+        for (i, num_steps) in self.inferred_step_counts().into_iter().enumerate() {
+            ensure!(
+                num_steps == num_reductions,
+                "Query round {} has {} steps, but the proof has {} commit-phase caps",
+                i,
+                num_steps,
+                num_reductions
+            );
+        }
+        Ok(num_reductions)
+    }
+
+    /// The number of bytes each query round adds to the serialized query rounds of the compressed
+    /// proof, given the query `indices` this proof was generated for.
+    ///
+    /// A round is charged for the encoding of its index and for the openings, with their compressed
+    /// Merkle paths, of the leaves no earlier round opened. Indices are encoded as in
+    /// `Write::write_query_indices`: the first round also pays for the lengths, and each distinct
+    /// index for its delta. The sizes add up to the size of the serialized
+    /// `CompressedFriQueryRounds`.
+    pub fn query_round_sizes<C: GenericConfig<D, F = F, Hasher = H>>(
+        &self,
+        indices: &[usize],
+        params: &FriParams,
+    ) -> Vec<usize> {
+        let CompressedFriQueryRounds {
+            initial_trees_proofs,
+            steps,
+            ..
+        } = self.clone().compress(indices, params).query_round_proofs;
+
+        let mut distinct = indices.to_vec();
+        distinct.sort_unstable();
+        distinct.dedup();
+
+        let mut seen_initial = HashSet::new();
+        let mut seen_steps = vec![HashSet::new(); steps.len()];
+        indices
+            .iter()
+            .enumerate()
+            .map(|(round, &index)| {
+                let mut buffer = Vec::new();
+                if round == 0 {
+                    buffer
+                        .write_varint(indices.len() as u64)
+                        .and_then(|_| buffer.write_varint(distinct.len() as u64))
+                        .expect("Writing to a byte-vector cannot fail.");
+                }
+                let position = distinct
+                    .binary_search(&index)
+                    .expect("Index is among the distinct indices");
+                let delta = index - position.checked_sub(1).map_or(0, |p| distinct[p]);
+                if !seen_initial.contains(&index) {
+                    buffer
+                        .write_varint(delta as u64)
+                        .expect("Writing to a byte-vector cannot fail.");
+                }
+                buffer
+                    .write_varint(position as u64)
+                    .expect("Writing to a byte-vector cannot fail.");
+                if seen_initial.insert(index) {
+                    buffer
+                        .write_fri_initial_proof::<F, C, D>(&initial_trees_proofs[&index])
+                        .expect("Writing to a byte-vector cannot fail.");
+                }
+                let mut index = index;
+                for (j, arity_bits) in params.reduction_arity_bits.iter().enumerate() {
+                    index >>= arity_bits;
+                    if seen_steps[j].insert(index) {
+                        buffer
+                            .write_fri_query_step::<F, C, D>(&steps[j][&index])
+                            .expect("Writing to a byte-vector cannot fail.");
+                    }
+                }
+                buffer.len()
+            })
+            .collect()
+    }
+
+    /// Every field element of the proof, in serialization order: the commit-phase caps, then for
+    /// each query round the initial trees' leaves and Merkle paths followed by each step's
+    /// evaluations and Merkle path, then the final polynomial and the PoW witnesses. Extension
+    /// elements are flattened to their `D` base field coordinates.
+    ///
+    /// This is the serialized proof without the Merkle path lengths, which `from_field_elements`
+    /// recovers from the FRI parameters.
+    pub fn to_field_elements(&self) -> Vec<F> {
+        let FriProof {
+            commit_phase_merkle_caps,
+            query_round_proofs,
+            final_poly,
+            pow_witness,
+            round_pow_witnesses,
+        } = self;
+
+        let mut elements = Vec::new();
+        for cap in commit_phase_merkle_caps {
+            elements.extend(cap.flatten());
+        }
+        for query_round in query_round_proofs {
+            for (leaf, merkle_proof) in &query_round.initial_trees_proof.evals_proofs {
+                elements.extend(leaf);
+                elements.extend(merkle_proof.siblings.iter().flat_map(|h| h.to_vec()));
+            }
+            for step in &query_round.steps {
+                elements.extend(flatten::<F, D>(&step.evals));
+                elements.extend(step.merkle_proof.siblings.iter().flat_map(|h| h.to_vec()));
+            }
+        }
+        elements.extend(flatten::<F, D>(&final_poly.coeffs));
+        elements.push(*pow_witness);
+        elements.extend(round_pow_witnesses);
+        elements
+    }
+
+    /// A hash of every element of the proof, in serialization order. Proofs of different shapes
+    /// are not domain-separated, so fingerprints should only be compared between proofs for the
+    /// same `FriParams`.
+    pub fn fingerprint(&self) -> H::Hash {
+        H::hash_no_pad(&self.to_field_elements())
+    }
+
+    /// Applies `f` to every field element of the proof, i.e. the evaluations, the pow witnesses,
+    /// each coordinate of the extension field elements, and the elements of every Merkle hash, see
+    /// `GenericHashOut::map_field`. For instance, mapping with
+    /// `|x| F::from_canonical_u64(x.to_canonical_u64())` canonicalizes the whole proof.
+    pub fn map_field<G: Fn(F) -> F>(self, f: G) -> Self {
+        let FriProof {
+            commit_phase_merkle_caps,
+            query_round_proofs,
+            final_poly,
+            pow_witness,
+            round_pow_witnesses,
+        } = self;
+        let map_ext =
+            |x: F::Extension| F::Extension::from_basefield_array(x.to_basefield_array().map(&f));
+        let map_hashes = |hashes: &[H::Hash]| -> Vec<H::Hash> {
+            hashes.iter().map(|h| h.map_field(&f)).collect()
+        };
+
+        FriProof {
+            commit_phase_merkle_caps: commit_phase_merkle_caps
+                .into_iter()
+                .map(|cap| MerkleCap(map_hashes(&cap.0)))
+                .collect(),
+            query_round_proofs: query_round_proofs
+                .into_iter()
+                .map(
+                    |FriQueryRound {
+                         initial_trees_proof,
+                         steps,
+                     }| FriQueryRound {
+                        initial_trees_proof: FriInitialTreeProof {
+                            evals_proofs: initial_trees_proof
+                                .evals_proofs
+                                .into_iter()
+                                .map(|(leaf, merkle_proof)| {
+                                    (
+                                        leaf.into_iter().map(&f).collect(),
+                                        MerkleProof {
+                                            siblings: map_hashes(&merkle_proof.siblings).into(),
+                                        },
+                                    )
+                                })
+                                .collect(),
+                        },
+                        steps: steps
+                            .into_iter()
+                            .map(
+                                |FriQueryStep {
+                                     evals,
+                                     merkle_proof,
+                                 }| FriQueryStep {
+                                    evals: evals.into_iter().map(map_ext).collect(),
+                                    merkle_proof: MerkleProof {
+                                        siblings: map_hashes(&merkle_proof.siblings).into(),
+                                    },
+                                },
+                            )
+                            .collect(),
+                    },
+                )
+                .collect(),
+            final_poly: PolynomialCoeffs::new(final_poly.coeffs.into_iter().map(map_ext).collect()),
+            pow_witness: f(pow_witness),
+            round_pow_witnesses: round_pow_witnesses.into_iter().map(&f).collect(),
+        }
+    }
+
+    /// Pads the proof to the shape of a proof for `params`, of a degree at least that of the proof
+    /// and with the same reduction arities, as `CircuitBuilder::verify_proof_variable_degree`
+    /// expects. Merkle proofs get dummy siblings above their actual trees, and the final polynomial
+    /// gets zero coefficients. The padded proof is not a valid proof for `params`.
+    pub fn padded_to(&self, params: &FriParams) -> Self {
+        assert_eq!(
+            self.commit_phase_merkle_caps.len(),
+            params.reduction_arity_bits.len(),
+            "The proof has a different number of reductions"
+        );
+        assert!(
+            self.final_poly.len() <= params.final_poly_len(),
+            "The proof is larger than the padded shape"
+        );
+        let cap_height = params.config.cap_height;
+        let dummy_sibling = H::hash_no_pad(&[]);
+        let pad = |proof: &MerkleProof<F, H>, len: usize| {
+            let mut siblings = proof.siblings.to_vec();
+            siblings.resize(len, dummy_sibling);
+            MerkleProof {
+                siblings: siblings.into(),
+            }
+        };
+
+        let initial_proof_len = params.lde_bits().merkle_proof_len(cap_height);
+        let query_round_proofs = self
+            .query_round_proofs
+            .iter()
+            .map(|round| {
+                let evals_proofs = round
+                    .initial_trees_proof
+                    .evals_proofs
+                    .iter()
+                    .map(|(evals, proof)| (evals.clone(), pad(proof, initial_proof_len)))
+                    .collect();
+                let mut proof_len = initial_proof_len;
+                let steps = round
+                    .steps
+                    .iter()
+                    .zip(&params.reduction_arity_bits)
+                    .map(|(step, &arity_bits)| {
+                        proof_len -= arity_bits;
+                        FriQueryStep {
+                            evals: step.evals.clone(),
+                            merkle_proof: pad(&step.merkle_proof, proof_len),
+                        }
+                    })
+                    .collect();
+                FriQueryRound {
+                    initial_trees_proof: FriInitialTreeProof { evals_proofs },
+                    steps,
+                }
+            })
+            .collect();
+
+        let mut final_poly = self.final_poly.clone();
+        final_poly
+            .coeffs
+            .resize(params.final_poly_len(), F::Extension::ZERO);
+
+        FriProof {
+            commit_phase_merkle_caps: self.commit_phase_merkle_caps.clone(),
+            query_round_proofs,
+            final_poly,
+            pow_witness: self.pow_witness,
+            round_pow_witnesses: self.round_pow_witnesses.clone(),
+        }
+    }
+
+    /// The size in bytes of the serialized `CompressedFriProof` that `compress` would return, as
+    /// written by `Write::write_compressed_fri_proof`. Only which openings and Merkle path siblings
+    /// survive the deduplication is tracked, so the compressed proof is never built.
+    pub fn compressed_size_bytes(&self, indices: &[usize], params: &FriParams) -> usize {
+        const FIELD_SIZE: usize = core::mem::size_of::<u64>();
+        // A Merkle proof is prefixed by its length, in a byte.
+        const MERKLE_PROOF_LEN_SIZE: usize = 1;
+        let cap_height = params.config.cap_height;
+
+        let mut size = self
+            .commit_phase_merkle_caps
+            .iter()
+            .map(|cap| cap.len() * H::HASH_SIZE)
+            .sum::<usize>();
+        let mut buffer = Vec::new();
+        buffer
+            .write_query_indices(indices)
+            .expect("Writing to a byte-vector cannot fail.");
+        size += buffer.len();
+
+        // Each distinct opening is written once, with the siblings its path doesn't share with
+        // the openings before it.
+        if let Some(first_round) = self.query_round_proofs.first() {
+            for (i, (_, proof)) in first_round
+                .initial_trees_proof
+                .evals_proofs
+                .iter()
+                .enumerate()
+            {
+                let mut seen = HashSet::new();
+                for (&index, round) in indices.iter().zip(&self.query_round_proofs) {
+                    if seen.insert(index) {
+                        let leaf = &round.initial_trees_proof.evals_proofs[i].0;
+                        size += leaf.len() * FIELD_SIZE + MERKLE_PROOF_LEN_SIZE;
+                    }
+                }
+                size += compressed_merkle_proofs_num_siblings(
+                    cap_height,
+                    proof.siblings.len(),
+                    indices,
+                ) * H::HASH_SIZE;
+            }
+
+            let mut step_indices = indices.to_vec();
+            for (j, &arity_bits) in params.reduction_arity_bits.iter().enumerate() {
+                step_indices
+                    .iter_mut()
+                    .for_each(|index| *index >>= arity_bits);
+                let mut seen = HashSet::new();
+                for (&index, round) in step_indices.iter().zip(&self.query_round_proofs) {
+                    if seen.insert(index) {
+                        // The evaluation at the queried point is inferred, and so not written.
+                        let num_evals = round.steps[j].evals.len() - 1;
+                        size += num_evals * D * FIELD_SIZE + MERKLE_PROOF_LEN_SIZE;
+                    }
+                }
+                size += compressed_merkle_proofs_num_siblings(
+                    cap_height,
+                    first_round.steps[j].merkle_proof.siblings.len(),
+                    &step_indices,
+                ) * H::HASH_SIZE;
+            }
+        }
+
+        size += self.final_poly.len() * D * FIELD_SIZE;
+        size += (1 + self.round_pow_witnesses.len()) * FIELD_SIZE;
+        size
+    }
+
     /// Compress all the Merkle paths in the FRI proof and remove duplicate indices.
     pub fn compress(self, indices: &[usize], params: &FriParams) -> CompressedFriProof<F, H, D> {
         let FriProof {
@@ -141,12 +632,15 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H,
             query_round_proofs,
             final_poly,
             pow_witness,
-            ..
+            round_pow_witnesses,
         } = self;
         let cap_height = params.config.cap_height;
         let reduction_arity_bits = &params.reduction_arity_bits;
         let num_reductions = reduction_arity_bits.len();
-        let num_initial_trees = query_round_proofs[0].initial_trees_proof.evals_proofs.len();
+        // There are no initial trees to compress if there are no query rounds.
+        let num_initial_trees = query_round_proofs
+            .first()
+            .map_or(0, |qrp| qrp.initial_trees_proof.evals_proofs.len());
 
         // "Transpose" the query round proofs, so that information for each Merkle tree is collected together.
         let mut initial_trees_indices = vec![vec![]; num_initial_trees];
@@ -156,11 +650,11 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H,
         let mut steps_evals = vec![vec![]; num_reductions];
         let mut steps_proofs = vec![vec![]; num_reductions];
 
-        for (mut index, qrp) in indices.iter().cloned().zip(&query_round_proofs) {
+        for (mut index, qrp) in indices.iter().cloned().zip(query_round_proofs) {
             let FriQueryRound {
                 initial_trees_proof,
                 steps,
-            } = qrp.clone();
+            } = qrp;
             for (i, (leaves_data, proof)) in
                 initial_trees_proof.evals_proofs.into_iter().enumerate()
             {
@@ -194,20 +688,32 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H,
 
         let mut compressed_query_proofs = CompressedFriQueryRounds {
             indices: indices.to_vec(),
-            initial_trees_proofs: HashMap::with_capacity(indices.len()),
-            steps: vec![HashMap::new(); num_reductions],
+            initial_trees_proofs: BTreeMap::new(),
+            steps: vec![BTreeMap::new(); num_reductions],
         };
 
-        // Replace the query round proofs with the compressed versions.
-        for (i, mut index) in indices.iter().copied().enumerate() {
+        // Replace the query round proofs with the compressed versions, moving each opening out of
+        // the per-tree vectors in query order.
+        let mut initial_trees_leaves = initial_trees_leaves
+            .into_iter()
+            .map(Vec::into_iter)
+            .collect::<Vec<_>>();
+        let mut initial_trees_proofs = initial_trees_proofs
+            .into_iter()
+            .map(Vec::into_iter)
+            .collect::<Vec<_>>();
+        let mut steps_evals = steps_evals
+            .into_iter()
+            .map(Vec::into_iter)
+            .collect::<Vec<_>>();
+        let mut steps_proofs = steps_proofs
+            .into_iter()
+            .map(Vec::into_iter)
+            .collect::<Vec<_>>();
+        for mut index in indices.iter().copied() {
             let initial_proof = FriInitialTreeProof {
-                evals_proofs: (0..num_initial_trees)
-                    .map(|j| {
-                        (
-                            initial_trees_leaves[j][i].clone(),
-                            initial_trees_proofs[j][i].clone(),
-                        )
-                    })
+                evals_proofs: izip!(&mut initial_trees_leaves, &mut initial_trees_proofs)
+                    .map(|(leaves, proofs)| (leaves.next().unwrap(), proofs.next().unwrap()))
                     .collect(),
             };
             compressed_query_proofs
@@ -217,8 +723,8 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H,
             for j in 0..num_reductions {
                 index >>= reduction_arity_bits[j];
                 let query_step = FriQueryStep {
-                    evals: steps_evals[j][i].clone(),
-                    merkle_proof: steps_proofs[j][i].clone(),
+                    evals: steps_evals[j].next().unwrap(),
+                    merkle_proof: steps_proofs[j].next().unwrap(),
                 };
                 compressed_query_proofs.steps[j]
                     .entry(index)
@@ -231,11 +737,186 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H,
             query_round_proofs: compressed_query_proofs,
             final_poly,
             pow_witness,
+            round_pow_witnesses,
         }
     }
 }
 
+impl<F: RichField + Extendable<D>, H: Hasher<F, Hash = HashOut<F>>, const D: usize>
+    FriProof<F, H, D>
+{
+    /// Reads back a proof flattened by `to_field_elements`, for the given `instance` whose `i`th
+    /// oracle was committed with rate `2^{-oracle_rate_bits[i]}`. The elements must be exactly
+    /// those of a proof of that shape.
+    pub fn from_field_elements(
+        elements: &[F],
+        instance: &FriInstanceInfo<F, D>,
+        oracle_rate_bits: &[usize],
+        params: &FriParams,
+    ) -> anyhow::Result<Self> {
+        ensure!(oracle_rate_bits.len() == instance.oracles.len());
+        let cap_height = params.config.cap_height;
+
+        let mut elements = elements.iter().copied();
+        let mut take = |n: usize| -> anyhow::Result<Vec<F>> {
+            let taken = elements.by_ref().take(n).collect::<Vec<_>>();
+            ensure!(taken.len() == n, "Too few field elements for the FRI proof");
+            Ok(taken)
+        };
+        let to_hashes = |hash_elements: Vec<F>| -> Vec<HashOut<F>> {
+            hash_elements
+                .chunks_exact(NUM_HASH_OUT_ELTS)
+                .map(HashOut::from_partial)
+                .collect()
+        };
+
+        let mut commit_phase_merkle_caps = Vec::with_capacity(params.reduction_arity_bits.len());
+        for _ in &params.reduction_arity_bits {
+            let cap = take((1 << cap_height) * NUM_HASH_OUT_ELTS)?;
+            commit_phase_merkle_caps.push(MerkleCap(to_hashes(cap)));
+        }
+
+        let mut query_round_proofs = Vec::with_capacity(params.config.num_query_rounds);
+        for _ in 0..params.config.num_query_rounds {
+            let mut evals_proofs = Vec::with_capacity(instance.oracles.len());
+            for (oracle, &rate_bits) in instance.oracles.iter().zip(oracle_rate_bits) {
+                let leaf = take(oracle.num_polys + salt_size(oracle.blinding && params.hiding))?;
+                let proof_len = params
+                    .initial_tree_bits(rate_bits)
+                    .merkle_proof_len(cap_height);
+                let siblings = to_hashes(take(proof_len * NUM_HASH_OUT_ELTS)?);
+                evals_proofs.push((
+                    leaf,
+                    MerkleProof {
+                        siblings: siblings.into(),
+                    },
+                ));
+            }
+
+            let mut codeword_len_bits = params.lde_bits();
+            let mut steps = Vec::with_capacity(params.reduction_arity_bits.len());
+            for &arity_bits in &params.reduction_arity_bits {
+                codeword_len_bits -= arity_bits;
+                let evals = unflatten::<F, D>(&take((1 << arity_bits) * D)?);
+                let proof_len = codeword_len_bits.merkle_proof_len(cap_height);
+                let siblings = to_hashes(take(proof_len * NUM_HASH_OUT_ELTS)?);
+                steps.push(FriQueryStep {
+                    evals,
+                    merkle_proof: MerkleProof {
+                        siblings: siblings.into(),
+                    },
+                });
+            }
+
+            query_round_proofs.push(FriQueryRound {
+                initial_trees_proof: FriInitialTreeProof { evals_proofs },
+                steps,
+            });
+        }
+
+        let final_poly =
+            PolynomialCoeffs::new(unflatten::<F, D>(&take(params.final_poly_len() * D)?));
+        let pow_witness = take(1)?[0];
+        let round_pow_witnesses = take(params.round_pow_bits().len())?;
+        ensure!(
+            elements.next().is_none(),
+            "Too many field elements for the FRI proof"
+        );
+
+        Ok(FriProof {
+            commit_phase_merkle_caps,
+            query_round_proofs,
+            final_poly,
+            pow_witness,
+            round_pow_witnesses,
+        })
+    }
+}
+
+/// Derives a single challenge binding a sequence of proofs and their public inputs, e.g. for a
+/// final aggregation step. Each proof's fingerprint and public inputs are observed in order, so the
+/// challenge depends on the order of the proofs.
+pub fn aggregate_challenge<
+    F: RichField + Extendable<D>,
+    H: Hasher<F>,
+    C: GenericConfig<D, F = F, Hasher = H>,
+    const D: usize,
+>(
+    proofs: &[(FriProof<F, H, D>, Vec<F>)],
+) -> HashOut<F> {
+    let mut challenger = Challenger::<F, C::Hasher>::new();
+    challenger.observe_element(F::from_canonical_usize(proofs.len()));
+    for (proof, public_inputs) in proofs {
+        challenger.observe_hash::<C::Hasher>(proof.fingerprint());
+        // The number of public inputs separates them from the next proof's fingerprint.
+        challenger.observe_element(F::from_canonical_usize(public_inputs.len()));
+        challenger.observe_elements(public_inputs);
+    }
+    challenger.get_hash()
+}
+
+/// Checks that `leaves`, the Merkle leaf hashed for a coset during decompression, holds the full
+/// coset of `2^arity_bits` extension elements, and that it agrees with `compressed_evals`, the
+/// coset's evaluations stored in the compressed proof, everywhere but at `index_within_coset`,
+/// whose evaluation is inferred. A length which isn't a multiple of `D`, as after an
+/// extension-degree mismatch, fails the check rather than panicking.
+pub fn check_reconstructed_evals<F: RichField + Extendable<D>, const D: usize>(
+    compressed_evals: &[F::Extension],
+    index_within_coset: usize,
+    arity_bits: usize,
+    leaves: &[F],
+) -> bool {
+    if leaves.len() != (1 << arity_bits) * D || index_within_coset >= 1 << arity_bits {
+        return false;
+    }
+    let mut evals = unflatten::<F, D>(leaves);
+    evals.remove(index_within_coset);
+    evals == compressed_evals
+}
+
 impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> CompressedFriProof<F, H, D> {
+    /// Encodes the query indices as in the byte format of compressed proofs: the distinct indices
+    /// in increasing order as LEB128 deltas, then the position of each query among them. This
+    /// preserves the order of the queries and any duplicates.
+    pub fn encode_indices(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes
+            .write_query_indices(&self.query_round_proofs.indices)
+            .expect("Writing to a byte-vector cannot fail.");
+        bytes
+    }
+
+    /// Decodes query indices encoded by `encode_indices`.
+    pub fn decode_indices(bytes: &[u8]) -> IoResult<Vec<usize>> {
+        let mut buffer = Buffer::new(bytes);
+        let indices = buffer.read_query_indices()?;
+        if !buffer.is_empty() {
+            return Err(IoError);
+        }
+        Ok(indices)
+    }
+
+    /// The opening of the initial trees at the query `index`, or `None` if the proof has none.
+    pub fn initial_proof_for(&self, index: usize) -> Option<&FriInitialTreeProof<F, H>> {
+        self.query_round_proofs.initial_trees_proofs.get(&index)
+    }
+
+    /// The query step `step` of the query at the initial `index`, or `None` if the proof has none.
+    /// The index is shifted by the arities of the reductions up to and including `step`, which are
+    /// inferred from the sizes of the compressed cosets, each missing its inferred element.
+    pub fn step_for(&self, step: usize, index: usize) -> Option<&FriQueryStep<F, H, D>> {
+        let steps = &self.query_round_proofs.steps;
+        let mut index = index;
+        for reduction in steps.get(..=step)? {
+            let arity = reduction.values().next()?.evals.len() + 1;
+            if !arity.is_power_of_two() {
+                return None;
+            }
+            index >>= arity.trailing_zeros();
+        }
+        steps[step].get(&index)
+    }
+
     /// Decompress all the Merkle paths in the FRI proof and reinsert duplicate indices.
     pub(crate) fn decompress(
         self,
@@ -248,7 +929,7 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> CompressedFriPr
             query_round_proofs,
             final_poly,
             pow_witness,
-            ..
+            round_pow_witnesses,
         } = self;
         let FriChallenges {
             fri_query_indices: indices,
@@ -262,18 +943,17 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> CompressedFriPr
             .initial_trees_proofs
             .values()
             .next()
-            .unwrap()
-            .evals_proofs
-            .len();
+            .map_or(0, |proof| proof.evals_proofs.len());
 
         // "Transpose" the query round proofs, so that information for each Merkle tree is collected together.
         let mut initial_trees_indices = vec![vec![]; num_initial_trees];
         let mut initial_trees_leaves = vec![vec![]; num_initial_trees];
         let mut initial_trees_proofs = vec![vec![]; num_initial_trees];
         let mut steps_indices = vec![vec![]; num_reductions];
-        let mut steps_evals = vec![vec![]; num_reductions];
+        let mut steps_evals: Vec<Vec<Vec<F::Extension>>> = vec![vec![]; num_reductions];
+        let mut steps_leaves = vec![vec![]; num_reductions];
         let mut steps_proofs = vec![vec![]; num_reductions];
-        let height = params.degree_bits + params.config.rate_bits;
+        let height = params.lde_bits();
         let heights = reduction_arity_bits
             .iter()
             .scan(height, |acc, &bits| {
@@ -282,35 +962,49 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> CompressedFriPr
             })
             .collect::<Vec<_>>();
 
-        // Holds the `evals` vectors that have already been reconstructed at each reduction depth.
-        let mut evals_by_depth =
-            vec![HashMap::<usize, Vec<_>>::new(); params.reduction_arity_bits.len()];
+        // Holds, at each reduction depth, the position in `steps_evals` of the `evals` vector
+        // reconstructed for each index.
+        let mut evals_by_depth = vec![HashMap::<usize, usize>::new(); num_reductions];
         for &(mut index) in indices {
-            let initial_trees_proof = query_round_proofs.initial_trees_proofs[&index].clone();
-            for (i, (leaves_data, proof)) in
-                initial_trees_proof.evals_proofs.into_iter().enumerate()
-            {
+            let initial_trees_proof = &query_round_proofs.initial_trees_proofs[&index];
+            for (i, (leaves_data, proof)) in initial_trees_proof.evals_proofs.iter().enumerate() {
                 initial_trees_indices[i].push(index);
-                initial_trees_leaves[i].push(leaves_data);
+                initial_trees_leaves[i].push(leaves_data.as_slice());
                 initial_trees_proofs[i].push(proof);
             }
             for i in 0..num_reductions {
                 let index_within_coset = index & ((1 << reduction_arity_bits[i]) - 1);
                 index >>= reduction_arity_bits[i];
                 let FriQueryStep {
-                    mut evals,
+                    evals: compressed_evals,
                     merkle_proof,
-                } = query_round_proofs.steps[i][&index].clone();
+                } = &query_round_proofs.steps[i][&index];
                 steps_indices[i].push(index);
-                if let Some(v) = evals_by_depth[i].get(&index) {
-                    // If this index has already been seen, get `evals` from the `HashMap`.
-                    evals = v.to_vec();
+                let (evals, leaves) = if let Some(&position) = evals_by_depth[i].get(&index) {
+                    // If this index has already been seen, reuse the reconstructed `evals`.
+                    let evals = steps_evals[i][position].clone();
+                    let leaves = flatten(&evals);
+                    (evals, leaves)
                 } else {
                     // Otherwise insert the next inferred element.
+                    let mut evals = Vec::with_capacity(compressed_evals.len() + 1);
+                    evals.extend_from_slice(compressed_evals);
                     evals.insert(index_within_coset, fri_inferred_elements.next().unwrap());
-                    evals_by_depth[i].insert(index, evals.clone());
-                }
-                steps_evals[i].push(flatten(&evals));
+                    evals_by_depth[i].insert(index, steps_evals[i].len());
+                    let leaves = flatten(&evals);
+                    debug_assert!(
+                        check_reconstructed_evals::<F, D>(
+                            compressed_evals,
+                            index_within_coset,
+                            reduction_arity_bits[i],
+                            &leaves,
+                        ),
+                        "Reconstructed evals don't match the compressed proof"
+                    );
+                    (evals, leaves)
+                };
+                steps_leaves[i].push(leaves);
+                steps_evals[i].push(evals);
                 steps_proofs[i].push(merkle_proof);
             }
         }
@@ -319,43 +1013,54 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> CompressedFriPr
         let initial_trees_proofs = izip!(
             &initial_trees_leaves,
             &initial_trees_indices,
-            initial_trees_proofs
+            &initial_trees_proofs
         )
-        .map(|(ls, is, ps)| decompress_merkle_proofs(ls, is, &ps, height, cap_height))
+        .map(|(ls, is, ps)| decompress_merkle_proofs(ls, is, ps, height, cap_height).into_iter())
         .collect::<Vec<_>>();
-        let steps_proofs = izip!(&steps_evals, &steps_indices, steps_proofs, heights)
-            .map(|(ls, is, ps, h)| decompress_merkle_proofs(ls, is, &ps, h, cap_height))
+        let steps_leaves = steps_leaves
+            .iter()
+            .map(|ls| ls.iter().map(Vec::as_slice).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let steps_proofs = izip!(&steps_leaves, &steps_indices, &steps_proofs, heights)
+            .map(|(ls, is, ps, h)| decompress_merkle_proofs(ls, is, ps, h, cap_height).into_iter())
             .collect::<Vec<_>>();
 
-        let mut decompressed_query_proofs = Vec::with_capacity(num_reductions);
-        for i in 0..indices.len() {
-            let initial_trees_proof = FriInitialTreeProof {
-                evals_proofs: (0..num_initial_trees)
-                    .map(|j| {
-                        (
-                            initial_trees_leaves[j][i].clone(),
-                            initial_trees_proofs[j][i].clone(),
-                        )
+        // Assemble the query rounds, moving each opening out of the per-tree vectors in query
+        // order.
+        let mut initial_trees_leaves = initial_trees_leaves
+            .into_iter()
+            .map(Vec::into_iter)
+            .collect::<Vec<_>>();
+        let mut initial_trees_proofs = initial_trees_proofs;
+        let mut steps_evals = steps_evals
+            .into_iter()
+            .map(Vec::into_iter)
+            .collect::<Vec<_>>();
+        let mut steps_proofs = steps_proofs;
+        let decompressed_query_proofs = (0..indices.len())
+            .map(|_| FriQueryRound {
+                initial_trees_proof: FriInitialTreeProof {
+                    evals_proofs: izip!(&mut initial_trees_leaves, &mut initial_trees_proofs)
+                        .map(|(leaves, proofs)| {
+                            (leaves.next().unwrap().to_vec(), proofs.next().unwrap())
+                        })
+                        .collect(),
+                },
+                steps: izip!(&mut steps_evals, &mut steps_proofs)
+                    .map(|(evals, proofs)| FriQueryStep {
+                        evals: evals.next().unwrap(),
+                        merkle_proof: proofs.next().unwrap(),
                     })
                     .collect(),
-            };
-            let steps = (0..num_reductions)
-                .map(|j| FriQueryStep {
-                    evals: unflatten(&steps_evals[j][i]),
-                    merkle_proof: steps_proofs[j][i].clone(),
-                })
-                .collect();
-            decompressed_query_proofs.push(FriQueryRound {
-                initial_trees_proof,
-                steps,
             })
-        }
+            .collect();
 
         FriProof {
             commit_phase_merkle_caps,
             query_round_proofs: decompressed_query_proofs,
             final_poly,
             pow_witness,
+            round_pow_witnesses,
         }
     }
 }
@@ -369,6 +1074,9 @@ pub struct FriChallenges<F: RichField + Extendable<D>, const D: usize> {
 
     pub fri_pow_response: F,
 
+    // PoW responses of the commit-phase rounds which are ground.
+    pub fri_round_pow_responses: Vec<F>,
+
     // Indices at which the oracle is queried in FRI.
     pub fri_query_indices: Vec<usize>,
 }
@@ -377,5 +1085,745 @@ pub struct FriChallengesTarget<const D: usize> {
     pub fri_alpha: ExtensionTarget<D>,
     pub fri_betas: Vec<ExtensionTarget<D>>,
     pub fri_pow_response: Target,
+    pub fri_round_pow_responses: Vec<Target>,
     pub fri_query_indices: Vec<Target>,
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{boxed::Box, string::ToString, vec, vec::Vec};
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, Field64, PrimeField64, Sample};
+    use crate::fri::oracle::SALT_SIZE;
+    use crate::fri::reduction_strategies::FriReductionStrategy;
+    use crate::gates::noop::NoopGate;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::plonk::plonk_common::PlonkOracle;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_salt_entropy_estimate() -> Result<()> {
+        use crate::iop::witness::WitnessWrite;
+        use crate::plonk::plonk_common::PlonkOracle;
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let prove = |config| {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let x = builder.add_virtual_public_input();
+            let y = builder.square(x);
+            builder.register_public_input(y);
+            let data = builder.build::<C>();
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(3));
+            data.prove(pw)
+        };
+
+        // A hiding prover draws its salts uniformly at random.
+        let proof = prove(CircuitConfig::standard_recursion_zk_config())?;
+        let mut fri_proof = proof.proof.opening_proof;
+        let entropy = fri_proof.salt_entropy_estimate(true);
+        assert!(entropy > 0.95, "entropy {}", entropy);
+
+        // Salts which are all zero carry no entropy.
+        for round in &mut fri_proof.query_round_proofs {
+            for (i, (evals, _)) in round
+                .initial_trees_proof
+                .evals_proofs
+                .iter_mut()
+                .enumerate()
+            {
+                if i != PlonkOracle::CONSTANTS_SIGMAS.index {
+                    let len = evals.len();
+                    evals[len - SALT_SIZE..].fill(F::ZERO);
+                }
+            }
+        }
+        assert_eq!(fri_proof.salt_entropy_estimate(true), 0.0);
+
+        // Proofs without salts have no entropy to estimate.
+        let proof = prove(CircuitConfig::standard_recursion_config())?;
+        assert_eq!(proof.proof.opening_proof.salt_entropy_estimate(false), 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_inferred_reduction_schedule() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![1, 2, 1]);
+
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.constant(F::rand());
+        let y = builder.constant(F::rand());
+        builder.mul(x, y);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        let fri_proof = &proof.proof.opening_proof;
+
+        let num_reductions = data.common.fri_params.reduction_arity_bits.len();
+        let num_query_rounds = data.common.config.fri_config.num_query_rounds;
+        assert_eq!(fri_proof.inferred_num_reductions(), num_reductions);
+        assert_eq!(
+            fri_proof.inferred_step_counts(),
+            vec![num_reductions; num_query_rounds]
+        );
+        assert_eq!(fri_proof.try_inferred_step_count()?, num_reductions);
+
+        // Drop a step from one query round to get a malformed proof.
+        let mut malformed = fri_proof.clone();
+        malformed.query_round_proofs[3].steps.pop();
+        assert_eq!(malformed.inferred_step_counts()[3], num_reductions - 1);
+        assert!(malformed.try_inferred_step_count().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_salt() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let oracle = |num_polys, blinding| FriOracleInfo {
+            num_polys,
+            blinding,
+        };
+        // Only blinding oracles are salted, and only when hiding.
+        let oracles = [oracle(3, false), oracle(5, true)];
+        let initial_trees_proof = |lens: &[usize]| FriInitialTreeProof::<F, H> {
+            evals_proofs: lens
+                .iter()
+                .map(|&len| {
+                    (
+                        F::rand_vec(len),
+                        MerkleProof {
+                            siblings: Box::default(),
+                        },
+                    )
+                })
+                .collect(),
+        };
+
+        let unsalted = initial_trees_proof(&[3, 5]);
+        assert_eq!(unsalted.validate_salt(&oracles, false), Ok(()));
+        let salted = initial_trees_proof(&[3, 5 + SALT_SIZE]);
+        assert_eq!(salted.validate_salt(&oracles, true), Ok(()));
+
+        // The hiding flag must match the proof.
+        assert_eq!(
+            unsalted.validate_salt(&oracles, true),
+            Err(FriError::SaltMismatch {
+                oracle_index: 1,
+                expected_len: 5 + SALT_SIZE,
+                len: 5,
+            })
+        );
+        assert_eq!(
+            salted.validate_salt(&oracles, false),
+            Err(FriError::SaltMismatch {
+                oracle_index: 1,
+                expected_len: 5,
+                len: 5 + SALT_SIZE,
+            })
+        );
+
+        // A salted non-blinding oracle, and a stripped or duplicated salt.
+        assert_eq!(
+            initial_trees_proof(&[3 + SALT_SIZE, 5 + SALT_SIZE]).validate_salt(&oracles, true),
+            Err(FriError::SaltMismatch {
+                oracle_index: 0,
+                expected_len: 3,
+                len: 3 + SALT_SIZE,
+            })
+        );
+        for len in [5, 5 + 2 * SALT_SIZE] {
+            assert_eq!(
+                initial_trees_proof(&[3, len]).validate_salt(&oracles, true),
+                Err(FriError::SaltMismatch {
+                    oracle_index: 1,
+                    expected_len: 5 + SALT_SIZE,
+                    len,
+                })
+            );
+        }
+
+        assert_eq!(
+            salted.validate_salt(&oracles[..1], true),
+            Err(FriError::InitialOracleCountMismatch {
+                expected_len: 1,
+                len: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_salt_zk_proof() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_zk_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove(pw)?;
+
+        let oracles = data.common.fri_oracles();
+        let hiding = data.common.fri_params.hiding;
+        assert!(hiding);
+        for round in &proof.proof.opening_proof.query_round_proofs {
+            assert_eq!(
+                round.initial_trees_proof.validate_salt(&oracles, hiding),
+                Ok(())
+            );
+        }
+
+        // Salting the constants, which don't blind, or stripping the salt of the wires is caught
+        // before any Merkle proof is checked.
+        for (oracle_index, len_change) in [
+            (PlonkOracle::CONSTANTS_SIGMAS.index, 1),
+            (PlonkOracle::WIRES.index, -1),
+        ] {
+            let mut tampered = proof.clone();
+            let evals = &mut tampered.proof.opening_proof.query_round_proofs[0]
+                .initial_trees_proof
+                .evals_proofs[oracle_index]
+                .0;
+            if len_change > 0 {
+                evals.push(F::ZERO);
+            } else {
+                evals.pop();
+            }
+            let err = data.verify(tampered).unwrap_err();
+            assert!(
+                err.to_string().contains("salted evaluations"),
+                "unexpected error: {err}"
+            );
+        }
+
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_trivial_proofs() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let zero_reductions = {
+            let mut config = CircuitConfig::standard_recursion_config();
+            config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![]);
+            config
+        };
+        let zero_query_rounds = {
+            let mut config = CircuitConfig::standard_recursion_config();
+            config.security_bits = 0;
+            config.fri_config.num_query_rounds = 0;
+            config
+        };
+
+        for (config, is_trivial) in [(zero_reductions, true), (zero_query_rounds, false)] {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let x = builder.constant(F::rand());
+            let y = builder.constant(F::rand());
+            builder.mul(x, y);
+            for _ in 0..100 {
+                builder.add_gate(NoopGate, vec![]);
+            }
+            let data = builder.build::<C>();
+            assert_eq!(data.common.fri_params.is_trivial(), is_trivial);
+
+            let proof = data.prove(PartialWitness::new())?;
+            data.verify(proof.clone())?;
+            let compressed_proof = data.compress(proof.clone())?;
+            assert_eq!(data.decompress(compressed_proof.clone())?, proof);
+            data.verify_compressed(compressed_proof)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pow_bits() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let pow_bits = config.fri_config.pow_strategy.final_bits().unwrap();
+        assert!(pow_bits > 0);
+
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        assert!(
+            proof
+                .proof
+                .opening_proof
+                .pow_bits(&challenges.fri_challenges)
+                >= pow_bits
+        );
+
+        data.verify(proof)
+    }
+    #[test]
+    fn test_query_round_sizes() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        let indices = &challenges.fri_challenges.fri_query_indices;
+        let fri_params = &data.common.fri_params;
+        let fri_proof = proof.proof.opening_proof;
+
+        let sizes = fri_proof.query_round_sizes::<C>(indices, fri_params);
+        assert_eq!(sizes.len(), indices.len());
+
+        let compressed = fri_proof.compress(indices, fri_params);
+        let mut buffer = Vec::new();
+        buffer
+            .write_compressed_fri_query_rounds::<F, C, D>(&compressed.query_round_proofs)
+            .unwrap();
+        assert_eq!(sizes.iter().sum::<usize>(), buffer.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressed_size_bytes() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        let indices = &challenges.fri_challenges.fri_query_indices;
+        let fri_params = &data.common.fri_params;
+        let fri_proof = proof.proof.opening_proof;
+
+        let size = fri_proof.compressed_size_bytes(indices, fri_params);
+        let mut buffer = Vec::new();
+        buffer
+            .write_compressed_fri_proof::<F, C, D>(&fri_proof.clone().compress(indices, fri_params))
+            .unwrap();
+        assert_eq!(size, buffer.len());
+
+        // Repeated indices share their openings.
+        let mut repeated = fri_proof.clone();
+        repeated.query_round_proofs[1] = repeated.query_round_proofs[0].clone();
+        let mut repeated_indices = indices.clone();
+        repeated_indices[1] = repeated_indices[0];
+        let size = repeated.compressed_size_bytes(&repeated_indices, fri_params);
+        let mut buffer = Vec::new();
+        buffer
+            .write_compressed_fri_proof::<F, C, D>(
+                &repeated.compress(&repeated_indices, fri_params),
+            )
+            .unwrap();
+        assert_eq!(size, buffer.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_indices() -> Result<()> {
+        use crate::fri::proof::CompressedFriProof;
+        use crate::plonk::proof::CompressedProofWithPublicInputs;
+        use crate::util::serialization::header::ARTIFACT_HEADER_LEN;
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        for _ in 0..1000 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        let compressed = data.compress(proof)?;
+        let fri_proof = &compressed.proof.opening_proof;
+        let indices = &fri_proof.query_round_proofs.indices;
+
+        let encoded = fri_proof.encode_indices();
+        assert_eq!(
+            &CompressedFriProof::<F, H, D>::decode_indices(&encoded).unwrap(),
+            indices
+        );
+        // Indices used to take four bytes each. Here about 2^13 / 28 apart, they take less than
+        // three.
+        assert!(
+            encoded.len() < indices.len() * 3,
+            "{} bytes for {} indices",
+            encoded.len(),
+            indices.len()
+        );
+        let bytes = compressed.to_bytes();
+        assert_eq!(
+            CompressedProofWithPublicInputs::from_bytes(bytes, &data.common)?,
+            compressed
+        );
+
+        // Compressed proofs written before the delta encoding are rejected from their header.
+        let mut bytes = compressed.to_bytes_with_header(&data.common);
+        bytes[ARTIFACT_HEADER_LEN - 2..ARTIFACT_HEADER_LEN].copy_from_slice(&1u16.to_le_bytes());
+        let err =
+            CompressedProofWithPublicInputs::<F, C, D>::from_bytes_with_header(bytes, &data.common)
+                .unwrap_err()
+                .to_string();
+        assert!(err.contains("without delta encoding"), "{err}");
+
+        // Unsorted and duplicate indices keep their order.
+        let mut unsorted = fri_proof.clone();
+        unsorted.query_round_proofs.indices = vec![300, 5, 300, 0, 1 << 40, 5];
+        let encoded = unsorted.encode_indices();
+        assert_eq!(
+            CompressedFriProof::<F, H, D>::decode_indices(&encoded).unwrap(),
+            unsorted.query_round_proofs.indices
+        );
+
+        // Trailing bytes, truncations and non-minimal varints are rejected.
+        let decode = CompressedFriProof::<F, H, D>::decode_indices;
+        assert!(decode(&[encoded.clone(), vec![0]].concat()).is_err());
+        assert!(decode(&encoded[..encoded.len() - 1]).is_err());
+        assert!(decode(&[1, 1, 0x80, 0x00, 0]).is_err());
+        assert_eq!(decode(&[1, 1, 0, 0]).unwrap(), vec![0]);
+        // So are repeated distinct indices and distinct indices which are never queried.
+        assert!(decode(&[2, 2, 3, 0, 0, 1]).is_err());
+        assert!(decode(&[1, 2, 3, 1, 0]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate_challenge() -> Result<()> {
+        use crate::field::types::Field;
+        use crate::fri::proof::aggregate_challenge;
+        use crate::iop::witness::WitnessWrite;
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let proofs = (1..=3)
+            .map(|i| {
+                let mut pw = PartialWitness::new();
+                pw.set_target(x, F::from_canonical_u64(i));
+                let proof = data.prove(pw)?;
+                Ok((proof.proof.opening_proof, proof.public_inputs))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let challenge = aggregate_challenge::<F, H, C, D>(&proofs);
+        assert_eq!(aggregate_challenge::<F, H, C, D>(&proofs), challenge);
+
+        let mut reordered = proofs.clone();
+        reordered.swap(0, 2);
+        assert_ne!(aggregate_challenge::<F, H, C, D>(&reordered), challenge);
+
+        // Moving a public input from one proof to the next must change the challenge too.
+        let mut shifted = proofs.clone();
+        let moved = shifted[0].1.pop().unwrap();
+        shifted[1].1.insert(0, moved);
+        assert_ne!(aggregate_challenge::<F, H, C, D>(&shifted), challenge);
+
+        assert_ne!(aggregate_challenge::<F, H, C, D>(&proofs[..2]), challenge);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_field() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![1, 2, 1]);
+
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.constant(F::rand());
+        let y = builder.constant(F::rand());
+        builder.mul(x, y);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        let mut fri_proof = proof.proof.opening_proof;
+        // So that at least one element has a non-canonical representation below.
+        fri_proof.pow_witness = F::ZERO;
+
+        // The identity is a no-op, and a map reaches every element.
+        assert_eq!(fri_proof.clone().map_field(|x| x), fri_proof);
+        let shifted = fri_proof.clone().map_field(|x| x + F::ONE);
+        let expected = fri_proof
+            .to_field_elements()
+            .into_iter()
+            .map(|x| x + F::ONE)
+            .collect::<Vec<_>>();
+        assert_eq!(shifted.to_field_elements(), expected);
+
+        let to_bytes = |fri_proof: &FriProof<F, PoseidonHash, D>| {
+            let mut bytes = Vec::new();
+            bytes
+                .write_fri_proof::<F, C, D>(fri_proof)
+                .expect("Writing to a byte-vector cannot fail.");
+            bytes
+        };
+        let non_canonical = fri_proof.clone().map_field(|x| {
+            let x = x.to_canonical_u64();
+            GoldilocksField(x.checked_add(F::ORDER).unwrap_or(x))
+        });
+        assert!(non_canonical
+            .to_field_elements()
+            .iter()
+            .any(|x| x.0 >= F::ORDER));
+
+        let canonicalize = |x: F| F::from_canonical_u64(x.to_canonical_u64());
+        let canonical = non_canonical.map_field(canonicalize);
+        assert!(canonical.to_field_elements().iter().all(|x| x.0 < F::ORDER));
+        assert_eq!(to_bytes(&canonical), to_bytes(&fri_proof));
+        assert_eq!(
+            to_bytes(&canonical.clone().map_field(canonicalize)),
+            to_bytes(&canonical)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_elements_roundtrip() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FE = <C as GenericConfig<D>>::FE;
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![1, 2, 1]);
+
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.constant(F::rand());
+        let y = builder.constant(F::rand());
+        builder.mul(x, y);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        let fri_proof = &proof.proof.opening_proof;
+        let params = &data.common.fri_params;
+
+        let elements = fri_proof.to_field_elements();
+        assert_eq!(
+            fri_proof.fingerprint(),
+            PoseidonHash::hash_no_pad(&elements)
+        );
+
+        // The serialized proof only adds a length byte to each Merkle path.
+        let mut bytes = Vec::new();
+        bytes
+            .write_fri_proof::<F, C, D>(fri_proof)
+            .expect("Writing to a byte-vector cannot fail.");
+        let num_merkle_proofs = fri_proof
+            .query_round_proofs
+            .iter()
+            .map(|qrp| qrp.initial_trees_proof.evals_proofs.len() + qrp.steps.len())
+            .sum::<usize>();
+        assert_eq!(elements.len(), (bytes.len() - num_merkle_proofs) / 8);
+        assert_eq!((bytes.len() - num_merkle_proofs) % 8, 0);
+
+        let instance = data.common.get_fri_instance(FE::rand());
+        let oracle_rate_bits = params.uniform_oracle_rate_bits(instance.oracles.len());
+        let roundtrip =
+            FriProof::from_field_elements(&elements, &instance, &oracle_rate_bits, params)?;
+        assert_eq!(&roundtrip, fri_proof);
+
+        assert!(FriProof::<F, PoseidonHash, D>::from_field_elements(
+            &elements[1..],
+            &instance,
+            &oracle_rate_bits,
+            params
+        )
+        .is_err());
+        let mut extended = elements.clone();
+        extended.push(F::ZERO);
+        assert!(FriProof::<F, PoseidonHash, D>::from_field_elements(
+            &extended,
+            &instance,
+            &oracle_rate_bits,
+            params
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_reconstructed_evals() {
+        const D: usize = 2;
+        type F = GoldilocksField;
+        type FE = <F as Extendable<D>>::Extension;
+        let arity_bits = 3;
+
+        let evals = FE::rand_vec(1 << arity_bits);
+        let leaves = flatten::<F, D>(&evals);
+        let mut compressed_evals = evals.clone();
+        compressed_evals.remove(5);
+        assert!(check_reconstructed_evals::<F, D>(
+            &compressed_evals,
+            5,
+            arity_bits,
+            &leaves
+        ));
+
+        // The inferred element is at a different position.
+        assert!(!check_reconstructed_evals::<F, D>(
+            &compressed_evals,
+            4,
+            arity_bits,
+            &leaves
+        ));
+        // A leaf which disagrees with the compressed proof.
+        let mut tampered = leaves.clone();
+        tampered[0] += F::ONE;
+        assert!(!check_reconstructed_evals::<F, D>(
+            &compressed_evals,
+            5,
+            arity_bits,
+            &tampered
+        ));
+        // Leaves whose length isn't a multiple of `D`, or which hold fewer extension elements than
+        // the coset.
+        for len in [leaves.len() - 1, leaves.len() - D] {
+            assert!(!check_reconstructed_evals::<F, D>(
+                &compressed_evals,
+                5,
+                arity_bits,
+                &leaves[..len]
+            ));
+        }
+        // Leaves flattened with a larger extension degree.
+        let wider = flatten::<F, 4>(&<F as Extendable<4>>::Extension::rand_vec(1 << arity_bits));
+        assert!(!check_reconstructed_evals::<F, D>(
+            &compressed_evals,
+            5,
+            arity_bits,
+            &wider
+        ));
+    }
+
+    #[test]
+    fn test_compressed_accessors() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![1, 2, 1]);
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        let fri_proof = proof.proof.opening_proof.clone();
+        let compressed = data.compress(proof)?.proof.opening_proof;
+        let indices = &compressed.query_round_proofs.indices;
+        let arity_bits = &data.common.fri_params.reduction_arity_bits;
+
+        // Each coset is stored once, without the element inferred by the first query opening it.
+        let mut seen = HashSet::new();
+        for (&index, round) in indices.iter().zip(&fri_proof.query_round_proofs) {
+            let initial = compressed.initial_proof_for(index).unwrap();
+            for ((leaf, _), (expected, _)) in initial
+                .evals_proofs
+                .iter()
+                .zip(&round.initial_trees_proof.evals_proofs)
+            {
+                assert_eq!(leaf, expected);
+            }
+            let mut shifted = index;
+            for (i, step) in round.steps.iter().enumerate() {
+                let coset_index = shifted & ((1 << arity_bits[i]) - 1);
+                shifted >>= arity_bits[i];
+                let compressed_step = compressed.step_for(i, index).unwrap();
+                if seen.insert((i, shifted)) {
+                    let mut expected = step.evals.clone();
+                    expected.remove(coset_index);
+                    assert_eq!(compressed_step.evals, expected);
+                }
+            }
+            assert!(compressed.step_for(arity_bits.len(), index).is_none());
+        }
+
+        let absent = (0..).find(|i| !indices.contains(i)).unwrap();
+        assert!(compressed.initial_proof_for(absent).is_none());
+        let shift = arity_bits.iter().sum::<usize>();
+        let absent = (0..)
+            .map(|i| i << shift)
+            .find(|&i| indices.iter().all(|j| j >> shift != i >> shift))
+            .unwrap();
+        assert!(compressed.step_for(arity_bits.len() - 1, absent).is_none());
+
+        Ok(())
+    }
+}
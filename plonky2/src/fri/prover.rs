@@ -2,21 +2,24 @@ use alloc::vec::Vec;
 
 use plonky2_maybe_rayon::*;
 
-use crate::field::extension::{flatten, unflatten, Extendable};
-use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use crate::field::extension::{unflatten, Extendable};
+use crate::field::polynomial::{FlatExtensionPolynomial, PolynomialCoeffs, PolynomialValues};
+use crate::fri::folding::fold_committed_values;
 use crate::fri::proof::{FriInitialTreeProof, FriProof, FriQueryRound, FriQueryStep};
-use crate::fri::{FriConfig, FriParams};
+use crate::fri::FriParams;
 use crate::hash::hash_types::RichField;
 use crate::hash::hashing::PlonkyPermutation;
 use crate::hash::merkle_tree::MerkleTree;
 use crate::iop::challenger::Challenger;
 use crate::plonk::config::GenericConfig;
-use crate::plonk::plonk_common::reduce_with_powers;
 use crate::timed;
-use crate::util::reverse_index_bits_in_place;
+use crate::util::sensitive::{wipe_vec, SensitiveBuffers};
 use crate::util::timing::TimingTree;
 
 /// Builds a FRI proof.
+///
+/// Only the values are folded, so the coefficients are merely checked for length and dropped, see
+/// [`fri_proof_from_values`].
 pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
     // Coefficients of the polynomial on which the LDT is performed. Only the first `1/rate` coefficients are non-zero.
@@ -26,97 +29,186 @@ pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
     challenger: &mut Challenger<F, C::Hasher>,
     fri_params: &FriParams,
     timing: &mut TimingTree,
+) -> FriProof<F, C::Hasher, D> {
+    assert_eq!(lde_polynomial_coeffs.len(), lde_polynomial_values.len());
+    drop(lde_polynomial_coeffs);
+    fri_proof_from_values::<F, C, D>(
+        initial_merkle_trees,
+        lde_polynomial_values.into(),
+        challenger,
+        fri_params,
+        timing,
+    )
+}
+
+/// Builds a FRI proof from the evaluation of the polynomial on the large domain, in natural order.
+///
+/// The commit phase folds the values directly, in place of the coefficients, so that a single
+/// codeword is alive besides the leaves of the Merkle tree being built.
+pub fn fri_proof_from_values<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
+    lde_polynomial_values: FlatExtensionPolynomial<F, D>,
+    challenger: &mut Challenger<F, C::Hasher>,
+    fri_params: &FriParams,
+    timing: &mut TimingTree,
+) -> FriProof<F, C::Hasher, D> {
+    fri_proof_from_values_with_zeroize::<F, C, D>(
+        initial_merkle_trees,
+        lde_polynomial_values,
+        challenger,
+        fri_params,
+        false,
+        timing,
+    )
+}
+
+/// Like `fri_proof_from_values`, but if `zeroize` is set, the folded codewords and the leaves of
+/// the commit-phase trees are overwritten with zeros once they are no longer needed.
+pub(crate) fn fri_proof_from_values_with_zeroize<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
+    lde_polynomial_values: FlatExtensionPolynomial<F, D>,
+    challenger: &mut Challenger<F, C::Hasher>,
+    fri_params: &FriParams,
+    zeroize: bool,
+    timing: &mut TimingTree,
 ) -> FriProof<F, C::Hasher, D> {
     let n = lde_polynomial_values.len();
-    assert_eq!(lde_polynomial_coeffs.len(), n);
 
     // Commit phase
-    let (trees, final_coeffs) = timed!(
+    let (trees, final_coeffs, round_pow_witnesses) = timed!(
         timing,
         "fold codewords in the commitment phase",
-        fri_committed_trees::<F, C, D>(
-            lde_polynomial_coeffs,
-            lde_polynomial_values,
-            challenger,
-            fri_params,
-        )
+        fri_committed_trees::<F, C, D>(lde_polynomial_values, challenger, fri_params, zeroize)
     );
 
     // PoW phase
-    let pow_witness = timed!(
-        timing,
-        "find proof-of-work witness",
-        fri_proof_of_work::<F, C, D>(challenger, &fri_params.config)
-    );
+    challenger.label(b"fri_pow");
+    challenger.observe_pow_context();
+    let pow_witness = match fri_params.config.pow_strategy.final_bits() {
+        Some(pow_bits) => timed!(
+            timing,
+            "find proof-of-work witness",
+            fri_proof_of_work::<F, C, D>(challenger, pow_bits)
+        ),
+        None => F::ZERO,
+    };
 
     // Query phase
     let query_round_proofs =
         fri_prover_query_rounds::<F, C, D>(initial_merkle_trees, &trees, challenger, n, fri_params);
 
+    let commit_phase_merkle_caps = trees.iter().map(|t| t.cap.clone()).collect();
+    for tree in trees {
+        drop(SensitiveBuffers::new(tree, zeroize));
+    }
+
     FriProof {
-        commit_phase_merkle_caps: trees.iter().map(|t| t.cap.clone()).collect(),
+        commit_phase_merkle_caps,
         query_round_proofs,
         final_poly: final_coeffs,
         pow_witness,
+        round_pow_witnesses,
     }
 }
 
 type FriCommitedTrees<F, C, const D: usize> = (
     Vec<MerkleTree<F, <C as GenericConfig<D>>::Hasher>>,
     PolynomialCoeffs<<F as Extendable<D>>::Extension>,
+    Vec<F>,
 );
 
 fn fri_committed_trees<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
-    mut coeffs: PolynomialCoeffs<F::Extension>,
-    mut values: PolynomialValues<F::Extension>,
+    mut values: FlatExtensionPolynomial<F, D>,
     challenger: &mut Challenger<F, C::Hasher>,
     fri_params: &FriParams,
+    zeroize: bool,
 ) -> FriCommitedTrees<F, C, D> {
     let mut trees = Vec::with_capacity(fri_params.reduction_arity_bits.len());
+    let round_pow_bits = fri_params.round_pow_bits();
+    let mut round_pow_witnesses = Vec::with_capacity(round_pow_bits.len());
 
+    // The values stay in the committed, bit-reversed order throughout the folding.
+    values.reverse_index_bits_in_place();
     let mut shift = F::MULTIPLICATIVE_GROUP_GENERATOR;
-    for arity_bits in &fri_params.reduction_arity_bits {
+    for (round, arity_bits) in fri_params.reduction_arity_bits.iter().enumerate() {
         let arity = 1 << arity_bits;
 
-        reverse_index_bits_in_place(&mut values.values);
         let chunked_values = values
-            .values
-            .par_chunks(arity)
-            .map(|chunk: &[F::Extension]| flatten(chunk))
+            .elements
+            .par_chunks(arity * D)
+            .map(|chunk| chunk.to_vec())
             .collect();
         let tree = MerkleTree::<F, C::Hasher>::new(chunked_values, fri_params.config.cap_height);
 
+        challenger.label(b"fri_commit_phase_cap");
         challenger.observe_cap(&tree.cap);
         trees.push(tree);
 
+        if let Some(&pow_bits) = round_pow_bits.get(round) {
+            challenger.label(b"fri_round_pow");
+            challenger.observe_round_pow_separator(round);
+            round_pow_witnesses.push(fri_proof_of_work::<F, C, D>(challenger, pow_bits));
+        }
+
+        challenger.label(b"fri_beta");
         let beta = challenger.get_extension_challenge::<D>();
         // P(x) = sum_{i<r} x^i * P_i(x^r) becomes sum_{i<r} beta^i * P_i(x).
-        coeffs = PolynomialCoeffs::new(
-            coeffs
-                .coeffs
-                .par_chunks_exact(arity)
-                .map(|chunk| reduce_with_powers(chunk, beta))
-                .collect::<Vec<_>>(),
-        );
+        let folded = fold_committed_values(&values, beta, *arity_bits, shift);
+        if zeroize {
+            wipe_vec(&mut values.elements, F::ZERO);
+        }
+        values = folded;
         shift = shift.exp_u64(arity as u64);
-        values = coeffs.coset_fft(shift.into())
     }
 
+    values.reverse_index_bits_in_place();
+    values.coset_ifft_in_place(shift);
+    let mut coeffs = values.into_coeffs();
     // The coefficients being removed here should always be zero.
     coeffs
         .coeffs
         .truncate(coeffs.len() >> fri_params.config.rate_bits);
 
+    challenger.label(b"fri_final_poly");
     challenger.observe_extension_elements(&coeffs.coeffs);
-    (trees, coeffs)
+    (trees, coeffs, round_pow_witnesses)
 }
 
-/// Performs the proof-of-work (a.k.a. grinding) step of the FRI protocol. Returns the PoW witness.
+/// Performs a proof-of-work (a.k.a. grinding) step of the FRI protocol, with `pow_bits` bits.
+/// Returns the PoW witness.
 fn fri_proof_of_work<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     challenger: &mut Challenger<F, C::Hasher>,
-    config: &FriConfig,
+    pow_bits: u32,
 ) -> F {
-    let min_leading_zeros = config.proof_of_work_bits + (64 - F::order().bits()) as u32;
+    let min_leading_zeros = pow_bits + (64 - F::order().bits()) as u32;
+
+    // An external transcript is opaque, so each candidate is tried on a clone of the challenger.
+    if !challenger.is_sponge() {
+        let pow_witness = (0..=F::NEG_ONE.to_canonical_u64())
+            .into_par_iter()
+            .map(F::from_canonical_u64)
+            .find_first(|&candidate| {
+                let mut challenger = challenger.clone();
+                challenger.observe_element(candidate);
+                challenger
+                    .get_challenge()
+                    .to_canonical_u64()
+                    .leading_zeros()
+                    >= min_leading_zeros
+            })
+            .expect("Proof of work failed. This is highly unlikely!");
+        challenger.observe_element(pow_witness);
+        challenger.get_challenge();
+        return pow_witness;
+    }
 
     // The easiest implementation would be repeatedly clone our Challenger. With each clone, we'd
     // observe an incrementing PoW witness, then get the PoW response. If it contained sufficient
@@ -138,9 +230,11 @@ fn fri_proof_of_work<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
     let witness_input_pos = challenger.input_buffer.len();
     duplex_intermediate_state.set_from_iter(challenger.input_buffer.clone(), 0);
 
+    // Take the smallest valid witness rather than any, so that proving is deterministic given the
+    // transcript, see `ProverOptions::salt_rng_seed`.
     let pow_witness = (0..=F::NEG_ONE.to_canonical_u64())
         .into_par_iter()
-        .find_any(|&candidate| {
+        .find_first(|&candidate| {
             let mut duplex_state = duplex_intermediate_state;
             duplex_state.set_elt(F::from_canonical_u64(candidate), witness_input_pos);
             duplex_state.permute();
@@ -170,11 +264,11 @@ fn fri_prover_query_rounds<
     n: usize,
     fri_params: &FriParams,
 ) -> Vec<FriQueryRound<F, C::Hasher, D>> {
+    challenger.label(b"fri_query_indices");
     challenger
-        .get_n_challenges(fri_params.config.num_query_rounds)
+        .sample_query_indices(n, fri_params.config.num_query_rounds)
         .into_par_iter()
-        .map(|rand| {
-            let x_index = rand.to_canonical_u64() as usize % n;
+        .map(|x_index| {
             fri_prover_query_round::<F, C, D>(initial_merkle_trees, trees, x_index, fri_params)
         })
         .collect()
@@ -214,3 +308,111 @@ fn fri_prover_query_round<
         steps: query_steps,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use super::*;
+    use crate::field::extension::flatten;
+    use crate::field::types::{Field, Sample};
+    use crate::fri::folding::fold_polynomial_coeffs;
+    use crate::fri::reduction_strategies::FriReductionStrategy;
+    use crate::fri::{FriConfig, FriPowStrategy};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::util::reverse_index_bits_in_place;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type FE = <F as Extendable<D>>::Extension;
+
+    /// The commit phase folding the coefficients, and evaluating each folded polynomial afresh.
+    fn coefficient_committed_trees(
+        mut coeffs: PolynomialCoeffs<FE>,
+        challenger: &mut Challenger<F, <C as GenericConfig<D>>::Hasher>,
+        fri_params: &FriParams,
+    ) -> FriCommitedTrees<F, C, D> {
+        let mut trees = Vec::new();
+        let round_pow_bits = fri_params.round_pow_bits();
+        let mut round_pow_witnesses = Vec::new();
+        let mut shift = F::MULTIPLICATIVE_GROUP_GENERATOR;
+        let mut values = coeffs.coset_fft(shift.into());
+        for (round, &arity_bits) in fri_params.reduction_arity_bits.iter().enumerate() {
+            reverse_index_bits_in_place(&mut values.values);
+            let chunked_values = values
+                .values
+                .chunks(1 << arity_bits)
+                .map(flatten::<F, D>)
+                .collect();
+            let tree = MerkleTree::new(chunked_values, fri_params.config.cap_height);
+            challenger.observe_cap(&tree.cap);
+            trees.push(tree);
+            if let Some(&pow_bits) = round_pow_bits.get(round) {
+                challenger.observe_round_pow_separator(round);
+                round_pow_witnesses.push(fri_proof_of_work::<F, C, D>(challenger, pow_bits));
+            }
+            let beta = challenger.get_extension_challenge::<D>();
+            coeffs = fold_polynomial_coeffs(&coeffs, beta, arity_bits);
+            shift = shift.exp_power_of_2(arity_bits);
+            values = coeffs.coset_fft(shift.into());
+        }
+        coeffs
+            .coeffs
+            .truncate(coeffs.len() >> fri_params.config.rate_bits);
+        challenger.observe_extension_elements::<D>(&coeffs.coeffs);
+        (trees, coeffs, round_pow_witnesses)
+    }
+
+    #[test]
+    fn test_committed_trees_match_coefficient_folding() {
+        let rate_bits = 2;
+        for (degree_bits, reduction_arity_bits) in [
+            (3, vec![]),
+            (4, vec![1]),
+            (6, vec![2, 3]),
+            (8, vec![4, 1, 2]),
+            (10, vec![3, 3, 3]),
+        ] {
+            let fri_params = FriParams {
+                config: FriConfig {
+                    rate_bits,
+                    cap_height: 1,
+                    pow_strategy: FriPowStrategy::PerRound(vec![2]),
+                    reduction_strategy: FriReductionStrategy::Fixed(reduction_arity_bits.clone()),
+                    num_query_rounds: 4,
+                    commit_final_poly: false,
+                },
+                hiding: false,
+                degree_bits: degree_bits.into(),
+                reduction_arity_bits,
+            };
+            let coeffs = PolynomialCoeffs::new(FE::rand_vec(1 << degree_bits))
+                .lde(fri_params.config.rate_bits);
+            let mut challenger = Challenger::new();
+            challenger.observe_element(F::rand());
+
+            let mut expected_challenger = challenger.clone();
+            let (expected_trees, expected_final_poly, expected_pow_witnesses) =
+                coefficient_committed_trees(coeffs.clone(), &mut expected_challenger, &fri_params);
+
+            let mut values = FlatExtensionPolynomial::from(coeffs);
+            values.coset_fft_in_place(F::MULTIPLICATIVE_GROUP_GENERATOR);
+            let (trees, final_poly, pow_witnesses) =
+                fri_committed_trees::<F, C, D>(values, &mut challenger, &fri_params, false);
+
+            assert_eq!(trees.len(), expected_trees.len());
+            for (tree, expected) in trees.iter().zip(&expected_trees) {
+                assert_eq!(tree.leaves, expected.leaves);
+                assert_eq!(tree.cap, expected.cap);
+            }
+            assert_eq!(final_poly, expected_final_poly);
+            assert_eq!(pow_witnesses, expected_pow_witnesses);
+            assert_eq!(
+                challenger.current_state(),
+                expected_challenger.current_state()
+            );
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Sampling of the FRI query indices from the transcript.
+//!
+//! By default, each query index is a challenge reduced modulo the size of the LDE, which is a power
+//! of two. A [`QueryIndexSampler`] replaces this reduction, for instance to sample the indices
+//! without bias. The prover and the verifier must use the same sampler; see
+//! `CircuitData::prove_with_query_index_sampler` and `CircuitData::verify_with_query_index_sampler`.
+//! Proofs sampled with a custom sampler cannot be verified recursively, as the recursive verifier
+//! only implements the default reduction.
+
+use alloc::vec::Vec;
+
+use crate::hash::hash_types::RichField;
+use crate::iop::challenger::Challenger;
+use crate::plonk::config::Hasher;
+
+/// Derives the FRI query indices from a challenger.
+pub trait QueryIndexSampler<F: RichField, H: Hasher<F>>: Send + Sync {
+    /// Samples `count` indices in `0..lde_size`, drawing challenges from `challenger`.
+    fn sample(
+        &self,
+        challenger: &mut Challenger<F, H>,
+        lde_size: usize,
+        count: usize,
+    ) -> Vec<usize>;
+}
+
+/// The default sampler, which masks each challenge down to the power-of-two LDE size.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct PowerOfTwoQueryIndexSampler;
+
+impl<F: RichField, H: Hasher<F>> QueryIndexSampler<F, H> for PowerOfTwoQueryIndexSampler {
+    fn sample(
+        &self,
+        challenger: &mut Challenger<F, H>,
+        lde_size: usize,
+        count: usize,
+    ) -> Vec<usize> {
+        assert!(
+            lde_size.is_power_of_two(),
+            "The LDE size {} is not a power of two",
+            lde_size
+        );
+        challenger
+            .get_n_challenges(count)
+            .into_iter()
+            .map(|challenge| challenge.to_canonical_u64() as usize & (lde_size - 1))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+    use alloc::vec;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::{Field, Field64, PrimeField64};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    /// Rejects the challenges in the last, incomplete multiple of `lde_size` below the field
+    /// order, so that the indices are uniform over any domain.
+    struct UnbiasedSampler;
+
+    impl QueryIndexSampler<F, H> for UnbiasedSampler {
+        fn sample(
+            &self,
+            challenger: &mut Challenger<F, H>,
+            lde_size: usize,
+            count: usize,
+        ) -> Vec<usize> {
+            let lde_size = lde_size as u64;
+            let limit = F::ORDER - F::ORDER % lde_size;
+            let mut indices = Vec::with_capacity(count);
+            while indices.len() < count {
+                let challenge = challenger.get_challenge().to_canonical_u64();
+                if challenge < limit {
+                    indices.push((challenge % lde_size) as usize);
+                }
+            }
+            indices
+        }
+    }
+
+    /// Shifts the default indices by one, so that they differ from those of the default sampler.
+    struct ShiftedSampler;
+
+    impl QueryIndexSampler<F, H> for ShiftedSampler {
+        fn sample(
+            &self,
+            challenger: &mut Challenger<F, H>,
+            lde_size: usize,
+            count: usize,
+        ) -> Vec<usize> {
+            PowerOfTwoQueryIndexSampler
+                .sample(challenger, lde_size, count)
+                .into_iter()
+                .map(|i| (i + 1) % lde_size)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_unbiased_sampler_over_non_power_of_two_domain() {
+        const LDE_SIZE: usize = 3 * 5;
+        const COUNT: usize = 15_000;
+        let mut challenger = Challenger::<F, H>::new();
+        challenger.observe_element(F::ONE);
+        let indices = UnbiasedSampler.sample(&mut challenger, LDE_SIZE, COUNT);
+        assert_eq!(indices.len(), COUNT);
+
+        let mut counts = vec![0usize; LDE_SIZE];
+        for i in indices {
+            counts[i] += 1;
+        }
+        // Each index is expected 1000 times, with a standard deviation of about 31.
+        for count in counts {
+            assert!((850..1150).contains(&count), "Skewed count {}", count);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "is not a power of two")]
+    fn test_default_sampler_rejects_non_power_of_two_domain() {
+        let mut challenger = Challenger::<F, H>::new();
+        PowerOfTwoQueryIndexSampler.sample(&mut challenger, 15, 1);
+    }
+
+    fn square_circuit() -> (CircuitData<F, C, D>, PartialWitness<F>) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        (builder.build::<C>(), pw)
+    }
+
+    #[test]
+    fn test_prove_with_query_index_sampler() -> Result<()> {
+        let (data, pw) = square_circuit();
+        let proof = data.prove_with_query_index_sampler(pw.clone(), Arc::new(UnbiasedSampler))?;
+        data.verify_with_query_index_sampler(proof, Arc::new(UnbiasedSampler))?;
+
+        let shifted = data.prove_with_query_index_sampler(pw, Arc::new(ShiftedSampler))?;
+        data.verify_with_query_index_sampler(shifted.clone(), Arc::new(ShiftedSampler))?;
+        // The verifier must sample the same indices as the prover.
+        assert!(data.verify(shifted.clone()).is_err());
+        assert!(data
+            .verify_with_query_index_sampler(shifted, Arc::new(UnbiasedSampler))
+            .is_err());
+        Ok(())
+    }
+}
@@ -18,6 +18,8 @@ use crate::iop::ext_target::{flatten_target, ExtensionTarget};
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
+use crate::recursion::variable_degree::VariableDegreeBits;
+use crate::util::log_size::LdeBits;
 use crate::util::reducing::ReducingFactorTarget;
 use crate::util::{log2_strict, reverse_index_bits_in_place};
 use crate::with_context;
@@ -91,11 +93,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
     }
 
-    fn fri_verify_proof_of_work(&mut self, fri_pow_response: Target, config: &FriConfig) {
-        self.assert_leading_zeros(
-            fri_pow_response,
-            config.proof_of_work_bits + (64 - F::order().bits()) as u32,
-        );
+    fn fri_verify_proof_of_work(&mut self, fri_pow_response: Target, pow_bits: u32) {
+        self.assert_leading_zeros(fri_pow_response, pow_bits + (64 - F::order().bits()) as u32);
     }
 
     pub fn verify_fri_proof<C: GenericConfig<D, F = F>>(
@@ -125,7 +124,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         with_context!(
             self,
             "check PoW",
-            self.fri_verify_proof_of_work(challenges.fri_pow_response, &params.config)
+            self.fri_verify_proofs_of_work(challenges, params)
         );
 
         // Check that parameters are coherent.
@@ -175,6 +174,135 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Like `verify_fri_proof`, for a proof padded to the shape of `params` whose actual degree is
+    /// the witnessed `degree_bits`, at most `params.degree_bits`. Query indices are masked to the
+    /// actual LDE, Merkle proofs are checked against trees of the actual heights, and the padding
+    /// of the final polynomial must be zero. The reduction arities are those of `params`
+    /// whatever the degree.
+    pub(crate) fn verify_fri_proof_variable_degree<C: GenericConfig<D, F = F>>(
+        &mut self,
+        instance: &FriInstanceInfoTarget<D>,
+        openings: &FriOpeningsTarget<D>,
+        challenges: &FriChallengesTarget<D>,
+        initial_merkle_caps: &[MerkleCapTarget],
+        proof: &FriProofTarget<D>,
+        params: &FriParams,
+        degree_bits: &VariableDegreeBits,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        if let Some(max_arity_bits) = params.max_arity_bits() {
+            self.check_recursion_config(max_arity_bits);
+        }
+
+        debug_assert_eq!(
+            params.final_poly_len(),
+            proof.final_poly.len(),
+            "Final polynomial has wrong degree."
+        );
+        debug_assert_eq!(
+            params.config.num_query_rounds,
+            proof.query_round_proofs.len(),
+            "Number of query rounds does not match config."
+        );
+
+        let max_degree_bits = params.degree_bits.get();
+        let min_degree_bits = *degree_bits.range().start();
+        assert_eq!(*degree_bits.range().end(), max_degree_bits);
+        assert!(
+            min_degree_bits >= params.total_arities()
+                && min_degree_bits + params.config.rate_bits
+                    >= params.total_arities() + params.config.cap_height,
+            "A degree of 2^{} is too small for the FRI reduction arities {:?}.",
+            min_degree_bits,
+            params.reduction_arity_bits
+        );
+
+        with_context!(
+            self,
+            "check PoW",
+            self.fri_verify_proofs_of_work(challenges, params)
+        );
+
+        with_context!(self, "check final polynomial padding", {
+            let min_final_poly_len = params.final_poly_len() >> (max_degree_bits - min_degree_bits);
+            for (j, coeff) in proof
+                .final_poly
+                .0
+                .iter()
+                .enumerate()
+                .skip(min_final_poly_len)
+            {
+                let is_padding = degree_bits.satisfies(self, |d| {
+                    j >= params.final_poly_len() >> (max_degree_bits - d)
+                });
+                for &c in &coeff.0 {
+                    let padding = self.mul(is_padding.target, c);
+                    self.assert_zero(padding);
+                }
+            }
+        });
+
+        let precomputed_reduced_evals = with_context!(
+            self,
+            "precompute reduced evaluations",
+            PrecomputedReducedOpeningsTarget::from_os_and_alpha(
+                openings,
+                challenges.fri_alpha,
+                self
+            )
+        );
+
+        for (i, round_proof) in proof.query_round_proofs.iter().enumerate() {
+            let level = if i == 1 {
+                log::Level::Debug
+            } else {
+                log::Level::Trace
+            };
+
+            let num_queries = proof.query_round_proofs.len();
+            with_context!(
+                self,
+                level,
+                &format!("verify one (of {num_queries}) query rounds"),
+                self.fri_verifier_query_round_variable_degree::<C>(
+                    instance,
+                    challenges,
+                    &precomputed_reduced_evals,
+                    initial_merkle_caps,
+                    proof,
+                    challenges.fri_query_indices[i],
+                    round_proof,
+                    params,
+                    degree_bits,
+                )
+            );
+        }
+    }
+
+    fn fri_verify_proofs_of_work(
+        &mut self,
+        challenges: &FriChallengesTarget<D>,
+        params: &FriParams,
+    ) {
+        if let Some(pow_bits) = params.config.pow_strategy.final_bits() {
+            self.fri_verify_proof_of_work(challenges.fri_pow_response, pow_bits);
+        }
+        let round_pow_bits = params.round_pow_bits();
+        assert_eq!(
+            challenges.fri_round_pow_responses.len(),
+            round_pow_bits.len(),
+            "Wrong number of commit-phase proof of work responses."
+        );
+        for (&response, &pow_bits) in challenges
+            .fri_round_pow_responses
+            .iter()
+            .zip(round_pow_bits)
+        {
+            self.fri_verify_proof_of_work(response, pow_bits);
+        }
+    }
+
     fn fri_verify_initial_proof<H: AlgebraicHasher<F>>(
         &mut self,
         x_index_bits: &[BoolTarget],
@@ -212,11 +340,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         params: &FriParams,
     ) -> ExtensionTarget<D> {
         assert!(D > 1, "Not implemented for D=1.");
-        let degree_log = params.degree_bits;
         debug_assert_eq!(
-            degree_log,
-            params.config.cap_height + proof.evals_proofs[0].1.siblings.len()
-                - params.config.rate_bits
+            params.lde_bits(),
+            LdeBits::new(params.config.cap_height + proof.evals_proofs[0].1.siblings.len())
         );
         let subgroup_x = self.convert_to_ext(subgroup_x);
         let mut alpha = ReducingFactorTarget::new(alpha);
@@ -360,6 +486,149 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.connect_extension(eval, old_eval);
     }
 
+    /// Like `fri_verifier_query_round`, where the LDE has `degree_bits + rate_bits` bits rather
+    /// than the `params.lde_bits()` of the padded proof.
+    fn fri_verifier_query_round_variable_degree<C: GenericConfig<D, F = F>>(
+        &mut self,
+        instance: &FriInstanceInfoTarget<D>,
+        challenges: &FriChallengesTarget<D>,
+        precomputed_reduced_evals: &PrecomputedReducedOpeningsTarget<D>,
+        initial_merkle_caps: &[MerkleCapTarget],
+        proof: &FriProofTarget<D>,
+        x_index: Target,
+        round_proof: &FriQueryRoundTarget<D>,
+        params: &FriParams,
+        degree_bits: &VariableDegreeBits,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let n_log = params.lde_bits().get();
+        let rate_bits = params.config.rate_bits;
+        let cap_height = params.config.cap_height;
+        let min_lde_bits = *degree_bits.range().start() + rate_bits;
+
+        Self::assert_noncanonical_indices_ok(&params.config);
+        // The native verifier keeps the bits of the index below the height of the actual LDE, so
+        // the higher ones are cleared. The index of `x` within the padded domain of size `2^n_log`
+        // then has the same bit reversal, up to a power of two, and `x` itself is unchanged.
+        let mut x_index_bits = self
+            .low_bits(x_index, n_log, F::BITS)
+            .into_iter()
+            .enumerate()
+            .map(|(i, bit)| {
+                if i < min_lde_bits {
+                    bit
+                } else {
+                    let in_lde = degree_bits.satisfies(self, |d| i < d + rate_bits);
+                    self.and(bit, in_lde)
+                }
+            })
+            .collect_vec();
+
+        // The number of layers between the leaves and the cap of the trees of the current round.
+        let mut depth = self.add_const(
+            degree_bits.target(),
+            F::from_noncanonical_i64(rate_bits as i64 - cap_height as i64),
+        );
+        let mut max_depth = n_log - cap_height;
+
+        with_context!(self, "check FRI initial proof", {
+            for (i, ((evals, merkle_proof), cap)) in round_proof
+                .initial_trees_proof
+                .evals_proofs
+                .iter()
+                .zip(initial_merkle_caps)
+                .enumerate()
+            {
+                with_context!(
+                    self,
+                    &format!("verify {i}'th initial Merkle proof"),
+                    self.verify_merkle_proof_variable_depth::<C::Hasher>(
+                        evals.clone(),
+                        &x_index_bits,
+                        cap,
+                        merkle_proof.siblings.clone(),
+                        depth,
+                        max_depth,
+                    )
+                );
+            }
+        });
+
+        let mut subgroup_x = with_context!(self, "compute x from its index", {
+            let g = self.constant(F::coset_shift());
+            let phi = F::primitive_root_of_unity(n_log);
+            let phi = self.exp_from_bits_const_base(phi, x_index_bits.iter().rev());
+            self.mul(g, phi)
+        });
+
+        let mut old_eval = with_context!(
+            self,
+            "combine initial oracles",
+            self.fri_combine_initial(
+                instance,
+                &round_proof.initial_trees_proof,
+                challenges.fri_alpha,
+                subgroup_x,
+                precomputed_reduced_evals,
+                params,
+            )
+        );
+
+        for (i, &arity_bits) in params.reduction_arity_bits.iter().enumerate() {
+            let evals = &round_proof.steps[i].evals;
+
+            let coset_index_bits = x_index_bits[arity_bits..].to_vec();
+            let x_index_within_coset_bits = &x_index_bits[..arity_bits];
+            let x_index_within_coset = self.le_sum(x_index_within_coset_bits.iter());
+
+            let new_eval = self.random_access_extension(x_index_within_coset, evals.clone());
+            self.connect_extension(new_eval, old_eval);
+
+            old_eval = with_context!(
+                self,
+                "infer evaluation using interpolation",
+                self.compute_evaluation(
+                    subgroup_x,
+                    x_index_within_coset_bits,
+                    arity_bits,
+                    evals,
+                    challenges.fri_betas[i],
+                )
+            );
+
+            depth = self.add_const(depth, F::from_noncanonical_i64(-(arity_bits as i64)));
+            max_depth -= arity_bits;
+            with_context!(
+                self,
+                "verify FRI round Merkle proof.",
+                self.verify_merkle_proof_variable_depth::<C::Hasher>(
+                    flatten_target(evals),
+                    &coset_index_bits,
+                    &proof.commit_phase_merkle_caps[i],
+                    round_proof.steps[i].merkle_proof.siblings.clone(),
+                    depth,
+                    max_depth,
+                )
+            );
+
+            subgroup_x = self.exp_power_of_2(subgroup_x, arity_bits);
+
+            x_index_bits = coset_index_bits;
+        }
+
+        // The padding of the final polynomial is zero, so evaluating it in full is fine.
+        let eval = with_context!(
+            self,
+            &format!(
+                "evaluate final polynomial of length {}",
+                proof.final_poly.len()
+            ),
+            proof.final_poly.eval_scalar(self, subgroup_x)
+        );
+        self.connect_extension(eval, old_eval);
+    }
+
     /// We decompose FRI query indices into bits without verifying that the decomposition given by
     /// the prover is the canonical one. In particular, if `x_index < 2^field_bits - p`, then the
     /// prover could supply the binary encoding of either `x_index` or `x_index + p`, since the are
@@ -385,35 +654,61 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         num_leaves_per_oracle: &[usize],
         params: &FriParams,
     ) -> FriProofTarget<D> {
+        self.add_virtual_fri_proof_with_rates(
+            num_leaves_per_oracle,
+            &params.uniform_oracle_rate_bits(num_leaves_per_oracle.len()),
+            params,
+        )
+    }
+
+    /// Like `add_virtual_fri_proof`, but the `i`th initial oracle is committed with rate
+    /// `2^{-oracle_rate_bits[i]}`, which determines the length of its Merkle proofs.
+    pub fn add_virtual_fri_proof_with_rates(
+        &mut self,
+        num_leaves_per_oracle: &[usize],
+        oracle_rate_bits: &[usize],
+        params: &FriParams,
+    ) -> FriProofTarget<D> {
+        assert_eq!(num_leaves_per_oracle.len(), oracle_rate_bits.len());
         let cap_height = params.config.cap_height;
         let num_queries = params.config.num_query_rounds;
         let commit_phase_merkle_caps = (0..params.reduction_arity_bits.len())
             .map(|_| self.add_virtual_cap(cap_height))
             .collect();
         let query_round_proofs = (0..num_queries)
-            .map(|_| self.add_virtual_fri_query(num_leaves_per_oracle, params))
+            .map(|_| self.add_virtual_fri_query(num_leaves_per_oracle, oracle_rate_bits, params))
             .collect();
         let final_poly = self.add_virtual_poly_coeff_ext(params.final_poly_len());
         let pow_witness = self.add_virtual_target();
+        let round_pow_witnesses = self.add_virtual_targets(params.round_pow_bits().len());
         FriProofTarget {
             commit_phase_merkle_caps,
             query_round_proofs,
             final_poly,
             pow_witness,
+            round_pow_witnesses,
         }
     }
 
     fn add_virtual_fri_query(
         &mut self,
         num_leaves_per_oracle: &[usize],
+        oracle_rate_bits: &[usize],
         params: &FriParams,
     ) -> FriQueryRoundTarget<D> {
         let cap_height = params.config.cap_height;
-        assert!(params.lde_bits() >= cap_height);
-        let mut merkle_proof_len = params.lde_bits() - cap_height;
+        let mut merkle_proof_len = params.lde_bits().merkle_proof_len(cap_height);
 
-        let initial_trees_proof =
-            self.add_virtual_fri_initial_trees_proof(num_leaves_per_oracle, merkle_proof_len);
+        let initial_merkle_proof_lens = oracle_rate_bits
+            .iter()
+            .map(|&rate_bits| {
+                params
+                    .initial_tree_bits(rate_bits)
+                    .merkle_proof_len(cap_height)
+            })
+            .collect::<Vec<_>>();
+        let initial_trees_proof = self
+            .add_virtual_fri_initial_trees_proof(num_leaves_per_oracle, &initial_merkle_proof_lens);
 
         let mut steps = Vec::with_capacity(params.reduction_arity_bits.len());
         for &arity_bits in &params.reduction_arity_bits {
@@ -431,13 +726,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     fn add_virtual_fri_initial_trees_proof(
         &mut self,
         num_leaves_per_oracle: &[usize],
-        initial_merkle_proof_len: usize,
+        initial_merkle_proof_lens: &[usize],
     ) -> FriInitialTreeProofTarget {
         let evals_proofs = num_leaves_per_oracle
             .iter()
-            .map(|&num_oracle_leaves| {
+            .zip(initial_merkle_proof_lens)
+            .map(|(&num_oracle_leaves, &merkle_proof_len)| {
                 let leaves = self.add_virtual_targets(num_oracle_leaves);
-                let merkle_proof = self.add_virtual_merkle_proof(initial_merkle_proof_len);
+                let merkle_proof = self.add_virtual_merkle_proof(merkle_proof_len);
                 (leaves, merkle_proof)
             })
             .collect();
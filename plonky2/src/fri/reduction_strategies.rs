@@ -6,6 +6,8 @@ use serde::Serialize;
 #[cfg(feature = "timing")]
 use web_time::Instant;
 
+use crate::util::log_size::DegreeBits;
+
 /// A method for deciding what arity to use at each reduction layer.
 #[derive(Debug, Clone, Eq, PartialEq, Serialize)]
 pub enum FriReductionStrategy {
@@ -29,11 +31,12 @@ impl FriReductionStrategy {
     /// The arity of each FRI reduction step, expressed as the log2 of the actual arity.
     pub fn reduction_arity_bits(
         &self,
-        mut degree_bits: usize,
+        degree_bits: DegreeBits,
         rate_bits: usize,
         cap_height: usize,
         num_queries: usize,
     ) -> Vec<usize> {
+        let mut degree_bits = degree_bits.get();
         match self {
             FriReductionStrategy::Fixed(reduction_arity_bits) => reduction_arity_bits.to_vec(),
             &FriReductionStrategy::ConstantArityBits(arity_bits, final_poly_bits) => {
@@ -0,0 +1,294 @@
+//! Verification of a serialized FRI proof read one query round at a time, e.g. from a slow network
+//! source, stopping at the first bad round without reading the rest of the proof.
+//!
+//! The encoding of a [`FriProof`](crate::fri::proof::FriProof) starts with the commit-phase Merkle
+//! caps, followed by the query rounds, and ends with the final polynomial and the proof of work
+//! witnesses. The challenges are derived from the caps and from this tail, so the verifier must
+//! already hold them; the streamed copies are checked against them, and each round is checked in
+//! full, down to the final polynomial, as soon as it is read.
+
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::field::polynomial::PolynomialCoeffs;
+use crate::fri::proof::{FriChallenges, FriQueryRound};
+use crate::fri::structure::{FriInstanceInfo, FriOpenings};
+use crate::fri::validate_shape::validate_fri_query_round_shape;
+use crate::fri::verifier::{
+    fri_verify_cap_heights, fri_verify_final_poly, fri_verify_proofs_of_work,
+    fri_verify_query_round, FailFast, NoopFriAccessRecorder, PrecomputedReducedOpenings,
+};
+use crate::fri::FriParams;
+use crate::hash::hash_types::RichField;
+use crate::hash::merkle_tree::MerkleCap;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::GenericConfig;
+use crate::util::serialization::{IoError, IoResult, Read};
+
+/// The parts of a `FriProof` encoded after its query rounds.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FriProofTail<F: RichField + Extendable<D>, const D: usize> {
+    pub final_poly: PolynomialCoeffs<F::Extension>,
+    pub pow_witness: F,
+    pub round_pow_witnesses: Vec<F>,
+}
+
+/// Reads a `FriProof`, as encoded by `Write::write_fri_proof`, from a stream. The commit-phase
+/// Merkle caps are read on creation, then the query rounds are yielded one at a time, each being
+/// read only when requested, and [`FriProofReader::finish`] reads the rest.
+pub struct FriProofReader<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+    R: Read,
+> {
+    reader: &'a mut R,
+    common_data: &'a CommonCircuitData<F, D>,
+    commit_phase_merkle_caps: Vec<MerkleCap<F, C::Hasher>>,
+    rounds_read: usize,
+}
+
+impl<'a, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize, R: Read>
+    FriProofReader<'a, F, C, D, R>
+{
+    /// Starts reading a proof of the circuit of `common_data` from `reader`.
+    pub fn new(reader: &'a mut R, common_data: &'a CommonCircuitData<F, D>) -> IoResult<Self> {
+        let cap_height = common_data.config.fri_config.cap_height;
+        let commit_phase_merkle_caps = (0..common_data.fri_params.reduction_arity_bits.len())
+            .map(|_| reader.read_merkle_cap(cap_height))
+            .collect::<IoResult<Vec<_>>>()?;
+        Ok(Self {
+            reader,
+            common_data,
+            commit_phase_merkle_caps,
+            rounds_read: 0,
+        })
+    }
+
+    pub fn commit_phase_merkle_caps(&self) -> &[MerkleCap<F, C::Hasher>] {
+        &self.commit_phase_merkle_caps
+    }
+
+    /// Reads the final polynomial and the proof of work witnesses, which follow the query rounds.
+    /// Fails if some query rounds haven't been read.
+    pub fn finish(self) -> IoResult<FriProofTail<F, D>> {
+        let fri_params = &self.common_data.fri_params;
+        if self.rounds_read != fri_params.config.num_query_rounds {
+            return Err(IoError);
+        }
+        let final_poly = PolynomialCoeffs::new(
+            self.reader
+                .read_field_ext_vec::<F, D>(fri_params.final_poly_len())?,
+        );
+        let pow_witness = self.reader.read_field()?;
+        let round_pow_witnesses = self
+            .reader
+            .read_field_vec(fri_params.round_pow_bits().len())?;
+        Ok(FriProofTail {
+            final_poly,
+            pow_witness,
+            round_pow_witnesses,
+        })
+    }
+}
+
+impl<'a, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize, R: Read> Iterator
+    for FriProofReader<'a, F, C, D, R>
+{
+    type Item = IoResult<FriQueryRound<F, C::Hasher, D>>;
+
+    /// Reads the next query round, or returns `None` once all of them have been read.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rounds_read == self.common_data.config.fri_config.num_query_rounds {
+            return None;
+        }
+        self.rounds_read += 1;
+        Some(
+            self.reader
+                .read_fri_query_round::<F, C, D>(self.common_data),
+        )
+    }
+}
+
+/// Same as `verify_fri_proof`, but reads the proof from `reader`, checking each query round before
+/// reading the next, so that verification stops at the first bad round without reading the rest
+/// of the proof. The proofs of work are checked first, as they only depend on `challenges`.
+///
+/// `challenges` must be derived from `commit_phase_merkle_caps` and `tail`, which the streamed
+/// proof must match.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_fri_proof_streaming<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+    R: Read,
+>(
+    mut reader: FriProofReader<'_, F, C, D, R>,
+    instance: &FriInstanceInfo<F, D>,
+    openings: &FriOpenings<F, D>,
+    challenges: &FriChallenges<F, D>,
+    initial_merkle_caps: &[MerkleCap<F, C::Hasher>],
+    commit_phase_merkle_caps: &[MerkleCap<F, C::Hasher>],
+    tail: &FriProofTail<F, D>,
+    params: &FriParams,
+) -> Result<()> {
+    ensure!(
+        reader.commit_phase_merkle_caps() == commit_phase_merkle_caps,
+        "The streamed commit-phase Merkle caps differ from those of the challenges."
+    );
+    fri_verify_cap_heights(initial_merkle_caps, commit_phase_merkle_caps, params)
+        .map_err(anyhow::Error::msg)?;
+    fri_verify_final_poly::<F, D>(&tail.final_poly, params).map_err(anyhow::Error::msg)?;
+    // A final PoW witness which is not observed must be zero, as in `validate_fri_proof_shape`.
+    ensure!(params.config.pow_strategy.final_bits().is_some() || tail.pow_witness.is_zero());
+    fri_verify_proofs_of_work::<F, D>(challenges, params)?;
+    ensure!(
+        challenges.fri_query_indices.len() == params.config.num_query_rounds,
+        "Number of query indices does not match config."
+    );
+
+    let precomputed_reduced_evals =
+        PrecomputedReducedOpenings::from_os_and_alpha(openings, challenges.fri_alpha);
+    for &x_index in &challenges.fri_query_indices {
+        let round_proof = reader
+            .next()
+            .ok_or_else(|| anyhow!("The proof has too few query rounds."))?
+            .map_err(anyhow::Error::msg)?;
+        validate_fri_query_round_shape(&round_proof, instance, params)?;
+        fri_verify_query_round::<F, C::Hasher, D, D, _, _>(
+            instance,
+            challenges.fri_alpha,
+            &challenges.fri_betas,
+            &precomputed_reduced_evals,
+            initial_merkle_caps,
+            commit_phase_merkle_caps,
+            &tail.final_poly,
+            x_index,
+            &round_proof,
+            params,
+            &mut NoopFriAccessRecorder,
+            &mut FailFast,
+        )
+        .map_err(anyhow::Error::msg)?;
+    }
+
+    ensure!(
+        &reader.finish().map_err(anyhow::Error::msg)? == tail,
+        "The streamed final polynomial or proof of work witnesses differ from those of the \
+         challenges."
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::gates::noop::NoopGate;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::util::serialization::{Buffer, Write};
+
+    #[test]
+    fn test_corrupt_round_stops_reading() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        // Enough gates for the proof to have commit-phase reductions.
+        while builder.num_gates() < 1 << 12 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        assert!(!data.common.fri_params.reduction_arity_bits.is_empty());
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5));
+        let proof = data.prove(pw)?;
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        let initial_merkle_caps = [
+            data.verifier_only.constants_sigmas_cap.clone(),
+            proof.proof.wires_cap.clone(),
+            proof.proof.plonk_zs_partial_products_cap.clone(),
+            proof.proof.quotient_polys_cap.clone(),
+        ];
+        let instance = data.common.get_fri_instance(challenges.plonk_zeta);
+        let openings = proof.proof.openings.to_fri_openings();
+        let fri_proof = &proof.proof.opening_proof;
+        let tail = FriProofTail {
+            final_poly: fri_proof.final_poly.clone(),
+            pow_witness: fri_proof.pow_witness,
+            round_pow_witnesses: fri_proof.round_pow_witnesses.clone(),
+        };
+        let verify = |buffer: &mut Buffer| {
+            verify_fri_proof_streaming::<F, C, D, _>(
+                FriProofReader::new(buffer, &data.common).map_err(anyhow::Error::msg)?,
+                &instance,
+                &openings,
+                &challenges.fri_challenges,
+                &initial_merkle_caps,
+                &fri_proof.commit_phase_merkle_caps,
+                &tail,
+                &data.common.fri_params,
+            )
+        };
+        let write = |fri_proof: &_| {
+            let mut bytes = Vec::new();
+            bytes.write_fri_proof::<F, C, D>(fri_proof).unwrap();
+            bytes
+        };
+
+        let bytes = write(fri_proof);
+        let mut buffer = Buffer::new(&bytes);
+        verify(&mut buffer)?;
+        assert_eq!(buffer.pos(), bytes.len());
+
+        // The end of the first query round.
+        let mut buffer = Buffer::new(&bytes);
+        let mut reader = FriProofReader::<F, C, D, _>::new(&mut buffer, &data.common).unwrap();
+        reader.next().unwrap().unwrap();
+        let first_round_end = buffer.pos();
+
+        let mut corrupt_proof = fri_proof.clone();
+        corrupt_proof.query_round_proofs[0]
+            .initial_trees_proof
+            .evals_proofs[1]
+            .0[0] += F::ONE;
+        let corrupt_bytes = write(&corrupt_proof);
+        let mut buffer = Buffer::new(&corrupt_bytes);
+        assert!(verify(&mut buffer).is_err());
+        assert_eq!(buffer.pos(), first_round_end);
+        assert!(first_round_end < corrupt_bytes.len());
+
+        // Streamed caps or tail other than those the challenges were derived from are rejected,
+        // even where the rest of the proof agrees with them.
+        let mut tampered_cap = fri_proof.clone();
+        tampered_cap.commit_phase_merkle_caps[0].0[0].elements[0] += F::ONE;
+        let mut tampered_final_poly = fri_proof.clone();
+        tampered_final_poly.final_poly.coeffs[0] += <F as Extendable<D>>::Extension::ONE;
+        let mut tampered_pow_witness = fri_proof.clone();
+        tampered_pow_witness.pow_witness += F::ONE;
+        for tampered in [tampered_cap, tampered_final_poly, tampered_pow_witness] {
+            assert!(verify(&mut Buffer::new(&write(&tampered))).is_err());
+        }
+
+        Ok(())
+    }
+}
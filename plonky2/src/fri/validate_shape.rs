@@ -1,12 +1,12 @@
 use anyhow::ensure;
 
 use crate::field::extension::Extendable;
-use crate::fri::proof::{FriProof, FriQueryRound, FriQueryStep};
+use crate::fri::proof::{FriInitialTreeProof, FriProof, FriQueryRound, FriQueryStep};
 use crate::fri::structure::FriInstanceInfo;
 use crate::fri::FriParams;
 use crate::hash::hash_types::RichField;
-use crate::plonk::config::GenericConfig;
-use crate::plonk::plonk_common::salt_size;
+use crate::plonk::config::{GenericConfig, Hasher};
+use crate::util::log_size::LdeBits;
 
 pub(crate) fn validate_fri_proof_shape<F, C, const D: usize>(
     proof: &FriProof<F, C::Hasher, D>,
@@ -21,47 +21,217 @@ where
         commit_phase_merkle_caps,
         query_round_proofs,
         final_poly,
-        pow_witness: _pow_witness,
+        pow_witness,
+        round_pow_witnesses,
     } = proof;
 
+    // A final PoW witness which is not observed must be zero, so that proofs are not malleable.
+    ensure!(params.config.pow_strategy.final_bits().is_some() || pow_witness.is_zero());
+    ensure!(round_pow_witnesses.len() == params.round_pow_bits().len());
+
     let cap_height = params.config.cap_height;
     for cap in commit_phase_merkle_caps {
-        ensure!(cap.height() == cap_height);
+        ensure!(cap.len() == 1 << cap_height);
     }
 
     for query_round in query_round_proofs {
-        let FriQueryRound {
-            initial_trees_proof,
-            steps,
-        } = query_round;
+        validate_fri_query_round_shape(query_round, instance, params)?;
+    }
 
-        ensure!(initial_trees_proof.evals_proofs.len() == instance.oracles.len());
-        for ((leaf, merkle_proof), oracle) in initial_trees_proof
-            .evals_proofs
-            .iter()
-            .zip(&instance.oracles)
-        {
-            ensure!(leaf.len() == oracle.num_polys + salt_size(oracle.blinding && params.hiding));
-            ensure!(merkle_proof.len() + cap_height == params.lde_bits());
-        }
+    ensure!(final_poly.len() == params.final_poly_len());
+
+    Ok(())
+}
+
+/// Checks the shape of a single query round, as `validate_fri_proof_shape` does for each of them.
+pub(crate) fn validate_fri_query_round_shape<F, H, const D: usize>(
+    query_round: &FriQueryRound<F, H, D>,
+    instance: &FriInstanceInfo<F, D>,
+    params: &FriParams,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    H: Hasher<F>,
+{
+    let FriQueryRound {
+        initial_trees_proof,
+        steps,
+    } = query_round;
 
-        ensure!(steps.len() == params.reduction_arity_bits.len());
-        let mut codeword_len_bits = params.lde_bits();
-        for (step, arity_bits) in steps.iter().zip(&params.reduction_arity_bits) {
-            let FriQueryStep {
-                evals,
-                merkle_proof,
-            } = step;
+    validate_initial_trees_proof_shape(
+        initial_trees_proof,
+        instance,
+        params,
+        &params.uniform_oracle_rate_bits(instance.oracles.len()),
+    )?;
 
-            let arity = 1 << arity_bits;
-            codeword_len_bits -= arity_bits;
+    let cap_height = params.config.cap_height;
+    ensure!(steps.len() == params.reduction_arity_bits.len());
+    let mut codeword_len_bits = params.lde_bits();
+    for (step, arity_bits) in steps.iter().zip(&params.reduction_arity_bits) {
+        let FriQueryStep {
+            evals,
+            merkle_proof,
+        } = step;
 
-            ensure!(evals.len() == arity);
-            ensure!(merkle_proof.len() + cap_height == codeword_len_bits);
-        }
+        let arity = 1 << arity_bits;
+        codeword_len_bits -= *arity_bits;
+
+        ensure!(evals.len() == arity);
+        ensure!(LdeBits::new(merkle_proof.len() + cap_height) == codeword_len_bits);
     }
 
-    ensure!(final_poly.len() == params.final_poly_len());
+    Ok(())
+}
+
+/// Checks the shape of the initial trees' openings, where the `i`th oracle was committed with rate
+/// `2^{-oracle_rate_bits[i]}` and so has a Merkle tree of height `degree_bits + oracle_rate_bits[i]`.
+pub fn validate_initial_trees_proof_shape<F, H, const D: usize>(
+    initial_trees_proof: &FriInitialTreeProof<F, H>,
+    instance: &FriInstanceInfo<F, D>,
+    params: &FriParams,
+    oracle_rate_bits: &[usize],
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    H: Hasher<F>,
+{
+    let cap_height = params.config.cap_height;
+    initial_trees_proof
+        .validate_salt(&instance.oracles, params.hiding)
+        .map_err(anyhow::Error::msg)?;
+    ensure!(oracle_rate_bits.len() == instance.oracles.len());
+    for ((_, merkle_proof), &rate_bits) in initial_trees_proof
+        .evals_proofs
+        .iter()
+        .zip(oracle_rate_bits)
+    {
+        ensure!(
+            LdeBits::new(merkle_proof.len() + cap_height) == params.initial_tree_bits(rate_bits)
+        );
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{vec, vec::Vec};
+
+    use super::*;
+    use crate::field::polynomial::PolynomialCoeffs;
+    use crate::field::types::Sample;
+    use crate::fri::oracle::PolynomialBatch;
+    use crate::fri::reduction_strategies::FriReductionStrategy;
+    use crate::fri::structure::FriOracleInfo;
+    use crate::fri::{FriConfig, FriPowStrategy};
+    use crate::hash::merkle_proofs::verify_merkle_proof_to_cap;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::util::log_size::DegreeBits;
+    use crate::util::timing::TimingTree;
+
+    #[test]
+    fn test_initial_trees_with_distinct_rates() -> anyhow::Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let degree_bits = 4;
+        let cap_height = 1;
+        let oracle_rate_bits = [3, 1];
+        let params = FriConfig {
+            rate_bits: 3,
+            cap_height,
+            pow_strategy: FriPowStrategy::None,
+            reduction_strategy: FriReductionStrategy::Fixed(vec![]),
+            num_query_rounds: 1,
+            commit_final_poly: false,
+        }
+        .fri_params(DegreeBits::new(degree_bits), false);
+
+        let num_polys = [2, 3];
+        let batches = num_polys
+            .iter()
+            .zip(oracle_rate_bits)
+            .map(|(&n, rate_bits)| {
+                let polys = (0..n)
+                    .map(|_| PolynomialCoeffs::new(F::rand_vec(1 << degree_bits)))
+                    .collect();
+                PolynomialBatch::<F, C, D>::from_coeffs(
+                    polys,
+                    rate_bits,
+                    false,
+                    cap_height,
+                    &mut TimingTree::default(),
+                    None,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let leaf_index = 5;
+        let initial_trees_proof = FriInitialTreeProof::<F, H> {
+            evals_proofs: batches
+                .iter()
+                .map(|b| {
+                    (
+                        b.merkle_tree.get(leaf_index).to_vec(),
+                        b.merkle_tree.prove(leaf_index),
+                    )
+                })
+                .collect(),
+        };
+        for (b, (leaf, proof)) in batches.iter().zip(&initial_trees_proof.evals_proofs) {
+            verify_merkle_proof_to_cap(leaf.clone(), leaf_index, &b.merkle_tree.cap, proof)?;
+        }
+
+        let instance = FriInstanceInfo::<F, D> {
+            oracles: num_polys
+                .iter()
+                .map(|&num_polys| FriOracleInfo {
+                    num_polys,
+                    blinding: false,
+                })
+                .collect(),
+            batches: vec![],
+        };
+        validate_initial_trees_proof_shape(
+            &initial_trees_proof,
+            &instance,
+            &params,
+            &oracle_rate_bits,
+        )?;
+        // The single-rate shape expects both trees to have the full LDE height.
+        assert!(validate_initial_trees_proof_shape(
+            &initial_trees_proof,
+            &instance,
+            &params,
+            &params.uniform_oracle_rate_bits(2),
+        )
+        .is_err());
+
+        let json = serde_json::to_string(&initial_trees_proof).map_err(anyhow::Error::msg)?;
+        let roundtrip: FriInitialTreeProof<F, H> =
+            serde_json::from_str(&json).map_err(anyhow::Error::msg)?;
+        assert_eq!(roundtrip, initial_trees_proof);
+
+        // The in-circuit proof targets have the same shape.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let proof_target =
+            builder.add_virtual_fri_proof_with_rates(&num_polys, &oracle_rate_bits, &params);
+        for ((leaf_t, proof_t), (leaf, proof)) in proof_target.query_round_proofs[0]
+            .initial_trees_proof
+            .evals_proofs
+            .iter()
+            .zip(&initial_trees_proof.evals_proofs)
+        {
+            assert_eq!(leaf_t.len(), leaf.len());
+            assert_eq!(proof_t.siblings.len(), proof.len());
+        }
+
+        Ok(())
+    }
+}
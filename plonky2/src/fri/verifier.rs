@@ -1,63 +1,277 @@
 use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
 
 use anyhow::{ensure, Result};
 
 use crate::field::extension::{flatten, Extendable, FieldExtension};
-use crate::field::interpolation::{barycentric_weights, interpolate};
+use crate::field::polynomial::PolynomialCoeffs;
 use crate::field::types::Field;
+use crate::fri::folding::compute_evaluation;
 use crate::fri::proof::{FriChallenges, FriInitialTreeProof, FriProof, FriQueryRound};
-use crate::fri::structure::{FriBatchInfo, FriInstanceInfo, FriOpenings};
+use crate::fri::structure::{FriBatchInfo, FriInstanceInfo, FriOpeningBatch, FriOpenings};
 use crate::fri::validate_shape::validate_fri_proof_shape;
-use crate::fri::{FriConfig, FriParams};
+use crate::fri::FriParams;
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_proofs::verify_merkle_proof_to_cap;
 use crate::hash::merkle_tree::MerkleCap;
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::util::reducing::ReducingFactor;
-use crate::util::{log2_strict, reverse_bits, reverse_index_bits_in_place};
+use crate::util::{log2_strict, reverse_bits};
 
-/// Computes P'(x^arity) from {P(x*g^i)}_(i=0..arity), where g is a `arity`-th root of unity
-/// and P' is the FRI reduced polynomial.
-pub(crate) fn compute_evaluation<F: Field + Extendable<D>, const D: usize>(
-    x: F,
-    x_index_within_coset: usize,
-    arity_bits: usize,
-    evals: &[F::Extension],
-    beta: F::Extension,
-) -> F::Extension {
-    let arity = 1 << arity_bits;
-    debug_assert_eq!(evals.len(), arity);
-
-    let g = F::primitive_root_of_unity(arity_bits);
-
-    // The evaluation vector needs to be reordered first.
-    let mut evals = evals.to_vec();
-    reverse_index_bits_in_place(&mut evals);
-    let rev_x_index_within_coset = reverse_bits(x_index_within_coset, arity_bits);
-    let coset_start = x * g.exp_u64((arity - rev_x_index_within_coset) as u64);
-    // The answer is gotten by interpolating {(x*g^i, P(x*g^i))} and evaluating at beta.
-    let points = g
-        .powers()
-        .map(|y| (coset_start * y).into())
-        .zip(evals)
-        .collect::<Vec<_>>();
-    let barycentric_weights = barycentric_weights(&points);
-    interpolate(&points, beta, &barycentric_weights)
+/// The number of leading zero bits of `fri_pow_response`, not counting those which every field
+/// element has.
+pub(crate) fn fri_pow_response_bits<F: RichField>(fri_pow_response: F) -> u32 {
+    fri_pow_response.to_canonical_u64().leading_zeros() - (64 - F::order().bits()) as u32
 }
 
 pub(crate) fn fri_verify_proof_of_work<F: RichField + Extendable<D>, const D: usize>(
     fri_pow_response: F,
-    config: &FriConfig,
+    pow_bits: u32,
 ) -> Result<()> {
     ensure!(
-        fri_pow_response.to_canonical_u64().leading_zeros()
-            >= config.proof_of_work_bits + (64 - F::order().bits()) as u32,
+        fri_pow_response_bits(fri_pow_response) >= pow_bits,
         "Invalid proof of work witness."
     );
 
     Ok(())
 }
 
+/// An error found while checking the FRI final polynomial.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FriError {
+    /// The final polynomial has a nonzero coefficient at index `degree >= max_len`, so it does
+    /// not have the degree expected after the reductions.
+    FinalPolyTooLong { max_len: usize, degree: usize },
+    /// The final polynomial's coefficient vector has `len` entries rather than `expected_len`.
+    FinalPolyMismatch { expected_len: usize, len: usize },
+    /// In the query round starting at `query_index`, the evaluation folded from the last
+    /// reduction step's evaluations disagrees with the final polynomial.
+    FinalReductionMismatch { query_index: usize },
+    /// The initial trees proof opens `len` oracles rather than `expected_len`.
+    InitialOracleCountMismatch { expected_len: usize, len: usize },
+    /// The opening of the oracle at `oracle_index` has `len` evaluations, rather than the
+    /// `expected_len` unsalted evaluations followed by its salt, so its salt was stripped or
+    /// duplicated.
+    SaltMismatch {
+        oracle_index: usize,
+        expected_len: usize,
+        len: usize,
+    },
+    /// The proof has no commit-phase Merkle cap for the given reduction.
+    MissingCap(usize),
+    /// The proof's shape doesn't match the parameters, see `validate_fri_proof_shape`.
+    InvalidShape,
+    /// The final proof of work response has too few leading zeros.
+    ProofOfWork,
+    /// The proof of work response of the given commit-phase reduction has too few leading zeros.
+    RoundProofOfWork { reduction: usize },
+    /// The proof has `len` query rounds rather than `expected_len`.
+    QueryRoundCountMismatch { expected_len: usize, len: usize },
+    /// In the query round starting at `query_index`, the Merkle proof of the initial oracle at
+    /// `oracle_index` doesn't open to its cap.
+    InitialMerkleProof {
+        query_index: usize,
+        oracle_index: usize,
+    },
+    /// In the query round starting at `query_index`, the evaluations of the given reduction step
+    /// disagree with the evaluation folded by the previous step.
+    FoldMismatch {
+        query_index: usize,
+        reduction: usize,
+    },
+    /// In the query round starting at `query_index`, the Merkle proof of the given reduction
+    /// step's evaluations doesn't open to its cap.
+    CommitPhaseMerkleProof {
+        query_index: usize,
+        reduction: usize,
+    },
+    /// The Merkle cap at `cap_index`, counting the initial oracles' caps followed by the
+    /// commit-phase caps, has `got` entries rather than the `expected` `2^cap_height`.
+    WrongCapHeight {
+        cap_index: usize,
+        got: usize,
+        expected: usize,
+    },
+}
+
+impl Display for FriError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FinalPolyTooLong { max_len, degree } => write!(
+                f,
+                "FRI final polynomial has degree {degree}, expected fewer than {max_len} coefficients"
+            ),
+            Self::FinalPolyMismatch { expected_len, len } => write!(
+                f,
+                "FRI final polynomial has {len} coefficients, expected {expected_len}"
+            ),
+            Self::FinalReductionMismatch { query_index } => write!(
+                f,
+                "FRI final polynomial disagrees with the last reduction of query index {query_index}"
+            ),
+            Self::InitialOracleCountMismatch { expected_len, len } => write!(
+                f,
+                "FRI initial trees proof opens {len} oracles, expected {expected_len}"
+            ),
+            Self::SaltMismatch {
+                oracle_index,
+                expected_len,
+                len,
+            } => write!(
+                f,
+                "FRI initial oracle {oracle_index} has {len} salted evaluations, expected {expected_len}"
+            ),
+            Self::MissingCap(reduction) => {
+                write!(f, "FRI proof has no commit-phase Merkle cap for reduction {reduction}")
+            }
+            Self::InvalidShape => write!(f, "FRI proof has an invalid shape"),
+            Self::ProofOfWork => write!(f, "Invalid proof of work witness"),
+            Self::RoundProofOfWork { reduction } => write!(
+                f,
+                "Invalid proof of work witness for reduction {reduction}"
+            ),
+            Self::QueryRoundCountMismatch { expected_len, len } => write!(
+                f,
+                "FRI proof has {len} query rounds, expected {expected_len}"
+            ),
+            Self::InitialMerkleProof {
+                query_index,
+                oracle_index,
+            } => write!(
+                f,
+                "Invalid Merkle proof for initial oracle {oracle_index} at query index {query_index}"
+            ),
+            Self::FoldMismatch {
+                query_index,
+                reduction,
+            } => write!(
+                f,
+                "FRI reduction {reduction} disagrees with the previous fold of query index {query_index}"
+            ),
+            Self::CommitPhaseMerkleProof {
+                query_index,
+                reduction,
+            } => write!(
+                f,
+                "Invalid Merkle proof for reduction {reduction} at query index {query_index}"
+            ),
+            Self::WrongCapHeight {
+                cap_index,
+                got,
+                expected,
+            } => write!(
+                f,
+                "Merkle cap {cap_index} has {got} entries, expected {expected}"
+            ),
+        }
+    }
+}
+
+/// Checks that the final polynomial has exactly `params.final_poly_len()` coefficients. Nonzero
+/// coefficients past that length are reported as a degree violation rather than a length mismatch.
+pub fn fri_verify_final_poly<F: RichField + Extendable<D>, const D: usize>(
+    final_poly: &PolynomialCoeffs<F::Extension>,
+    params: &FriParams,
+) -> Result<(), FriError> {
+    let expected_len = params.final_poly_len();
+    let len = final_poly.len();
+    if let Some(degree) = final_poly
+        .coeffs
+        .iter()
+        .rposition(|c| *c != F::Extension::ZERO)
+        .filter(|&degree| degree >= expected_len)
+    {
+        return Err(FriError::FinalPolyTooLong {
+            max_len: expected_len,
+            degree,
+        });
+    }
+    if len != expected_len {
+        return Err(FriError::FinalPolyMismatch { expected_len, len });
+    }
+    Ok(())
+}
+
+/// Checks that every Merkle cap, i.e. the initial oracles' caps followed by the commit-phase caps,
+/// has `2^cap_height` entries. The sibling counts of the Merkle proofs, which also depend on the
+/// cap height, are checked against the config by `validate_fri_proof_shape`.
+pub fn fri_verify_cap_heights<F: RichField, H: Hasher<F>>(
+    initial_merkle_caps: &[MerkleCap<F, H>],
+    commit_phase_merkle_caps: &[MerkleCap<F, H>],
+    params: &FriParams,
+) -> Result<(), FriError> {
+    let expected = 1 << params.config.cap_height;
+    for (cap_index, cap) in initial_merkle_caps
+        .iter()
+        .chain(commit_phase_merkle_caps)
+        .enumerate()
+    {
+        if cap.len() != expected {
+            return Err(FriError::WrongCapHeight {
+                cap_index,
+                got: cap.len(),
+                expected,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the final polynomial, evaluated at `x`, matches `folded_eval`, the evaluation which
+/// the query round starting at `query_index` folded from its last reduction step's evaluations.
+pub fn fri_verify_final_reduction<F: RichField + Extendable<D>, const D: usize>(
+    final_poly: &PolynomialCoeffs<F::Extension>,
+    x: F,
+    folded_eval: F::Extension,
+    query_index: usize,
+) -> Result<(), FriError> {
+    if final_poly.eval(x.into()) != folded_eval {
+        return Err(FriError::FinalReductionMismatch { query_index });
+    }
+    Ok(())
+}
+
+/// A Merkle tree read during FRI verification.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum FriTree {
+    /// One of the initial oracles, identified by its index in `initial_merkle_caps`.
+    Initial(usize),
+    /// The tree committed in the given commit-phase reduction step.
+    CommitPhase(usize),
+}
+
+/// Observes the Merkle tree accesses made by the FRI verifier, e.g. to profile cache behavior.
+///
+/// Accesses are reported in verification order: for each query round, the initial trees in oracle
+/// order, then each reduction step. Every leaf access is followed by its siblings, from the leaf
+/// layer up to the cap.
+pub trait FriAccessRecorder {
+    /// Called when the leaf at `leaf_index` of `tree` is read.
+    fn record_leaf(&mut self, _tree: FriTree, _leaf_index: usize) {}
+
+    /// Called when a Merkle path sibling is read. `layer` counts up from the leaves (layer 0), and
+    /// `index` is the sibling's position within that layer.
+    fn record_sibling(&mut self, _tree: FriTree, _layer: usize, _index: usize) {}
+}
+
+/// A `FriAccessRecorder` which ignores all accesses.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoopFriAccessRecorder;
+
+impl FriAccessRecorder for NoopFriAccessRecorder {}
+
+fn record_merkle_path<R: FriAccessRecorder>(
+    recorder: &mut R,
+    tree: FriTree,
+    leaf_index: usize,
+    num_siblings: usize,
+) {
+    recorder.record_leaf(tree, leaf_index);
+    for layer in 0..num_siblings {
+        recorder.record_sibling(tree, layer, (leaf_index >> layer) ^ 1);
+    }
+}
+
 pub fn verify_fri_proof<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -70,89 +284,389 @@ pub fn verify_fri_proof<
     proof: &FriProof<F, C::Hasher, D>,
     params: &FriParams,
 ) -> Result<()> {
-    validate_fri_proof_shape::<F, C, D>(proof, instance, params)?;
+    verify_fri_proof_recorded::<F, C, D, _>(
+        instance,
+        openings,
+        challenges,
+        initial_merkle_caps,
+        proof,
+        params,
+        &mut NoopFriAccessRecorder,
+    )
+}
+
+/// Same as `verify_fri_proof`, but reports every Merkle leaf and sibling access to `recorder`.
+pub fn verify_fri_proof_recorded<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+    R: FriAccessRecorder,
+>(
+    instance: &FriInstanceInfo<F, D>,
+    openings: &FriOpenings<F, D>,
+    challenges: &FriChallenges<F, D>,
+    initial_merkle_caps: &[MerkleCap<F, C::Hasher>],
+    proof: &FriProof<F, C::Hasher, D>,
+    params: &FriParams,
+    recorder: &mut R,
+) -> Result<()> {
+    fri_verify_proof_prelude::<F, C, D>(instance, challenges, initial_merkle_caps, proof, params)?;
 
-    // Size of the LDE domain.
-    let n = params.lde_size();
+    let precomputed_reduced_evals =
+        PrecomputedReducedOpenings::from_os_and_alpha(openings, challenges.fri_alpha);
+    for (&query_index, round_proof) in challenges
+        .fri_query_indices
+        .iter()
+        .zip(&proof.query_round_proofs)
+    {
+        fri_verify_query_round::<F, C::Hasher, D, D, R, _>(
+            instance,
+            challenges.fri_alpha,
+            &challenges.fri_betas,
+            &precomputed_reduced_evals,
+            initial_merkle_caps,
+            &proof.commit_phase_merkle_caps,
+            &proof.final_poly,
+            query_index,
+            round_proof,
+            params,
+            recorder,
+            &mut FailFast,
+        )
+        .map_err(anyhow::Error::msg)?;
+    }
 
-    // Check PoW.
-    fri_verify_proof_of_work(challenges.fri_pow_response, &params.config)?;
+    Ok(())
+}
 
-    // Check that parameters are coherent.
-    ensure!(
-        params.config.num_query_rounds == proof.query_round_proofs.len(),
-        "Number of query rounds does not match config."
+/// Same as `verify_fri_proof`, but rather than stopping at the first failing check, runs all of
+/// them and returns every failure, in verification order. An empty result means the proof is valid.
+///
+/// Within a query round, folding carries on from the evaluation derived from the proof after a
+/// mismatch, so each bad reduction step is reported on its own. Only a proof with an invalid
+/// shape stops verification early, as its query rounds can't be read.
+///
+/// This does more work than `verify_fri_proof` on invalid proofs, and is meant for debugging
+/// provers.
+pub fn verify_fri_proof_collect_errors<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    instance: &FriInstanceInfo<F, D>,
+    openings: &FriOpenings<F, D>,
+    challenges: &FriChallenges<F, D>,
+    initial_merkle_caps: &[MerkleCap<F, C::Hasher>],
+    proof: &FriProof<F, C::Hasher, D>,
+    params: &FriParams,
+) -> Vec<FriError> {
+    let mut errors = Vec::new();
+
+    errors.extend(
+        fri_verify_cap_heights(initial_merkle_caps, &proof.commit_phase_merkle_caps, params).err(),
+    );
+    errors.extend(fri_verify_final_poly::<F, D>(&proof.final_poly, params).err());
+    errors.extend(
+        (0..params.reduction_arity_bits.len()).filter_map(|reduction| proof.cap(reduction).err()),
     );
+    errors.extend(proof.query_round_proofs.iter().find_map(|round| {
+        round
+            .initial_trees_proof
+            .validate_salt(&instance.oracles, params.hiding)
+            .err()
+    }));
+    if validate_fri_proof_shape::<F, C, D>(proof, instance, params).is_err() {
+        errors.push(FriError::InvalidShape);
+        return errors;
+    }
+
+    if let Some(pow_bits) = params.config.pow_strategy.final_bits() {
+        if fri_verify_proof_of_work::<F, D>(challenges.fri_pow_response, pow_bits).is_err() {
+            errors.push(FriError::ProofOfWork);
+        }
+    }
+    for (reduction, (&response, &pow_bits)) in challenges
+        .fri_round_pow_responses
+        .iter()
+        .zip(params.round_pow_bits())
+        .enumerate()
+    {
+        if fri_verify_proof_of_work::<F, D>(response, pow_bits).is_err() {
+            errors.push(FriError::RoundProofOfWork { reduction });
+        }
+    }
+    if params.config.num_query_rounds != proof.query_round_proofs.len() {
+        errors.push(FriError::QueryRoundCountMismatch {
+            expected_len: params.config.num_query_rounds,
+            len: proof.query_round_proofs.len(),
+        });
+    }
 
     let precomputed_reduced_evals =
         PrecomputedReducedOpenings::from_os_and_alpha(openings, challenges.fri_alpha);
-    for (&x_index, round_proof) in challenges
+    for (&query_index, round_proof) in challenges
         .fri_query_indices
         .iter()
         .zip(&proof.query_round_proofs)
     {
-        fri_verifier_query_round::<F, C, D>(
+        // Collecting the errors never stops verification, so this always succeeds.
+        let _ = fri_verify_query_round::<F, C::Hasher, D, D, _, _>(
             instance,
-            challenges,
+            challenges.fri_alpha,
+            &challenges.fri_betas,
             &precomputed_reduced_evals,
             initial_merkle_caps,
-            proof,
-            x_index,
-            n,
+            &proof.commit_phase_merkle_caps,
+            &proof.final_poly,
+            query_index,
             round_proof,
             params,
-        )?;
+            &mut NoopFriAccessRecorder,
+            &mut errors,
+        );
     }
 
-    Ok(())
+    errors
 }
 
-fn fri_verify_initial_proof<F: RichField, H: Hasher<F>>(
-    x_index: usize,
-    proof: &FriInitialTreeProof<F, H>,
-    initial_merkle_caps: &[MerkleCap<F, H>],
+/// Embeds an element of the degree `D` extension of `F` into its degree `E` extension.
+///
+/// Both extensions must be binomial with the same `W`, and `D` must divide `E`, so that the
+/// generator `x` of the smaller one (with `x^D = W`) can be sent to `y^(E / D)`, where `y` generates
+/// the larger one. This is a field homomorphism, so any identity which holds in the degree `D`
+/// extension also holds after lifting.
+pub fn lift_extension<F: Extendable<D> + Extendable<E>, const D: usize, const E: usize>(
+    x: <F as Extendable<D>>::Extension,
+) -> <F as Extendable<E>>::Extension {
+    assert_eq!(
+        E % D,
+        0,
+        "Cannot embed a degree {D} extension into a degree {E} one."
+    );
+    assert!(
+        D == 1 || <F as Extendable<D>>::W == <F as Extendable<E>>::W,
+        "The degree {D} and degree {E} extensions are defined by different W."
+    );
+    let stride = E / D;
+    let mut arr = [F::ZERO; E];
+    for (i, c) in x.to_basefield_array().into_iter().enumerate() {
+        arr[i * stride] = c;
+    }
+    <F as Extendable<E>>::Extension::from_basefield_array(arr)
+}
+
+/// Same as `verify_fri_proof`, but runs all extension field arithmetic in the degree `E`
+/// extension, into which the proof's degree `D` challenges, openings and commit-phase evaluations
+/// are lifted with `lift_extension`. Merkle proofs are still checked against the original
+/// base-field leaves, so the commitments are untouched.
+///
+/// Goldilocks has no cubic extension, so in practice `E` is a multiple of `D` such as `D = 2`,
+/// `E = 4`.
+pub fn verify_fri_proof_cross_degree<
+    F: RichField + Extendable<D> + Extendable<E>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+    const E: usize,
+>(
+    instance: &FriInstanceInfo<F, D>,
+    openings: &FriOpenings<F, D>,
+    challenges: &FriChallenges<F, D>,
+    initial_merkle_caps: &[MerkleCap<F, C::Hasher>],
+    proof: &FriProof<F, C::Hasher, D>,
+    params: &FriParams,
 ) -> Result<()> {
-    for ((evals, merkle_proof), cap) in proof.evals_proofs.iter().zip(initial_merkle_caps) {
-        verify_merkle_proof_to_cap::<F, H>(evals.clone(), x_index, cap, merkle_proof)?;
+    fri_verify_proof_prelude::<F, C, D>(instance, challenges, initial_merkle_caps, proof, params)?;
+
+    let lift = lift_extension::<F, D, E>;
+    let lifted_instance = FriInstanceInfo::<F, E> {
+        oracles: instance.oracles.clone(),
+        batches: instance
+            .batches
+            .iter()
+            .map(|batch| FriBatchInfo {
+                point: lift(batch.point),
+                polynomials: batch.polynomials.clone(),
+            })
+            .collect(),
+    };
+    let lifted_openings = FriOpenings::<F, E> {
+        batches: openings
+            .batches
+            .iter()
+            .map(|batch| FriOpeningBatch {
+                values: batch.values.iter().copied().map(lift).collect(),
+            })
+            .collect(),
+    };
+    let alpha = lift(challenges.fri_alpha);
+    let betas = challenges
+        .fri_betas
+        .iter()
+        .copied()
+        .map(lift)
+        .collect::<Vec<_>>();
+    let final_poly =
+        PolynomialCoeffs::new(proof.final_poly.coeffs.iter().copied().map(lift).collect());
+    let precomputed_reduced_evals =
+        PrecomputedReducedOpenings::<F, E>::from_os_and_alpha(&lifted_openings, alpha);
+
+    for (&query_index, round_proof) in challenges
+        .fri_query_indices
+        .iter()
+        .zip(&proof.query_round_proofs)
+    {
+        fri_verify_query_round::<F, C::Hasher, D, E, _, _>(
+            &lifted_instance,
+            alpha,
+            &betas,
+            &precomputed_reduced_evals,
+            initial_merkle_caps,
+            &proof.commit_phase_merkle_caps,
+            &final_poly,
+            query_index,
+            round_proof,
+            params,
+            &mut NoopFriAccessRecorder,
+            &mut FailFast,
+        )
+        .map_err(anyhow::Error::msg)?;
     }
 
     Ok(())
 }
 
-pub(crate) fn fri_combine_initial<
+/// Checks everything about a FRI proof which doesn't depend on the query rounds: the final
+/// polynomial's length, the proof's shape, and the proof of work responses.
+fn fri_verify_proof_prelude<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
     const D: usize,
 >(
     instance: &FriInstanceInfo<F, D>,
-    proof: &FriInitialTreeProof<F, C::Hasher>,
+    challenges: &FriChallenges<F, D>,
+    initial_merkle_caps: &[MerkleCap<F, C::Hasher>],
+    proof: &FriProof<F, C::Hasher, D>,
+    params: &FriParams,
+) -> Result<()> {
+    fri_verify_cap_heights(initial_merkle_caps, &proof.commit_phase_merkle_caps, params)
+        .map_err(anyhow::Error::msg)?;
+    fri_verify_final_poly::<F, D>(&proof.final_poly, params).map_err(anyhow::Error::msg)?;
+    for reduction in 0..params.reduction_arity_bits.len() {
+        proof.cap(reduction).map_err(anyhow::Error::msg)?;
+    }
+    validate_fri_proof_shape::<F, C, D>(proof, instance, params)?;
+    fri_verify_proofs_of_work::<F, D>(challenges, params)?;
+
+    // Check that parameters are coherent.
+    ensure!(
+        params.config.num_query_rounds == proof.query_round_proofs.len(),
+        "Number of query rounds does not match config."
+    );
+
+    Ok(())
+}
+
+/// Checks the final and commit-phase proof of work responses. They are part of the challenges, so
+/// this doesn't need the proof.
+pub(crate) fn fri_verify_proofs_of_work<F: RichField + Extendable<D>, const D: usize>(
+    challenges: &FriChallenges<F, D>,
+    params: &FriParams,
+) -> Result<()> {
+    if let Some(pow_bits) = params.config.pow_strategy.final_bits() {
+        fri_verify_proof_of_work::<F, D>(challenges.fri_pow_response, pow_bits)?;
+    }
+    let round_pow_bits = params.round_pow_bits();
+    ensure!(
+        challenges.fri_round_pow_responses.len() == round_pow_bits.len(),
+        "Wrong number of commit-phase proof of work responses."
+    );
+    for (&response, &pow_bits) in challenges
+        .fri_round_pow_responses
+        .iter()
+        .zip(round_pow_bits)
+    {
+        fri_verify_proof_of_work::<F, D>(response, pow_bits)?;
+    }
+    Ok(())
+}
+
+/// Combines the initial trees' evaluations at `subgroup_x` into the value which the first
+/// reduction folds, as `fri_combine_evals` does with the evaluations of the polynomials of each
+/// batch of `instance`, in order, with their salt stripped.
+pub fn fri_combine_initial<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+    instance: &FriInstanceInfo<F, D>,
+    proof: &FriInitialTreeProof<F, H>,
     alpha: F::Extension,
     subgroup_x: F,
     precomputed_reduced_evals: &PrecomputedReducedOpenings<F, D>,
     params: &FriParams,
+) -> F::Extension {
+    let points = instance
+        .batches
+        .iter()
+        .map(|batch| batch.point)
+        .collect::<Vec<_>>();
+    let evals = instance
+        .batches
+        .iter()
+        .map(|batch| {
+            batch
+                .polynomials
+                .iter()
+                .map(|p| {
+                    let poly_blinding = instance.oracles[p.oracle_index].blinding;
+                    let salted = params.hiding && poly_blinding;
+                    proof.unsalted_eval(p.oracle_index, p.polynomial_index, salted)
+                })
+                .collect()
+        })
+        .collect::<Vec<_>>();
+    fri_combine_evals::<F, D>(
+        &points,
+        &evals,
+        alpha,
+        subgroup_x,
+        precomputed_reduced_evals,
+    )
+}
+
+/// Combines the evaluations at `x` of the polynomials opened in each batch into a single value,
+/// the evaluation at `x` of the polynomial which the prover runs FRI on.
+///
+/// Batch `i` opens the polynomials `f_ij`, whose evaluations at `x` are `evals[i][j]`, at
+/// `points[i]`. With `F_i = sum_j alpha^j f_ij`, the combined value is
+/// `sum_i alpha^(m_i) (F_i(x) - F_i(points[i])) / (x - points[i])`, where `m_i` is the number of
+/// polynomials in all batches after `i`, so that each power of `alpha` is used once. The
+/// `F_i(points[i])` are taken from `precomputed_reduced_evals`, see `precompute_reduced_openings`.
+///
+/// In Plonky2 proofs there are two batches, the openings at `zeta` followed by those at
+/// `g * zeta`, whose polynomials are ordered as in `CommonCircuitData::get_fri_instance`.
+pub fn fri_combine_evals<F: RichField + Extendable<D>, const D: usize>(
+    points: &[F::Extension],
+    evals: &[Vec<F>],
+    alpha: F::Extension,
+    x: F,
+    precomputed_reduced_evals: &PrecomputedReducedOpenings<F, D>,
 ) -> F::Extension {
     assert!(D > 1, "Not implemented for D=1.");
-    let subgroup_x = F::Extension::from_basefield(subgroup_x);
+    assert_eq!(points.len(), evals.len(), "Each batch needs a point");
+    let x = F::Extension::from_basefield(x);
     let mut alpha = ReducingFactor::new(alpha);
     let mut sum = F::Extension::ZERO;
 
-    for (batch, reduced_openings) in instance
-        .batches
+    for ((&point, batch_evals), reduced_openings) in points
         .iter()
+        .zip(evals)
         .zip(&precomputed_reduced_evals.reduced_openings_at_point)
     {
-        let FriBatchInfo { point, polynomials } = batch;
-        let evals = polynomials
-            .iter()
-            .map(|p| {
-                let poly_blinding = instance.oracles[p.oracle_index].blinding;
-                let salted = params.hiding && poly_blinding;
-                proof.unsalted_eval(p.oracle_index, p.polynomial_index, salted)
-            })
-            .map(F::Extension::from_basefield);
-        let reduced_evals = alpha.reduce(evals);
+        let reduced_evals = alpha.reduce(
+            batch_evals
+                .iter()
+                .copied()
+                .map(F::Extension::from_basefield),
+        );
         let numerator = reduced_evals - *reduced_openings;
-        let denominator = subgroup_x - *point;
+        let denominator = x - point;
         sum = alpha.shift(sum);
         sum += numerator / denominator;
     }
@@ -160,81 +674,165 @@ pub(crate) fn fri_combine_initial<
     sum
 }
 
-fn fri_verifier_query_round<
-    F: RichField + Extendable<D>,
-    C: GenericConfig<D, F = F>,
+/// Receives the failures found by `fri_verify_query_round`. Returning an error stops
+/// verification with that error, while returning `Ok` lets the round carry on to its other checks.
+pub(crate) trait FriErrorSink {
+    fn report(&mut self, error: FriError) -> Result<(), FriError>;
+}
+
+/// A `FriErrorSink` which stops at the first failure.
+pub(crate) struct FailFast;
+
+impl FriErrorSink for FailFast {
+    fn report(&mut self, error: FriError) -> Result<(), FriError> {
+        Err(error)
+    }
+}
+
+/// Collects every failure, in verification order.
+impl FriErrorSink for Vec<FriError> {
+    fn report(&mut self, error: FriError) -> Result<(), FriError> {
+        self.push(error);
+        Ok(())
+    }
+}
+
+/// Checks the Merkle proofs and the reductions of the query round starting at `query_index`, and
+/// that the final polynomial matches the evaluation folded by the last reduction. Each failure is
+/// reported to `errors`. Within a round, folding carries on from the evaluation derived from the
+/// proof after a mismatch, so each bad reduction step is reported on its own.
+///
+/// The extension field arithmetic runs in the degree `E` extension, into which the commit-phase
+/// evaluations are lifted with `lift_extension`; `instance`, `alpha`, `betas`, the reduced openings
+/// and `final_poly` must already be lifted. Native verification uses `E = D`.
+pub(crate) fn fri_verify_query_round<
+    F: RichField + Extendable<D> + Extendable<E>,
+    H: Hasher<F>,
     const D: usize,
+    const E: usize,
+    R: FriAccessRecorder,
+    S: FriErrorSink,
 >(
-    instance: &FriInstanceInfo<F, D>,
-    challenges: &FriChallenges<F, D>,
-    precomputed_reduced_evals: &PrecomputedReducedOpenings<F, D>,
-    initial_merkle_caps: &[MerkleCap<F, C::Hasher>],
-    proof: &FriProof<F, C::Hasher, D>,
-    mut x_index: usize,
-    n: usize,
-    round_proof: &FriQueryRound<F, C::Hasher, D>,
+    instance: &FriInstanceInfo<F, E>,
+    alpha: <F as Extendable<E>>::Extension,
+    betas: &[<F as Extendable<E>>::Extension],
+    precomputed_reduced_evals: &PrecomputedReducedOpenings<F, E>,
+    initial_merkle_caps: &[MerkleCap<F, H>],
+    commit_phase_merkle_caps: &[MerkleCap<F, H>],
+    final_poly: &PolynomialCoeffs<<F as Extendable<E>>::Extension>,
+    query_index: usize,
+    round_proof: &FriQueryRound<F, H, D>,
     params: &FriParams,
-) -> Result<()> {
-    fri_verify_initial_proof::<F, C::Hasher>(
-        x_index,
-        &round_proof.initial_trees_proof,
-        initial_merkle_caps,
-    )?;
+    recorder: &mut R,
+    errors: &mut S,
+) -> Result<(), FriError> {
+    for (oracle_index, ((evals, merkle_proof), cap)) in round_proof
+        .initial_trees_proof
+        .evals_proofs
+        .iter()
+        .zip(initial_merkle_caps)
+        .enumerate()
+    {
+        record_merkle_path(
+            recorder,
+            FriTree::Initial(oracle_index),
+            query_index,
+            merkle_proof.siblings.len(),
+        );
+        if verify_merkle_proof_to_cap::<F, H>(evals.clone(), query_index, cap, merkle_proof)
+            .is_err()
+        {
+            errors.report(FriError::InitialMerkleProof {
+                query_index,
+                oracle_index,
+            })?;
+        }
+    }
+
     // `subgroup_x` is `subgroup[x_index]`, i.e., the actual field element in the domain.
-    let log_n = log2_strict(n);
+    let log_n = log2_strict(params.lde_size());
+    let mut x_index = query_index;
     let mut subgroup_x = F::MULTIPLICATIVE_GROUP_GENERATOR
         * F::primitive_root_of_unity(log_n).exp_u64(reverse_bits(x_index, log_n) as u64);
 
     // old_eval is the last derived evaluation; it will be checked for consistency with its
     // committed "parent" value in the next iteration.
-    let mut old_eval = fri_combine_initial::<F, C, D>(
+    let mut old_eval = fri_combine_initial::<F, H, E>(
         instance,
         &round_proof.initial_trees_proof,
-        challenges.fri_alpha,
+        alpha,
         subgroup_x,
         precomputed_reduced_evals,
         params,
     );
 
-    for (i, &arity_bits) in params.reduction_arity_bits.iter().enumerate() {
-        let arity = 1 << arity_bits;
-        let evals = &round_proof.steps[i].evals;
+    for (reduction, &arity_bits) in params.reduction_arity_bits.iter().enumerate() {
+        let step = &round_proof.steps[reduction];
+        let evals = step
+            .evals
+            .iter()
+            .copied()
+            .map(lift_extension::<F, D, E>)
+            .collect::<Vec<_>>();
 
         // Split x_index into the index of the coset x is in, and the index of x within that coset.
         let coset_index = x_index >> arity_bits;
-        let x_index_within_coset = x_index & (arity - 1);
+        let x_index_within_coset = x_index & ((1 << arity_bits) - 1);
 
         // Check consistency with our old evaluation from the previous round.
-        ensure!(evals[x_index_within_coset] == old_eval);
+        if evals[x_index_within_coset] != old_eval {
+            errors.report(FriError::FoldMismatch {
+                query_index,
+                reduction,
+            })?;
+        }
 
         // Infer P(y) from {P(x)}_{x^arity=y}.
-        old_eval = compute_evaluation(
+        old_eval = compute_evaluation::<F, E>(
             subgroup_x,
             x_index_within_coset,
             arity_bits,
-            evals,
-            challenges.fri_betas[i],
+            &evals,
+            betas[reduction],
         );
 
-        verify_merkle_proof_to_cap::<F, C::Hasher>(
-            flatten(evals),
-            coset_index,
-            &proof.commit_phase_merkle_caps[i],
-            &round_proof.steps[i].merkle_proof,
-        )?;
+        match commit_phase_merkle_caps.get(reduction) {
+            Some(cap) => {
+                record_merkle_path(
+                    recorder,
+                    FriTree::CommitPhase(reduction),
+                    coset_index,
+                    step.merkle_proof.siblings.len(),
+                );
+                if verify_merkle_proof_to_cap::<F, H>(
+                    flatten::<F, D>(&step.evals),
+                    coset_index,
+                    cap,
+                    &step.merkle_proof,
+                )
+                .is_err()
+                {
+                    errors.report(FriError::CommitPhaseMerkleProof {
+                        query_index,
+                        reduction,
+                    })?;
+                }
+            }
+            None => errors.report(FriError::MissingCap(reduction))?,
+        }
 
         // Update the point x to x^arity.
         subgroup_x = subgroup_x.exp_power_of_2(arity_bits);
-
         x_index = coset_index;
     }
 
-    // Final check of FRI. After all the reductions, we check that the final polynomial is equal
-    // to the one sent by the prover.
-    ensure!(
-        proof.final_poly.eval(subgroup_x.into()) == old_eval,
-        "Final polynomial evaluation is invalid."
-    );
+    // Final check of FRI. After all the reductions, we check that the final polynomial is equal to
+    // the one sent by the prover.
+    if let Err(error) =
+        fri_verify_final_reduction::<F, E>(final_poly, subgroup_x, old_eval, query_index)
+    {
+        errors.report(error)?;
+    }
 
     Ok(())
 }
@@ -242,19 +840,659 @@ fn fri_verifier_query_round<
 /// For each opening point, holds the reduced (by `alpha`) evaluations of each polynomial that's
 /// opened at that point.
 #[derive(Clone, Debug)]
-pub(crate) struct PrecomputedReducedOpenings<F: RichField + Extendable<D>, const D: usize> {
+pub struct PrecomputedReducedOpenings<F: RichField + Extendable<D>, const D: usize> {
     pub reduced_openings_at_point: Vec<F::Extension>,
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> PrecomputedReducedOpenings<F, D> {
     pub(crate) fn from_os_and_alpha(openings: &FriOpenings<F, D>, alpha: F::Extension) -> Self {
-        let reduced_openings_at_point = openings
-            .batches
-            .iter()
-            .map(|batch| ReducingFactor::new(alpha).reduce(batch.values.iter()))
-            .collect();
-        Self {
-            reduced_openings_at_point,
+        precompute_reduced_openings(openings, alpha)
+    }
+}
+
+/// Reduces the values opened in each batch by `alpha`, into `sum_j alpha^j values[j]`. These are
+/// the values at the batch's point of the combinations `F_i` which `fri_combine_evals` divides.
+pub fn precompute_reduced_openings<F: RichField + Extendable<D>, const D: usize>(
+    openings: &FriOpenings<F, D>,
+    alpha: F::Extension,
+) -> PrecomputedReducedOpenings<F, D> {
+    let reduced_openings_at_point = openings
+        .batches
+        .iter()
+        .map(|batch| ReducingFactor::new(alpha).reduce(batch.values.iter()))
+        .collect();
+    PrecomputedReducedOpenings {
+        reduced_openings_at_point,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Sample;
+    use crate::gates::noop::NoopGate;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::plonk::proof::{Proof, ProofWithPublicInputs};
+
+    #[derive(Default)]
+    struct CollectingRecorder {
+        leaves: Vec<(FriTree, usize)>,
+        siblings: Vec<(FriTree, usize, usize)>,
+    }
+
+    impl FriAccessRecorder for CollectingRecorder {
+        fn record_leaf(&mut self, tree: FriTree, leaf_index: usize) {
+            self.leaves.push((tree, leaf_index));
+        }
+
+        fn record_sibling(&mut self, tree: FriTree, layer: usize, index: usize) {
+            self.siblings.push((tree, layer, index));
+        }
+    }
+
+    #[test]
+    fn test_recorded_accesses_follow_query_indices() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.mul(x, x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove(pw)?;
+
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        let initial_merkle_caps = [
+            data.verifier_only.constants_sigmas_cap.clone(),
+            proof.proof.wires_cap.clone(),
+            proof.proof.plonk_zs_partial_products_cap.clone(),
+            proof.proof.quotient_polys_cap.clone(),
+        ];
+        let mut recorder = CollectingRecorder::default();
+        verify_fri_proof_recorded::<F, C, D, _>(
+            &data.common.get_fri_instance(challenges.plonk_zeta),
+            &proof.proof.openings.to_fri_openings(),
+            &challenges.fri_challenges,
+            &initial_merkle_caps,
+            &proof.proof.opening_proof,
+            &data.common.fri_params,
+            &mut recorder,
+        )?;
+
+        // Each tree has `tree_bits` leaf index bits, and its Merkle paths stop at the cap.
+        let params = &data.common.fri_params;
+        let cap_height = params.config.cap_height;
+        let mut expected_leaves = vec![];
+        let mut expected_siblings = vec![];
+        let mut expect_path = |tree, leaf_index: usize, tree_bits: usize| {
+            expected_leaves.push((tree, leaf_index));
+            for layer in 0..tree_bits - cap_height {
+                expected_siblings.push((tree, layer, (leaf_index >> layer) ^ 1));
+            }
+        };
+        for &x_index in &challenges.fri_challenges.fri_query_indices {
+            let mut tree_bits = params.lde_bits().get();
+            for i in 0..initial_merkle_caps.len() {
+                expect_path(FriTree::Initial(i), x_index, tree_bits);
+            }
+            let mut index = x_index;
+            for (i, &arity_bits) in params.reduction_arity_bits.iter().enumerate() {
+                index >>= arity_bits;
+                tree_bits -= arity_bits;
+                expect_path(FriTree::CommitPhase(i), index, tree_bits);
+            }
         }
+        assert_eq!(recorder.leaves, expected_leaves);
+        assert_eq!(recorder.siblings, expected_siblings);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_over_long_final_poly_rejected() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FE = <F as Extendable<D>>::Extension;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5));
+        let proof = data.prove(pw)?;
+        data.verify(proof.clone())?;
+
+        let final_poly_error = |final_poly_coeffs: Vec<FE>| {
+            let mut proof = proof.clone();
+            proof.proof.opening_proof.final_poly = PolynomialCoeffs::new(final_poly_coeffs);
+            let err = data.verify(proof).unwrap_err();
+            *err.downcast_ref::<FriError>().unwrap()
+        };
+        let coeffs = &proof.proof.opening_proof.final_poly.coeffs;
+        let max_len = coeffs.len();
+
+        // A nonzero coefficient past the expected length raises the degree.
+        let mut padded = coeffs.clone();
+        padded.extend([FE::ZERO, FE::ONE]);
+        assert_eq!(
+            final_poly_error(padded),
+            FriError::FinalPolyTooLong {
+                max_len,
+                degree: max_len + 1
+            }
+        );
+
+        // Zero padding keeps the degree, but the length no longer matches.
+        let mut padded = coeffs.clone();
+        padded.push(FE::ZERO);
+        assert_eq!(
+            final_poly_error(padded),
+            FriError::FinalPolyMismatch {
+                expected_len: max_len,
+                len: max_len + 1
+            }
+        );
+
+        let truncated = coeffs[..max_len - 1].to_vec();
+        assert_eq!(
+            final_poly_error(truncated),
+            FriError::FinalPolyMismatch {
+                expected_len: max_len,
+                len: max_len - 1
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Combines random oracles' polynomials, opened at a point and at its shift as in Plonky2 proofs,
+    /// both as the prover does and from their evaluations with the public functions.
+    #[test]
+    fn test_combination_matches_prover() {
+        use crate::field::types::PrimeField64;
+        use crate::fri::oracle::PolynomialBatch;
+        use crate::fri::structure::{FriOracleInfo, FriPolynomialInfo};
+        use crate::util::log_size::DegreeBits;
+        use crate::util::timing::TimingTree;
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FE = <F as Extendable<D>>::Extension;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let random_below = |n: usize| F::rand().to_canonical_u64() as usize % n;
+        let degree_bits = 5;
+        let fri_config = CircuitConfig::standard_recursion_config().fri_config;
+
+        for hiding in [false, true] {
+            let params = fri_config.fri_params(DegreeBits::new(degree_bits), hiding);
+            let log_n = log2_strict(params.lde_size());
+
+            let num_oracles = 1 + random_below(4);
+            let oracle_infos = (0..num_oracles)
+                .map(|_| FriOracleInfo {
+                    num_polys: 1 + random_below(5),
+                    blinding: random_below(2) == 0,
+                })
+                .collect::<Vec<_>>();
+            let oracles = oracle_infos
+                .iter()
+                .map(|info| {
+                    let polys = (0..info.num_polys)
+                        .map(|_| PolynomialCoeffs::new(F::rand_vec(1 << degree_bits)))
+                        .collect();
+                    PolynomialBatch::<F, C, D>::from_coeffs(
+                        polys,
+                        fri_config.rate_bits,
+                        hiding && info.blinding,
+                        fri_config.cap_height,
+                        &mut TimingTree::default(),
+                        None,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            // All polynomials are opened at `zeta`, and those of the first oracle at `g * zeta`.
+            let zeta = FE::rand();
+            let g = F::primitive_root_of_unity(degree_bits);
+            let instance = FriInstanceInfo {
+                oracles: oracle_infos.clone(),
+                batches: vec![
+                    FriBatchInfo {
+                        point: zeta,
+                        polynomials: oracle_infos
+                            .iter()
+                            .enumerate()
+                            .flat_map(|(i, info)| {
+                                FriPolynomialInfo::from_range(i, 0..info.num_polys)
+                            })
+                            .collect(),
+                    },
+                    FriBatchInfo {
+                        point: zeta * <FE as FieldExtension<D>>::from_basefield(g),
+                        polynomials: FriPolynomialInfo::from_range(0, 0..oracle_infos[0].num_polys),
+                    },
+                ],
+            };
+            let poly =
+                |p: &FriPolynomialInfo| &oracles[p.oracle_index].polynomials[p.polynomial_index];
+            let openings = FriOpenings {
+                batches: instance
+                    .batches
+                    .iter()
+                    .map(|batch| FriOpeningBatch {
+                        values: batch
+                            .polynomials
+                            .iter()
+                            .map(|p| poly(p).to_extension::<D>().eval(batch.point))
+                            .collect(),
+                    })
+                    .collect(),
+            };
+
+            let alpha = FE::rand();
+            let oracle_refs = oracles.iter().collect::<Vec<_>>();
+            let combined = PolynomialBatch::<F, C, D>::combine_openings(
+                &instance,
+                &oracle_refs,
+                alpha,
+                &mut TimingTree::default(),
+            );
+            let precomputed = precompute_reduced_openings(&openings, alpha);
+            let points = instance
+                .batches
+                .iter()
+                .map(|batch| batch.point)
+                .collect::<Vec<_>>();
+
+            for _ in 0..8 {
+                let x_index = random_below(1 << log_n);
+                let x = F::MULTIPLICATIVE_GROUP_GENERATOR
+                    * F::primitive_root_of_unity(log_n)
+                        .exp_u64(reverse_bits(x_index, log_n) as u64);
+                let expected = combined.eval(x.into());
+
+                // From the raw evaluations of the opened polynomials.
+                let evals = instance
+                    .batches
+                    .iter()
+                    .map(|batch| batch.polynomials.iter().map(|p| poly(p).eval(x)).collect())
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    fri_combine_evals::<F, D>(&points, &evals, alpha, x, &precomputed),
+                    expected
+                );
+
+                // From the leaves of the oracles' Merkle trees, which hold the salt if any.
+                let initial_trees_proof = FriInitialTreeProof::<F, H> {
+                    evals_proofs: oracles
+                        .iter()
+                        .map(|oracle| {
+                            (
+                                oracle.merkle_tree.get(x_index).to_vec(),
+                                oracle.merkle_tree.prove(x_index),
+                            )
+                        })
+                        .collect(),
+                };
+                assert_eq!(
+                    fri_combine_initial::<F, H, D>(
+                        &instance,
+                        &initial_trees_proof,
+                        alpha,
+                        x,
+                        &precomputed,
+                        &params
+                    ),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_missing_cap_rejected() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        // Enough gates for the proof to have commit-phase reductions.
+        while builder.num_gates() < 1 << 12 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        assert!(!data.common.fri_params.reduction_arity_bits.is_empty());
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5));
+        let mut proof = data.prove(pw)?;
+        let caps = &mut proof.proof.opening_proof.commit_phase_merkle_caps;
+        let last = caps.len() - 1;
+        caps.pop();
+
+        let err = data.verify(proof).unwrap_err();
+        assert_eq!(
+            *err.downcast_ref::<FriError>().unwrap(),
+            FriError::MissingCap(last)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_cap_height_rejected() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        // Enough gates for the proof to have commit-phase reductions.
+        while builder.num_gates() < 1 << 12 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let params = &data.common.fri_params;
+        assert!(!params.reduction_arity_bits.is_empty());
+        let expected = 1 << params.config.cap_height;
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5));
+        let proof = data.prove(pw)?;
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        let initial_merkle_caps = vec![
+            data.verifier_only.constants_sigmas_cap.clone(),
+            proof.proof.wires_cap.clone(),
+            proof.proof.plonk_zs_partial_products_cap.clone(),
+            proof.proof.quotient_polys_cap.clone(),
+        ];
+        let verify = |initial_merkle_caps: &[MerkleCap<F, _>], fri_proof: &FriProof<F, _, D>| {
+            let err = verify_fri_proof::<F, C, D>(
+                &data.common.get_fri_instance(challenges.plonk_zeta),
+                &proof.proof.openings.to_fri_openings(),
+                &challenges.fri_challenges,
+                initial_merkle_caps,
+                fri_proof,
+                params,
+            )
+            .unwrap_err();
+            *err.downcast_ref::<FriError>().unwrap()
+        };
+
+        // A commit-phase cap which is one entry short, so not of any height.
+        let mut fri_proof = proof.proof.opening_proof.clone();
+        fri_proof.commit_phase_merkle_caps[1].0.pop();
+        assert_eq!(
+            verify(&initial_merkle_caps, &fri_proof),
+            FriError::WrongCapHeight {
+                cap_index: initial_merkle_caps.len() + 1,
+                got: expected - 1,
+                expected,
+            }
+        );
+        let err = data.verify(ProofWithPublicInputs {
+            proof: Proof {
+                opening_proof: fri_proof,
+                ..proof.proof.clone()
+            },
+            public_inputs: proof.public_inputs.clone(),
+        });
+        assert!(matches!(
+            err.unwrap_err().downcast_ref::<FriError>(),
+            Some(FriError::WrongCapHeight { .. })
+        ));
+
+        // An initial oracle's cap one level too high. The siblings of its Merkle proofs, which
+        // still stop at the original cap, can't make up for it.
+        let mut caps = initial_merkle_caps.clone();
+        let wires_cap = &mut caps[1].0;
+        wires_cap.extend(wires_cap.clone());
+        assert_eq!(
+            verify(&caps, &proof.proof.opening_proof),
+            FriError::WrongCapHeight {
+                cap_index: 1,
+                got: 2 * expected,
+                expected,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupted_final_poly_coefficient_rejected() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FE = <F as Extendable<D>>::Extension;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5));
+        let proof = data.prove(pw)?;
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        let initial_merkle_caps = [
+            data.verifier_only.constants_sigmas_cap.clone(),
+            proof.proof.wires_cap.clone(),
+            proof.proof.plonk_zs_partial_products_cap.clone(),
+            proof.proof.quotient_polys_cap.clone(),
+        ];
+
+        // The challenges are kept, so that only the final reduction can catch the corruption.
+        let mut fri_proof = proof.proof.opening_proof.clone();
+        fri_proof.final_poly.coeffs[0] += FE::ONE;
+        let err = verify_fri_proof::<F, C, D>(
+            &data.common.get_fri_instance(challenges.plonk_zeta),
+            &proof.proof.openings.to_fri_openings(),
+            &challenges.fri_challenges,
+            &initial_merkle_caps,
+            &fri_proof,
+            &data.common.fri_params,
+        )
+        .unwrap_err();
+        assert_eq!(
+            *err.downcast_ref::<FriError>().unwrap(),
+            FriError::FinalReductionMismatch {
+                query_index: challenges.fri_challenges.fri_query_indices[0]
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_errors_reports_independent_corruptions() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        // Enough gates for the proof to have commit-phase reductions.
+        while builder.num_gates() < 1 << 12 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5));
+        let proof = data.prove(pw)?;
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        let initial_merkle_caps = [
+            data.verifier_only.constants_sigmas_cap.clone(),
+            proof.proof.wires_cap.clone(),
+            proof.proof.plonk_zs_partial_products_cap.clone(),
+            proof.proof.quotient_polys_cap.clone(),
+        ];
+        let instance = data.common.get_fri_instance(challenges.plonk_zeta);
+        let openings = proof.proof.openings.to_fri_openings();
+        let collect_errors = |fri_proof: &FriProof<F, _, D>| {
+            verify_fri_proof_collect_errors::<F, C, D>(
+                &instance,
+                &openings,
+                &challenges.fri_challenges,
+                &initial_merkle_caps,
+                fri_proof,
+                &data.common.fri_params,
+            )
+        };
+        assert_eq!(collect_errors(&proof.proof.opening_proof), vec![]);
+
+        // Corrupt a Merkle path sibling of the wires oracle in the first query round, and one of
+        // the first reduction step in the second. Neither affects the evaluations.
+        let mut fri_proof = proof.proof.opening_proof.clone();
+        let rounds = &mut fri_proof.query_round_proofs;
+        rounds[0].initial_trees_proof.evals_proofs[1].1.siblings[0].elements[0] += F::ONE;
+        rounds[1].steps[0].merkle_proof.siblings[0].elements[0] += F::ONE;
+
+        let query_indices = &challenges.fri_challenges.fri_query_indices;
+        assert_eq!(
+            collect_errors(&fri_proof),
+            vec![
+                FriError::InitialMerkleProof {
+                    query_index: query_indices[0],
+                    oracle_index: 1,
+                },
+                FriError::CommitPhaseMerkleProof {
+                    query_index: query_indices[1],
+                    reduction: 0,
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lift_extension_is_homomorphism() {
+        type F = GoldilocksField;
+        type FE2 = <F as Extendable<2>>::Extension;
+        let lift = lift_extension::<F, 2, 4>;
+
+        let a = FE2::rand();
+        let b = FE2::rand();
+        assert_eq!(lift(a + b), lift(a) + lift(b));
+        assert_eq!(lift(a * b), lift(a) * lift(b));
+        assert_eq!(lift(a.inverse()), lift(a).inverse());
+        assert_eq!(lift(F::TWO.into()), F::TWO.into());
+
+        // The quadratic generator squares to `W`, and so must its image.
+        let x = FE2::from_basefield_array([F::ZERO, F::ONE]);
+        assert_eq!(lift(x) * lift(x), <F as Extendable<2>>::W.into());
+    }
+
+    #[test]
+    fn test_cross_degree_verification() -> Result<()> {
+        const D: usize = 2;
+        const E: usize = 4;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FE = <F as Extendable<D>>::Extension;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5));
+        let proof = data.prove(pw)?;
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        let instance = data.common.get_fri_instance(challenges.plonk_zeta);
+        let openings = proof.proof.openings.to_fri_openings();
+        let initial_merkle_caps = [
+            data.verifier_only.constants_sigmas_cap.clone(),
+            proof.proof.wires_cap.clone(),
+            proof.proof.plonk_zs_partial_products_cap.clone(),
+            proof.proof.quotient_polys_cap.clone(),
+        ];
+        let verify_cross_degree = |fri_proof: &FriProof<F, _, D>| {
+            verify_fri_proof_cross_degree::<F, C, D, E>(
+                &instance,
+                &openings,
+                &challenges.fri_challenges,
+                &initial_merkle_caps,
+                fri_proof,
+                &data.common.fri_params,
+            )
+        };
+
+        // The lifted check agrees with the native one on an honest proof.
+        let fri_proof = &proof.proof.opening_proof;
+        verify_fri_proof::<F, C, D>(
+            &instance,
+            &openings,
+            &challenges.fri_challenges,
+            &initial_merkle_caps,
+            fri_proof,
+            &data.common.fri_params,
+        )?;
+        verify_cross_degree(fri_proof)?;
+
+        // ... and on a corrupted one.
+        let mut corrupted = fri_proof.clone();
+        corrupted.final_poly.coeffs[0] += FE::ONE;
+        let err = verify_cross_degree(&corrupted).unwrap_err();
+        assert_eq!(
+            *err.downcast_ref::<FriError>().unwrap(),
+            FriError::FinalReductionMismatch {
+                query_index: challenges.fri_challenges.fri_query_indices[0]
+            }
+        );
+
+        Ok(())
     }
 }
@@ -17,6 +17,10 @@ pub fn set_fri_proof_target<F, W, H, const D: usize>(
     H: AlgebraicHasher<F>,
 {
     witness.set_target(fri_proof_target.pow_witness, fri_proof.pow_witness);
+    witness.set_target_arr(
+        &fri_proof_target.round_pow_witnesses,
+        &fri_proof.round_pow_witnesses,
+    );
 
     for (&t, &x) in fri_proof_target
         .final_poly
@@ -49,7 +53,7 @@ pub fn set_fri_proof_target<F, W, H, const D: usize>(
             for (&t, &x) in at.0.iter().zip_eq(&a.0) {
                 witness.set_target(t, x);
             }
-            for (&t, &x) in at.1.siblings.iter().zip_eq(&a.1.siblings) {
+            for (&t, &x) in at.1.siblings.iter().zip_eq(a.1.siblings.iter()) {
                 witness.set_hash_target(t, x);
             }
         }
@@ -62,7 +66,7 @@ pub fn set_fri_proof_target<F, W, H, const D: usize>(
                 .merkle_proof
                 .siblings
                 .iter()
-                .zip_eq(&s.merkle_proof.siblings)
+                .zip_eq(s.merkle_proof.siblings.iter())
             {
                 witness.set_hash_target(t, x);
             }
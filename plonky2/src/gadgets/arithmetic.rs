@@ -3,6 +3,8 @@ use alloc::vec;
 use alloc::vec::Vec;
 use core::borrow::Borrow;
 
+use hashbrown::HashMap;
+
 use crate::field::extension::Extendable;
 use crate::field::types::Field64;
 use crate::gates::arithmetic_base::ArithmeticGate;
@@ -78,7 +80,16 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
 
         // Otherwise, we must actually perform the operation using an ArithmeticExtensionGate slot.
-        let result = self.add_base_arithmetic_operation(operation);
+        let result = if self.config.optimize_arithmetic || self.config.eliminate_dead_code {
+            // The operation is placed at build time, once it's known whether it can be fused or
+            // is dead.
+            let result = self.add_virtual_target();
+            self.pending_base_arithmetic_operations
+                .push((operation, result));
+            result
+        } else {
+            self.add_base_arithmetic_operation(operation)
+        };
         self.base_arithmetic_results.insert(operation, result);
         result
     }
@@ -98,6 +109,145 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         Target::wire(gate, ArithmeticGate::wire_ith_output(i))
     }
 
+    /// Places the operations buffered when `config.optimize_arithmetic` or
+    /// `config.eliminate_dead_code` is set. Dead operations are dropped first, if enabled, then each
+    /// operation whose result has no other use is fused into the operation using it, if enabled.
+    ///
+    /// A result `r` is fused into `k_0 * r * c + k_1 * w`, for a constant `c`, if `k_1 * w` or the
+    /// addend term of `r`'s operation vanishes, and into `k_1 * r` otherwise. Operations are
+    /// visited in the order they were added, so chains collapse and building is deterministic.
+    pub(crate) fn place_pending_arithmetic_operations(&mut self) {
+        let mut pending = core::mem::take(&mut self.pending_base_arithmetic_operations);
+        if self.config.eliminate_dead_code {
+            pending = self.live_arithmetic_operations(pending);
+        }
+        if pending.is_empty() {
+            return;
+        }
+
+        let mut operations: Vec<_> = pending.iter().map(|&(operation, _)| operation).collect();
+        let mut fused = vec![false; operations.len()];
+        if self.config.optimize_arithmetic {
+            let mut uses = self.target_use_counts();
+            let mut producers = HashMap::new();
+            for (i, (operation, result)) in pending.iter().enumerate() {
+                for input in operation.inputs() {
+                    *uses.entry(input).or_insert(0) += 1;
+                }
+                producers.insert(*result, i);
+            }
+
+            for i in 0..operations.len() {
+                while let Some((j, fused_operation)) =
+                    self.fuse_arithmetic_operation(&operations, i, &producers, &uses)
+                {
+                    for input in operations[i].inputs() {
+                        *uses.get_mut(&input).unwrap() -= 1;
+                    }
+                    for input in fused_operation.inputs() {
+                        *uses.get_mut(&input).unwrap() += 1;
+                    }
+                    for input in operations[j].inputs() {
+                        *uses.get_mut(&input).unwrap() -= 1;
+                    }
+                    operations[i] = fused_operation;
+                    fused[j] = true;
+                }
+            }
+        }
+
+        for ((operation, (_, result)), fused) in operations.into_iter().zip(pending).zip(fused) {
+            if !fused {
+                let output = self.add_base_arithmetic_operation(operation);
+                self.connect(result, output);
+            }
+        }
+    }
+
+    /// Keeps the pending operations whose results are used outside of the pending operations, or
+    /// by a kept operation. An operation only uses results of earlier ones, so a single backwards
+    /// pass finds them all, and the kept operations stay in order.
+    fn live_arithmetic_operations(
+        &self,
+        pending: Vec<(BaseArithmeticOperation<F>, Target)>,
+    ) -> Vec<(BaseArithmeticOperation<F>, Target)> {
+        let mut used = self.target_use_counts();
+        let mut live = vec![false; pending.len()];
+        for (i, (operation, result)) in pending.iter().enumerate().rev() {
+            if used.contains_key(result) {
+                live[i] = true;
+                for input in operation.inputs() {
+                    *used.entry(input).or_insert(0) += 1;
+                }
+            }
+        }
+        pending
+            .into_iter()
+            .zip(live)
+            .filter_map(|(op, live)| live.then_some(op))
+            .collect()
+    }
+
+    /// Finds a pending operation whose result can be fused into `operations[i]`, and returns its
+    /// index along with the fused operation.
+    fn fuse_arithmetic_operation(
+        &self,
+        operations: &[BaseArithmeticOperation<F>],
+        i: usize,
+        producers: &HashMap<Target, usize>,
+        uses: &HashMap<Target, usize>,
+    ) -> Option<(usize, BaseArithmeticOperation<F>)> {
+        let operation = operations[i];
+        let is_zero = |t: Target| self.target_as_constant(t) == Some(F::ZERO);
+        // Only results used once, necessarily by `operation`, can be fused.
+        let producer = |t: Target| {
+            producers
+                .get(&t)
+                .copied()
+                .filter(|&j| j < i && uses[&t] == 1)
+        };
+
+        // `operation` is `k_0 * r * c + k_1 * w`.
+        for (r, c) in [
+            (operation.multiplicand_0, operation.multiplicand_1),
+            (operation.multiplicand_1, operation.multiplicand_0),
+        ] {
+            let (j, c) = match (producer(r), self.target_as_constant(c)) {
+                (Some(j), Some(c)) => (j, c),
+                _ => continue,
+            };
+            let inner = operations[j];
+            let scale = operation.const_0 * c;
+            if operation.const_1 == F::ZERO || is_zero(operation.addend) {
+                return Some((j, inner.scaled(scale)));
+            }
+            if inner.const_1 == F::ZERO || is_zero(inner.addend) {
+                return Some((
+                    j,
+                    BaseArithmeticOperation {
+                        const_0: scale * inner.const_0,
+                        const_1: operation.const_1,
+                        multiplicand_0: inner.multiplicand_0,
+                        multiplicand_1: inner.multiplicand_1,
+                        addend: operation.addend,
+                    },
+                ));
+            }
+        }
+
+        // `operation` is `k_1 * r`.
+        let product_vanishes = operation.const_0 == F::ZERO
+            || is_zero(operation.multiplicand_0)
+            || is_zero(operation.multiplicand_1);
+        if product_vanishes {
+            if let Some(j) = producer(operation.addend) {
+                return Some((j, operations[j].scaled(operation.const_1)));
+            }
+        }
+
+        None
+    }
+
     /// Checks for special cases where the value of
     /// `const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend`
     /// can be determined without adding an `ArithmeticGate`.
@@ -344,7 +494,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
     /// Computes the logical OR through the arithmetic expression: `b1 + b2 - b1 * b2`.
     pub fn or(&mut self, b1: BoolTarget, b2: BoolTarget) -> BoolTarget {
-        let res_minus_b2 = self.arithmetic(-F::ONE, F::ONE, b1.target, b2.target, b1.target);
+        let res_minus_b2 = self.arithmetic(F::NEG_ONE, F::ONE, b1.target, b2.target, b1.target);
         BoolTarget::new_unsafe(self.add(res_minus_b2, b2.target))
     }
 
@@ -429,3 +579,173 @@ pub(crate) struct BaseArithmeticOperation<F: Field64> {
     multiplicand_1: Target,
     addend: Target,
 }
+
+impl<F: Field64> BaseArithmeticOperation<F> {
+    const fn inputs(&self) -> [Target; 3] {
+        [self.multiplicand_0, self.multiplicand_1, self.addend]
+    }
+
+    /// The operation computing `scale` times the result of this one.
+    fn scaled(self, scale: F) -> Self {
+        Self {
+            const_0: scale * self.const_0,
+            const_1: scale * self.const_1,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::{Field, Sample};
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Builds a chain of 1000 operations repeating `mul`, `add` and `mul_const`, of which each
+    /// `add` and `mul_const` can be fused into the preceding operation.
+    fn arithmetic_chain(optimize_arithmetic: bool) -> (CircuitBuilder<F, D>, [Target; 3]) {
+        let config = CircuitConfig {
+            optimize_arithmetic,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let inputs = builder.add_virtual_target_arr::<3>();
+        let [x, y, z] = inputs;
+        let k = F::from_canonical_u64(7);
+        let mut t = x;
+        for i in 0..1000 {
+            t = match i % 3 {
+                0 => builder.mul(t, y),
+                1 => builder.add(t, z),
+                _ => builder.mul_const(k, t),
+            };
+        }
+        builder.register_public_input(t);
+        (builder, inputs)
+    }
+
+    #[test]
+    fn test_arithmetic_fusion_reduces_rows() {
+        let (unoptimized, _) = arithmetic_chain(false);
+        let (mut optimized, _) = arithmetic_chain(true);
+        optimized.place_pending_arithmetic_operations();
+
+        // 1000 operations take 50 rows of 20 operations. After fusion, each `mul`, `add`,
+        // `mul_const` triple is a single operation, leaving 334 operations in 17 rows.
+        let ops_per_row = ArithmeticGate::new_from_config(&optimized.config).num_ops;
+        assert_eq!(ops_per_row, 20);
+        assert_eq!(unoptimized.num_gates() - optimized.num_gates(), 50 - 17);
+    }
+
+    #[test]
+    fn test_arithmetic_fusion_preserves_witness() -> Result<()> {
+        let (unoptimized, _) = arithmetic_chain(false);
+        let (optimized, inputs) = arithmetic_chain(true);
+        let unoptimized = unoptimized.build::<C>();
+        let optimized = optimized.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target_arr(&inputs, &F::rand_array::<3>());
+        let unoptimized_proof = unoptimized.prove(pw.clone())?;
+        let optimized_proof = optimized.prove(pw)?;
+        assert_eq!(
+            optimized_proof.public_inputs,
+            unoptimized_proof.public_inputs
+        );
+        unoptimized.verify(unoptimized_proof)?;
+        optimized.verify(optimized_proof)?;
+
+        // Fusion is deterministic.
+        let (rebuilt, _) = arithmetic_chain(true);
+        assert_eq!(rebuilt.build::<C>().verifier_only, optimized.verifier_only);
+        Ok(())
+    }
+
+    #[test]
+    fn test_arithmetic_fusion_keeps_used_results() -> Result<()> {
+        let config = CircuitConfig {
+            optimize_arithmetic: true,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let [x, y, z] = builder.add_virtual_target_arr::<3>();
+        let product = builder.mul(x, y);
+        let sum = builder.add(product, z);
+        let scaled = builder.mul_const(F::TWO, sum);
+        // `sum` can't be fused into `scaled`, since it's also a public input.
+        builder.register_public_input(sum);
+        builder.register_public_input(scaled);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        let [x_value, y_value, z_value] = F::rand_array();
+        pw.set_target(x, x_value);
+        pw.set_target(y, y_value);
+        pw.set_target(z, z_value);
+        let proof = data.prove(pw)?;
+        let expected_sum = x_value * y_value + z_value;
+        assert_eq!(proof.public_inputs, [expected_sum, F::TWO * expected_sum]);
+        data.verify(proof)
+    }
+
+    /// Builds a circuit proving `x^2 + y`, along with a chain of 2000 operations on its result
+    /// whose end is never used.
+    fn circuit_with_dead_code(eliminate_dead_code: bool) -> (CircuitData<F, C, D>, [Target; 2]) {
+        let config = CircuitConfig {
+            eliminate_dead_code,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let inputs = builder.add_virtual_target_arr::<2>();
+        let [x, y] = inputs;
+        let square = builder.mul(x, x);
+        let result = builder.add(square, y);
+        builder.register_public_input(result);
+
+        let mut t = result;
+        for _ in 0..1000 {
+            t = builder.mul(t, y);
+            t = builder.add(t, x);
+        }
+        (builder.build::<C>(), inputs)
+    }
+
+    #[test]
+    fn test_dead_code_elimination() -> Result<()> {
+        let (full, full_inputs) = circuit_with_dead_code(false);
+        let (pruned, inputs) = circuit_with_dead_code(true);
+        assert!(pruned.common.degree_bits() < full.common.degree_bits());
+
+        let values = F::rand_array::<2>();
+        let mut pw = PartialWitness::new();
+        pw.set_target_arr(&full_inputs, &values);
+        let full_proof = full.prove(pw)?;
+        let mut pw = PartialWitness::new();
+        pw.set_target_arr(&inputs, &values);
+        let proof = pruned.prove(pw)?;
+        assert_eq!(proof.public_inputs, full_proof.public_inputs);
+        full.verify(full_proof)?;
+        pruned.verify(proof.clone())?;
+
+        // Elimination is deterministic.
+        let (rebuilt, _) = circuit_with_dead_code(true);
+        assert_eq!(rebuilt.verifier_only, pruned.verifier_only);
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let proof_t = builder.add_virtual_proof_with_pis(&pruned.common);
+        let verifier_t = builder.constant_verifier_data(&pruned.verifier_only);
+        builder.verify_proof::<C>(&proof_t, &verifier_t, &pruned.common);
+        let recursive = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_t, &proof);
+        recursive.verify(recursive.prove(pw)?)
+    }
+}
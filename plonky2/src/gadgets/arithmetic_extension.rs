@@ -595,14 +595,78 @@ mod tests {
     use anyhow::Result;
 
     use crate::field::extension::algebra::ExtensionAlgebra;
-    use crate::field::types::Sample;
+    use crate::field::extension::FieldExtension;
+    use crate::field::types::{Field, Sample};
     use crate::iop::ext_target::ExtensionAlgebraTarget;
-    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::iop::generator::generate_partial_witness;
+    use crate::iop::witness::{PartialWitness, Witness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
     use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::{GenericConfig, KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
     use crate::plonk::verifier::verify;
 
+    #[test]
+    fn test_extension_witness_bulk_api() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let xs = builder.add_virtual_extension_targets(3);
+        let base = builder.add_virtual_target();
+        let base_ext = builder.convert_to_ext(base);
+        let c = FF::rand();
+        let c_t = builder.constant_extension(c);
+        let one = builder.one_extension();
+        let zero = builder.zero_extension();
+
+        // `x_0 * x_1 + c`, `x_2 * base + 1` and `x_2 + 0`.
+        let outputs = [
+            builder.mul_add_extension(xs[0], xs[1], c_t),
+            builder.mul_add_extension(xs[2], base_ext, one),
+            builder.add_extension(xs[2], zero),
+        ];
+        for &output in &outputs {
+            builder.register_public_inputs(&output.0);
+        }
+        let data = builder.build::<C>();
+
+        let x_values = FF::rand_vec(3);
+        let base_value = F::rand();
+        let mut pw = PartialWitness::new();
+        pw.set_extension_targets(&xs, &x_values);
+        pw.set_target(base, base_value);
+        let witness = generate_partial_witness(pw.clone(), &data.prover_only, &data.common);
+        let base_value_ext = <FF as FieldExtension<D>>::from_basefield(base_value);
+        assert_eq!(
+            witness.get_extension_targets(&outputs),
+            [
+                x_values[0] * x_values[1] + c,
+                x_values[2] * base_value_ext + FF::ONE,
+                x_values[2],
+            ]
+        );
+        // The high limbs of a converted base target are constrained to zero.
+        assert_eq!(witness.get_extension_target(base_ext), base_value_ext);
+
+        data.verify(data.prove(pw)?)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_set_extension_targets_length_mismatch() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let xs = builder.add_virtual_extension_targets(3);
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_extension_targets(&xs, &FF::rand_vec(2));
+    }
+
     #[test]
     fn test_mul_many() -> Result<()> {
         const D: usize = 2;
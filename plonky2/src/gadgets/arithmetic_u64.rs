@@ -0,0 +1,488 @@
+//! 64-bit unsigned integer arithmetic, with each value held as a pair of 32-bit limbs.
+//!
+//! A single field element cannot hold an arbitrary `u64`, since the Goldilocks order is below
+//! `2^64`, so [`U64Target`] splits it into two range-checked [`U32Target`] limbs. Carries between
+//! limbs are extracted with [`CircuitBuilder::split_low_high`], whose generator fills in the
+//! witness for every operation below.
+
+use alloc::vec::Vec;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A `Target` constrained to be less than `2^32`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct U32Target(pub Target);
+
+/// A 64-bit unsigned integer, as the little-endian pair of its 32-bit limbs.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct U64Target {
+    pub lo: U32Target,
+    pub hi: U32Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a `U32Target` whose value is range-checked to 32 bits.
+    pub fn add_virtual_u32_target(&mut self) -> U32Target {
+        let t = self.add_virtual_target();
+        self.range_check(t, 32);
+        U32Target(t)
+    }
+
+    /// Adds a `U64Target` whose limbs are range-checked to 32 bits.
+    pub fn add_virtual_u64_target(&mut self) -> U64Target {
+        U64Target {
+            lo: self.add_virtual_u32_target(),
+            hi: self.add_virtual_u32_target(),
+        }
+    }
+
+    pub fn constant_u32(&mut self, c: u32) -> U32Target {
+        U32Target(self.constant(F::from_canonical_u32(c)))
+    }
+
+    pub fn constant_u64(&mut self, c: u64) -> U64Target {
+        U64Target {
+            lo: self.constant_u32(c as u32),
+            hi: self.constant_u32((c >> 32) as u32),
+        }
+    }
+
+    pub fn connect_u64(&mut self, x: U64Target, y: U64Target) {
+        self.connect(x.lo.0, y.lo.0);
+        self.connect(x.hi.0, y.hi.0);
+    }
+
+    /// Splits `x < 2^num_bits` into its low 32 bits and the remaining high bits.
+    pub(crate) fn split_u32(&mut self, x: Target, num_bits: usize) -> (U32Target, Target) {
+        let (low, high) = self.split_low_high(x, 32, num_bits);
+        if num_bits >= F::BITS {
+            // `low + 2^32 * high` may also be `x + F::ORDER`. The decomposition is that of a
+            // canonical value exactly when `high` is not maximal or `low` is zero.
+            let max_high = self.constant(F::from_canonical_u32(u32::MAX));
+            let high_is_max = self.is_equal(high, max_high);
+            let low_if_max = self.mul(high_is_max.target, low);
+            self.assert_zero(low_if_max);
+        }
+        (U32Target(low), high)
+    }
+
+    /// Computes `x + y + carry`, returning the low 64 bits of the sum and the carry out.
+    pub fn add_u64_with_carry(
+        &mut self,
+        x: U64Target,
+        y: U64Target,
+        carry: BoolTarget,
+    ) -> (U64Target, BoolTarget) {
+        let lo_sum = self.add_many([x.lo.0, y.lo.0, carry.target]);
+        let (lo, lo_carry) = self.split_u32(lo_sum, 33);
+        let hi_sum = self.add_many([x.hi.0, y.hi.0, lo_carry]);
+        let (hi, hi_carry) = self.split_u32(hi_sum, 33);
+        // `new_unsafe` is safe here because `split_u32` range-checks the carry to one bit.
+        (U64Target { lo, hi }, BoolTarget::new_unsafe(hi_carry))
+    }
+
+    /// Computes `x + y` modulo `2^64`.
+    pub fn add_u64(&mut self, x: U64Target, y: U64Target) -> U64Target {
+        let zero = self._false();
+        self.add_u64_with_carry(x, y, zero).0
+    }
+
+    /// Computes `x - y - borrow` modulo `2^64`, returning the difference and the borrow out, which
+    /// is true exactly when `x < y + borrow`.
+    pub fn sub_u64_with_borrow(
+        &mut self,
+        x: U64Target,
+        y: U64Target,
+        borrow: BoolTarget,
+    ) -> (U64Target, BoolTarget) {
+        // Each limb difference is offset by `2^32`, so that the bit above the low 32 is set exactly
+        // when that limb does not borrow.
+        let two_32 = F::from_canonical_u64(1 << 32);
+        let lo_diff = self.sub(x.lo.0, y.lo.0);
+        let lo_diff = self.sub(lo_diff, borrow.target);
+        let lo_diff = self.add_const(lo_diff, two_32);
+        let (lo, lo_no_borrow) = self.split_u32(lo_diff, 33);
+        let hi_diff = self.sub(x.hi.0, y.hi.0);
+        let hi_diff = self.add(hi_diff, lo_no_borrow);
+        let hi_diff = self.add_const(hi_diff, two_32 - F::ONE);
+        let (hi, hi_no_borrow) = self.split_u32(hi_diff, 33);
+        // `new_unsafe` is safe here because `split_u32` range-checks `hi_no_borrow` to one bit.
+        let borrow = self.not(BoolTarget::new_unsafe(hi_no_borrow));
+        (U64Target { lo, hi }, borrow)
+    }
+
+    /// Computes `x - y` modulo `2^64`.
+    pub fn sub_u64(&mut self, x: U64Target, y: U64Target) -> U64Target {
+        let zero = self._false();
+        self.sub_u64_with_borrow(x, y, zero).0
+    }
+
+    /// Splits the product of two 32-bit limbs into its low and high 32 bits.
+    fn mul_u32_limbs(&mut self, x: U32Target, y: U32Target) -> (U32Target, U32Target) {
+        let product = self.mul(x.0, y.0);
+        let (lo, hi) = self.split_u32(product, 64);
+        (lo, U32Target(hi))
+    }
+
+    /// Computes `x * y` modulo `2^64`.
+    pub fn mul_u64(&mut self, x: U64Target, y: U64Target) -> U64Target {
+        let (p00_lo, p00_hi) = self.mul_u32_limbs(x.lo, y.lo);
+        let (p01_lo, _) = self.mul_u32_limbs(x.lo, y.hi);
+        let (p10_lo, _) = self.mul_u32_limbs(x.hi, y.lo);
+        let limb1 = self.add_many([p00_hi.0, p01_lo.0, p10_lo.0]);
+        let (hi, _) = self.split_u32(limb1, 34);
+        U64Target { lo: p00_lo, hi }
+    }
+
+    /// Computes the full 128-bit product `x * y`, returning its low and high 64 bits.
+    pub fn mul_u64_wide(&mut self, x: U64Target, y: U64Target) -> (U64Target, U64Target) {
+        let (p00_lo, p00_hi) = self.mul_u32_limbs(x.lo, y.lo);
+        let (p01_lo, p01_hi) = self.mul_u32_limbs(x.lo, y.hi);
+        let (p10_lo, p10_hi) = self.mul_u32_limbs(x.hi, y.lo);
+        let (p11_lo, p11_hi) = self.mul_u32_limbs(x.hi, y.hi);
+
+        let limb1 = self.add_many([p00_hi.0, p01_lo.0, p10_lo.0]);
+        let (limb1, carry1) = self.split_u32(limb1, 34);
+        let limb2 = self.add_many([p01_hi.0, p10_hi.0, p11_lo.0, carry1]);
+        let (limb2, carry2) = self.split_u32(limb2, 34);
+        // The product is below `2^128`, so the top limb cannot carry.
+        let limb3 = self.add(p11_hi.0, carry2);
+        self.range_check(limb3, 32);
+
+        let low = U64Target {
+            lo: p00_lo,
+            hi: limb1,
+        };
+        let high = U64Target {
+            lo: limb2,
+            hi: U32Target(limb3),
+        };
+        (low, high)
+    }
+
+    /// Returns whether `x < y`.
+    pub fn lt_u64(&mut self, x: U64Target, y: U64Target) -> BoolTarget {
+        let zero = self._false();
+        self.sub_u64_with_borrow(x, y, zero).1
+    }
+
+    /// Returns whether `x == y`.
+    pub fn eq_u64(&mut self, x: U64Target, y: U64Target) -> BoolTarget {
+        let lo_equal = self.is_equal(x.lo.0, y.lo.0);
+        let hi_equal = self.is_equal(x.hi.0, y.hi.0);
+        self.and(lo_equal, hi_equal)
+    }
+
+    /// Computes the bitwise XOR of `x` and `y`.
+    pub fn xor_u64(&mut self, x: U64Target, y: U64Target) -> U64Target {
+        U64Target {
+            lo: self.xor_u32(x.lo, y.lo),
+            hi: self.xor_u32(x.hi, y.hi),
+        }
+    }
+
+    fn xor_u32(&mut self, x: U32Target, y: U32Target) -> U32Target {
+        let x_bits = self.split_le(x.0, 32);
+        let y_bits = self.split_le(y.0, 32);
+        let bits = x_bits
+            .into_iter()
+            .zip(y_bits)
+            .map(|(a, b)| {
+                // `a ^ b = a + b - 2ab`.
+                let sum = self.add(a.target, b.target);
+                let xor = self.arithmetic(
+                    F::from_noncanonical_i64(-2),
+                    F::ONE,
+                    a.target,
+                    b.target,
+                    sum,
+                );
+                BoolTarget::new_unsafe(xor)
+            })
+            .collect::<Vec<_>>();
+        U32Target(self.le_sum(bits.into_iter()))
+    }
+
+    /// Splits a limb into its low `n` bits and its high `32 - n` bits, for `0 < n < 32`.
+    fn split_u32_at(&mut self, x: U32Target, n: usize) -> (Target, Target) {
+        self.split_low_high(x.0, n, 32)
+    }
+
+    /// Computes `x << n` modulo `2^64`, for a constant `n < 64`.
+    pub fn shl_u64(&mut self, x: U64Target, n: usize) -> U64Target {
+        assert!(n < 64, "shift amount {n} is not below 64");
+        let zero = U32Target(self.zero());
+        if n == 0 {
+            return x;
+        }
+        if n >= 32 {
+            return self.shl_u64(U64Target { lo: zero, hi: x.lo }, n - 32);
+        }
+        let shift = F::from_canonical_u64(1 << n);
+        let (lo_low, lo_high) = self.split_u32_at(x.lo, 32 - n);
+        let (hi_low, _) = self.split_u32_at(x.hi, 32 - n);
+        let lo = self.mul_const(shift, lo_low);
+        let hi = self.mul_const_add(shift, hi_low, lo_high);
+        U64Target {
+            lo: U32Target(lo),
+            hi: U32Target(hi),
+        }
+    }
+
+    /// Computes `x >> n`, for a constant `n < 64`.
+    pub fn shr_u64(&mut self, x: U64Target, n: usize) -> U64Target {
+        assert!(n < 64, "shift amount {n} is not below 64");
+        let zero = U32Target(self.zero());
+        if n == 0 {
+            return x;
+        }
+        if n >= 32 {
+            return self.shr_u64(U64Target { lo: x.hi, hi: zero }, n - 32);
+        }
+        let (_, lo_high) = self.split_u32_at(x.lo, n);
+        let (hi_low, hi_high) = self.split_u32_at(x.hi, n);
+        let lo = self.mul_const_add(F::from_canonical_u64(1 << (32 - n)), hi_low, lo_high);
+        U64Target {
+            lo: U32Target(lo),
+            hi: U32Target(hi_high),
+        }
+    }
+
+    /// Rotates `x` left by a constant `n < 64` bits.
+    pub fn rotl_u64(&mut self, x: U64Target, n: usize) -> U64Target {
+        assert!(n < 64, "rotation amount {n} is not below 64");
+        if n == 0 {
+            return x;
+        }
+        if n >= 32 {
+            return self.rotl_u64(U64Target { lo: x.hi, hi: x.lo }, n - 32);
+        }
+        let shift = F::from_canonical_u64(1 << n);
+        let (lo_low, lo_high) = self.split_u32_at(x.lo, 32 - n);
+        let (hi_low, hi_high) = self.split_u32_at(x.hi, 32 - n);
+        let lo = self.mul_const_add(shift, lo_low, hi_high);
+        let hi = self.mul_const_add(shift, hi_low, lo_high);
+        U64Target {
+            lo: U32Target(lo),
+            hi: U32Target(hi),
+        }
+    }
+
+    /// Rotates `x` right by a constant `n < 64` bits.
+    pub fn rotr_u64(&mut self, x: U64Target, n: usize) -> U64Target {
+        assert!(n < 64, "rotation amount {n} is not below 64");
+        self.rotl_u64(x, (64 - n) % 64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::types::Field64;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Proves a circuit applying `op` to the two inputs, with its outputs connected to `expected`.
+    fn prove_binary_op<const N: usize>(
+        pairs: &[(u64, u64)],
+        op: impl Fn(&mut CircuitBuilder<F, D>, U64Target, U64Target) -> [U64Target; N],
+        expected: impl Fn(u64, u64) -> [u64; N],
+    ) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+        for &(x, y) in pairs {
+            let x_t = builder.add_virtual_u64_target();
+            let y_t = builder.add_virtual_u64_target();
+            for (out, value) in op(&mut builder, x_t, y_t).into_iter().zip(expected(x, y)) {
+                let value = builder.constant_u64(value);
+                builder.connect_u64(out, value);
+            }
+            pw.set_u64_target(x_t, x);
+            pw.set_u64_target(y_t, y);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    /// The boundary cases, followed by random pairs.
+    fn test_pairs() -> Vec<(u64, u64)> {
+        let mut pairs = vec![
+            (u64::MAX, 1),
+            (0, 1),
+            (u64::MAX, u64::MAX),
+            (0, 0),
+            (u32::MAX as u64, 1),
+            (1 << 32, 1),
+            (F::ORDER, F::ORDER),
+        ];
+        pairs.extend((0..4).map(|_| (OsRng.gen(), OsRng.gen())));
+        pairs
+    }
+
+    fn u64_from_bool(builder: &mut CircuitBuilder<F, D>, b: BoolTarget) -> U64Target {
+        let zero = builder.zero();
+        U64Target {
+            lo: U32Target(b.target),
+            hi: U32Target(zero),
+        }
+    }
+
+    #[test]
+    fn test_add_u64() -> Result<()> {
+        prove_binary_op(
+            &test_pairs(),
+            |builder, x, y| {
+                let carry_in = builder._true();
+                let sum = builder.add_u64(x, y);
+                let (sum_with_carry, carry_out) = builder.add_u64_with_carry(x, y, carry_in);
+                let carry_out = u64_from_bool(builder, carry_out);
+                [sum, sum_with_carry, carry_out]
+            },
+            |x, y| {
+                let sum = x as u128 + y as u128 + 1;
+                [x.wrapping_add(y), sum as u64, (sum >> 64) as u64]
+            },
+        )
+    }
+
+    #[test]
+    fn test_sub_u64() -> Result<()> {
+        prove_binary_op(
+            &test_pairs(),
+            |builder, x, y| {
+                let borrow_in = builder._true();
+                let diff = builder.sub_u64(x, y);
+                let (diff_with_borrow, borrow_out) = builder.sub_u64_with_borrow(x, y, borrow_in);
+                let borrow_out = u64_from_bool(builder, borrow_out);
+                [diff, diff_with_borrow, borrow_out]
+            },
+            |x, y| {
+                let (diff, borrow1) = x.overflowing_sub(y);
+                let (diff_with_borrow, borrow2) = diff.overflowing_sub(1);
+                [diff, diff_with_borrow, (borrow1 || borrow2) as u64]
+            },
+        )
+    }
+
+    #[test]
+    fn test_mul_u64() -> Result<()> {
+        prove_binary_op(
+            &test_pairs(),
+            |builder, x, y| {
+                let product = builder.mul_u64(x, y);
+                let (low, high) = builder.mul_u64_wide(x, y);
+                [product, low, high]
+            },
+            |x, y| {
+                let product = x as u128 * y as u128;
+                [x.wrapping_mul(y), product as u64, (product >> 64) as u64]
+            },
+        )
+    }
+
+    #[test]
+    fn test_compare_u64() -> Result<()> {
+        let mut pairs = test_pairs();
+        pairs.extend([(5, 5), (u64::MAX, u64::MAX - 1), (1 << 32, (1 << 32) - 1)]);
+        prove_binary_op(
+            &pairs,
+            |builder, x, y| {
+                let lt = builder.lt_u64(x, y);
+                let eq = builder.eq_u64(x, y);
+                [u64_from_bool(builder, lt), u64_from_bool(builder, eq)]
+            },
+            |x, y| [(x < y) as u64, (x == y) as u64],
+        )
+    }
+
+    #[test]
+    fn test_shifts_u64() -> Result<()> {
+        for n in [0, 1, 13, 31, 32, 33, 63] {
+            prove_binary_op(
+                &test_pairs(),
+                |builder, x, y| {
+                    [
+                        builder.shl_u64(x, n),
+                        builder.shr_u64(x, n),
+                        builder.rotl_u64(x, n),
+                        builder.rotr_u64(x, n),
+                        builder.xor_u64(x, y),
+                    ]
+                },
+                |x, y| {
+                    [
+                        x << n,
+                        x >> n,
+                        x.rotate_left(n as u32),
+                        x.rotate_right(n as u32),
+                        x ^ y,
+                    ]
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_add_u64_wrong_sum() {
+        prove_binary_op(
+            &[(u64::MAX, 1)],
+            |builder, x, y| [builder.add_u64(x, y)],
+            |_, _| [1 << 32],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_xorshift64() -> Result<()> {
+        fn xorshift64(mut x: u64) -> u64 {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        }
+
+        const STEPS: usize = 4;
+        let seed = 0x2545_f491_4f6c_dd1d;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let seed_t = builder.add_virtual_u64_target();
+        let mut x = seed_t;
+        for _ in 0..STEPS {
+            let shifted = builder.shl_u64(x, 13);
+            x = builder.xor_u64(x, shifted);
+            let shifted = builder.shr_u64(x, 7);
+            x = builder.xor_u64(x, shifted);
+            let shifted = builder.shl_u64(x, 17);
+            x = builder.xor_u64(x, shifted);
+        }
+        let expected = (0..STEPS).fold(seed, |x, _| xorshift64(x));
+        let expected = builder.constant_u64(expected);
+        builder.connect_u64(x, expected);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_u64_target(seed_t, seed);
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
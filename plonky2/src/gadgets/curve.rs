@@ -0,0 +1,376 @@
+//! Points of short Weierstrass curves over the base field, and fixed-base scalar multiplication.
+//!
+//! [`CircuitBuilder::fixed_base_scalar_mul`] adds the precomputed multiples `2^i * base` selected
+//! by the scalar's bits, several steps per row of a [`MulAddPointGate`]. Affine addition is not
+//! complete, so the curve's group must have prime order larger than the scalars, which rules out
+//! every exceptional case but the identity. The identity is tracked by a flag.
+
+use alloc::vec;
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::gates::mul_add_point::MulAddPointGate;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The curve `y^2 = x^3 + a x + b` over the base field, whose group of points has prime order of
+/// at least `2^min_order_bits`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Curve<F: Field> {
+    pub a: F,
+    pub b: F,
+    pub min_order_bits: usize,
+}
+
+/// A point of a [`Curve`] known when building the circuit. The identity has both coordinates zero.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CurvePointConstant<F: Field> {
+    pub x: F,
+    pub y: F,
+    pub is_identity: bool,
+}
+
+/// A point of a [`Curve`] in the circuit. The identity has both coordinates zero.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CurvePointTarget {
+    pub x: Target,
+    pub y: Target,
+    pub is_identity: BoolTarget,
+}
+
+impl<F: Field> CurvePointConstant<F> {
+    pub const IDENTITY: Self = Self {
+        x: F::ZERO,
+        y: F::ZERO,
+        is_identity: true,
+    };
+
+    pub const fn affine(x: F, y: F) -> Self {
+        Self {
+            x,
+            y,
+            is_identity: false,
+        }
+    }
+}
+
+impl<F: Field> Curve<F> {
+    pub fn contains(&self, p: CurvePointConstant<F>) -> bool {
+        p.is_identity || p.y.square() == p.x.cube() + self.a * p.x + self.b
+    }
+
+    pub fn neg(&self, p: CurvePointConstant<F>) -> CurvePointConstant<F> {
+        if p.is_identity {
+            p
+        } else {
+            CurvePointConstant::affine(p.x, -p.y)
+        }
+    }
+
+    pub fn add(&self, p: CurvePointConstant<F>, q: CurvePointConstant<F>) -> CurvePointConstant<F> {
+        if p.is_identity {
+            return q;
+        }
+        if q.is_identity {
+            return p;
+        }
+        let lambda = if p.x != q.x {
+            (q.y - p.y) / (q.x - p.x)
+        } else if p.y == q.y && p.y.is_nonzero() {
+            (F::from_canonical_u64(3) * p.x.square() + self.a) / p.y.double()
+        } else {
+            // `q = -p`.
+            return CurvePointConstant::IDENTITY;
+        };
+        let x = lambda.square() - p.x - q.x;
+        CurvePointConstant::affine(x, lambda * (p.x - x) - p.y)
+    }
+
+    pub fn double(&self, p: CurvePointConstant<F>) -> CurvePointConstant<F> {
+        self.add(p, p)
+    }
+
+    /// Computes `scalar * p` by double-and-add.
+    pub fn mul(&self, p: CurvePointConstant<F>, scalar: u64) -> CurvePointConstant<F> {
+        let mut result = CurvePointConstant::IDENTITY;
+        let mut power = p;
+        for i in 0..u64::BITS - scalar.leading_zeros() {
+            if (scalar >> i) & 1 == 1 {
+                result = self.add(result, power);
+            }
+            power = self.double(power);
+        }
+        result
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    pub fn constant_curve_point(&mut self, p: CurvePointConstant<F>) -> CurvePointTarget {
+        CurvePointTarget {
+            x: self.constant(p.x),
+            y: self.constant(p.y),
+            is_identity: self.constant_bool(p.is_identity),
+        }
+    }
+
+    pub fn connect_curve_points(&mut self, p: CurvePointTarget, q: CurvePointTarget) {
+        self.connect(p.x, q.x);
+        self.connect(p.y, q.y);
+        self.connect(p.is_identity.target, q.is_identity.target);
+    }
+
+    /// Computes `sum_i scalar_bits[i] * 2^i * base`, with the scalar's bits in little-endian
+    /// order. There may be at most `curve.min_order_bits` of them.
+    pub fn fixed_base_scalar_mul(
+        &mut self,
+        curve: &Curve<F>,
+        base: CurvePointConstant<F>,
+        scalar_bits: &[BoolTarget],
+    ) -> CurvePointTarget {
+        assert!(curve.contains(base), "The base is not on the curve");
+        assert!(
+            scalar_bits.len() <= curve.min_order_bits,
+            "Scalars of {} bits may exceed the order of the curve",
+            scalar_bits.len()
+        );
+        if base.is_identity {
+            return self.constant_curve_point(base);
+        }
+
+        // Before step `i`, the accumulator is `k * base` for some `k < 2^i`, which is neither
+        // `2^i * base` nor its negation since `k + 2^i < 2^(i + 1)` is below the order.
+
+        let gate = MulAddPointGate::new_from_config(&self.config);
+        let mut accumulator = self.constant_curve_point(CurvePointConstant::IDENTITY);
+        let mut multiple = base;
+        for chunk in scalar_bits.chunks(gate.num_steps) {
+            let row = self.add_gate(gate, vec![]);
+            let wire = |column| Target::wire(row, column);
+            self.connect(
+                accumulator.is_identity.target,
+                wire(MulAddPointGate::<F, D>::WIRE_IS_IDENTITY_IN),
+            );
+            self.connect(accumulator.x, wire(MulAddPointGate::<F, D>::WIRE_X_IN));
+            self.connect(accumulator.y, wire(MulAddPointGate::<F, D>::WIRE_Y_IN));
+
+            for i in 0..gate.num_steps {
+                // Steps past the end of the scalar add nothing.
+                let (bit, x, y) = match chunk.get(i) {
+                    Some(&bit) => {
+                        let x = self.constant(multiple.x);
+                        let y = self.constant(multiple.y);
+                        multiple = curve.double(multiple);
+                        (bit, x, y)
+                    }
+                    None => {
+                        let zero = self.zero();
+                        (self._false(), zero, zero)
+                    }
+                };
+                self.connect(bit.target, wire(gate.wire_bit(i)));
+                self.connect(x, wire(gate.wire_point_x(i)));
+                self.connect(y, wire(gate.wire_point_y(i)));
+            }
+
+            accumulator = CurvePointTarget {
+                x: wire(MulAddPointGate::<F, D>::WIRE_X_OUT),
+                y: wire(MulAddPointGate::<F, D>::WIRE_Y_OUT),
+                is_identity: BoolTarget::new_unsafe(wire(
+                    MulAddPointGate::<F, D>::WIRE_IS_IDENTITY_OUT,
+                )),
+            };
+        }
+        accumulator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// `y^2 = x^3 + 3 x + 99`, whose group of points has prime order `ORDER`.
+    const CURVE: Curve<GoldilocksField> = Curve {
+        a: GoldilocksField(3),
+        b: GoldilocksField(99),
+        min_order_bits: 63,
+    };
+    const ORDER: u64 = 0xfffffffff0c8ccb7;
+    const GENERATOR: CurvePointConstant<GoldilocksField> =
+        CurvePointConstant::affine(GoldilocksField(2), GoldilocksField(7949046337338096808));
+
+    fn scalar_bits(builder: &mut CircuitBuilder<F, D>, scalar: u64) -> Vec<BoolTarget> {
+        (0..CURVE.min_order_bits)
+            .map(|i| builder.constant_bool((scalar >> i) & 1 == 1))
+            .collect()
+    }
+
+    #[test]
+    fn test_native_curve() {
+        let g = GENERATOR;
+        let identity = CurvePointConstant::IDENTITY;
+        assert!(CURVE.contains(g));
+        assert!(!CURVE.contains(CurvePointConstant::affine(g.x, g.y + F::ONE)));
+
+        assert_eq!(CURVE.add(g, identity), g);
+        assert_eq!(CURVE.add(identity, g), g);
+        assert_eq!(CURVE.add(g, CURVE.neg(g)), identity);
+        assert_eq!(CURVE.add(g, g), CURVE.double(g));
+        assert_eq!(CURVE.mul(g, 2), CURVE.double(g));
+        assert_eq!(CURVE.mul(g, 0), identity);
+        assert_eq!(CURVE.mul(g, ORDER), identity);
+        assert_eq!(CURVE.mul(g, ORDER - 1), CURVE.neg(g));
+        assert_eq!(CURVE.mul(identity, 12345), identity);
+
+        let (s, t) = (OsRng.gen::<u32>() as u64, OsRng.gen::<u32>() as u64);
+        let p = CURVE.mul(g, s);
+        assert!(CURVE.contains(p));
+        assert_eq!(CURVE.add(p, CURVE.mul(g, t)), CURVE.mul(g, s + t));
+        assert_eq!(CURVE.mul(p, t), CURVE.mul(g, s * t));
+    }
+
+    #[test]
+    fn test_fixed_base_scalar_mul() -> Result<()> {
+        let max_scalar = (1 << CURVE.min_order_bits) - 1;
+        let scalars = [0, 1, 2, 3, max_scalar, 1 << 62, OsRng.gen::<u64>() >> 1];
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        for scalar in scalars {
+            let bits = scalar_bits(&mut builder, scalar);
+            let product = builder.fixed_base_scalar_mul(&CURVE, GENERATOR, &bits);
+            let expected = builder.constant_curve_point(CURVE.mul(GENERATOR, scalar));
+            builder.connect_curve_points(product, expected);
+        }
+
+        // A scalar given by the witness.
+        let bits = (0..CURVE.min_order_bits)
+            .map(|_| builder.add_virtual_bool_target_safe())
+            .collect::<Vec<_>>();
+        let product = builder.fixed_base_scalar_mul(&CURVE, GENERATOR, &bits);
+        builder.register_public_input(product.x);
+        builder.register_public_input(product.y);
+        builder.register_public_input(product.is_identity.target);
+
+        let data = builder.build::<C>();
+        let scalar = OsRng.gen::<u64>() >> 1;
+        let mut pw = PartialWitness::new();
+        for (i, &bit) in bits.iter().enumerate() {
+            pw.set_bool_target(bit, (scalar >> i) & 1 == 1);
+        }
+        let proof = data.prove(pw)?;
+        let expected = CURVE.mul(GENERATOR, scalar);
+        assert_eq!(
+            proof.public_inputs,
+            vec![expected.x, expected.y, F::from_bool(expected.is_identity)]
+        );
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_fixed_base_scalar_mul_identity_base() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let bits = scalar_bits(&mut builder, 12345);
+        let product = builder.fixed_base_scalar_mul(&CURVE, CurvePointConstant::IDENTITY, &bits);
+        let identity = builder.constant_curve_point(CurvePointConstant::IDENTITY);
+        builder.connect_curve_points(product, identity);
+        let data = builder.build::<C>();
+        data.verify(data.prove(PartialWitness::new())?)
+    }
+
+    #[test]
+    #[should_panic(expected = "may exceed the order")]
+    fn test_fixed_base_scalar_mul_too_many_bits() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let bits = vec![builder._true(); CURVE.min_order_bits + 1];
+        builder.fixed_base_scalar_mul(&CURVE, GENERATOR, &bits);
+    }
+
+    /// Adds `scalar_bits[i] * 2^i * base` for each `i` with generic arithmetic.
+    fn naive_fixed_base_scalar_mul(
+        builder: &mut CircuitBuilder<F, D>,
+        base: CurvePointConstant<F>,
+        scalar_bits: &[BoolTarget],
+    ) -> CurvePointTarget {
+        let mut accumulator = builder.constant_curve_point(CurvePointConstant::IDENTITY);
+        let mut multiple = base;
+        for &bit in scalar_bits {
+            let point = builder.constant_curve_point(multiple);
+            multiple = CURVE.double(multiple);
+
+            let dx = builder.sub(point.x, accumulator.x);
+            let dy = builder.sub(point.y, accumulator.y);
+            let lambda = builder.div(dy, dx);
+            let x_sum = builder.mul_sub(lambda, lambda, point.x);
+            let x_sum = builder.sub(x_sum, accumulator.x);
+            let x_drop = builder.sub(accumulator.x, x_sum);
+            let y_sum = builder.mul_sub(lambda, x_drop, accumulator.y);
+
+            let x_added = builder.select(accumulator.is_identity, point.x, x_sum);
+            let y_added = builder.select(accumulator.is_identity, point.y, y_sum);
+            let not_bit = builder.not(bit);
+            accumulator = CurvePointTarget {
+                x: builder.select(bit, x_added, accumulator.x),
+                y: builder.select(bit, y_added, accumulator.y),
+                is_identity: builder.and(accumulator.is_identity, not_bit),
+            };
+        }
+        accumulator
+    }
+
+    #[test]
+    fn test_row_count() -> Result<()> {
+        let scalar = OsRng.gen::<u64>() >> 1;
+        let expected = CURVE.mul(GENERATOR, scalar);
+
+        let mut num_rows = vec![];
+        for naive in [false, true] {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let bits = (0..CURVE.min_order_bits)
+                .map(|_| builder.add_virtual_bool_target_safe())
+                .collect::<Vec<_>>();
+            // Both constructions use the same constants, so count the other rows only.
+            let before = builder.num_gates();
+            let product = if naive {
+                naive_fixed_base_scalar_mul(&mut builder, GENERATOR, &bits)
+            } else {
+                builder.fixed_base_scalar_mul(&CURVE, GENERATOR, &bits)
+            };
+            num_rows.push(builder.num_gates() - before);
+
+            let expected = builder.constant_curve_point(expected);
+            builder.connect_curve_points(product, expected);
+            let data = builder.build::<C>();
+            let mut pw = PartialWitness::new();
+            for (i, &bit) in bits.iter().enumerate() {
+                pw.set_bool_target(bit, (scalar >> i) & 1 == 1);
+            }
+            data.verify(data.prove(pw)?)?;
+        }
+
+        // 63 bits take 4 rows of 18 steps, while generic arithmetic needs about one row per bit.
+        assert_eq!(num_rows[0], 4);
+        assert!(
+            num_rows[1] > 10 * num_rows[0],
+            "{} rows for the naive construction",
+            num_rows[1]
+        );
+        Ok(())
+    }
+}
@@ -4,12 +4,17 @@
 
 pub mod arithmetic;
 pub mod arithmetic_extension;
+pub mod arithmetic_u64;
+pub mod curve;
 pub mod hash;
 pub mod interpolation;
 pub mod lookup;
 pub mod polynomial;
+pub mod poseidon_bn254;
+pub mod public_input_set;
 pub mod random_access;
 pub mod range_check;
+pub mod running_sum;
 pub mod select;
 pub mod split_base;
 pub mod split_join;
@@ -0,0 +1,741 @@
+//! Poseidon over the BN254 scalar field in circuits, as computed natively by
+//! [`PoseidonBN254Hash`] and [`PoseidonBN254Permutation`].
+//!
+//! A BN254 element doesn't fit in a field element, so [`Bn254Target`] holds it as sixteen 16-bit
+//! limbs, and arithmetic modulo the BN254 modulus `r` is emulated. An integer expression `e` of
+//! limbs is reduced by witnessing `q` and `y`, whose limbs are range-checked, and checking that
+//! `e - q r - y` is zero. This is checked on the 32-bit columns of the expression, each the sum of
+//! its terms in a single target, with carries between columns. The bounds of the terms are known
+//! when building the circuit, and are checked to be low enough that no column, and no carry, can
+//! wrap around the order of the field, so that the identity holds over the integers.
+//!
+//! [`PoseidonBN254Hash`]: crate::hash::poseidon_bn254::PoseidonBN254Hash
+//! [`PoseidonBN254Permutation`]: crate::hash::poseidon_bn254::PoseidonBN254Permutation
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+
+use num::{BigUint, One, Zero};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
+use crate::hash::poseidon_bn254::{
+    digest_digits, Bn254Scalar, ELTS_PER_LIMB_PACK, HALF_N_FULL_ROUNDS, MDS_MATRIX,
+    N_PARTIAL_ROUNDS, N_ROUNDS, ROUND_CONSTANTS, SPONGE_WIDTH, WIDTH,
+};
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// The number of bits of the limbs of a [`Bn254Target`].
+const LIMB_BITS: usize = 16;
+const LIMB_MAX: u64 = (1 << LIMB_BITS) - 1;
+/// The number of limbs of a [`Bn254Target`].
+pub const NUM_BN254_LIMBS: usize = 16;
+/// The number of bits of the columns on which identities are checked. A product of two limbs,
+/// shifted by one limb, takes 48 bits, which leaves room for the sum of the products of a column.
+const COLUMN_BITS: usize = 32;
+
+/// An integer congruent to an element of the BN254 scalar field, as its little-endian 16-bit
+/// limbs, each range-checked. The elements returned by the gadgets below are canonical, i.e. below
+/// the modulus.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Bn254Target {
+    pub limbs: [Target; NUM_BN254_LIMBS],
+}
+
+/// The limbs of an integer, each with a bound known when building the circuit.
+#[derive(Clone, Debug)]
+struct BoundedLimbs {
+    limbs: Vec<Target>,
+    max: Vec<u64>,
+}
+
+impl From<Bn254Target> for BoundedLimbs {
+    fn from(x: Bn254Target) -> Self {
+        Self {
+            limbs: x.limbs.to_vec(),
+            max: vec![LIMB_MAX; NUM_BN254_LIMBS],
+        }
+    }
+}
+
+fn biguint(limbs: &[u64; 4]) -> BigUint {
+    limbs
+        .iter()
+        .rev()
+        .fold(BigUint::zero(), |acc, &limb| (acc << 64) + limb)
+}
+
+fn bn254_modulus() -> BigUint {
+    biguint(&Bn254Scalar::MODULUS)
+}
+
+/// The 16-bit limbs of `value`, followed by zeros.
+fn limbs_of(value: &BigUint) -> impl Iterator<Item = u64> + '_ {
+    value
+        .iter_u32_digits()
+        .flat_map(|d| [d as u64 & LIMB_MAX, d as u64 >> LIMB_BITS])
+        .chain(iter::repeat(0))
+}
+
+fn limbs_value<F: RichField>(witness: &PartitionWitness<F>, limbs: &[Target]) -> BigUint {
+    limbs.iter().rev().fold(BigUint::zero(), |acc, &limb| {
+        (acc << LIMB_BITS) + witness.get_target(limb).to_canonical_u64()
+    })
+}
+
+fn set_limbs<F: RichField>(out_buffer: &mut GeneratedValues<F>, limbs: &[Target], value: &BigUint) {
+    debug_assert!(value.bits() as usize <= limbs.len() * LIMB_BITS);
+    for (&limb, v) in limbs.iter().zip(limbs_of(value)) {
+        out_buffer.set_target(limb, F::from_canonical_u64(v));
+    }
+}
+
+/// A signed integer expression, as the sums of its 32-bit columns. The sums are targets, and the
+/// bounds of their positive and negative terms are known when building the circuit.
+#[derive(Default)]
+struct Columns {
+    sums: Vec<Option<Target>>,
+    max_pos: Vec<u128>,
+    max_neg: Vec<u128>,
+}
+
+impl Columns {
+    fn sum<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        column: usize,
+    ) -> Target {
+        if self.sums.len() <= column {
+            self.sums.resize(column + 1, None);
+            self.max_pos.resize(column + 1, 0);
+            self.max_neg.resize(column + 1, 0);
+        }
+        *self.sums[column].get_or_insert_with(|| builder.zero())
+    }
+
+    fn add_bound(&mut self, column: usize, max: u128, negate: bool) {
+        if negate {
+            self.max_neg[column] += max;
+        } else {
+            self.max_pos[column] += max;
+        }
+    }
+
+    /// Adds `c x y 2^offset`, for a small constant `c`.
+    fn add_product<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        offset: usize,
+        c: u64,
+        (x, x_max): (Target, u64),
+        (y, y_max): (Target, u64),
+    ) {
+        let (column, shift) = (offset / COLUMN_BITS, offset % COLUMN_BITS);
+        let sum = self.sum(builder, column);
+        let c = c << shift;
+        self.sums[column] = Some(builder.arithmetic(F::from_canonical_u64(c), F::ONE, x, y, sum));
+        self.add_bound(column, c as u128 * x_max as u128 * y_max as u128, false);
+    }
+
+    /// Adds `±c x 2^offset`, for a constant `c`.
+    fn add_scaled<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        offset: usize,
+        (x, x_max): (Target, u64),
+        c: &BigUint,
+        negate: bool,
+    ) {
+        let (column, shift) = (offset / COLUMN_BITS, offset % COLUMN_BITS);
+        for (i, chunk) in (c << shift).iter_u32_digits().enumerate() {
+            if chunk == 0 {
+                continue;
+            }
+            let sum = self.sum(builder, column + i);
+            let coeff = F::from_canonical_u32(chunk);
+            let coeff = if negate { -coeff } else { coeff };
+            self.sums[column + i] = Some(builder.mul_const_add(coeff, x, sum));
+            self.add_bound(column + i, chunk as u128 * x_max as u128, negate);
+        }
+    }
+
+    /// Adds `±c x 2^offset`, for a constant `c` and the integer `x` of the given limbs.
+    fn add_limbs<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        offset: usize,
+        x: &BoundedLimbs,
+        c: &BigUint,
+        negate: bool,
+    ) {
+        for (i, (&limb, &max)) in x.limbs.iter().zip(&x.max).enumerate() {
+            self.add_scaled(builder, offset + i * LIMB_BITS, (limb, max), c, negate);
+        }
+    }
+
+    /// Adds `±c`, for a constant `c`.
+    fn add_constant<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        c: &BigUint,
+        negate: bool,
+    ) {
+        for (column, chunk) in c.iter_u32_digits().enumerate() {
+            let sum = self.sum(builder, column);
+            let coeff = F::from_canonical_u32(chunk);
+            let coeff = if negate { -coeff } else { coeff };
+            self.sums[column] = Some(builder.add_const(sum, coeff));
+            self.add_bound(column, chunk as u128, negate);
+        }
+    }
+
+    /// The largest value of the expression. Only meaningful if it has no negative terms.
+    fn max_value(&self) -> BigUint {
+        debug_assert!(self.max_neg.iter().all(|&max| max == 0));
+        self.max_pos
+            .iter()
+            .rev()
+            .fold(BigUint::zero(), |acc, &max| (acc << COLUMN_BITS) + max)
+    }
+
+    fn targets<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Vec<Target> {
+        (0..self.sums.len()).map(|i| self.sum(builder, i)).collect()
+    }
+
+    /// Asserts that the expression is zero.
+    ///
+    /// Each column sum, plus the carry from the previous column, is asserted to be `2^32` times
+    /// the next carry, which is range-checked. Both sides are bounded in absolute value by the
+    /// order of the field, so the identity holds over the integers, and so does the telescoping
+    /// sum of all columns.
+    fn assert_zero<F: RichField + Extendable<D>, const D: usize>(
+        mut self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) {
+        let order = F::ORDER as u128;
+        let sums = self.targets(builder);
+        let Some(last) = sums.len().checked_sub(1) else {
+            return;
+        };
+        let carries = builder.add_virtual_targets(last);
+        builder.add_simple_generator(Bn254CarryGenerator {
+            sums: sums.clone(),
+            carries: carries.clone(),
+        });
+
+        let (mut carry_pos, mut carry_neg) = (0, 0);
+        for (i, &sum) in sums.iter().enumerate() {
+            let pos = self.max_pos[i] + carry_pos;
+            let neg = self.max_neg[i] + carry_neg;
+            let sum = if i == 0 {
+                sum
+            } else {
+                builder.add(sum, carries[i - 1])
+            };
+            if i == last {
+                assert!(pos < order && neg < order, "Column sum overflows the field");
+                builder.assert_zero(sum);
+                break;
+            }
+
+            // The carry is in `[-neg / 2^32, pos / 2^32]`, and once range-checked, in
+            // `[-carry_neg, carry_pos]`.
+            carry_neg = neg >> COLUMN_BITS;
+            let bits = (u128::BITS - (carry_neg + (pos >> COLUMN_BITS)).leading_zeros()).max(1);
+            carry_pos = (1u128 << bits) - 1 - carry_neg;
+            assert!(
+                pos + (carry_neg << COLUMN_BITS) < order
+                    && neg + (carry_pos << COLUMN_BITS) < order,
+                "Column sum overflows the field"
+            );
+            let carry = carries[i];
+            let shifted = builder.add_const(carry, F::from_canonical_u64(carry_neg as u64));
+            builder.range_check(shifted, bits as usize);
+            let rest = builder.mul_const_add(-F::from_canonical_u64(1 << COLUMN_BITS), carry, sum);
+            builder.assert_zero(rest);
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Returns the BN254 element whose little-endian 64-bit limbs are `limbs`, which must be below
+    /// the modulus.
+    pub fn constant_bn254(&mut self, limbs: [u64; 4]) -> Bn254Target {
+        let value = biguint(&limbs);
+        assert!(value < bn254_modulus(), "Not a canonical BN254 element");
+        let mut limbs = limbs_of(&value).map(|limb| self.constant(F::from_canonical_u64(limb)));
+        Bn254Target {
+            limbs: core::array::from_fn(|_| limbs.next().unwrap()),
+        }
+    }
+
+    pub fn connect_bn254(&mut self, x: Bn254Target, y: Bn254Target) {
+        for (x, y) in x.limbs.into_iter().zip(y.limbs) {
+            self.connect(x, y);
+        }
+    }
+
+    /// Packs up to three field elements into a BN254 element, as `x_0 + x_1 2^64 + x_2 2^128`,
+    /// like the native permutation.
+    pub fn pack_bn254(&mut self, elts: &[Target]) -> Bn254Target {
+        assert!(elts.len() <= ELTS_PER_LIMB_PACK);
+        let mut limbs = [self.zero(); NUM_BN254_LIMBS];
+        for (chunk, &x) in limbs.chunks_mut(64 / LIMB_BITS).zip(elts) {
+            chunk.copy_from_slice(&self.split_u16_limbs(x));
+        }
+        Bn254Target { limbs }
+    }
+
+    /// The canonical decomposition of `x` into four 16-bit limbs.
+    fn split_u16_limbs(&mut self, x: Target) -> [Target; 4] {
+        let (lo, hi) = self.split_u32(x, F::BITS);
+        let (l0, l1) = self.split_low_high(lo.0, LIMB_BITS, 32);
+        let (l2, l3) = self.split_low_high(hi, LIMB_BITS, 32);
+        [l0, l1, l2, l3]
+    }
+
+    /// The little-endian 64-bit limbs of the canonical `x`, each reduced modulo the order of the
+    /// field, like the native permutation.
+    fn unpack_bn254(&mut self, x: Bn254Target) -> [Target; 4] {
+        let mut limbs = x.limbs.chunks(64 / LIMB_BITS).map(|chunk| {
+            chunk.iter().rev().fold(self.zero(), |acc, &limb| {
+                self.mul_const_add(F::from_canonical_u64(1 << LIMB_BITS), acc, limb)
+            })
+        });
+        core::array::from_fn(|_| limbs.next().unwrap())
+    }
+
+    /// Witnesses an integer below `2^256` congruent to the non-negative expression `e` modulo the
+    /// BN254 modulus, or the canonical one if `canonical` is set.
+    fn reduce_bn254(&mut self, mut e: Columns, canonical: bool) -> Bn254Target {
+        let modulus = bn254_modulus();
+        let quotient_bits = (e.max_value() / &modulus).bits() as usize;
+        let quotient = self.add_virtual_targets(quotient_bits.div_ceil(LIMB_BITS));
+        let remainder = self.add_virtual_target_arr::<NUM_BN254_LIMBS>();
+        let sums = e.targets(self);
+        self.add_simple_generator(Bn254ReductionGenerator {
+            sums,
+            quotient: quotient.clone(),
+            remainder: remainder.to_vec(),
+        });
+
+        for (i, &q) in quotient.iter().enumerate() {
+            let bits = LIMB_BITS.min(quotient_bits - i * LIMB_BITS);
+            self.range_check(q, bits);
+            e.add_scaled(self, i * LIMB_BITS, (q, (1 << bits) - 1), &modulus, true);
+        }
+        for &limb in &remainder {
+            self.range_check(limb, LIMB_BITS);
+        }
+        let y = Bn254Target { limbs: remainder };
+        e.add_limbs(self, 0, &y.into(), &BigUint::one(), true);
+        e.assert_zero(self);
+
+        if canonical {
+            self.assert_canonical_bn254(y);
+        }
+        y
+    }
+
+    /// Asserts that `x` is below the BN254 modulus `r`, by witnessing `r - 1 - x` in range-checked
+    /// limbs.
+    fn assert_canonical_bn254(&mut self, x: Bn254Target) {
+        let slack = self.add_virtual_target_arr::<NUM_BN254_LIMBS>();
+        self.add_simple_generator(Bn254SlackGenerator {
+            value: x.limbs.to_vec(),
+            slack: slack.to_vec(),
+        });
+        for &limb in &slack {
+            self.range_check(limb, LIMB_BITS);
+        }
+
+        let one = BigUint::one();
+        let mut columns = Columns::default();
+        columns.add_limbs(self, 0, &x.into(), &one, false);
+        columns.add_limbs(self, 0, &Bn254Target { limbs: slack }.into(), &one, false);
+        columns.add_constant(self, &(bn254_modulus() - 1u32), true);
+        columns.assert_zero(self);
+    }
+
+    fn mul_bn254(&mut self, x: &BoundedLimbs, y: &BoundedLimbs) -> BoundedLimbs {
+        let mut columns = Columns::default();
+        for (i, xi) in x.limbs.iter().zip(&x.max).enumerate() {
+            for (j, yj) in y.limbs.iter().zip(&y.max).enumerate() {
+                columns.add_product(self, (i + j) * LIMB_BITS, 1, (*xi.0, *xi.1), (*yj.0, *yj.1));
+            }
+        }
+        self.reduce_bn254(columns, false).into()
+    }
+
+    fn square_bn254(&mut self, x: &BoundedLimbs) -> BoundedLimbs {
+        let mut columns = Columns::default();
+        let limbs = || x.limbs.iter().copied().zip(x.max.iter().copied());
+        for (i, xi) in limbs().enumerate() {
+            for (j, xj) in limbs().enumerate().skip(i) {
+                let c = if i == j { 1 } else { 2 };
+                columns.add_product(self, (i + j) * LIMB_BITS, c, xi, xj);
+            }
+        }
+        self.reduce_bn254(columns, false).into()
+    }
+
+    fn exp5_bn254(&mut self, x: &BoundedLimbs) -> BoundedLimbs {
+        let x2 = self.square_bn254(x);
+        let x4 = self.square_bn254(&x2);
+        self.mul_bn254(&x4, x)
+    }
+
+    /// The Poseidon permutation over BN254, like [`PoseidonBN254Hash`] computes it. The outputs
+    /// are canonical.
+    ///
+    /// The round constants of the first round are added limb by limb, and those of the next rounds
+    /// are added along with the MDS matrix of the previous round, before reducing.
+    ///
+    /// [`PoseidonBN254Hash`]: crate::hash::poseidon_bn254::PoseidonBN254Hash
+    pub fn poseidon_bn254(&mut self, state: [Bn254Target; WIDTH]) -> [Bn254Target; WIDTH] {
+        let round_constants: Vec<BigUint> = ROUND_CONSTANTS.iter().map(biguint).collect();
+        let mds = MDS_MATRIX.map(|row| row.map(|m| biguint(&m)));
+        let partial_rounds = HALF_N_FULL_ROUNDS..HALF_N_FULL_ROUNDS + N_PARTIAL_ROUNDS;
+
+        let mut state: Vec<BoundedLimbs> = state
+            .iter()
+            .zip(&round_constants)
+            .map(|(x, c)| {
+                let (limbs, max) = x
+                    .limbs
+                    .iter()
+                    .zip(limbs_of(c))
+                    .map(|(&limb, c)| {
+                        (self.add_const(limb, F::from_canonical_u64(c)), LIMB_MAX + c)
+                    })
+                    .unzip();
+                BoundedLimbs { limbs, max }
+            })
+            .collect();
+        let mut output = Vec::new();
+        for round in 0..N_ROUNDS {
+            let sboxed: Vec<BoundedLimbs> = state
+                .iter()
+                .enumerate()
+                .map(|(i, x)| {
+                    if i == 0 || !partial_rounds.contains(&round) {
+                        self.exp5_bn254(x)
+                    } else {
+                        x.clone()
+                    }
+                })
+                .collect();
+
+            let last = round == N_ROUNDS - 1;
+            output = mds
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let mut columns = Columns::default();
+                    for (m, x) in row.iter().zip(&sboxed) {
+                        columns.add_limbs(self, 0, x, m, false);
+                    }
+                    if !last {
+                        columns.add_constant(
+                            self,
+                            &round_constants[(round + 1) * WIDTH + i],
+                            false,
+                        );
+                    }
+                    self.reduce_bn254(columns, last)
+                })
+                .collect();
+            state = output.iter().map(|&y| y.into()).collect();
+        }
+        output.try_into().unwrap()
+    }
+
+    /// Permutes twelve field elements, like [`PoseidonBN254Permutation`].
+    ///
+    /// [`PoseidonBN254Permutation`]: crate::hash::poseidon_bn254::PoseidonBN254Permutation
+    pub fn poseidon_bn254_permute(
+        &mut self,
+        inputs: [Target; SPONGE_WIDTH],
+    ) -> [Target; SPONGE_WIDTH] {
+        let mut packed = inputs
+            .chunks(ELTS_PER_LIMB_PACK)
+            .map(|chunk| self.pack_bn254(chunk));
+        let packed = core::array::from_fn(|_| packed.next().unwrap());
+        let permuted = self.poseidon_bn254(packed);
+
+        let mut outputs = inputs;
+        for (chunk, x) in outputs.chunks_mut(ELTS_PER_LIMB_PACK).zip(permuted) {
+            chunk.copy_from_slice(&self.unpack_bn254(x)[..ELTS_PER_LIMB_PACK]);
+        }
+        outputs
+    }
+
+    /// Hashes field elements, like [`PoseidonBN254Hash::hash_no_pad`]. The digest is the canonical
+    /// encoding of a BN254 element as its digits in base the order of the field.
+    ///
+    /// [`PoseidonBN254Hash::hash_no_pad`]: crate::plonk::config::Hasher::hash_no_pad
+    pub fn poseidon_bn254_hash_no_pad(&mut self, inputs: &[Target]) -> HashOutTarget {
+        let mut state = [self.constant_bn254([0; 4]); WIDTH];
+        for chunk in inputs.chunks((WIDTH - 1) * ELTS_PER_LIMB_PACK) {
+            for (x, packed) in state[1..].iter_mut().zip(chunk.chunks(ELTS_PER_LIMB_PACK)) {
+                *x = self.pack_bn254(packed);
+            }
+            state = self.poseidon_bn254(state);
+        }
+        self.bn254_digest(state[0])
+    }
+
+    /// The digits of the canonical `x` in base the order of the field. Each digit is decomposed
+    /// canonically into limbs, which are recomposed to `x`.
+    fn bn254_digest(&mut self, x: Bn254Target) -> HashOutTarget {
+        let digits = self.add_virtual_target_arr::<NUM_HASH_OUT_ELTS>();
+        self.add_simple_generator(Bn254DigestGenerator {
+            value: x.limbs.to_vec(),
+            digits,
+        });
+
+        let order = BigUint::from(F::ORDER);
+        let mut columns = Columns::default();
+        for (i, &digit) in digits.iter().enumerate() {
+            let limbs = BoundedLimbs {
+                limbs: self.split_u16_limbs(digit).to_vec(),
+                max: vec![LIMB_MAX; 4],
+            };
+            columns.add_limbs(self, 0, &limbs, &order.pow(i as u32), false);
+        }
+        columns.add_limbs(self, 0, &x.into(), &BigUint::one(), true);
+        columns.assert_zero(self);
+        HashOutTarget { elements: digits }
+    }
+}
+
+/// Computes the carries between the columns of an expression asserted to be zero.
+#[derive(Debug, Default)]
+pub struct Bn254CarryGenerator {
+    sums: Vec<Target>,
+    carries: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Bn254CarryGenerator {
+    fn id(&self) -> String {
+        "Bn254CarryGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.sums.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let order = F::ORDER as i128;
+        let mut carry = 0;
+        for (&sum, &carry_target) in self.sums.iter().zip(&self.carries) {
+            let sum = witness.get_target(sum).to_canonical_u64() as i128;
+            let sum = if sum > order / 2 { sum - order } else { sum };
+            carry = (sum + carry) >> COLUMN_BITS;
+            out_buffer.set_target(carry_target, F::from_noncanonical_i64(carry as i64));
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.sums)?;
+        dst.write_target_vec(&self.carries)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let sums = src.read_target_vec()?;
+        let carries = src.read_target_vec()?;
+        Ok(Self { sums, carries })
+    }
+}
+
+/// Computes the quotient and the remainder of a non-negative expression, given by its column
+/// sums, by the BN254 modulus.
+#[derive(Debug, Default)]
+pub struct Bn254ReductionGenerator {
+    sums: Vec<Target>,
+    quotient: Vec<Target>,
+    remainder: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for Bn254ReductionGenerator
+{
+    fn id(&self) -> String {
+        "Bn254ReductionGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.sums.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let value = self.sums.iter().rev().fold(BigUint::zero(), |acc, &sum| {
+            (acc << COLUMN_BITS) + witness.get_target(sum).to_canonical_u64()
+        });
+        let modulus = bn254_modulus();
+        set_limbs(out_buffer, &self.quotient, &(&value / &modulus));
+        set_limbs(out_buffer, &self.remainder, &(&value % &modulus));
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.sums)?;
+        dst.write_target_vec(&self.quotient)?;
+        dst.write_target_vec(&self.remainder)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let sums = src.read_target_vec()?;
+        let quotient = src.read_target_vec()?;
+        let remainder = src.read_target_vec()?;
+        Ok(Self {
+            sums,
+            quotient,
+            remainder,
+        })
+    }
+}
+
+/// Computes `r - 1 - x` for a canonical BN254 element `x`.
+#[derive(Debug, Default)]
+pub struct Bn254SlackGenerator {
+    value: Vec<Target>,
+    slack: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Bn254SlackGenerator {
+    fn id(&self) -> String {
+        "Bn254SlackGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.value.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let value = limbs_value(witness, &self.value);
+        set_limbs(out_buffer, &self.slack, &(bn254_modulus() - 1u32 - value));
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.value)?;
+        dst.write_target_vec(&self.slack)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let value = src.read_target_vec()?;
+        let slack = src.read_target_vec()?;
+        Ok(Self { value, slack })
+    }
+}
+
+/// Computes the digits of a canonical BN254 element in base the order of the field.
+#[derive(Debug, Default)]
+pub struct Bn254DigestGenerator {
+    value: Vec<Target>,
+    digits: [Target; NUM_HASH_OUT_ELTS],
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Bn254DigestGenerator {
+    fn id(&self) -> String {
+        "Bn254DigestGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.value.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let value = limbs_value(witness, &self.value);
+        let mut limbs = [0; 4];
+        for (limb, digit) in limbs.iter_mut().zip(value.iter_u64_digits()) {
+            *limb = digit;
+        }
+        for (&target, digit) in self.digits.iter().zip(digest_digits::<F>(limbs)) {
+            out_buffer.set_target(target, digit);
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.value)?;
+        dst.write_target_array(&self.digits)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let value = src.read_target_vec()?;
+        let digits = src.read_target_array()?;
+        Ok(Self { value, digits })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::hash::hashing::PlonkyPermutation;
+    use crate::hash::poseidon_bn254::{PoseidonBN254Hash, PoseidonBN254Permutation};
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Proves that `circuit` maps random inputs to `expected` of them.
+    fn check_against_native(
+        num_inputs: usize,
+        circuit: impl FnOnce(&mut CircuitBuilder<F, D>, &[Target]) -> Vec<Target>,
+        expected: impl FnOnce(&[F]) -> Vec<F>,
+    ) -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let inputs = builder.add_virtual_targets(num_inputs);
+        let outputs = circuit(&mut builder, &inputs);
+        builder.register_public_inputs(&outputs);
+        let data = builder.build::<C>();
+
+        let values = F::rand_vec(num_inputs);
+        let mut pw = PartialWitness::new();
+        pw.set_target_arr(&inputs, &values);
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs, expected(&values));
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_poseidon_bn254_hash_no_pad() -> Result<()> {
+        check_against_native(
+            9,
+            |builder, inputs| builder.poseidon_bn254_hash_no_pad(inputs).elements.to_vec(),
+            |values| PoseidonBN254Hash::hash_no_pad(values).elements.to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_poseidon_bn254_permute() -> Result<()> {
+        check_against_native(
+            SPONGE_WIDTH,
+            |builder, inputs| {
+                builder
+                    .poseidon_bn254_permute(inputs.try_into().unwrap())
+                    .to_vec()
+            },
+            |values| {
+                let mut perm = PoseidonBN254Permutation::new(values.iter().copied());
+                perm.permute();
+                perm.as_ref().to_vec()
+            },
+        )
+    }
+}
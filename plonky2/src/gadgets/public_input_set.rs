@@ -0,0 +1,321 @@
+//! Gadgets asserting that a proof's public inputs are one of a set of allowed values, given by the
+//! hashes of the allowed public inputs, either listed in the circuit or committed to by a Merkle
+//! root. See `verify_proof_public_inputs_in_set` and `verify_proof_public_inputs_in_merkle_set`
+//! for the native counterparts.
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOutTarget, RichField};
+use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::AlgebraicHasher;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// The witness of membership in a Merkle tree of allowed public inputs hashes, see
+/// [`CircuitBuilder::assert_public_inputs_in_merkle_set`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PublicInputsMembershipTarget {
+    /// The index of the leaf.
+    pub index: Target,
+    pub proof: MerkleProofTarget,
+}
+
+impl PublicInputsMembershipTarget {
+    /// Sets the index of the leaf and its Merkle proof.
+    pub fn set_witness<F: RichField, H: AlgebraicHasher<F>>(
+        &self,
+        witness: &mut impl WitnessWrite<F>,
+        index: usize,
+        proof: &MerkleProof<F, H>,
+    ) {
+        assert_eq!(
+            proof.siblings.len(),
+            self.proof.siblings.len(),
+            "The Merkle proof has the wrong depth"
+        );
+        witness.set_target(self.index, F::from_canonical_usize(index));
+        for (&t, &sibling) in self.proof.siblings.iter().zip(proof.siblings.iter()) {
+            witness.set_hash_target(t, sibling);
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Asserts that `pis_hash` is one of `allowed`, and returns the index of the match, which the
+    /// prover finds. If several entries match, the first one is used. Proving fails if none do.
+    ///
+    /// `pis_hash` is typically the hash of an inner proof's public inputs with the inner
+    /// `C::InnerHasher`, as in `ProofWithPublicInputs::get_public_inputs_hash`. The set is limited
+    /// by the size of a `RandomAccessGate`, i.e. 64 entries with the standard configuration; see
+    /// `assert_public_inputs_in_merkle_set` for larger sets.
+    pub fn assert_public_inputs_in_set(
+        &mut self,
+        pis_hash: HashOutTarget,
+        allowed: &[HashOutTarget],
+    ) -> Target {
+        assert!(!allowed.is_empty(), "The allowed set is empty");
+        let index = self.add_virtual_target();
+        self.add_simple_generator(PublicInputsSetIndexGenerator {
+            pis_hash,
+            allowed: allowed.to_vec(),
+            index,
+        });
+
+        // Pad to a power of two by repeating the last entry, which the index can then reach as
+        // well.
+        let mut candidates = allowed.to_vec();
+        candidates.resize(
+            allowed.len().next_power_of_two(),
+            allowed[allowed.len() - 1],
+        );
+        let selected = self.random_access_hash(index, candidates);
+        self.connect_hashes(pis_hash, selected);
+        index
+    }
+
+    /// Asserts that `pis_hash` is a leaf of the Merkle tree of height `height` with root
+    /// `merkle_root`, the leaves being the elements of the allowed public inputs hashes. The
+    /// returned targets, holding the index of the leaf and its Merkle proof, must be set by the
+    /// prover with [`PublicInputsMembershipTarget::set_witness`].
+    pub fn assert_public_inputs_in_merkle_set<H: AlgebraicHasher<F>>(
+        &mut self,
+        pis_hash: HashOutTarget,
+        merkle_root: HashOutTarget,
+        height: usize,
+    ) -> PublicInputsMembershipTarget {
+        let index = self.add_virtual_target();
+        let index_bits = self.split_le(index, height);
+        let proof = MerkleProofTarget {
+            siblings: self.add_virtual_hashes(height),
+        };
+        self.verify_merkle_proof::<H>(pis_hash.elements.to_vec(), &index_bits, merkle_root, &proof);
+        PublicInputsMembershipTarget { index, proof }
+    }
+}
+
+/// Finds the first entry of `allowed` matching `pis_hash`.
+#[derive(Debug, Default)]
+pub struct PublicInputsSetIndexGenerator {
+    pis_hash: HashOutTarget,
+    allowed: Vec<HashOutTarget>,
+    index: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for PublicInputsSetIndexGenerator
+{
+    fn id(&self) -> String {
+        "PublicInputsSetIndexGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        let mut deps = self.pis_hash.elements.to_vec();
+        deps.extend(self.allowed.iter().flat_map(|h| h.elements));
+        deps
+    }
+
+    fn outputs(&self) -> Vec<Target> {
+        vec![self.index]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let pis_hash = witness.get_hash_target(self.pis_hash);
+        // Without a match, the index is left unset, which fails the generation of the witness.
+        if let Some(i) = self
+            .allowed
+            .iter()
+            .position(|&h| witness.get_hash_target(h) == pis_hash)
+        {
+            out_buffer.set_target(self.index, F::from_canonical_usize(i));
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_hash(&self.pis_hash)?;
+        dst.write_usize(self.allowed.len())?;
+        for h in &self.allowed {
+            dst.write_target_hash(h)?;
+        }
+        dst.write_target(self.index)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let pis_hash = src.read_target_hash()?;
+        let len = src.read_usize()?;
+        let allowed = (0..len)
+            .map(|_| src.read_target_hash())
+            .collect::<IoResult<Vec<_>>>()?;
+        let index = src.read_target()?;
+        Ok(Self {
+            pis_hash,
+            allowed,
+            index,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::hash::hash_types::HashOut;
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::ProofWithPublicInputs;
+    use crate::plonk::verifier::{
+        verify_proof_public_inputs_in_merkle_set, verify_proof_public_inputs_in_set,
+    };
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+    type InnerH = <C as GenericConfig<D>>::InnerHasher;
+
+    const NUM_PUBLIC_INPUTS: usize = 3;
+
+    fn public_inputs_hash(public_inputs: &[F]) -> HashOut<F> {
+        InnerH::hash_no_pad(public_inputs)
+    }
+
+    /// A circuit whose public inputs must hash to one of `allowed`.
+    fn set_circuit(allowed: &[HashOut<F>]) -> (CircuitData<F, C, D>, Vec<Target>) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let public_inputs = builder
+            .add_virtual_public_input_arr::<NUM_PUBLIC_INPUTS>()
+            .to_vec();
+        let pis_hash = builder.hash_n_to_hash_no_pad::<InnerH>(public_inputs.clone());
+        let allowed = allowed
+            .iter()
+            .map(|&h| builder.constant_hash(h))
+            .collect::<Vec<_>>();
+        builder.assert_public_inputs_in_set(pis_hash, &allowed);
+        (builder.build::<C>(), public_inputs)
+    }
+
+    fn prove_with_public_inputs(
+        data: &CircuitData<F, C, D>,
+        targets: &[Target],
+        values: &[F],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut pw = PartialWitness::new();
+        pw.set_target_arr(targets, values);
+        data.prove(pw)
+    }
+
+    #[test]
+    fn test_public_inputs_in_set() -> Result<()> {
+        let allowed_pis = (0..5)
+            .map(|_| F::rand_vec(NUM_PUBLIC_INPUTS))
+            .collect::<Vec<_>>();
+        let allowed = allowed_pis
+            .iter()
+            .map(|pis| public_inputs_hash(pis))
+            .collect::<Vec<_>>();
+        let (data, targets) = set_circuit(&allowed);
+
+        for i in [0, allowed.len() - 1] {
+            let proof = prove_with_public_inputs(&data, &targets, &allowed_pis[i])?;
+            let index = verify_proof_public_inputs_in_set(
+                proof,
+                &data.verifier_only,
+                &data.common,
+                &allowed_pis,
+            )?;
+            assert_eq!(index, i);
+        }
+
+        // Public inputs outside of the set can't be proven.
+        let other = F::rand_vec(NUM_PUBLIC_INPUTS);
+        let err = prove_with_public_inputs(&data, &targets, &other)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("PublicInputsSetIndexGenerator"), "{err}");
+
+        // Nor verified natively against the set.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = builder
+            .add_virtual_public_input_arr::<NUM_PUBLIC_INPUTS>()
+            .to_vec();
+        let unconstrained = builder.build::<C>();
+        let proof = prove_with_public_inputs(&unconstrained, &targets, &other)?;
+        assert!(verify_proof_public_inputs_in_set(
+            proof,
+            &unconstrained.verifier_only,
+            &unconstrained.common,
+            &allowed_pis
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_inputs_in_merkle_set() -> Result<()> {
+        let height = 10;
+        let allowed_pis = (0..1 << height)
+            .map(|_| F::rand_vec(NUM_PUBLIC_INPUTS))
+            .collect::<Vec<_>>();
+        let tree = MerkleTree::<F, H>::new(
+            allowed_pis
+                .iter()
+                .map(|pis| public_inputs_hash(pis).to_vec())
+                .collect(),
+            0,
+        );
+        let root = tree.cap.0[0];
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let targets = builder
+            .add_virtual_public_input_arr::<NUM_PUBLIC_INPUTS>()
+            .to_vec();
+        let pis_hash = builder.hash_n_to_hash_no_pad::<InnerH>(targets.clone());
+        let root_target = builder.constant_hash(root);
+        let membership =
+            builder.assert_public_inputs_in_merkle_set::<H>(pis_hash, root_target, height);
+        let data = builder.build::<C>();
+
+        let prove = |i: usize| {
+            let mut pw = PartialWitness::new();
+            pw.set_target_arr(&targets, &allowed_pis[i]);
+            membership.set_witness(&mut pw, i, &tree.prove(i));
+            data.prove(pw)
+        };
+
+        for i in [0, 700, (1 << height) - 1] {
+            let proof = prove(i)?;
+            data.verify(proof.clone())?;
+            verify_proof_public_inputs_in_merkle_set(
+                proof,
+                &data.verifier_only,
+                &data.common,
+                i,
+                &tree.prove(i),
+                root,
+            )?;
+        }
+
+        // A Merkle proof for another leaf doesn't prove membership.
+        let proof = prove(4)?;
+        assert!(verify_proof_public_inputs_in_merkle_set(
+            proof,
+            &data.verifier_only,
+            &data.common,
+            3,
+            &tree.prove(3),
+            root,
+        )
+        .is_err());
+        Ok(())
+    }
+}
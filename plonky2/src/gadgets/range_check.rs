@@ -12,9 +12,20 @@ use crate::plonk::circuit_data::CommonCircuitData;
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
-    /// Checks that `x < 2^n_log` using a `BaseSumGate`.
-    pub fn range_check(&mut self, x: Target, n_log: usize) {
-        self.split_le(x, n_log);
+    /// Checks that `x < 2^num_bits` using `BaseSumGate`s.
+    ///
+    /// When `num_bits` fits in the limbs of a single `BaseSumGate` (63 bits with the standard
+    /// configurations), one gate is used and its unused limbs are constrained to zero.
+    ///
+    /// Every field element is less than `2^F::BITS`, so for `num_bits >= F::BITS` the check is
+    /// vacuous and no constraints are added. A `F::BITS`-bit decomposition would not be sound
+    /// anyway, since both `x` and `x + F::ORDER` may fit in `F::BITS` bits. Below that, the sum of
+    /// the bits is at most `2^{F::BITS - 1} - 1 < F::ORDER` and cannot wrap around.
+    pub fn range_check(&mut self, x: Target, num_bits: usize) {
+        if num_bits >= F::BITS {
+            return;
+        }
+        self.split_le(x, num_bits);
     }
 
     /// Returns the first `num_low_bits` little-endian bits of `x`.
@@ -100,3 +111,64 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Low
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::{Field, Field64};
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn prove_range_check(value: u64, num_bits: usize) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        builder.range_check(x, num_bits);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(value));
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_range_check() -> Result<()> {
+        prove_range_check(0, 8)?;
+        prove_range_check(255, 8)?;
+        prove_range_check(u32::MAX as u64, 32)?;
+        prove_range_check((1 << 63) - 1, 63)?;
+        prove_range_check(F::ORDER - 1, 64)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_range_check_out_of_range() {
+        prove_range_check(256, 8).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_range_check_out_of_range_63_bits() {
+        prove_range_check(1 << 63, 63).unwrap();
+    }
+
+    #[test]
+    fn test_range_check_num_gates() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let before = builder.num_gates();
+        builder.range_check(x, 63);
+        assert_eq!(builder.num_gates() - before, 1);
+        builder.range_check(x, 64);
+        assert_eq!(builder.num_gates() - before, 1);
+    }
+}
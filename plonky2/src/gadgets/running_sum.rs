@@ -0,0 +1,197 @@
+use alloc::vec;
+
+use crate::field::extension::Extendable;
+use crate::gates::noop::NoopGate;
+use crate::gates::running_sum::RunningSumGate;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes `initial + sum(addends)` with one `RunningSumGate` per addend, on consecutive
+    /// rows, each row passing its accumulator on to the next one.
+    pub fn running_sum(&mut self, initial: Target, addends: &[Target]) -> Target {
+        if addends.is_empty() {
+            return initial;
+        }
+
+        let mut accumulator = initial;
+        for (i, &addend) in addends.iter().enumerate() {
+            let row = self.add_gate(RunningSumGate, vec![]);
+            if i == 0 {
+                self.connect(initial, Target::wire(row, RunningSumGate::WIRE_ACCUMULATOR));
+            }
+            self.connect(addend, Target::wire(row, RunningSumGate::WIRE_ADDEND));
+            accumulator = Target::wire(row + 1, RunningSumGate::WIRE_ACCUMULATOR);
+        }
+        // The last gate's next row holds the final sum, so it must be reserved right away.
+        self.add_gate(NoopGate, vec![]);
+        accumulator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{string::String, vec::Vec};
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::{Field, Sample};
+    use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
+    use crate::iop::witness::{PartialWitness, PartitionWitness, Witness, WitnessWrite};
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData, CommonCircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::ProofWithPublicInputs;
+    use crate::util::serialization::{Buffer, IoResult};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const NUM_ADDENDS: usize = 10;
+
+    /// Registers `initial`, the addends and their running sum as public inputs. Also returns the
+    /// row of the first `RunningSumGate`.
+    fn running_sum_circuit(
+        zero_knowledge: bool,
+    ) -> (CircuitData<F, C, D>, Target, Vec<Target>, usize) {
+        let config = CircuitConfig {
+            zero_knowledge,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let initial = builder.add_virtual_public_input();
+        let addends = builder
+            .add_virtual_public_input_arr::<NUM_ADDENDS>()
+            .to_vec();
+        let first_row = builder.num_gates();
+        let sum = builder.running_sum(initial, &addends);
+        builder.register_public_input(sum);
+        (builder.build::<C>(), initial, addends, first_row)
+    }
+
+    fn prove_running_sum(
+        data: &CircuitData<F, C, D>,
+        initial: Target,
+        addends: &[Target],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut pw = PartialWitness::new();
+        pw.set_target(initial, F::rand());
+        for &addend in addends {
+            pw.set_target(addend, F::rand());
+        }
+        data.prove(pw)
+    }
+
+    #[test]
+    fn test_running_sum() -> Result<()> {
+        for zero_knowledge in [false, true] {
+            let (data, initial, addends, _) = running_sum_circuit(zero_knowledge);
+            assert_eq!(data.common.num_next_row_wires(), 1);
+
+            let proof = prove_running_sum(&data, initial, &addends)?;
+            let expected_sum = proof.public_inputs[..=NUM_ADDENDS]
+                .iter()
+                .copied()
+                .sum::<F>();
+            assert_eq!(proof.public_inputs[NUM_ADDENDS + 1], expected_sum);
+            assert_eq!(proof.proof.openings.wires_next.len(), 1);
+
+            let compressed = data.compress(proof.clone())?;
+            data.verify_compressed(compressed)?;
+            data.verify(proof)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_running_sum_recursive() -> Result<()> {
+        let (inner_data, initial, addends, _) = running_sum_circuit(false);
+        let inner_proof = prove_running_sum(&inner_data, initial, &addends)?;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let proof_t = builder.add_virtual_proof_with_pis(&inner_data.common);
+        let verifier_t = builder.constant_verifier_data(&inner_data.verifier_only);
+        builder.verify_proof::<C>(&proof_t, &verifier_t, &inner_data.common);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_t, &inner_proof);
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    /// A buggy replacement for `RunningSumGenerator` which is off by one.
+    #[derive(Debug)]
+    struct OffByOneGenerator {
+        row: usize,
+    }
+
+    impl SimpleGenerator<F, D> for OffByOneGenerator {
+        fn id(&self) -> String {
+            "OffByOneGenerator".into()
+        }
+
+        fn dependencies(&self) -> Vec<Target> {
+            vec![
+                Target::wire(self.row, RunningSumGate::WIRE_ACCUMULATOR),
+                Target::wire(self.row, RunningSumGate::WIRE_ADDEND),
+            ]
+        }
+
+        fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+            let accumulator = witness.get_target(self.dependencies()[0]);
+            let addend = witness.get_target(self.dependencies()[1]);
+            out_buffer.set_target(
+                Target::wire(self.row + 1, RunningSumGate::WIRE_ACCUMULATOR),
+                accumulator + addend + F::ONE,
+            );
+        }
+
+        fn serialize(
+            &self,
+            _dst: &mut Vec<u8>,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<()> {
+            unimplemented!()
+        }
+
+        fn deserialize(
+            _src: &mut Buffer,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<Self> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_violated_transition_rejected() {
+        let (mut data, initial, addends, first_row) = running_sum_circuit(false);
+
+        // Swap the honest generator of a transition in the middle of the chain for the buggy one.
+        let row = first_row + NUM_ADDENDS / 2;
+        let generator = data
+            .prover_only
+            .generators
+            .iter_mut()
+            .find(|g| {
+                g.0.watch_list()
+                    .contains(&Target::wire(row, RunningSumGate::WIRE_ADDEND))
+            })
+            .unwrap();
+        *generator = WitnessGeneratorRef::new(OffByOneGenerator { row }.adapter());
+
+        let result = prove_running_sum(&data, initial, &addends).and_then(|proof| {
+            let sum = proof.public_inputs[..=NUM_ADDENDS]
+                .iter()
+                .copied()
+                .sum::<F>();
+            assert_eq!(proof.public_inputs[NUM_ADDENDS + 1], sum + F::ONE);
+            data.verify(proof)
+        });
+        assert!(result.is_err());
+    }
+}
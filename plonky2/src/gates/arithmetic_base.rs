@@ -248,7 +248,7 @@ mod tests {
 
     use crate::field::goldilocks_field::GoldilocksField;
     use crate::gates::arithmetic_base::ArithmeticGate;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generators, test_low_degree};
     use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -266,4 +266,13 @@ mod tests {
         let gate = ArithmeticGate::new_from_config(&CircuitConfig::standard_recursion_config());
         test_eval_fns::<F, C, _, D>(gate)
     }
+
+    #[test]
+    fn generators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = ArithmeticGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_generators::<F, C, _, D>(gate)
+    }
 }
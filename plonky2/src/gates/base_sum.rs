@@ -109,7 +109,7 @@ impl<F: RichField + Extendable<D>, const D: usize, const B: usize> Gate<F, D> fo
                     // acc' = acc (x - i)
                     //      = acc x + (-i) acc
                     // Since -i is constant, we can do this in one arithmetic_extension call.
-                    let neg_i = -F::from_canonical_usize(i);
+                    let neg_i = F::from_noncanonical_i64(-(i as i64));
                     acc = builder.arithmetic_extension(F::ONE, neg_i, acc, limb, acc)
                 });
                 acc
@@ -226,10 +226,13 @@ impl<F: RichField + Extendable<D>, const B: usize, const D: usize> SimpleGenerat
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
 
     use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Field;
     use crate::gates::base_sum::BaseSumGate;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generators_with_inputs, test_low_degree};
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
     #[test]
@@ -244,4 +247,17 @@ mod tests {
         type F = <C as GenericConfig<D>>::F;
         test_eval_fns::<F, C, _, D>(BaseSumGate::<6>::new(11))
     }
+
+    #[test]
+    fn generators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        // The sum must fit in the limbs.
+        let max_sum = 6u64.pow(11);
+        test_generators_with_inputs::<F, C, _, D>(BaseSumGate::<6>::new(11), |column| {
+            assert_eq!(column, BaseSumGate::<6>::WIRE_SUM);
+            F::from_canonical_u64(OsRng.gen_range(0..max_sum))
+        })
+    }
 }
@@ -136,7 +136,7 @@ mod tests {
 
     use crate::field::goldilocks_field::GoldilocksField;
     use crate::gates::constant::ConstantGate;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generators, test_low_degree};
     use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -156,4 +156,13 @@ mod tests {
         let gate = ConstantGate { num_consts };
         test_eval_fns::<F, C, _, D>(gate)
     }
+
+    #[test]
+    fn generators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let num_consts = CircuitConfig::standard_recursion_config().num_constants;
+        test_generators::<F, C, _, D>(ConstantGate { num_consts })
+    }
 }
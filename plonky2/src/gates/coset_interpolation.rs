@@ -641,7 +641,7 @@ mod tests {
     use super::*;
     use crate::field::goldilocks_field::GoldilocksField;
     use crate::field::types::{Field, Sample};
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generators, test_low_degree};
     use crate::hash::hash_types::HashOut;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -791,6 +791,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn generators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        for degree in 2..=4 {
+            test_generators::<F, C, _, D>(CosetInterpolationGate::with_max_degree(2, degree))?;
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_gate_constraint() {
         const D: usize = 2;
@@ -839,6 +850,7 @@ mod tests {
         let vars = EvaluationVars {
             local_constants: &[],
             local_wires: &get_wires(shift, values, eval_point),
+            next_wires: &[],
             public_inputs_hash: &HashOut::rand(),
         };
 
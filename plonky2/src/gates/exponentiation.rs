@@ -316,7 +316,7 @@ mod tests {
     use super::*;
     use crate::field::goldilocks_field::GoldilocksField;
     use crate::field::types::Sample;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generators_with_inputs, test_low_degree};
     use crate::hash::hash_types::HashOut;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
     use crate::util::log2_ceil;
@@ -359,6 +359,24 @@ mod tests {
         ))
     }
 
+    #[test]
+    fn generators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = ExponentiationGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        let power_bit_wires = (0..gate.num_power_bits)
+            .map(|i| gate.wire_power_bit(i))
+            .collect::<Vec<_>>();
+        test_generators_with_inputs::<F, C, _, D>(gate, |column| {
+            if power_bit_wires.contains(&column) {
+                F::from_bool(OsRng.gen())
+            } else {
+                F::rand()
+            }
+        })
+    }
+
     #[test]
     fn test_gate_constraint() {
         const D: usize = 2;
@@ -415,6 +433,7 @@ mod tests {
         let vars = EvaluationVars {
             local_constants: &[],
             local_wires: &get_wires(base, power as u64),
+            next_wires: &[],
             public_inputs_hash: &HashOut::rand(),
         };
         assert!(
@@ -93,10 +93,16 @@ pub trait Gate<F: RichField + Extendable<D>, const D: usize>: 'static + Send + S
             .iter()
             .map(|w| F::Extension::from_basefield(*w))
             .collect::<Vec<_>>();
+        let next_wires = &vars_base
+            .next_wires
+            .iter()
+            .map(|w| F::Extension::from_basefield(*w))
+            .collect::<Vec<_>>();
         let public_inputs_hash = &vars_base.public_inputs_hash;
         let vars = EvaluationVars {
             local_constants,
             local_wires,
+            next_wires,
             public_inputs_hash,
         };
         let values = self.eval_unfiltered(vars);
@@ -251,6 +257,15 @@ pub trait Gate<F: RichField + Extendable<D>, const D: usize>: 'static + Send + S
     fn extra_constant_wires(&self) -> Vec<(usize, usize)> {
         vec![]
     }
+
+    /// The number of wires of the next row which this gate's constraints read, through
+    /// `vars.next_wires`. These are the first wires of the next row, whichever gate is placed
+    /// there; the row after the last one is the first row.
+    ///
+    /// Each such wire is also opened at `g * zeta`, so this should be as small as possible.
+    fn num_next_row_wires(&self) -> usize {
+        0
+    }
 }
 
 /// A wrapper trait over a `Gate`, to allow for gate serialization.
@@ -323,7 +338,12 @@ pub struct PrefixedGate<F: RichField + Extendable<D>, const D: usize> {
 }
 
 /// A gate's filter designed so that it is non-zero if `s = row`.
-fn compute_filter<K: Field>(row: usize, group_range: Range<usize>, s: K, many_selector: bool) -> K {
+pub(crate) fn compute_filter<K: Field>(
+    row: usize,
+    group_range: Range<usize>,
+    s: K,
+    many_selector: bool,
+) -> K {
     debug_assert!(group_range.contains(&row));
     group_range
         .filter(|&i| i != row)
@@ -1,3 +1,16 @@
+//! Checks which every gate implementation should pass. A new gate is expected to come with tests
+//! running each of them:
+//!
+//! - `test_low_degree`, which checks that the constraints' degree is at most `Gate::degree()`;
+//! - `test_eval_fns`, which checks that the base field, extension field and in-circuit
+//!   evaluations of the constraints agree;
+//! - `test_generators` (or `test_generators_with_inputs`, for gates whose inputs must satisfy
+//!   some precondition), which checks that the gate's generators produce a witness satisfying its
+//!   constraints.
+//!
+//! The generator check doesn't apply to gates whose wires are filled by the circuit builder rather
+//! than by generators, like `PublicInputGate`, or which rely on lookup tables.
+
 use alloc::vec;
 use alloc::vec::Vec;
 
@@ -7,11 +20,15 @@ use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use crate::field::types::{Field, Sample};
 use crate::gates::gate::Gate;
+use crate::gates::noop::NoopGate;
 use crate::hash::hash_types::{HashOut, RichField};
-use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::iop::generator::{generate_partial_witness, GeneratedValues};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartialWitness, PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CircuitConfig;
-use crate::plonk::config::GenericConfig;
+use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::prover::check_constraints;
 use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBaseBatch};
 use crate::plonk::verifier::verify;
 use crate::util::{log2_ceil, transpose};
@@ -28,13 +45,18 @@ pub fn test_low_degree<F: RichField + Extendable<D>, G: Gate<F, D>, const D: usi
     let constant_ldes = random_low_degree_matrix::<F::Extension>(gate.num_constants(), rate_bits);
     assert_eq!(wire_ldes.len(), constant_ldes.len());
     let public_inputs_hash = &HashOut::rand();
+    // The next row of the trace is `1 << rate_bits` points further in the LDE.
+    let next_row_step = 1 << rate_bits;
+    let num_next_row_wires = gate.num_next_row_wires();
 
     let constraint_evals = wire_ldes
         .iter()
         .zip(constant_ldes.iter())
-        .map(|(local_wires, local_constants)| EvaluationVars {
+        .enumerate()
+        .map(|(i, (local_wires, local_constants))| EvaluationVars {
             local_constants,
             local_wires,
+            next_wires: &wire_ldes[(i + next_row_step) % wire_ldes.len()][..num_next_row_wires],
             public_inputs_hash,
         })
         .map(|vars| gate.eval_unfiltered(vars))
@@ -96,11 +118,16 @@ pub fn test_eval_fns<
 ) -> Result<()> {
     // Test that `eval_unfiltered` and `eval_unfiltered_base` are coherent.
     let wires_base = F::rand_vec(gate.num_wires());
+    let next_wires_base = F::rand_vec(gate.num_next_row_wires());
     let constants_base = F::rand_vec(gate.num_constants());
     let wires = wires_base
         .iter()
         .map(|&x| F::Extension::from_basefield(x))
         .collect::<Vec<_>>();
+    let next_wires = next_wires_base
+        .iter()
+        .map(|&x| F::Extension::from_basefield(x))
+        .collect::<Vec<_>>();
     let constants = constants_base
         .iter()
         .map(|&x| F::Extension::from_basefield(x))
@@ -108,11 +135,17 @@ pub fn test_eval_fns<
     let public_inputs_hash = HashOut::rand();
 
     // Batch of 1.
-    let vars_base_batch =
-        EvaluationVarsBaseBatch::new(1, &constants_base, &wires_base, &public_inputs_hash);
+    let vars_base_batch = EvaluationVarsBaseBatch::new(
+        1,
+        &constants_base,
+        &wires_base,
+        &next_wires_base,
+        &public_inputs_hash,
+    );
     let vars = EvaluationVars {
         local_constants: &constants,
         local_wires: &wires,
+        next_wires: &next_wires,
         public_inputs_hash: &public_inputs_hash,
     };
 
@@ -129,6 +162,7 @@ pub fn test_eval_fns<
 
     // Test that `eval_unfiltered` and `eval_unfiltered_recursively` are coherent.
     let wires = F::Extension::rand_vec(gate.num_wires());
+    let next_wires = F::Extension::rand_vec(gate.num_next_row_wires());
     let constants = F::Extension::rand_vec(gate.num_constants());
 
     let config = CircuitConfig::standard_recursion_config();
@@ -136,8 +170,10 @@ pub fn test_eval_fns<
     let mut builder = CircuitBuilder::<F, D>::new(config);
 
     let wires_t = builder.add_virtual_extension_targets(wires.len());
+    let next_wires_t = builder.add_virtual_extension_targets(next_wires.len());
     let constants_t = builder.add_virtual_extension_targets(constants.len());
     pw.set_extension_targets(&wires_t, &wires);
+    pw.set_extension_targets(&next_wires_t, &next_wires);
     pw.set_extension_targets(&constants_t, &constants);
     let public_inputs_hash_t = builder.add_virtual_hash();
     pw.set_hash_target(public_inputs_hash_t, public_inputs_hash);
@@ -145,6 +181,7 @@ pub fn test_eval_fns<
     let vars = EvaluationVars {
         local_constants: &constants,
         local_wires: &wires,
+        next_wires: &next_wires,
         public_inputs_hash: &public_inputs_hash,
     };
     let evals = gate.eval_unfiltered(vars);
@@ -152,6 +189,7 @@ pub fn test_eval_fns<
     let vars_t = EvaluationTargets {
         local_constants: &constants_t,
         local_wires: &wires_t,
+        next_wires: &next_wires_t,
         public_inputs_hash: &public_inputs_hash_t,
     };
     let evals_t = gate.eval_unfiltered_circuit(&mut builder, vars_t);
@@ -161,3 +199,113 @@ pub fn test_eval_fns<
     let proof = data.prove(pw)?;
     verify::<F, C, D>(proof, &data.verifier_only, &data.common)
 }
+
+/// Tests that the witness generated by the gate's generators from random inputs satisfies the
+/// gate's constraints. The gate is placed alone in a circuit, with random constants.
+pub fn test_generators<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    G: Gate<F, D>,
+    const D: usize,
+>(
+    gate: G,
+) -> Result<()> {
+    test_generators_with_inputs::<F, C, G, D>(gate, |_| F::rand())
+}
+
+/// Same as `test_generators`, but the value of each input wire is given by `sample_input`, called
+/// with the wire's column.
+///
+/// The inputs are the wires which the generators read but none of them writes. Inputs on the next
+/// row are sampled like those of the gate's own row.
+pub fn test_generators_with_inputs<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    G: Gate<F, D>,
+    const D: usize,
+>(
+    gate: G,
+    mut sample_input: impl FnMut(usize) -> F,
+) -> Result<()> {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    let constants = F::rand_vec(gate.num_constants());
+    let row = builder.num_gates();
+    let inputs = generator_inputs(
+        &gate,
+        row,
+        &constants,
+        builder.config.num_wires,
+        &mut sample_input,
+    );
+    // Extra constant wires are only filled when the builder has constants to put in them.
+    for (constant_index, _) in gate.extra_constant_wires() {
+        builder.constant(constants[constant_index]);
+    }
+    let has_next_row_wires = gate.num_next_row_wires() > 0;
+    assert_eq!(builder.add_gate(gate, constants), row);
+    if has_next_row_wires {
+        // Keep the next row free of any other gate's wires.
+        builder.add_gate(NoopGate, vec![]);
+    }
+    let data = builder.build::<C>();
+
+    let mut pw = PartialWitness::new();
+    for target in inputs {
+        let Target::Wire(wire) = target else {
+            unreachable!("Gate generators only read wires")
+        };
+        pw.set_target(target, sample_input(wire.column));
+    }
+    let witness = generate_partial_witness(pw, &data.prover_only, &data.common);
+    let public_inputs = witness.get_targets(&data.prover_only.public_inputs);
+    let public_inputs_hash = C::InnerHasher::hash_no_pad(&public_inputs);
+    let violations = check_constraints(
+        &witness.full_witness(),
+        &data.prover_only,
+        &data.common,
+        &public_inputs_hash,
+    );
+    ensure!(
+        violations.is_empty(),
+        "{} constraint(s) violated by the generated witness, first: {:?}",
+        violations.len(),
+        violations.first()
+    );
+    Ok(())
+}
+
+/// The targets which the generators of a gate at `row` read but none of them writes. The written
+/// targets are found by running every generator once, on sampled values of everything it reads.
+fn generator_inputs<F: RichField + Extendable<D>, G: Gate<F, D>, const D: usize>(
+    gate: &G,
+    row: usize,
+    constants: &[F],
+    num_wires: usize,
+    sample_input: &mut impl FnMut(usize) -> F,
+) -> Vec<Target> {
+    let generators = gate.generators(row, constants);
+    let mut watched = Vec::new();
+    for target in generators.iter().flat_map(|g| g.0.watch_list()) {
+        if !watched.contains(&target) {
+            watched.push(target);
+        }
+    }
+
+    // A scratch witness covering the gate's row and the next one, where no wires are connected.
+    let degree = row + 2;
+    let representative_map = (0..num_wires * degree).collect::<Vec<_>>();
+    let mut witness = PartitionWitness::new(num_wires, degree, &representative_map);
+    for &target in &watched {
+        let Target::Wire(wire) = target else {
+            unreachable!("Gate generators only read wires")
+        };
+        witness.set_target(target, sample_input(wire.column));
+    }
+    let mut written = GeneratedValues::empty();
+    for generator in &generators {
+        generator.0.run(&witness, &mut written);
+    }
+
+    watched.retain(|target| written.target_values.iter().all(|(t, _)| t != target));
+    watched
+}
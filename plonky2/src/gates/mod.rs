@@ -32,6 +32,7 @@ pub mod exponentiation;
 pub mod gate;
 pub mod lookup;
 pub mod lookup_table;
+pub mod mul_add_point;
 pub mod multiplication_extension;
 pub mod noop;
 pub mod packed_util;
@@ -41,6 +42,7 @@ pub mod public_input;
 pub mod random_access;
 pub mod reducing;
 pub mod reducing_extension;
+pub mod running_sum;
 pub(crate) mod selectors;
 pub mod util;
 
@@ -0,0 +1,438 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::marker::PhantomData;
+
+use crate::field::extension::Extendable;
+use crate::field::packed::PackedField;
+use crate::field::types::Field;
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// A gate performing several steps `acc <- acc + bit * P` of a double-and-add scalar
+/// multiplication on a short Weierstrass curve over the base field, in affine coordinates. Each
+/// step reads a bit and a point `P`, and the accumulator is passed from one step to the next.
+///
+/// The accumulator carries a flag marking the identity, whose coordinates are then ignored. `P`
+/// must not be the identity, and adding it must not be an exceptional case of the affine addition
+/// formulas, i.e. the accumulator, if not the identity, must not be `P` or `-P`. Since the curve's
+/// coefficients only appear in doublings, the gate works for any curve.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MulAddPointGate<F: RichField + Extendable<D>, const D: usize> {
+    /// The number of double-and-add steps performed by the gate.
+    pub num_steps: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> MulAddPointGate<F, D> {
+    pub const fn new(num_steps: usize) -> Self {
+        Self {
+            num_steps,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn new_from_config(config: &CircuitConfig) -> Self {
+        // The input and output accumulators, and each step's bit and point, are routed.
+        let max_for_routed_wires = (config.num_routed_wires - 6) / 3;
+        // Each step also needs its slope and, but for the last one, its output accumulator.
+        let max_for_wires = (config.num_wires - 3) / 7;
+        Self::new(max_for_routed_wires.min(max_for_wires))
+    }
+
+    pub const WIRE_IS_IDENTITY_IN: usize = 0;
+    pub const WIRE_X_IN: usize = 1;
+    pub const WIRE_Y_IN: usize = 2;
+    pub const WIRE_IS_IDENTITY_OUT: usize = 3;
+    pub const WIRE_X_OUT: usize = 4;
+    pub const WIRE_Y_OUT: usize = 5;
+
+    /// The bit of step `i`, selecting whether the point is added.
+    pub fn wire_bit(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_steps);
+        6 + 3 * i
+    }
+
+    /// The x coordinate of the point added in step `i`.
+    pub fn wire_point_x(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_steps);
+        7 + 3 * i
+    }
+
+    /// The y coordinate of the point added in step `i`.
+    pub fn wire_point_y(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_steps);
+        8 + 3 * i
+    }
+
+    /// All above wires are routed.
+    pub const fn num_routed_wires(&self) -> usize {
+        6 + 3 * self.num_steps
+    }
+
+    /// The slope of the line through the accumulator and the point in step `i`.
+    pub fn wire_lambda(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_steps);
+        self.num_routed_wires() + i
+    }
+
+    /// The identity flag and coordinates of the accumulator after step `i`.
+    pub fn wires_accumulator_out(&self, i: usize) -> [usize; 3] {
+        debug_assert!(i < self.num_steps);
+        if i + 1 == self.num_steps {
+            [
+                Self::WIRE_IS_IDENTITY_OUT,
+                Self::WIRE_X_OUT,
+                Self::WIRE_Y_OUT,
+            ]
+        } else {
+            let start = self.num_routed_wires() + self.num_steps + 3 * i;
+            [start, start + 1, start + 2]
+        }
+    }
+
+    /// The identity flag and coordinates of the accumulator before step `i`.
+    pub fn wires_accumulator_in(&self, i: usize) -> [usize; 3] {
+        if i == 0 {
+            [Self::WIRE_IS_IDENTITY_IN, Self::WIRE_X_IN, Self::WIRE_Y_IN]
+        } else {
+            self.wires_accumulator_out(i - 1)
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for MulAddPointGate<F, D> {
+    fn id(&self) -> String {
+        format!("{self:?}<D={D}>")
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.num_steps)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let num_steps = src.read_usize()?;
+        Ok(Self::new(num_steps))
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+        let one = F::Extension::ONE;
+        for i in 0..self.num_steps {
+            let [is_identity, x, y] = self.wires_accumulator_in(i).map(|w| vars.local_wires[w]);
+            let [is_identity_out, x_out, y_out] =
+                self.wires_accumulator_out(i).map(|w| vars.local_wires[w]);
+            let bit = vars.local_wires[self.wire_bit(i)];
+            let px = vars.local_wires[self.wire_point_x(i)];
+            let py = vars.local_wires[self.wire_point_y(i)];
+            let lambda = vars.local_wires[self.wire_lambda(i)];
+
+            // Whether the point is added to an accumulator other than the identity.
+            let add = bit * (one - is_identity);
+            let bit_and_identity = bit * is_identity;
+            let dx = px - x;
+            let dy = py - y;
+
+            constraints.push(bit * bit - bit);
+            constraints.push(add * (lambda * dx - dy));
+            constraints
+                .push(x_out - x - bit_and_identity * dx - add * (lambda * lambda - px - x - x));
+            constraints
+                .push(y_out - y - bit_and_identity * dy - add * (lambda * (x - x_out) - y - y));
+            constraints.push(is_identity_out - is_identity * (one - bit));
+        }
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+        let one = builder.one_extension();
+        for i in 0..self.num_steps {
+            let [is_identity, x, y] = self.wires_accumulator_in(i).map(|w| vars.local_wires[w]);
+            let [is_identity_out, x_out, y_out] =
+                self.wires_accumulator_out(i).map(|w| vars.local_wires[w]);
+            let bit = vars.local_wires[self.wire_bit(i)];
+            let px = vars.local_wires[self.wire_point_x(i)];
+            let py = vars.local_wires[self.wire_point_y(i)];
+            let lambda = vars.local_wires[self.wire_lambda(i)];
+
+            let not_identity = builder.sub_extension(one, is_identity);
+            let add = builder.mul_extension(bit, not_identity);
+            let bit_and_identity = builder.mul_extension(bit, is_identity);
+            let dx = builder.sub_extension(px, x);
+            let dy = builder.sub_extension(py, y);
+
+            constraints.push(builder.mul_sub_extension(bit, bit, bit));
+
+            let slope_diff = builder.mul_sub_extension(lambda, dx, dy);
+            constraints.push(builder.mul_extension(add, slope_diff));
+
+            let x_sum = builder.mul_sub_extension(lambda, lambda, px);
+            let x_sum = builder.sub_extension(x_sum, x);
+            let x_sum = builder.sub_extension(x_sum, x);
+            let x_sum = builder.mul_extension(add, x_sum);
+            let x_diff = builder.sub_extension(x_out, x);
+            let x_diff = builder.sub_extension(x_diff, x_sum);
+            constraints.push(builder.arithmetic_extension(
+                F::NEG_ONE,
+                F::ONE,
+                bit_and_identity,
+                dx,
+                x_diff,
+            ));
+
+            let x_drop = builder.sub_extension(x, x_out);
+            let y_sum = builder.mul_sub_extension(lambda, x_drop, y);
+            let y_sum = builder.sub_extension(y_sum, y);
+            let y_sum = builder.mul_extension(add, y_sum);
+            let y_diff = builder.sub_extension(y_out, y);
+            let y_diff = builder.sub_extension(y_diff, y_sum);
+            constraints.push(builder.arithmetic_extension(
+                F::NEG_ONE,
+                F::ONE,
+                bit_and_identity,
+                dy,
+                y_diff,
+            ));
+
+            let not_bit = builder.sub_extension(one, bit);
+            constraints.push(builder.arithmetic_extension(
+                F::NEG_ONE,
+                F::ONE,
+                is_identity,
+                not_bit,
+                is_identity_out,
+            ));
+        }
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        let gen = MulAddPointGenerator::<F, D> { row, gate: *self };
+        vec![WitnessGeneratorRef::new(gen.adapter())]
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_routed_wires() + 4 * self.num_steps - 3
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        4
+    }
+
+    fn num_constraints(&self) -> usize {
+        5 * self.num_steps
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+    for MulAddPointGate<F, D>
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        for i in 0..self.num_steps {
+            let [is_identity, x, y] = self.wires_accumulator_in(i).map(|w| vars.local_wires[w]);
+            let [is_identity_out, x_out, y_out] =
+                self.wires_accumulator_out(i).map(|w| vars.local_wires[w]);
+            let bit = vars.local_wires[self.wire_bit(i)];
+            let px = vars.local_wires[self.wire_point_x(i)];
+            let py = vars.local_wires[self.wire_point_y(i)];
+            let lambda = vars.local_wires[self.wire_lambda(i)];
+
+            let add = bit * (P::ONES - is_identity);
+            let bit_and_identity = bit * is_identity;
+            let dx = px - x;
+            let dy = py - y;
+
+            yield_constr.one(bit * bit - bit);
+            yield_constr.one(add * (lambda * dx - dy));
+            yield_constr
+                .one(x_out - x - bit_and_identity * dx - add * (lambda * lambda - px - x - x));
+            yield_constr
+                .one(y_out - y - bit_and_identity * dy - add * (lambda * (x - x_out) - y - y));
+            yield_constr.one(is_identity_out - is_identity * (P::ONES - bit));
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MulAddPointGenerator<F: RichField + Extendable<D>, const D: usize> {
+    row: usize,
+    gate: MulAddPointGate<F, D>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for MulAddPointGenerator<F, D>
+{
+    fn id(&self) -> String {
+        "MulAddPointGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        let local_target = |column| Target::wire(self.row, column);
+
+        let mut deps = self.gate.wires_accumulator_in(0).map(local_target).to_vec();
+        for i in 0..self.gate.num_steps {
+            deps.push(local_target(self.gate.wire_bit(i)));
+            deps.push(local_target(self.gate.wire_point_x(i)));
+            deps.push(local_target(self.gate.wire_point_y(i)));
+        }
+        deps
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let local_wire = |column| Wire {
+            row: self.row,
+            column,
+        };
+        let get_local_wire = |column| witness.get_wire(local_wire(column));
+
+        let [mut is_identity, mut x, mut y] = self.gate.wires_accumulator_in(0).map(get_local_wire);
+        for i in 0..self.gate.num_steps {
+            let bit = get_local_wire(self.gate.wire_bit(i));
+            let px = get_local_wire(self.gate.wire_point_x(i));
+            let py = get_local_wire(self.gate.wire_point_y(i));
+
+            let add = bit * (F::ONE - is_identity);
+            let bit_and_identity = bit * is_identity;
+            let dx = px - x;
+            let dy = py - y;
+            let lambda = if add.is_zero() {
+                F::ZERO
+            } else {
+                dy * dx
+                    .try_inverse()
+                    .expect("Exceptional case of the affine addition formulas")
+            };
+            let x_out = x + bit_and_identity * dx + add * (lambda.square() - px - x - x);
+            let y_out = y + bit_and_identity * dy + add * (lambda * (x - x_out) - y - y);
+            is_identity *= F::ONE - bit;
+            x = x_out;
+            y = y_out;
+
+            out_buffer.set_wire(local_wire(self.gate.wire_lambda(i)), lambda);
+            let [is_identity_wire, x_wire, y_wire] = self.gate.wires_accumulator_out(i);
+            out_buffer.set_wire(local_wire(is_identity_wire), is_identity);
+            out_buffer.set_wire(local_wire(x_wire), x);
+            out_buffer.set_wire(local_wire(y_wire), y);
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.row)?;
+        self.gate.serialize(dst, common_data)
+    }
+
+    fn deserialize(src: &mut Buffer, common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let row = src.read_usize()?;
+        let gate = MulAddPointGate::deserialize(src, common_data)?;
+        Ok(Self { row, gate })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Sample;
+    use crate::gates::gate_testing::{test_eval_fns, test_generators_with_inputs, test_low_degree};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn wire_indices() {
+        let gate = MulAddPointGate::<GoldilocksField, 4>::new(3);
+
+        assert_eq!(gate.wire_bit(0), 6);
+        assert_eq!(gate.wire_point_y(2), 14);
+        assert_eq!(gate.num_routed_wires(), 15);
+        assert_eq!(gate.wire_lambda(0), 15);
+        assert_eq!(gate.wires_accumulator_in(1), [18, 19, 20]);
+        assert_eq!(gate.wires_accumulator_in(2), [21, 22, 23]);
+        assert_eq!(gate.wires_accumulator_out(2), [3, 4, 5]);
+        assert_eq!(<_ as Gate<GoldilocksField, 4>>::num_wires(&gate), 24);
+    }
+
+    #[test]
+    fn new_from_config() {
+        let config = CircuitConfig::standard_recursion_config();
+        let gate = MulAddPointGate::<GoldilocksField, 2>::new_from_config(&config);
+        assert_eq!(gate.num_steps, 18);
+        assert!(gate.num_routed_wires() <= config.num_routed_wires);
+        assert!(<_ as Gate<GoldilocksField, 2>>::num_wires(&gate) <= config.num_wires);
+    }
+
+    #[test]
+    fn low_degree() {
+        let gate = MulAddPointGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_low_degree::<GoldilocksField, _, 4>(gate);
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = MulAddPointGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_eval_fns::<F, C, _, D>(gate)
+    }
+
+    #[test]
+    fn generators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = MulAddPointGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        let mut flag_wires = (0..gate.num_steps)
+            .map(|i| gate.wire_bit(i))
+            .collect::<Vec<_>>();
+        flag_wires.push(MulAddPointGate::<F, D>::WIRE_IS_IDENTITY_IN);
+        test_generators_with_inputs::<F, C, _, D>(gate, |column| {
+            if flag_wires.contains(&column) {
+                F::from_bool(OsRng.gen())
+            } else {
+                F::rand()
+            }
+        })
+    }
+}
@@ -71,7 +71,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for NoopGate {
 #[cfg(test)]
 mod tests {
     use crate::field::goldilocks_field::GoldilocksField;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generators, test_low_degree};
     use crate::gates::noop::NoopGate;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -87,4 +87,12 @@ mod tests {
         type F = <C as GenericConfig<D>>::F;
         test_eval_fns::<F, C, _, D>(NoopGate)
     }
+
+    #[test]
+    fn generators() -> anyhow::Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_generators::<F, C, _, D>(NoopGate)
+    }
 }
@@ -536,10 +536,12 @@ mod tests {
     use alloc::{vec, vec::Vec};
 
     use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
 
     use crate::field::goldilocks_field::GoldilocksField;
-    use crate::field::types::Field;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::field::types::{Field, Sample};
+    use crate::gates::gate_testing::{test_eval_fns, test_generators_with_inputs, test_low_degree};
     use crate::gates::poseidon::PoseidonGate;
     use crate::hash::poseidon::{Poseidon, SPONGE_WIDTH};
     use crate::iop::generator::generate_partial_witness;
@@ -637,4 +639,19 @@ mod tests {
         let gate = PoseidonGate::<F, 2>::new();
         test_eval_fns::<F, C, _, D>(gate)
     }
+
+    #[test]
+    fn generators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = PoseidonGate::<F, 2>::new();
+        test_generators_with_inputs::<F, C, _, D>(gate, |column| {
+            if column == PoseidonGate::<F, 2>::WIRE_SWAP {
+                F::from_bool(OsRng.gen())
+            } else {
+                F::rand()
+            }
+        })
+    }
 }
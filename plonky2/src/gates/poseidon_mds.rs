@@ -267,7 +267,7 @@ impl<F: RichField + Extendable<D> + Poseidon, const D: usize> SimpleGenerator<F,
 
 #[cfg(test)]
 mod tests {
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generators, test_low_degree};
     use crate::gates::poseidon_mds::PoseidonMdsGate;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -288,4 +288,13 @@ mod tests {
         let gate = PoseidonMdsGate::<F, D>::new();
         test_eval_fns::<F, C, _, D>(gate)
     }
+
+    #[test]
+    fn generators() -> anyhow::Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = PoseidonMdsGate::<F, D>::new();
+        test_generators::<F, C, _, D>(gate)
+    }
 }
@@ -417,7 +417,7 @@ mod tests {
     use super::*;
     use crate::field::goldilocks_field::GoldilocksField;
     use crate::field::types::Sample;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generators_with_inputs, test_low_degree};
     use crate::hash::hash_types::HashOut;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -434,6 +434,24 @@ mod tests {
         test_eval_fns::<F, C, _, D>(RandomAccessGate::new(4, 4, 1))
     }
 
+    #[test]
+    fn generators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = RandomAccessGate::new(4, 4, 1);
+        let access_index_wires = (0..gate.num_copies)
+            .map(|copy| gate.wire_access_index(copy))
+            .collect::<Vec<_>>();
+        test_generators_with_inputs::<F, C, _, D>(gate, |column| {
+            if access_index_wires.contains(&column) {
+                F::from_canonical_usize(OsRng.gen_range(0..gate.vec_size()))
+            } else {
+                F::rand()
+            }
+        })
+    }
+
     #[test]
     fn test_gate_constraint() {
         const D: usize = 2;
@@ -504,6 +522,7 @@ mod tests {
                 good_claimed_elements,
                 &constants,
             ),
+            next_wires: &[],
             public_inputs_hash: &HashOut::rand(),
         };
         let bad_claimed_elements = F::rand_vec(4);
@@ -516,6 +535,7 @@ mod tests {
                 bad_claimed_elements,
                 &constants,
             ),
+            next_wires: &[],
             public_inputs_hash: &HashOut::rand(),
         };
 
@@ -243,7 +243,7 @@ mod tests {
     use anyhow::Result;
 
     use crate::field::goldilocks_field::GoldilocksField;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generators, test_low_degree};
     use crate::gates::reducing::ReducingGate;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -259,4 +259,12 @@ mod tests {
         type F = <C as GenericConfig<D>>::F;
         test_eval_fns::<F, C, _, D>(ReducingGate::new(22))
     }
+
+    #[test]
+    fn generators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_generators::<F, C, _, D>(ReducingGate::new(22))
+    }
 }
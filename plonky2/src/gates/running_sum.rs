@@ -0,0 +1,220 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use crate::field::extension::Extendable;
+use crate::field::packed::PackedField;
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// A gate which adds one term to a running sum carried from row to row: the accumulator of the
+/// next row must equal the accumulator of this row plus this row's addend.
+///
+/// A chain of `n` additions thus takes `n` consecutive rows, followed by a row whose first wire
+/// holds the final sum.
+#[derive(Copy, Clone, Debug)]
+pub struct RunningSumGate;
+
+impl RunningSumGate {
+    pub const WIRE_ACCUMULATOR: usize = 0;
+    pub const WIRE_ADDEND: usize = 1;
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for RunningSumGate {
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn serialize(
+        &self,
+        _dst: &mut Vec<u8>,
+        _common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn deserialize(_src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        Ok(Self)
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let accumulator = vars.local_wires[Self::WIRE_ACCUMULATOR];
+        let addend = vars.local_wires[Self::WIRE_ADDEND];
+        let next_accumulator = vars.next_wires[Self::WIRE_ACCUMULATOR];
+        vec![next_accumulator - (accumulator + addend)]
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let accumulator = vars.local_wires[Self::WIRE_ACCUMULATOR];
+        let addend = vars.local_wires[Self::WIRE_ADDEND];
+        let next_accumulator = vars.next_wires[Self::WIRE_ACCUMULATOR];
+        let sum = builder.add_extension(accumulator, addend);
+        vec![builder.sub_extension(next_accumulator, sum)]
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        vec![WitnessGeneratorRef::new(
+            RunningSumGenerator { row }.adapter(),
+        )]
+    }
+
+    fn num_wires(&self) -> usize {
+        2
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        1
+    }
+
+    fn num_constraints(&self) -> usize {
+        1
+    }
+
+    fn num_next_row_wires(&self) -> usize {
+        Self::WIRE_ACCUMULATOR + 1
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D> for RunningSumGate {
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        let accumulator = vars.local_wires[Self::WIRE_ACCUMULATOR];
+        let addend = vars.local_wires[Self::WIRE_ADDEND];
+        let next_accumulator = vars.next_wires[Self::WIRE_ACCUMULATOR];
+        yield_constr.one(next_accumulator - (accumulator + addend));
+    }
+}
+
+/// Sets the accumulator of the row following a `RunningSumGate`.
+#[derive(Debug, Default)]
+pub struct RunningSumGenerator {
+    row: usize,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for RunningSumGenerator {
+    fn id(&self) -> String {
+        "RunningSumGenerator".into()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        vec![
+            Target::wire(self.row, RunningSumGate::WIRE_ACCUMULATOR),
+            Target::wire(self.row, RunningSumGate::WIRE_ADDEND),
+        ]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let accumulator =
+            witness.get_target(Target::wire(self.row, RunningSumGate::WIRE_ACCUMULATOR));
+        let addend = witness.get_target(Target::wire(self.row, RunningSumGate::WIRE_ADDEND));
+        out_buffer.set_target(
+            Target::wire(self.row + 1, RunningSumGate::WIRE_ACCUMULATOR),
+            accumulator + addend,
+        );
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.row)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let row = src.read_usize()?;
+        Ok(Self { row })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, Sample};
+    use crate::gates::gate::Gate;
+    use crate::gates::gate_testing::{test_eval_fns, test_generators, test_low_degree};
+    use crate::gates::running_sum::RunningSumGate;
+    use crate::hash::hash_types::HashOut;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::vars::EvaluationVars;
+
+    #[test]
+    fn low_degree() {
+        test_low_degree::<GoldilocksField, _, 4>(RunningSumGate)
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_eval_fns::<F, C, _, D>(RunningSumGate)
+    }
+
+    #[test]
+    fn generators() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_generators::<F, C, _, D>(RunningSumGate)
+    }
+
+    #[test]
+    fn test_gate_constraint() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let accumulator = FF::rand();
+        let addend = FF::rand();
+        let eval = |next_accumulator: FF| {
+            let vars = EvaluationVars::<F, D> {
+                local_constants: &[],
+                local_wires: &[accumulator, addend],
+                next_wires: &[next_accumulator],
+                public_inputs_hash: &HashOut::rand(),
+            };
+            <RunningSumGate as Gate<F, D>>::eval_unfiltered(&RunningSumGate, vars)
+        };
+
+        assert!(eval(accumulator + addend).iter().all(|c| c.is_zero()));
+        assert!(!eval(accumulator + addend + FF::ONE)
+            .iter()
+            .all(|c| c.is_zero()));
+    }
+}
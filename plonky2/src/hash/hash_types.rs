@@ -1,6 +1,10 @@
+use alloc::string::String;
 use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
 
-use anyhow::ensure;
+use anyhow::{ensure, Result};
+use serde::de::{self, Error as _, MapAccess, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::field::goldilocks_field::GoldilocksField;
@@ -16,9 +20,11 @@ impl RichField for GoldilocksField {}
 
 pub const NUM_HASH_OUT_ELTS: usize = 4;
 
+/// The size in bytes of the canonical byte form of a [`HashOut`].
+pub const HASH_OUT_BYTES: usize = 8 * NUM_HASH_OUT_ELTS;
+
 /// Represents a ~256 bit hash output.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
-#[serde(bound = "")]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct HashOut<F: Field> {
     pub elements: [F; NUM_HASH_OUT_ELTS],
 }
@@ -43,6 +49,127 @@ impl<F: Field> HashOut<F> {
     }
 }
 
+impl<F: RichField> HashOut<F> {
+    /// Builds a hash from its elements' canonical representations, failing if any of them is not
+    /// less than the field order.
+    pub fn from_u64s(elements: [u64; NUM_HASH_OUT_ELTS]) -> Result<Self> {
+        for x in elements {
+            ensure!(x < F::ORDER, "Non-canonical hash element {}", x);
+        }
+        Ok(Self {
+            elements: elements.map(F::from_canonical_u64),
+        })
+    }
+
+    /// The canonical byte form: each element's canonical representation, little-endian.
+    pub fn to_byte_array(&self) -> [u8; HASH_OUT_BYTES] {
+        let mut bytes = [0; HASH_OUT_BYTES];
+        for (chunk, x) in bytes.chunks_exact_mut(8).zip(self.elements) {
+            chunk.copy_from_slice(&x.to_canonical_u64().to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Parses the canonical byte form, failing if any element is not canonical.
+    ///
+    /// Unlike `GenericHashOut::from_bytes`, this rejects encodings which would otherwise be
+    /// silently reduced, so that each hash has exactly one byte form.
+    pub fn try_from_byte_array(bytes: &[u8; HASH_OUT_BYTES]) -> Result<Self> {
+        let mut elements = [0; NUM_HASH_OUT_ELTS];
+        for (x, chunk) in elements.iter_mut().zip(bytes.chunks_exact(8)) {
+            *x = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        Self::from_u64s(elements)
+    }
+
+    /// Compares two hashes in constant time, for when a hash is used as an authentication tag.
+    pub fn ct_eq(&self, other: &Self) -> bool {
+        let diff = self
+            .elements
+            .iter()
+            .zip(&other.elements)
+            .fold(0, |acc, (x, y)| {
+                acc | (x.to_canonical_u64() ^ y.to_canonical_u64())
+            });
+        core::hint::black_box(diff) == 0
+    }
+}
+
+impl<F: RichField> Serialize for HashOut<F> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.to_byte_array())
+    }
+}
+
+/// Reads a [`HashOut`] from its canonical byte form or, in self-describing formats, from the
+/// `{"elements": [..]}` form which earlier releases wrote.
+struct HashOutVisitor<F>(PhantomData<F>);
+
+impl<F: RichField> HashOutVisitor<F> {
+    fn from_bytes<E: de::Error>(bytes: &[u8]) -> Result<HashOut<F>, E> {
+        let bytes: &[u8; HASH_OUT_BYTES] = bytes
+            .try_into()
+            .map_err(|_| E::custom("Invalid hash length"))?;
+        HashOut::try_from_byte_array(bytes).map_err(E::custom)
+    }
+}
+
+impl<'de, F: RichField> Visitor<'de> for HashOutVisitor<F> {
+    type Value = HashOut<F>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{} bytes, or a map of hash elements",
+            HASH_OUT_BYTES
+        )
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        Self::from_bytes(bytes)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(HASH_OUT_BYTES);
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        Self::from_bytes(&bytes)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut elements = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key != "elements" || elements.is_some() {
+                return Err(A::Error::custom("Expected a single `elements` field"));
+            }
+            elements = Some(map.next_value::<[u64; NUM_HASH_OUT_ELTS]>()?);
+        }
+        let elements = elements.ok_or_else(|| A::Error::missing_field("elements"))?;
+        // Earlier releases wrote elements in whatever form the prover held them.
+        Ok(HashOut {
+            elements: elements.map(F::from_noncanonical_u64),
+        })
+    }
+}
+
+impl<'de, F: RichField> Deserialize<'de> for HashOut<F> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let visitor = HashOutVisitor(PhantomData);
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(visitor)
+        } else {
+            deserializer.deserialize_bytes(visitor)
+        }
+    }
+}
+
 impl<F: Field> From<[F; NUM_HASH_OUT_ELTS]> for HashOut<F> {
     fn from(elements: [F; NUM_HASH_OUT_ELTS]) -> Self {
         Self { elements }
@@ -82,10 +209,7 @@ where
 
 impl<F: RichField> GenericHashOut<F> for HashOut<F> {
     fn to_bytes(&self) -> Vec<u8> {
-        self.elements
-            .into_iter()
-            .flat_map(|x| x.to_canonical_u64().to_le_bytes())
-            .collect()
+        self.to_byte_array().to_vec()
     }
 
     fn from_bytes(bytes: &[u8]) -> Self {
@@ -103,6 +227,12 @@ impl<F: RichField> GenericHashOut<F> for HashOut<F> {
     fn to_vec(&self) -> Vec<F> {
         self.elements.to_vec()
     }
+
+    fn map_field<G: Fn(F) -> F>(self, f: G) -> Self {
+        Self {
+            elements: self.elements.map(f),
+        }
+    }
 }
 
 impl<F: Field> Default for HashOut<F> {
@@ -112,7 +242,7 @@ impl<F: Field> Default for HashOut<F> {
 }
 
 /// Represents a ~256 bit hash output.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub struct HashOutTarget {
     pub elements: [Target; NUM_HASH_OUT_ELTS],
 }
@@ -208,3 +338,92 @@ impl<'de, const N: usize> Deserialize<'de> for BytesHash<N> {
         todo!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::format;
+
+    use super::*;
+    use crate::field::types::Field64;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_hash_out_bytes() -> Result<()> {
+        let h = HashOut::<F>::rand();
+        let bytes = h.to_byte_array();
+        assert_eq!(HashOut::try_from_byte_array(&bytes)?, h);
+        assert_eq!(GenericHashOut::<F>::to_bytes(&h), bytes.to_vec());
+        assert_eq!(
+            HashOut::from_u64s(h.elements.map(|x| x.to_canonical_u64()))?,
+            h
+        );
+
+        let json = serde_json::to_string(&h).map_err(anyhow::Error::msg)?;
+        assert_eq!(
+            serde_json::from_str::<HashOut<F>>(&json).map_err(anyhow::Error::msg)?,
+            h
+        );
+
+        assert!(HashOut::<F>::from_u64s([0, 0, F::ORDER, 0]).is_err());
+        let mut non_canonical = bytes;
+        non_canonical[24..].copy_from_slice(&F::ORDER.to_le_bytes());
+        assert!(HashOut::<F>::try_from_byte_array(&non_canonical).is_err());
+        let json = serde_json::to_string(&non_canonical.to_vec()).map_err(anyhow::Error::msg)?;
+        assert!(serde_json::from_str::<HashOut<F>>(&json).is_err());
+
+        // The encoding of earlier releases, possibly holding non-canonical elements.
+        let [a, b, c, _] = h.elements.map(|x| x.to_canonical_u64());
+        let legacy = format!(r#"{{"elements":[{},{},{},{}]}}"#, a, b, c, F::ORDER + 5);
+        let mut expected = h;
+        expected.elements[3] = F::from_canonical_u64(5);
+        assert_eq!(
+            serde_json::from_str::<HashOut<F>>(&legacy).map_err(anyhow::Error::msg)?,
+            expected
+        );
+        assert!(serde_json::from_str::<HashOut<F>>(r#"{"elements":[1,2,3]}"#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ct_eq() {
+        let h = HashOut::<F>::rand();
+        assert!(h.ct_eq(&h));
+        let mut other = h;
+        other.elements[3] += F::ONE;
+        assert!(!h.ct_eq(&other));
+        assert!(HashOut::<F>::ZERO.ct_eq(&HashOut::default()));
+    }
+
+    #[test]
+    fn test_hash_is_equal() -> Result<()> {
+        let h = HashOut::<F>::rand();
+        let mut other = h;
+        other.elements[1] += F::ONE;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_hash();
+        let constant = builder.constant_hash(h);
+        let eq = builder.hash_is_equal(x, constant);
+        builder.register_public_input(eq.target);
+        let data = builder.build::<C>();
+
+        for (value, expected) in [(h, F::ONE), (other, F::ZERO)] {
+            let mut pw = PartialWitness::new();
+            pw.set_hash_target(x, value);
+            let proof = data.prove(pw)?;
+            assert_eq!(proof.public_inputs, [expected]);
+            data.verify(proof)?;
+        }
+
+        Ok(())
+    }
+}
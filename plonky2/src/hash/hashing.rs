@@ -6,7 +6,8 @@ use core::fmt::Debug;
 use crate::field::extension::Extendable;
 use crate::field::types::Field;
 use crate::hash::hash_types::{HashOut, HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
-use crate::iop::target::Target;
+use crate::hash::poseidon::PoseidonPermutation;
+use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::AlgebraicHasher;
 
@@ -27,6 +28,39 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         HashOutTarget::from_vec(self.hash_n_to_m_no_pad::<H>(inputs, NUM_HASH_OUT_ELTS))
     }
 
+    /// Compresses two digests into one, like [`Hasher::two_to_one`], with a single permutation.
+    ///
+    /// [`Hasher::two_to_one`]: crate::plonk::config::Hasher::two_to_one
+    pub fn two_to_one<H: AlgebraicHasher<F>>(
+        &mut self,
+        left: HashOutTarget,
+        right: HashOutTarget,
+    ) -> HashOutTarget {
+        let _false = self._false();
+        self.two_to_one_swapped::<H>(left, right, _false)
+    }
+
+    /// Compresses two digests into one, first swapping them if `swap` is set. This is the parent
+    /// of a Merkle path node and its sibling, where `swap` says whether the node is the right
+    /// child.
+    pub(crate) fn two_to_one_swapped<H: AlgebraicHasher<F>>(
+        &mut self,
+        left: HashOutTarget,
+        right: HashOutTarget,
+        swap: BoolTarget,
+    ) -> HashOutTarget {
+        let zero = self.zero();
+        let mut perm_inputs = H::AlgebraicPermutation::default();
+        perm_inputs.set_from_slice(&left.elements, 0);
+        perm_inputs.set_from_slice(&right.elements, NUM_HASH_OUT_ELTS);
+        // Ensure the rest of the state, if any, is zero:
+        perm_inputs.set_from_iter(core::iter::repeat(zero), 2 * NUM_HASH_OUT_ELTS);
+        let perm_outs = self.permute_swapped::<H>(perm_inputs, swap);
+        HashOutTarget {
+            elements: perm_outs.squeeze()[..NUM_HASH_OUT_ELTS].try_into().unwrap(),
+        }
+    }
+
     pub fn hash_n_to_m_no_pad<H: AlgebraicHasher<F>>(
         &mut self,
         inputs: Vec<Target>,
@@ -143,3 +177,86 @@ pub fn hash_n_to_m_no_pad<F: RichField, P: PlonkyPermutation<F>>(
 pub fn hash_n_to_hash_no_pad<F: RichField, P: PlonkyPermutation<F>>(inputs: &[F]) -> HashOut<F> {
     HashOut::from_vec(hash_n_to_m_no_pad::<F, P>(inputs, NUM_HASH_OUT_ELTS))
 }
+
+/// Expands a `seed` into `num_outputs` pseudorandom field elements, which are unpredictable without
+/// the seed. Independent uses of the same seed must pass distinct `domain`s of the same length.
+pub fn expand_seed<F: RichField>(seed: u64, domain: &[F], num_outputs: usize) -> Vec<F> {
+    if num_outputs == 0 {
+        return Vec::new();
+    }
+    let mut inputs = Vec::with_capacity(2 + domain.len());
+    inputs.push(F::from_canonical_u32(seed as u32));
+    inputs.push(F::from_canonical_u32((seed >> 32) as u32));
+    inputs.extend_from_slice(domain);
+    hash_n_to_m_no_pad::<F, PoseidonPermutation<F>>(&inputs, num_outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::hash::poseidon::{PoseidonHash, PoseidonPermutation};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_two_to_one_matches_compress() {
+        for _ in 0..100 {
+            let left = HashOut::<F>::rand();
+            let right = HashOut::<F>::rand();
+            let expected = compress::<F, PoseidonPermutation<F>>(left, right);
+            assert_eq!(PoseidonPermutation::two_to_one(left, right), expected);
+            assert_eq!(PoseidonHash::two_to_one(left, right), expected);
+        }
+
+        // The root of a small tree, computed by hand with `compress`.
+        let leaves = (0..4).map(|_| F::rand_vec(7)).collect::<Vec<_>>();
+        let digests = leaves
+            .iter()
+            .map(|leaf| hash_n_to_hash_no_pad::<F, PoseidonPermutation<F>>(leaf))
+            .collect::<Vec<_>>();
+        let root = compress::<F, PoseidonPermutation<F>>(
+            compress::<F, PoseidonPermutation<F>>(digests[0], digests[1]),
+            compress::<F, PoseidonPermutation<F>>(digests[2], digests[3]),
+        );
+        let tree = MerkleTree::<F, PoseidonHash>::new(leaves, 0);
+        assert_eq!(tree.cap.0, vec![root]);
+    }
+
+    #[test]
+    fn test_two_to_one_circuit() -> Result<()> {
+        let left = HashOut::<F>::rand();
+        let right = HashOut::<F>::rand();
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let left_t = builder.add_virtual_hash();
+        let right_t = builder.add_virtual_hash();
+        let num_gates = builder.num_gates();
+        let digest_t = builder.two_to_one::<PoseidonHash>(left_t, right_t);
+        // A single Poseidon gate, and nothing else.
+        assert_eq!(builder.num_gates(), num_gates + 1);
+        builder.register_public_inputs(&digest_t.elements);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_hash_target(left_t, left);
+        pw.set_hash_target(right_t, right);
+        let proof = data.prove(pw)?;
+        assert_eq!(
+            proof.public_inputs,
+            PoseidonHash::two_to_one(left, right).elements
+        );
+        data.verify(proof)
+    }
+}
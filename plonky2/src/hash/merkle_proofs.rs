@@ -1,3 +1,4 @@
+use alloc::boxed::Box;
 use alloc::vec;
 use alloc::vec::Vec;
 
@@ -13,12 +14,13 @@ use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::VerifierCircuitTarget;
 use crate::plonk::config::{AlgebraicHasher, Hasher};
+use crate::util::log2_strict;
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(bound = "")]
 pub struct MerkleProof<F: RichField, H: Hasher<F>> {
     /// The Merkle digest of each sibling subtree, staying from the bottommost layer.
-    pub siblings: Vec<H::Hash>,
+    pub siblings: Box<[H::Hash]>,
 }
 
 impl<F: RichField, H: Hasher<F>> MerkleProof<F, H> {
@@ -121,33 +123,97 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     ) {
         debug_assert!(H::AlgebraicPermutation::RATE >= NUM_HASH_OUT_ELTS);
 
-        let zero = self.zero();
         let mut state: HashOutTarget = self.hash_or_noop::<H>(leaf_data);
         debug_assert_eq!(state.elements.len(), NUM_HASH_OUT_ELTS);
 
         for (&bit, &sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
-            debug_assert_eq!(sibling.elements.len(), NUM_HASH_OUT_ELTS);
-
-            let mut perm_inputs = H::AlgebraicPermutation::default();
-            perm_inputs.set_from_slice(&state.elements, 0);
-            perm_inputs.set_from_slice(&sibling.elements, NUM_HASH_OUT_ELTS);
-            // Ensure the rest of the state, if any, is zero:
-            perm_inputs.set_from_iter(core::iter::repeat(zero), 2 * NUM_HASH_OUT_ELTS);
-            let perm_outs = self.permute_swapped::<H>(perm_inputs, bit);
-            let hash_outs = perm_outs.squeeze()[0..NUM_HASH_OUT_ELTS]
-                .try_into()
-                .unwrap();
-            state = HashOutTarget {
-                elements: hash_outs,
-            };
+            state = self.two_to_one_swapped::<H>(state, sibling, bit);
+        }
+
+        self.connect_merkle_cap_entry(merkle_cap, cap_index, state);
+    }
+
+    /// Verifies that the given leaf data is present at the given index in a Merkle tree whose
+    /// depth, i.e. the number of layers between the leaves and the cap, is the witnessed `depth`
+    /// rather than fixed when building the circuit. This always costs `max_depth` layers.
+    ///
+    /// `leaf_index_bits` holds the little-endian bits of the leaf index followed by those of the
+    /// cap index, so it has `max_depth` plus the cap height entries. Only the first `depth` of
+    /// `proof_siblings` are used, and only the first `depth` plus the cap height index bits.
+    ///
+    /// Layer `i` hashes the digest with its sibling if `i < depth`, and passes it through unchanged
+    /// otherwise. These flags are derived from equality checks of `depth` against each of
+    /// `0..=max_depth`, exactly one of which must hold, so the hashing layers always form a prefix
+    /// of length `depth`, and the digest compared with the cap is the root of a real path of that
+    /// length. The proof says nothing about which depth is the right one, though: callers must
+    /// bind `depth` to the leaf, e.g. by deriving it from the key, and keep leaves
+    /// domain-separated from inner nodes, or an inner node could pass as a leaf at a smaller
+    /// depth.
+    pub fn verify_merkle_proof_variable_depth<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaf_data: Vec<Target>,
+        leaf_index_bits: &[BoolTarget],
+        merkle_cap: &MerkleCapTarget,
+        proof_siblings: Vec<HashOutTarget>,
+        depth: Target,
+        max_depth: usize,
+    ) {
+        let cap_height = log2_strict(merkle_cap.0.len());
+        assert_eq!(
+            proof_siblings.len(),
+            max_depth,
+            "Expected one sibling per layer."
+        );
+        assert_eq!(
+            leaf_index_bits.len(),
+            max_depth + cap_height,
+            "Expected index bits for every layer and the cap."
+        );
+
+        // `is_depth[d]` is whether `depth == d`, which must hold for exactly one `d`.
+        let is_depth = (0..=max_depth)
+            .map(|d| {
+                let d = self.constant(F::from_canonical_usize(d));
+                self.is_equal(depth, d)
+            })
+            .collect::<Vec<_>>();
+        let num_matches = self.add_many(is_depth.iter().map(|b| b.target));
+        self.assert_one(num_matches);
+
+        let mut state = self.hash_or_noop::<H>(leaf_data);
+        let mut hashing = self._true();
+        for (i, (&bit, &sibling)) in leaf_index_bits.iter().zip(&proof_siblings).enumerate() {
+            // Layer `i` hashes iff no `d <= i` equals `depth`.
+            let is_not_depth = self.not(is_depth[i]);
+            hashing = self.and(hashing, is_not_depth);
+            let parent = self.two_to_one_swapped::<H>(state, sibling, bit);
+            state = self.select_hash(hashing, parent, state);
         }
 
+        // The cap index bits follow the `depth` bits of the path.
+        let mut cap_index = self.zero();
+        if cap_height > 0 {
+            for (d, is_d) in is_depth.into_iter().enumerate() {
+                let candidate = self.le_sum(leaf_index_bits[d..d + cap_height].iter());
+                cap_index = self.mul_add(is_d.target, candidate, cap_index);
+            }
+        }
+        self.connect_merkle_cap_entry(merkle_cap, cap_index, state);
+    }
+
+    /// Connects `digest` to the entry of `merkle_cap` at `cap_index`.
+    fn connect_merkle_cap_entry(
+        &mut self,
+        merkle_cap: &MerkleCapTarget,
+        cap_index: Target,
+        digest: HashOutTarget,
+    ) {
         for i in 0..NUM_HASH_OUT_ELTS {
             let result = self.random_access(
                 cap_index,
                 merkle_cap.0.iter().map(|h| h.elements[i]).collect(),
             );
-            self.connect(result, state.elements[i]);
+            self.connect(result, digest.elements[i]);
         }
     }
 
@@ -157,6 +223,16 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Returns whether `x` and `y` are equal, rather than constraining them to be.
+    pub fn hash_is_equal(&mut self, x: HashOutTarget, y: HashOutTarget) -> BoolTarget {
+        let mut result = self._true();
+        for i in 0..NUM_HASH_OUT_ELTS {
+            let eq = self.is_equal(x.elements[i], y.elements[i]);
+            result = self.and(result, eq);
+        }
+        result
+    }
+
     pub fn connect_merkle_caps(&mut self, x: &MerkleCapTarget, y: &MerkleCapTarget) {
         for (h0, h1) in x.0.iter().zip_eq(&y.0) {
             self.connect_hashes(*h0, *h1);
@@ -176,7 +252,8 @@ mod tests {
     use rand::Rng;
 
     use super::*;
-    use crate::field::types::Field;
+    use crate::field::types::{Field, Sample};
+    use crate::hash::hash_types::HashOut;
     use crate::hash::merkle_tree::MerkleTree;
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
@@ -232,4 +309,94 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    const MAX_DEPTH: usize = 32;
+    const CAP_HEIGHT: usize = 2;
+
+    /// Proves membership of a random leaf at the end of a random path of length `depth` below a
+    /// cap of height `CAP_HEIGHT`, claiming a depth of `claimed_depth`.
+    fn prove_variable_depth(depth: usize, claimed_depth: usize) -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+
+        let leaf = F::rand_vec(7);
+        let index_bits = MAX_DEPTH + CAP_HEIGHT;
+        let index: usize = OsRng.gen_range(0..1 << (depth + CAP_HEIGHT));
+        let siblings = (0..MAX_DEPTH).map(|_| HashOut::rand()).collect::<Vec<_>>();
+        let mut root = H::hash_or_noop(&leaf);
+        for (i, &sibling) in siblings[..depth].iter().enumerate() {
+            root = if (index >> i) & 1 == 1 {
+                H::two_to_one(sibling, root)
+            } else {
+                H::two_to_one(root, sibling)
+            };
+        }
+        let mut cap = MerkleCap::<F, H>((0..1 << CAP_HEIGHT).map(|_| HashOut::rand()).collect());
+        cap.0[index >> depth] = root;
+        verify_merkle_proof_to_cap(
+            leaf.clone(),
+            index,
+            &cap,
+            &MerkleProof {
+                siblings: siblings[..depth].into(),
+            },
+        )?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut pw = PartialWitness::new();
+        let leaf_t = builder.add_virtual_targets(leaf.len());
+        pw.set_target_arr(&leaf_t, &leaf);
+        let index_t = builder.add_virtual_target();
+        pw.set_target(index_t, F::from_canonical_usize(index));
+        let index_bits_t = builder.split_le(index_t, index_bits);
+        let cap_t = builder.add_virtual_cap(CAP_HEIGHT);
+        pw.set_cap_target(&cap_t, &cap);
+        let siblings_t = builder.add_virtual_hashes(MAX_DEPTH);
+        for (&sibling_t, &sibling) in siblings_t.iter().zip(&siblings) {
+            pw.set_hash_target(sibling_t, sibling);
+        }
+        let depth_t = builder.add_virtual_public_input();
+        pw.set_target(depth_t, F::from_canonical_usize(claimed_depth));
+
+        builder.verify_merkle_proof_variable_depth::<H>(
+            leaf_t,
+            &index_bits_t,
+            &cap_t,
+            siblings_t,
+            depth_t,
+            MAX_DEPTH,
+        );
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_variable_depth_merkle_proof() -> Result<()> {
+        for depth in [1, 17, MAX_DEPTH] {
+            prove_variable_depth(depth, depth)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_variable_depth_merkle_proof_too_shallow() {
+        prove_variable_depth(17, 16).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_variable_depth_merkle_proof_too_deep() {
+        prove_variable_depth(17, 18).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_variable_depth_merkle_proof_beyond_max_depth() {
+        prove_variable_depth(MAX_DEPTH, MAX_DEPTH + 1).unwrap();
+    }
 }
@@ -4,11 +4,13 @@ use core::slice;
 
 use plonky2_maybe_rayon::*;
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
 
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_proofs::MerkleProof;
 use crate::plonk::config::{GenericHashOut, Hasher};
 use crate::util::log2_strict;
+use crate::util::sensitive::{wipe_vec, SensitiveData};
 
 /// The Merkle cap of height `h` of a Merkle tree is the `h`-th layer (from the root) of the tree.
 /// It can be used in place of the root to verify Merkle paths, which are `h` elements shorter.
@@ -70,6 +72,23 @@ impl<F: RichField, H: Hasher<F>> Default for MerkleTree<F, H> {
     }
 }
 
+/// Wipes the leaves, which hold the committed values. The digests are left as they are.
+impl<F: RichField, H: Hasher<F>> Zeroize for MerkleTree<F, H> {
+    fn zeroize(&mut self) {
+        for leaf in &mut self.leaves {
+            wipe_vec(leaf, F::ZERO);
+        }
+    }
+}
+
+impl<F: RichField, H: Hasher<F>> SensitiveData for MerkleTree<F, H> {
+    type Field = F;
+
+    fn for_each_value(&self, f: &mut dyn FnMut(F)) {
+        self.leaves.iter().flatten().copied().for_each(f);
+    }
+}
+
 fn capacity_up_to_mut<T>(v: &mut Vec<T>, len: usize) -> &mut [MaybeUninit<T>] {
     assert!(v.capacity() >= len);
     let v_ptr = v.as_mut_ptr().cast::<MaybeUninit<T>>();
@@ -147,8 +166,81 @@ fn fill_digests_buf<F: RichField, H: Hasher<F>>(
     );
 }
 
+/// Hashes the layers of a Merkle tree, e.g. on a GPU, for [`MerkleTree::new_with_backend`].
+///
+/// The tree is built one layer at a time, bottom-up, with one call per layer: `hash_layer` for the
+/// leaves, then `two_to_one_layer` for each layer of inner nodes, up to the cap.
+pub trait MerkleBackend<F: RichField, H: Hasher<F>> {
+    /// Hashes each input with `H::hash_or_noop`.
+    fn hash_layer(&self, inputs: &[Vec<F>]) -> Vec<H::Hash>;
+
+    /// Hashes each pair of adjacent digests with `H::two_to_one`, giving the layer above.
+    fn two_to_one_layer(&self, digests: &[H::Hash]) -> Vec<H::Hash>;
+}
+
+/// A [`MerkleBackend`] hashing on the CPU, in parallel if the `parallel` feature is enabled.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CpuMerkleBackend;
+
+impl<F: RichField, H: Hasher<F>> MerkleBackend<F, H> for CpuMerkleBackend {
+    fn hash_layer(&self, inputs: &[Vec<F>]) -> Vec<H::Hash> {
+        inputs.par_iter().map(|x| H::hash_or_noop(x)).collect()
+    }
+
+    fn two_to_one_layer(&self, digests: &[H::Hash]) -> Vec<H::Hash> {
+        digests
+            .par_chunks_exact(2)
+            .map(|pair| H::two_to_one(pair[0], pair[1]))
+            .collect()
+    }
+}
+
+/// Appends the digests of the subtree below node `index` of `layers[layer]` to `digests`, in the
+/// layout of `MerkleTree::digests`.
+fn push_subtree_digests<T: Copy>(
+    digests: &mut Vec<T>,
+    layers: &[Vec<T>],
+    layer: usize,
+    index: usize,
+) {
+    if layer == 0 {
+        return;
+    }
+    let children = &layers[layer - 1];
+    push_subtree_digests(digests, layers, layer - 1, 2 * index);
+    digests.push(children[2 * index]);
+    digests.push(children[2 * index + 1]);
+    push_subtree_digests(digests, layers, layer - 1, 2 * index + 1);
+}
+
+/// The position, within the digests of its sub-tree, of the digest of node `node_index` of layer
+/// `layer`, where layer 0 holds the hashes of the leaves.
+const fn subtree_digest_index(node_index: usize, layer: usize) -> usize {
+    // The layers' data is interleaved as follows:
+    // [layer 0, layer 1, layer 0, layer 2, layer 0, layer 1, layer 0, layer 3, ...].
+    // Each of the above is a pair of siblings.
+    // `pair_index` is the index of the pair within the layer.
+    // The index of that the pair within `digests` is
+    // `pair_index * 2 ** (layer + 1) + (2 ** layer - 1)`.
+    let pair_index = node_index >> 1;
+    let siblings_index = (pair_index << (layer + 1)) + (1 << layer) - 1;
+    // We have an index for the _pair_, but we want the index of the node. Double the pair index to
+    // get the index of the left sibling, and add `1` for the right sibling.
+    2 * siblings_index + (node_index & 1)
+}
+
 impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
     pub fn new(leaves: Vec<Vec<F>>, cap_height: usize) -> Self {
+        Self::new_reusing_digests(leaves, Vec::new(), cap_height)
+    }
+
+    /// Like `new`, but stores the digests in `digests`, whose contents are discarded, so that its
+    /// allocation is reused if large enough.
+    pub(crate) fn new_reusing_digests(
+        leaves: Vec<Vec<F>>,
+        mut digests: Vec<H::Hash>,
+        cap_height: usize,
+    ) -> Self {
         let log2_leaves_len = log2_strict(leaves.len());
         assert!(
             cap_height <= log2_leaves_len,
@@ -158,7 +250,8 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
         );
 
         let num_digests = 2 * (leaves.len() - (1 << cap_height));
-        let mut digests = Vec::with_capacity(num_digests);
+        digests.clear();
+        digests.reserve_exact(num_digests);
 
         let len_cap = 1 << cap_height;
         let mut cap = Vec::with_capacity(len_cap);
@@ -181,10 +274,84 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
         }
     }
 
+    /// Like `new`, but hashes each layer of the tree with `backend`. With [`CpuMerkleBackend`],
+    /// this builds the same tree as `new`.
+    pub fn new_with_backend<B: MerkleBackend<F, H>>(
+        leaves: Vec<Vec<F>>,
+        cap_height: usize,
+        backend: &B,
+    ) -> Self {
+        let log2_leaves_len = log2_strict(leaves.len());
+        assert!(
+            cap_height <= log2_leaves_len,
+            "cap_height={} should be at most log2(leaves.len())={}",
+            cap_height,
+            log2_leaves_len
+        );
+        let num_layers = log2_leaves_len - cap_height;
+
+        let mut layers = Vec::with_capacity(num_layers + 1);
+        layers.push(backend.hash_layer(&leaves));
+        for _ in 0..num_layers {
+            let layer = backend.two_to_one_layer(layers.last().unwrap());
+            layers.push(layer);
+        }
+
+        let cap = layers.pop().unwrap();
+        assert_eq!(
+            cap.len(),
+            1 << cap_height,
+            "Backend returned a layer of the wrong size"
+        );
+        let mut digests = Vec::with_capacity(2 * (leaves.len() - cap.len()));
+        for i in 0..cap.len() {
+            push_subtree_digests(&mut digests, &layers, num_layers, i);
+        }
+
+        Self {
+            leaves,
+            digests,
+            cap: MerkleCap(cap),
+        }
+    }
+
     pub fn get(&self, i: usize) -> &[F] {
         &self.leaves[i]
     }
 
+    /// Replaces the leaf at `leaf_index`, rehashing only its path up to the cap, and returns the
+    /// new cap. The tree is then the same as one built afresh from the updated leaves.
+    pub fn update_leaf(&mut self, leaf_index: usize, new_leaf: Vec<F>) -> &MerkleCap<F, H> {
+        assert!(
+            leaf_index < self.leaves.len(),
+            "Leaf index {} is out of range for {} leaves",
+            leaf_index,
+            self.leaves.len()
+        );
+        let cap_height = log2_strict(self.cap.len());
+        let num_layers = log2_strict(self.leaves.len()) - cap_height;
+
+        let tree_index = leaf_index >> num_layers;
+        let tree_len = self.digests.len() >> cap_height;
+        let digest_tree = &mut self.digests[tree_len * tree_index..tree_len * (tree_index + 1)];
+
+        let mut digest = H::hash_or_noop(&new_leaf);
+        let mut node_index = leaf_index & ((1 << num_layers) - 1);
+        for i in 0..num_layers {
+            digest_tree[subtree_digest_index(node_index, i)] = digest;
+            let sibling = digest_tree[subtree_digest_index(node_index ^ 1, i)];
+            digest = if node_index & 1 == 0 {
+                H::two_to_one(digest, sibling)
+            } else {
+                H::two_to_one(sibling, digest)
+            };
+            node_index >>= 1;
+        }
+        self.cap.0[tree_index] = digest;
+        self.leaves[leaf_index] = new_leaf;
+        &self.cap
+    }
+
     /// Create a Merkle proof from a leaf index.
     pub fn prove(&self, leaf_index: usize) -> MerkleProof<F, H> {
         let cap_height = log2_strict(self.cap.len());
@@ -198,23 +365,11 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
         };
 
         // Mask out high bits to get the index within the sub-tree.
-        let mut pair_index = leaf_index & ((1 << num_layers) - 1);
+        let mut node_index = leaf_index & ((1 << num_layers) - 1);
         let siblings = (0..num_layers)
             .map(|i| {
-                let parity = pair_index & 1;
-                pair_index >>= 1;
-
-                // The layers' data is interleaved as follows:
-                // [layer 0, layer 1, layer 0, layer 2, layer 0, layer 1, layer 0, layer 3, ...].
-                // Each of the above is a pair of siblings.
-                // `pair_index` is the index of the pair within layer `i`.
-                // The index of that the pair within `digests` is
-                // `pair_index * 2 ** (i + 1) + (2 ** i - 1)`.
-                let siblings_index = (pair_index << (i + 1)) + (1 << i) - 1;
-                // We have an index for the _pair_, but we want the index of the _sibling_.
-                // Double the pair index to get the index of the left sibling. Conditionally add `1`
-                // if we are to retrieve the right sibling.
-                let sibling_index = 2 * siblings_index + (1 - parity);
+                let sibling_index = subtree_digest_index(node_index ^ 1, i);
+                node_index >>= 1;
                 digest_tree[sibling_index]
             })
             .collect();
@@ -225,7 +380,11 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
 
 #[cfg(test)]
 mod tests {
+    use core::cell::Cell;
+
     use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
 
     use super::*;
     use crate::field::extension::Extendable;
@@ -295,4 +454,81 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_update_leaf() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let log_n = 8;
+        let mut rng = OsRng;
+        for cap_height in [0, 3, log_n] {
+            let mut leaves = random_data::<F>(1 << log_n, 7);
+            let mut tree = MerkleTree::<F, H>::new(leaves.clone(), cap_height);
+            for _ in 0..10 {
+                let i = rng.gen_range(0..leaves.len());
+                // Short leaves aren't hashed, so cover both cases.
+                let new_leaf = random_data::<F>(1, if rng.gen() { 7 } else { 3 }).remove(0);
+                leaves[i] = new_leaf.clone();
+                let cap = tree.update_leaf(i, new_leaf).clone();
+
+                let expected = MerkleTree::<F, H>::new(leaves.clone(), cap_height);
+                assert_eq!(cap, expected.cap);
+                assert_eq!(tree, expected);
+                verify_merkle_proof_to_cap(leaves[i].clone(), i, &cap, &tree.prove(i))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Counts the calls of the wrapped backend.
+    struct CountingBackend {
+        hash_layer_calls: Cell<usize>,
+        two_to_one_layer_calls: Cell<usize>,
+    }
+
+    impl<F: RichField, H: Hasher<F>> MerkleBackend<F, H> for CountingBackend {
+        fn hash_layer(&self, inputs: &[Vec<F>]) -> Vec<H::Hash> {
+            self.hash_layer_calls.set(self.hash_layer_calls.get() + 1);
+            MerkleBackend::<F, H>::hash_layer(&CpuMerkleBackend, inputs)
+        }
+
+        fn two_to_one_layer(&self, digests: &[H::Hash]) -> Vec<H::Hash> {
+            self.two_to_one_layer_calls
+                .set(self.two_to_one_layer_calls.get() + 1);
+            MerkleBackend::<F, H>::two_to_one_layer(&CpuMerkleBackend, digests)
+        }
+    }
+
+    #[test]
+    fn test_backend_matches_new() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let log_n = 8;
+        for cap_height in [0, 3, log_n] {
+            let leaves = random_data::<F>(1 << log_n, 7);
+            let expected = MerkleTree::<F, H>::new(leaves.clone(), cap_height);
+
+            let backend = CountingBackend {
+                hash_layer_calls: Cell::new(0),
+                two_to_one_layer_calls: Cell::new(0),
+            };
+            let tree = MerkleTree::<F, H>::new_with_backend(leaves.clone(), cap_height, &backend);
+            assert_eq!(tree, expected);
+            assert_eq!(backend.hash_layer_calls.get(), 1);
+            assert_eq!(backend.two_to_one_layer_calls.get(), log_n - cap_height);
+
+            for (i, leaf) in leaves.into_iter().enumerate() {
+                verify_merkle_proof_to_cap(leaf, i, &tree.cap, &tree.prove(i))?;
+            }
+        }
+
+        Ok(())
+    }
 }
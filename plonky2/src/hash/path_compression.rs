@@ -7,6 +7,7 @@ use num::Integer;
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_proofs::MerkleProof;
 use crate::plonk::config::Hasher;
+use crate::util::log_size::LdeBits;
 
 /// Compress multiple Merkle proofs on the same tree by removing redundancy in the Merkle paths.
 pub(crate) fn compress_merkle_proofs<F: RichField, H: Hasher<F>>(
@@ -14,8 +15,10 @@ pub(crate) fn compress_merkle_proofs<F: RichField, H: Hasher<F>>(
     indices: &[usize],
     proofs: &[MerkleProof<F, H>],
 ) -> Vec<MerkleProof<F, H>> {
-    assert!(!proofs.is_empty());
-    let height = cap_height + proofs[0].siblings.len();
+    if proofs.is_empty() {
+        return Vec::new();
+    }
+    let height = LdeBits::new(cap_height + proofs[0].siblings.len());
     let num_leaves = 1 << height;
     let mut compressed_proofs = Vec::with_capacity(proofs.len());
     // Holds the known nodes in the tree at a given time. The root is at index 1.
@@ -24,47 +27,83 @@ pub(crate) fn compress_merkle_proofs<F: RichField, H: Hasher<F>>(
     let mut known = vec![false; 2 * num_leaves];
     for &i in indices {
         // The path from a leaf to the cap is known.
-        for j in 0..(height - cap_height) {
+        for j in 0..height.merkle_proof_len(cap_height) {
             known[(i + num_leaves) >> j] = true;
         }
     }
     // For each proof collect all the unknown proof elements.
     for (&i, p) in indices.iter().zip(proofs) {
-        let mut compressed_proof = MerkleProof {
-            siblings: Vec::new(),
-        };
         let mut index = i + num_leaves;
-        for &sibling in &p.siblings {
+        // Marking the path doesn't change which of its own siblings are known, so they can be
+        // counted beforehand, sizing the boxed siblings exactly.
+        let num_unknown = (0..p.len()).filter(|&j| !known[(index >> j) ^ 1]).count();
+        let mut siblings = Vec::with_capacity(num_unknown);
+        for &sibling in p.siblings.iter() {
             let sibling_index = index ^ 1;
             if !known[sibling_index] {
                 // If the sibling is not yet known, add it to the proof and set it to known.
-                compressed_proof.siblings.push(sibling);
+                siblings.push(sibling);
                 known[sibling_index] = true;
             }
             // Go up the tree and set the parent to known.
             index >>= 1;
             known[index] = true;
         }
-        compressed_proofs.push(compressed_proof);
+        compressed_proofs.push(MerkleProof {
+            siblings: siblings.into(),
+        });
     }
 
     compressed_proofs
 }
 
+/// The total number of siblings kept by `compress_merkle_proofs` for Merkle proofs of length
+/// `proof_len` of the leaves at `indices`, without the proofs themselves.
+pub(crate) fn compressed_merkle_proofs_num_siblings(
+    cap_height: usize,
+    proof_len: usize,
+    indices: &[usize],
+) -> usize {
+    if indices.is_empty() {
+        return 0;
+    }
+    let num_leaves = 1 << (cap_height + proof_len);
+    let mut known = vec![false; 2 * num_leaves];
+    for &i in indices {
+        for j in 0..proof_len {
+            known[(i + num_leaves) >> j] = true;
+        }
+    }
+    let mut num_siblings = 0;
+    for &i in indices {
+        let mut index = i + num_leaves;
+        for _ in 0..proof_len {
+            if !known[index ^ 1] {
+                num_siblings += 1;
+                known[index ^ 1] = true;
+            }
+            index >>= 1;
+            known[index] = true;
+        }
+    }
+    num_siblings
+}
+
 /// Decompress compressed Merkle proofs.
 /// Note: The data and indices must be in the same order as in `compress_merkle_proofs`.
 pub(crate) fn decompress_merkle_proofs<F: RichField, H: Hasher<F>>(
-    leaves_data: &[Vec<F>],
+    leaves_data: &[&[F]],
     leaves_indices: &[usize],
-    compressed_proofs: &[MerkleProof<F, H>],
-    height: usize,
+    compressed_proofs: &[&MerkleProof<F, H>],
+    height: LdeBits,
     cap_height: usize,
 ) -> Vec<MerkleProof<F, H>> {
     let num_leaves = 1 << height;
-    let compressed_proofs = compressed_proofs.to_vec();
+    let proof_len = height.merkle_proof_len(cap_height);
     let mut decompressed_proofs = Vec::with_capacity(compressed_proofs.len());
-    // Holds the already seen nodes in the tree along with their value.
-    let mut seen = HashMap::new();
+    // Holds the already seen nodes in the tree along with their value. Each leaf's path adds at most
+    // two nodes per layer.
+    let mut seen = HashMap::with_capacity(leaves_indices.len() * (2 * proof_len + 1));
 
     for (&i, v) in leaves_indices.iter().zip(leaves_data) {
         // Observe the leaves.
@@ -77,7 +116,7 @@ pub(crate) fn decompress_merkle_proofs<F: RichField, H: Hasher<F>>(
         .map(|p| p.siblings.iter())
         .collect::<Vec<_>>();
     // Fill the `seen` map from the bottom of the tree to the cap.
-    for layer_height in 0..height - cap_height {
+    for layer_height in 0..proof_len {
         for (&i, p) in leaves_indices.iter().zip(siblings.iter_mut()) {
             let index = (i + num_leaves) >> layer_height;
             let current_hash = seen[&index];
@@ -95,18 +134,16 @@ pub(crate) fn decompress_merkle_proofs<F: RichField, H: Hasher<F>>(
     }
     // For every index, go up the tree by querying `seen` to get node values.
     for &i in leaves_indices {
-        let mut decompressed_proof = MerkleProof {
-            siblings: Vec::new(),
-        };
         let mut index = i + num_leaves;
-        for _ in 0..height - cap_height {
-            let sibling_index = index ^ 1;
-            let h = seen[&sibling_index];
-            decompressed_proof.siblings.push(h);
-            index >>= 1;
-        }
-
-        decompressed_proofs.push(decompressed_proof);
+        let siblings = (0..proof_len)
+            .map(|_| {
+                let sibling_index = index ^ 1;
+                index >>= 1;
+                seen[&sibling_index]
+            })
+            .collect();
+
+        decompressed_proofs.push(MerkleProof { siblings });
     }
 
     decompressed_proofs
@@ -139,10 +176,13 @@ mod tests {
 
         let compressed_proofs = compress_merkle_proofs(cap_height, &indices, &proofs);
         let decompressed_proofs = decompress_merkle_proofs(
-            &indices.iter().map(|&i| vs[i].clone()).collect::<Vec<_>>(),
+            &indices
+                .iter()
+                .map(|&i| vs[i].as_slice())
+                .collect::<Vec<_>>(),
             &indices,
-            &compressed_proofs,
-            h,
+            &compressed_proofs.iter().collect::<Vec<_>>(),
+            LdeBits::new(h),
             cap_height,
         );
 
@@ -5,6 +5,8 @@ use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt::Debug;
 
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
 use unroll::unroll_for_loops;
 
 use crate::field::extension::{Extendable, FieldExtension};
@@ -12,8 +14,8 @@ use crate::field::types::{Field, PrimeField64};
 use crate::gates::gate::Gate;
 use crate::gates::poseidon::PoseidonGate;
 use crate::gates::poseidon_mds::PoseidonMdsGate;
-use crate::hash::hash_types::{HashOut, RichField};
-use crate::hash::hashing::{compress, hash_n_to_hash_no_pad, PlonkyPermutation};
+use crate::hash::hash_types::{HashOut, RichField, NUM_HASH_OUT_ELTS};
+use crate::hash::hashing::{hash_n_to_hash_no_pad, PlonkyPermutation};
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
@@ -633,6 +635,126 @@ pub trait Poseidon: PrimeField64 {
     }
 }
 
+/// The parameters of the Poseidon permutation over a field, for implementations outside this
+/// crate.
+///
+/// Each of the `full_rounds + partial_rounds` rounds adds the round's constants to the state,
+/// applies the S-box `x -> x^sbox_exponent` to every element in full rounds and to the first
+/// element in partial rounds, and then multiplies the state by the MDS matrix. Half of the full
+/// rounds come before the partial rounds, and half after.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PoseidonParams {
+    /// The number of field elements in the state.
+    pub width: usize,
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
+    pub sbox_exponent: u64,
+    /// The round constants, `width` per round: element `i` of the state is offset by
+    /// `round_constants[round * width + i]` in round `round`.
+    pub round_constants: Vec<u64>,
+    /// The MDS matrix, by rows, as canonical field elements. Element `r` of the new state is
+    /// `sum_c mds[r][c] * state[c]`.
+    pub mds: Vec<Vec<u64>>,
+}
+
+/// The parameters of the Poseidon permutation over `F`, as used by [`Poseidon::poseidon`].
+pub fn params<F: Poseidon>() -> PoseidonParams {
+    let mds = (0..SPONGE_WIDTH)
+        .map(|r| {
+            (0..SPONGE_WIDTH)
+                .map(|c| {
+                    let mut entry = F::from_canonical_u64(
+                        F::MDS_MATRIX_CIRC[(c + SPONGE_WIDTH - r) % SPONGE_WIDTH],
+                    );
+                    if r == c {
+                        entry += F::from_canonical_u64(F::MDS_MATRIX_DIAG[r]);
+                    }
+                    entry.to_canonical_u64()
+                })
+                .collect()
+        })
+        .collect();
+    PoseidonParams {
+        width: SPONGE_WIDTH,
+        full_rounds: N_FULL_ROUNDS_TOTAL,
+        partial_rounds: N_PARTIAL_ROUNDS,
+        sbox_exponent: 7,
+        round_constants: ALL_ROUND_CONSTANTS[..SPONGE_WIDTH * N_ROUNDS].to_vec(),
+        mds,
+    }
+}
+
+/// Applies the permutation described by `params` to `input`, naively, one round at a time.
+fn reference_permutation<F: PrimeField64>(params: &PoseidonParams, input: &[F]) -> Vec<F> {
+    let width = params.width;
+    let half_full_rounds = params.full_rounds / 2;
+    let mut state = input.to_vec();
+    for round in 0..params.full_rounds + params.partial_rounds {
+        let round_constants = &params.round_constants[round * width..(round + 1) * width];
+        for (x, &c) in state.iter_mut().zip(round_constants) {
+            *x += F::from_noncanonical_u64(c);
+        }
+
+        let is_full_round =
+            round < half_full_rounds || round >= half_full_rounds + params.partial_rounds;
+        let num_sboxes = if is_full_round { width } else { 1 };
+        for x in &mut state[..num_sboxes] {
+            *x = x.exp_u64(params.sbox_exponent);
+        }
+
+        state = params
+            .mds
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(&state)
+                    .map(|(&m, &x)| F::from_canonical_u64(m) * x)
+                    .fold(F::ZERO, |acc, y| acc + y)
+            })
+            .collect();
+    }
+    state
+}
+
+/// Checks that the exported [`params`] describe the permutation computed by the optimized
+/// [`Poseidon::poseidon`], by comparing both on a few fixed states.
+pub fn verify_params_against_permutation<F: Poseidon>() -> Result<()> {
+    let params = params::<F>();
+    ensure!(
+        params.round_constants.len() == params.width * (params.full_rounds + params.partial_rounds),
+        "Expected one round constant per state element and round"
+    );
+    ensure!(
+        params.mds.len() == params.width && params.mds.iter().all(|row| row.len() == params.width),
+        "Expected a square MDS matrix of the state's width"
+    );
+
+    let mut inputs = vec![
+        [F::ZERO; SPONGE_WIDTH],
+        core::array::from_fn(|i| F::from_canonical_usize(i)),
+        [F::NEG_ONE; SPONGE_WIDTH],
+    ];
+    // A few pseudo-random states, from a fixed linear congruential generator.
+    let mut seed = 0x9e3779b97f4a7c15u64;
+    for _ in 0..4 {
+        inputs.push(core::array::from_fn(|_| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            F::from_noncanonical_u64(seed)
+        }));
+    }
+
+    for input in inputs {
+        ensure!(
+            F::poseidon(input).as_slice() == reference_permutation(&params, &input),
+            "Poseidon diverges from its exported parameters on input {:?}",
+            input
+        );
+    }
+    Ok(())
+}
+
 #[derive(Copy, Clone, Default, Debug, PartialEq)]
 pub struct PoseidonPermutation<T> {
     state: [T; SPONGE_WIDTH],
@@ -701,6 +823,22 @@ impl<T: Copy + Debug + Default + Eq + Permuter + Send + Sync> PlonkyPermutation<
     }
 }
 
+impl<F: Poseidon> PoseidonPermutation<F> {
+    /// Compresses two digests with a single permutation of `left || right || 0^4`, keeping the
+    /// first four outputs. This computes exactly [`compress`](crate::hash::hashing::compress) for
+    /// this permutation, without going through the generic [`PlonkyPermutation`] interface.
+    #[inline]
+    pub fn two_to_one(left: HashOut<F>, right: HashOut<F>) -> HashOut<F> {
+        let mut state = [F::ZERO; SPONGE_WIDTH];
+        state[..NUM_HASH_OUT_ELTS].copy_from_slice(&left.elements);
+        state[NUM_HASH_OUT_ELTS..2 * NUM_HASH_OUT_ELTS].copy_from_slice(&right.elements);
+        let state = F::poseidon(state);
+        HashOut {
+            elements: [state[0], state[1], state[2], state[3]],
+        }
+    }
+}
+
 /// Poseidon hash function.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct PoseidonHash;
@@ -714,7 +852,7 @@ impl<F: RichField> Hasher<F> for PoseidonHash {
     }
 
     fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
-        compress::<F, Self::Permutation>(left, right)
+        PoseidonPermutation::two_to_one(left, right)
     }
 }
 
@@ -247,6 +247,19 @@ impl Poseidon for GoldilocksField {
         result
     }
 
+    #[inline]
+    fn full_rounds(state: &mut [Self; 12], round_ctr: &mut usize) {
+        crate::hash::poseidon_simd::full_rounds(state, round_ctr);
+    }
+
+    #[cfg(not(all(target_arch = "aarch64", target_feature = "neon")))]
+    #[inline(always)]
+    fn sbox_layer(state: &mut [Self; 12]) {
+        for x in state.iter_mut() {
+            *x = x.sbox();
+        }
+    }
+
     // #[cfg(all(target_arch="x86_64", target_feature="avx2", target_feature="bmi2"))]
     // #[inline]
     // fn poseidon(input: [Self; 12]) -> [Self; 12] {
@@ -447,12 +460,20 @@ mod tests {
     #[cfg(not(feature = "std"))]
     use alloc::{vec, vec::Vec};
 
+    use anyhow::Result;
+    use serde::{Deserialize, Serialize};
+
     use crate::field::goldilocks_field::GoldilocksField as F;
     use crate::field::types::{Field, PrimeField64};
     use crate::hash::poseidon::test_helpers::{check_consistency, check_test_vectors};
-
-    #[test]
-    fn test_vectors() {
+    use crate::hash::poseidon::{
+        params, verify_params_against_permutation, Poseidon, PoseidonParams, SPONGE_WIDTH,
+    };
+    #[cfg(feature = "std")]
+    use crate::util::test_fixtures::write_fixture;
+
+    /// The known-answer vectors, as `(input, output)` states.
+    fn known_answer_vectors() -> Vec<([u64; 12], [u64; 12])> {
         // Test inputs are:
         // 1. all zeros
         // 2. range 0..WIDTH
@@ -485,8 +506,71 @@ mod tests {
               0x8a7a6ca87892574f, 0xc7017e1cad1a674e, 0x1f06668922318e34, 0xa3b203bc8102676f,
               0xfcc781b0ce382bf2, 0x934c69ff3ed14ba5, 0x504688a5996e8f13, 0x401f3f2ed524a2ba, ]),
         ];
+        test_vectors12
+    }
 
-        check_test_vectors::<F>(test_vectors12);
+    #[test]
+    fn test_vectors() {
+        check_test_vectors::<F>(known_answer_vectors());
+    }
+
+    /// The exported parameters along with the known-answer vectors, for external implementations.
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct KnownAnswers {
+        params: PoseidonParams,
+        vectors: Vec<KnownAnswer>,
+    }
+
+    #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+    struct KnownAnswer {
+        input: [u64; SPONGE_WIDTH],
+        output: [u64; SPONGE_WIDTH],
+    }
+
+    fn known_answers() -> KnownAnswers {
+        let vectors = known_answer_vectors()
+            .into_iter()
+            .map(|(input, _)| KnownAnswer {
+                input,
+                output: F::poseidon(input.map(F::from_canonical_u64)).map(|x| x.to_canonical_u64()),
+            })
+            .collect();
+        KnownAnswers {
+            params: params::<F>(),
+            vectors,
+        }
+    }
+
+    #[test]
+    fn test_known_answers_fixture() -> Result<()> {
+        let fixture: KnownAnswers =
+            serde_json::from_str(include_str!("fixtures/poseidon_goldilocks.json"))
+                .map_err(anyhow::Error::msg)?;
+        assert_eq!(fixture, known_answers());
+        let fixture_vectors: Vec<_> = fixture
+            .vectors
+            .iter()
+            .map(|v| (v.input, v.output))
+            .collect();
+        assert_eq!(fixture_vectors, known_answer_vectors());
+        Ok(())
+    }
+
+    #[test]
+    fn test_params_match_permutation() -> Result<()> {
+        verify_params_against_permutation::<F>()
+    }
+
+    /// Regenerates the known-answer fixture, after a deliberate change of the parameters.
+    #[cfg(feature = "std")]
+    #[test]
+    #[ignore]
+    fn regenerate_poseidon_fixture() -> Result<()> {
+        write_fixture(
+            "hash/fixtures/poseidon_goldilocks.json",
+            serde_json::to_string_pretty(&known_answers())? + "\n",
+        )?;
+        Ok(())
     }
 
     #[test]
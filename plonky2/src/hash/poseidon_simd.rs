@@ -0,0 +1,372 @@
+//! Poseidon's full rounds over Goldilocks on [`GoldilocksVector`]s, with the implementation chosen
+//! at runtime rather than by compile-time target features.
+//!
+//! Every [`SimdBackend`] computes the same permutation; only the speed differs. The backend is
+//! detected the first time it is needed, unless the `PLONKY2_SIMD_BACKEND` environment variable
+//! names one, and can be overridden with [`set_simd_backend`].
+
+use core::simd::{LaneCount, SupportedLaneCount};
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use anyhow::{ensure, Result};
+#[cfg(feature = "timing")]
+use web_time::{Duration, Instant};
+
+use crate::field::goldilocks_field::GoldilocksField;
+use crate::field::simd::GoldilocksVector;
+use crate::hash::poseidon::{
+    Poseidon, ALL_ROUND_CONSTANTS, HALF_N_FULL_ROUNDS, N_ROUNDS, SPONGE_WIDTH,
+};
+
+/// Whether an x86 feature is available, at runtime with `std`, and otherwise only if enabled at
+/// compile time.
+macro_rules! x86_feature_detected {
+    ($feature:tt) => {{
+        #[cfg(all(feature = "std", target_arch = "x86_64"))]
+        {
+            std::arch::is_x86_feature_detected!($feature)
+        }
+        #[cfg(not(all(feature = "std", target_arch = "x86_64")))]
+        {
+            cfg!(all(target_arch = "x86_64", target_feature = $feature))
+        }
+    }};
+}
+
+/// An implementation of Poseidon's full rounds.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SimdBackend {
+    /// The scalar layers of `Poseidon for GoldilocksField`.
+    Scalar,
+    /// 4 lanes, with whichever vector instructions the crate was compiled for, e.g. NEON on
+    /// AArch64.
+    Portable,
+    /// 4 lanes with AVX2.
+    Avx2,
+    /// 8 lanes with AVX-512.
+    Avx512,
+}
+
+impl SimdBackend {
+    pub const ALL: [Self; 4] = [Self::Scalar, Self::Portable, Self::Avx2, Self::Avx512];
+
+    /// The name used by `PLONKY2_SIMD_BACKEND`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Scalar => "scalar",
+            Self::Portable => "portable",
+            Self::Avx2 => "avx2",
+            Self::Avx512 => "avx512",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.name() == name)
+    }
+
+    /// Whether the CPU can run this backend.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Self::Scalar | Self::Portable => true,
+            Self::Avx2 => x86_feature_detected!("avx2"),
+            Self::Avx512 => x86_feature_detected!("avx512f") && x86_feature_detected!("avx512dq"),
+        }
+    }
+
+    /// The fastest backend the CPU supports, found by timing each on a few full rounds. Wider
+    /// vectors aren't always faster: the vectors only hold a single state, and the scalar layers
+    /// are heavily optimized, so on some x86 CPUs the scalar backend wins even against AVX-512.
+    /// Without the `timing` feature, this is the scalar backend.
+    pub fn detect() -> Self {
+        #[cfg(feature = "timing")]
+        {
+            Self::ALL
+                .into_iter()
+                .filter(|b| b.is_supported())
+                .min_by_key(|&b| b.time_full_rounds())
+                .unwrap_or(Self::Scalar)
+        }
+        #[cfg(not(feature = "timing"))]
+        {
+            Self::Scalar
+        }
+    }
+
+    /// The best time of a few runs of the full rounds of 64 permutations.
+    #[cfg(feature = "timing")]
+    fn time_full_rounds(self) -> Duration {
+        const RUNS: usize = 3;
+        const PERMUTATIONS: usize = 64;
+        (0..RUNS)
+            .map(|_| {
+                let mut state = [GoldilocksField(0); SPONGE_WIDTH];
+                let start = Instant::now();
+                for _ in 0..PERMUTATIONS {
+                    let mut round_ctr = 0;
+                    // Safety: only supported backends are timed.
+                    unsafe { full_rounds_with(self, &mut state, &mut round_ctr) };
+                }
+                core::hint::black_box(state);
+                start.elapsed()
+            })
+            .min()
+            .unwrap()
+    }
+
+    fn from_u8(x: u8) -> Self {
+        Self::ALL[x as usize]
+    }
+}
+
+const UNSELECTED: u8 = u8::MAX;
+
+/// The selected backend as its index in `SimdBackend::ALL`, or `UNSELECTED`.
+static SELECTED_BACKEND: AtomicU8 = AtomicU8::new(UNSELECTED);
+
+/// The backend Poseidon uses, selected on the first call.
+pub fn simd_backend() -> SimdBackend {
+    match SELECTED_BACKEND.load(Ordering::Relaxed) {
+        UNSELECTED => {
+            let backend = backend_from_env().unwrap_or_else(SimdBackend::detect);
+            SELECTED_BACKEND.store(backend as u8, Ordering::Relaxed);
+            backend
+        }
+        selected => SimdBackend::from_u8(selected),
+    }
+}
+
+/// Forces Poseidon to use `backend`, or to detect one again if `None`. Fails if the CPU doesn't
+/// support `backend`.
+pub fn set_simd_backend(backend: Option<SimdBackend>) -> Result<()> {
+    let backend = match backend {
+        Some(backend) => {
+            ensure!(
+                backend.is_supported(),
+                "The CPU doesn't support the {} backend",
+                backend.name()
+            );
+            backend
+        }
+        None => SimdBackend::detect(),
+    };
+    SELECTED_BACKEND.store(backend as u8, Ordering::Relaxed);
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+fn backend_from_env() -> Option<SimdBackend> {
+    let name = std::env::var("PLONKY2_SIMD_BACKEND").ok()?;
+    let backend = SimdBackend::from_name(&name)
+        .ok_or_else(|| anyhow::anyhow!("Unknown backend `{name}`"))
+        .and_then(|backend| set_simd_backend(Some(backend)).map(|_| backend));
+    match backend {
+        Ok(backend) => Some(backend),
+        Err(e) => {
+            log::warn!("Ignoring PLONKY2_SIMD_BACKEND: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+fn backend_from_env() -> Option<SimdBackend> {
+    None
+}
+
+/// The state, padded so that any number of lanes up to 8 divides its length.
+const PADDED_WIDTH: usize = 16;
+
+/// `ALL_ROUND_CONSTANTS`, with each round's constants padded to `PADDED_WIDTH`.
+const PADDED_ROUND_CONSTANTS: [[u64; PADDED_WIDTH]; N_ROUNDS] = {
+    let mut constants = [[0; PADDED_WIDTH]; N_ROUNDS];
+    let mut round = 0;
+    while round < N_ROUNDS {
+        let mut i = 0;
+        while i < SPONGE_WIDTH {
+            constants[round][i] = ALL_ROUND_CONSTANTS[SPONGE_WIDTH * round + i];
+            i += 1;
+        }
+        round += 1;
+    }
+    constants
+};
+
+/// `HALF_N_FULL_ROUNDS` full rounds, as in `Poseidon::full_rounds`, with the backend returned by
+/// [`simd_backend`].
+#[inline]
+pub(crate) fn full_rounds(state: &mut [GoldilocksField; SPONGE_WIDTH], round_ctr: &mut usize) {
+    // Safety: `set_simd_backend` and `detect` only select backends the CPU supports.
+    unsafe { full_rounds_with(simd_backend(), state, round_ctr) }
+}
+
+/// The full rounds with `backend`, which the CPU must support.
+#[inline(always)]
+unsafe fn full_rounds_with(
+    backend: SimdBackend,
+    state: &mut [GoldilocksField; SPONGE_WIDTH],
+    round_ctr: &mut usize,
+) {
+    match backend {
+        SimdBackend::Scalar => full_rounds_scalar(state, round_ctr),
+        SimdBackend::Portable => full_rounds_vector::<4>(state, round_ctr),
+        #[cfg(target_arch = "x86_64")]
+        SimdBackend::Avx2 => full_rounds_avx2(state, round_ctr),
+        #[cfg(target_arch = "x86_64")]
+        SimdBackend::Avx512 => full_rounds_avx512(state, round_ctr),
+        #[cfg(not(target_arch = "x86_64"))]
+        SimdBackend::Avx2 | SimdBackend::Avx512 => unreachable!("Not an x86_64 CPU"),
+    }
+}
+
+#[inline(always)]
+fn full_rounds_scalar(state: &mut [GoldilocksField; SPONGE_WIDTH], round_ctr: &mut usize) {
+    for _ in 0..HALF_N_FULL_ROUNDS {
+        GoldilocksField::constant_layer(state, *round_ctr);
+        GoldilocksField::sbox_layer(state);
+        *state = GoldilocksField::mds_layer(state);
+        *round_ctr += 1;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn full_rounds_avx2(state: &mut [GoldilocksField; SPONGE_WIDTH], round_ctr: &mut usize) {
+    full_rounds_vector::<4>(state, round_ctr);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512dq")]
+unsafe fn full_rounds_avx512(state: &mut [GoldilocksField; SPONGE_WIDTH], round_ctr: &mut usize) {
+    full_rounds_vector::<8>(state, round_ctr);
+}
+
+/// The full rounds on vectors of `LANES` elements of the state. This is inlined into the
+/// `target_feature` functions above, so that it is compiled for their instruction sets.
+#[inline(always)]
+fn full_rounds_vector<const LANES: usize>(
+    state: &mut [GoldilocksField; SPONGE_WIDTH],
+    round_ctr: &mut usize,
+) where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    let mut padded = [0u64; PADDED_WIDTH];
+    for (p, x) in padded.iter_mut().zip(state.iter()) {
+        *p = x.0;
+    }
+
+    for _ in 0..HALF_N_FULL_ROUNDS {
+        let round_constants = &PADDED_ROUND_CONSTANTS[*round_ctr];
+        for start in (0..SPONGE_WIDTH).step_by(LANES) {
+            let x = GoldilocksVector::<LANES>::from_slice(&padded[start..]);
+            let x = x + GoldilocksVector::from_slice(&round_constants[start..]);
+            // x |--> x^7
+            let x2 = x.square();
+            let x3 = x * x2;
+            let x4 = x2.square();
+            (x3 * x4).write_to_slice(&mut padded[start..]);
+        }
+        padded = mds_layer_vector::<LANES>(&padded);
+        *round_ctr += 1;
+    }
+
+    for (x, &p) in state.iter_mut().zip(&padded) {
+        *x = GoldilocksField(p);
+    }
+}
+
+/// The columns of the MDS matrix, padded to `PADDED_WIDTH`. Row `r` of the circulant part is
+/// `MDS_MATRIX_CIRC` rotated right by `r`, so column `j` holds `MDS_MATRIX_CIRC[j - r]` in row `r`.
+const PADDED_MDS_COLUMNS: [[u64; PADDED_WIDTH]; SPONGE_WIDTH] = {
+    let mut columns = [[0; PADDED_WIDTH]; SPONGE_WIDTH];
+    let mut j = 0;
+    while j < SPONGE_WIDTH {
+        let mut r = 0;
+        while r < SPONGE_WIDTH {
+            columns[j][r] = <GoldilocksField as Poseidon>::MDS_MATRIX_CIRC
+                [(j + SPONGE_WIDTH - r) % SPONGE_WIDTH];
+            r += 1;
+        }
+        columns[j][j] += <GoldilocksField as Poseidon>::MDS_MATRIX_DIAG[j];
+        j += 1;
+    }
+    columns
+};
+
+/// The MDS layer, as in `Poseidon::mds_layer`, computing `LANES` rows at a time as the sum of the
+/// columns of the matrix scaled by the elements of the state.
+#[inline(always)]
+fn mds_layer_vector<const LANES: usize>(state: &[u64; PADDED_WIDTH]) -> [u64; PADDED_WIDTH]
+where
+    LaneCount<LANES>: SupportedLaneCount,
+{
+    let xs = core::array::from_fn(|j| GoldilocksVector::<LANES>::splat(GoldilocksField(state[j])));
+    let mut result = [0u64; PADDED_WIDTH];
+    for start in (0..SPONGE_WIDTH).step_by(LANES) {
+        let cs = PADDED_MDS_COLUMNS.map(|column| GoldilocksVector::from_slice(&column[start..]));
+        GoldilocksVector::linear_combination_small(xs, cs).write_to_slice(&mut result[start..]);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::field::types::{PrimeField64, Sample};
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::plonk::config::Hasher;
+
+    #[test]
+    fn test_backends_match() -> Result<()> {
+        let inputs = (0..16)
+            .map(|_| GoldilocksField::rand_array::<SPONGE_WIDTH>())
+            .chain([[GoldilocksField(u64::MAX); SPONGE_WIDTH]])
+            .collect::<Vec<_>>();
+        let messages = (0..20).map(GoldilocksField::rand_vec).collect::<Vec<_>>();
+
+        let run = |backend: SimdBackend| -> Result<_> {
+            set_simd_backend(Some(backend))?;
+            assert_eq!(simd_backend(), backend);
+            let permutations = inputs
+                .iter()
+                .map(|&input| GoldilocksField::poseidon(input).map(|x| x.to_canonical_u64()))
+                .collect::<Vec<_>>();
+            let digests = messages
+                .iter()
+                .map(|m| <PoseidonHash as Hasher<GoldilocksField>>::hash_no_pad(m))
+                .collect::<Vec<_>>();
+            Ok((permutations, digests))
+        };
+
+        let expected = run(SimdBackend::Scalar)?;
+        for backend in SimdBackend::ALL {
+            if backend.is_supported() {
+                assert_eq!(run(backend)?, expected, "{}", backend.name());
+            } else {
+                assert!(set_simd_backend(Some(backend)).is_err());
+            }
+        }
+        set_simd_backend(None)
+    }
+
+    #[test]
+    fn test_full_rounds_match_naive() {
+        for backend in SimdBackend::ALL.into_iter().filter(|b| b.is_supported()) {
+            let input = GoldilocksField::rand_array::<SPONGE_WIDTH>();
+            let mut state = input;
+            let mut round_ctr = 0;
+            unsafe { full_rounds_with(backend, &mut state, &mut round_ctr) };
+            assert_eq!(round_ctr, HALF_N_FULL_ROUNDS);
+
+            let mut expected = input;
+            for round in 0..HALF_N_FULL_ROUNDS {
+                GoldilocksField::constant_layer_field(&mut expected, round);
+                GoldilocksField::sbox_layer_field(&mut expected);
+                expected = GoldilocksField::mds_layer_field(&expected);
+            }
+            assert_eq!(state, expected, "{}", backend.name());
+        }
+    }
+}
@@ -0,0 +1,672 @@
+//! Export and import of proofs in the JSON shape consumed by gnark-based plonky2 verifiers.
+//!
+//! Field elements are encoded as decimal strings of their canonical `u64` value, and extension
+//! field elements as arrays of their `D` base field coefficients. A hash is encoded as the array
+//! of little-endian 64-bit words of its byte representation, so a Poseidon [`HashOut`] is simply
+//! its four elements in order.
+//!
+//! [`HashOut`]: crate::hash::hash_types::HashOut
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use serde_json::{json, Value};
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::polynomial::PolynomialCoeffs;
+use crate::field::types::PrimeField64;
+use crate::fri::proof::{FriInitialTreeProof, FriProof, FriQueryRound, FriQueryStep};
+use crate::fri::FriPowStrategy;
+use crate::hash::hash_types::RichField;
+use crate::hash::merkle_proofs::MerkleProof;
+use crate::hash::merkle_tree::MerkleCap;
+use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
+use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::proof::{OpeningSet, Proof, ProofWithPublicInputs};
+use crate::plonk::validate_shape::validate_proof_with_pis_shape;
+
+/// Serializes a proof, together with the verifier data and the subset of the common circuit data
+/// needed by gnark verifiers, into a single JSON object with the keys `proof_with_public_inputs`,
+/// `verifier_only_circuit_data` and `common_circuit_data`.
+///
+/// Returns an error if the circuit uses a feature gnark verifiers do not support; see
+/// [`common_data_to_gnark_value`].
+pub fn to_gnark_json<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<String> {
+    Ok(json!({
+        "proof_with_public_inputs": proof_with_pis_to_gnark_value(proof_with_pis),
+        "verifier_only_circuit_data": verifier_only_to_gnark_value(verifier_data),
+        "common_circuit_data": common_data_to_gnark_value(common_data)?,
+    })
+    .to_string())
+}
+
+/// Parses a proof from JSON produced by [`to_gnark_json`], or from a bare proof object as produced
+/// by [`proof_with_pis_to_gnark_value`]. The proof's shape is checked against `common_data`.
+pub fn from_gnark_json<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    json: &str,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| anyhow!("Invalid gnark JSON: {}", e))?;
+    let proof_value = value.get("proof_with_public_inputs").unwrap_or(&value);
+    let proof_with_pis = proof_with_pis_from_gnark_value(proof_value)?;
+    validate_proof_with_pis_shape(&proof_with_pis, common_data)?;
+    Ok(proof_with_pis)
+}
+
+/// Parses verifier data from JSON produced by [`to_gnark_json`], or from a bare verifier data
+/// object as produced by [`verifier_only_to_gnark_value`].
+pub fn verifier_only_from_gnark_json<C: GenericConfig<D>, const D: usize>(
+    json: &str,
+) -> Result<VerifierOnlyCircuitData<C, D>> {
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| anyhow!("Invalid gnark JSON: {}", e))?;
+    let value = value.get("verifier_only_circuit_data").unwrap_or(&value);
+    Ok(VerifierOnlyCircuitData {
+        constants_sigmas_cap: cap_from_value::<C::F, C::Hasher>(get(
+            value,
+            "constants_sigmas_cap",
+        )?)?,
+        circuit_digest: hash_from_value::<C::F, C::Hasher>(get(value, "circuit_digest")?)?,
+    })
+}
+
+pub fn proof_with_pis_to_gnark_value<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+) -> Value {
+    let Proof {
+        wires_cap,
+        // Not supported by the gnark verifier, see `common_data_to_gnark_value`.
+        challenge_dependent_cap: _,
+        plonk_zs_partial_products_cap,
+        quotient_polys_cap,
+        openings,
+        opening_proof,
+    } = &proof_with_pis.proof;
+    json!({
+        "proof": {
+            "wires_cap": cap_to_value(wires_cap),
+            "plonk_zs_partial_products_cap": cap_to_value(plonk_zs_partial_products_cap),
+            "quotient_polys_cap": cap_to_value(quotient_polys_cap),
+            "openings": openings_to_value(openings),
+            "opening_proof": fri_proof_to_value(opening_proof),
+        },
+        "public_inputs": fields_to_value(&proof_with_pis.public_inputs),
+    })
+}
+
+pub fn verifier_only_to_gnark_value<C: GenericConfig<D>, const D: usize>(
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+) -> Value {
+    json!({
+        "constants_sigmas_cap": cap_to_value(&verifier_data.constants_sigmas_cap),
+        "circuit_digest": hash_to_value::<C::F, C::Hasher>(&verifier_data.circuit_digest),
+    })
+}
+
+/// The subset of the common circuit data needed by gnark verifiers. Gates are identified by their
+/// [`id`](crate::gates::gate::Gate::id).
+///
+/// Returns an error if the circuit uses a feature gnark verifiers do not support: challenge-dependent
+/// columns, next-row wires, lookups, a committed final polynomial, or a proof-of-work strategy
+/// other than [`FriPowStrategy::Single`].
+pub fn common_data_to_gnark_value<F: RichField + Extendable<D>, const D: usize>(
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<Value> {
+    let config = &common_data.config;
+    let fri_params = &common_data.fri_params;
+    let selectors_info = &common_data.selectors_info;
+    ensure!(
+        !common_data.has_challenge_dependent_columns(),
+        "The gnark verifier does not support challenge-dependent columns"
+    );
+    ensure!(
+        common_data.num_next_row_wires() == 0,
+        "The gnark verifier does not support next-row wires"
+    );
+    ensure!(
+        common_data.num_lookup_polys == 0,
+        "The gnark verifier does not support lookups"
+    );
+    ensure!(
+        !config.fri_config.commit_final_poly,
+        "The gnark verifier does not support a committed final polynomial"
+    );
+    let proof_of_work_bits = match &config.fri_config.pow_strategy {
+        FriPowStrategy::Single(bits) => *bits,
+        strategy => bail!(
+            "The gnark verifier only supports FriPowStrategy::Single, got {:?}",
+            strategy
+        ),
+    };
+    // The gnark verifier reads the FRI config both from the circuit config and from the FRI
+    // parameters.
+    let fri_config = json!({
+        "rate_bits": config.fri_config.rate_bits,
+        "cap_height": config.fri_config.cap_height,
+        "proof_of_work_bits": proof_of_work_bits,
+        "reduction_strategy": config.fri_config.reduction_strategy,
+        "num_query_rounds": config.fri_config.num_query_rounds,
+    });
+    Ok(json!({
+        "config": {
+            "num_wires": config.num_wires,
+            "num_routed_wires": config.num_routed_wires,
+            "num_constants": config.num_constants,
+            "use_base_arithmetic_gate": config.use_base_arithmetic_gate,
+            "security_bits": config.security_bits,
+            "num_challenges": config.num_challenges,
+            "zero_knowledge": config.zero_knowledge,
+            "max_quotient_degree_factor": config.max_quotient_degree_factor,
+            "fri_config": fri_config,
+        },
+        "fri_params": {
+            "config": fri_config,
+            "hiding": fri_params.hiding,
+            "degree_bits": fri_params.degree_bits,
+            "reduction_arity_bits": fri_params.reduction_arity_bits,
+        },
+        "gates": common_data.gates.iter().map(|g| g.0.id()).collect::<Vec<_>>(),
+        "selectors_info": {
+            "selector_indices": selectors_info.selector_indices,
+            "groups": selectors_info
+                .groups
+                .iter()
+                .map(|r| json!({ "start": r.start, "end": r.end }))
+                .collect::<Vec<_>>(),
+        },
+        "quotient_degree_factor": common_data.quotient_degree_factor,
+        "num_gate_constraints": common_data.num_gate_constraints,
+        "num_constants": common_data.num_constants,
+        "num_public_inputs": common_data.num_public_inputs,
+        "k_is": fields_to_value(&common_data.k_is),
+        "num_partial_products": common_data.num_partial_products,
+    }))
+}
+
+pub fn proof_with_pis_from_gnark_value<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    value: &Value,
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    let proof = get(value, "proof")?;
+    Ok(ProofWithPublicInputs {
+        proof: Proof {
+            wires_cap: cap_from_value(get(proof, "wires_cap")?)?,
+            challenge_dependent_cap: MerkleCap(vec![]),
+            plonk_zs_partial_products_cap: cap_from_value(get(
+                proof,
+                "plonk_zs_partial_products_cap",
+            )?)?,
+            quotient_polys_cap: cap_from_value(get(proof, "quotient_polys_cap")?)?,
+            openings: openings_from_value(get(proof, "openings")?)?,
+            opening_proof: fri_proof_from_value(get(proof, "opening_proof")?)?,
+        },
+        public_inputs: fields_from_value(get(value, "public_inputs")?)?,
+    })
+}
+
+fn field_to_value<F: PrimeField64>(x: F) -> Value {
+    Value::String(x.to_canonical_u64().to_string())
+}
+
+fn fields_to_value<F: PrimeField64>(xs: &[F]) -> Value {
+    Value::Array(xs.iter().map(|&x| field_to_value(x)).collect())
+}
+
+fn ext_to_value<F: RichField + Extendable<D>, const D: usize>(x: F::Extension) -> Value {
+    fields_to_value(&x.to_basefield_array())
+}
+
+fn exts_to_value<F: RichField + Extendable<D>, const D: usize>(xs: &[F::Extension]) -> Value {
+    Value::Array(xs.iter().map(|&x| ext_to_value::<F, D>(x)).collect())
+}
+
+fn hash_to_value<F: RichField, H: Hasher<F>>(hash: &H::Hash) -> Value {
+    Value::Array(
+        hash.to_bytes()
+            .chunks(8)
+            .map(|chunk| {
+                let mut word = [0; 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+                Value::String(u64::from_le_bytes(word).to_string())
+            })
+            .collect(),
+    )
+}
+
+fn hashes_to_value<F: RichField, H: Hasher<F>>(hashes: &[H::Hash]) -> Value {
+    Value::Array(hashes.iter().map(hash_to_value::<F, H>).collect())
+}
+
+fn cap_to_value<F: RichField, H: Hasher<F>>(cap: &MerkleCap<F, H>) -> Value {
+    hashes_to_value::<F, H>(&cap.0)
+}
+
+fn merkle_proof_to_value<F: RichField, H: Hasher<F>>(proof: &MerkleProof<F, H>) -> Value {
+    json!({ "siblings": hashes_to_value::<F, H>(&proof.siblings) })
+}
+
+fn openings_to_value<F: RichField + Extendable<D>, const D: usize>(
+    openings: &OpeningSet<F, D>,
+) -> Value {
+    json!({
+        "constants": exts_to_value::<F, D>(&openings.constants),
+        "plonk_sigmas": exts_to_value::<F, D>(&openings.plonk_sigmas),
+        "wires": exts_to_value::<F, D>(&openings.wires),
+        "plonk_zs": exts_to_value::<F, D>(&openings.plonk_zs),
+        "plonk_zs_next": exts_to_value::<F, D>(&openings.plonk_zs_next),
+        "partial_products": exts_to_value::<F, D>(&openings.partial_products),
+        "quotient_polys": exts_to_value::<F, D>(&openings.quotient_polys),
+        "lookup_zs": exts_to_value::<F, D>(&openings.lookup_zs),
+        "lookup_zs_next": exts_to_value::<F, D>(&openings.lookup_zs_next),
+    })
+}
+
+fn fri_proof_to_value<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+    proof: &FriProof<F, H, D>,
+) -> Value {
+    let query_round_proofs = proof
+        .query_round_proofs
+        .iter()
+        .map(|qrp| {
+            let evals_proofs = qrp
+                .initial_trees_proof
+                .evals_proofs
+                .iter()
+                .map(|(evals, merkle_proof)| {
+                    json!([fields_to_value(evals), merkle_proof_to_value(merkle_proof)])
+                })
+                .collect::<Vec<_>>();
+            let steps = qrp
+                .steps
+                .iter()
+                .map(|step| {
+                    json!({
+                        "evals": exts_to_value::<F, D>(&step.evals),
+                        "merkle_proof": merkle_proof_to_value(&step.merkle_proof),
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "initial_trees_proof": { "evals_proofs": evals_proofs },
+                "steps": steps,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "commit_phase_merkle_caps": proof
+            .commit_phase_merkle_caps
+            .iter()
+            .map(cap_to_value)
+            .collect::<Vec<_>>(),
+        "query_round_proofs": query_round_proofs,
+        "final_poly": { "coeffs": exts_to_value::<F, D>(&proof.final_poly.coeffs) },
+        "pow_witness": field_to_value(proof.pow_witness),
+    })
+}
+
+fn get<'a>(value: &'a Value, key: &str) -> Result<&'a Value> {
+    value
+        .get(key)
+        .ok_or_else(|| anyhow!("Missing field `{}`", key))
+}
+
+fn array(value: &Value) -> Result<&Vec<Value>> {
+    value
+        .as_array()
+        .ok_or_else(|| anyhow!("Expected an array, got {}", value))
+}
+
+fn u64_from_value(value: &Value) -> Result<u64> {
+    let s = value
+        .as_str()
+        .ok_or_else(|| anyhow!("Expected a decimal string, got {}", value))?;
+    s.parse::<u64>()
+        .map_err(|e| anyhow!("Invalid decimal string {}: {}", s, e))
+}
+
+fn field_from_value<F: RichField>(value: &Value) -> Result<F> {
+    let n = u64_from_value(value)?;
+    ensure!(n < F::ORDER, "Field element {} is not canonical", n);
+    Ok(F::from_canonical_u64(n))
+}
+
+fn fields_from_value<F: RichField>(value: &Value) -> Result<Vec<F>> {
+    array(value)?.iter().map(field_from_value).collect()
+}
+
+fn ext_from_value<F: RichField + Extendable<D>, const D: usize>(
+    value: &Value,
+) -> Result<F::Extension> {
+    let coeffs: Vec<F> = fields_from_value(value)?;
+    let coeffs: [F; D] = coeffs
+        .try_into()
+        .map_err(|_| anyhow!("Expected {} extension coefficients", D))?;
+    Ok(F::Extension::from_basefield_array(coeffs))
+}
+
+fn exts_from_value<F: RichField + Extendable<D>, const D: usize>(
+    value: &Value,
+) -> Result<Vec<F::Extension>> {
+    array(value)?.iter().map(ext_from_value::<F, D>).collect()
+}
+
+fn hash_from_value<F: RichField, H: Hasher<F>>(value: &Value) -> Result<H::Hash> {
+    let words = array(value)?;
+    ensure!(
+        words.len() == H::HASH_SIZE.div_ceil(8),
+        "Expected {} words in hash",
+        H::HASH_SIZE.div_ceil(8)
+    );
+    let mut bytes = Vec::with_capacity(8 * words.len());
+    for word in words {
+        bytes.extend_from_slice(&u64_from_value(word)?.to_le_bytes());
+    }
+    ensure!(
+        bytes[H::HASH_SIZE..].iter().all(|&b| b == 0),
+        "Hash has nonzero padding"
+    );
+    bytes.truncate(H::HASH_SIZE);
+    Ok(H::Hash::from_bytes(&bytes))
+}
+
+fn hashes_from_value<F: RichField, H: Hasher<F>>(value: &Value) -> Result<Vec<H::Hash>> {
+    array(value)?.iter().map(hash_from_value::<F, H>).collect()
+}
+
+fn cap_from_value<F: RichField, H: Hasher<F>>(value: &Value) -> Result<MerkleCap<F, H>> {
+    Ok(MerkleCap(hashes_from_value::<F, H>(value)?))
+}
+
+fn merkle_proof_from_value<F: RichField, H: Hasher<F>>(value: &Value) -> Result<MerkleProof<F, H>> {
+    Ok(MerkleProof {
+        siblings: hashes_from_value::<F, H>(get(value, "siblings")?)?.into(),
+    })
+}
+
+fn openings_from_value<F: RichField + Extendable<D>, const D: usize>(
+    value: &Value,
+) -> Result<OpeningSet<F, D>> {
+    let field = |key| exts_from_value::<F, D>(get(value, key)?);
+    Ok(OpeningSet {
+        constants: field("constants")?,
+        plonk_sigmas: field("plonk_sigmas")?,
+        wires: field("wires")?,
+        wires_next: vec![],
+        plonk_zs: field("plonk_zs")?,
+        plonk_zs_next: field("plonk_zs_next")?,
+        partial_products: field("partial_products")?,
+        quotient_polys: field("quotient_polys")?,
+        lookup_zs: field("lookup_zs")?,
+        lookup_zs_next: field("lookup_zs_next")?,
+        challenge_dependent: vec![],
+        challenge_dependent_next: vec![],
+    })
+}
+
+fn fri_proof_from_value<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+    value: &Value,
+) -> Result<FriProof<F, H, D>> {
+    let commit_phase_merkle_caps = array(get(value, "commit_phase_merkle_caps")?)?
+        .iter()
+        .map(cap_from_value)
+        .collect::<Result<Vec<_>>>()?;
+    let query_round_proofs = array(get(value, "query_round_proofs")?)?
+        .iter()
+        .map(|qrp| {
+            let evals_proofs = array(get(get(qrp, "initial_trees_proof")?, "evals_proofs")?)?
+                .iter()
+                .map(|pair| {
+                    let pair = array(pair)?;
+                    ensure!(pair.len() == 2, "Expected an (evals, merkle_proof) pair");
+                    Ok((
+                        fields_from_value(&pair[0])?,
+                        merkle_proof_from_value(&pair[1])?,
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let steps = array(get(qrp, "steps")?)?
+                .iter()
+                .map(|step| {
+                    Ok(FriQueryStep {
+                        evals: exts_from_value::<F, D>(get(step, "evals")?)?,
+                        merkle_proof: merkle_proof_from_value(get(step, "merkle_proof")?)?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(FriQueryRound {
+                initial_trees_proof: FriInitialTreeProof { evals_proofs },
+                steps,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(FriProof {
+        commit_phase_merkle_caps,
+        query_round_proofs,
+        final_poly: PolynomialCoeffs::new(exts_from_value::<F, D>(get(
+            get(value, "final_poly")?,
+            "coeffs",
+        )?)?),
+        pow_witness: field_from_value(get(value, "pow_witness")?)?,
+        round_pow_witnesses: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::gates::noop::NoopGate;
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{
+        KeccakGoldilocksConfig, PoseidonBN254GoldilocksConfig, PoseidonGoldilocksConfig,
+    };
+
+    const D: usize = 2;
+
+    /// A small fixed circuit proving knowledge of `x` such that `x^2 + 1` is public.
+    fn fixture_circuit<C: GenericConfig<D>>() -> (CircuitData<C::F, C, D>, Target) {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<C::F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let x2 = builder.square(x);
+        let y = builder.add_const(x2, C::F::ONE);
+        builder.register_public_input(y);
+        for _ in 0..10 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        (builder.build::<C>(), x)
+    }
+
+    fn roundtrip<C: GenericConfig<D>>() -> Result<()> {
+        let (data, x) = fixture_circuit::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, C::F::from_canonical_u64(5));
+        let proof = data.prove(pw)?;
+
+        let json = to_gnark_json(&proof, &data.verifier_only, &data.common)?;
+        let parsed = from_gnark_json::<C::F, C, D>(&json, &data.common)?;
+        assert_eq!(parsed, proof);
+        let verifier_only = verifier_only_from_gnark_json::<C, D>(&json)?;
+        assert_eq!(verifier_only, data.verifier_only);
+
+        // A bare proof object is accepted too.
+        let bare = proof_with_pis_to_gnark_value(&proof).to_string();
+        assert_eq!(from_gnark_json::<C::F, C, D>(&bare, &data.common)?, proof);
+
+        data.verify(parsed)
+    }
+
+    #[test]
+    fn test_gnark_json_roundtrip() -> Result<()> {
+        roundtrip::<PoseidonGoldilocksConfig>()
+    }
+
+    #[test]
+    fn test_gnark_json_roundtrip_keccak() -> Result<()> {
+        roundtrip::<KeccakGoldilocksConfig>()
+    }
+
+    #[test]
+    fn test_gnark_json_roundtrip_poseidon_bn254() -> Result<()> {
+        roundtrip::<PoseidonBN254GoldilocksConfig>()
+    }
+
+    #[test]
+    fn test_gnark_json_rejects_noncanonical() -> Result<()> {
+        type C = PoseidonGoldilocksConfig;
+        let (data, x) = fixture_circuit::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, <C as GenericConfig<D>>::F::ONE);
+        let proof = data.prove(pw)?;
+        let mut value = proof_with_pis_to_gnark_value(&proof);
+        value["public_inputs"][0] = Value::String(u64::MAX.to_string());
+        assert!(from_gnark_json::<_, C, D>(&value.to_string(), &data.common).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_gnark_json_golden() {
+        type C = PoseidonGoldilocksConfig;
+        let (data, _) = fixture_circuit::<C>();
+        let expected: Value =
+            serde_json::from_str(include_str!("fixtures/gnark_circuit_data.json")).unwrap();
+        let actual = json!({
+            "verifier_only_circuit_data": verifier_only_to_gnark_value(&data.verifier_only),
+            "common_circuit_data": common_data_to_gnark_value(&data.common).unwrap(),
+        });
+        assert_eq!(actual, expected);
+    }
+
+    /// The parts of the gnark verifier's `CommonCircuitDataRaw` which it reads. Go leaves missing
+    /// fields zeroed rather than failing, so we check that each of them is present.
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct GnarkCommonCircuitData {
+        config: GnarkCircuitConfig,
+        fri_params: GnarkFriParams,
+        gates: Vec<String>,
+        selectors_info: GnarkSelectorsInfo,
+        quotient_degree_factor: u64,
+        num_gate_constraints: u64,
+        num_constants: u64,
+        num_public_inputs: u64,
+        k_is: Vec<String>,
+        num_partial_products: u64,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct GnarkCircuitConfig {
+        num_wires: u64,
+        num_routed_wires: u64,
+        num_constants: u64,
+        use_base_arithmetic_gate: bool,
+        security_bits: u64,
+        num_challenges: u64,
+        zero_knowledge: bool,
+        max_quotient_degree_factor: u64,
+        fri_config: GnarkFriConfig,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct GnarkFriConfig {
+        rate_bits: u64,
+        cap_height: u64,
+        proof_of_work_bits: u64,
+        reduction_strategy: GnarkReductionStrategy,
+        num_query_rounds: u64,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct GnarkReductionStrategy {
+        #[serde(rename = "ConstantArityBits")]
+        constant_arity_bits: Vec<u64>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct GnarkFriParams {
+        config: GnarkFriConfig,
+        hiding: bool,
+        degree_bits: u64,
+        reduction_arity_bits: Vec<u64>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct GnarkSelectorsInfo {
+        selector_indices: Vec<u64>,
+        groups: Vec<GnarkRange>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct GnarkRange {
+        start: u64,
+        end: u64,
+    }
+
+    #[test]
+    fn test_gnark_json_common_data_schema() {
+        let fixture: Value =
+            serde_json::from_str(include_str!("fixtures/gnark_circuit_data.json")).unwrap();
+        let (data, _) = fixture_circuit::<PoseidonGoldilocksConfig>();
+        for value in [
+            fixture["common_circuit_data"].clone(),
+            common_data_to_gnark_value(&data.common).unwrap(),
+        ] {
+            serde_json::from_value::<GnarkCommonCircuitData>(value).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_gnark_json_unsupported() {
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let common_data = |config: CircuitConfig| {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let x = builder.add_virtual_target();
+            builder.register_public_input(x);
+            builder.build::<C>().common
+        };
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.pow_strategy = FriPowStrategy::None;
+        config.security_bits = 80;
+        assert!(common_data_to_gnark_value(&common_data(config)).is_err());
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.pow_strategy = FriPowStrategy::PerRound(vec![8]);
+        config.security_bits = 80;
+        assert!(common_data_to_gnark_value(&common_data(config)).is_err());
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.commit_final_poly = true;
+        assert!(common_data_to_gnark_value(&common_data(config)).is_err());
+
+        let config = CircuitConfig::standard_recursion_config();
+        assert!(common_data_to_gnark_value(&common_data(config)).is_ok());
+    }
+}
@@ -0,0 +1,3 @@
+//! Conversions between plonky2 data structures and the formats expected by external verifiers.
+
+pub mod gnark;
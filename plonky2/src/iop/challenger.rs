@@ -1,8 +1,11 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::marker::PhantomData;
 
 use crate::field::extension::{Extendable, FieldExtension};
+use crate::fri::query_index::{PowerOfTwoQueryIndexSampler, QueryIndexSampler};
 use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::hashing::PlonkyPermutation;
 use crate::hash::merkle_tree::MerkleCap;
@@ -11,12 +14,142 @@ use crate::iop::target::Target;
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{AlgebraicHasher, GenericHashOut, Hasher};
 
+/// A Fiat-Shamir transcript, which absorbs labeled prover messages and derives labeled challenges
+/// from them.
+///
+/// [`Challenger`], plonky2's duplex sponge, is the default transcript. Another construction, such
+/// as a Merlin-like transcript with length-prefixed labels, can be selected with
+/// `GenericConfig::Transcript`: the prover and the native verifier then draw every challenge from
+/// it. The recursive verifier only implements the sponge, and rejects other transcripts at build
+/// time, so that this doesn't compile:
+///
+/// ```compile_fail
+/// use plonky2::field::extension::quadratic::QuadraticExtension;
+/// use plonky2::field::goldilocks_field::GoldilocksField;
+/// use plonky2::hash::poseidon::PoseidonHash;
+/// use plonky2::iop::challenger::{Challenger, Transcript};
+/// use plonky2::plonk::circuit_builder::CircuitBuilder;
+/// use plonky2::plonk::circuit_data::CircuitConfig;
+/// use plonky2::plonk::config::GenericConfig;
+///
+/// type F = GoldilocksField;
+///
+/// #[derive(Clone, Default)]
+/// struct Labeled(Challenger<F, PoseidonHash>);
+///
+/// impl Transcript<F> for Labeled {
+///     fn absorb_label_elements(&mut self, _label: &[u8], elements: &[F]) {
+///         self.0.observe_elements(elements);
+///     }
+///
+///     fn challenge_elements(&mut self, _label: &[u8], n: usize) -> Vec<F> {
+///         self.0.get_n_challenges(n)
+///     }
+/// }
+///
+/// #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// struct LabeledConfig;
+///
+/// impl GenericConfig<2> for LabeledConfig {
+///     type F = F;
+///     type FE = QuadraticExtension<F>;
+///     type Hasher = PoseidonHash;
+///     type InnerHasher = PoseidonHash;
+///     type Transcript = Labeled;
+/// }
+///
+/// let config = CircuitConfig::standard_recursion_config();
+/// let inner = CircuitBuilder::<F, 2>::new(config.clone()).build::<LabeledConfig>();
+/// let mut builder = CircuitBuilder::<F, 2>::new(config);
+/// let proof = builder.add_virtual_proof_with_pis(&inner.common);
+/// let verifier_data = builder.constant_verifier_data(&inner.verifier_only);
+/// builder.verify_proof::<LabeledConfig>(&proof, &verifier_data, &inner.common);
+/// ```
+pub trait Transcript<F: RichField>: 'static + Clone + Default + Send + Sync {
+    /// Set only for plonky2's sponge, i.e. a [`Challenger`], which the recursive verifier can
+    /// replay in circuit. A [`SpongeMarker`] cannot be constructed outside this crate, so no other
+    /// transcript can claim to be the sponge.
+    const SPONGE: Option<SpongeMarker> = None;
+
+    /// Absorbs `elements`, a prover message, under `label`.
+    fn absorb_label_elements(&mut self, label: &[u8], elements: &[F]);
+
+    /// Derives `n` challenges under `label` from everything absorbed so far.
+    fn challenge_elements(&mut self, label: &[u8], n: usize) -> Vec<F>;
+}
+
+/// Marks plonky2's sponge as such, see [`Transcript::SPONGE`].
+#[derive(Copy, Clone, Debug)]
+pub struct SpongeMarker(());
+
+/// The sponge ignores labels, so that it derives the same challenges as `observe_elements` and
+/// `get_n_challenges`, and its proofs are unchanged.
+impl<F: RichField, H: Hasher<F> + 'static> Transcript<F> for Challenger<F, H> {
+    const SPONGE: Option<SpongeMarker> = Some(SpongeMarker(()));
+
+    fn absorb_label_elements(&mut self, _label: &[u8], elements: &[F]) {
+        self.observe_elements(elements);
+    }
+
+    fn challenge_elements(&mut self, _label: &[u8], n: usize) -> Vec<F> {
+        self.get_n_challenges(n)
+    }
+}
+
+/// The label under which a [`Challenger`] with an external transcript absorbs the messages observed
+/// since its last challenge, unless they were labeled with `Challenger::label`.
+pub const MESSAGES_LABEL: &[u8] = b"plonky2-messages";
+
+/// The label under which a [`Challenger`] with an external transcript derives each challenge,
+/// unless it was labeled with `Challenger::label`.
+pub const CHALLENGE_LABEL: &[u8] = b"plonky2-challenge";
+
+/// A [`Transcript`] behind a `Box`, so that a [`Challenger`] can hold and clone it.
+trait BoxedTranscript<F: RichField>: Send + Sync {
+    fn absorb_label_elements(&mut self, label: &[u8], elements: &[F]);
+
+    fn challenge_elements(&mut self, label: &[u8], n: usize) -> Vec<F>;
+
+    fn clone_box(&self) -> Box<dyn BoxedTranscript<F>>;
+}
+
+impl<F: RichField, T: Transcript<F>> BoxedTranscript<F> for T {
+    fn absorb_label_elements(&mut self, label: &[u8], elements: &[F]) {
+        Transcript::absorb_label_elements(self, label, elements);
+    }
+
+    fn challenge_elements(&mut self, label: &[u8], n: usize) -> Vec<F> {
+        Transcript::challenge_elements(self, label, n)
+    }
+
+    fn clone_box(&self) -> Box<dyn BoxedTranscript<F>> {
+        Box::new(self.clone())
+    }
+}
+
+impl<F: RichField> Clone for Box<dyn BoxedTranscript<F>> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
 /// Observes prover messages, and generates challenges by hashing the transcript, a la Fiat-Shamir.
 #[derive(Clone)]
 pub struct Challenger<F: RichField, H: Hasher<F>> {
     pub(crate) sponge_state: H::Permutation,
     pub(crate) input_buffer: Vec<F>,
     output_buffer: Vec<F>,
+    num_observed: usize,
+    /// External data, such as a block number, which is observed right before the final FRI
+    /// proof-of-work, binding the grinding to it.
+    pub(crate) pow_context: Vec<F>,
+    /// Derives the FRI query indices, if not the default `PowerOfTwoQueryIndexSampler`.
+    query_index_sampler: Option<Arc<dyn QueryIndexSampler<F, H>>>,
+    /// The transcript drawing the challenges in place of the sponge, if any, see
+    /// `with_transcript`.
+    external: Option<Box<dyn BoxedTranscript<F>>>,
+    /// The label of the messages and challenges since the last call to `label`, if any.
+    label: Option<&'static [u8]>,
 }
 
 /// Observes prover messages, and generates verifier challenges based on the transcript.
@@ -33,16 +166,117 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
             sponge_state: H::Permutation::new(core::iter::repeat(F::ZERO)),
             input_buffer: Vec::with_capacity(H::Permutation::RATE),
             output_buffer: Vec::with_capacity(H::Permutation::RATE),
+            num_observed: 0,
+            pow_context: Vec::new(),
+            query_index_sampler: None,
+            external: None,
+            label: None,
+        }
+    }
+
+    /// Creates a challenger drawing its challenges from a fresh transcript `T`, typically a
+    /// config's `GenericConfig::Transcript`. If `T` is the sponge, this is `new`.
+    ///
+    /// Otherwise, the observed elements are buffered, and absorbed under their label, see
+    /// `label`, right before the next label or challenge. Challenges are derived one at a time,
+    /// so that they don't depend on how they are grouped into requests.
+    pub fn with_transcript<T: Transcript<F>>() -> Challenger<F, H> {
+        let mut challenger = Self::new();
+        if T::SPONGE.is_none() {
+            challenger.external = Some(Box::<T>::default());
+        }
+        challenger
+    }
+
+    /// Whether the challenges are drawn from the sponge rather than an external transcript.
+    pub fn is_sponge(&self) -> bool {
+        self.external.is_none()
+    }
+
+    /// Names the prover messages observed and the challenges derived from now on, until the next
+    /// call, e.g. `b"wires_cap"` or `b"plonk_betas"`. An external transcript absorbs each message
+    /// and derives each challenge under its label, so the prover and the verifier label the same
+    /// steps; before the first call, [`MESSAGES_LABEL`] and [`CHALLENGE_LABEL`] are used. The
+    /// sponge ignores labels.
+    pub fn label(&mut self, label: &'static [u8]) {
+        if self.external.is_some() {
+            self.absorb_external_messages();
+            self.label = Some(label);
         }
     }
 
+    /// Absorbs the elements observed since the last label or challenge into the external
+    /// transcript.
+    fn absorb_external_messages(&mut self) {
+        if let Some(external) = &mut self.external {
+            if !self.input_buffer.is_empty() {
+                external.absorb_label_elements(
+                    self.label.unwrap_or(MESSAGES_LABEL),
+                    &self.input_buffer,
+                );
+                self.input_buffer.clear();
+            }
+        }
+    }
+
+    /// Sets the context to observe before the final FRI proof-of-work. A proof generated with a
+    /// given context only verifies under that same context. An empty context, the default, leaves
+    /// the transcript unchanged.
+    pub fn set_pow_context(&mut self, pow_context: Vec<F>) {
+        self.pow_context = pow_context;
+    }
+
+    /// Sets the sampler deriving the FRI query indices. A proof generated with a given sampler only
+    /// verifies under that same sampler.
+    pub fn set_query_index_sampler(&mut self, sampler: Arc<dyn QueryIndexSampler<F, H>>) {
+        self.query_index_sampler = Some(sampler);
+    }
+
+    /// Samples `count` FRI query indices in `0..lde_size`, with the configured sampler.
+    pub fn sample_query_indices(&mut self, lde_size: usize, count: usize) -> Vec<usize> {
+        let indices = match self.query_index_sampler.clone() {
+            Some(sampler) => sampler.sample(self, lde_size, count),
+            None => PowerOfTwoQueryIndexSampler.sample(self, lde_size, count),
+        };
+        assert_eq!(
+            indices.len(),
+            count,
+            "Sampled the wrong number of query indices"
+        );
+        assert!(
+            indices.iter().all(|&i| i < lde_size),
+            "Sampled a query index outside the LDE"
+        );
+        indices
+    }
+
+    /// Returns a copy of this challenger which has observed `label`, for deriving the challenges
+    /// of a sub-protocol. Forks with different labels produce independent challenges, and `self`
+    /// is left unchanged.
+    ///
+    /// The label is preceded by its length, so no label is a prefix of another. A fork is
+    /// otherwise identical to observing the same elements, so labels should not double as
+    /// transcript messages.
+    pub fn fork(&self, label: &[F]) -> Challenger<F, H> {
+        let mut fork = self.clone();
+        fork.observe_element(F::from_canonical_usize(label.len()));
+        fork.observe_elements(label);
+        fork
+    }
+
+    /// The number of field elements observed so far.
+    pub fn num_observations(&self) -> usize {
+        self.num_observed
+    }
+
     pub fn observe_element(&mut self, element: F) {
         // Any buffered outputs are now invalid, since they wouldn't reflect this input.
         self.output_buffer.clear();
 
         self.input_buffer.push(element);
+        self.num_observed += 1;
 
-        if self.input_buffer.len() == H::Permutation::RATE {
+        if self.external.is_none() && self.input_buffer.len() == H::Permutation::RATE {
             self.duplexing();
         }
     }
@@ -80,6 +314,12 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
     }
 
     pub fn get_challenge(&mut self) -> F {
+        if self.external.is_some() {
+            self.absorb_external_messages();
+            let label = self.label.unwrap_or(CHALLENGE_LABEL);
+            return self.external.as_mut().unwrap().challenge_elements(label, 1)[0];
+        }
+
         // If we have buffered inputs, we must perform a duplexing so that the challenge will
         // reflect them. Or if we've run out of outputs, we must perform a duplexing to get more.
         if !self.input_buffer.is_empty() || self.output_buffer.is_empty() {
@@ -106,6 +346,15 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
         }
     }
 
+    /// Samples an extension field challenge as exactly `D` base field challenges, the `i`th of
+    /// which is the coefficient of `X^i` in the extension's basis, as in
+    /// `FieldExtension::from_basefield_array`. As `get_challenge` takes the sponge's outputs from
+    /// the end of the rate, after a fresh permutation coefficient `i` is the element at index
+    /// `RATE - 1 - i` of the state. `RecursiveChallenger::get_extension_challenge` samples the same
+    /// elements in the same order.
+    ///
+    /// Each coefficient is a full field element, so the challenge has the entropy of the extension
+    /// field, about 128 bits for the quadratic extension of Goldilocks.
     pub fn get_extension_challenge<const D: usize>(&mut self) -> F::Extension
     where
         F: RichField + Extendable<D>,
@@ -143,7 +392,26 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
             .extend_from_slice(self.sponge_state.squeeze());
     }
 
+    /// A snapshot of the transcript state, for debugging: the sponge state, in canonical form,
+    /// followed by any inputs observed since the last permutation.
+    ///
+    /// Two challengers which have observed the same messages have the same snapshot, so comparing
+    /// snapshots can locate the first observation where two transcripts diverge. With an external
+    /// transcript, whose state is opaque, only the buffered inputs are meaningful.
+    pub fn current_state(&self) -> Vec<F> {
+        self.sponge_state
+            .as_ref()
+            .iter()
+            .chain(&self.input_buffer)
+            .map(|x| F::from_canonical_u64(x.to_canonical_u64()))
+            .collect()
+    }
+
     pub fn compact(&mut self) -> H::Permutation {
+        assert!(
+            self.is_sponge(),
+            "An external transcript has no sponge state"
+        );
         if !self.input_buffer.is_empty() {
             self.duplexing();
         }
@@ -152,7 +420,7 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
     }
 }
 
-impl<F: RichField, H: AlgebraicHasher<F>> Default for Challenger<F, H> {
+impl<F: RichField, H: Hasher<F>> Default for Challenger<F, H> {
     fn default() -> Self {
         Self::new()
     }
@@ -169,6 +437,19 @@ pub struct RecursiveChallenger<F: RichField + Extendable<D>, H: AlgebraicHasher<
     __: PhantomData<(F, H)>,
 }
 
+impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize> Clone
+    for RecursiveChallenger<F, H, D>
+{
+    fn clone(&self) -> Self {
+        Self {
+            sponge_state: self.sponge_state,
+            input_buffer: self.input_buffer.clone(),
+            output_buffer: self.output_buffer.clone(),
+            __: PhantomData,
+        }
+    }
+}
+
 impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
     RecursiveChallenger<F, H, D>
 {
@@ -257,6 +538,8 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         }
     }
 
+    /// Samples an extension field challenge as exactly `D` base field challenges, in the order of
+    /// `Challenger::get_extension_challenge`.
     pub fn get_extension_challenge(
         &mut self,
         builder: &mut CircuitBuilder<F, D>,
@@ -296,14 +579,22 @@ mod tests {
     #[cfg(not(feature = "std"))]
     use alloc::vec::Vec;
 
-    use crate::field::types::Sample;
-    use crate::iop::challenger::{Challenger, RecursiveChallenger};
+    use anyhow::Result;
+
+    use crate::field::extension::quadratic::QuadraticExtension;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, Sample};
+    use crate::fri::FriPowStrategy;
+    use crate::hash::hashing::PlonkyPermutation;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::challenger::{Challenger, RecursiveChallenger, Transcript};
     use crate::iop::generator::generate_partial_witness;
     use crate::iop::target::Target;
-    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::iop::witness::{PartialWitness, Witness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
     use crate::plonk::circuit_data::CircuitConfig;
-    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::ProofWithPublicInputs;
 
     #[test]
     fn no_duplicate_challenges() {
@@ -326,6 +617,210 @@ mod tests {
         assert_eq!(dedup_challenges, challenges);
     }
 
+    #[test]
+    fn test_fork() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+        let label =
+            |bytes: &[u8]| -> Vec<F> { bytes.iter().map(|&b| F::from_canonical_u8(b)).collect() };
+
+        let mut main = Challenger::<F, H>::new();
+        main.observe_elements(&F::rand_vec(3));
+        let state = main.current_state();
+
+        let mut fri = main.fork(&label(b"fri"));
+        let mut lookup = main.fork(&label(b"lookup"));
+        assert_eq!(main.current_state(), state);
+        assert_ne!(fri.get_n_challenges(4), lookup.get_n_challenges(4));
+
+        // Forking is deterministic, and a fork is unaffected by the main transcript's later use.
+        let expected = main.fork(&label(b"fri")).get_n_challenges(4);
+        let mut fri = main.fork(&label(b"fri"));
+        main.observe_elements(&F::rand_vec(3));
+        assert_ne!(main.get_n_challenges(4), expected);
+        assert_eq!(fri.get_n_challenges(4), expected);
+
+        // A label and its zero-padded extension are distinct.
+        let mut padded = label(b"fri");
+        padded.push(F::ZERO);
+        assert_ne!(
+            main.fork(&padded).get_n_challenges(4),
+            main.fork(&label(b"fri")).get_n_challenges(4)
+        );
+    }
+
+    #[test]
+    fn test_current_state() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+        let width = <H as Hasher<F>>::Permutation::WIDTH;
+
+        let mut challenger = Challenger::<F, H>::new();
+        let initial = challenger.current_state();
+        assert_eq!(initial.len(), width);
+        assert_eq!(challenger.current_state(), initial);
+
+        // A buffered input shows up before any permutation is applied.
+        let x = F::rand();
+        challenger.observe_element(x);
+        let buffered = challenger.current_state();
+        assert_eq!(buffered[..width], initial[..]);
+        assert_eq!(buffered[width..], [x]);
+        assert_eq!(challenger.current_state(), buffered);
+
+        challenger.get_challenge();
+        let permuted = challenger.current_state();
+        assert_eq!(permuted.len(), width);
+        assert_ne!(permuted, initial);
+        assert_eq!(challenger.current_state(), permuted);
+
+        let mut other = Challenger::<F, H>::new();
+        other.observe_element(x + F::ONE);
+        other.get_challenge();
+        assert_ne!(other.current_state(), permuted);
+    }
+
+    /// A toy Merlin-like transcript over the Poseidon sponge, which absorbs each label and each
+    /// message prefixed by its length.
+    #[derive(Clone, Default)]
+    struct LabeledTranscript(Challenger<GoldilocksField, PoseidonHash>);
+
+    impl LabeledTranscript {
+        fn absorb_label(&mut self, label: &[u8]) {
+            self.0
+                .observe_element(GoldilocksField::from_canonical_usize(label.len()));
+            for &b in label {
+                self.0
+                    .observe_element(GoldilocksField::from_canonical_u8(b));
+            }
+        }
+    }
+
+    impl Transcript<GoldilocksField> for LabeledTranscript {
+        fn absorb_label_elements(&mut self, label: &[u8], elements: &[GoldilocksField]) {
+            self.absorb_label(label);
+            self.0
+                .observe_element(GoldilocksField::from_canonical_usize(elements.len()));
+            self.0.observe_elements(elements);
+        }
+
+        fn challenge_elements(&mut self, label: &[u8], n: usize) -> Vec<GoldilocksField> {
+            self.absorb_label(label);
+            self.0
+                .observe_element(GoldilocksField::from_canonical_usize(n));
+            self.0.get_n_challenges(n)
+        }
+    }
+
+    /// `PoseidonGoldilocksConfig`, with its challenges drawn from `LabeledTranscript`.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    struct LabeledConfig;
+
+    impl GenericConfig<2> for LabeledConfig {
+        type F = GoldilocksField;
+        type FE = QuadraticExtension<Self::F>;
+        type Hasher = PoseidonHash;
+        type InnerHasher = PoseidonHash;
+        type Transcript = LabeledTranscript;
+    }
+
+    #[test]
+    fn test_external_transcript() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        assert!(Challenger::<F, PoseidonHash>::with_transcript::<
+            <C as GenericConfig<D>>::Transcript,
+        >()
+        .is_sponge());
+        assert!(!Challenger::<F, PoseidonHash>::with_transcript::<LabeledTranscript>().is_sponge());
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        // Grinding tries each candidate on a clone of an external transcript, so keep it short.
+        config.fri_config.pow_strategy = FriPowStrategy::Single(8);
+        config.security_bits -= 8;
+        let build = || {
+            let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+            let x = builder.add_virtual_public_input();
+            let y = builder.mul(x, x);
+            builder.register_public_input(y);
+            (builder, x)
+        };
+        let x_value = F::rand();
+
+        let (builder, x) = build();
+        let sponge_data = builder.build::<C>();
+        let mut sponge_pw = PartialWitness::new();
+        sponge_pw.set_target(x, x_value);
+
+        let (builder, x) = build();
+        let labeled_data = builder.build::<LabeledConfig>();
+        let mut labeled_pw = PartialWitness::new();
+        labeled_pw.set_target(x, x_value);
+
+        let sponge_proof = sponge_data.prove(sponge_pw)?;
+        sponge_data.verify(sponge_proof.clone())?;
+        let labeled_proof = labeled_data.prove(labeled_pw)?;
+        labeled_data.verify(labeled_proof.clone())?;
+
+        // The circuits are identical, so a proof only fails under the other config because its
+        // challenges differ.
+        let common = &sponge_data.common;
+        assert_eq!(
+            sponge_data.verifier_only.circuit_digest,
+            labeled_data.verifier_only.circuit_digest
+        );
+        let as_sponge =
+            ProofWithPublicInputs::<F, C, D>::from_bytes(labeled_proof.to_bytes(), common)?;
+        assert!(sponge_data.verify(as_sponge).is_err());
+        let as_labeled = ProofWithPublicInputs::<F, LabeledConfig, D>::from_bytes(
+            sponge_proof.to_bytes(),
+            common,
+        )?;
+        assert!(labeled_data.verify(as_labeled).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_labels() {
+        type F = GoldilocksField;
+
+        let challenges = |mut challenger: Challenger<F, PoseidonHash>,
+                          labels: [&'static [u8]; 2]| {
+            challenger.label(labels[0]);
+            challenger.observe_elements(&[F::ONE, F::TWO]);
+            challenger.label(labels[1]);
+            challenger.get_n_challenges(2)
+        };
+        let sponge = Challenger::<F, PoseidonHash>::new;
+        let labeled = Challenger::<F, PoseidonHash>::with_transcript::<LabeledTranscript>;
+
+        // The sponge ignores labels, while an external transcript derives challenges under them.
+        assert_eq!(
+            challenges(sponge(), [b"wires_cap", b"plonk_betas"]),
+            challenges(sponge(), [b"quotient_polys_cap", b"plonk_zeta"])
+        );
+        let expected = challenges(labeled(), [b"wires_cap", b"plonk_betas"]);
+        assert_eq!(
+            challenges(labeled(), [b"wires_cap", b"plonk_betas"]),
+            expected
+        );
+        assert_ne!(
+            challenges(labeled(), [b"quotient_polys_cap", b"plonk_betas"]),
+            expected
+        );
+        assert_ne!(
+            challenges(labeled(), [b"wires_cap", b"plonk_zeta"]),
+            expected
+        );
+    }
+
     /// Tests for consistency between `Challenger` and `RecursiveChallenger`.
     #[test]
     fn test_consistency() {
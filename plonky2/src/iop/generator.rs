@@ -1,23 +1,35 @@
 use alloc::boxed::Box;
 use alloc::string::{String, ToString};
-use alloc::vec;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
-use core::fmt::Debug;
+use alloc::{format, vec};
+use core::fmt::{Debug, Write as _};
 use core::marker::PhantomData;
+use core::time::Duration;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "timing")]
+use web_time::Instant;
+use zeroize::Zeroize;
 
 use crate::field::extension::Extendable;
 use crate::field::types::Field;
 use crate::hash::hash_types::RichField;
+use crate::hash::hashing::expand_seed;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::iop::wire::Wire;
 use crate::iop::witness::{PartialWitness, PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
 use crate::plonk::config::GenericConfig;
-use crate::util::serialization::{Buffer, IoResult, Read, Write};
+use crate::util::sensitive::{wipe_vec, SensitiveBuffers, SensitiveData};
+use crate::util::serialization::{Buffer, IoError, IoResult, Read, Write};
 
 /// Given a `PartitionWitness` that has only inputs set, populates the rest of the witness using the
 /// given set of generators.
+///
+/// Panics if generation fails, see [`try_generate_partial_witness`].
 pub fn generate_partial_witness<
     'a,
     F: RichField + Extendable<D>,
@@ -28,29 +40,133 @@ pub fn generate_partial_witness<
     prover_data: &'a ProverOnlyCircuitData<F, C, D>,
     common_data: &'a CommonCircuitData<F, D>,
 ) -> PartitionWitness<'a, F> {
+    try_generate_partial_witness(inputs, prover_data, common_data, None, None)
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`generate_partial_witness`], but returns an error rather than panicking.
+///
+/// Generation fails if it stops making progress while some generators haven't finished, in which
+/// case the error lists them along with the targets they are still waiting on. It also fails if a
+/// generator finishes without setting one of its declared
+/// [`outputs`](WitnessGenerator::outputs), or, with the `timing` feature, once it has run for
+/// longer than `timeout`. The timeout is checked between generator runs, so a generator which
+/// never returns from `run` still hangs generation. If `random_seed` is set, the random values used
+/// for blinding are derived from it, see `ProverOptions::salt_rng_seed`. The `inputs` are wiped
+/// once they have been copied into the partition witness, which is cheap since they are few.
+pub fn try_generate_partial_witness<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    mut inputs: PartialWitness<F>,
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    common_data: &'a CommonCircuitData<F, D>,
+    timeout: Option<Duration>,
+    random_seed: Option<u64>,
+) -> Result<PartitionWitness<'a, F>> {
     let config = &common_data.config;
     let generators = &prover_data.generators;
-    let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
 
     let mut witness = PartitionWitness::new(
         config.num_wires,
         common_data.degree(),
         &prover_data.representative_map,
     );
+    witness.random_seed = random_seed;
 
-    for (t, v) in inputs.target_values.into_iter() {
+    for (&t, &v) in &inputs.target_values {
         witness.set_target(t, v);
     }
+    inputs.zeroize();
 
-    // Build a list of "pending" generators which are queued to be run. Initially, all generators
-    // are queued.
-    let mut pending_generator_indices: Vec<_> = (0..generators.len()).collect();
-
-    // We also track a list of "expired" generators which have already returned false.
+    // We track a list of "expired" generators which have already returned false.
     let mut generator_is_expired = vec![false; generators.len()];
-    let mut remaining_generators = generators.len();
+    run_generators(
+        &mut witness,
+        prover_data,
+        |_| true,
+        &mut generator_is_expired,
+        |_, _, _| {},
+        timeout,
+    )?;
+    ensure_all_generators_ran(&witness, prover_data, &generator_is_expired)?;
+
+    Ok(witness)
+}
+
+/// The maximum number of pending generators listed by [`ensure_all_generators_ran`].
+const MAX_REPORTED_GENERATORS: usize = 10;
+
+/// Fails if any generator hasn't expired, listing the first few of them along with the targets of
+/// their watch lists which are still unset.
+fn ensure_all_generators_ran<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    witness: &PartitionWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    generator_is_expired: &[bool],
+) -> Result<()> {
+    let pending: Vec<_> = (0..generator_is_expired.len())
+        .filter(|&i| !generator_is_expired[i])
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let mut report = format!("{} generators weren't run:", pending.len());
+    for &i in pending.iter().take(MAX_REPORTED_GENERATORS) {
+        let generator = &prover_data.generators[i].0;
+        let unset: Vec<_> = generator
+            .watch_list()
+            .into_iter()
+            .filter(|&t| !witness.contains(t))
+            .collect();
+        let _ = write!(report, "\n  `{}` waiting on {:?}", generator.id(), unset);
+    }
+    if pending.len() > MAX_REPORTED_GENERATORS {
+        let _ = write!(
+            report,
+            "\n  and {} more",
+            pending.len() - MAX_REPORTED_GENERATORS
+        );
+    }
+    bail!(report)
+}
+
+/// Runs the selected generators which haven't expired until they fail to make progress, marking
+/// those which finish as expired. `on_set` is called with the index of the generator, the target and
+/// the value of each partition of `witness` which a generator sets for the first time.
+///
+/// Fails if a generator finishes without setting its declared outputs, or if `timeout` elapses
+/// (only enforced with the `timing` feature).
+fn run_generators<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    witness: &mut PartitionWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    is_selected: impl Fn(usize) -> bool,
+    generator_is_expired: &mut [bool],
+    mut on_set: impl FnMut(usize, Target, F),
+    timeout: Option<Duration>,
+) -> Result<()> {
+    #[cfg(feature = "timing")]
+    let start = Instant::now();
+    #[cfg(not(feature = "timing"))]
+    let _ = timeout;
+
+    let generators = &prover_data.generators;
+    let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
 
-    let mut buffer = GeneratedValues::empty();
+    // Build a list of "pending" generators which are queued to be run. Initially, all selected
+    // generators are queued.
+    let mut pending_generator_indices: Vec<_> = (0..generators.len())
+        .filter(|&i| is_selected(i) && !generator_is_expired[i])
+        .collect();
+
+    // Values drained from the buffer linger in its spare capacity, so it is wiped when dropped.
+    let mut buffer = SensitiveBuffers::new(GeneratedValues::empty(), true);
 
     // Keep running generators until we fail to make progress.
     while !pending_generator_indices.is_empty() {
@@ -61,42 +177,219 @@ pub fn generate_partial_witness<
                 continue;
             }
 
-            let finished = generators[generator_idx].0.run(&witness, &mut buffer);
+            let generator = &generators[generator_idx].0;
+            #[cfg(feature = "timing")]
+            if let Some(timeout) = timeout {
+                ensure!(
+                    start.elapsed() <= timeout,
+                    "Witness generation timed out after {:?}, before running generator `{}`",
+                    timeout,
+                    generator.id()
+                );
+            }
+
+            let finished = generator.run(witness, &mut buffer);
             if finished {
                 generator_is_expired[generator_idx] = true;
-                remaining_generators -= 1;
             }
 
-            // Merge any generated values into our witness, and get a list of newly-populated
-            // targets' representatives.
-            let new_target_reps = buffer
-                .target_values
-                .drain(..)
-                .flat_map(|(t, v)| witness.set_target_returning_rep(t, v));
-
-            // Enqueue unfinished generators that were watching one of the newly populated targets.
-            for watch in new_target_reps {
-                let opt_watchers = generator_indices_by_watches.get(&watch);
-                if let Some(watchers) = opt_watchers {
+            // Merge any generated values into our witness, and enqueue unfinished generators that
+            // were watching one of the newly populated targets' representatives.
+            for (t, v) in buffer.target_values.drain(..) {
+                let Some(watch) = witness.set_target_returning_rep(t, v) else {
+                    continue;
+                };
+                on_set(generator_idx, t, v);
+                if let Some(watchers) = generator_indices_by_watches.get(&watch) {
                     for &watching_generator_idx in watchers {
-                        if !generator_is_expired[watching_generator_idx] {
+                        if is_selected(watching_generator_idx)
+                            && !generator_is_expired[watching_generator_idx]
+                        {
                             next_pending_generator_indices.push(watching_generator_idx);
                         }
                     }
                 }
             }
+
+            if finished {
+                for t in generator.outputs() {
+                    ensure!(
+                        witness.contains(t),
+                        "Generator `{}` finished without setting its output {:?}",
+                        generator.id(),
+                        t
+                    );
+                }
+            }
         }
 
         pending_generator_indices = next_pending_generator_indices;
     }
 
-    assert_eq!(
-        remaining_generators, 0,
-        "{} generators weren't run",
-        remaining_generators,
+    Ok(())
+}
+
+/// The witness values generated by the generators of one group, see
+/// [`generate_partial_witness_for_group`]. Shards of a circuit are combined with
+/// [`merge_witness_shards`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PartialWitnessShard<F: Field> {
+    /// The label of the group.
+    pub group: String,
+    /// The inputs and the values generated by the group, one target for each partition of
+    /// copy-constrained targets which was set.
+    pub target_values: Vec<(Target, F)>,
+    /// The indices of the generators of the group which ran to completion.
+    pub completed_generators: Vec<usize>,
+}
+
+/// Runs the generators of the group with the given label on `inputs`, along with the generators
+/// outside of any group which they depend on. Values generated by other groups are not available,
+/// so the group must only depend on `inputs` and on generators outside of any group, which must
+/// then be deterministic.
+///
+/// Only the values generated by the group itself are kept in the shard. Generators outside of any
+/// group run again in [`merge_witness_shards`].
+pub fn generate_partial_witness_for_group<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    group: &str,
+    inputs: PartialWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<PartialWitnessShard<F>> {
+    let group_index = prover_data
+        .generator_group_index(group)
+        .ok_or_else(|| anyhow!("The circuit has no generator group `{}`", group))?;
+
+    let mut witness = PartitionWitness::new(
+        common_data.config.num_wires,
+        common_data.degree(),
+        &prover_data.representative_map,
     );
+    let mut target_values = Vec::new();
+    for (t, v) in inputs.target_values.into_iter() {
+        if witness.set_target_returning_rep(t, v).is_some() {
+            target_values.push((t, v));
+        }
+    }
 
-    witness
+    let generator_groups = &prover_data.generator_groups;
+    let in_group = |i: usize| generator_groups[i] == Some(group_index);
+    let mut generator_is_expired = vec![false; prover_data.generators.len()];
+    run_generators(
+        &mut witness,
+        prover_data,
+        |i| in_group(i) || generator_groups[i].is_none(),
+        &mut generator_is_expired,
+        |i, t, v| {
+            if in_group(i) {
+                target_values.push((t, v));
+            }
+        },
+        None,
+    )?;
+
+    Ok(PartialWitnessShard {
+        group: group.to_string(),
+        target_values,
+        completed_generators: (0..generator_is_expired.len())
+            .filter(|&i| in_group(i) && generator_is_expired[i])
+            .collect(),
+    })
+}
+
+/// Combines the shards of a circuit's witness, generated by [`generate_partial_witness_for_group`],
+/// into the witness the prover takes.
+///
+/// Fails if two shards disagree on the value of a target, or if a generator of a group wasn't
+/// completed by any shard, e.g. because the shard of that group is missing. The generators outside
+/// of any group, such as those combining the outputs of several groups, are then run on the merged
+/// witness, and must complete.
+pub fn merge_witness_shards<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    shards: &[PartialWitnessShard<F>],
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    common_data: &'a CommonCircuitData<F, D>,
+) -> Result<PartitionWitness<'a, F>> {
+    let generators = &prover_data.generators;
+    let mut witness = PartitionWitness::new(
+        common_data.config.num_wires,
+        common_data.degree(),
+        &prover_data.representative_map,
+    );
+
+    // The shard which first set each partition, by representative index.
+    let mut setters = vec![None::<usize>; prover_data.representative_map.len()];
+    for (shard_index, shard) in shards.iter().enumerate() {
+        for &(t, v) in &shard.target_values {
+            let index = witness.target_index(t);
+            ensure!(
+                index < prover_data.representative_map.len(),
+                "Shard `{}` sets {:?}, which isn't in the circuit",
+                shard.group,
+                t
+            );
+            let rep_index = prover_data.representative_map[index];
+            match (witness.values[rep_index], setters[rep_index]) {
+                (Some(old_v), Some(old_shard)) => ensure!(
+                    old_v == v,
+                    "Shards `{}` and `{}` disagree on the value of {:?}: {} != {}",
+                    shards[old_shard].group,
+                    shard.group,
+                    t,
+                    old_v,
+                    v
+                ),
+                _ => {
+                    witness.values[rep_index] = Some(v);
+                    setters[rep_index] = Some(shard_index);
+                }
+            }
+        }
+    }
+
+    let mut generator_is_expired = vec![false; generators.len()];
+    for shard in shards {
+        for &i in &shard.completed_generators {
+            ensure!(
+                i < generators.len(),
+                "Shard `{}` completed generator {}, which isn't in the circuit",
+                shard.group,
+                i
+            );
+            generator_is_expired[i] = true;
+        }
+    }
+    for (i, group) in prover_data.generator_groups.iter().enumerate() {
+        if let Some(group) = group {
+            ensure!(
+                generator_is_expired[i],
+                "Generator `{}` of group `{}` wasn't completed by any shard",
+                generators[i].0.id(),
+                prover_data.generator_group_labels[*group]
+            );
+        }
+    }
+
+    run_generators(
+        &mut witness,
+        prover_data,
+        |_| true,
+        &mut generator_is_expired,
+        |_, _, _| {},
+        None,
+    )?;
+    ensure_all_generators_ran(&witness, prover_data, &generator_is_expired)?;
+
+    Ok(witness)
 }
 
 /// A generator participates in the generation of the witness.
@@ -109,6 +402,12 @@ pub trait WitnessGenerator<F: RichField + Extendable<D>, const D: usize>:
     /// the generator will be queued to run.
     fn watch_list(&self) -> Vec<Target>;
 
+    /// Targets which this generator must have set once it reports that it is finished. Generation
+    /// fails if any of them is still unset. None are declared by default.
+    fn outputs(&self) -> Vec<Target> {
+        Vec::new()
+    }
+
     /// Run this generator, returning a flag indicating whether the generator is finished. If the
     /// flag is true, the generator will never be run again, otherwise it will be queued for another
     /// run next time a target in its watch list is populated.
@@ -131,6 +430,11 @@ impl<F: RichField + Extendable<D>, const D: usize> WitnessGeneratorRef<F, D> {
     pub fn new<G: WitnessGenerator<F, D>>(generator: G) -> WitnessGeneratorRef<F, D> {
         WitnessGeneratorRef(Box::new(generator))
     }
+
+    /// A reference to a generator which may be shared, e.g. by the snapshots of a builder.
+    pub(crate) fn shared(generator: Arc<dyn WitnessGenerator<F, D>>) -> Self {
+        WitnessGeneratorRef(Box::new(SharedGenerator(generator)))
+    }
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> PartialEq for WitnessGeneratorRef<F, D> {
@@ -147,6 +451,41 @@ impl<F: RichField + Extendable<D>, const D: usize> Debug for WitnessGeneratorRef
     }
 }
 
+/// A generator shared by several `WitnessGeneratorRef`s, which behaves as the generator itself.
+#[derive(Debug)]
+struct SharedGenerator<F: RichField + Extendable<D>, const D: usize>(
+    Arc<dyn WitnessGenerator<F, D>>,
+);
+
+impl<F: RichField + Extendable<D>, const D: usize> WitnessGenerator<F, D>
+    for SharedGenerator<F, D>
+{
+    fn id(&self) -> String {
+        self.0.id()
+    }
+
+    fn watch_list(&self) -> Vec<Target> {
+        self.0.watch_list()
+    }
+
+    fn outputs(&self) -> Vec<Target> {
+        self.0.outputs()
+    }
+
+    fn run(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> bool {
+        self.0.run(witness, out_buffer)
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        self.0.serialize(dst, common_data)
+    }
+
+    fn deserialize(_src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        // It is serialized under the ID of the shared generator, which deserializes it instead.
+        Err(IoError)
+    }
+}
+
 /// Values generated by a generator invocation.
 #[derive(Debug)]
 pub struct GeneratedValues<F: Field> {
@@ -159,6 +498,24 @@ impl<F: Field> From<Vec<(Target, F)>> for GeneratedValues<F> {
     }
 }
 
+/// Wipes the values, including those left in the spare capacity after draining.
+impl<F: Field> Zeroize for GeneratedValues<F> {
+    fn zeroize(&mut self) {
+        wipe_vec(
+            &mut self.target_values,
+            (Target::VirtualTarget { index: 0 }, F::ZERO),
+        );
+    }
+}
+
+impl<F: RichField> SensitiveData for GeneratedValues<F> {
+    type Field = F;
+
+    fn for_each_value(&self, f: &mut dyn FnMut(F)) {
+        self.target_values.iter().for_each(|&(_, value)| f(value));
+    }
+}
+
 impl<F: Field> WitnessWrite<F> for GeneratedValues<F> {
     fn set_target(&mut self, target: Target, value: F) {
         self.target_values.push((target, value));
@@ -203,6 +560,11 @@ pub trait SimpleGenerator<F: RichField + Extendable<D>, const D: usize>:
 
     fn dependencies(&self) -> Vec<Target>;
 
+    /// Targets which `run_once` must set, see [`WitnessGenerator::outputs`].
+    fn outputs(&self) -> Vec<Target> {
+        Vec::new()
+    }
+
     fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>);
 
     fn adapter(self) -> SimpleGeneratorAdapter<F, Self, D>
@@ -243,6 +605,10 @@ impl<F: RichField + Extendable<D>, SG: SimpleGenerator<F, D>, const D: usize> Wi
         self.inner.dependencies()
     }
 
+    fn outputs(&self) -> Vec<Target> {
+        self.inner.outputs()
+    }
+
     fn run(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> bool {
         if witness.contains_all(&self.inner.dependencies()) {
             self.inner.run_once(witness, out_buffer);
@@ -312,8 +678,22 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Ran
         Vec::new()
     }
 
-    fn run_once(&self, _witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
-        let random_value = F::rand();
+    fn outputs(&self) -> Vec<Target> {
+        vec![self.target]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let random_value = match witness.random_seed {
+            // The domain is distinct from those of the salts, see `PolynomialBatch::lde_values`.
+            Some(seed) => {
+                let domain = [
+                    F::NEG_ONE,
+                    F::from_canonical_usize(witness.target_index(self.target)),
+                ];
+                expand_seed(seed, &domain, 1)[0]
+            }
+            None => F::rand(),
+        };
         out_buffer.set_target(self.target, random_value);
     }
 
@@ -415,3 +795,324 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Con
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::plonk::prover::prove_with_partition_witness;
+    #[cfg(feature = "timing")]
+    use crate::plonk::prover::ProverOptions;
+    use crate::util::serialization::DefaultGeneratorSerializer;
+    use crate::util::timing::TimingTree;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const GROUPS: [&str; 3] = ["tx0", "tx1", "tx2"];
+
+    /// A circuit with an independent computation per group, whose results are summed outside of
+    /// any group.
+    fn sharded_circuit() -> (CircuitData<F, C, D>, PartialWitness<F>) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut pw = PartialWitness::new();
+        let mut results = Vec::new();
+        for (i, group) in GROUPS.into_iter().enumerate() {
+            builder.set_generator_group(group);
+            let x = builder.add_virtual_public_input();
+            pw.set_target(x, F::from_canonical_usize(i + 2));
+            let mut acc = x;
+            for _ in 0..10 {
+                acc = builder.mul_add(acc, acc, x);
+            }
+            results.push(builder.inverse(acc));
+        }
+        builder.clear_generator_group();
+        let sum = builder.add_many(results);
+        builder.register_public_input(sum);
+        (builder.build::<C>(), pw)
+    }
+
+    fn generate_shards(
+        data: &CircuitData<F, C, D>,
+        pw: &PartialWitness<F>,
+        groups: &[&str],
+    ) -> Result<Vec<PartialWitnessShard<F>>> {
+        groups
+            .iter()
+            .map(|group| {
+                generate_partial_witness_for_group(
+                    group,
+                    pw.clone(),
+                    &data.prover_only,
+                    &data.common,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_witness_shards() -> Result<()> {
+        let (data, pw) = sharded_circuit();
+
+        let mut grouped = vec![false; data.prover_only.generators.len()];
+        for group in GROUPS {
+            let indices = data.prover_only.generator_group(group).unwrap();
+            assert!(!indices.is_empty());
+            for i in indices {
+                assert!(!grouped[i], "generator {i} is in several groups");
+                grouped[i] = true;
+            }
+        }
+        assert!(data.prover_only.generator_group("tx3").is_none());
+        assert!(generate_partial_witness_for_group(
+            "tx3",
+            pw.clone(),
+            &data.prover_only,
+            &data.common
+        )
+        .is_err());
+
+        // The groups survive serialization.
+        let generator_serializer = DefaultGeneratorSerializer::<C, D> {
+            _phantom: PhantomData,
+        };
+        let bytes = data
+            .prover_only
+            .to_bytes(&generator_serializer, &data.common)
+            .unwrap();
+        let prover_only = ProverOnlyCircuitData::<F, C, D>::from_bytes(
+            &bytes,
+            &generator_serializer,
+            &data.common,
+        )
+        .unwrap();
+        assert_eq!(
+            prover_only.generator_group_labels,
+            data.prover_only.generator_group_labels
+        );
+        assert_eq!(
+            prover_only.generator_groups,
+            data.prover_only.generator_groups
+        );
+
+        // Shards may be generated in any order.
+        let shards = generate_shards(&data, &pw, &["tx2", "tx0", "tx1"])?;
+        let witness = merge_witness_shards(&shards, &data.prover_only, &data.common)?;
+        let proof = prove_with_partition_witness(
+            &data.prover_only,
+            &data.common,
+            witness,
+            &mut TimingTree::default(),
+        )?;
+        assert_eq!(proof.public_inputs, data.prove(pw)?.public_inputs);
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_merge_invalid_witness_shards() -> Result<()> {
+        let (data, pw) = sharded_circuit();
+        let shards = generate_shards(&data, &pw, &GROUPS)?;
+
+        // A missing shard.
+        let err = merge_witness_shards(&shards[..2], &data.prover_only, &data.common)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`tx2`"), "{err}");
+
+        // A shard disagreeing with another on a value generated by `tx1`.
+        let mut conflicting = shards[1].clone();
+        conflicting.group = "tx1'".to_string();
+        let (target, value) = conflicting
+            .target_values
+            .iter_mut()
+            .find(|(t, _)| !pw.target_values.contains_key(t))
+            .unwrap();
+        *value += F::ONE;
+        let target = *target;
+        let mut conflicting_shards = shards.clone();
+        conflicting_shards.push(conflicting);
+        let err = merge_witness_shards(&conflicting_shards, &data.prover_only, &data.common)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(&format!("{target:?}")), "{err}");
+        assert!(err.contains("`tx1`") && err.contains("`tx1'`"), "{err}");
+        Ok(())
+    }
+
+    /// Never finishes, and never sets its output.
+    #[derive(Debug)]
+    struct StuckGenerator {
+        input: Target,
+    }
+
+    impl WitnessGenerator<F, D> for StuckGenerator {
+        fn id(&self) -> String {
+            "StuckGenerator".into()
+        }
+
+        fn watch_list(&self) -> Vec<Target> {
+            vec![self.input]
+        }
+
+        fn run(
+            &self,
+            _witness: &PartitionWitness<F>,
+            _out_buffer: &mut GeneratedValues<F>,
+        ) -> bool {
+            false
+        }
+
+        fn serialize(
+            &self,
+            _dst: &mut Vec<u8>,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<()> {
+            unimplemented!()
+        }
+
+        fn deserialize(
+            _src: &mut Buffer,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<Self> {
+            unimplemented!()
+        }
+    }
+
+    /// Sets `output` to `input + 1`, slowly. If `lazy`, finishes without setting anything.
+    #[derive(Debug)]
+    struct IncrementGenerator {
+        input: Target,
+        output: Target,
+        lazy: bool,
+    }
+
+    impl SimpleGenerator<F, D> for IncrementGenerator {
+        fn id(&self) -> String {
+            "IncrementGenerator".into()
+        }
+
+        fn dependencies(&self) -> Vec<Target> {
+            vec![self.input]
+        }
+
+        fn outputs(&self) -> Vec<Target> {
+            vec![self.output]
+        }
+
+        fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+            if !self.lazy {
+                #[cfg(feature = "std")]
+                std::thread::sleep(Duration::from_millis(2));
+                out_buffer.set_target(self.output, witness.get_target(self.input) + F::ONE);
+            }
+        }
+
+        fn serialize(
+            &self,
+            _dst: &mut Vec<u8>,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<()> {
+            unimplemented!()
+        }
+
+        fn deserialize(
+            _src: &mut Buffer,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<Self> {
+            unimplemented!()
+        }
+    }
+
+    /// A chain of `len` increments of a public input, the last of which is `lazy`.
+    fn increment_chain(len: usize, lazy: bool) -> (CircuitData<F, C, D>, PartialWitness<F>) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let mut acc = x;
+        for i in 0..len {
+            let output = builder.add_virtual_target();
+            builder.add_simple_generator(IncrementGenerator {
+                input: acc,
+                output,
+                lazy: lazy && i == len - 1,
+            });
+            acc = output;
+        }
+        builder.register_public_input(acc);
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::ZERO);
+        (builder.build::<C>(), pw)
+    }
+
+    #[test]
+    fn test_stuck_generator_reported() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.add_virtual_target();
+        builder.add_generators(vec![WitnessGeneratorRef::new(StuckGenerator { input: x })]);
+        let z = builder.mul(y, y);
+        builder.register_public_input(z);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::ONE);
+
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let err = try_generate_partial_witness(pw, &data.prover_only, &data.common, None, None)
+            .err()
+            .unwrap()
+            .to_string();
+        #[cfg(feature = "std")]
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(err.contains("generators weren't run"), "{err}");
+        assert!(err.contains("`StuckGenerator` waiting on []"), "{err}");
+        // The multiplication is waiting on the wires copied from `y`.
+        assert!(err.contains("ArithmeticBaseGenerator"), "{err}");
+    }
+
+    #[test]
+    fn test_slow_generators_complete() -> Result<()> {
+        let (data, pw) = increment_chain(50, false);
+        let witness = try_generate_partial_witness(
+            pw,
+            &data.prover_only,
+            &data.common,
+            Some(Duration::from_secs(600)),
+            None,
+        )?;
+        let last = data.prover_only.public_inputs[1];
+        assert_eq!(witness.get_target(last), F::from_canonical_usize(50));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "timing")]
+    fn test_witness_generation_timeout() {
+        let (data, pw) = increment_chain(50, false);
+        let options = ProverOptions {
+            witness_generation_timeout: Some(Duration::from_millis(10)),
+            ..Default::default()
+        };
+        let err = data
+            .prove_with_options(pw, &options)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("timed out"), "{err}");
+    }
+
+    #[test]
+    fn test_missing_outputs_reported() {
+        let (data, pw) = increment_chain(3, true);
+        let err = try_generate_partial_witness(pw, &data.prover_only, &data.common, None, None)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            err.contains("`IncrementGenerator` finished without setting its output"),
+            "{err}"
+        );
+    }
+}
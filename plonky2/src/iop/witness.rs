@@ -3,11 +3,13 @@ use alloc::vec::Vec;
 
 use hashbrown::HashMap;
 use itertools::{zip_eq, Itertools};
+use zeroize::Zeroize;
 
 use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::types::Field;
 use crate::fri::structure::{FriOpenings, FriOpeningsTarget};
 use crate::fri::witness_util::set_fri_proof_target;
+use crate::gadgets::arithmetic_u64::{U32Target, U64Target};
 use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
@@ -16,6 +18,7 @@ use crate::iop::wire::Wire;
 use crate::plonk::circuit_data::{VerifierCircuitTarget, VerifierOnlyCircuitData};
 use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
 use crate::plonk::proof::{Proof, ProofTarget, ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use crate::util::sensitive::{wipe_vec, SensitiveData};
 
 pub trait WitnessWrite<F: Field> {
     fn set_target(&mut self, target: Target, value: F);
@@ -50,6 +53,8 @@ pub trait WitnessWrite<F: Field> {
         zip_eq(targets, values).for_each(|(&target, &value)| self.set_target(target, value));
     }
 
+    /// Sets each of `ets` to the corresponding value. Panics if the lengths differ, as
+    /// `set_target_arr` does.
     fn set_extension_targets<const D: usize>(
         &mut self,
         ets: &[ExtensionTarget<D>],
@@ -57,16 +62,18 @@ pub trait WitnessWrite<F: Field> {
     ) where
         F: RichField + Extendable<D>,
     {
-        debug_assert_eq!(ets.len(), values.len());
-        ets.iter()
-            .zip(values)
-            .for_each(|(&et, &v)| self.set_extension_target(et, v));
+        zip_eq(ets, values).for_each(|(&et, &v)| self.set_extension_target(et, v));
     }
 
     fn set_bool_target(&mut self, target: BoolTarget, value: bool) {
         self.set_target(target.target, F::from_bool(value))
     }
 
+    fn set_u64_target(&mut self, target: U64Target, value: u64) {
+        self.set_target(target.lo.0, F::from_canonical_u32(value as u32));
+        self.set_target(target.hi.0, F::from_canonical_u32((value >> 32) as u32));
+    }
+
     /// Set the targets in a `ProofWithPublicInputsTarget` to their corresponding values in a
     /// `ProofWithPublicInputs`.
     fn set_proof_with_pis_target<C: GenericConfig<D, F = F>, const D: usize>(
@@ -104,6 +111,10 @@ pub trait WitnessWrite<F: Field> {
         C::Hasher: AlgebraicHasher<F>,
     {
         self.set_cap_target(&proof_target.wires_cap, &proof.wires_cap);
+        self.set_cap_target(
+            &proof_target.challenge_dependent_cap,
+            &proof.challenge_dependent_cap,
+        );
         self.set_cap_target(
             &proof_target.plonk_zs_partial_products_cap,
             &proof.plonk_zs_partial_products_cap,
@@ -216,6 +227,20 @@ pub trait Witness<F: Field>: WitnessWrite<F> {
         panic!("not a bool")
     }
 
+    fn get_u64_target(&self, target: U64Target) -> u64
+    where
+        F: RichField,
+    {
+        let U64Target {
+            lo: U32Target(lo),
+            hi: U32Target(hi),
+        } = target;
+        let lo = self.get_target(lo).to_canonical_u64();
+        let hi = self.get_target(hi).to_canonical_u64();
+        assert!(lo <= u32::MAX as u64 && hi <= u32::MAX as u64, "not a u64");
+        (hi << 32) | lo
+    }
+
     fn get_hash_target(&self, ht: HashOutTarget) -> HashOut<F> {
         HashOut {
             elements: self.get_targets(&ht.elements).try_into().unwrap(),
@@ -261,6 +286,27 @@ impl<F: Field> MatrixWitness<F> {
     pub fn get_wire(&self, gate: usize, input: usize) -> F {
         self.wire_values[input][gate]
     }
+
+    /// The number of rows.
+    pub fn degree(&self) -> usize {
+        self.wire_values.first().map_or(0, Vec::len)
+    }
+}
+
+impl<F: Field> Zeroize for MatrixWitness<F> {
+    fn zeroize(&mut self) {
+        for column in &mut self.wire_values {
+            wipe_vec(column, F::ZERO);
+        }
+    }
+}
+
+impl<F: RichField> SensitiveData for MatrixWitness<F> {
+    type Field = F;
+
+    fn for_each_value(&self, f: &mut dyn FnMut(F)) {
+        self.wire_values.iter().flatten().copied().for_each(f);
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -295,6 +341,16 @@ impl<F: Field> Witness<F> for PartialWitness<F> {
     }
 }
 
+impl<F: Field> Zeroize for PartialWitness<F> {
+    fn zeroize(&mut self) {
+        for value in self.target_values.values_mut() {
+            // SAFETY: `value` is a valid, aligned reference.
+            unsafe { core::ptr::write_volatile(value, F::ZERO) };
+        }
+        self.target_values.clear();
+    }
+}
+
 /// `PartitionWitness` holds a disjoint-set forest of the targets respecting a circuit's copy constraints.
 /// The value of a target is defined to be the value of its root in the forest.
 #[derive(Clone, Debug)]
@@ -303,6 +359,9 @@ pub struct PartitionWitness<'a, F: Field> {
     pub representative_map: &'a [usize],
     pub num_wires: usize,
     pub degree: usize,
+    /// If set, random values used for blinding are derived from this seed rather than sampled, see
+    /// `ProverOptions::salt_rng_seed`.
+    pub random_seed: Option<u64>,
 }
 
 impl<'a, F: Field> PartitionWitness<'a, F> {
@@ -312,6 +371,7 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
             representative_map,
             num_wires,
             degree,
+            random_seed: None,
         }
     }
 
@@ -338,7 +398,17 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
     }
 
     pub fn full_witness(self) -> MatrixWitness<F> {
-        let mut wire_values = vec![vec![F::ZERO; self.degree]; self.num_wires];
+        self.full_witness_into(Vec::new())
+    }
+
+    /// Like `full_witness`, but reuses the allocations of `wire_values`. Every entry is
+    /// overwritten, so nothing previously stored in `wire_values` is carried over.
+    pub fn full_witness_into(&self, mut wire_values: Vec<Vec<F>>) -> MatrixWitness<F> {
+        wire_values.resize_with(self.num_wires, Vec::new);
+        for column in &mut wire_values {
+            column.clear();
+            column.resize(self.degree, F::ZERO);
+        }
         for i in 0..self.degree {
             for j in 0..self.num_wires {
                 let t = Target::Wire(Wire { row: i, column: j });
@@ -364,3 +434,18 @@ impl<'a, F: Field> Witness<F> for PartitionWitness<'a, F> {
         self.values[rep_index]
     }
 }
+
+impl<'a, F: Field> Zeroize for PartitionWitness<'a, F> {
+    fn zeroize(&mut self) {
+        // Unlike `None`, this overwrites the value of every set target.
+        wipe_vec(&mut self.values, Some(F::ZERO));
+    }
+}
+
+impl<'a, F: RichField> SensitiveData for PartitionWitness<'a, F> {
+    type Field = F;
+
+    fn for_each_value(&self, f: &mut dyn FnMut(F)) {
+        self.values.iter().flatten().copied().for_each(f);
+    }
+}
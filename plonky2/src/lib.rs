@@ -1,5 +1,7 @@
 #![allow(clippy::too_many_arguments)]
 #![allow(clippy::needless_range_loop)]
+#![feature(avx512_target_feature)]
+#![feature(portable_simd)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
 pub extern crate alloc;
@@ -8,14 +10,20 @@ pub extern crate alloc;
 #[doc(inline)]
 pub use plonky2_field as field;
 
+#[cfg(feature = "std")]
+pub mod entrypoint;
 pub mod fri;
 pub mod gadgets;
 pub mod gates;
 pub mod hash;
+pub mod interop;
 pub mod iop;
 pub mod plonk;
 pub mod recursion;
 pub mod util;
 
+#[doc(inline)]
+pub use recursion::wrap;
+
 #[cfg(test)]
 mod lookup_test;
@@ -0,0 +1,401 @@
+//! Columns which are committed in an extra prover round, after the wires, and which may depend on
+//! verifier challenges drawn from the wires commitment.
+//!
+//! This is the extension point for lookup- or permutation-style arguments built on top of plonky2:
+//! such an argument commits to the wires, receives random challenges, and only then commits to
+//! auxiliary columns (e.g. running products) whose values depend on those challenges. The columns
+//! are registered with
+//! [`CircuitBuilder::add_challenge_dependent_columns`](crate::plonk::circuit_builder::CircuitBuilder::add_challenge_dependent_columns),
+//! which makes the prover draw the generator's challenges right after observing the wires cap,
+//! commit to the generated columns in an extra oracle, and add the generator's constraints to the
+//! vanishing polynomial.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Error, Formatter};
+use core::hash::{Hash, Hasher};
+
+use serde::{Serialize, Serializer};
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::polynomial::PolynomialValues;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::witness::MatrixWitness;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+
+/// Generates, and constrains, a set of challenge-dependent columns.
+///
+/// Constraints are evaluated on every row, with no selector; a generator which should only
+/// constrain some rows must commit its own selector column, or read one from the wires.
+pub trait ChallengeDependentGenerator<F: RichField + Extendable<D>, const D: usize>:
+    'static + Send + Sync + Debug
+{
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    /// The number of challenges this generator receives.
+    fn num_challenges(&self) -> usize;
+
+    /// The number of constraints returned by the `eval` methods.
+    fn num_constraints(&self) -> usize;
+
+    /// The maximum degree of the constraints, where wires and columns have degree 1.
+    fn degree(&self) -> usize;
+
+    /// Generates the columns' values on the trace domain, given this generator's challenges and
+    /// the wire values.
+    fn generate(&self, challenges: &[F], witness: &MatrixWitness<F>) -> Vec<PolynomialValues<F>>;
+
+    /// Evaluates the constraints at an extension field point.
+    fn eval(&self, vars: ChallengeDependentVars<F::Extension>) -> Vec<F::Extension>;
+
+    /// Evaluates the constraints at a base field point. By default, this lifts the point to the
+    /// extension field and calls `eval`.
+    fn eval_base(&self, vars: ChallengeDependentVars<F>) -> Vec<F> {
+        let lift = |xs: &[F]| {
+            xs.iter()
+                .map(|&x| F::Extension::from_basefield(x))
+                .collect::<Vec<_>>()
+        };
+        let challenges = lift(vars.challenges);
+        let local_wires = lift(vars.local_wires);
+        let local_columns = lift(vars.local_columns);
+        let next_columns = lift(vars.next_columns);
+        self.eval(ChallengeDependentVars {
+            challenges: &challenges,
+            l_0: F::Extension::from_basefield(vars.l_0),
+            local_wires: &local_wires,
+            local_columns: &local_columns,
+            next_columns: &next_columns,
+        })
+        .into_iter()
+        .map(|c| c.to_basefield_array()[0])
+        .collect()
+    }
+
+    /// Evaluates the constraints in a recursive circuit.
+    fn eval_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: ChallengeDependentVars<ExtensionTarget<D>>,
+    ) -> Vec<ExtensionTarget<D>>;
+}
+
+/// The values seen by a generator's constraints at a single point `x`.
+#[derive(Debug, Copy, Clone)]
+pub struct ChallengeDependentVars<'a, T> {
+    /// This generator's challenges.
+    pub challenges: &'a [T],
+    /// The first Lagrange basis polynomial, `L_0(x)`.
+    pub l_0: T,
+    pub local_wires: &'a [T],
+    /// This generator's columns at `x`.
+    pub local_columns: &'a [T],
+    /// This generator's columns at `g x`, i.e. on the next row.
+    pub next_columns: &'a [T],
+}
+
+/// A set of challenge-dependent columns along with their generator. Implements `PartialEq`, `Eq`
+/// and `Hash` based on the generator's ID.
+#[derive(Clone)]
+pub struct ChallengeDependentColumns<F: RichField + Extendable<D>, const D: usize> {
+    pub num_columns: usize,
+    pub generator: Arc<dyn ChallengeDependentGenerator<F, D>>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PartialEq for ChallengeDependentColumns<F, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.num_columns == other.num_columns && self.generator.id() == other.generator.id()
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Eq for ChallengeDependentColumns<F, D> {}
+
+impl<F: RichField + Extendable<D>, const D: usize> Hash for ChallengeDependentColumns<F, D> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.num_columns.hash(state);
+        self.generator.id().hash(state)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Debug for ChallengeDependentColumns<F, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{} ({} columns)", self.generator.id(), self.num_columns)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Serialize for ChallengeDependentColumns<F, D> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{self:?}"))
+    }
+}
+
+/// Generates all challenge-dependent columns, in the order in which they were added.
+pub(crate) fn generate_challenge_dependent_columns<F: RichField + Extendable<D>, const D: usize>(
+    common_data: &CommonCircuitData<F, D>,
+    challenges: &[F],
+    witness: &MatrixWitness<F>,
+) -> Vec<PolynomialValues<F>> {
+    let mut challenges = challenges;
+    let mut all_columns = Vec::with_capacity(common_data.num_challenge_dependent_columns());
+    for cd in &common_data.challenge_dependent_columns {
+        let (current, rest) = challenges.split_at(cd.generator.num_challenges());
+        challenges = rest;
+        let columns = cd.generator.generate(current, witness);
+        assert_eq!(
+            columns.len(),
+            cd.num_columns,
+            "{} generated the wrong number of columns",
+            cd.generator.id()
+        );
+        assert!(
+            columns.iter().all(|c| c.len() == common_data.degree()),
+            "{} generated columns of the wrong length",
+            cd.generator.id()
+        );
+        all_columns.extend(columns);
+    }
+    all_columns
+}
+
+/// Splits `vars`, which hold the challenges and columns of all generators, into per-generator vars
+/// and evaluates each generator's constraints with `eval`.
+fn eval_all<'a, F, T, const D: usize>(
+    common_data: &'a CommonCircuitData<F, D>,
+    vars: ChallengeDependentVars<'a, T>,
+    mut eval: impl FnMut(
+        &dyn ChallengeDependentGenerator<F, D>,
+        ChallengeDependentVars<'a, T>,
+    ) -> Vec<T>,
+) -> Vec<T>
+where
+    F: RichField + Extendable<D>,
+    T: Copy,
+{
+    let mut constraints = Vec::with_capacity(common_data.num_challenge_dependent_constraints());
+    let mut challenge_offset = 0;
+    let mut column_offset = 0;
+    for cd in &common_data.challenge_dependent_columns {
+        let challenges = challenge_offset..challenge_offset + cd.generator.num_challenges();
+        let columns = column_offset..column_offset + cd.num_columns;
+        challenge_offset = challenges.end;
+        column_offset = columns.end;
+        let generator_vars = ChallengeDependentVars {
+            challenges: &vars.challenges[challenges],
+            l_0: vars.l_0,
+            local_wires: vars.local_wires,
+            local_columns: &vars.local_columns[columns.clone()],
+            next_columns: &vars.next_columns[columns],
+        };
+        let generator_constraints = eval(cd.generator.as_ref(), generator_vars);
+        debug_assert_eq!(generator_constraints.len(), cd.generator.num_constraints());
+        constraints.extend(generator_constraints);
+    }
+    constraints
+}
+
+pub(crate) fn eval_challenge_dependent_constraints<F: RichField + Extendable<D>, const D: usize>(
+    common_data: &CommonCircuitData<F, D>,
+    vars: ChallengeDependentVars<F::Extension>,
+) -> Vec<F::Extension> {
+    eval_all(common_data, vars, |g, vars| g.eval(vars))
+}
+
+pub(crate) fn eval_challenge_dependent_constraints_base<
+    F: RichField + Extendable<D>,
+    const D: usize,
+>(
+    common_data: &CommonCircuitData<F, D>,
+    vars: ChallengeDependentVars<F>,
+) -> Vec<F> {
+    eval_all(common_data, vars, |g, vars| g.eval_base(vars))
+}
+
+pub(crate) fn eval_challenge_dependent_constraints_circuit<
+    F: RichField + Extendable<D>,
+    const D: usize,
+>(
+    builder: &mut CircuitBuilder<F, D>,
+    common_data: &CommonCircuitData<F, D>,
+    vars: ChallengeDependentVars<ExtensionTarget<D>>,
+) -> Vec<ExtensionTarget<D>> {
+    eval_all(common_data, vars, |g, vars| g.eval_circuit(builder, vars))
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{boxed::Box, vec};
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::{Field, Sample};
+    use crate::gates::noop::NoopGate;
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+    use crate::util::serialization::Write;
+
+    /// A toy grand-product argument showing that wire columns `a` and `b` hold the same multiset
+    /// of values, using a single running product `z` with `z(g^0) = 1` and
+    /// `z(g x) (gamma + b(x)) = z(x) (gamma + a(x))`.
+    ///
+    /// The running product wraps around from the last row to the first, so it only works when the
+    /// multisets are equal over the whole trace, which excludes zero-knowledge blinding rows.
+    #[derive(Debug)]
+    struct GrandProduct {
+        a: usize,
+        b: usize,
+    }
+
+    impl<F: RichField + Extendable<D>, const D: usize> ChallengeDependentGenerator<F, D>
+        for GrandProduct
+    {
+        fn num_challenges(&self) -> usize {
+            1
+        }
+
+        fn num_constraints(&self) -> usize {
+            2
+        }
+
+        fn degree(&self) -> usize {
+            2
+        }
+
+        fn generate(
+            &self,
+            challenges: &[F],
+            witness: &MatrixWitness<F>,
+        ) -> Vec<PolynomialValues<F>> {
+            let gamma = challenges[0];
+            let n = witness.degree();
+            let mut z = Vec::with_capacity(n);
+            let mut acc = F::ONE;
+            for row in 0..n {
+                z.push(acc);
+                let a = witness.get_wire(row, self.a);
+                let b = witness.get_wire(row, self.b);
+                acc *= (gamma + a) * (gamma + b).inverse();
+            }
+            vec![PolynomialValues::new(z)]
+        }
+
+        fn eval(&self, vars: ChallengeDependentVars<F::Extension>) -> Vec<F::Extension> {
+            let gamma = vars.challenges[0];
+            let a = vars.local_wires[self.a];
+            let b = vars.local_wires[self.b];
+            let z = vars.local_columns[0];
+            let z_next = vars.next_columns[0];
+            vec![
+                vars.l_0 * (z - F::Extension::ONE),
+                z_next * (gamma + b) - z * (gamma + a),
+            ]
+        }
+
+        fn eval_circuit(
+            &self,
+            builder: &mut CircuitBuilder<F, D>,
+            vars: ChallengeDependentVars<ExtensionTarget<D>>,
+        ) -> Vec<ExtensionTarget<D>> {
+            let gamma = vars.challenges[0];
+            let z = vars.local_columns[0];
+            let z_next = vars.next_columns[0];
+            let one = builder.one_extension();
+            let z_minus_one = builder.sub_extension(z, one);
+            let first = builder.mul_extension(vars.l_0, z_minus_one);
+            let gamma_a = builder.add_extension(gamma, vars.local_wires[self.a]);
+            let gamma_b = builder.add_extension(gamma, vars.local_wires[self.b]);
+            let rhs = builder.mul_extension(z, gamma_a);
+            let transition = builder.mul_sub_extension(z_next, gamma_b, rhs);
+            vec![first, transition]
+        }
+    }
+
+    #[test]
+    fn test_grand_product() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        // Rows whose first two wires we fill in below. The remaining rows hold zeros or the same
+        // constant in both wires.
+        let num_rows = 1 << 6;
+        for _ in 0..num_rows {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let (a, b) = (0, 1);
+        builder.add_challenge_dependent_columns(1, Box::new(GrandProduct { a, b }));
+        let data = builder.build::<C>();
+        assert_eq!(data.common.num_challenge_dependent_columns(), 1);
+
+        // The `b` values are a rotation of the `a` values.
+        let values = F::rand_vec(num_rows);
+        let mut pw = PartialWitness::new();
+        for (row, &value) in values.iter().enumerate() {
+            pw.set_target(Target::wire(row, a), value);
+            pw.set_target(Target::wire((row + 1) % num_rows, b), value);
+        }
+        let proof = data.prove(pw)?;
+        assert_eq!(
+            proof.proof.opening_proof.query_round_proofs[0]
+                .initial_trees_proof
+                .evals_proofs
+                .len(),
+            5
+        );
+        data.verify(proof.clone())?;
+        let compressed = data.compress(proof.clone())?;
+        assert_eq!(data.decompress(compressed.clone())?, proof);
+        data.verify_compressed(compressed)?;
+
+        // A tampered challenge-dependent cap changes the challenges and fails verification.
+        let mut bad_proof = proof.clone();
+        bad_proof.proof.challenge_dependent_cap.0[0].elements[0] += F::ONE;
+        assert!(verify(bad_proof, &data.verifier_only, &data.common).is_err());
+
+        // Verify the proof recursively.
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let proof_t = builder.add_virtual_proof_with_pis(&data.common);
+        // Like the generators, the targets of the columns are not serializable.
+        assert!(Vec::new()
+            .write_target_proof_with_public_inputs(&proof_t)
+            .is_err());
+        let verifier_t = builder.constant_verifier_data(&data.verifier_only);
+        builder.verify_proof::<C>(&proof_t, &verifier_t, &data.common);
+        let recursive_data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_t, &proof);
+        let recursive_proof = recursive_data.prove(pw)?;
+        recursive_data.verify(recursive_proof)
+    }
+
+    #[test]
+    fn test_grand_product_mismatch() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        builder.add_gate(NoopGate, vec![]);
+        builder.add_challenge_dependent_columns(1, Box::new(GrandProduct { a: 0, b: 1 }));
+        let data = builder.build::<C>();
+
+        // The `a` column holds a one which is missing from the `b` column.
+        let mut pw = PartialWitness::new();
+        pw.set_target(Target::wire(0, 0), F::ONE);
+        let proof = data.prove(pw)?;
+        assert!(data.verify(proof).is_err());
+        Ok(())
+    }
+}
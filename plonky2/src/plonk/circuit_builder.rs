@@ -1,6 +1,8 @@
 //! Logic for building plonky2 circuits.
 
+use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
@@ -8,6 +10,7 @@ use core::cmp::max;
 #[cfg(feature = "std")]
 use std::time::Instant;
 
+use anyhow::{ensure, Result};
 use hashbrown::{HashMap, HashSet};
 use itertools::Itertools;
 use log::{debug, info, warn, Level};
@@ -17,9 +20,8 @@ use crate::field::cosets::get_unique_coset_shifts;
 use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::fft::fft_root_table;
 use crate::field::polynomial::PolynomialValues;
-use crate::field::types::Field;
 use crate::fri::oracle::PolynomialBatch;
-use crate::fri::{FriConfig, FriParams};
+use crate::fri::FriParams;
 use crate::gadgets::arithmetic::BaseArithmeticOperation;
 use crate::gadgets::arithmetic_extension::ExtensionArithmeticOperation;
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
@@ -37,10 +39,12 @@ use crate::hash::merkle_proofs::MerkleProofTarget;
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::generator::{
-    ConstantGenerator, CopyGenerator, RandomValueGenerator, SimpleGenerator, WitnessGeneratorRef,
+    ConstantGenerator, CopyGenerator, RandomValueGenerator, SimpleGenerator, WitnessGenerator,
+    WitnessGeneratorRef,
 };
 use crate::iop::target::{BoolTarget, Target};
 use crate::iop::wire::Wire;
+use crate::plonk::challenge_dependent::{ChallengeDependentColumns, ChallengeDependentGenerator};
 use crate::plonk::circuit_data::{
     CircuitConfig, CircuitData, CommonCircuitData, MockCircuitData, ProverCircuitData,
     ProverOnlyCircuitData, VerifierCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
@@ -51,6 +55,7 @@ use crate::plonk::permutation_argument::Forest;
 use crate::plonk::plonk_common::PlonkOracle;
 use crate::timed;
 use crate::util::context_tree::ContextTree;
+use crate::util::log_size::DegreeBits;
 use crate::util::partial_products::num_partial_products;
 use crate::util::timing::TimingTree;
 use crate::util::{log2_ceil, log2_strict, transpose, transpose_poly_values};
@@ -155,6 +160,9 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// Targets to be made public.
     public_inputs: Vec<Target>,
 
+    /// Targets which must not be public inputs, nor copies of one.
+    private_targets: Vec<Target>,
+
     /// The next available index for a `VirtualTarget`.
     virtual_target_index: usize,
 
@@ -163,8 +171,19 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// A tree of named scopes, used for debugging.
     context_log: ContextTree,
 
-    /// Generators used to generate the witness.
-    generators: Vec<WitnessGeneratorRef<F, D>>,
+    /// Generators used to generate the witness. They are shared with the builder's snapshots.
+    generators: Vec<Arc<dyn WitnessGenerator<F, D>>>,
+
+    /// The labels passed to `set_generator_group`, in order of first use.
+    generator_group_labels: Vec<String>,
+    /// The index in `generator_group_labels` of the group of generators being added, if any.
+    current_generator_group: Option<usize>,
+    /// The group of each generator in `generators`.
+    generator_groups: Vec<Option<usize>>,
+    /// The group of each gate in `gate_instances`, which its generators are placed in.
+    gate_groups: Vec<Option<usize>>,
+    /// The group of each operation placed with `find_slot` in another group than its gate.
+    slot_groups: HashMap<(usize, usize), Option<usize>>,
 
     constants_to_targets: HashMap<F, Target>,
     targets_to_constants: HashMap<Target, F>,
@@ -172,6 +191,10 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// Memoized results of `arithmetic` calls.
     pub(crate) base_arithmetic_results: HashMap<BaseArithmeticOperation<F>, Target>,
 
+    /// Base arithmetic operations awaiting placement, with their results, when
+    /// `config.optimize_arithmetic` or `config.eliminate_dead_code` is set.
+    pub(crate) pending_base_arithmetic_operations: Vec<(BaseArithmeticOperation<F>, Target)>,
+
     /// Memoized results of `arithmetic_extension` calls.
     pub(crate) arithmetic_results: HashMap<ExtensionArithmeticOperation<F, D>, ExtensionTarget<D>>,
 
@@ -191,6 +214,9 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     // Lookup tables in the form of `Vec<(input_value, output_value)>`.
     luts: Vec<LookupTable>,
 
+    /// Columns committed in an extra round after the wires.
+    challenge_dependent_columns: Vec<ChallengeDependentColumns<F, D>>,
+
     /// Optional common data. When it is `Some(goal_data)`, the `build` function panics if the resulting
     /// common data doesn't equal `goal_data`.
     /// This is used in cyclic recursion.
@@ -213,19 +239,27 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             gates: HashSet::new(),
             gate_instances: Vec::new(),
             public_inputs: Vec::new(),
+            private_targets: Vec::new(),
             virtual_target_index: 0,
             copy_constraints: Vec::new(),
             context_log: ContextTree::new(),
             generators: Vec::new(),
+            generator_group_labels: Vec::new(),
+            current_generator_group: None,
+            generator_groups: Vec::new(),
+            gate_groups: Vec::new(),
+            slot_groups: HashMap::new(),
             constants_to_targets: HashMap::new(),
             targets_to_constants: HashMap::new(),
             base_arithmetic_results: HashMap::new(),
+            pending_base_arithmetic_operations: Vec::new(),
             arithmetic_results: HashMap::new(),
             current_slots: HashMap::new(),
             constant_generators: Vec::new(),
             lookup_rows: Vec::new(),
             lut_to_lookups: Vec::new(),
             luts: Vec::new(),
+            challenge_dependent_columns: Vec::new(),
             goal_common_data: None,
             verifier_data_public_input: None,
         };
@@ -236,28 +270,49 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Assert that the configuration used to create this `CircuitBuilder` is consistent,
     /// i.e. that the different parameters meet the targeted security level.
     fn check_config(&self) {
-        let &CircuitConfig {
-            security_bits,
-            fri_config:
-                FriConfig {
-                    rate_bits,
-                    proof_of_work_bits,
-                    num_query_rounds,
-                    ..
-                },
-            ..
-        } = &self.config;
-
-        // Conjectured FRI security; see the ethSTARK paper.
-        let fri_field_bits = F::Extension::order().bits() as usize;
-        let fri_query_security_bits = num_query_rounds * rate_bits + proof_of_work_bits as usize;
-        let fri_security_bits = fri_field_bits.min(fri_query_security_bits);
         assert!(
-            fri_security_bits >= security_bits,
+            self.config.conjectured_security_bits::<F, D>() >= self.config.security_bits,
             "FRI params fall short of target security"
         );
     }
 
+    /// Copies the plan-time state of this builder (gates, copy constraints, generators, contexts,
+    /// ...), so that variants of a circuit can be built from a common prefix. Building consumes a
+    /// builder, so each variant must be built from its own snapshot. Gates and generators are
+    /// immutable once added and are shared between the copies.
+    pub fn snapshot(&self) -> Self {
+        CircuitBuilder {
+            config: self.config.clone(),
+            domain_separator: self.domain_separator.clone(),
+            gates: self.gates.clone(),
+            gate_instances: self.gate_instances.clone(),
+            public_inputs: self.public_inputs.clone(),
+            private_targets: self.private_targets.clone(),
+            virtual_target_index: self.virtual_target_index,
+            copy_constraints: self.copy_constraints.clone(),
+            context_log: self.context_log.clone(),
+            generators: self.generators.clone(),
+            generator_group_labels: self.generator_group_labels.clone(),
+            current_generator_group: self.current_generator_group,
+            generator_groups: self.generator_groups.clone(),
+            gate_groups: self.gate_groups.clone(),
+            slot_groups: self.slot_groups.clone(),
+            constants_to_targets: self.constants_to_targets.clone(),
+            targets_to_constants: self.targets_to_constants.clone(),
+            base_arithmetic_results: self.base_arithmetic_results.clone(),
+            pending_base_arithmetic_operations: self.pending_base_arithmetic_operations.clone(),
+            arithmetic_results: self.arithmetic_results.clone(),
+            current_slots: self.current_slots.clone(),
+            constant_generators: self.constant_generators.clone(),
+            lookup_rows: self.lookup_rows.clone(),
+            lut_to_lookups: self.lut_to_lookups.clone(),
+            luts: self.luts.clone(),
+            challenge_dependent_columns: self.challenge_dependent_columns.clone(),
+            goal_common_data: self.goal_common_data.clone(),
+            verifier_data_public_input: self.verifier_data_public_input.clone(),
+        }
+    }
+
     pub fn set_domain_separator(&mut self, separator: Vec<F>) {
         assert!(self.domain_separator.is_none());
         self.domain_separator = Some(separator);
@@ -268,6 +323,16 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.gate_instances.len()
     }
 
+    /// The number of `ConstantGate`s `build` will add to hold the constants used so far, beyond
+    /// those which fit in the spare constant wires of existing gates.
+    pub fn num_constant_gates(&self) -> usize {
+        let missing = self
+            .constants_to_targets
+            .len()
+            .saturating_sub(self.constant_generators.len());
+        ceil_div_usize(missing, self.config.num_constants)
+    }
+
     /// Registers the given target as a public input.
     pub fn register_public_input(&mut self, target: Target) {
         self.public_inputs.push(target);
@@ -283,6 +348,13 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.public_inputs.len()
     }
 
+    /// Requires that none of `targets` is a public input, or is connected to one. This is checked
+    /// when building the circuit, once all copy constraints are known. Without zero knowledge the
+    /// openings leak the witness, so building also requires `zero_knowledge` in the config.
+    pub fn keep_private(&mut self, targets: &[Target]) {
+        self.private_targets.extend_from_slice(targets);
+    }
+
     /// Adds lookup rows for a lookup table.
     pub fn add_lookup_rows(
         &mut self,
@@ -430,6 +502,33 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         verifier_data
     }
 
+    /// Adds `num_columns` columns, generated by `generator` from the wires and challenges drawn
+    /// after committing to the wires, and constrained by `generator` on every row.
+    ///
+    /// All challenge-dependent columns share a single extra oracle, committed right after the
+    /// wires. In zero-knowledge mode the oracle is salted, but no rows are reserved to blind the
+    /// columns' openings, so a generator must only be used with witnesses which are not secret or
+    /// blind its columns itself.
+    pub fn add_challenge_dependent_columns(
+        &mut self,
+        num_columns: usize,
+        generator: Box<dyn ChallengeDependentGenerator<F, D>>,
+    ) {
+        assert!(num_columns > 0, "{} has no columns", generator.id());
+        assert!(
+            generator.degree() <= self.config.max_quotient_degree_factor + 1,
+            "{} has constraints of degree {}, but our CircuitConfig supports at most {}",
+            generator.id(),
+            generator.degree(),
+            self.config.max_quotient_degree_factor + 1
+        );
+        self.challenge_dependent_columns
+            .push(ChallengeDependentColumns {
+                num_columns,
+                generator: Arc::from(generator),
+            });
+    }
+
     /// Adds a gate to the circuit, and returns its index.
     pub fn add_gate<G: Gate<F, D>>(&mut self, gate_type: G, mut constants: Vec<F>) -> usize {
         self.check_gate_compatibility(&gate_type);
@@ -464,6 +563,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             gate_ref,
             constants,
         });
+        self.gate_groups.push(self.current_generator_group);
 
         row
     }
@@ -483,6 +583,13 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             gate.num_constants(),
             self.config.num_constants
         );
+        assert!(
+            gate.num_next_row_wires() <= self.config.num_wires,
+            "{:?} reads {} wires of the next row, but our CircuitConfig has only {}",
+            gate.id(),
+            gate.num_next_row_wires(),
+            self.config.num_wires
+        );
     }
 
     /// Adds a gate type to the set of gates to be used in this circuit. This can be useful
@@ -547,12 +654,40 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     pub fn add_generators(&mut self, generators: Vec<WitnessGeneratorRef<F, D>>) {
-        self.generators.extend(generators);
+        self.generator_groups.resize(
+            self.generators.len() + generators.len(),
+            self.current_generator_group,
+        );
+        self.generators
+            .extend(generators.into_iter().map(|g| Arc::from(g.0)));
     }
 
     pub fn add_simple_generator<G: SimpleGenerator<F, D>>(&mut self, generator: G) {
-        self.generators
-            .push(WitnessGeneratorRef::new(generator.adapter()));
+        self.generators.push(Arc::new(generator.adapter()));
+        self.generator_groups.push(self.current_generator_group);
+    }
+
+    /// Places the generators of everything added from now on, including those of gates, in the
+    /// group with the given label, until the group is changed or cleared. The witness values of
+    /// each group can then be generated separately, see `generate_partial_witness_for_group`.
+    ///
+    /// Arithmetic operations buffered by `config.optimize_arithmetic`, lookups, and constants are
+    /// placed at build time, outside of any group. Memoized operations belong to the group which
+    /// first added them, so groups shouldn't repeat each other's operations.
+    pub fn set_generator_group(&mut self, label: &str) {
+        let index = match self.generator_group_labels.iter().position(|l| l == label) {
+            Some(index) => index,
+            None => {
+                self.generator_group_labels.push(label.to_string());
+                self.generator_group_labels.len() - 1
+            }
+        };
+        self.current_generator_group = Some(index);
+    }
+
+    /// Places the generators of everything added from now on outside of any group.
+    pub fn clear_generator_group(&mut self) {
+        self.current_generator_group = None;
     }
 
     /// Returns a routable target with a value of 0.
@@ -647,6 +782,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.targets_to_constants.get(&target).cloned()
     }
 
+    /// The number of times each target appears in a copy constraint, as a public input or in the
+    /// watch list of a generator.
+    pub(crate) fn target_use_counts(&self) -> HashMap<Target, usize> {
+        let mut uses = HashMap::new();
+        let copy_constraint_targets = self
+            .copy_constraints
+            .iter()
+            .flat_map(|c| [c.pair.0, c.pair.1]);
+        let watched_targets = self.generators.iter().flat_map(|g| g.watch_list());
+        for target in copy_constraint_targets
+            .chain(self.public_inputs.iter().copied())
+            .chain(watched_targets)
+        {
+            *uses.entry(target).or_insert(0) += 1;
+        }
+        uses
+    }
+
     /// If the given [`ExtensionTarget`] is a constant (i.e. it was created by the
     /// `constant_extension(F)` method), returns its constant value. Otherwise, returns `None`.
     pub fn target_as_constant_ext(&self, target: ExtensionTarget<D>) -> Option<F::Extension> {
@@ -787,6 +940,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             self.add_gate(gate, constants.to_vec());
             (num_gates, 0)
         };
+        if self.gate_groups[gate_idx] != self.current_generator_group {
+            self.slot_groups
+                .insert((gate_idx, slot_idx), self.current_generator_group);
+        }
         let current_slot = &mut self.current_slots.get_mut(&gate_ref).unwrap().current_slot;
         if slot_idx == num_ops - 1 {
             // We've filled up the slots at this index.
@@ -799,7 +956,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         (gate_idx, slot_idx)
     }
 
-    fn fri_params(&self, degree_bits: usize) -> FriParams {
+    fn fri_params(&self, degree_bits: DegreeBits) -> FriParams {
         self.config
             .fri_config
             .fri_params(degree_bits, self.config.zero_knowledge)
@@ -827,7 +984,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let degree_bits_estimate = log2_strict(degree_estimate);
         let fri_queries = self.config.fri_config.num_query_rounds;
         let arities: Vec<usize> = self
-            .fri_params(degree_bits_estimate)
+            .fri_params(DegreeBits::new(degree_bits_estimate))
             .reduction_arity_bits
             .iter()
             .map(|x| 1 << x)
@@ -836,8 +993,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let final_poly_coeffs: usize = degree_estimate / arities.iter().product::<usize>();
         let fri_openings = fri_queries * (1 + D * total_fri_folding_points + D * final_poly_coeffs);
 
-        // We add D for openings at zeta.
-        let regular_poly_openings = D + fri_openings;
+        // We add D for openings at zeta, and D more if some wires are also opened at g * zeta.
+        let has_next_row_wires = self.gates.iter().any(|g| g.0.num_next_row_wires() > 0);
+        let regular_poly_openings = D * (1 + usize::from(has_next_row_wires)) + fri_openings;
         // We add 2 * D for openings at zeta and g * zeta.
         let z_openings = 2 * D + fri_openings;
 
@@ -878,6 +1036,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Blinds the witness by appending rows of random values, rather than by masking each witness
+    /// polynomial with a random multiple of the vanishing polynomial. The blinding must hide every
+    /// value revealed outside the trace domain, which includes every FRI query opening, so a mask
+    /// would need as many random coefficients as there are blinding rows here. The masked
+    /// polynomials would then exceed the trace degree by the same amount, doubling the FRI degree
+    /// bound at the same boundary while also raising the quotient degree, so masking saves nothing.
     fn blind(&mut self) {
         let (regular_poly_openings, z_openings) = self.blinding_counts();
         info!(
@@ -1024,20 +1188,65 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     ) -> CircuitData<F, C, D> {
         let (circuit_data, success) = self.try_build_with_options(commit_to_sigma);
         if !success {
-            panic!("Failed to build circuit");
+            panic!("Failed to build circuit: the circuit does not match the expected common data");
         }
         circuit_data
     }
 
+    /// Like `build`, but returns an error rather than panicking if the builder was misused, e.g. if
+    /// the circuit does not match the common data expected by cyclic recursion.
+    pub fn try_build<C: GenericConfig<D, F = F>>(self) -> Result<CircuitData<F, C, D>> {
+        let (circuit_data, success) = self.build_inner(true, None)?;
+        ensure!(
+            success,
+            "The circuit does not match the common data expected by cyclic recursion"
+        );
+        Ok(circuit_data)
+    }
+
     pub fn try_build_with_options<C: GenericConfig<D, F = F>>(
-        mut self,
+        self,
         commit_to_sigma: bool,
     ) -> (CircuitData<F, C, D>, bool) {
+        self.build_inner(commit_to_sigma, None)
+            .unwrap_or_else(|e| panic!("Failed to build circuit: {e}"))
+    }
+
+    /// Builds a variant of `base`, e.g. one which only differs from it in some constants. If both
+    /// circuits have the same wiring, the LDEs of the sigma polynomials are reused from `base`
+    /// rather than recomputed, and only the constant polynomials are committed to afresh.
+    /// Otherwise, the circuit is built as by `build`.
+    pub fn build_variant<C: GenericConfig<D, F = F>>(
+        self,
+        base: &CircuitData<F, C, D>,
+    ) -> CircuitData<F, C, D> {
+        let (circuit_data, success) = self
+            .build_inner(true, Some(&base.prover_only))
+            .unwrap_or_else(|e| panic!("Failed to build circuit: {e}"));
+        if !success {
+            panic!("Failed to build circuit: the circuit does not match the expected common data");
+        }
+        circuit_data
+    }
+
+    fn build_inner<C: GenericConfig<D, F = F>>(
+        mut self,
+        commit_to_sigma: bool,
+        base: Option<&ProverOnlyCircuitData<F, C, D>>,
+    ) -> Result<(CircuitData<F, C, D>, bool)> {
+        ensure!(
+            self.verifier_data_public_inputs_are_last(),
+            "Public inputs were registered after `add_verifier_data_public_inputs`"
+        );
+
         let mut timing = TimingTree::new("preprocess", Level::Trace);
 
         #[cfg(feature = "std")]
         let start = Instant::now();
 
+        // Everything placed from here on serves the whole circuit.
+        self.clear_generator_group();
+
         let rate_bits = self.config.fri_config.rate_bits;
         let cap_height = self.config.fri_config.cap_height;
         // Total number of LUTs.
@@ -1060,6 +1269,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         // Place LUT-related gates.
         self.add_all_lookups();
 
+        // Place the arithmetic operations buffered for fusion or dead code elimination.
+        self.place_pending_arithmetic_operations();
+
         // Make sure we have enough constant generators. If not, add a `ConstantGate`.
         while self.constants_to_targets.len() > self.constant_generators.len() {
             self.add_gate(
@@ -1097,8 +1309,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let degree = self.gate_instances.len();
         debug!("Degree after blinding & padding: {}", degree);
         let degree_bits = log2_strict(degree);
-        let fri_params = self.fri_params(degree_bits);
-        assert!(
+        let fri_params = self.fri_params(DegreeBits::new(degree_bits));
+        ensure!(
             fri_params.total_arities() <= degree_bits + rate_bits - cap_height,
             "FRI total reduction arity is too large.",
         );
@@ -1135,22 +1347,62 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             self.sigma_vecs(&k_is, &subgroup)
         );
 
+        ensure!(
+            self.private_targets.is_empty() || self.config.zero_knowledge,
+            "Targets kept private require a zero-knowledge config"
+        );
+        let public_reps = self
+            .public_inputs
+            .iter()
+            .map(|&t| forest.parents[forest.target_index(t)])
+            .collect::<HashSet<_>>();
+        for &target in &self.private_targets {
+            ensure!(
+                !public_reps.contains(&forest.parents[forest.target_index(target)]),
+                "{:?} must be kept private, but is connected to a public input",
+                target
+            );
+        }
+
         // Precompute FFT roots.
         let max_fft_points = 1 << (degree_bits + max(rate_bits, log2_ceil(quotient_degree_factor)));
         let fft_root_table = fft_root_table(max_fft_points);
 
-        let constants_sigmas_commitment = if commit_to_sigma {
-            let constants_sigmas_vecs = [constant_vecs, sigma_vecs.clone()].concat();
-            PolynomialBatch::<F, C, D>::from_values(
-                constants_sigmas_vecs,
-                rate_bits,
-                PlonkOracle::CONSTANTS_SIGMAS.blinding,
-                cap_height,
-                &mut timing,
-                Some(&fft_root_table),
-            )
-        } else {
-            PolynomialBatch::<F, C, D>::default()
+        let sigmas = transpose_poly_values(sigma_vecs.clone());
+        // The sigma LDEs of `base` can be reused if it has the same wiring, and hence the same
+        // sigmas, and the same layout of constants.
+        let base_commitment = base
+            .filter(|base| {
+                let commitment = &base.constants_sigmas_commitment;
+                base.sigmas == sigmas
+                    && commitment.polynomials.len() == num_constants + sigma_vecs.len()
+                    && commitment.degree_log == degree_bits
+                    && commitment.rate_bits == rate_bits
+            })
+            .map(|base| &base.constants_sigmas_commitment);
+        let constants_sigmas_commitment = match base_commitment {
+            Some(base_commitment) if commit_to_sigma => timed!(
+                timing,
+                "commit to constants, reusing sigma LDEs",
+                base_commitment.with_leading_values(
+                    constant_vecs,
+                    cap_height,
+                    &mut timing,
+                    Some(&fft_root_table),
+                )
+            ),
+            _ if commit_to_sigma => {
+                let constants_sigmas_vecs = [constant_vecs, sigma_vecs].concat();
+                PolynomialBatch::<F, C, D>::from_values(
+                    constants_sigmas_vecs,
+                    rate_bits,
+                    PlonkOracle::CONSTANTS_SIGMAS.blinding,
+                    cap_height,
+                    &mut timing,
+                    Some(&fft_root_table),
+                )
+            }
+            _ => PolynomialBatch::<F, C, D>::default(),
         };
 
         // Map between gates where not all generators are used and the gate's number of used generators.
@@ -1160,25 +1412,30 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             .flat_map(|current_slot| current_slot.current_slot.values().copied())
             .collect::<HashMap<_, _>>();
 
-        // Add gate generators.
-        self.add_generators(
-            self.gate_instances
-                .iter()
-                .enumerate()
-                .flat_map(|(index, gate)| {
-                    let mut gens = gate.gate_ref.0.generators(index, &gate.constants);
-                    // Remove unused generators, if any.
-                    if let Some(&op) = incomplete_gates.get(&index) {
-                        gens.drain(op..);
-                    }
-                    gens
-                })
-                .collect(),
-        );
+        let mut generators = core::mem::take(&mut self.generators)
+            .into_iter()
+            .map(WitnessGeneratorRef::shared)
+            .collect::<Vec<_>>();
+        // Add gate generators, each in the group of its gate, or of its operation for gates
+        // placed with `find_slot`.
+        for (index, gate) in self.gate_instances.iter().enumerate() {
+            let mut gens = gate.gate_ref.0.generators(index, &gate.constants);
+            // Remove unused generators, if any.
+            if let Some(&op) = incomplete_gates.get(&index) {
+                gens.drain(op..);
+            }
+            self.generator_groups.extend((0..gens.len()).map(|op| {
+                self.slot_groups
+                    .get(&(index, op))
+                    .copied()
+                    .unwrap_or(self.gate_groups[index])
+            }));
+            generators.extend(gens);
+        }
 
         // Index generator indices by their watched targets.
         let mut generator_indices_by_watches = BTreeMap::new();
-        for (i, generator) in self.generators.iter().enumerate() {
+        for (i, generator) in generators.iter().enumerate() {
             for watch in generator.0.watch_list() {
                 let watch_index = forest.target_index(watch);
                 let watch_rep_index = forest.parents[watch_index];
@@ -1237,6 +1494,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             num_lookup_polys,
             num_lookup_selectors,
             luts: self.luts,
+            challenge_dependent_columns: self.challenge_dependent_columns,
         };
 
         let mut success = true;
@@ -1249,10 +1507,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
 
         let prover_only = ProverOnlyCircuitData::<F, C, D> {
-            generators: self.generators,
+            generators,
             generator_indices_by_watches,
+            generator_group_labels: self.generator_group_labels,
+            generator_groups: self.generator_groups,
             constants_sigmas_commitment,
-            sigmas: transpose_poly_values(sigma_vecs),
+            sigmas,
             subgroup,
             public_inputs: self.public_inputs,
             representative_map: forest.parents,
@@ -1270,14 +1530,37 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         timing.print();
         #[cfg(feature = "std")]
         debug!("Building circuit took {}s", start.elapsed().as_secs_f32());
-        (
+        Ok((
             CircuitData {
                 prover_only,
                 verifier_only,
                 common,
             },
             success,
-        )
+        ))
+    }
+
+    /// Whether the public inputs registered by `add_verifier_data_public_inputs`, if any, are the
+    /// last ones, where cyclic recursion expects them.
+    fn verifier_data_public_inputs_are_last(&self) -> bool {
+        self.verifier_data_public_input
+            .as_ref()
+            .map_or(true, |verifier_data| {
+                let targets = verifier_data
+                    .circuit_digest
+                    .elements
+                    .iter()
+                    .chain(
+                        verifier_data
+                            .constants_sigmas_cap
+                            .0
+                            .iter()
+                            .flat_map(|h| &h.elements),
+                    )
+                    .copied()
+                    .collect::<Vec<_>>();
+                self.public_inputs.ends_with(&targets)
+            })
     }
 
     /// Builds a "full circuit", with both prover and verifier data.
@@ -1306,3 +1589,157 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         circuit_data.verifier_data()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Field;
+    use crate::fri::reduction_strategies::FriReductionStrategy;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    #[test]
+    fn test_snapshot() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let prefix = |builder: &mut CircuitBuilder<F, D>| {
+            let x = builder.add_virtual_target();
+            let y = builder.add_virtual_target();
+            let z = builder.mul(x, y);
+            builder.register_public_input(z);
+            // The generator of the inverse is shared with the snapshot.
+            builder.inverse(z);
+            (x, y, z)
+        };
+
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let (x, y, z) = prefix(&mut builder);
+        let mut variant = builder.snapshot();
+        let w = variant.add_const(z, F::ONE);
+        variant.register_public_input(w);
+
+        let mut fresh = CircuitBuilder::<F, D>::new(config);
+        prefix(&mut fresh);
+
+        let data = builder.build::<C>();
+        let variant_data = variant.build::<C>();
+        let fresh_data = fresh.build::<C>();
+
+        // Mutating the snapshot leaves the original untouched.
+        assert_eq!(data.common, fresh_data.common);
+        assert_eq!(data.verifier_only, fresh_data.verifier_only);
+        assert_eq!(data.common.num_public_inputs, 1);
+        assert_eq!(variant_data.common.num_public_inputs, 2);
+        assert_ne!(
+            data.verifier_only.circuit_digest,
+            variant_data.verifier_only.circuit_digest
+        );
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        pw.set_target(y, F::from_canonical_u64(5));
+        let proof = data.prove(pw.clone())?;
+        assert_eq!(proof.public_inputs, vec![F::from_canonical_u64(15)]);
+        data.verify(proof)?;
+        let proof = variant_data.prove(pw)?;
+        assert_eq!(
+            proof.public_inputs,
+            vec![F::from_canonical_u64(15), F::from_canonical_u64(16)]
+        );
+        variant_data.verify(proof)
+    }
+
+    #[test]
+    fn test_try_build_goal_mismatch() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_target();
+        builder.register_public_input(x);
+        let goal = builder.snapshot().build::<C>().common;
+        assert!(builder.try_build::<C>().is_ok());
+
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        builder.goal_common_data = Some(goal);
+        assert!(builder.try_build::<C>().is_err());
+    }
+
+    #[test]
+    fn test_try_build_misuse() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let err =
+            |builder: CircuitBuilder<F, D>| builder.try_build::<C>().err().unwrap().to_string();
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        builder.add_verifier_data_public_inputs();
+        let x = builder.add_virtual_public_input();
+        builder.register_public_input(x);
+        assert!(err(builder).contains("after `add_verifier_data_public_inputs`"));
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        builder.keep_private(&[x]);
+        assert!(err(builder).contains("require a zero-knowledge config"));
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![4; 8]);
+        let builder = CircuitBuilder::<F, D>::new(config);
+        assert!(err(builder).contains("FRI total reduction arity is too large"));
+    }
+
+    #[test]
+    fn test_build_variant() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let builder_with_constants = |a: u64, b: u64| {
+            let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+            let x = builder.add_virtual_public_input();
+            let y = builder.mul_const(F::from_canonical_u64(a), x);
+            let b = builder.constant(F::from_canonical_u64(b));
+            let z = builder.add(y, b);
+            builder.register_public_input(z);
+            (builder, x)
+        };
+
+        let (builder, _) = builder_with_constants(1000, 2000);
+        let base = builder.build::<C>();
+
+        let variants = (0..5)
+            .map(|i| {
+                let (builder, x) = builder_with_constants(1001 + i, 2001 + i);
+                (builder.build_variant(&base), x)
+            })
+            .collect::<Vec<_>>();
+        for (i, (data, x)) in variants.iter().enumerate() {
+            // The wiring is shared, but the commitment matches that of a from-scratch build.
+            assert_eq!(data.prover_only.sigmas, base.prover_only.sigmas);
+            let (builder, _) = builder_with_constants(1001 + i as u64, 2001 + i as u64);
+            assert_eq!(data.verifier_only, builder.build::<C>().verifier_only);
+
+            let mut pw = PartialWitness::new();
+            pw.set_target(*x, F::TWO);
+            let proof = data.prove(pw)?;
+            let expected = F::from_canonical_u64(2 * (1001 + i as u64) + 2001 + i as u64);
+            assert_eq!(proof.public_inputs, vec![F::TWO, expected]);
+            for (j, (other, _)) in variants.iter().enumerate() {
+                let result = other.verify(proof.clone());
+                assert_eq!(result.is_ok(), i == j);
+            }
+            assert!(base.verify(proof).is_err());
+        }
+
+        Ok(())
+    }
+}
@@ -13,6 +13,8 @@
 //! This is useful to allow even small devices to verify plonky2 proofs.
 
 use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::ops::{Range, RangeFrom};
@@ -25,30 +27,45 @@ use crate::field::extension::Extendable;
 use crate::field::fft::FftRootTable;
 use crate::field::types::Field;
 use crate::fri::oracle::PolynomialBatch;
+use crate::fri::query_index::QueryIndexSampler;
 use crate::fri::reduction_strategies::FriReductionStrategy;
 use crate::fri::structure::{
     FriBatchInfo, FriBatchInfoTarget, FriInstanceInfo, FriInstanceInfoTarget, FriOracleInfo,
     FriPolynomialInfo,
 };
-use crate::fri::{FriConfig, FriParams};
+use crate::fri::{FriConfig, FriParams, FriPowStrategy};
 use crate::gates::gate::GateRef;
 use crate::gates::lookup::Lookup;
 use crate::gates::lookup_table::LookupTable;
 use crate::gates::selectors::SelectorsInfo;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::merkle_proofs::{verify_merkle_proof, MerkleProof};
 use crate::hash::merkle_tree::MerkleCap;
+use crate::iop::challenger::Challenger;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::generator::{generate_partial_witness, WitnessGeneratorRef};
 use crate::iop::target::Target;
 use crate::iop::witness::{PartialWitness, PartitionWitness};
+use crate::plonk::challenge_dependent::ChallengeDependentColumns;
 use crate::plonk::circuit_builder::CircuitBuilder;
-use crate::plonk::config::{GenericConfig, Hasher};
-use crate::plonk::plonk_common::PlonkOracle;
-use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
-use crate::plonk::prover::prove;
-use crate::plonk::verifier::verify;
+use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::generated_witness::GeneratedWitness;
+use crate::plonk::plonk_common::{salt_size, PlonkOracle};
+use crate::plonk::proof::{
+    CompressedProofWithPublicInputs, ProofWithCachedDigest, ProofWithPublicInputs,
+};
+use crate::plonk::prover::{
+    generate_witness, prove, prove_from_witness, prove_with_buffers, prove_with_options,
+    prove_with_query_index_sampler, ProverBuffers, ProverOptions,
+};
+use crate::plonk::transcript::{TranscriptVersionPolicy, TRANSCRIPT_VERSION};
+use crate::plonk::verifier::{
+    verify, verify_with_options, verify_with_pow_context, verify_with_query_index_sampler,
+    verify_with_transcript_versions, VerificationReport, VerifierOptions,
+};
+use crate::util::log_size::{DegreeBits, LdeBits};
 use crate::util::serialization::{
-    Buffer, GateSerializer, IoResult, Read, WitnessGeneratorSerializer, Write,
+    Buffer, ConfigLayout, GateSerializer, IoResult, Read, WitnessGeneratorSerializer, Write,
 };
 use crate::util::timing::TimingTree;
 
@@ -72,6 +89,18 @@ pub struct CircuitConfig {
     /// Whether to use a dedicated gate for base field arithmetic, rather than using a single gate
     /// for both base field and extension field arithmetic.
     pub use_base_arithmetic_gate: bool,
+    /// Whether to fuse chains of base arithmetic operations into fewer [`ArithmeticGate`]
+    /// operations when the circuit is built. Fusing changes the circuit's layout, and so its
+    /// digest. Operations are only placed at build time, so [`CircuitBuilder::num_gates`] doesn't
+    /// count them before then.
+    ///
+    /// [`ArithmeticGate`]: crate::gates::arithmetic_base::ArithmeticGate
+    pub optimize_arithmetic: bool,
+    /// Whether to drop, when the circuit is built, the base arithmetic operations whose results
+    /// are never used, directly or through other operations, by a copy constraint, a public input
+    /// or a generator. As with `optimize_arithmetic`, operations are then only placed at build
+    /// time. Other gates are always kept, since they may constrain their inputs.
+    pub eliminate_dead_code: bool,
     pub security_bits: usize,
     /// The number of challenge points to generate, for IOPs that have soundness errors of (roughly)
     /// `degree / |F|`.
@@ -96,6 +125,13 @@ impl CircuitConfig {
         self.num_wires - self.num_routed_wires
     }
 
+    /// The conjectured security of FRI with this config, in bits, when challenges are drawn from
+    /// the degree `D` extension of `F`; see the ethSTARK paper.
+    pub fn conjectured_security_bits<F: RichField + Extendable<D>, const D: usize>(&self) -> usize {
+        let fri_field_bits = F::Extension::order().bits() as usize;
+        fri_field_bits.min(self.fri_config.conjectured_query_security_bits())
+    }
+
     /// A typical recursion config, without zero-knowledge, targeting ~100 bit security.
     pub const fn standard_recursion_config() -> Self {
         Self {
@@ -103,6 +139,8 @@ impl CircuitConfig {
             num_routed_wires: 80,
             num_constants: 2,
             use_base_arithmetic_gate: true,
+            optimize_arithmetic: false,
+            eliminate_dead_code: false,
             security_bits: 100,
             num_challenges: 2,
             zero_knowledge: false,
@@ -110,9 +148,10 @@ impl CircuitConfig {
             fri_config: FriConfig {
                 rate_bits: 3,
                 cap_height: 4,
-                proof_of_work_bits: 16,
+                pow_strategy: FriPowStrategy::Single(16),
                 reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
                 num_query_rounds: 28,
+                commit_final_poly: false,
             },
         }
     }
@@ -137,6 +176,20 @@ impl CircuitConfig {
             ..Self::standard_recursion_config()
         }
     }
+
+    /// A recursion config with a high rate and few query rounds, for proofs which are smaller and
+    /// cheaper to verify, at the cost of proving time. Also targets ~100 bit security.
+    pub fn high_rate_recursion_config() -> Self {
+        let standard = Self::standard_recursion_config();
+        Self {
+            fri_config: FriConfig {
+                rate_bits: 7,
+                num_query_rounds: 12,
+                ..standard.fri_config
+            },
+            ..standard
+        }
+    }
 }
 
 /// Mock circuit data to only do witness generation without generating a proof.
@@ -194,10 +247,154 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         )
     }
 
+    /// Runs the witness generators, returning the full witness, see `prove_from_witness`.
+    pub fn generate_witness(
+        &self,
+        inputs: PartialWitness<F>,
+    ) -> Result<GeneratedWitness<F, C::Hasher>> {
+        generate_witness::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+        )
+    }
+
+    /// Proves from a witness returned by `generate_witness`, skipping witness generation.
+    pub fn prove_from_witness(
+        &self,
+        witness: &GeneratedWitness<F, C::Hasher>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_from_witness::<F, C, D>(
+            witness,
+            &self.prover_only,
+            &self.common,
+            &mut TimingTree::default(),
+        )
+    }
+
+    pub fn prove_with_options(
+        &self,
+        inputs: PartialWitness<F>,
+        options: &ProverOptions<F>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_options::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            options,
+            &mut TimingTree::default(),
+        )
+    }
+
+    /// Proves, deriving the FRI query indices with `query_index_sampler`. The proof only verifies
+    /// under the same sampler, see `verify_with_query_index_sampler`, and not recursively.
+    pub fn prove_with_query_index_sampler(
+        &self,
+        inputs: PartialWitness<F>,
+        query_index_sampler: Arc<dyn QueryIndexSampler<F, C::Hasher>>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_query_index_sampler::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &ProverOptions::default(),
+            query_index_sampler,
+            &mut TimingTree::default(),
+        )
+    }
+
+    /// Proves, reusing the allocations in `buffers` across calls.
+    pub fn prove_with_buffers(
+        &self,
+        inputs: PartialWitness<F>,
+        buffers: &mut ProverBuffers<F, C::Hasher>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_buffers::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &ProverOptions::default(),
+            buffers,
+            &mut TimingTree::default(),
+        )
+    }
+
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> Result<()> {
         verify::<F, C, D>(proof_with_pis, &self.verifier_only, &self.common)
     }
 
+    /// Verifies a proof generated with `ProverOptions::pow_context` set to `pow_context`.
+    pub fn verify_with_pow_context(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        pow_context: &[F],
+    ) -> Result<()> {
+        verify_with_pow_context::<F, C, D>(
+            proof_with_pis,
+            &self.verifier_only,
+            &self.common,
+            pow_context,
+        )
+    }
+
+    /// Verifies a proof generated with `prove_with_query_index_sampler` and the same sampler.
+    pub fn verify_with_query_index_sampler(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        query_index_sampler: Arc<dyn QueryIndexSampler<F, C::Hasher>>,
+    ) -> Result<()> {
+        verify_with_query_index_sampler::<F, C, D>(
+            proof_with_pis,
+            &self.verifier_only,
+            &self.common,
+            query_index_sampler,
+        )
+    }
+
+    /// Verifies a proof with the given options, which may check fewer FRI query rounds than the
+    /// config prescribes; see `VerifierOptions::max_queries_to_check`.
+    pub fn verify_with_options(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        options: &VerifierOptions,
+    ) -> Result<VerificationReport> {
+        self.verify_with_options_and_challenger(
+            proof_with_pis,
+            options,
+            Challenger::with_transcript::<C::Transcript>(),
+        )
+    }
+
+    /// Like `verify_with_options`, but draws the challenges from `challenger`, which must be
+    /// configured like the prover's, for instance with the same PoW context and query index
+    /// sampler.
+    pub fn verify_with_options_and_challenger(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        options: &VerifierOptions,
+        challenger: Challenger<F, C::Hasher>,
+    ) -> Result<VerificationReport> {
+        verify_with_options::<F, C, D>(
+            proof_with_pis,
+            &self.verifier_only,
+            &self.common,
+            options,
+            challenger,
+            &[TRANSCRIPT_VERSION],
+        )
+    }
+
+    /// Verifies a proof using its cached public inputs hash. If `paranoid` is set, the hash is
+    /// recomputed and the proof rejected if its public inputs no longer match the cache.
+    pub fn verify_cached(
+        &self,
+        proof_with_digest: ProofWithCachedDigest<F, C, D>,
+        paranoid: bool,
+    ) -> Result<()> {
+        proof_with_digest.verify(&self.verifier_only, &self.common, paranoid)
+    }
+
     pub fn verify_compressed(
         &self,
         compressed_proof_with_pis: CompressedProofWithPublicInputs<F, C, D>,
@@ -228,6 +425,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         VerifierCircuitData {
             verifier_only: verifier_only.clone(),
             common: common.clone(),
+            accepted_transcript_versions: TranscriptVersionPolicy::default(),
         }
     }
 
@@ -290,6 +488,62 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             &mut TimingTree::default(),
         )
     }
+
+    /// Runs the witness generators, returning the full witness, see `prove_from_witness`.
+    pub fn generate_witness(
+        &self,
+        inputs: PartialWitness<F>,
+    ) -> Result<GeneratedWitness<F, C::Hasher>> {
+        generate_witness::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+        )
+    }
+
+    /// Proves from a witness returned by `generate_witness`, skipping witness generation.
+    pub fn prove_from_witness(
+        &self,
+        witness: &GeneratedWitness<F, C::Hasher>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_from_witness::<F, C, D>(
+            witness,
+            &self.prover_only,
+            &self.common,
+            &mut TimingTree::default(),
+        )
+    }
+
+    pub fn prove_with_options(
+        &self,
+        inputs: PartialWitness<F>,
+        options: &ProverOptions<F>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_options::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            options,
+            &mut TimingTree::default(),
+        )
+    }
+
+    /// Proves, reusing the allocations in `buffers` across calls.
+    pub fn prove_with_buffers(
+        &self,
+        inputs: PartialWitness<F>,
+        buffers: &mut ProverBuffers<F, C::Hasher>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_buffers::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &ProverOptions::default(),
+            buffers,
+            &mut TimingTree::default(),
+        )
+    }
 }
 
 /// Circuit data required by the prover.
@@ -301,6 +555,9 @@ pub struct VerifierCircuitData<
 > {
     pub verifier_only: VerifierOnlyCircuitData<C, D>,
     pub common: CommonCircuitData<F, D>,
+    /// The transcript versions of the proofs accepted by `verify` and `verify_compressed`. This is
+    /// a choice of the verifier rather than part of the circuit, so it is not serialized.
+    pub accepted_transcript_versions: TranscriptVersionPolicy,
 }
 
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
@@ -320,15 +577,138 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         buffer.read_verifier_circuit_data(gate_serializer)
     }
 
+    /// Deserializes verifier data whose config was written with the given layout, e.g. by a past
+    /// release.
+    pub fn from_bytes_with_layout(
+        bytes: Vec<u8>,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        layout: ConfigLayout,
+    ) -> IoResult<Self> {
+        let mut buffer = Buffer::new(&bytes);
+        buffer.read_verifier_circuit_data_with_layout(gate_serializer, layout)
+    }
+
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> Result<()> {
-        verify::<F, C, D>(proof_with_pis, &self.verifier_only, &self.common)
+        verify_with_transcript_versions::<F, C, D>(
+            proof_with_pis,
+            &self.verifier_only,
+            &self.common,
+            &self.accepted_transcript_versions.versions(),
+        )
+    }
+
+    /// Verifies a proof generated with `ProverOptions::pow_context` set to `pow_context`. Only
+    /// the current transcript version is accepted.
+    pub fn verify_with_pow_context(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        pow_context: &[F],
+    ) -> Result<()> {
+        verify_with_pow_context::<F, C, D>(
+            proof_with_pis,
+            &self.verifier_only,
+            &self.common,
+            pow_context,
+        )
+    }
+
+    /// Verifies a proof whose FRI query indices were derived with `query_index_sampler`. Only the
+    /// current transcript version is accepted.
+    pub fn verify_with_query_index_sampler(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        query_index_sampler: Arc<dyn QueryIndexSampler<F, C::Hasher>>,
+    ) -> Result<()> {
+        verify_with_query_index_sampler::<F, C, D>(
+            proof_with_pis,
+            &self.verifier_only,
+            &self.common,
+            query_index_sampler,
+        )
+    }
+
+    /// Verifies a proof with the given options, which may check fewer FRI query rounds than the
+    /// config prescribes; see `VerifierOptions::max_queries_to_check`. Any of the
+    /// `accepted_transcript_versions` is accepted.
+    pub fn verify_with_options(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        options: &VerifierOptions,
+    ) -> Result<VerificationReport> {
+        self.verify_with_options_and_challenger(
+            proof_with_pis,
+            options,
+            Challenger::with_transcript::<C::Transcript>(),
+        )
+    }
+
+    /// Like `verify_with_options`, but draws the challenges from `challenger`, which must be
+    /// configured like the prover's, for instance with the same PoW context and query index
+    /// sampler.
+    pub fn verify_with_options_and_challenger(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        options: &VerifierOptions,
+        challenger: Challenger<F, C::Hasher>,
+    ) -> Result<VerificationReport> {
+        verify_with_options::<F, C, D>(
+            proof_with_pis,
+            &self.verifier_only,
+            &self.common,
+            options,
+            challenger,
+            &self.accepted_transcript_versions.versions(),
+        )
+    }
+
+    /// Verifies a proof using its cached public inputs hash. If `paranoid` is set, the hash is
+    /// recomputed and the proof rejected if its public inputs no longer match the cache.
+    pub fn verify_cached(
+        &self,
+        proof_with_digest: ProofWithCachedDigest<F, C, D>,
+        paranoid: bool,
+    ) -> Result<()> {
+        proof_with_digest.verify(&self.verifier_only, &self.common, paranoid)
     }
 
     pub fn verify_compressed(
         &self,
         compressed_proof_with_pis: CompressedProofWithPublicInputs<F, C, D>,
     ) -> Result<()> {
-        compressed_proof_with_pis.verify(&self.verifier_only, &self.common)
+        compressed_proof_with_pis.verify_with_transcript_versions(
+            &self.verifier_only,
+            &self.common,
+            &self.accepted_transcript_versions.versions(),
+        )
+    }
+
+    /// The leaf committing to this circuit in a Merkle tree of allowed circuits: the digest of the
+    /// common data followed by the circuit digest, so that the leaf binds the circuit's constants
+    /// and wiring as well as its shape.
+    pub fn vk_membership_leaf(&self) -> Vec<F> {
+        [
+            self.common.digest::<C>().to_vec(),
+            self.verifier_only.circuit_digest.to_vec(),
+        ]
+        .concat()
+    }
+
+    /// Checks that this circuit is the `vk_index`-th leaf of the Merkle tree of allowed circuits
+    /// with root `vk_root`, then verifies the proof.
+    pub fn verify_with_vk_membership(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        vk_index: usize,
+        vk_merkle_proof: &MerkleProof<F, C::Hasher>,
+        vk_root: <C::Hasher as Hasher<F>>::Hash,
+    ) -> Result<()> {
+        verify_merkle_proof(
+            self.vk_membership_leaf(),
+            vk_index,
+            vk_root,
+            vk_merkle_proof,
+        )?;
+        self.verify(proof_with_pis)
     }
 }
 
@@ -343,6 +723,10 @@ pub struct ProverOnlyCircuitData<
     /// Generator indices (within the `Vec` above), indexed by the representative of each target
     /// they watch.
     pub generator_indices_by_watches: BTreeMap<usize, Vec<usize>>,
+    /// The labels of the generator groups, see `CircuitBuilder::set_generator_group`.
+    pub generator_group_labels: Vec<String>,
+    /// For each generator, the index in `generator_group_labels` of its group, if any.
+    pub generator_groups: Vec<Option<usize>>,
     /// Commitments to the constants polynomials and sigma polynomials.
     pub constants_sigmas_commitment: PolynomialBatch<F, C, D>,
     /// The transpose of the list of sigma polynomials.
@@ -368,6 +752,21 @@ pub struct ProverOnlyCircuitData<
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     ProverOnlyCircuitData<F, C, D>
 {
+    /// The indices of the generators in the group with the given label, see
+    /// `CircuitBuilder::set_generator_group`.
+    pub fn generator_group(&self, label: &str) -> Option<Vec<usize>> {
+        let group = self.generator_group_index(label)?;
+        Some(
+            (0..self.generators.len())
+                .filter(|&i| self.generator_groups[i] == Some(group))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn generator_group_index(&self, label: &str) -> Option<usize> {
+        self.generator_group_labels.iter().position(|l| l == label)
+    }
+
     pub fn to_bytes(
         &self,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
@@ -449,6 +848,10 @@ pub struct CommonCircuitData<F: RichField + Extendable<D>, const D: usize> {
 
     /// The stored lookup tables.
     pub luts: Vec<LookupTable>,
+
+    /// Columns committed in an extra round after the wires, which may depend on challenges drawn
+    /// from the wires commitment.
+    pub challenge_dependent_columns: Vec<ChallengeDependentColumns<F, D>>,
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
@@ -466,20 +869,86 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         buffer.read_common_circuit_data(gate_serializer)
     }
 
-    pub const fn degree_bits(&self) -> usize {
+    /// A digest of this common data: the configuration, the circuit's shape and its gates, as
+    /// identified by their `id`s. It does not depend on the circuit's constants or wiring, which
+    /// are bound by `VerifierOnlyCircuitData::circuit_digest` instead.
+    pub fn digest<C: GenericConfig<D, F = F>>(&self) -> <C::Hasher as Hasher<F>>::Hash {
+        let mut bytes = Vec::new();
+        self.write_digest_preimage(&mut bytes)
+            .expect("Writing to a Vec cannot fail");
+        // The length comes first so that the zero padding of the last chunk can't collide with
+        // trailing zero bytes. Chunks are 7 bytes since 8 bytes would allow collisions.
+        let elements = core::iter::once(F::from_canonical_usize(bytes.len()))
+            .chain(bytes.chunks(7).map(|chunk| {
+                let mut arr = [0; 8];
+                arr[..chunk.len()].copy_from_slice(chunk);
+                F::from_canonical_u64(u64::from_le_bytes(arr))
+            }))
+            .collect::<Vec<_>>();
+        C::Hasher::hash_no_pad(&elements)
+    }
+
+    fn write_digest_preimage(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
+        buffer.write_circuit_config(&self.config)?;
+        buffer.write_fri_params(&self.fri_params)?;
+        buffer.write_selectors_info(&self.selectors_info)?;
+        buffer.write_usize(self.quotient_degree_factor)?;
+        buffer.write_usize(self.num_gate_constraints)?;
+        buffer.write_usize(self.num_constants)?;
+        buffer.write_usize(self.num_public_inputs)?;
+        buffer.write_usize(self.k_is.len())?;
+        buffer.write_field_vec(&self.k_is)?;
+        buffer.write_usize(self.num_partial_products)?;
+        buffer.write_usize(self.num_lookup_polys)?;
+        buffer.write_usize(self.num_lookup_selectors)?;
+        buffer.write_usize(self.luts.len())?;
+        for lut in &self.luts {
+            buffer.write_lut(lut)?;
+        }
+        buffer.write_usize(self.gates.len())?;
+        for gate in &self.gates {
+            let id = gate.0.id();
+            buffer.write_usize(id.len())?;
+            buffer.write_all(id.as_bytes())?;
+        }
+        buffer.write_usize(self.num_challenge_dependent_columns())?;
+        buffer.write_usize(self.num_challenge_dependent_challenges())
+    }
+
+    pub const fn degree_bits(&self) -> DegreeBits {
         self.fri_params.degree_bits
     }
 
     pub const fn degree(&self) -> usize {
-        1 << self.degree_bits()
+        self.degree_bits().size()
+    }
+
+    /// The log of the number of rows in the execution trace. Same as `degree_bits`.
+    pub const fn trace_degree_bits(&self) -> DegreeBits {
+        self.degree_bits()
+    }
+
+    /// The number of rows in the execution trace. Same as `degree`.
+    pub const fn trace_degree(&self) -> usize {
+        self.degree()
+    }
+
+    /// The log of the size of the low-degree extension domain, i.e. `degree_bits + rate_bits`.
+    pub const fn lde_degree_bits(&self) -> LdeBits {
+        self.fri_params.lde_bits()
     }
 
     pub const fn lde_size(&self) -> usize {
         self.fri_params.lde_size()
     }
 
+    /// The height of the initial FRI Merkle trees, i.e. the number of bits in a query index.
+    pub const fn fri_height(&self) -> LdeBits {
+        self.lde_degree_bits()
+    }
+
     pub fn lde_generator(&self) -> F {
-        F::primitive_root_of_unity(self.degree_bits() + self.config.fri_config.rate_bits)
+        F::primitive_root_of_unity(self.lde_degree_bits().get())
     }
 
     pub fn constraint_degree(&self) -> usize {
@@ -533,7 +1002,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         };
 
         // The Z polynomials are also opened at g * zeta.
-        let g = F::Extension::primitive_root_of_unity(self.degree_bits());
+        let g = F::Extension::primitive_root_of_unity(self.degree_bits().get());
         let zeta_next = g * zeta;
         let zeta_next_batch = FriBatchInfo {
             point: zeta_next,
@@ -551,6 +1020,19 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         &self,
         builder: &mut CircuitBuilder<F, D>,
         zeta: ExtensionTarget<D>,
+    ) -> FriInstanceInfoTarget<D> {
+        // The Z polynomials are also opened at g * zeta.
+        let g = F::primitive_root_of_unity(self.degree_bits().get());
+        let zeta_next = builder.mul_const_extension(g, zeta);
+        self.get_fri_instance_target_at(zeta, zeta_next)
+    }
+
+    /// Like `get_fri_instance_target`, but with the point `g * zeta` computed by the caller, e.g.
+    /// when the generator `g` depends on a witnessed degree.
+    pub(crate) fn get_fri_instance_target_at(
+        &self,
+        zeta: ExtensionTarget<D>,
+        zeta_next: ExtensionTarget<D>,
     ) -> FriInstanceInfoTarget<D> {
         // All polynomials are opened at zeta.
         let zeta_batch = FriBatchInfoTarget {
@@ -558,9 +1040,6 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
             polynomials: self.fri_all_polys(),
         };
 
-        // The Z polynomials are also opened at g * zeta.
-        let g = F::primitive_root_of_unity(self.degree_bits());
-        let zeta_next = builder.mul_const_extension(g, zeta);
         let zeta_next_batch = FriBatchInfoTarget {
             point: zeta_next,
             polynomials: self.fri_next_batch_polys(),
@@ -573,8 +1052,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         }
     }
 
-    fn fri_oracles(&self) -> Vec<FriOracleInfo> {
-        vec![
+    pub(crate) fn fri_oracles(&self) -> Vec<FriOracleInfo> {
+        let mut oracles = vec![
             FriOracleInfo {
                 num_polys: self.num_preprocessed_polys(),
                 blinding: PlonkOracle::CONSTANTS_SIGMAS.blinding,
@@ -591,7 +1070,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
                 num_polys: self.num_quotient_polys(),
                 blinding: PlonkOracle::QUOTIENT.blinding,
             },
-        ]
+        ];
+        if self.has_challenge_dependent_columns() {
+            oracles.push(FriOracleInfo {
+                num_polys: self.num_challenge_dependent_columns(),
+                blinding: PlonkOracle::CHALLENGE_DEPENDENT.blinding,
+            });
+        }
+        oracles
     }
 
     fn fri_preprocessed_polys(&self) -> Vec<FriPolynomialInfo> {
@@ -631,7 +1117,27 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
 
     /// Returns polynomials that require evaluation at `zeta` and `g * zeta`.
     fn fri_next_batch_polys(&self) -> Vec<FriPolynomialInfo> {
-        [self.fri_zs_polys(), self.fri_lookup_polys()].concat()
+        [
+            self.fri_zs_polys(),
+            self.fri_lookup_polys(),
+            self.fri_challenge_dependent_polys(),
+            self.fri_next_row_wire_polys(),
+        ]
+        .concat()
+    }
+
+    fn fri_next_row_wire_polys(&self) -> Vec<FriPolynomialInfo> {
+        FriPolynomialInfo::from_range(PlonkOracle::WIRES.index, 0..self.num_next_row_wires())
+    }
+
+    /// The number of wires whose values on the next row are read by some gate, which are the
+    /// first wires of each row.
+    pub fn num_next_row_wires(&self) -> usize {
+        self.gates
+            .iter()
+            .map(|gate| gate.0.num_next_row_wires())
+            .max()
+            .unwrap_or(0)
     }
 
     fn fri_quotient_polys(&self) -> Vec<FriPolynomialInfo> {
@@ -646,10 +1152,74 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
                 ..self.num_zs_partial_products_polys() + self.num_all_lookup_polys(),
         )
     }
-    pub(crate) const fn num_quotient_polys(&self) -> usize {
+
+    fn fri_challenge_dependent_polys(&self) -> Vec<FriPolynomialInfo> {
+        FriPolynomialInfo::from_range(
+            PlonkOracle::CHALLENGE_DEPENDENT.index,
+            0..self.num_challenge_dependent_columns(),
+        )
+    }
+
+    pub fn has_challenge_dependent_columns(&self) -> bool {
+        !self.challenge_dependent_columns.is_empty()
+    }
+
+    /// The number of challenge-dependent columns, across all generators.
+    pub fn num_challenge_dependent_columns(&self) -> usize {
+        self.challenge_dependent_columns
+            .iter()
+            .map(|cd| cd.num_columns)
+            .sum()
+    }
+
+    /// The number of challenges drawn before committing to the challenge-dependent columns.
+    pub fn num_challenge_dependent_challenges(&self) -> usize {
+        self.challenge_dependent_columns
+            .iter()
+            .map(|cd| cd.generator.num_challenges())
+            .sum()
+    }
+
+    pub(crate) fn num_challenge_dependent_constraints(&self) -> usize {
+        self.challenge_dependent_columns
+            .iter()
+            .map(|cd| cd.generator.num_constraints())
+            .sum()
+    }
+
+    /// The number of quotient polynomial chunks, across all challenges.
+    pub const fn num_quotient_polys(&self) -> usize {
         self.config.num_challenges * self.quotient_degree_factor
     }
 
+    /// The number of leaf elements in the constants and sigmas oracle. It is never salted.
+    pub const fn salted_constants_len(&self) -> usize {
+        self.num_preprocessed_polys()
+    }
+
+    /// The number of leaf elements in the wires oracle, including any salt.
+    pub const fn salted_wires_len(&self) -> usize {
+        self.config.num_wires + salt_size(self.fri_params.hiding)
+    }
+
+    /// The number of leaf elements in the `Z`s, partial products and lookup oracle, including any
+    /// salt.
+    pub const fn salted_zs_len(&self) -> usize {
+        self.num_zs_partial_products_polys()
+            + self.num_all_lookup_polys()
+            + salt_size(self.fri_params.hiding)
+    }
+
+    /// The number of leaf elements in the quotient oracle, including any salt.
+    pub const fn salted_quotient_len(&self) -> usize {
+        self.num_quotient_polys() + salt_size(self.fri_params.hiding)
+    }
+
+    /// The number of leaf elements in the challenge-dependent oracle, including any salt.
+    pub fn salted_challenge_dependent_len(&self) -> usize {
+        self.num_challenge_dependent_columns() + salt_size(self.fri_params.hiding)
+    }
+
     fn fri_all_polys(&self) -> Vec<FriPolynomialInfo> {
         [
             self.fri_preprocessed_polys(),
@@ -657,6 +1227,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
             self.fri_zs_partial_products_polys(),
             self.fri_quotient_polys(),
             self.fri_lookup_polys(),
+            self.fri_challenge_dependent_polys(),
         ]
         .concat()
     }
@@ -674,3 +1245,111 @@ pub struct VerifierCircuitTarget {
     /// seed Fiat-Shamir.
     pub circuit_digest: HashOutTarget,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::iop::witness::WitnessWrite;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn common_data(config: CircuitConfig) -> CommonCircuitData<F, D> {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.mul(x, x);
+        builder.register_public_input(y);
+        builder.build::<C>().common
+    }
+
+    #[test]
+    fn test_degree_accessors() {
+        let cd = common_data(CircuitConfig::standard_recursion_config());
+        assert_eq!(cd.trace_degree_bits(), DegreeBits::new(2));
+        assert_eq!(cd.trace_degree(), 4);
+        assert_eq!(cd.lde_degree_bits(), LdeBits::new(5));
+        assert_eq!(cd.lde_size(), 32);
+        assert_eq!(cd.fri_height(), LdeBits::new(5));
+        assert_eq!(cd.quotient_degree(), 32);
+        assert_eq!(cd.num_quotient_polys(), 16);
+        assert_eq!(cd.salted_constants_len(), 84);
+        assert_eq!(cd.salted_wires_len(), 135);
+        assert_eq!(cd.salted_zs_len(), 20);
+        assert_eq!(cd.salted_quotient_len(), 16);
+    }
+
+    #[test]
+    fn test_degree_accessors_zk() {
+        let cd = common_data(CircuitConfig::standard_recursion_zk_config());
+        assert_eq!(cd.trace_degree_bits(), DegreeBits::new(14));
+        assert_eq!(cd.trace_degree(), 1 << 14);
+        assert_eq!(cd.lde_degree_bits(), LdeBits::new(17));
+        assert_eq!(cd.lde_size(), 1 << 17);
+        assert_eq!(cd.fri_height(), LdeBits::new(17));
+        assert_eq!(cd.quotient_degree(), 1 << 17);
+        assert_eq!(cd.num_quotient_polys(), 16);
+        // The constants and sigmas oracle is never salted; the others carry `SALT_SIZE` extra
+        // elements.
+        assert_eq!(cd.salted_constants_len(), 84);
+        assert_eq!(cd.salted_wires_len(), 139);
+        assert_eq!(cd.salted_zs_len(), 24);
+        assert_eq!(cd.salted_quotient_len(), 20);
+    }
+
+    /// Builds a circuit squaring its input `num_squarings` times.
+    fn squaring_circuit(num_squarings: usize) -> CircuitData<F, C, D> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut x = builder.add_virtual_public_input();
+        for _ in 0..num_squarings {
+            x = builder.mul(x, x);
+        }
+        builder.register_public_input(x);
+        builder.build::<C>()
+    }
+
+    #[test]
+    fn test_verify_with_vk_membership() -> Result<()> {
+        let circuits = (1..=5).map(squaring_circuit).collect::<Vec<_>>();
+        let verifier_data = circuits
+            .iter()
+            .map(|data| data.verifier_data())
+            .collect::<Vec<_>>();
+        assert_ne!(
+            verifier_data[0].vk_membership_leaf(),
+            verifier_data[1].vk_membership_leaf()
+        );
+
+        // Only the first four circuits are allowed.
+        let tree = MerkleTree::<F, <C as GenericConfig<D>>::Hasher>::new(
+            verifier_data[..4]
+                .iter()
+                .map(|vd| vd.vk_membership_leaf())
+                .collect(),
+            0,
+        );
+        let root = tree.cap.0[0];
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(circuits[1].prover_only.public_inputs[0], F::TWO);
+        let proof = circuits[1].prove(pw)?;
+        verifier_data[1].verify_with_vk_membership(proof.clone(), 1, &tree.prove(1), root)?;
+        // The membership proof must be for the circuit's own leaf.
+        assert!(verifier_data[1]
+            .verify_with_vk_membership(proof, 2, &tree.prove(2), root)
+            .is_err());
+
+        // The fifth circuit is not a member, whichever proof of membership is given.
+        let mut pw = PartialWitness::new();
+        pw.set_target(circuits[4].prover_only.public_inputs[0], F::TWO);
+        let proof = circuits[4].prove(pw)?;
+        for i in 0..4 {
+            assert!(verifier_data[4]
+                .verify_with_vk_membership(proof.clone(), i, &tree.prove(i), root)
+                .is_err());
+        }
+        verifier_data[4].verify(proof)
+    }
+}
@@ -0,0 +1,231 @@
+//! Verification of archived proofs generated by earlier releases.
+//!
+//! Proofs are grouped into [`ProofEra`]s, within which the verifier logic is unchanged. All eras
+//! share the field, hash and Merkle primitives, and the verifier itself; what differs from one era
+//! to the next is dispatched on here, so that [`verify`](crate::plonk::verifier::verify) only has to
+//! know about the current era. So far the eras differ in the transcript deriving the challenges
+//! and in the [`ConfigLayout`] of their verifier data, and share the layout of the opening set.
+//!
+//! [`verify_legacy`] takes serialized artifacts, for circuits using [`PoseidonGoldilocksConfig`]
+//! over the quadratic extension.
+
+use anyhow::{anyhow, ensure, Result};
+
+use crate::plonk::circuit_data::VerifierCircuitData;
+use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use crate::plonk::proof_header::ProofHeader;
+use crate::plonk::transcript::{LEGACY_TRANSCRIPT_VERSION, TRANSCRIPT_VERSION};
+use crate::plonk::verifier::verify_with_transcript_versions;
+use crate::util::serialization::header::ARTIFACT_MAGIC;
+use crate::util::serialization::{Buffer, ConfigLayout, DefaultGateSerializer, Read};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// A range of releases whose proofs are verified the same way.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ProofEra {
+    /// Proofs generated before transcripts were versioned. Such proofs were serialized without a
+    /// [`ProofHeader`], and their verifier data with [`ConfigLayout::V1`].
+    V1,
+    /// Proofs using version 1 of the transcript, which absorbs its version first.
+    V2,
+}
+
+impl ProofEra {
+    /// All eras, oldest first.
+    pub const ALL: [Self; 2] = [Self::V1, Self::V2];
+
+    /// The era of proofs generated by this release.
+    pub const CURRENT: Self = Self::V2;
+
+    /// The era of the proof with the given header. Proofs without a header are of era
+    /// [`ProofEra::V1`], or of a later era if they were serialized without one.
+    pub fn from_header(header: &ProofHeader) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|era| era.transcript_version() == header.transcript_version)
+            .ok_or_else(|| {
+                anyhow!(
+                    "No proof era uses transcript version {}",
+                    header.transcript_version
+                )
+            })
+    }
+
+    /// The version of the transcript deriving the challenges of proofs of this era.
+    pub const fn transcript_version(self) -> u32 {
+        match self {
+            Self::V1 => LEGACY_TRANSCRIPT_VERSION,
+            Self::V2 => TRANSCRIPT_VERSION,
+        }
+    }
+
+    /// The layout of the verifier data of circuits of this era.
+    pub const fn config_layout(self) -> ConfigLayout {
+        match self {
+            Self::V1 => ConfigLayout::V1,
+            Self::V2 => ConfigLayout::V2,
+        }
+    }
+}
+
+/// Verifies a proof of the given era, against verifier data serialized with
+/// [`VerifierCircuitData::to_bytes`] and [`DefaultGateSerializer`] by a release of that era.
+///
+/// The proof may be prefixed by a [`ProofHeader`], which must then be that of an uncompressed
+/// proof of the given era, for the circuit of the verifier data. Otherwise the proof is read as a
+/// bare `ProofWithPublicInputs`, as serialized by releases predating proof headers.
+pub fn verify_legacy(era: ProofEra, proof_bytes: &[u8], verifier_data_bytes: &[u8]) -> Result<()> {
+    let verifier_data = VerifierCircuitData::<F, C, D>::from_bytes_with_layout(
+        verifier_data_bytes.to_vec(),
+        &DefaultGateSerializer,
+        era.config_layout(),
+    )
+    .map_err(anyhow::Error::msg)?;
+    let common_data = &verifier_data.common;
+
+    let body = if proof_bytes.starts_with(&ARTIFACT_MAGIC) {
+        let (header, body) = ProofHeader::split(proof_bytes)?;
+        let header_era = ProofEra::from_header(&header)?;
+        ensure!(
+            header_era == era,
+            "The proof header says the proof is of era {:?}, not {:?}",
+            header_era,
+            era
+        );
+        header.check::<F, C, D>(false, common_data)?;
+        body
+    } else {
+        proof_bytes
+    };
+
+    let mut buffer = Buffer::new(body);
+    let proof = buffer
+        .read_proof_with_public_inputs::<F, C, D>(common_data)
+        .map_err(anyhow::Error::msg)?;
+    verify_with_transcript_versions::<F, C, D>(
+        proof,
+        &verifier_data.verifier_only,
+        common_data,
+        &[era.transcript_version()],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::proof::ProofWithPublicInputs;
+    use crate::plonk::transcript::TranscriptVersionPolicy;
+    use crate::util::test_fixtures::{
+        fixture_circuit, fixture_config, fixture_fri_config, prove_fixture,
+    };
+
+    /// A proof archived by a release of the given era, along with the verifier data of its
+    /// circuit, the fixture circuit.
+    struct Fixture {
+        era: ProofEra,
+        verifier_data: &'static [u8],
+        proof: &'static [u8],
+    }
+
+    /// The `V1` fixtures were generated by the last release of that era, and stand for proofs
+    /// which can no longer be generated. The `V2` fixtures were generated by this release.
+    fn fixtures() -> [Fixture; 2] {
+        [
+            Fixture {
+                era: ProofEra::V1,
+                verifier_data: include_bytes!("fixtures/compat_v1_verifier_data.bin"),
+                proof: include_bytes!("fixtures/compat_v1_proof.bin"),
+            },
+            Fixture {
+                era: ProofEra::V2,
+                verifier_data: include_bytes!("fixtures/compat_v2_verifier_data.bin"),
+                proof: include_bytes!("fixtures/compat_v2_proof.bin"),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_fixtures_verify_only_in_their_era() {
+        for fixture in fixtures() {
+            for era in ProofEra::ALL {
+                let result = verify_legacy(era, fixture.proof, fixture.verifier_data);
+                assert_eq!(
+                    result.is_ok(),
+                    era == fixture.era,
+                    "{:?} fixture verified under {:?}: {:?}",
+                    fixture.era,
+                    era,
+                    result
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixtures_are_of_the_fixture_circuit() {
+        let (data, _) = fixture_circuit(fixture_config(false, fixture_fri_config()), 3);
+        for fixture in fixtures() {
+            let verifier_data = VerifierCircuitData::<F, C, D>::from_bytes_with_layout(
+                fixture.verifier_data.to_vec(),
+                &DefaultGateSerializer,
+                fixture.era.config_layout(),
+            )
+            .unwrap();
+            assert_eq!(verifier_data.verifier_only, data.verifier_only);
+            assert_eq!(verifier_data.common, data.common);
+        }
+    }
+
+    #[test]
+    fn test_era_dispatch_on_header() -> Result<()> {
+        let (data, x) = fixture_circuit(fixture_config(false, fixture_fri_config()), 3);
+        let verifier_data = data
+            .verifier_data()
+            .to_bytes(&DefaultGateSerializer)
+            .unwrap();
+        let proof = prove_fixture(&data, x)?.to_bytes_with_header(&data.common);
+
+        let era = ProofEra::from_header(&ProofHeader::peek(&proof)?)?;
+        assert_eq!(era, ProofEra::CURRENT);
+        verify_legacy(era, &proof, &verifier_data)?;
+
+        // Without its header, the proof is still only valid under its own transcript.
+        let (_, body) = ProofHeader::split(&proof)?;
+        verify_legacy(ProofEra::V2, body, &verifier_data)?;
+        let mut verifier_data = data.verifier_data();
+        verifier_data.accepted_transcript_versions =
+            TranscriptVersionPolicy::Only(alloc::vec![LEGACY_TRANSCRIPT_VERSION]);
+        assert!(verifier_data
+            .verify(ProofWithPublicInputs::from_bytes(
+                body.to_vec(),
+                &data.common
+            )?)
+            .is_err());
+        Ok(())
+    }
+
+    /// Regenerates the fixtures of the current era. Those of past eras can't be regenerated.
+    #[cfg(feature = "std")]
+    #[test]
+    #[ignore]
+    fn regenerate_compat_fixtures() -> Result<()> {
+        use crate::util::test_fixtures::write_fixture;
+
+        let (data, x) = fixture_circuit(fixture_config(false, fixture_fri_config()), 3);
+        let stem = format!("plonk/fixtures/compat_{:?}", ProofEra::CURRENT).to_lowercase();
+        write_fixture(
+            &format!("{stem}_verifier_data.bin"),
+            data.verifier_data()
+                .to_bytes(&DefaultGateSerializer)
+                .unwrap(),
+        )?;
+        write_fixture(
+            &format!("{stem}_proof.bin"),
+            prove_fixture(&data, x)?.to_bytes_with_header(&data.common),
+        )?;
+        Ok(())
+    }
+}
@@ -3,8 +3,9 @@
 //! This module defines a [`Hasher`] trait as well as its recursive
 //! counterpart [`AlgebraicHasher`] for in-circuit hashing. It also
 //! provides concrete configurations, one fully recursive leveraging
-//! the Poseidon hash function both internally and natively, and one
-//! mixing Poseidon internally and truncated Keccak externally.
+//! the Poseidon hash function both internally and natively, one
+//! mixing Poseidon internally and truncated Keccak externally, and one
+//! mixing Poseidon internally and Poseidon over BN254 externally.
 
 use alloc::vec;
 use alloc::vec::Vec;
@@ -20,6 +21,8 @@ use crate::hash::hash_types::{HashOut, RichField};
 use crate::hash::hashing::PlonkyPermutation;
 use crate::hash::keccak::KeccakHash;
 use crate::hash::poseidon::PoseidonHash;
+use crate::hash::poseidon_bn254::PoseidonBN254Hash;
+use crate::iop::challenger::{Challenger, Transcript};
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 
@@ -30,6 +33,13 @@ pub trait GenericHashOut<F: RichField>:
     fn from_bytes(bytes: &[u8]) -> Self;
 
     fn to_vec(&self) -> Vec<F>;
+
+    /// Applies `f` to each field element of the hash. By default, the hash is returned unchanged,
+    /// as befits hashes which are not made of field elements, such as `BytesHash`; hashes made of
+    /// field elements must override it.
+    fn map_field<G: Fn(F) -> F>(self, _f: G) -> Self {
+        self
+    }
 }
 
 /// Trait for hash functions.
@@ -103,6 +113,10 @@ pub trait GenericConfig<const D: usize>:
     type Hasher: Hasher<Self::F>;
     /// Algebraic hash function used for the challenger and hashing public inputs.
     type InnerHasher: AlgebraicHasher<Self::F>;
+    /// Fiat-Shamir transcript from which the prover and the native verifier draw the challenges.
+    /// Only `Challenger<Self::F, Self::Hasher>`, the sponge, can be verified recursively. Any
+    /// `Challenger` selects the sponge over `Self::Hasher`, whatever its hasher parameter.
+    type Transcript: Transcript<Self::F>;
 }
 
 /// Configuration using Poseidon over the Goldilocks field.
@@ -113,6 +127,7 @@ impl GenericConfig<2> for PoseidonGoldilocksConfig {
     type FE = QuadraticExtension<Self::F>;
     type Hasher = PoseidonHash;
     type InnerHasher = PoseidonHash;
+    type Transcript = Challenger<Self::F, Self::Hasher>;
 }
 
 /// Configuration using truncated Keccak over the Goldilocks field.
@@ -123,4 +138,18 @@ impl GenericConfig<2> for KeccakGoldilocksConfig {
     type FE = QuadraticExtension<Self::F>;
     type Hasher = KeccakHash<25>;
     type InnerHasher = PoseidonHash;
+    type Transcript = Challenger<Self::F, Self::Hasher>;
+}
+
+/// Configuration using Poseidon over BN254 for Merkle trees and the challenger, for proofs which
+/// are wrapped in a SNARK over BN254. Like [`KeccakGoldilocksConfig`], it cannot be verified
+/// recursively.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PoseidonBN254GoldilocksConfig;
+impl GenericConfig<2> for PoseidonBN254GoldilocksConfig {
+    type F = GoldilocksField;
+    type FE = QuadraticExtension<Self::F>;
+    type Hasher = PoseidonBN254Hash;
+    type InnerHasher = PoseidonHash;
+    type Transcript = Challenger<Self::F, Self::Hasher>;
 }
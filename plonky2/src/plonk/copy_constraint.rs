@@ -3,6 +3,7 @@ use alloc::string::String;
 use crate::iop::target::Target;
 
 /// A named copy constraint.
+#[derive(Clone)]
 pub struct CopyConstraint {
     pub pair: (Target, Target),
     pub name: String,
@@ -0,0 +1,266 @@
+//! The full witness of a proving run, which can be persisted to analyze constraint failures
+//! offline, or to prove again later without running the witness generators, see
+//! [`prove_from_witness`](crate::plonk::prover::prove_from_witness).
+//!
+//! A serialized witness is laid out as
+//! - an [`ArtifactHeader`] of kind [`ArtifactKind::Witness`],
+//! - the digest of the circuit the witness was generated for,
+//! - the number of rows and the number of wires, each a little-endian `u32`,
+//! - the rows in order, each as its field elements. As padding rows are mostly all zeros, each
+//!   row with a nonzero element is preceded by the number of all-zero rows before it, as a
+//!   little-endian `u32`, and the number of all-zero rows at the end closes the encoding.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, ensure, Result};
+use zeroize::Zeroize;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+#[cfg(test)]
+use crate::iop::target::Target;
+use crate::iop::witness::MatrixWitness;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::Hasher;
+use crate::util::serialization::header::{ArtifactHeader, ArtifactKind};
+use crate::util::serialization::{Buffer, IoResult, Read, Remaining, Write};
+
+/// The values of every wire of a circuit, along with the digest of that circuit.
+#[derive(Clone, Debug)]
+pub struct GeneratedWitness<F: RichField, H: Hasher<F>> {
+    /// The `circuit_digest` of the circuit the witness was generated for.
+    pub circuit_digest: H::Hash,
+    pub witness: MatrixWitness<F>,
+}
+
+/// Wipes the wire values. The prover never wipes a `GeneratedWitness`, as it is owned by the
+/// caller; `zeroize` it once it is no longer needed.
+impl<F: RichField, H: Hasher<F>> Zeroize for GeneratedWitness<F, H> {
+    fn zeroize(&mut self) {
+        self.witness.zeroize();
+    }
+}
+
+impl<F: RichField, H: Hasher<F>> GeneratedWitness<F, H> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.write(&mut buffer)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
+        let columns = &self.witness.wire_values;
+        let degree = self.witness.degree();
+        ArtifactHeader::current(ArtifactKind::Witness).write(buffer);
+        buffer.write_hash::<F, H>(self.circuit_digest)?;
+        buffer.write_u32(degree as u32)?;
+        buffer.write_u32(columns.len() as u32)?;
+
+        let mut zero_rows = 0;
+        let mut row_values = Vec::with_capacity(columns.len());
+        for row in 0..degree {
+            row_values.clear();
+            row_values.extend(columns.iter().map(|column| column[row]));
+            if row_values.iter().all(|x| x.is_zero()) {
+                zero_rows += 1;
+                continue;
+            }
+            buffer.write_u32(zero_rows)?;
+            buffer.write_field_vec(&row_values)?;
+            zero_rows = 0;
+        }
+        buffer.write_u32(zero_rows)
+    }
+
+    /// Deserializes a witness written by [`Self::to_bytes`]. The witness must have the degree and
+    /// the number of wires of the given circuit, which is checked before allocating its values.
+    pub fn from_bytes<const D: usize>(
+        bytes: &[u8],
+        common_data: &CommonCircuitData<F, D>,
+    ) -> Result<Self>
+    where
+        F: Extendable<D>,
+    {
+        let body =
+            ArtifactHeader::split_kind(bytes, ArtifactKind::Witness).map_err(|e| anyhow!(e))?;
+
+        let mut buffer = Buffer::new(body);
+        let truncated = |_| anyhow!("The witness is truncated");
+        let circuit_digest = buffer.read_hash::<F, H>().map_err(truncated)?;
+        let degree = buffer.read_u32().map_err(truncated)? as usize;
+        let num_wires = buffer.read_u32().map_err(truncated)? as usize;
+        ensure!(
+            degree == common_data.degree() && num_wires == common_data.config.num_wires,
+            "The witness has {degree} rows of {num_wires} wires, but the circuit has {} rows of {} \
+             wires",
+            common_data.degree(),
+            common_data.config.num_wires
+        );
+
+        let mut wire_values = vec![vec![F::ZERO; degree]; num_wires];
+        let mut row = 0;
+        loop {
+            row += buffer.read_u32().map_err(truncated)? as usize;
+            ensure!(row <= degree, "The witness has more than {degree} rows");
+            if row == degree {
+                break;
+            }
+            let row_values = buffer.read_field_vec::<F>(num_wires).map_err(truncated)?;
+            for (column, value) in wire_values.iter_mut().zip(row_values) {
+                column[row] = value;
+            }
+            row += 1;
+        }
+        ensure!(
+            buffer.remaining() == 0,
+            "The witness has trailing bytes after its last row"
+        );
+
+        Ok(Self {
+            circuit_digest,
+            witness: MatrixWitness { wire_values },
+        })
+    }
+
+    /// Sets every wire copied from `target` to `value`. The copy constraints still hold, but the
+    /// gates using `target` generally no longer do; tests use this to check that unsatisfied
+    /// constraints are caught, where assigning `value` as an input would instead make witness
+    /// generation panic on a conflicting assignment.
+    #[cfg(test)]
+    pub(crate) fn overwrite_target(
+        &mut self,
+        target: Target,
+        value: F,
+        representative_map: &[usize],
+    ) {
+        let num_wires = self.witness.wire_values.len();
+        let degree = self.witness.degree();
+        let rep = representative_map[target.index(num_wires, degree)];
+        for (column, values) in self.witness.wire_values.iter_mut().enumerate() {
+            for (row, wire_value) in values.iter_mut().enumerate() {
+                if representative_map[Target::wire(row, column).index(num_wires, degree)] == rep {
+                    *wire_value = value;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::string::ToString;
+
+    use hashbrown::HashMap;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::hash::hash_types::HASH_OUT_BYTES;
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::util::serialization::header::ARTIFACT_HEADER_LEN;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// A circuit proving `y = lut(x)^2 + 1` for public `x` and `y`.
+    fn circuit() -> CircuitData<F, C, D> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let lut_index = builder.add_lookup_table_from_fn(|x| 3 * x, &[0, 1, 2, 3]);
+        let z = builder.add_lookup_from_index(x, lut_index);
+        let one = builder.one();
+        let y = builder.mul_add(z, z, one);
+        builder.register_public_input(y);
+        builder.build::<C>()
+    }
+
+    #[test]
+    fn test_reprove_from_reloaded_witness() -> Result<()> {
+        let data = circuit();
+        let mut pw = PartialWitness::new();
+        pw.set_target(data.prover_only.public_inputs[0], F::TWO);
+        let witness = data.generate_witness(pw)?;
+        // Generation randomizes some unused wires, but proving from a given witness is
+        // deterministic without zero knowledge.
+        let proof = data.prove_from_witness(&witness)?;
+
+        let bytes = witness.to_bytes();
+        let dense_len = data.common.degree() * data.common.config.num_wires * 8;
+        assert!(bytes.len() < dense_len);
+
+        let reloaded = GeneratedWitness::<F, <C as GenericConfig<D>>::Hasher>::from_bytes(
+            &bytes,
+            &data.common,
+        )?;
+        assert_eq!(reloaded.witness.wire_values, witness.witness.wire_values);
+        let reproof = data.prove_from_witness(&reloaded)?;
+        assert_eq!(
+            reproof.public_inputs,
+            vec![F::TWO, F::from_canonical_u64(37)]
+        );
+        assert_eq!(reproof, proof);
+        data.verify(reproof)
+    }
+
+    #[test]
+    fn test_witness_of_another_shape_rejected() -> Result<()> {
+        type H = <C as GenericConfig<D>>::Hasher;
+        let data = circuit();
+        let mut pw = PartialWitness::new();
+        pw.set_target(data.prover_only.public_inputs[0], F::ONE);
+        let mut bytes = data.generate_witness(pw)?.to_bytes();
+
+        // A forged degree must be rejected before the values are allocated.
+        let degree_offset = ARTIFACT_HEADER_LEN + HASH_OUT_BYTES;
+        bytes[degree_offset..degree_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = GeneratedWitness::<F, H>::from_bytes(&bytes, &data.common)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("rows"), "{err}");
+
+        let err = GeneratedWitness::<F, H>::from_bytes(&bytes[..3], &data.common)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("header"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_witness_of_another_circuit_rejected() -> Result<()> {
+        let data = circuit();
+        let mut pw = PartialWitness::new();
+        pw.set_target(data.prover_only.public_inputs[0], F::ONE);
+        let mut witness = data.generate_witness(pw)?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let other = builder.build::<C>();
+        assert!(other.prove_from_witness(&witness).is_err());
+
+        // So are the copy constraints: change one of two wires copied from each other.
+        let (num_wires, degree) = (data.common.config.num_wires, data.common.degree());
+        let mut wire_by_rep = HashMap::new();
+        let (row, column) = (0..degree)
+            .flat_map(|row| (0..num_wires).map(move |column| (row, column)))
+            .find(|&(row, column)| {
+                let index = Target::wire(row, column).index(num_wires, degree);
+                wire_by_rep
+                    .insert(data.prover_only.representative_map[index], ())
+                    .is_some()
+            })
+            .unwrap();
+        witness.witness.wire_values[column][row] += F::ONE;
+        assert!(data.prove_from_witness(&witness).is_err());
+
+        Ok(())
+    }
+}
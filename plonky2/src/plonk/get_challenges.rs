@@ -6,8 +6,9 @@ use hashbrown::HashSet;
 use super::circuit_builder::NUM_COINS_LOOKUP;
 use crate::field::extension::Extendable;
 use crate::field::polynomial::PolynomialCoeffs;
+use crate::fri::folding::compute_evaluation;
 use crate::fri::proof::{CompressedFriProof, FriChallenges, FriProof, FriProofTarget};
-use crate::fri::verifier::{compute_evaluation, fri_combine_initial, PrecomputedReducedOpenings};
+use crate::fri::verifier::{fri_combine_initial, PrecomputedReducedOpenings};
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
@@ -21,32 +22,59 @@ use crate::plonk::proof::{
     OpeningSetTarget, Proof, ProofChallenges, ProofChallengesTarget, ProofTarget,
     ProofWithPublicInputs, ProofWithPublicInputsTarget,
 };
+use crate::plonk::transcript::{
+    observe_transcript_version, observe_transcript_version_circuit, TRANSCRIPT_VERSION,
+};
+use crate::recursion::variable_degree::VariableDegreeBits;
 use crate::util::reverse_bits;
 
 fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    challenger: &mut Challenger<F, C::Hasher>,
     public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
     wires_cap: &MerkleCap<F, C::Hasher>,
+    challenge_dependent_cap: &MerkleCap<F, C::Hasher>,
     plonk_zs_partial_products_cap: &MerkleCap<F, C::Hasher>,
     quotient_polys_cap: &MerkleCap<F, C::Hasher>,
     openings: &OpeningSet<F, D>,
     commit_phase_merkle_caps: &[MerkleCap<F, C::Hasher>],
     final_poly: &PolynomialCoeffs<F::Extension>,
     pow_witness: F,
+    round_pow_witnesses: &[F],
     circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
     common_data: &CommonCircuitData<F, D>,
+    transcript_version: u32,
 ) -> anyhow::Result<ProofChallenges<F, D>> {
     let config = &common_data.config;
     let num_challenges = config.num_challenges;
 
-    let mut challenger = Challenger::<F, C::Hasher>::new();
     let has_lookup = common_data.num_lookup_polys != 0;
 
+    observe_transcript_version(challenger, transcript_version)?;
+
     // Observe the instance.
+    challenger.label(b"circuit_digest");
     challenger.observe_hash::<C::Hasher>(*circuit_digest);
+    challenger.label(b"public_inputs_hash");
     challenger.observe_hash::<C::InnerHasher>(public_inputs_hash);
 
+    challenger.label(b"wires_cap");
     challenger.observe_cap::<C::Hasher>(wires_cap);
+
+    // The challenge-dependent columns' round, if any, comes right after the wires.
+    let challenge_dependent_challenges = if common_data.has_challenge_dependent_columns() {
+        challenger.label(b"challenge_dependent_challenges");
+        let challenges =
+            challenger.get_n_challenges(common_data.num_challenge_dependent_challenges());
+        challenger.label(b"challenge_dependent_cap");
+        challenger.observe_cap::<C::Hasher>(challenge_dependent_cap);
+        challenges
+    } else {
+        vec![]
+    };
+
+    challenger.label(b"plonk_betas");
     let plonk_betas = challenger.get_n_challenges(num_challenges);
+    challenger.label(b"plonk_gammas");
     let plonk_gammas = challenger.get_n_challenges(num_challenges);
 
     // If there are lookups in the circuit, we should get delta challenges as well.
@@ -55,6 +83,7 @@ fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, cons
         let num_lookup_challenges = NUM_COINS_LOOKUP * num_challenges;
         let mut deltas = Vec::with_capacity(num_lookup_challenges);
         let num_additional_challenges = num_lookup_challenges - 2 * num_challenges;
+        challenger.label(b"plonk_deltas");
         let additional = challenger.get_n_challenges(num_additional_challenges);
         deltas.extend(&plonk_betas);
         deltas.extend(&plonk_gammas);
@@ -65,12 +94,17 @@ fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, cons
     };
 
     // `plonk_zs_partial_products_cap` also contains the commitment to lookup polynomials.
+    challenger.label(b"plonk_zs_partial_products_cap");
     challenger.observe_cap::<C::Hasher>(plonk_zs_partial_products_cap);
+    challenger.label(b"plonk_alphas");
     let plonk_alphas = challenger.get_n_challenges(num_challenges);
 
+    challenger.label(b"quotient_polys_cap");
     challenger.observe_cap::<C::Hasher>(quotient_polys_cap);
+    challenger.label(b"plonk_zeta");
     let plonk_zeta = challenger.get_extension_challenge::<D>();
 
+    challenger.label(b"openings");
     challenger.observe_openings(&openings.to_fri_openings());
 
     Ok(ProofChallenges {
@@ -78,11 +112,13 @@ fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, cons
         plonk_gammas,
         plonk_alphas,
         plonk_deltas,
+        challenge_dependent_challenges,
         plonk_zeta,
         fri_challenges: challenger.fri_challenges::<C, D>(
             commit_phase_merkle_caps,
             final_poly,
             pow_witness,
+            round_pow_witnesses,
             common_data.degree_bits(),
             &config.fri_config,
         ),
@@ -109,9 +145,46 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
         circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
         common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<ProofChallenges<F, D>> {
+        self.get_challenges_for_version(
+            public_inputs_hash,
+            circuit_digest,
+            common_data,
+            TRANSCRIPT_VERSION,
+        )
+    }
+
+    /// Computes all Fiat-Shamir challenges used in the Plonk proof, as derived by the given
+    /// transcript version.
+    pub fn get_challenges_for_version(
+        &self,
+        public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+        circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
+        common_data: &CommonCircuitData<F, D>,
+        transcript_version: u32,
+    ) -> anyhow::Result<ProofChallenges<F, D>> {
+        self.get_challenges_with_challenger(
+            &mut Challenger::with_transcript::<C::Transcript>(),
+            public_inputs_hash,
+            circuit_digest,
+            common_data,
+            transcript_version,
+        )
+    }
+
+    /// Computes all Fiat-Shamir challenges used in the Plonk proof, observing the transcript into
+    /// the given challenger.
+    pub(crate) fn get_challenges_with_challenger(
+        &self,
+        challenger: &mut Challenger<F, C::Hasher>,
+        public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+        circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
+        common_data: &CommonCircuitData<F, D>,
+        transcript_version: u32,
     ) -> anyhow::Result<ProofChallenges<F, D>> {
         let Proof {
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
@@ -120,21 +193,26 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
                     commit_phase_merkle_caps,
                     final_poly,
                     pow_witness,
+                    round_pow_witnesses,
                     ..
                 },
         } = &self.proof;
 
         get_challenges::<F, C, D>(
+            challenger,
             public_inputs_hash,
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
             commit_phase_merkle_caps,
             final_poly,
             *pow_witness,
+            round_pow_witnesses,
             circuit_digest,
             common_data,
+            transcript_version,
         )
     }
 }
@@ -148,9 +226,27 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
         circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
         common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<ProofChallenges<F, D>> {
+        self.get_challenges_for_version(
+            public_inputs_hash,
+            circuit_digest,
+            common_data,
+            TRANSCRIPT_VERSION,
+        )
+    }
+
+    /// Computes all Fiat-Shamir challenges used in the Plonk proof, as derived by the given
+    /// transcript version.
+    pub(crate) fn get_challenges_for_version(
+        &self,
+        public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+        circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
+        common_data: &CommonCircuitData<F, D>,
+        transcript_version: u32,
     ) -> anyhow::Result<ProofChallenges<F, D>> {
         let CompressedProof {
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
@@ -159,21 +255,26 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
                     commit_phase_merkle_caps,
                     final_poly,
                     pow_witness,
+                    round_pow_witnesses,
                     ..
                 },
         } = &self.proof;
 
         get_challenges::<F, C, D>(
+            &mut Challenger::with_transcript::<C::Transcript>(),
             public_inputs_hash,
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
             commit_phase_merkle_caps,
             final_poly,
             *pow_witness,
+            round_pow_witnesses,
             circuit_digest,
             common_data,
+            transcript_version,
         )
     }
 
@@ -202,13 +303,13 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             &self.proof.openings.to_fri_openings(),
             *fri_alpha,
         );
-        let log_n = common_data.degree_bits() + common_data.config.fri_config.rate_bits;
+        let log_n = common_data.lde_degree_bits().get();
         // Simulate the proof verification and collect the inferred elements.
         // The content of the loop is basically the same as the `fri_verifier_query_round` function.
         for &(mut x_index) in fri_query_indices {
             let mut subgroup_x = F::MULTIPLICATIVE_GROUP_GENERATOR
                 * F::primitive_root_of_unity(log_n).exp_u64(reverse_bits(x_index, log_n) as u64);
-            let mut old_eval = fri_combine_initial::<F, C, D>(
+            let mut old_eval = fri_combine_initial::<F, C::Hasher, D>(
                 &common_data.get_fri_instance(*plonk_zeta),
                 &self
                     .proof
@@ -258,14 +359,17 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         &mut self,
         public_inputs_hash: HashOutTarget,
         wires_cap: &MerkleCapTarget,
+        challenge_dependent_cap: &MerkleCapTarget,
         plonk_zs_partial_products_cap: &MerkleCapTarget,
         quotient_polys_cap: &MerkleCapTarget,
         openings: &OpeningSetTarget<D>,
         commit_phase_merkle_caps: &[MerkleCapTarget],
         final_poly: &PolynomialCoeffsExtTarget<D>,
         pow_witness: Target,
+        round_pow_witnesses: &[Target],
         inner_circuit_digest: HashOutTarget,
         inner_common_data: &CommonCircuitData<F, D>,
+        degree_bits: Option<&VariableDegreeBits>,
     ) -> ProofChallengesTarget<D>
     where
         C::Hasher: AlgebraicHasher<F>,
@@ -276,12 +380,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let mut challenger = RecursiveChallenger::<F, C::Hasher, D>::new(self);
         let has_lookup = inner_common_data.num_lookup_polys != 0;
 
+        observe_transcript_version_circuit(self, &mut challenger);
+
         // Observe the instance.
         challenger.observe_hash(&inner_circuit_digest);
         challenger.observe_hash(&public_inputs_hash);
 
         challenger.observe_cap(wires_cap);
 
+        let challenge_dependent_challenges = if inner_common_data.has_challenge_dependent_columns()
+        {
+            let challenges = challenger
+                .get_n_challenges(self, inner_common_data.num_challenge_dependent_challenges());
+            challenger.observe_cap(challenge_dependent_cap);
+            challenges
+        } else {
+            vec![]
+        };
+
         let plonk_betas = challenger.get_n_challenges(self, num_challenges);
         let plonk_gammas = challenger.get_n_challenges(self, num_challenges);
 
@@ -308,19 +424,34 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         challenger.observe_openings(&openings.to_fri_openings());
 
-        ProofChallengesTarget {
-            plonk_betas,
-            plonk_gammas,
-            plonk_alphas,
-            plonk_deltas,
-            plonk_zeta,
-            fri_challenges: challenger.fri_challenges(
+        let fri_challenges = match degree_bits {
+            Some(degree_bits) => challenger.fri_challenges_variable_degree(
+                self,
+                commit_phase_merkle_caps,
+                final_poly,
+                pow_witness,
+                round_pow_witnesses,
+                &inner_common_data.fri_params,
+                degree_bits,
+            ),
+            None => challenger.fri_challenges(
                 self,
                 commit_phase_merkle_caps,
                 final_poly,
                 pow_witness,
+                round_pow_witnesses,
                 &inner_common_data.config.fri_config,
             ),
+        };
+
+        ProofChallengesTarget {
+            plonk_betas,
+            plonk_gammas,
+            plonk_alphas,
+            plonk_deltas,
+            challenge_dependent_challenges,
+            plonk_zeta,
+            fri_challenges,
         }
     }
 }
@@ -333,11 +464,57 @@ impl<const D: usize> ProofWithPublicInputsTarget<D> {
         inner_circuit_digest: HashOutTarget,
         inner_common_data: &CommonCircuitData<F, D>,
     ) -> ProofChallengesTarget<D>
+    where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        self.get_challenges_with_degree::<F, C>(
+            builder,
+            public_inputs_hash,
+            inner_circuit_digest,
+            inner_common_data,
+            None,
+        )
+    }
+
+    /// Like `get_challenges`, for a proof padded to the shape of `inner_common_data` whose actual
+    /// degree is the witnessed `degree_bits`.
+    pub(crate) fn get_challenges_variable_degree<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    >(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        public_inputs_hash: HashOutTarget,
+        inner_circuit_digest: HashOutTarget,
+        inner_common_data: &CommonCircuitData<F, D>,
+        degree_bits: &VariableDegreeBits,
+    ) -> ProofChallengesTarget<D>
+    where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        self.get_challenges_with_degree::<F, C>(
+            builder,
+            public_inputs_hash,
+            inner_circuit_digest,
+            inner_common_data,
+            Some(degree_bits),
+        )
+    }
+
+    fn get_challenges_with_degree<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        public_inputs_hash: HashOutTarget,
+        inner_circuit_digest: HashOutTarget,
+        inner_common_data: &CommonCircuitData<F, D>,
+        degree_bits: Option<&VariableDegreeBits>,
+    ) -> ProofChallengesTarget<D>
     where
         C::Hasher: AlgebraicHasher<F>,
     {
         let ProofTarget {
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
@@ -346,6 +523,7 @@ impl<const D: usize> ProofWithPublicInputsTarget<D> {
                     commit_phase_merkle_caps,
                     final_poly,
                     pow_witness,
+                    round_pow_witnesses,
                     ..
                 },
         } = &self.proof;
@@ -353,14 +531,17 @@ impl<const D: usize> ProofWithPublicInputsTarget<D> {
         builder.get_challenges::<C>(
             public_inputs_hash,
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
             commit_phase_merkle_caps,
             final_poly,
             *pow_witness,
+            round_pow_witnesses,
             inner_circuit_digest,
             inner_common_data,
+            degree_bits,
         )
     }
 }
@@ -3,16 +3,21 @@
 //! This module also defines the [CircuitBuilder](circuit_builder::CircuitBuilder)
 //! structure, used to build custom plonky2 circuits satisfying arbitrary statements.
 
+pub mod challenge_dependent;
 pub mod circuit_builder;
 pub mod circuit_data;
+pub mod compat;
 pub mod config;
 pub(crate) mod copy_constraint;
+pub mod generated_witness;
 mod get_challenges;
 pub(crate) mod permutation_argument;
 pub mod plonk_common;
 pub mod proof;
+pub mod proof_header;
 pub mod prover;
-mod validate_shape;
+pub mod transcript;
+pub(crate) mod validate_shape;
 pub(crate) mod vanishing_poly;
 pub mod vars;
 pub mod verifier;
@@ -38,6 +38,11 @@ impl PlonkOracle {
         index: 3,
         blinding: true,
     };
+    /// Only present when the circuit has challenge-dependent columns.
+    pub const CHALLENGE_DEPENDENT: PlonkOracle = PlonkOracle {
+        index: 4,
+        blinding: true,
+    };
 }
 
 pub const fn salt_size(salted: bool) -> usize {
@@ -7,11 +7,11 @@
 use alloc::vec;
 use alloc::vec::Vec;
 
-use anyhow::ensure;
+use anyhow::{anyhow, ensure};
 use plonky2_maybe_rayon::*;
 use serde::{Deserialize, Serialize};
 
-use crate::field::extension::Extendable;
+use crate::field::extension::{Extendable, FieldExtension};
 use crate::fri::oracle::PolynomialBatch;
 use crate::fri::proof::{
     CompressedFriProof, FriChallenges, FriChallengesTarget, FriProof, FriProofTarget,
@@ -22,11 +22,14 @@ use crate::fri::structure::{
 use crate::fri::FriParams;
 use crate::hash::hash_types::{MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
+use crate::iop::challenger::Challenger;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
-use crate::plonk::config::{GenericConfig, Hasher};
-use crate::plonk::verifier::verify_with_challenges;
+use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::proof_header::ProofHeader;
+use crate::plonk::transcript::TRANSCRIPT_VERSION;
+use crate::plonk::verifier::{verify_with_challenges, verify_with_public_inputs_hash};
 use crate::util::serialization::{Buffer, Read, Write};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -34,6 +37,8 @@ use crate::util::serialization::{Buffer, Read, Write};
 pub struct Proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
     /// Merkle cap of LDEs of wire values.
     pub wires_cap: MerkleCap<F, C::Hasher>,
+    /// Merkle cap of LDEs of challenge-dependent columns, empty if the circuit has none.
+    pub challenge_dependent_cap: MerkleCap<F, C::Hasher>,
     /// Merkle cap of LDEs of Z, in the context of Plonk's permutation argument.
     pub plonk_zs_partial_products_cap: MerkleCap<F, C::Hasher>,
     /// Merkle cap of LDEs of the quotient polynomial components.
@@ -47,6 +52,7 @@ pub struct Proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ProofTarget<const D: usize> {
     pub wires_cap: MerkleCapTarget,
+    pub challenge_dependent_cap: MerkleCapTarget,
     pub plonk_zs_partial_products_cap: MerkleCapTarget,
     pub quotient_polys_cap: MerkleCapTarget,
     pub openings: OpeningSetTarget<D>,
@@ -58,6 +64,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> P
     pub fn compress(self, indices: &[usize], params: &FriParams) -> CompressedProof<F, C, D> {
         let Proof {
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
@@ -66,6 +73,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> P
 
         CompressedProof {
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
@@ -107,6 +115,33 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         C::InnerHasher::hash_no_pad(&self.public_inputs)
     }
 
+    /// Pads the proof to the shape of a proof for `common_data`, which must describe the same
+    /// circuit but with a degree at least that of the proof, for recursive verification with
+    /// `CircuitBuilder::verify_proof_variable_degree`. See `FriProof::padded_to`.
+    pub fn padded_to(&self, common_data: &CommonCircuitData<F, D>) -> Self {
+        Self {
+            proof: Proof {
+                opening_proof: self.proof.opening_proof.padded_to(&common_data.fri_params),
+                ..self.proof.clone()
+            },
+            public_inputs: self.public_inputs.clone(),
+        }
+    }
+
+    /// The canonical byte encoding of the public inputs hash, e.g. for use as a deduplication key.
+    pub fn public_inputs_digest_bytes(&self) -> Vec<u8> {
+        self.get_public_inputs_hash().to_bytes()
+    }
+
+    /// Hashes the public inputs once, so that later verifications and digest lookups can reuse it.
+    pub fn with_cached_digest(self) -> ProofWithCachedDigest<F, C, D> {
+        let public_inputs_hash = self.get_public_inputs_hash();
+        ProofWithCachedDigest {
+            proof_with_pis: self,
+            public_inputs_hash,
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut buffer = Vec::new();
         buffer
@@ -125,6 +160,110 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             .map_err(anyhow::Error::msg)?;
         Ok(proof)
     }
+
+    /// Serializes the proof, prefixed by a [`ProofHeader`] identifying the circuit.
+    pub fn to_bytes_with_header(&self, common_data: &CommonCircuitData<F, D>) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        ProofHeader::new::<F, C, D>(false, common_data).write(&mut buffer);
+        buffer
+            .write_proof_with_public_inputs(self)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Deserializes a proof written by [`Self::to_bytes_with_header`]. Proofs generated for
+    /// another circuit are rejected from their header, before the rest of the proof is read.
+    pub fn from_bytes_with_header(
+        bytes: Vec<u8>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<Self> {
+        let (header, body) = ProofHeader::split(&bytes)?;
+        header.check::<F, C, D>(false, common_data)?;
+        let mut buffer = Buffer::new(body);
+        let proof = buffer
+            .read_proof_with_public_inputs(common_data)
+            .map_err(anyhow::Error::msg)?;
+        Ok(proof)
+    }
+}
+
+/// A [`ProofWithPublicInputs`] along with the hash of its public inputs, computed once by
+/// [`ProofWithPublicInputs::with_cached_digest`].
+///
+/// The proof is only readable, through `proof_with_pis`, so its public inputs cannot change once
+/// their hash is cached; `into_inner` gives the proof back for modification. Verification trusts
+/// the cache unless run in paranoid mode, which recomputes the hash and rejects a mismatch. Only
+/// the proof is serialized, and the hash is recomputed on deserialization.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProofWithCachedDigest<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    proof_with_pis: ProofWithPublicInputs<F, C, D>,
+    public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    ProofWithCachedDigest<F, C, D>
+{
+    /// The cached hash of the public inputs.
+    pub fn public_inputs_hash(&self) -> <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash {
+        self.public_inputs_hash
+    }
+
+    /// The canonical byte encoding of the cached public inputs hash.
+    pub fn public_inputs_digest_bytes(&self) -> Vec<u8> {
+        self.public_inputs_hash.to_bytes()
+    }
+
+    /// The proof, whose public inputs hash to the cached hash.
+    pub fn proof_with_pis(&self) -> &ProofWithPublicInputs<F, C, D> {
+        &self.proof_with_pis
+    }
+
+    /// Drops the cached hash and returns the proof.
+    pub fn into_inner(self) -> ProofWithPublicInputs<F, C, D> {
+        self.proof_with_pis
+    }
+
+    pub(crate) fn verify(
+        self,
+        verifier_data: &VerifierOnlyCircuitData<C, D>,
+        common_data: &CommonCircuitData<F, D>,
+        paranoid: bool,
+    ) -> anyhow::Result<()> {
+        if paranoid {
+            ensure!(
+                self.proof_with_pis.get_public_inputs_hash() == self.public_inputs_hash,
+                "Public inputs do not match the cached digest"
+            );
+        }
+        verify_with_public_inputs_hash::<F, C, D>(
+            self.proof_with_pis,
+            self.public_inputs_hash,
+            verifier_data,
+            common_data,
+            Challenger::with_transcript::<C::Transcript>(),
+        )
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Serialize
+    for ProofWithCachedDigest<F, C, D>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.proof_with_pis.serialize(serializer)
+    }
+}
+
+impl<'de, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Deserialize<'de>
+    for ProofWithCachedDigest<F, C, D>
+{
+    fn deserialize<De: serde::Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        ProofWithPublicInputs::deserialize(deserializer)
+            .map(ProofWithPublicInputs::with_cached_digest)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -133,6 +272,8 @@ pub struct CompressedProof<F: RichField + Extendable<D>, C: GenericConfig<D, F =
 {
     /// Merkle cap of LDEs of wire values.
     pub wires_cap: MerkleCap<F, C::Hasher>,
+    /// Merkle cap of LDEs of challenge-dependent columns, empty if the circuit has none.
+    pub challenge_dependent_cap: MerkleCap<F, C::Hasher>,
     /// Merkle cap of LDEs of Z, in the context of Plonk's permutation argument.
     pub plonk_zs_partial_products_cap: MerkleCap<F, C::Hasher>,
     /// Merkle cap of LDEs of the quotient polynomial components.
@@ -155,6 +296,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     ) -> Proof<F, C, D> {
         let CompressedProof {
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
@@ -163,6 +305,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
 
         Proof {
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
@@ -206,28 +349,66 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         self,
         verifier_data: &VerifierOnlyCircuitData<C, D>,
         common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<()> {
+        self.verify_with_transcript_versions(verifier_data, common_data, &[TRANSCRIPT_VERSION])
+    }
+
+    /// Verifies the proof, which may use any of the given transcript versions.
+    pub(crate) fn verify_with_transcript_versions(
+        self,
+        verifier_data: &VerifierOnlyCircuitData<C, D>,
+        common_data: &CommonCircuitData<F, D>,
+        transcript_versions: &[u32],
     ) -> anyhow::Result<()> {
         ensure!(
             self.public_inputs.len() == common_data.num_public_inputs,
             "Number of public inputs doesn't match circuit data."
         );
         let public_inputs_hash = self.get_public_inputs_hash();
-        let challenges = self.get_challenges(
-            public_inputs_hash,
-            &verifier_data.circuit_digest,
-            common_data,
-        )?;
-        let fri_inferred_elements = self.get_inferred_elements(&challenges, common_data);
-        let decompressed_proof =
-            self.proof
-                .decompress(&challenges, fri_inferred_elements, &common_data.fri_params);
-        verify_with_challenges::<F, C, D>(
-            decompressed_proof,
-            public_inputs_hash,
-            challenges,
-            verifier_data,
-            common_data,
-        )
+        let mut result = Err(anyhow!("No transcript version is accepted"));
+        for &version in transcript_versions {
+            let challenges = self.get_challenges_for_version(
+                public_inputs_hash,
+                &verifier_data.circuit_digest,
+                common_data,
+                version,
+            )?;
+            // A compressed proof only holds openings at the query indices of its own transcript.
+            let initial_trees_proofs = &self
+                .proof
+                .opening_proof
+                .query_round_proofs
+                .initial_trees_proofs;
+            if !challenges
+                .fri_challenges
+                .fri_query_indices
+                .iter()
+                .all(|i| initial_trees_proofs.contains_key(i))
+            {
+                result = Err(anyhow!(
+                    "Compressed proof has no openings at the query indices of transcript version {}",
+                    version
+                ));
+                continue;
+            }
+            let fri_inferred_elements = self.get_inferred_elements(&challenges, common_data);
+            let decompressed_proof = self.proof.clone().decompress(
+                &challenges,
+                fri_inferred_elements,
+                &common_data.fri_params,
+            );
+            result = verify_with_challenges::<F, C, D>(
+                decompressed_proof,
+                public_inputs_hash,
+                challenges,
+                verifier_data,
+                common_data,
+            );
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
     }
 
     pub(crate) fn get_public_inputs_hash(
@@ -254,6 +435,31 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             .map_err(anyhow::Error::msg)?;
         Ok(proof)
     }
+
+    /// Serializes the proof, prefixed by a [`ProofHeader`] identifying the circuit.
+    pub fn to_bytes_with_header(&self, common_data: &CommonCircuitData<F, D>) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        ProofHeader::new::<F, C, D>(true, common_data).write(&mut buffer);
+        buffer
+            .write_compressed_proof_with_public_inputs(self)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Deserializes a proof written by [`Self::to_bytes_with_header`]. Proofs generated for
+    /// another circuit are rejected from their header, before the rest of the proof is read.
+    pub fn from_bytes_with_header(
+        bytes: Vec<u8>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<Self> {
+        let (header, body) = ProofHeader::split(&bytes)?;
+        header.check::<F, C, D>(true, common_data)?;
+        let mut buffer = Buffer::new(body);
+        let proof = buffer
+            .read_compressed_proof_with_public_inputs(common_data)
+            .map_err(anyhow::Error::msg)?;
+        Ok(proof)
+    }
 }
 
 pub struct ProofChallenges<F: RichField + Extendable<D>, const D: usize> {
@@ -269,17 +475,62 @@ pub struct ProofChallenges<F: RichField + Extendable<D>, const D: usize> {
     /// Lookup challenges.
     pub plonk_deltas: Vec<F>,
 
+    /// Challenges drawn before committing to the challenge-dependent columns.
+    pub challenge_dependent_challenges: Vec<F>,
+
     /// Point at which the PLONK polynomials are opened.
     pub plonk_zeta: F::Extension,
 
     pub fri_challenges: FriChallenges<F, D>,
 }
 
+/// A serializable summary of `ProofChallenges`, with every challenge in canonical form, meant for
+/// logging and diffing the challenges seen by the prover and the verifier. Extension field
+/// challenges are given as their base field coordinates.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ChallengesSummary {
+    pub plonk_betas: Vec<u64>,
+    pub plonk_gammas: Vec<u64>,
+    pub plonk_alphas: Vec<u64>,
+    pub plonk_deltas: Vec<u64>,
+    pub challenge_dependent_challenges: Vec<u64>,
+    pub plonk_zeta: Vec<u64>,
+    pub fri_alpha: Vec<u64>,
+    pub fri_betas: Vec<Vec<u64>>,
+    pub fri_pow_response: u64,
+    pub fri_round_pow_responses: Vec<u64>,
+    pub fri_query_indices: Vec<usize>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> From<&ProofChallenges<F, D>>
+    for ChallengesSummary
+{
+    fn from(challenges: &ProofChallenges<F, D>) -> Self {
+        let canonical = |xs: &[F]| xs.iter().map(|x| x.to_canonical_u64()).collect::<Vec<_>>();
+        let canonical_ext = |x: &F::Extension| canonical(&x.to_basefield_array());
+        let fri = &challenges.fri_challenges;
+        Self {
+            plonk_betas: canonical(&challenges.plonk_betas),
+            plonk_gammas: canonical(&challenges.plonk_gammas),
+            plonk_alphas: canonical(&challenges.plonk_alphas),
+            plonk_deltas: canonical(&challenges.plonk_deltas),
+            challenge_dependent_challenges: canonical(&challenges.challenge_dependent_challenges),
+            plonk_zeta: canonical_ext(&challenges.plonk_zeta),
+            fri_alpha: canonical_ext(&fri.fri_alpha),
+            fri_betas: fri.fri_betas.iter().map(canonical_ext).collect(),
+            fri_pow_response: fri.fri_pow_response.to_canonical_u64(),
+            fri_round_pow_responses: canonical(&fri.fri_round_pow_responses),
+            fri_query_indices: fri.fri_query_indices.clone(),
+        }
+    }
+}
+
 pub(crate) struct ProofChallengesTarget<const D: usize> {
     pub plonk_betas: Vec<Target>,
     pub plonk_gammas: Vec<Target>,
     pub plonk_alphas: Vec<Target>,
     pub plonk_deltas: Vec<Target>,
+    pub challenge_dependent_challenges: Vec<Target>,
     pub plonk_zeta: ExtensionTarget<D>,
     pub fri_challenges: FriChallengesTarget<D>,
 }
@@ -301,12 +552,16 @@ pub struct OpeningSet<F: RichField + Extendable<D>, const D: usize> {
     pub constants: Vec<F::Extension>,
     pub plonk_sigmas: Vec<F::Extension>,
     pub wires: Vec<F::Extension>,
+    /// The first `num_next_row_wires` wires, opened at `g * zeta`.
+    pub wires_next: Vec<F::Extension>,
     pub plonk_zs: Vec<F::Extension>,
     pub plonk_zs_next: Vec<F::Extension>,
     pub partial_products: Vec<F::Extension>,
     pub quotient_polys: Vec<F::Extension>,
     pub lookup_zs: Vec<F::Extension>,
     pub lookup_zs_next: Vec<F::Extension>,
+    pub challenge_dependent: Vec<F::Extension>,
+    pub challenge_dependent_next: Vec<F::Extension>,
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
@@ -317,6 +572,7 @@ impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
         wires_commitment: &PolynomialBatch<F, C, D>,
         zs_partial_products_lookup_commitment: &PolynomialBatch<F, C, D>,
         quotient_polys_commitment: &PolynomialBatch<F, C, D>,
+        challenge_dependent_commitment: Option<&PolynomialBatch<F, C, D>>,
         common_data: &CommonCircuitData<F, D>,
     ) -> Self {
         let eval_commitment = |z: F::Extension, c: &PolynomialBatch<F, C, D>| {
@@ -333,11 +589,19 @@ impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
         let zs_partial_products_lookup_next_eval =
             eval_commitment(g * zeta, zs_partial_products_lookup_commitment);
         let quotient_polys = eval_commitment(zeta, quotient_polys_commitment);
+        let (challenge_dependent, challenge_dependent_next) = challenge_dependent_commitment
+            .map_or((vec![], vec![]), |c| {
+                (eval_commitment(zeta, c), eval_commitment(g * zeta, c))
+            });
 
         Self {
             constants: constants_sigmas_eval[common_data.constants_range()].to_vec(),
             plonk_sigmas: constants_sigmas_eval[common_data.sigmas_range()].to_vec(),
             wires: eval_commitment(zeta, wires_commitment),
+            wires_next: wires_commitment.polynomials[..common_data.num_next_row_wires()]
+                .par_iter()
+                .map(|p| p.to_extension().eval(g * zeta))
+                .collect(),
             plonk_zs: zs_partial_products_lookup_eval[common_data.zs_range()].to_vec(),
             plonk_zs_next: zs_partial_products_lookup_next_eval[common_data.zs_range()].to_vec(),
             partial_products: zs_partial_products_lookup_eval[common_data.partial_products_range()]
@@ -346,11 +610,13 @@ impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
             lookup_zs: zs_partial_products_lookup_eval[common_data.lookup_range()].to_vec(),
             lookup_zs_next: zs_partial_products_lookup_next_eval[common_data.lookup_range()]
                 .to_vec(),
+            challenge_dependent,
+            challenge_dependent_next,
         }
     }
     pub(crate) fn to_fri_openings(&self) -> FriOpenings<F, D> {
         let has_lookup = !self.lookup_zs.is_empty();
-        let zeta_batch = if has_lookup {
+        let mut zeta_batch = if has_lookup {
             FriOpeningBatch {
                 values: [
                     self.constants.as_slice(),
@@ -376,7 +642,7 @@ impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
                 .concat(),
             }
         };
-        let zeta_next_batch = if has_lookup {
+        let mut zeta_next_batch = if has_lookup {
             FriOpeningBatch {
                 values: [self.plonk_zs_next.clone(), self.lookup_zs_next.clone()].concat(),
             }
@@ -385,6 +651,13 @@ impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
                 values: self.plonk_zs_next.clone(),
             }
         };
+        // Challenge-dependent columns come last in both batches, followed by the next-row wires in
+        // the `g * zeta` batch, matching `fri_all_polys` and `fri_next_batch_polys`.
+        zeta_batch.values.extend(&self.challenge_dependent);
+        zeta_next_batch
+            .values
+            .extend(&self.challenge_dependent_next);
+        zeta_next_batch.values.extend(&self.wires_next);
         FriOpenings {
             batches: vec![zeta_batch, zeta_next_batch],
         }
@@ -397,18 +670,21 @@ pub struct OpeningSetTarget<const D: usize> {
     pub constants: Vec<ExtensionTarget<D>>,
     pub plonk_sigmas: Vec<ExtensionTarget<D>>,
     pub wires: Vec<ExtensionTarget<D>>,
+    pub wires_next: Vec<ExtensionTarget<D>>,
     pub plonk_zs: Vec<ExtensionTarget<D>>,
     pub plonk_zs_next: Vec<ExtensionTarget<D>>,
     pub lookup_zs: Vec<ExtensionTarget<D>>,
     pub next_lookup_zs: Vec<ExtensionTarget<D>>,
     pub partial_products: Vec<ExtensionTarget<D>>,
     pub quotient_polys: Vec<ExtensionTarget<D>>,
+    pub challenge_dependent: Vec<ExtensionTarget<D>>,
+    pub challenge_dependent_next: Vec<ExtensionTarget<D>>,
 }
 
 impl<const D: usize> OpeningSetTarget<D> {
     pub(crate) fn to_fri_openings(&self) -> FriOpeningsTarget<D> {
         let has_lookup = !self.lookup_zs.is_empty();
-        let zeta_batch = if has_lookup {
+        let mut zeta_batch = if has_lookup {
             FriOpeningBatchTarget {
                 values: [
                     self.constants.as_slice(),
@@ -434,7 +710,7 @@ impl<const D: usize> OpeningSetTarget<D> {
                 .concat(),
             }
         };
-        let zeta_next_batch = if has_lookup {
+        let mut zeta_next_batch = if has_lookup {
             FriOpeningBatchTarget {
                 values: [self.plonk_zs_next.clone(), self.next_lookup_zs.clone()].concat(),
             }
@@ -443,6 +719,11 @@ impl<const D: usize> OpeningSetTarget<D> {
                 values: self.plonk_zs_next.clone(),
             }
         };
+        zeta_batch.values.extend(&self.challenge_dependent);
+        zeta_next_batch
+            .values
+            .extend(&self.challenge_dependent_next);
+        zeta_next_batch.values.extend(&self.wires_next);
         FriOpeningsTarget {
             batches: vec![zeta_batch, zeta_next_batch],
         }
@@ -507,6 +788,62 @@ mod tests {
         data.verify_compressed(compressed_proof)
     }
 
+    #[test]
+    fn test_compressed_proof_serialization_is_deterministic() -> Result<()> {
+        use alloc::collections::BTreeMap;
+
+        use hashbrown::HashMap;
+
+        use crate::fri::proof::FriInitialTreeProof;
+        use crate::iop::witness::WitnessWrite;
+        use crate::plonk::proof::CompressedProofWithPublicInputs;
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.num_query_rounds = 50;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        let proof = data.prove(pw)?;
+
+        // Two independent compressions of the same proof serialize to the same bytes.
+        let compressed = data.compress(proof.clone())?;
+        let bytes = serde_json::to_vec(&compressed).map_err(anyhow::Error::msg)?;
+        assert_eq!(
+            serde_json::to_vec(&data.compress(proof)?).map_err(anyhow::Error::msg)?,
+            bytes
+        );
+        let decoded: CompressedProofWithPublicInputs<F, C, D> =
+            serde_json::from_slice(&bytes).map_err(anyhow::Error::msg)?;
+        assert_eq!(
+            serde_json::to_vec(&decoded).map_err(anyhow::Error::msg)?,
+            bytes
+        );
+
+        // Openings serialized from a `HashMap`, as they used to be, still deserialize.
+        let mut compressed = compressed;
+        let query_round_proofs = &mut compressed.proof.opening_proof.query_round_proofs;
+        let legacy = query_round_proofs
+            .initial_trees_proofs
+            .iter()
+            .map(|(&i, p)| (i, p.clone()))
+            .collect::<HashMap<_, _>>();
+        let legacy = serde_json::to_vec(&legacy).map_err(anyhow::Error::msg)?;
+        let decoded: BTreeMap<usize, FriInitialTreeProof<F, H>> =
+            serde_json::from_slice(&legacy).map_err(anyhow::Error::msg)?;
+        assert_eq!(decoded, query_round_proofs.initial_trees_proofs);
+        query_round_proofs.initial_trees_proofs = decoded;
+        data.verify_compressed(compressed)
+    }
+
     #[test]
     fn test_proof_compression_lookup() -> Result<()> {
         const D: usize = 2;
@@ -563,4 +900,133 @@ mod tests {
         verify(proof, &data.verifier_only, &data.common)?;
         data.verify_compressed(compressed_proof)
     }
+
+    #[test]
+    fn test_challenges_summary() -> Result<()> {
+        use plonky2_field::types::Field;
+
+        use crate::iop::witness::WitnessWrite;
+        use crate::plonk::proof::ChallengesSummary;
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let summarize = |x_value: u64| -> Result<ChallengesSummary> {
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(x_value));
+            let proof = data.prove(pw)?;
+            let challenges = proof.get_challenges(
+                proof.get_public_inputs_hash(),
+                &data.verifier_only.circuit_digest,
+                &data.common,
+            )?;
+            Ok(ChallengesSummary::from(&challenges))
+        };
+
+        let summary = summarize(3)?;
+        assert_eq!(summary.plonk_zeta.len(), D);
+        assert_eq!(
+            summary.fri_query_indices.len(),
+            data.common.config.fri_config.num_query_rounds
+        );
+        let json = serde_json::to_string(&summary).map_err(anyhow::Error::msg)?;
+        let roundtrip: ChallengesSummary =
+            serde_json::from_str(&json).map_err(anyhow::Error::msg)?;
+        assert_eq!(roundtrip, summary);
+
+        assert_ne!(summarize(4)?, summary);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde-canonical")]
+    #[test]
+    fn test_canonical_proof_serialization() -> Result<()> {
+        use crate::field::goldilocks_field::GoldilocksField;
+        use crate::field::types::{Field, Field64};
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.constant(F::from_canonical_u64(7));
+        builder.register_public_input(x);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+
+        // The same proof, with a non-canonical representation of the public input.
+        let mut non_canonical = proof.clone();
+        non_canonical.public_inputs[0] = GoldilocksField(7 + F::ORDER);
+        assert_eq!(non_canonical, proof);
+
+        let json = serde_json::to_string(&proof)?;
+        assert_eq!(serde_json::to_string(&non_canonical)?, json);
+        data.verify(serde_json::from_str(&json)?)
+    }
+
+    #[test]
+    fn test_cached_public_inputs_digest() -> Result<()> {
+        use crate::field::types::Field;
+        use crate::iop::witness::WitnessWrite;
+        use crate::plonk::proof::ProofWithCachedDigest;
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        let proof = data.prove(pw)?;
+
+        let cached = proof.clone().with_cached_digest();
+        assert_eq!(cached.public_inputs_hash(), proof.get_public_inputs_hash());
+        assert_eq!(
+            cached.public_inputs_digest_bytes(),
+            proof.public_inputs_digest_bytes()
+        );
+        data.verify_cached(cached.clone(), false)?;
+        data.verify_cached(cached.clone(), true)?;
+
+        // The proof is read-only outside this module, and modifying it requires dropping the cache.
+        assert_eq!(cached.proof_with_pis(), &proof);
+        assert_eq!(cached.clone().into_inner(), proof);
+
+        // Tampering with the public inputs from within this module leaves the cache stale.
+        let mut tampered = cached.clone();
+        tampered.proof_with_pis.public_inputs[0] += F::ONE;
+        assert_eq!(tampered.public_inputs_hash(), cached.public_inputs_hash());
+        assert!(data.verify_cached(tampered.clone(), true).is_err());
+
+        // A serde round-trip recomputes the digest rather than restoring the stale one.
+        let json = serde_json::to_string(&tampered).map_err(anyhow::Error::msg)?;
+        assert_eq!(
+            json,
+            serde_json::to_string(tampered.proof_with_pis()).map_err(anyhow::Error::msg)?
+        );
+        let decoded: ProofWithCachedDigest<F, C, D> =
+            serde_json::from_str(&json).map_err(anyhow::Error::msg)?;
+        assert_eq!(
+            decoded.public_inputs_hash(),
+            tampered.proof_with_pis().get_public_inputs_hash()
+        );
+        assert_ne!(decoded.public_inputs_hash(), cached.public_inputs_hash());
+        assert!(data.verify_cached(decoded, false).is_err());
+        Ok(())
+    }
 }
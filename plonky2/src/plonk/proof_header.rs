@@ -0,0 +1,293 @@
+//! The header prepended to proofs serialized with [`ProofWithPublicInputs::to_bytes_with_header`]
+//! and [`CompressedProofWithPublicInputs::to_bytes_with_header`].
+//!
+//! The header records the encoding of the proof and the circuit it was generated for, so that a
+//! proof can be routed to the right verifier with [`ProofHeader::peek`] before anything else is
+//! known about it. It is laid out as
+//! - an [`ArtifactHeader`] of kind [`ArtifactKind::Proof`] or [`ArtifactKind::CompressedProof`],
+//! - a flags byte, see [`ProofHeader::hiding`],
+//! - the transcript version, a little-endian `u32`,
+//! - the length of the config digest as a byte, followed by the digest.
+//!
+//! [`ProofWithPublicInputs::to_bytes_with_header`]: crate::plonk::proof::ProofWithPublicInputs::to_bytes_with_header
+//! [`CompressedProofWithPublicInputs::to_bytes_with_header`]: crate::plonk::proof::CompressedProofWithPublicInputs::to_bytes_with_header
+
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::{GenericConfig, GenericHashOut};
+use crate::plonk::transcript::{KNOWN_TRANSCRIPT_VERSIONS, TRANSCRIPT_VERSION};
+use crate::util::serialization::header::{
+    ArtifactHeader, ArtifactKind, HeaderError, ARTIFACT_HEADER_LEN,
+};
+
+const FLAG_HIDING: u8 = 1 << 0;
+
+/// The length of the header before the config digest.
+const FIXED_HEADER_LEN: usize = ARTIFACT_HEADER_LEN + 1 + 4 + 1;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProofHeader {
+    pub format_version: u16,
+    /// Whether the proof is a [`CompressedProofWithPublicInputs`].
+    ///
+    /// [`CompressedProofWithPublicInputs`]: crate::plonk::proof::CompressedProofWithPublicInputs
+    pub compressed: bool,
+    /// Whether the circuit was built with `zero_knowledge` set.
+    pub hiding: bool,
+    /// The transcript version the prover used.
+    pub transcript_version: u32,
+    /// The [`CommonCircuitData::digest`] of the circuit the proof was generated for.
+    pub config_digest: Vec<u8>,
+}
+
+const fn artifact_kind(compressed: bool) -> ArtifactKind {
+    if compressed {
+        ArtifactKind::CompressedProof
+    } else {
+        ArtifactKind::Proof
+    }
+}
+
+impl ProofHeader {
+    /// The header of a proof generated by this release for the given circuit.
+    pub(crate) fn new<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+        compressed: bool,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> Self {
+        Self {
+            format_version: artifact_kind(compressed).format_version(),
+            compressed,
+            hiding: common_data.config.zero_knowledge,
+            transcript_version: TRANSCRIPT_VERSION,
+            config_digest: common_data.digest::<C>().to_bytes(),
+        }
+    }
+
+    /// Parses the header of a serialized proof, without reading the proof itself.
+    pub fn peek(bytes: &[u8]) -> Result<Self> {
+        Self::split(bytes).map(|(header, _)| header)
+    }
+
+    /// Parses the header of a serialized proof, and returns it along with the proof's body.
+    pub(crate) fn split(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (artifact_header, rest) = ArtifactHeader::split(bytes).map_err(|e| match e {
+            HeaderError::BadMagic => anyhow!(
+                "The proof doesn't start with the artifact magic; it may have been serialized \
+                 without a header, with `to_bytes`, which must be read with `from_bytes`"
+            ),
+            e => anyhow!(e),
+        })?;
+        let compressed = match artifact_header.kind {
+            ArtifactKind::Proof => false,
+            ArtifactKind::CompressedProof => true,
+            kind => return Err(anyhow!("Expected a proof, but got the {kind}")),
+        };
+        ensure!(
+            bytes.len() >= FIXED_HEADER_LEN,
+            "The proof is too short to hold a header"
+        );
+        let (fixed, rest) = rest.split_at(FIXED_HEADER_LEN - ARTIFACT_HEADER_LEN);
+
+        let flags = fixed[0];
+        ensure!(
+            flags & !FLAG_HIDING == 0,
+            "The proof header has unknown flags {flags:#04x}"
+        );
+        let transcript_version = u32::from_le_bytes(fixed[1..5].try_into().unwrap());
+        ensure!(
+            KNOWN_TRANSCRIPT_VERSIONS.contains(&transcript_version),
+            "The proof uses unknown transcript version {transcript_version}"
+        );
+        let digest_len = fixed[5] as usize;
+        ensure!(
+            rest.len() >= digest_len,
+            "The proof is too short to hold its config digest"
+        );
+        let (config_digest, body) = rest.split_at(digest_len);
+
+        let header = Self {
+            format_version: artifact_header.format_version,
+            compressed,
+            hiding: flags & FLAG_HIDING != 0,
+            transcript_version,
+            config_digest: config_digest.to_vec(),
+        };
+        Ok((header, body))
+    }
+
+    pub(crate) fn write(&self, buffer: &mut Vec<u8>) {
+        let mut flags = 0;
+        if self.hiding {
+            flags |= FLAG_HIDING;
+        }
+        ArtifactHeader {
+            kind: artifact_kind(self.compressed),
+            format_version: self.format_version,
+        }
+        .write(buffer);
+        buffer.push(flags);
+        buffer.extend_from_slice(&self.transcript_version.to_le_bytes());
+        buffer.push(
+            self.config_digest
+                .len()
+                .try_into()
+                .expect("Config digests are shorter than 256 bytes"),
+        );
+        buffer.extend_from_slice(&self.config_digest);
+    }
+
+    /// Checks that the proof is of the expected kind and was generated for the given circuit.
+    pub(crate) fn check<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &self,
+        compressed: bool,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> Result<()> {
+        ensure!(
+            self.compressed == compressed,
+            "Expected the {}, but got the {}",
+            artifact_kind(compressed),
+            artifact_kind(self.compressed)
+        );
+        ensure!(
+            self.config_digest == common_data.digest::<C>().to_bytes(),
+            "The proof's config digest doesn't match the common circuit data; it was generated \
+             for another circuit"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::string::ToString;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::plonk::circuit_data::CircuitData;
+    use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
+    use crate::util::test_fixtures::{
+        fixture_circuit, fixture_config, fixture_fri_config, prove_fixture, C, D, F,
+    };
+
+    fn prove(
+        zero_knowledge: bool,
+    ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
+        let (data, x) = fixture_circuit(fixture_config(zero_knowledge, fixture_fri_config()), 3);
+        let proof = prove_fixture(&data, x)?;
+        Ok((data, proof))
+    }
+
+    #[test]
+    fn test_header_round_trip() -> Result<()> {
+        for zero_knowledge in [false, true] {
+            let (data, proof) = prove(zero_knowledge)?;
+            let bytes = proof.to_bytes_with_header(&data.common);
+            let header = ProofHeader::peek(&bytes)?;
+            assert_eq!(
+                header,
+                ProofHeader {
+                    format_version: ArtifactKind::Proof.format_version(),
+                    compressed: false,
+                    hiding: zero_knowledge,
+                    transcript_version: TRANSCRIPT_VERSION,
+                    config_digest: data.common.digest::<C>().to_bytes(),
+                }
+            );
+            // A header alone is enough to peek at.
+            let header_len = FIXED_HEADER_LEN + header.config_digest.len();
+            assert_eq!(ProofHeader::peek(&bytes[..header_len])?, header);
+            assert!(ProofHeader::peek(&bytes[..header_len - 1]).is_err());
+
+            assert_eq!(
+                ProofWithPublicInputs::from_bytes_with_header(bytes, &data.common)?,
+                proof
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_headerless_proof() -> Result<()> {
+        let (data, proof) = prove(false)?;
+        let bytes = proof.to_bytes();
+
+        let err =
+            ProofWithPublicInputs::<F, C, D>::from_bytes_with_header(bytes.clone(), &data.common)
+                .unwrap_err()
+                .to_string();
+        assert!(err.contains("without a header"), "{err}");
+        assert!(ProofHeader::peek(&bytes).is_err());
+        assert_eq!(
+            ProofWithPublicInputs::from_bytes(bytes, &data.common)?,
+            proof
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mismatched_config_digest() -> Result<()> {
+        let (data, proof) = prove(false)?;
+        let (other_data, _) = prove(true)?;
+        let err = ProofWithPublicInputs::<F, C, D>::from_bytes_with_header(
+            proof.to_bytes_with_header(&data.common),
+            &other_data.common,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("config digest"), "{err}");
+
+        let mut bytes = proof.to_bytes_with_header(&data.common);
+        bytes[ARTIFACT_HEADER_LEN - 2] += 1;
+        assert!(ProofHeader::peek(&bytes).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_on_compressed_flag() -> Result<()> {
+        let (data, proof) = prove(false)?;
+        let compressed = proof
+            .clone()
+            .compress(&data.verifier_only.circuit_digest, &data.common)?;
+        let blobs = [
+            proof.to_bytes_with_header(&data.common),
+            compressed.to_bytes_with_header(&data.common),
+        ];
+
+        for bytes in blobs {
+            if ProofHeader::peek(&bytes)?.compressed {
+                assert!(ProofWithPublicInputs::<F, C, D>::from_bytes_with_header(
+                    bytes.clone(),
+                    &data.common
+                )
+                .is_err());
+                let compressed =
+                    CompressedProofWithPublicInputs::from_bytes_with_header(bytes, &data.common)?;
+                data.verify_compressed(compressed)?;
+            } else {
+                assert!(
+                    CompressedProofWithPublicInputs::<F, C, D>::from_bytes_with_header(
+                        bytes.clone(),
+                        &data.common
+                    )
+                    .is_err()
+                );
+                data.verify(ProofWithPublicInputs::from_bytes_with_header(
+                    bytes,
+                    &data.common,
+                )?)?;
+            }
+        }
+        Ok(())
+    }
+}
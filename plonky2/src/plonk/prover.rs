@@ -1,37 +1,51 @@
 //! plonky2 prover implementation.
 
+use alloc::string::String;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use alloc::{format, vec};
 use core::cmp::min;
 use core::mem::swap;
+use core::time::Duration;
 
 use anyhow::{ensure, Result};
 use hashbrown::HashMap;
 use plonky2_maybe_rayon::*;
 
 use super::circuit_builder::{LookupChallenges, LookupWire};
-use crate::field::extension::Extendable;
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::fft::fft_root_table;
 use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use crate::field::types::Field;
 use crate::field::zero_poly_coset::ZeroPolyOnCoset;
-use crate::fri::oracle::PolynomialBatch;
+use crate::fri::oracle::{BatchBuffers, PolynomialBatch};
+use crate::fri::query_index::QueryIndexSampler;
+use crate::gates::gate::compute_filter;
 use crate::gates::lookup::LookupGate;
 use crate::gates::lookup_table::LookupTableGate;
 use crate::gates::selectors::LookupSelectors;
-use crate::hash::hash_types::RichField;
+use crate::hash::hash_types::{HashOut, RichField};
+use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::challenger::Challenger;
-use crate::iop::generator::generate_partial_witness;
+use crate::iop::generator::try_generate_partial_witness;
 use crate::iop::target::Target;
 use crate::iop::witness::{MatrixWitness, PartialWitness, PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::challenge_dependent::generate_challenge_dependent_columns;
 use crate::plonk::circuit_builder::NUM_COINS_LOOKUP;
-use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
+use crate::plonk::circuit_data::{
+    CommonCircuitData, ProverOnlyCircuitData, VerifierOnlyCircuitData,
+};
 use crate::plonk::config::{GenericConfig, Hasher};
-use crate::plonk::plonk_common::PlonkOracle;
+use crate::plonk::generated_witness::GeneratedWitness;
+use crate::plonk::plonk_common::{salt_size, PlonkOracle};
 use crate::plonk::proof::{OpeningSet, Proof, ProofWithPublicInputs};
+use crate::plonk::transcript::{observe_transcript_version, TRANSCRIPT_VERSION};
 use crate::plonk::vanishing_poly::{eval_vanishing_poly_base_batch, get_lut_poly};
-use crate::plonk::vars::EvaluationVarsBaseBatch;
+use crate::plonk::vars::{EvaluationVars, EvaluationVarsBaseBatch};
+use crate::plonk::verifier::verify_with_challenger;
 use crate::timed;
 use crate::util::partial_products::{partial_products_and_z_gx, quotient_chunk_products};
+use crate::util::sensitive::{wipe_vec, SensitiveBuffers};
 use crate::util::timing::TimingTree;
 use crate::util::{ceil_div_usize, log2_ceil, transpose};
 
@@ -109,12 +123,212 @@ pub fn set_lookup_wires<
     }
 }
 
+/// Options controlling optional, more expensive prover behavior.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProverOptions<F: Field> {
+    /// If set, the native verifier is run on the freshly generated proof before it is returned.
+    /// In debug builds, all gate constraints are additionally checked directly on the witness
+    /// before committing to it, so that a failure pinpoints the offending gate and row.
+    pub verify_after_prove: bool,
+    /// External data, such as a block number, bound into the FRI proof-of-work. The proof then
+    /// only verifies under the same context, see `CircuitData::verify_with_pow_context`. Such
+    /// proofs cannot be verified recursively yet.
+    pub pow_context: Vec<F>,
+    /// If set, witness generation fails once it has run for longer than this. Only enforced with
+    /// the `timing` feature, and only between generator runs, see `try_generate_partial_witness`.
+    pub witness_generation_timeout: Option<Duration>,
+    /// If set, the random values used for zero knowledge, i.e. the salts of the hiding oracles and
+    /// the random witness values used for blinding, are derived from this seed instead of being
+    /// sampled, so that proving twice with the same seed gives identical proofs. Without the seed,
+    /// they remain unpredictable to a verifier, but the seed should be kept as secret as the
+    /// witness.
+    pub salt_rng_seed: Option<u64>,
+    /// If set, the main buffers derived from the witness are overwritten with zeros once they are
+    /// no longer needed, rather than left on the heap: the partition witness, the witness matrix,
+    /// the polynomials, LDEs and salts of the committed oracles, the quotient polynomials before
+    /// they are split into chunks, and the folded codewords and commit-phase trees of FRI. The
+    /// reused `ProverBuffers` are wiped too. The inputs and the values buffered while running the
+    /// generators are wiped regardless of this option.
+    ///
+    /// Short-lived temporaries are not wiped, such as the per-block evaluations of the quotient
+    /// computation, the intermediate products of the permutation argument, or the combined
+    /// polynomials of the FRI openings. Neither is a [`GeneratedWitness`], which is owned by the
+    /// caller of [`generate_witness`]; [`prove_from_witness`] takes no options.
+    pub zeroize_witness: bool,
+}
+
+/// Large allocations which can be reused across proofs of the same circuit, to avoid allocating
+/// them afresh for every proof.
+///
+/// These are the witness matrix, the wire polynomials, and for each oracle committed by the
+/// prover, its LDEs, which the FFTs are done in place in, and the leaves and digests of its Merkle
+/// tree. The buffers thus hold about as much memory as the prover at its peak. Smaller
+/// allocations, such as those of the quotient computation or of the challenger, are not reused.
+///
+/// Each proof fully overwrites the buffers before reading them, so no witness data carries over
+/// from one proof to the next. After a proof, however, the buffers still hold that proof's
+/// witness; use `wipe` if they are kept around.
+#[derive(Clone, Debug)]
+pub struct ProverBuffers<F: RichField, H: Hasher<F>> {
+    /// The full witness, one column per wire.
+    wire_values: Vec<Vec<F>>,
+    /// Columns for the wire polynomials, recycled from the previous proof's wires commitment.
+    wire_polys: Vec<Vec<F>>,
+    /// The buffers of each oracle, by `PlonkOracle::index`.
+    batches: Vec<BatchBuffers<F, H>>,
+}
+
+impl<F: RichField, H: Hasher<F>> Default for ProverBuffers<F, H> {
+    fn default() -> Self {
+        Self {
+            wire_values: Vec::new(),
+            wire_polys: Vec::new(),
+            batches: Vec::new(),
+        }
+    }
+}
+
+impl<F: RichField, H: Hasher<F>> ProverBuffers<F, H> {
+    /// Allocates buffers sized for proofs of the circuit described by `common_data`.
+    pub fn new<const D: usize>(common_data: &CommonCircuitData<F, D>) -> Self
+    where
+        F: Extendable<D>,
+    {
+        let config = &common_data.config;
+        let columns = vec![vec![F::ZERO; common_data.degree()]; config.num_wires];
+        let batches = common_data
+            .fri_oracles()
+            .iter()
+            .enumerate()
+            .map(|(index, oracle)| {
+                // The preprocessed oracle is committed to when building the circuit.
+                if index == PlonkOracle::CONSTANTS_SIGMAS.index {
+                    return BatchBuffers::default();
+                }
+                let salted = config.zero_knowledge && oracle.blinding;
+                BatchBuffers::new(
+                    oracle.num_polys + salt_size(salted),
+                    common_data.lde_size(),
+                    config.fri_config.cap_height,
+                )
+            })
+            .collect();
+        Self {
+            wire_values: columns.clone(),
+            wire_polys: columns,
+            batches,
+        }
+    }
+
+    /// The buffers of `oracle`.
+    fn batch(&mut self, oracle: PlonkOracle) -> &mut BatchBuffers<F, H> {
+        if self.batches.len() <= oracle.index {
+            self.batches
+                .resize_with(oracle.index + 1, BatchBuffers::default);
+        }
+        &mut self.batches[oracle.index]
+    }
+
+    /// Every value held, other than the Merkle digests.
+    #[cfg(test)]
+    fn values(&self) -> impl Iterator<Item = &F> {
+        let wires = self.wire_values.iter().chain(&self.wire_polys).flatten();
+        wires.chain(self.batches.iter().flat_map(BatchBuffers::values))
+    }
+
+    /// Overwrites the contents of the buffers with zeros, keeping their allocations. The Merkle
+    /// digests are left as they are, as by `MerkleTree::zeroize`.
+    pub fn wipe(&mut self) {
+        for column in self.wire_values.iter_mut().chain(&mut self.wire_polys) {
+            wipe_vec(column, F::ZERO);
+        }
+        for batch in &mut self.batches {
+            batch.wipe();
+        }
+    }
+}
+
+/// A gate constraint which is not satisfied by a witness.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConstraintViolation<F: Field> {
+    /// The ID of the violated gate.
+    pub gate: String,
+    /// The row at which the gate is placed.
+    pub row: usize,
+    /// The index of the violated constraint within the gate.
+    pub constraint_index: usize,
+    /// The (nonzero) value of the unfiltered constraint.
+    pub value: F,
+}
+
 pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     prover_data: &ProverOnlyCircuitData<F, C, D>,
     common_data: &CommonCircuitData<F, D>,
     inputs: PartialWitness<F>,
     timing: &mut TimingTree,
 ) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    prove_with_options(
+        prover_data,
+        common_data,
+        inputs,
+        &ProverOptions::default(),
+        timing,
+    )
+}
+
+pub fn prove_with_options<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    options: &ProverOptions<F>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    let partition_witness = timed!(
+        timing,
+        &format!("run {} generators", prover_data.generators.len()),
+        try_generate_partial_witness(
+            inputs,
+            prover_data,
+            common_data,
+            options.witness_generation_timeout,
+            options.salt_rng_seed,
+        )?
+    );
+
+    prove_with_partition_witness_and_options(
+        prover_data,
+        common_data,
+        partition_witness,
+        options,
+        timing,
+    )
+}
+
+/// Like `prove_with_options`, but reuses the allocations in `buffers`.
+pub fn prove_with_buffers<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    options: &ProverOptions<F>,
+    buffers: &mut ProverBuffers<F, C::Hasher>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
 where
     C::Hasher: Hasher<F>,
     C::InnerHasher: Hasher<F>,
@@ -122,9 +336,101 @@ where
     let partition_witness = timed!(
         timing,
         &format!("run {} generators", prover_data.generators.len()),
-        generate_partial_witness(inputs, prover_data, common_data)
+        try_generate_partial_witness(
+            inputs,
+            prover_data,
+            common_data,
+            options.witness_generation_timeout,
+            options.salt_rng_seed,
+        )?
     );
 
+    prove_with_partition_witness_and_buffers(
+        prover_data,
+        common_data,
+        partition_witness,
+        options,
+        buffers,
+        timing,
+    )
+}
+
+/// Runs the witness generators on `inputs`, and returns the full witness, which can be proven
+/// with [`prove_from_witness`] or serialized with [`GeneratedWitness::to_bytes`].
+pub fn generate_witness<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+) -> Result<GeneratedWitness<F, C::Hasher>> {
+    let mut partition_witness = timed!(
+        timing,
+        &format!("run {} generators", prover_data.generators.len()),
+        try_generate_partial_witness(inputs, prover_data, common_data, None, None)?
+    );
+    set_lookup_wires(prover_data, common_data, &mut partition_witness);
+
+    Ok(GeneratedWitness {
+        circuit_digest: prover_data.circuit_digest,
+        witness: partition_witness.full_witness(),
+    })
+}
+
+/// Proves from a witness returned by [`generate_witness`], skipping witness generation. Witnesses
+/// generated for another circuit, or breaking one of its copy constraints, are rejected.
+pub fn prove_from_witness<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    witness: &GeneratedWitness<F, C::Hasher>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    ensure!(
+        witness.circuit_digest == prover_data.circuit_digest,
+        "The witness was generated for another circuit"
+    );
+    let degree = common_data.degree();
+    let num_wires = common_data.config.num_wires;
+    let wire_values = &witness.witness.wire_values;
+    ensure!(
+        wire_values.len() == num_wires && witness.witness.degree() == degree,
+        "The witness has {} rows of {} wires, but the circuit has {} rows of {} wires",
+        witness.witness.degree(),
+        wire_values.len(),
+        degree,
+        num_wires
+    );
+
+    // Rebuild the partition witness, so that targets copied from wires, such as the public
+    // inputs, get their values back.
+    let mut partition_witness =
+        PartitionWitness::new(num_wires, degree, &prover_data.representative_map);
+    for (column, values) in wire_values.iter().enumerate() {
+        for (row, &value) in values.iter().enumerate() {
+            let target = Target::wire(row, column);
+            let rep_index = prover_data.representative_map[partition_witness.target_index(target)];
+            match partition_witness.values[rep_index] {
+                Some(old_value) => ensure!(
+                    old_value == value,
+                    "The witness breaks the copy constraint of {:?}",
+                    target
+                ),
+                None => partition_witness.values[rep_index] = Some(value),
+            }
+        }
+    }
+
     prove_with_partition_witness(prover_data, common_data, partition_witness, timing)
 }
 
@@ -135,7 +441,132 @@ pub fn prove_with_partition_witness<
 >(
     prover_data: &ProverOnlyCircuitData<F, C, D>,
     common_data: &CommonCircuitData<F, D>,
-    mut partition_witness: PartitionWitness<F>,
+    partition_witness: PartitionWitness<F>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    prove_with_partition_witness_and_options(
+        prover_data,
+        common_data,
+        partition_witness,
+        &ProverOptions::default(),
+        timing,
+    )
+}
+
+pub fn prove_with_partition_witness_and_options<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    partition_witness: PartitionWitness<F>,
+    options: &ProverOptions<F>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    prove_with_partition_witness_and_buffers(
+        prover_data,
+        common_data,
+        partition_witness,
+        options,
+        &mut ProverBuffers::default(),
+        timing,
+    )
+}
+
+/// Like `prove_with_partition_witness_and_options`, but reuses the allocations in `buffers`.
+pub fn prove_with_partition_witness_and_buffers<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    partition_witness: PartitionWitness<F>,
+    options: &ProverOptions<F>,
+    buffers: &mut ProverBuffers<F, C::Hasher>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    prove_with_transcript_version(
+        prover_data,
+        common_data,
+        partition_witness,
+        options,
+        None,
+        buffers,
+        TRANSCRIPT_VERSION,
+        timing,
+    )
+}
+
+/// Like `prove_with_options`, but derives the FRI query indices with `query_index_sampler`. The
+/// proof only verifies under the same sampler, see `CircuitData::verify_with_query_index_sampler`.
+pub fn prove_with_query_index_sampler<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    options: &ProverOptions<F>,
+    query_index_sampler: Arc<dyn QueryIndexSampler<F, C::Hasher>>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    let partition_witness = timed!(
+        timing,
+        &format!("run {} generators", prover_data.generators.len()),
+        try_generate_partial_witness(
+            inputs,
+            prover_data,
+            common_data,
+            options.witness_generation_timeout,
+            options.salt_rng_seed,
+        )?
+    );
+
+    prove_with_transcript_version(
+        prover_data,
+        common_data,
+        partition_witness,
+        options,
+        Some(query_index_sampler),
+        &mut ProverBuffers::default(),
+        TRANSCRIPT_VERSION,
+        timing,
+    )
+}
+
+/// Generates a proof whose challenges are derived with the given transcript version, rather than
+/// the current one. Only tests need proofs of older transcript versions, to pin compatibility.
+pub(crate) fn prove_with_transcript_version<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    partition_witness: PartitionWitness<F>,
+    options: &ProverOptions<F>,
+    query_index_sampler: Option<Arc<dyn QueryIndexSampler<F, C::Hasher>>>,
+    buffers: &mut ProverBuffers<F, C::Hasher>,
+    transcript_version: u32,
     timing: &mut TimingTree,
 ) -> Result<ProofWithPublicInputs<F, C, D>>
 where
@@ -147,59 +578,136 @@ where
     let num_challenges = config.num_challenges;
     let quotient_degree = common_data.quotient_degree();
     let degree = common_data.degree();
+    let zeroize = options.zeroize_witness;
 
+    let mut partition_witness = SensitiveBuffers::new(partition_witness, zeroize);
     set_lookup_wires(prover_data, common_data, &mut partition_witness);
 
     let public_inputs = partition_witness.get_targets(&prover_data.public_inputs);
     let public_inputs_hash = C::InnerHasher::hash_no_pad(&public_inputs);
 
-    let witness = timed!(
-        timing,
-        "compute full witness",
-        partition_witness.full_witness()
+    let mut witness = SensitiveBuffers::new(
+        timed!(
+            timing,
+            "compute full witness",
+            partition_witness.full_witness_into(core::mem::take(&mut buffers.wire_values))
+        ),
+        zeroize,
     );
 
+    if options.verify_after_prove && cfg!(debug_assertions) {
+        let violations = timed!(
+            timing,
+            "check gate constraints on witness",
+            check_constraints(&witness, prover_data, common_data, &public_inputs_hash)
+        );
+        ensure!(
+            violations.is_empty(),
+            "{} gate constraint(s) violated by the witness, first: {:?}",
+            violations.len(),
+            violations[0]
+        );
+    }
+
+    let mut wire_polys = core::mem::take(&mut buffers.wire_polys);
+    wire_polys.resize_with(witness.wire_values.len(), Vec::new);
     let wires_values: Vec<PolynomialValues<F>> = timed!(
         timing,
         "compute wire polynomials",
-        witness
-            .wire_values
-            .par_iter()
-            .map(|column| PolynomialValues::new(column.clone()))
+        wire_polys
+            .into_par_iter()
+            .zip(&witness.wire_values)
+            .map(|(mut values, column)| {
+                values.clear();
+                values.extend_from_slice(column);
+                PolynomialValues::new(values)
+            })
             .collect()
     );
 
-    let wires_commitment = timed!(
-        timing,
-        "compute wires commitment",
-        PolynomialBatch::<F, C, D>::from_values(
-            wires_values,
-            config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::WIRES.blinding,
-            config.fri_config.cap_height,
+    let mut wires_commitment = SensitiveBuffers::new(
+        timed!(
             timing,
-            prover_data.fft_root_table.as_ref(),
-        )
+            "compute wires commitment",
+            PolynomialBatch::<F, C, D>::from_values_with_buffers(
+                wires_values,
+                config.fri_config.rate_bits,
+                config.zero_knowledge && PlonkOracle::WIRES.blinding,
+                config.fri_config.cap_height,
+                timing,
+                prover_data.fft_root_table.as_ref(),
+                options.salt_rng_seed,
+                PlonkOracle::WIRES.index,
+                zeroize,
+                buffers.batch(PlonkOracle::WIRES),
+            )
+        ),
+        zeroize,
     );
 
-    let mut challenger = Challenger::<F, C::Hasher>::new();
+    let mut challenger = Challenger::<F, C::Hasher>::with_transcript::<C::Transcript>();
+    challenger.set_pow_context(options.pow_context.clone());
+    if let Some(sampler) = &query_index_sampler {
+        challenger.set_query_index_sampler(sampler.clone());
+    }
+
+    observe_transcript_version(&mut challenger, transcript_version)?;
 
     // Observe the instance.
+    challenger.label(b"circuit_digest");
     challenger.observe_hash::<C::Hasher>(prover_data.circuit_digest);
+    challenger.label(b"public_inputs_hash");
     challenger.observe_hash::<C::InnerHasher>(public_inputs_hash);
 
+    challenger.label(b"wires_cap");
     challenger.observe_cap::<C::Hasher>(&wires_commitment.merkle_tree.cap);
 
+    let (challenge_dependent_challenges, mut challenge_dependent_commitment) =
+        if common_data.has_challenge_dependent_columns() {
+            challenger.label(b"challenge_dependent_challenges");
+            let challenges =
+                challenger.get_n_challenges(common_data.num_challenge_dependent_challenges());
+            let columns = timed!(
+                timing,
+                "generate challenge-dependent columns",
+                generate_challenge_dependent_columns(common_data, &challenges, &witness)
+            );
+            let commitment = timed!(
+                timing,
+                "commit to challenge-dependent columns",
+                PolynomialBatch::<F, C, D>::from_values_with_buffers(
+                    columns,
+                    config.fri_config.rate_bits,
+                    config.zero_knowledge && PlonkOracle::CHALLENGE_DEPENDENT.blinding,
+                    config.fri_config.cap_height,
+                    timing,
+                    prover_data.fft_root_table.as_ref(),
+                    options.salt_rng_seed,
+                    PlonkOracle::CHALLENGE_DEPENDENT.index,
+                    zeroize,
+                    buffers.batch(PlonkOracle::CHALLENGE_DEPENDENT),
+                )
+            );
+            challenger.label(b"challenge_dependent_cap");
+            challenger.observe_cap::<C::Hasher>(&commitment.merkle_tree.cap);
+            (challenges, Some(SensitiveBuffers::new(commitment, zeroize)))
+        } else {
+            (vec![], None)
+        };
+
     // We need 4 values per challenge: 2 for the combos, 1 for (X-combo) in the accumulators and 1 to prove that the lookup table was computed correctly.
     // We can reuse betas and gammas for two of them.
     let num_lookup_challenges = NUM_COINS_LOOKUP * num_challenges;
 
+    challenger.label(b"plonk_betas");
     let betas = challenger.get_n_challenges(num_challenges);
+    challenger.label(b"plonk_gammas");
     let gammas = challenger.get_n_challenges(num_challenges);
 
     let deltas = if has_lookup {
         let mut delts = Vec::with_capacity(2 * num_challenges);
         let num_additional_challenges = num_lookup_challenges - 2 * num_challenges;
+        challenger.label(b"plonk_deltas");
         let additional = challenger.get_n_challenges(num_additional_challenges);
         delts.extend(&betas);
         delts.extend(&gammas);
@@ -224,49 +732,65 @@ where
         .iter_mut()
         .map(|partial_products_and_z| partial_products_and_z.pop().unwrap())
         .collect();
-    let zs_partial_products = [plonk_z_vecs, partial_products_and_zs.concat()].concat();
+    // The polynomials are moved rather than cloned, so that no copies are left behind unwiped.
+    let mut zs_partial_products_lookups: Vec<_> = plonk_z_vecs;
+    zs_partial_products_lookups.extend(partial_products_and_zs.into_iter().flatten());
 
     // All lookup polys: RE and partial SLDCs.
-    let lookup_polys =
-        compute_all_lookup_polys(&witness, &deltas, prover_data, common_data, has_lookup);
-
-    let zs_partial_products_lookups = if has_lookup {
-        [zs_partial_products, lookup_polys].concat()
-    } else {
-        zs_partial_products
-    };
+    if has_lookup {
+        zs_partial_products_lookups.extend(compute_all_lookup_polys(
+            &witness,
+            &deltas,
+            prover_data,
+            common_data,
+            has_lookup,
+        ));
+    }
 
-    let partial_products_zs_and_lookup_commitment = timed!(
-        timing,
-        "commit to partial products, Z's and, if any, lookup polynomials",
-        PolynomialBatch::from_values(
-            zs_partial_products_lookups,
-            config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::ZS_PARTIAL_PRODUCTS.blinding,
-            config.fri_config.cap_height,
+    let mut partial_products_zs_and_lookup_commitment = SensitiveBuffers::new(
+        timed!(
             timing,
-            prover_data.fft_root_table.as_ref(),
-        )
+            "commit to partial products, Z's and, if any, lookup polynomials",
+            PolynomialBatch::from_values_with_buffers(
+                zs_partial_products_lookups,
+                config.fri_config.rate_bits,
+                config.zero_knowledge && PlonkOracle::ZS_PARTIAL_PRODUCTS.blinding,
+                config.fri_config.cap_height,
+                timing,
+                prover_data.fft_root_table.as_ref(),
+                options.salt_rng_seed,
+                PlonkOracle::ZS_PARTIAL_PRODUCTS.index,
+                zeroize,
+                buffers.batch(PlonkOracle::ZS_PARTIAL_PRODUCTS),
+            )
+        ),
+        zeroize,
     );
 
+    challenger.label(b"plonk_zs_partial_products_cap");
     challenger.observe_cap::<C::Hasher>(&partial_products_zs_and_lookup_commitment.merkle_tree.cap);
 
+    challenger.label(b"plonk_alphas");
     let alphas = challenger.get_n_challenges(num_challenges);
 
     let quotient_polys = timed!(
         timing,
         "compute quotient polys",
-        compute_quotient_polys::<F, C, D>(
+        QuotientComputation::<F, C, D> {
             common_data,
             prover_data,
-            &public_inputs_hash,
-            &wires_commitment,
-            &partial_products_zs_and_lookup_commitment,
-            &betas,
-            &gammas,
-            &deltas,
-            &alphas,
-        )
+            public_inputs_hash: &public_inputs_hash,
+            wires_commitment: &wires_commitment,
+            zs_partial_products_and_lookup_commitment: &partial_products_zs_and_lookup_commitment,
+            challenge_dependent_commitment: challenge_dependent_commitment.as_deref(),
+            betas: &betas,
+            gammas: &gammas,
+            deltas: &deltas,
+            challenge_dependent_challenges: &challenge_dependent_challenges,
+            alphas: &alphas,
+            block_bits: QUOTIENT_BLOCK_BITS,
+        }
+        .compute()
     );
 
     let all_quotient_poly_chunks: Vec<PolynomialCoeffs<F>> = timed!(
@@ -279,33 +803,46 @@ where
                     "Quotient has failed, the vanishing polynomial is not divisible by Z_H",
                 );
                 // Split quotient into degree-n chunks.
-                quotient_poly.chunks(degree)
+                let chunks = quotient_poly.chunks(degree);
+                if zeroize {
+                    wipe_vec(&mut quotient_poly.coeffs, F::ZERO);
+                }
+                chunks
             })
             .collect()
     );
 
-    let quotient_polys_commitment = timed!(
-        timing,
-        "commit to quotient polys",
-        PolynomialBatch::<F, C, D>::from_coeffs(
-            all_quotient_poly_chunks,
-            config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::QUOTIENT.blinding,
-            config.fri_config.cap_height,
+    let mut quotient_polys_commitment = SensitiveBuffers::new(
+        timed!(
             timing,
-            prover_data.fft_root_table.as_ref(),
-        )
+            "commit to quotient polys",
+            PolynomialBatch::<F, C, D>::from_coeffs_with_buffers(
+                all_quotient_poly_chunks,
+                config.fri_config.rate_bits,
+                config.zero_knowledge && PlonkOracle::QUOTIENT.blinding,
+                config.fri_config.cap_height,
+                timing,
+                prover_data.fft_root_table.as_ref(),
+                options.salt_rng_seed,
+                PlonkOracle::QUOTIENT.index,
+                zeroize,
+                buffers.batch(PlonkOracle::QUOTIENT),
+            )
+        ),
+        zeroize,
     );
 
+    challenger.label(b"quotient_polys_cap");
     challenger.observe_cap::<C::Hasher>(&quotient_polys_commitment.merkle_tree.cap);
 
+    challenger.label(b"plonk_zeta");
     let zeta = challenger.get_extension_challenge::<D>();
     // To avoid leaking witness data, we want to ensure that our opening locations, `zeta` and
     // `g * zeta`, are not in our subgroup `H`. It suffices to check `zeta` only, since
     // `(g * zeta)^n = zeta^n`, where `n` is the order of `g`.
-    let g = F::Extension::primitive_root_of_unity(common_data.degree_bits());
+    let g = F::Extension::primitive_root_of_unity(common_data.degree_bits().get());
     ensure!(
-        zeta.exp_power_of_2(common_data.degree_bits()) != F::Extension::ONE,
+        zeta.exp_power_of_2(common_data.degree_bits().get()) != F::Extension::ONE,
         "Opening point is in the subgroup."
     );
 
@@ -319,40 +856,175 @@ where
             &wires_commitment,
             &partial_products_zs_and_lookup_commitment,
             &quotient_polys_commitment,
+            challenge_dependent_commitment.as_deref(),
             common_data
         )
     );
+    challenger.label(b"openings");
     challenger.observe_openings(&openings.to_fri_openings());
     let instance = common_data.get_fri_instance(zeta);
 
+    let mut oracles = vec![
+        &prover_data.constants_sigmas_commitment,
+        &*wires_commitment,
+        &*partial_products_zs_and_lookup_commitment,
+        &*quotient_polys_commitment,
+    ];
+    oracles.extend(challenge_dependent_commitment.as_deref());
     let opening_proof = timed!(
         timing,
         "compute opening proofs",
-        PolynomialBatch::<F, C, D>::prove_openings(
+        PolynomialBatch::<F, C, D>::prove_openings_with_zeroize(
             &instance,
-            &[
-                &prover_data.constants_sigmas_commitment,
-                &wires_commitment,
-                &partial_products_zs_and_lookup_commitment,
-                &quotient_polys_commitment,
-            ],
+            &oracles,
             &mut challenger,
             &common_data.fri_params,
+            zeroize,
             timing,
         )
     );
 
     let proof = Proof::<F, C, D> {
-        wires_cap: wires_commitment.merkle_tree.cap,
-        plonk_zs_partial_products_cap: partial_products_zs_and_lookup_commitment.merkle_tree.cap,
-        quotient_polys_cap: quotient_polys_commitment.merkle_tree.cap,
+        wires_cap: wires_commitment.merkle_tree.cap.clone(),
+        challenge_dependent_cap: challenge_dependent_commitment
+            .as_ref()
+            .map_or(MerkleCap(vec![]), |c| c.merkle_tree.cap.clone()),
+        plonk_zs_partial_products_cap: partial_products_zs_and_lookup_commitment
+            .merkle_tree
+            .cap
+            .clone(),
+        quotient_polys_cap: quotient_polys_commitment.merkle_tree.cap.clone(),
         openings,
         opening_proof,
     };
-    Ok(ProofWithPublicInputs::<F, C, D> {
+    let proof_with_pis = ProofWithPublicInputs::<F, C, D> {
         proof,
         public_inputs,
-    })
+    };
+
+    // Hand the large allocations back for the next proof. The rest of the witness data is wiped
+    // when dropped, if `zeroize` is set.
+    buffers.wire_values = core::mem::take(&mut witness.wire_values);
+    buffers.wire_polys = core::mem::take(&mut wires_commitment.polynomials)
+        .into_iter()
+        .map(|poly| poly.coeffs)
+        .collect();
+    buffers
+        .batch(PlonkOracle::WIRES)
+        .recycle(&mut wires_commitment.merkle_tree);
+    buffers
+        .batch(PlonkOracle::ZS_PARTIAL_PRODUCTS)
+        .recycle(&mut partial_products_zs_and_lookup_commitment.merkle_tree);
+    buffers
+        .batch(PlonkOracle::QUOTIENT)
+        .recycle(&mut quotient_polys_commitment.merkle_tree);
+    if let Some(commitment) = &mut challenge_dependent_commitment {
+        buffers
+            .batch(PlonkOracle::CHALLENGE_DEPENDENT)
+            .recycle(&mut commitment.merkle_tree);
+    }
+    if zeroize {
+        buffers.wipe();
+    }
+
+    if options.verify_after_prove {
+        let verifier_data = VerifierOnlyCircuitData {
+            constants_sigmas_cap: prover_data
+                .constants_sigmas_commitment
+                .merkle_tree
+                .cap
+                .clone(),
+            circuit_digest: prover_data.circuit_digest,
+        };
+        timed!(timing, "verify freshly generated proof", {
+            let mut challenger = Challenger::with_transcript::<C::Transcript>();
+            challenger.set_pow_context(options.pow_context.clone());
+            if let Some(sampler) = query_index_sampler {
+                challenger.set_query_index_sampler(sampler);
+            }
+            verify_with_challenger(
+                proof_with_pis.clone(),
+                &verifier_data,
+                common_data,
+                challenger,
+            )?
+        });
+    }
+
+    Ok(proof_with_pis)
+}
+
+/// Evaluates every gate's constraints directly on the witness, at every row, and returns all
+/// constraints which do not vanish. This does not check copy constraints or lookups.
+pub fn check_constraints<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    witness: &MatrixWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    public_inputs_hash: &HashOut<F>,
+) -> Vec<ConstraintViolation<F>> {
+    let num_selectors = common_data.selectors_info.num_selectors();
+    // Recover the values of the constant polynomials (including selectors) on each row.
+    let constant_values: Vec<Vec<F>> = prover_data.constants_sigmas_commitment.polynomials
+        [common_data.constants_range()]
+    .par_iter()
+    .map(|poly| poly.clone().fft().values)
+    .collect();
+
+    (0..common_data.degree())
+        .into_par_iter()
+        .flat_map_iter(|row| {
+            let local_constants: Vec<F::Extension> = constant_values
+                .iter()
+                .map(|values| F::Extension::from_basefield(values[row]))
+                .collect();
+            let local_wires: Vec<F::Extension> = (0..common_data.config.num_wires)
+                .map(|column| F::Extension::from_basefield(witness.get_wire(row, column)))
+                .collect();
+            let next_row = (row + 1) % common_data.degree();
+            let next_wires: Vec<F::Extension> = (0..common_data.num_next_row_wires())
+                .map(|column| F::Extension::from_basefield(witness.get_wire(next_row, column)))
+                .collect();
+
+            let mut violations = Vec::new();
+            for (gate_index, gate) in common_data.gates.iter().enumerate() {
+                let selector_index = common_data.selectors_info.selector_indices[gate_index];
+                let filter = compute_filter(
+                    gate_index,
+                    common_data.selectors_info.groups[selector_index].clone(),
+                    local_constants[selector_index],
+                    num_selectors > 1,
+                );
+                if filter.is_zero() {
+                    continue;
+                }
+
+                let vars = EvaluationVars {
+                    local_constants: &local_constants
+                        [num_selectors + common_data.num_lookup_selectors..],
+                    local_wires: &local_wires,
+                    next_wires: &next_wires,
+                    public_inputs_hash,
+                };
+                for (constraint_index, value) in
+                    gate.0.eval_unfiltered(vars).into_iter().enumerate()
+                {
+                    if !value.is_zero() {
+                        violations.push(ConstraintViolation {
+                            gate: gate.0.id(),
+                            row,
+                            constraint_index,
+                            value: value.to_basefield_array()[0],
+                        });
+                    }
+                }
+            }
+            violations
+        })
+        .collect()
 }
 
 /// Compute the partial products used in the `Z` polynomials.
@@ -580,8 +1252,8 @@ fn compute_all_lookup_polys<
     lookup: bool,
 ) -> Vec<PolynomialValues<F>> {
     if lookup {
-        let polys: Vec<Vec<PolynomialValues<F>>> = (0..common_data.config.num_challenges)
-            .map(|c| {
+        (0..common_data.config.num_challenges)
+            .flat_map(|c| {
                 compute_lookup_polys(
                     witness,
                     &deltas[c * NUM_COINS_LOOKUP..(c + 1) * NUM_COINS_LOOKUP]
@@ -591,8 +1263,7 @@ fn compute_all_lookup_polys<
                     common_data,
                 )
             })
-            .collect();
-        polys.concat()
+            .collect()
     } else {
         vec![]
     }
@@ -600,210 +1271,628 @@ fn compute_all_lookup_polys<
 
 const BATCH_SIZE: usize = 32;
 
-fn compute_quotient_polys<
+/// The default `QuotientComputation::block_bits`.
+pub const QUOTIENT_BLOCK_BITS: usize = 12;
+
+/// Computes the quotient polynomials, i.e. the vanishing polynomial of each challenge divided by
+/// `Z_H`, from the committed oracles and the challenges.
+///
+/// The quotients are evaluated over the LDE domain in blocks of `2^block_bits` consecutive rows,
+/// each handled by one rayon task. Within a block, the constraints are evaluated `BATCH_SIZE` rows
+/// at a time with the gates' packed batch evaluation, and the quotient values are written straight
+/// into the per-challenge columns which are then interpolated. Apart from those columns, a worker
+/// thus holds fewer than `num_challenges * 2^block_bits` intermediate values at once.
+pub struct QuotientComputation<
     'a,
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
     const D: usize,
->(
-    common_data: &CommonCircuitData<F, D>,
-    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
-    public_inputs_hash: &<<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
-    wires_commitment: &'a PolynomialBatch<F, C, D>,
-    zs_partial_products_and_lookup_commitment: &'a PolynomialBatch<F, C, D>,
-    betas: &[F],
-    gammas: &[F],
-    deltas: &[F],
-    alphas: &[F],
-) -> Vec<PolynomialCoeffs<F>> {
-    let num_challenges = common_data.config.num_challenges;
+> {
+    pub common_data: &'a CommonCircuitData<F, D>,
+    pub prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    pub public_inputs_hash: &'a <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+    pub wires_commitment: &'a PolynomialBatch<F, C, D>,
+    pub zs_partial_products_and_lookup_commitment: &'a PolynomialBatch<F, C, D>,
+    pub challenge_dependent_commitment: Option<&'a PolynomialBatch<F, C, D>>,
+    pub betas: &'a [F],
+    pub gammas: &'a [F],
+    pub deltas: &'a [F],
+    pub challenge_dependent_challenges: &'a [F],
+    pub alphas: &'a [F],
+    /// The log of the number of rows evaluated by each rayon task. The quotients don't depend on
+    /// it.
+    pub block_bits: usize,
+}
 
-    let has_lookup = common_data.num_lookup_polys != 0;
+impl<'a, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    QuotientComputation<'a, F, C, D>
+{
+    /// Returns the quotient polynomial of each challenge.
+    pub fn compute(&self) -> Vec<PolynomialCoeffs<F>> {
+        let common_data = self.common_data;
+        let num_challenges = common_data.config.num_challenges;
+        let has_lookup = common_data.num_lookup_polys != 0;
+
+        let quotient_degree_bits = log2_ceil(common_data.quotient_degree_factor);
+        assert!(
+            quotient_degree_bits <= common_data.config.fri_config.rate_bits,
+            "Having constraints of degree higher than the rate is not supported yet. \
+            If we need this in the future, we can precompute the larger LDE before computing the `PolynomialBatch`s."
+        );
+
+        // We reuse the LDE computed in `PolynomialBatch` and extract every `step` points to get
+        // an LDE matching `max_filtered_constraint_degree`.
+        let step = 1 << (common_data.config.fri_config.rate_bits - quotient_degree_bits);
+        // When opening the `Z`s polys at the "next" point in Plonk, need to look at the point `next_step`
+        // steps away since we work on an LDE of degree `max_filtered_constraint_degree`.
+        let next_step = 1 << quotient_degree_bits;
+
+        let lde_bits = common_data.degree_bits().get() + quotient_degree_bits;
+        let lde_size = 1 << lde_bits;
+
+        let z_h_on_coset =
+            ZeroPolyOnCoset::new(common_data.degree_bits().get(), quotient_degree_bits);
+
+        // Precompute the lookup table evals on the challenges in delta
+        // These values are used to produce the final RE constraints for each lut,
+        // and are the same each time in check_lookup_constraints_batched.
+        // lut_poly_evals[i][j] gives the eval for the i'th challenge and the j'th lookup table
+        let lut_re_poly_evals: Vec<Vec<F>> = if has_lookup {
+            let num_lut_slots = LookupTableGate::num_slots(&common_data.config);
+            (0..num_challenges)
+                .map(move |i| {
+                    let cur_deltas = &self.deltas[NUM_COINS_LOOKUP * i..NUM_COINS_LOOKUP * (i + 1)];
+                    let cur_challenge_delta = cur_deltas[LookupChallenges::ChallengeDelta as usize];
+
+                    (LookupSelectors::StartEnd as usize..common_data.num_lookup_selectors)
+                        .map(|r| {
+                            let lut_row_number = ceil_div_usize(
+                                common_data.luts[r - LookupSelectors::StartEnd as usize].len(),
+                                num_lut_slots,
+                            );
+
+                            get_lut_poly(
+                                common_data,
+                                r - LookupSelectors::StartEnd as usize,
+                                cur_deltas,
+                                num_lut_slots * lut_row_number,
+                            )
+                            .eval(cur_challenge_delta)
+                        })
+                        .collect()
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        let lut_re_poly_evals_refs: Vec<&[F]> =
+            lut_re_poly_evals.iter().map(|v| v.as_slice()).collect();
+
+        // Each task gets the same block of every challenge's column, so that the values never
+        // have to be transposed.
+        let block_size = 1 << self.block_bits.min(lde_bits);
+        let mut quotient_values = vec![vec![F::ZERO; lde_size]; num_challenges];
+        let mut blocks: Vec<Vec<&mut [F]>> = (0..lde_size / block_size)
+            .map(|_| Vec::with_capacity(num_challenges))
+            .collect();
+        for column in &mut quotient_values {
+            for (block, column_block) in blocks.iter_mut().zip(column.chunks_mut(block_size)) {
+                block.push(column_block);
+            }
+        }
 
-    let quotient_degree_bits = log2_ceil(common_data.quotient_degree_factor);
-    assert!(
-        quotient_degree_bits <= common_data.config.fri_config.rate_bits,
-        "Having constraints of degree higher than the rate is not supported yet. \
-        If we need this in the future, we can precompute the larger LDE before computing the `PolynomialBatch`s."
-    );
+        let g = F::primitive_root_of_unity(lde_bits);
+        blocks
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(block_i, mut block)| {
+                let block_start = block_i * block_size;
+                let mut shifted_x = F::coset_shift() * g.exp_u64(block_start as u64);
+                for batch_start in (block_start..block_start + block_size).step_by(BATCH_SIZE) {
+                    let batch_len = BATCH_SIZE.min(block_start + block_size - batch_start);
+                    let indices_batch: Vec<usize> =
+                        (batch_start..batch_start + batch_len).collect();
+                    let shifted_xs_batch: Vec<F> = (0..batch_len)
+                        .map(|_| {
+                            let x = shifted_x;
+                            shifted_x *= g;
+                            x
+                        })
+                        .collect();
+
+                    let quotient_values_batch = self.eval_vanishing_poly_batch(
+                        &indices_batch,
+                        &shifted_xs_batch,
+                        step,
+                        next_step,
+                        &z_h_on_coset,
+                        &lut_re_poly_evals_refs,
+                    );
+                    for (&i, values) in indices_batch.iter().zip(quotient_values_batch) {
+                        let denominator_inv = z_h_on_coset.eval_inverse(i);
+                        for (column_block, v) in block.iter_mut().zip(values) {
+                            column_block[i - block_start] = v * denominator_inv;
+                        }
+                    }
+                }
+            });
 
-    // We reuse the LDE computed in `PolynomialBatch` and extract every `step` points to get
-    // an LDE matching `max_filtered_constraint_degree`.
-    let step = 1 << (common_data.config.fri_config.rate_bits - quotient_degree_bits);
-    // When opening the `Z`s polys at the "next" point in Plonk, need to look at the point `next_step`
-    // steps away since we work on an LDE of degree `max_filtered_constraint_degree`.
-    let next_step = 1 << quotient_degree_bits;
-
-    let points = F::two_adic_subgroup(common_data.degree_bits() + quotient_degree_bits);
-    let lde_size = points.len();
-
-    let z_h_on_coset = ZeroPolyOnCoset::new(common_data.degree_bits(), quotient_degree_bits);
-
-    // Precompute the lookup table evals on the challenges in delta
-    // These values are used to produce the final RE constraints for each lut,
-    // and are the same each time in check_lookup_constraints_batched.
-    // lut_poly_evals[i][j] gives the eval for the i'th challenge and the j'th lookup table
-    let lut_re_poly_evals: Vec<Vec<F>> = if has_lookup {
-        let num_lut_slots = LookupTableGate::num_slots(&common_data.config);
-        (0..num_challenges)
-            .map(move |i| {
-                let cur_deltas = &deltas[NUM_COINS_LOOKUP * i..NUM_COINS_LOOKUP * (i + 1)];
-                let cur_challenge_delta = cur_deltas[LookupChallenges::ChallengeDelta as usize];
-
-                (LookupSelectors::StartEnd as usize..common_data.num_lookup_selectors)
-                    .map(|r| {
-                        let lut_row_number = ceil_div_usize(
-                            common_data.luts[r - LookupSelectors::StartEnd as usize].len(),
-                            num_lut_slots,
-                        );
-
-                        get_lut_poly(
-                            common_data,
-                            r - LookupSelectors::StartEnd as usize,
-                            cur_deltas,
-                            num_lut_slots * lut_row_number,
-                        )
-                        .eval(cur_challenge_delta)
-                    })
-                    .collect()
-            })
+        // The columns share one root table, rather than each computing its own.
+        let root_table = fft_root_table(lde_size);
+        quotient_values
+            .into_par_iter()
+            .map(PolynomialValues::new)
+            .map(|values| values.coset_ifft_with_options(F::coset_shift(), Some(&root_table)))
             .collect()
-    } else {
-        vec![]
-    };
+    }
 
-    let lut_re_poly_evals_refs: Vec<&[F]> =
-        lut_re_poly_evals.iter().map(|v| v.as_slice()).collect();
+    /// Evaluates the vanishing polynomial of each challenge at the given rows of the quotient LDE,
+    /// whose points are `shifted_xs_batch`.
+    fn eval_vanishing_poly_batch(
+        &self,
+        indices_batch: &[usize],
+        shifted_xs_batch: &[F],
+        step: usize,
+        next_step: usize,
+        z_h_on_coset: &ZeroPolyOnCoset<F>,
+        lut_re_poly_evals: &[&[F]],
+    ) -> Vec<Vec<F>> {
+        let common_data = self.common_data;
+        let num_challenges = common_data.config.num_challenges;
+        let num_next_row_wires = common_data.num_next_row_wires();
+        let has_lookup = common_data.num_lookup_polys != 0;
+        let lde_size = common_data.degree() * next_step;
+        let batch_len = indices_batch.len();
+
+        let mut local_zs_batch = Vec::with_capacity(batch_len);
+        let mut next_zs_batch = Vec::with_capacity(batch_len);
+
+        let mut local_lookup_batch = Vec::with_capacity(batch_len);
+        let mut next_lookup_batch = Vec::with_capacity(batch_len);
+
+        let mut local_challenge_dependent_batch = Vec::with_capacity(batch_len);
+        let mut next_challenge_dependent_batch = Vec::with_capacity(batch_len);
+
+        let mut partial_products_batch = Vec::with_capacity(batch_len);
+        let mut s_sigmas_batch = Vec::with_capacity(batch_len);
+
+        let mut local_constants_batch_refs = Vec::with_capacity(batch_len);
+        let mut local_wires_batch_refs = Vec::with_capacity(batch_len);
+        let mut next_wires_batch_refs = Vec::with_capacity(batch_len);
+
+        for &i in indices_batch {
+            let i_next = (i + next_step) % lde_size;
+            let local_constants_sigmas = self
+                .prover_data
+                .constants_sigmas_commitment
+                .get_lde_values(i, step);
+            let local_constants = &local_constants_sigmas[common_data.constants_range()];
+            let s_sigmas = &local_constants_sigmas[common_data.sigmas_range()];
+            let local_wires = self.wires_commitment.get_lde_values(i, step);
+            let local_zs_partial_and_lookup = self
+                .zs_partial_products_and_lookup_commitment
+                .get_lde_values(i, step);
+            let next_zs_partial_and_lookup = self
+                .zs_partial_products_and_lookup_commitment
+                .get_lde_values(i_next, step);
+
+            let local_zs = &local_zs_partial_and_lookup[common_data.zs_range()];
+
+            let next_zs = &next_zs_partial_and_lookup[common_data.zs_range()];
+
+            let partial_products =
+                &local_zs_partial_and_lookup[common_data.partial_products_range()];
+
+            if has_lookup {
+                let local_lookup_zs = &local_zs_partial_and_lookup[common_data.lookup_range()];
+
+                let next_lookup_zs = &next_zs_partial_and_lookup[common_data.lookup_range()];
+                debug_assert_eq!(local_lookup_zs.len(), common_data.num_all_lookup_polys());
+
+                local_lookup_batch.push(local_lookup_zs);
+                next_lookup_batch.push(next_lookup_zs);
+            }
 
-    let points_batches = points.par_chunks(BATCH_SIZE);
-    let num_batches = ceil_div_usize(points.len(), BATCH_SIZE);
+            if let Some(commitment) = self.challenge_dependent_commitment {
+                local_challenge_dependent_batch.push(commitment.get_lde_values(i, step));
+                next_challenge_dependent_batch.push(commitment.get_lde_values(i_next, step));
+            }
 
-    let quotient_values: Vec<Vec<F>> = points_batches
-        .enumerate()
-        .flat_map(|(batch_i, xs_batch)| {
-            // Each batch must be the same size, except the last one, which may be smaller.
-            debug_assert!(
-                xs_batch.len() == BATCH_SIZE
-                    || (batch_i == num_batches - 1 && xs_batch.len() <= BATCH_SIZE)
-            );
+            debug_assert_eq!(local_wires.len(), common_data.config.num_wires);
+            debug_assert_eq!(local_zs.len(), num_challenges);
 
-            let indices_batch: Vec<usize> =
-                (BATCH_SIZE * batch_i..BATCH_SIZE * batch_i + xs_batch.len()).collect();
+            local_constants_batch_refs.push(local_constants);
+            local_wires_batch_refs.push(local_wires);
+            next_wires_batch_refs
+                .push(&self.wires_commitment.get_lde_values(i_next, step)[..num_next_row_wires]);
 
-            let mut shifted_xs_batch = Vec::with_capacity(xs_batch.len());
-            let mut local_zs_batch = Vec::with_capacity(xs_batch.len());
-            let mut next_zs_batch = Vec::with_capacity(xs_batch.len());
+            local_zs_batch.push(local_zs);
+            next_zs_batch.push(next_zs);
+            partial_products_batch.push(partial_products);
+            s_sigmas_batch.push(s_sigmas);
+        }
 
-            let mut local_lookup_batch = Vec::with_capacity(xs_batch.len());
-            let mut next_lookup_batch = Vec::with_capacity(xs_batch.len());
+        // NB (JN): I'm not sure how (in)efficient the below is. It needs measuring.
+        let mut local_constants_batch =
+            vec![F::ZERO; batch_len * local_constants_batch_refs[0].len()];
+        for i in 0..local_constants_batch_refs[0].len() {
+            for (j, constants) in local_constants_batch_refs.iter().enumerate() {
+                local_constants_batch[i * batch_len + j] = constants[i];
+            }
+        }
 
-            let mut partial_products_batch = Vec::with_capacity(xs_batch.len());
-            let mut s_sigmas_batch = Vec::with_capacity(xs_batch.len());
+        let mut local_wires_batch = vec![F::ZERO; batch_len * local_wires_batch_refs[0].len()];
+        for i in 0..local_wires_batch_refs[0].len() {
+            for (j, wires) in local_wires_batch_refs.iter().enumerate() {
+                local_wires_batch[i * batch_len + j] = wires[i];
+            }
+        }
 
-            let mut local_constants_batch_refs = Vec::with_capacity(xs_batch.len());
-            let mut local_wires_batch_refs = Vec::with_capacity(xs_batch.len());
+        let mut next_wires_batch = vec![F::ZERO; batch_len * num_next_row_wires];
+        for i in 0..num_next_row_wires {
+            for (j, wires) in next_wires_batch_refs.iter().enumerate() {
+                next_wires_batch[i * batch_len + j] = wires[i];
+            }
+        }
 
-            for (&i, &x) in indices_batch.iter().zip(xs_batch) {
-                let shifted_x = F::coset_shift() * x;
-                let i_next = (i + next_step) % lde_size;
-                let local_constants_sigmas = prover_data
-                    .constants_sigmas_commitment
-                    .get_lde_values(i, step);
-                let local_constants = &local_constants_sigmas[common_data.constants_range()];
-                let s_sigmas = &local_constants_sigmas[common_data.sigmas_range()];
-                let local_wires = wires_commitment.get_lde_values(i, step);
-                let local_zs_partial_and_lookup =
-                    zs_partial_products_and_lookup_commitment.get_lde_values(i, step);
-                let next_zs_partial_and_lookup =
-                    zs_partial_products_and_lookup_commitment.get_lde_values(i_next, step);
+        let vars_batch = EvaluationVarsBaseBatch::new(
+            batch_len,
+            &local_constants_batch,
+            &local_wires_batch,
+            &next_wires_batch,
+            self.public_inputs_hash,
+        );
 
-                let local_zs = &local_zs_partial_and_lookup[common_data.zs_range()];
+        eval_vanishing_poly_base_batch::<F, D>(
+            common_data,
+            indices_batch,
+            shifted_xs_batch,
+            vars_batch,
+            &local_zs_batch,
+            &next_zs_batch,
+            &local_lookup_batch,
+            &next_lookup_batch,
+            &local_challenge_dependent_batch,
+            &next_challenge_dependent_batch,
+            &partial_products_batch,
+            &s_sigmas_batch,
+            self.betas,
+            self.gammas,
+            self.deltas,
+            self.challenge_dependent_challenges,
+            self.alphas,
+            z_h_on_coset,
+            lut_re_poly_evals,
+        )
+    }
+}
 
-                let next_zs = &next_zs_partial_and_lookup[common_data.zs_range()];
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
 
-                let partial_products =
-                    &local_zs_partial_and_lookup[common_data.partial_products_range()];
+    use super::*;
+    use crate::gates::arithmetic_base::ArithmeticGate;
+    use crate::iop::generator::{
+        generate_partial_witness, GeneratedValues, SimpleGenerator, WitnessGeneratorRef,
+    };
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::util::serialization::{Buffer, IoResult};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// A buggy replacement for `ArithmeticBaseGenerator` which is off by one.
+    #[derive(Debug)]
+    struct OffByOneGenerator {
+        row: usize,
+    }
 
-                if has_lookup {
-                    let local_lookup_zs = &local_zs_partial_and_lookup[common_data.lookup_range()];
+    impl SimpleGenerator<F, D> for OffByOneGenerator {
+        fn id(&self) -> String {
+            "OffByOneGenerator".into()
+        }
 
-                    let next_lookup_zs = &next_zs_partial_and_lookup[common_data.lookup_range()];
-                    debug_assert_eq!(local_lookup_zs.len(), common_data.num_all_lookup_polys());
+        fn dependencies(&self) -> Vec<Target> {
+            vec![
+                Target::wire(self.row, ArithmeticGate::wire_ith_multiplicand_0(0)),
+                Target::wire(self.row, ArithmeticGate::wire_ith_multiplicand_1(0)),
+            ]
+        }
 
-                    local_lookup_batch.push(local_lookup_zs);
-                    next_lookup_batch.push(next_lookup_zs);
-                }
+        fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+            let x = witness.get_target(self.dependencies()[0]);
+            let y = witness.get_target(self.dependencies()[1]);
+            out_buffer.set_target(
+                Target::wire(self.row, ArithmeticGate::wire_ith_output(0)),
+                x * y + F::ONE,
+            );
+        }
 
-                debug_assert_eq!(local_wires.len(), common_data.config.num_wires);
-                debug_assert_eq!(local_zs.len(), num_challenges);
+        fn serialize(
+            &self,
+            _dst: &mut Vec<u8>,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<()> {
+            unimplemented!()
+        }
 
-                local_constants_batch_refs.push(local_constants);
-                local_wires_batch_refs.push(local_wires);
+        fn deserialize(
+            _src: &mut Buffer,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<Self> {
+            unimplemented!()
+        }
+    }
 
-                shifted_xs_batch.push(shifted_x);
-                local_zs_batch.push(local_zs);
-                next_zs_batch.push(next_zs);
-                partial_products_batch.push(partial_products);
-                s_sigmas_batch.push(s_sigmas);
-            }
+    #[test]
+    fn test_check_constraints_reports_violation() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let gate = ArithmeticGate::new_from_config(&config);
+        let num_ops = gate.num_ops;
+        let row = builder.add_gate(gate, vec![F::ONE, F::ZERO]);
+        let mut data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        for i in 0..num_ops {
+            pw.set_target(
+                Target::wire(row, ArithmeticGate::wire_ith_multiplicand_0(i)),
+                F::TWO,
+            );
+            pw.set_target(
+                Target::wire(row, ArithmeticGate::wire_ith_multiplicand_1(i)),
+                F::from_canonical_u64(3),
+            );
+            pw.set_target(
+                Target::wire(row, ArithmeticGate::wire_ith_addend(i)),
+                F::ZERO,
+            );
+        }
 
-            // NB (JN): I'm not sure how (in)efficient the below is. It needs measuring.
-            let mut local_constants_batch =
-                vec![F::ZERO; xs_batch.len() * local_constants_batch_refs[0].len()];
-            for i in 0..local_constants_batch_refs[0].len() {
-                for (j, constants) in local_constants_batch_refs.iter().enumerate() {
-                    local_constants_batch[i * xs_batch.len() + j] = constants[i];
-                }
-            }
+        // Swap the honest generator of the first operation for the buggy one.
+        let output = Target::wire(row, ArithmeticGate::wire_ith_output(0));
+        let generator = data
+            .prover_only
+            .generators
+            .iter_mut()
+            .find(|g| {
+                g.0.watch_list()
+                    .contains(&Target::wire(row, ArithmeticGate::wire_ith_addend(0)))
+            })
+            .unwrap();
+        *generator = WitnessGeneratorRef::new(OffByOneGenerator { row }.adapter());
+
+        let partition_witness =
+            generate_partial_witness(pw.clone(), &data.prover_only, &data.common);
+        assert_eq!(
+            partition_witness.get_target(output),
+            F::from_canonical_u64(7)
+        );
+        let witness = partition_witness.full_witness();
+        let public_inputs_hash = <C as GenericConfig<D>>::InnerHasher::hash_no_pad(&[]);
+        let violations = check_constraints(
+            &witness,
+            &data.prover_only,
+            &data.common,
+            &public_inputs_hash,
+        );
+        assert_eq!(violations.len(), 1);
+        let violation = &violations[0];
+        assert!(violation.gate.starts_with("ArithmeticGate"));
+        assert_eq!(violation.row, row);
+        assert_eq!(violation.constraint_index, 0);
+        assert_eq!(violation.value, F::ONE);
+
+        #[cfg(debug_assertions)]
+        {
+            let options = ProverOptions {
+                verify_after_prove: true,
+                ..Default::default()
+            };
+            assert!(data.prove_with_options(pw, &options).is_err());
+        }
+    }
 
-            let mut local_wires_batch =
-                vec![F::ZERO; xs_batch.len() * local_wires_batch_refs[0].len()];
-            for i in 0..local_wires_batch_refs[0].len() {
-                for (j, wires) in local_wires_batch_refs.iter().enumerate() {
-                    local_wires_batch[i * xs_batch.len() + j] = wires[i];
-                }
-            }
+    #[test]
+    fn test_verify_after_prove() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let z = builder.mul(x, y);
+        builder.register_public_input(z);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::TWO);
+        pw.set_target(y, F::from_canonical_u64(21));
+        let options = ProverOptions {
+            verify_after_prove: true,
+            ..Default::default()
+        };
+        let proof = data.prove_with_options(pw, &options)?;
+        assert_eq!(proof.public_inputs, vec![F::from_canonical_u64(42)]);
+        data.verify(proof)
+    }
 
-            let vars_batch = EvaluationVarsBaseBatch::new(
-                xs_batch.len(),
-                &local_constants_batch,
-                &local_wires_batch,
-                public_inputs_hash,
-            );
+    #[test]
+    fn test_pow_context_binding() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let context_a = vec![F::from_canonical_u64(19_000_000)];
+        let context_b = vec![F::from_canonical_u64(19_000_001)];
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let options = ProverOptions {
+            verify_after_prove: true,
+            pow_context: context_a.clone(),
+            ..Default::default()
+        };
+        let proof = data.prove_with_options(pw, &options)?;
+
+        data.verify_with_pow_context(proof.clone(), &context_a)?;
+        assert!(data
+            .verify_with_pow_context(proof.clone(), &context_b)
+            .is_err());
+        assert!(data.verify(proof.clone()).is_err());
+        // The context is length-prefixed, so appending a zero is a different context.
+        let mut padded = context_a.clone();
+        padded.push(F::ZERO);
+        assert!(data.verify_with_pow_context(proof, &padded).is_err());
+        Ok(())
+    }
 
-            let mut quotient_values_batch = eval_vanishing_poly_base_batch::<F, D>(
-                common_data,
-                &indices_batch,
-                &shifted_xs_batch,
-                vars_batch,
-                &local_zs_batch,
-                &next_zs_batch,
-                &local_lookup_batch,
-                &next_lookup_batch,
-                &partial_products_batch,
-                &s_sigmas_batch,
-                betas,
-                gammas,
-                deltas,
-                alphas,
-                &z_h_on_coset,
-                &lut_re_poly_evals_refs,
-            );
+    #[test]
+    fn test_salt_rng_seed() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_zk_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let prove_with_seed = |seed| {
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(3));
+            let options = ProverOptions {
+                salt_rng_seed: seed,
+                ..Default::default()
+            };
+            data.prove_with_options(pw, &options)
+        };
+
+        let proof = prove_with_seed(Some(1))?;
+        let bytes = proof.to_bytes();
+        assert_eq!(prove_with_seed(Some(1))?.to_bytes(), bytes);
+        assert_ne!(prove_with_seed(Some(2))?.to_bytes(), bytes);
+        data.verify(proof)
+    }
 
-            for (&i, quotient_values) in indices_batch.iter().zip(quotient_values_batch.iter_mut())
-            {
-                let denominator_inv = z_h_on_coset.eval_inverse(i);
-                quotient_values
-                    .iter_mut()
-                    .for_each(|v| *v *= denominator_inv);
-            }
-            quotient_values_batch
-        })
-        .collect();
+    #[test]
+    fn test_prover_buffers_reuse() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.mul(x, x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut buffers = ProverBuffers::new(&data.common);
+
+        let canary = F::from_canonical_u64(0xdead_beef);
+        let contains_canary = |buffers: &ProverBuffers<F, <C as GenericConfig<D>>::Hasher>| {
+            buffers.wire_values.iter().flatten().any(|&v| v == canary)
+        };
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, canary);
+        let proof = data.prove_with_buffers(pw, &mut buffers)?;
+        data.verify(proof)?;
+        assert!(contains_canary(&buffers));
+        let wire_values_ptr = buffers.wire_values[0].as_ptr();
+        let wire_polys_ptr = buffers.wire_polys[0].as_ptr();
+
+        // The next proof reuses the allocations, and leaves nothing of the previous witness.
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove_with_buffers(pw, &mut buffers)?;
+        assert_eq!(proof.public_inputs, vec![F::from_canonical_u64(9)]);
+        data.verify(proof)?;
+        assert!(!contains_canary(&buffers));
+        assert_eq!(buffers.wire_values[0].as_ptr(), wire_values_ptr);
+        assert_eq!(buffers.wire_polys[0].as_ptr(), wire_polys_ptr);
+
+        buffers.wipe();
+        assert!(buffers.values().all(|v| v.is_zero()));
+
+        Ok(())
+    }
 
-    transpose(&quotient_values)
-        .into_par_iter()
-        .map(PolynomialValues::new)
-        .map(|values| values.coset_ifft(F::coset_shift()))
-        .collect()
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_zeroize_witness() -> Result<()> {
+        use crate::util::sensitive::inspection;
+
+        let config = CircuitConfig::standard_recursion_zk_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.mul(x, x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut buffers = ProverBuffers::new(&data.common);
+
+        const CANARY: u64 = 0xdead_beef;
+        let mut prove = |zeroize_witness| {
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(CANARY));
+            let options = ProverOptions {
+                zeroize_witness,
+                ..ProverOptions::default()
+            };
+            inspection::record(CANARY, || {
+                prove_with_buffers::<F, C, D>(
+                    &data.prover_only,
+                    &data.common,
+                    pw,
+                    &options,
+                    &mut buffers,
+                    &mut TimingTree::default(),
+                )
+            })
+        };
+        let inspected = |remains: &[inspection::Remains], name: &str| {
+            remains
+                .iter()
+                .filter(|r| r.type_name.contains(name))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        // Without the option, the witness and the salts are still there when the buffers are
+        // dropped, so the checks below can tell.
+        let (proof, remains) = prove(false);
+        proof?;
+        assert!(inspected(&remains, "PartitionWitness")
+            .iter()
+            .any(|r| r.contains_canary));
+        assert!(inspected(&remains, "PolynomialBatch")
+            .iter()
+            .any(|r| r.num_nonzero > 0));
+
+        let (proof, remains) = prove(true);
+        let proof = proof?;
+        for name in [
+            "PartitionWitness",
+            "MatrixWitness",
+            "PolynomialBatch",
+            "MerkleTree",
+        ] {
+            assert!(
+                !inspected(&remains, name).is_empty(),
+                "{name} not inspected"
+            );
+        }
+        // Every buffer, including the LDEs and salts of the oracles, was wiped.
+        for r in &remains {
+            assert_eq!(r.num_nonzero, 0, "{} not wiped", r.type_name);
+        }
+
+        // Neither the reused buffers nor the proof retain the witness.
+        assert!(buffers.values().all(|v| v.is_zero()));
+        let canary_bytes = CANARY.to_le_bytes();
+        assert!(!proof
+            .to_bytes()
+            .windows(canary_bytes.len())
+            .any(|w| w == canary_bytes));
+        data.verify(proof)
+    }
 }
@@ -0,0 +1,293 @@
+//! Versioning of the Fiat-Shamir transcript of Plonk proofs.
+//!
+//! Proofs may be stored long after they were generated, so a change to the way challenges are
+//! derived must not silently invalidate them. The prover absorbs [`TRANSCRIPT_VERSION`] before
+//! anything else, so any change to the transcript which bumps the version changes every challenge,
+//! and verifiers can keep accepting proofs of older versions through a
+//! [`TranscriptVersionPolicy`].
+//!
+//! The version must be bumped whenever the sequence of observations and challenges changes, and
+//! the golden fixtures in this module's tests must then be regenerated.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use anyhow::ensure;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::challenger::{Challenger, RecursiveChallenger};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, Hasher};
+
+/// The version of the transcript used by proofs generated with this release.
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+/// The transcript of proofs generated before transcripts were versioned. It absorbs no version, and
+/// is otherwise identical to version 1.
+pub const LEGACY_TRANSCRIPT_VERSION: u32 = 0;
+
+/// All transcript versions this release can derive challenges for.
+pub const KNOWN_TRANSCRIPT_VERSIONS: [u32; 2] = [LEGACY_TRANSCRIPT_VERSION, TRANSCRIPT_VERSION];
+
+/// Which transcript versions a verifier accepts. Proofs don't record their version, so a proof is
+/// accepted if it verifies under any accepted version.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum TranscriptVersionPolicy {
+    /// Only accept proofs using [`TRANSCRIPT_VERSION`].
+    #[default]
+    Current,
+
+    /// Accept proofs using any of [`KNOWN_TRANSCRIPT_VERSIONS`], including proofs generated before
+    /// transcripts were versioned.
+    AllKnown,
+
+    /// Accept proofs using any of the given versions. Unknown versions are rejected.
+    Only(Vec<u32>),
+}
+
+impl TranscriptVersionPolicy {
+    /// The accepted versions, in the order in which a verifier tries them.
+    pub fn versions(&self) -> Vec<u32> {
+        match self {
+            Self::Current => vec![TRANSCRIPT_VERSION],
+            // Most proofs are expected to be recent, so try the current version first.
+            Self::AllKnown => KNOWN_TRANSCRIPT_VERSIONS.iter().rev().copied().collect(),
+            Self::Only(versions) => versions.clone(),
+        }
+    }
+}
+
+/// Absorbs the transcript version, which must be the first observation of the transcript.
+pub(crate) fn observe_transcript_version<F: RichField, H: Hasher<F>>(
+    challenger: &mut Challenger<F, H>,
+    version: u32,
+) -> anyhow::Result<()> {
+    ensure!(
+        KNOWN_TRANSCRIPT_VERSIONS.contains(&version),
+        "Unknown transcript version {}",
+        version
+    );
+    if version != LEGACY_TRANSCRIPT_VERSION {
+        challenger.label(b"transcript_version");
+        challenger.observe_element(F::from_canonical_u32(version));
+    }
+    Ok(())
+}
+
+/// Absorbs the current transcript version in circuit.
+pub(crate) fn observe_transcript_version_circuit<
+    F: RichField + Extendable<D>,
+    H: AlgebraicHasher<F>,
+    const D: usize,
+>(
+    builder: &mut CircuitBuilder<F, D>,
+    challenger: &mut RecursiveChallenger<F, H, D>,
+) {
+    let version = builder.constant(F::from_canonical_u32(TRANSCRIPT_VERSION));
+    challenger.observe_element(version);
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::fri::reduction_strategies::FriReductionStrategy;
+    use crate::fri::{FriConfig, FriPowStrategy};
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::proof::{ChallengesSummary, ProofWithPublicInputs};
+    use crate::util::serialization::{Buffer, Read, Write};
+    #[cfg(feature = "std")]
+    use crate::util::test_fixtures::write_fixture;
+    use crate::util::test_fixtures::{
+        fixture_circuit, fixture_config, fixture_fri_config, prove_fixture, C, D, F,
+    };
+
+    /// A golden transcript: a proof of [`fixture_circuit`] generated with the current transcript
+    /// version, and the challenges derived from it. The prover randomizes some witness values, so
+    /// the proof bytes are pinned by the fixture rather than by proving again.
+    struct Fixture {
+        name: &'static str,
+        config: CircuitConfig,
+        proof: &'static [u8],
+        challenges: &'static str,
+    }
+
+    fn fri_config_b() -> FriConfig {
+        FriConfig {
+            rate_bits: 3,
+            cap_height: 0,
+            pow_strategy: FriPowStrategy::PerRound(vec![2, 2]),
+            reduction_strategy: FriReductionStrategy::Fixed(vec![1, 2]),
+            num_query_rounds: 3,
+            commit_final_poly: true,
+        }
+    }
+
+    fn fixtures() -> [Fixture; 4] {
+        [
+            Fixture {
+                name: "fri_a",
+                config: fixture_config(false, fixture_fri_config()),
+                proof: include_bytes!("fixtures/transcript_v1_fri_a.bin"),
+                challenges: include_str!("fixtures/transcript_v1_fri_a.json"),
+            },
+            Fixture {
+                name: "fri_a_zk",
+                config: fixture_config(true, fixture_fri_config()),
+                proof: include_bytes!("fixtures/transcript_v1_fri_a_zk.bin"),
+                challenges: include_str!("fixtures/transcript_v1_fri_a_zk.json"),
+            },
+            Fixture {
+                name: "fri_b",
+                config: fixture_config(false, fri_config_b()),
+                proof: include_bytes!("fixtures/transcript_v1_fri_b.bin"),
+                challenges: include_str!("fixtures/transcript_v1_fri_b.json"),
+            },
+            Fixture {
+                name: "fri_b_zk",
+                config: fixture_config(true, fri_config_b()),
+                proof: include_bytes!("fixtures/transcript_v1_fri_b_zk.bin"),
+                challenges: include_str!("fixtures/transcript_v1_fri_b_zk.json"),
+            },
+        ]
+    }
+
+    /// The fixtures hold the proof alone, without a `ProofHeader`, as the transcript doesn't depend
+    /// on the container format.
+    fn proof_body_bytes(proof: &ProofWithPublicInputs<F, C, D>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes
+            .write_proof_with_public_inputs(proof)
+            .expect("Writing to a byte-vector cannot fail.");
+        bytes
+    }
+
+    fn summarize(
+        proof: &ProofWithPublicInputs<F, C, D>,
+        data: &CircuitData<F, C, D>,
+        version: u32,
+    ) -> Result<ChallengesSummary> {
+        let challenges = proof.get_challenges_for_version(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+            version,
+        )?;
+        Ok(ChallengesSummary::from(&challenges))
+    }
+
+    #[test]
+    fn test_transcript_fixtures() -> Result<()> {
+        for fixture in fixtures() {
+            let (data, _) = fixture_circuit(fixture.config, 3);
+            let proof = Buffer::new(fixture.proof)
+                .read_proof_with_public_inputs::<F, C, D>(&data.common)
+                .map_err(anyhow::Error::msg)?;
+            assert_eq!(proof_body_bytes(&proof), fixture.proof, "{}", fixture.name);
+
+            let expected: ChallengesSummary =
+                serde_json::from_str(fixture.challenges).map_err(anyhow::Error::msg)?;
+            assert_eq!(
+                summarize(&proof, &data, TRANSCRIPT_VERSION)?,
+                expected,
+                "Challenges of {} changed; the transcript version must be bumped",
+                fixture.name
+            );
+            data.verify(proof.clone())?;
+
+            // The legacy transcript derives different challenges, under which the proof is
+            // invalid.
+            assert_ne!(
+                summarize(&proof, &data, LEGACY_TRANSCRIPT_VERSION)?,
+                expected
+            );
+            let mut verifier_data = data.verifier_data();
+            verifier_data.accepted_transcript_versions =
+                TranscriptVersionPolicy::Only(vec![LEGACY_TRANSCRIPT_VERSION]);
+            assert!(verifier_data.verify(proof.clone()).is_err());
+            verifier_data.accepted_transcript_versions = TranscriptVersionPolicy::AllKnown;
+            verifier_data.verify(proof)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_transcript_version_policy() -> Result<()> {
+        let (data, x) = fixture_circuit(fixture_config(false, fixture_fri_config()), 3);
+        let proof = prove_fixture(&data, x)?;
+        let compressed = proof
+            .clone()
+            .compress(&data.verifier_only.circuit_digest, &data.common)?;
+
+        let mut verifier_data = data.verifier_data();
+        for policy in [
+            TranscriptVersionPolicy::Current,
+            TranscriptVersionPolicy::AllKnown,
+            TranscriptVersionPolicy::Only(vec![LEGACY_TRANSCRIPT_VERSION, TRANSCRIPT_VERSION]),
+        ] {
+            verifier_data.accepted_transcript_versions = policy;
+            verifier_data.verify(proof.clone())?;
+            verifier_data.verify_compressed(compressed.clone())?;
+        }
+
+        for policy in [
+            TranscriptVersionPolicy::Only(vec![]),
+            TranscriptVersionPolicy::Only(vec![LEGACY_TRANSCRIPT_VERSION]),
+            TranscriptVersionPolicy::Only(vec![TRANSCRIPT_VERSION + 1]),
+        ] {
+            verifier_data.accepted_transcript_versions = policy;
+            assert!(verifier_data.verify(proof.clone()).is_err());
+            assert!(verifier_data.verify_compressed(compressed.clone()).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_num_transcript_observations() -> Result<()> {
+        for fixture in fixtures() {
+            let (data, x) = fixture_circuit(fixture.config, 3);
+            let proof = prove_fixture(&data, x)?;
+            let mut challenger = Challenger::new();
+            proof.get_challenges_with_challenger(
+                &mut challenger,
+                proof.get_public_inputs_hash(),
+                &data.verifier_only.circuit_digest,
+                &data.common,
+                TRANSCRIPT_VERSION,
+            )?;
+            assert_eq!(
+                challenger.num_observations(),
+                data.common
+                    .fri_params
+                    .num_transcript_observations::<F, C, D>(&data.common),
+                "{}",
+                fixture.name
+            );
+        }
+        Ok(())
+    }
+
+    /// Regenerates the golden fixtures, after a deliberate change of the transcript version.
+    #[cfg(feature = "std")]
+    #[test]
+    #[ignore]
+    fn regenerate_transcript_fixtures() -> Result<()> {
+        for fixture in fixtures() {
+            let (data, x) = fixture_circuit(fixture.config, 3);
+            let proof = prove_fixture(&data, x)?;
+            let summary = summarize(&proof, &data, TRANSCRIPT_VERSION)?;
+            let stem = format!("transcript_v{}_{}", TRANSCRIPT_VERSION, fixture.name);
+            write_fixture(
+                &format!("plonk/fixtures/{stem}.bin"),
+                proof_body_bytes(&proof),
+            )?;
+            write_fixture(
+                &format!("plonk/fixtures/{stem}.json"),
+                serde_json::to_string_pretty(&summary)? + "\n",
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -37,6 +37,7 @@ where
     let config = &common_data.config;
     let Proof {
         wires_cap,
+        challenge_dependent_cap,
         plonk_zs_partial_products_cap,
         quotient_polys_cap,
         openings,
@@ -48,25 +49,36 @@ where
         constants,
         plonk_sigmas,
         wires,
+        wires_next,
         plonk_zs,
         plonk_zs_next,
         partial_products,
         quotient_polys,
         lookup_zs,
         lookup_zs_next,
+        challenge_dependent,
+        challenge_dependent_next,
     } = openings;
     let cap_height = common_data.fri_params.config.cap_height;
-    ensure!(wires_cap.height() == cap_height);
-    ensure!(plonk_zs_partial_products_cap.height() == cap_height);
-    ensure!(quotient_polys_cap.height() == cap_height);
+    ensure!(wires_cap.len() == 1 << cap_height);
+    if common_data.has_challenge_dependent_columns() {
+        ensure!(challenge_dependent_cap.len() == 1 << cap_height);
+    } else {
+        ensure!(challenge_dependent_cap.0.is_empty());
+    }
+    ensure!(plonk_zs_partial_products_cap.len() == 1 << cap_height);
+    ensure!(quotient_polys_cap.len() == 1 << cap_height);
     ensure!(constants.len() == common_data.num_constants);
     ensure!(plonk_sigmas.len() == config.num_routed_wires);
     ensure!(wires.len() == config.num_wires);
+    ensure!(wires_next.len() == common_data.num_next_row_wires());
     ensure!(plonk_zs.len() == config.num_challenges);
     ensure!(plonk_zs_next.len() == config.num_challenges);
     ensure!(partial_products.len() == config.num_challenges * common_data.num_partial_products);
     ensure!(quotient_polys.len() == common_data.num_quotient_polys());
     ensure!(lookup_zs.len() == common_data.num_all_lookup_polys());
     ensure!(lookup_zs_next.len() == common_data.num_all_lookup_polys());
+    ensure!(challenge_dependent.len() == common_data.num_challenge_dependent_columns());
+    ensure!(challenge_dependent_next.len() == common_data.num_challenge_dependent_columns());
     Ok(())
 }
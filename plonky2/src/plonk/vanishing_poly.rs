@@ -17,10 +17,13 @@ use crate::gates::selectors::LookupSelectors;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
+use crate::plonk::challenge_dependent::{
+    eval_challenge_dependent_constraints, eval_challenge_dependent_constraints_base,
+    eval_challenge_dependent_constraints_circuit, ChallengeDependentVars,
+};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CommonCircuitData;
 use crate::plonk::plonk_common;
-use crate::plonk::plonk_common::eval_l_0_circuit;
 use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBaseBatch};
 use crate::util::partial_products::{check_partial_products, check_partial_products_circuit};
 use crate::util::reducing::ReducingFactorTarget;
@@ -56,12 +59,15 @@ pub(crate) fn eval_vanishing_poly<F: RichField + Extendable<D>, const D: usize>(
     next_zs: &[F::Extension],
     local_lookup_zs: &[F::Extension],
     next_lookup_zs: &[F::Extension],
+    local_challenge_dependent: &[F::Extension],
+    next_challenge_dependent: &[F::Extension],
     partial_products: &[F::Extension],
     s_sigmas: &[F::Extension],
     betas: &[F],
     gammas: &[F],
     alphas: &[F],
     deltas: &[F],
+    challenge_dependent_challenges: &[F],
 ) -> Vec<F::Extension> {
     let has_lookup = common_data.num_lookup_polys != 0;
     let max_degree = common_data.quotient_degree_factor;
@@ -145,11 +151,27 @@ pub(crate) fn eval_vanishing_poly<F: RichField + Extendable<D>, const D: usize>(
         vanishing_partial_products_terms.extend(partial_product_checks);
     }
 
+    let challenge_dependent_challenges = challenge_dependent_challenges
+        .iter()
+        .map(|&c| c.into())
+        .collect::<Vec<_>>();
+    let challenge_dependent_terms = eval_challenge_dependent_constraints(
+        common_data,
+        ChallengeDependentVars {
+            challenges: &challenge_dependent_challenges,
+            l_0: l_0_x,
+            local_wires: vars.local_wires,
+            local_columns: local_challenge_dependent,
+            next_columns: next_challenge_dependent,
+        },
+    );
+
     let vanishing_terms = [
         vanishing_z_1_terms,
         vanishing_partial_products_terms,
         vanishing_all_lookup_terms,
         constraint_terms,
+        challenge_dependent_terms,
     ]
     .concat();
 
@@ -167,16 +189,20 @@ pub(crate) fn eval_vanishing_poly_base_batch<F: RichField + Extendable<D>, const
     next_zs_batch: &[&[F]],
     local_lookup_zs_batch: &[&[F]],
     next_lookup_zs_batch: &[&[F]],
+    local_challenge_dependent_batch: &[&[F]],
+    next_challenge_dependent_batch: &[&[F]],
     partial_products_batch: &[&[F]],
     s_sigmas_batch: &[&[F]],
     betas: &[F],
     gammas: &[F],
     deltas: &[F],
+    challenge_dependent_challenges: &[F],
     alphas: &[F],
     z_h_on_coset: &ZeroPolyOnCoset<F>,
     lut_re_poly_evals: &[&[F]],
 ) -> Vec<Vec<F>> {
     let has_lookup = common_data.num_lookup_polys != 0;
+    let has_challenge_dependent = common_data.has_challenge_dependent_columns();
 
     let n = indices_batch.len();
     assert_eq!(xs_batch.len(), n);
@@ -190,6 +216,13 @@ pub(crate) fn eval_vanishing_poly_base_batch<F: RichField + Extendable<D>, const
         assert_eq!(local_lookup_zs_batch.len(), 0);
         assert_eq!(next_lookup_zs_batch.len(), 0);
     }
+    if has_challenge_dependent {
+        assert_eq!(local_challenge_dependent_batch.len(), n);
+        assert_eq!(next_challenge_dependent_batch.len(), n);
+    } else {
+        assert_eq!(local_challenge_dependent_batch.len(), 0);
+        assert_eq!(next_challenge_dependent_batch.len(), 0);
+    }
     assert_eq!(partial_products_batch.len(), n);
     assert_eq!(s_sigmas_batch.len(), n);
 
@@ -308,11 +341,28 @@ pub(crate) fn eval_vanishing_poly_base_batch<F: RichField + Extendable<D>, const
             denominator_values.clear();
         }
 
+        let challenge_dependent_terms = if has_challenge_dependent {
+            let local_wires = vars.local_wires.into_iter().copied().collect::<Vec<_>>();
+            eval_challenge_dependent_constraints_base(
+                common_data,
+                ChallengeDependentVars {
+                    challenges: challenge_dependent_challenges,
+                    l_0: l_0_x,
+                    local_wires: &local_wires,
+                    local_columns: local_challenge_dependent_batch[k],
+                    next_columns: next_challenge_dependent_batch[k],
+                },
+            )
+        } else {
+            vec![]
+        };
+
         let vanishing_terms = vanishing_z_1_terms
             .iter()
             .chain(vanishing_partial_products_terms.iter())
             .chain(vanishing_all_lookup_terms.iter())
-            .chain(constraint_terms);
+            .chain(constraint_terms)
+            .chain(challenge_dependent_terms.iter());
         let res = plonk_common::reduce_with_powers_multi(vanishing_terms, alphas);
         res_batch.push(res);
 
@@ -781,24 +831,30 @@ pub(crate) fn get_lut_poly_circuit<F: RichField + Extendable<D>, const D: usize>
 /// linear combination of gate constraints, plus some other terms relating to the permutation
 /// argument. All such terms should vanish on `H`.
 ///
+/// `l_0_x` is the Lagrange basis `L_0(x)`, which the caller evaluates with `eval_l_0_circuit`
+/// unless the degree is itself a witness.
+///
 /// Assumes `x != 1`; if `x` could be 1 then this is unsound. This is fine if `x` is a random
 /// variable drawn from a sufficiently large domain.
 pub(crate) fn eval_vanishing_poly_circuit<F: RichField + Extendable<D>, const D: usize>(
     builder: &mut CircuitBuilder<F, D>,
     common_data: &CommonCircuitData<F, D>,
     x: ExtensionTarget<D>,
-    x_pow_deg: ExtensionTarget<D>,
+    l_0_x: ExtensionTarget<D>,
     vars: EvaluationTargets<D>,
     local_zs: &[ExtensionTarget<D>],
     next_zs: &[ExtensionTarget<D>],
     local_lookup_zs: &[ExtensionTarget<D>],
     next_lookup_zs: &[ExtensionTarget<D>],
+    local_challenge_dependent: &[ExtensionTarget<D>],
+    next_challenge_dependent: &[ExtensionTarget<D>],
     partial_products: &[ExtensionTarget<D>],
     s_sigmas: &[ExtensionTarget<D>],
     betas: &[Target],
     gammas: &[Target],
     alphas: &[Target],
     deltas: &[Target],
+    challenge_dependent_challenges: &[Target],
 ) -> Vec<ExtensionTarget<D>> {
     let has_lookup = common_data.num_lookup_polys != 0;
     let max_degree = common_data.quotient_degree_factor;
@@ -829,8 +885,6 @@ pub(crate) fn eval_vanishing_poly_circuit<F: RichField + Extendable<D>, const D:
     // The terms checking the partial products.
     let mut vanishing_partial_products_terms = Vec::new();
 
-    let l_0_x = eval_l_0_circuit(builder, common_data.degree(), x, x_pow_deg);
-
     // Holds `k[i] * x`.
     let mut s_ids = Vec::with_capacity(common_data.config.num_routed_wires);
     for j in 0..common_data.config.num_routed_wires {
@@ -899,11 +953,32 @@ pub(crate) fn eval_vanishing_poly_circuit<F: RichField + Extendable<D>, const D:
         vanishing_partial_products_terms.extend(partial_product_checks);
     }
 
+    let challenge_dependent_challenges = challenge_dependent_challenges
+        .iter()
+        .map(|&c| builder.convert_to_ext(c))
+        .collect::<Vec<_>>();
+    let challenge_dependent_terms = with_context!(
+        builder,
+        "evaluate challenge-dependent constraints",
+        eval_challenge_dependent_constraints_circuit(
+            builder,
+            common_data,
+            ChallengeDependentVars {
+                challenges: &challenge_dependent_challenges,
+                l_0: l_0_x,
+                local_wires: vars.local_wires,
+                local_columns: local_challenge_dependent,
+                next_columns: next_challenge_dependent,
+            },
+        )
+    );
+
     let vanishing_terms = [
         vanishing_z_1_terms,
         vanishing_partial_products_terms,
         vanishing_all_lookup_terms,
         constraint_terms,
+        challenge_dependent_terms,
     ]
     .concat();
 
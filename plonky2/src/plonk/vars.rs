@@ -14,6 +14,8 @@ use crate::util::strided_view::PackedStridedView;
 pub struct EvaluationVars<'a, F: RichField + Extendable<D>, const D: usize> {
     pub local_constants: &'a [F::Extension],
     pub local_wires: &'a [F::Extension],
+    /// The first `common_data.num_next_row_wires()` wires of the next row.
+    pub next_wires: &'a [F::Extension],
     pub public_inputs_hash: &'a HashOut<F>,
 }
 
@@ -25,6 +27,7 @@ pub struct EvaluationVarsBaseBatch<'a, F: Field> {
     batch_size: usize,
     pub local_constants: &'a [F],
     pub local_wires: &'a [F],
+    pub next_wires: &'a [F],
     pub public_inputs_hash: &'a HashOut<F>,
 }
 
@@ -33,6 +36,7 @@ pub struct EvaluationVarsBaseBatch<'a, F: Field> {
 pub struct EvaluationVarsBase<'a, F: Field> {
     pub local_constants: PackedStridedView<'a, F>,
     pub local_wires: PackedStridedView<'a, F>,
+    pub next_wires: PackedStridedView<'a, F>,
     pub public_inputs_hash: &'a HashOut<F>,
 }
 
@@ -43,6 +47,7 @@ pub struct EvaluationVarsBase<'a, F: Field> {
 pub struct EvaluationVarsBasePacked<'a, P: PackedField> {
     pub local_constants: PackedStridedView<'a, P>,
     pub local_wires: PackedStridedView<'a, P>,
+    pub next_wires: PackedStridedView<'a, P>,
     pub public_inputs_hash: &'a HashOut<P::Scalar>,
 }
 
@@ -66,14 +71,17 @@ impl<'a, F: Field> EvaluationVarsBaseBatch<'a, F> {
         batch_size: usize,
         local_constants: &'a [F],
         local_wires: &'a [F],
+        next_wires: &'a [F],
         public_inputs_hash: &'a HashOut<F>,
     ) -> Self {
         assert_eq!(local_constants.len() % batch_size, 0);
         assert_eq!(local_wires.len() % batch_size, 0);
+        assert_eq!(next_wires.len() % batch_size, 0);
         Self {
             batch_size,
             local_constants,
             local_wires,
+            next_wires,
             public_inputs_hash,
         }
     }
@@ -95,9 +103,11 @@ impl<'a, F: Field> EvaluationVarsBaseBatch<'a, F> {
         assert!(index < self.len());
         let local_constants = PackedStridedView::new(self.local_constants, self.len(), index);
         let local_wires = PackedStridedView::new(self.local_wires, self.len(), index);
+        let next_wires = PackedStridedView::new(self.next_wires, self.len(), index);
         EvaluationVarsBase {
             local_constants,
             local_wires,
+            next_wires,
             public_inputs_hash: self.public_inputs_hash,
         }
     }
@@ -190,9 +200,12 @@ impl<'a, P: PackedField> Iterator for EvaluationVarsBaseBatchIterPacked<'a, P> {
             );
             let local_wires =
                 PackedStridedView::new(self.vars_batch.local_wires, self.vars_batch.len(), self.i);
+            let next_wires =
+                PackedStridedView::new(self.vars_batch.next_wires, self.vars_batch.len(), self.i);
             let res = EvaluationVarsBasePacked {
                 local_constants,
                 local_wires,
+                next_wires,
                 public_inputs_hash: self.vars_batch.public_inputs_hash,
             };
             self.i += P::WIDTH;
@@ -223,6 +236,7 @@ impl<'a, const D: usize> EvaluationTargets<'a, D> {
 pub struct EvaluationTargets<'a, const D: usize> {
     pub local_constants: &'a [ExtensionTarget<D>],
     pub local_wires: &'a [ExtensionTarget<D>],
+    pub next_wires: &'a [ExtensionTarget<D>],
     pub public_inputs_hash: &'a HashOutTarget,
 }
 
@@ -1,31 +1,172 @@
 //! plonky2 verifier implementation.
 
-use anyhow::{ensure, Result};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, ensure, Result};
+use log::warn;
 
 use crate::field::extension::Extendable;
 use crate::field::types::Field;
+use crate::fri::query_index::QueryIndexSampler;
 use crate::fri::verifier::verify_fri_proof;
 use crate::hash::hash_types::RichField;
+use crate::hash::merkle_proofs::{verify_merkle_proof, MerkleProof};
+use crate::iop::challenger::Challenger;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
-use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
 use crate::plonk::plonk_common::reduce_with_powers;
 use crate::plonk::proof::{Proof, ProofChallenges, ProofWithPublicInputs};
+use crate::plonk::transcript::TRANSCRIPT_VERSION;
 use crate::plonk::validate_shape::validate_proof_with_pis_shape;
 use crate::plonk::vanishing_poly::eval_vanishing_poly;
 use crate::plonk::vars::EvaluationVars;
 
+/// Options trading verification cost for soundness.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VerifierOptions {
+    /// **Unsound when set below the configured number of query rounds.** If set, only the first
+    /// `max_queries_to_check` FRI query rounds are checked, and the remaining ones are ignored
+    /// beyond their shape. Each skipped round lowers the conjectured security by `rate_bits`, so
+    /// an accepted proof then only offers the soundness given in the [`VerificationReport`]; a
+    /// malicious prover can forge proofs passing a small number of rounds. Only use this where
+    /// accepting that lower soundness is an explicit decision.
+    pub max_queries_to_check: Option<usize>,
+}
+
+/// The soundness achieved by a successful verification with [`VerifierOptions`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerificationReport {
+    /// The number of FRI query rounds which were checked.
+    pub query_rounds_checked: usize,
+    /// The conjectured security of the verification, in bits, as in
+    /// `CircuitConfig::conjectured_security_bits` but counting only the checked query rounds.
+    pub conjectured_security_bits: usize,
+}
+
 pub(crate) fn verify<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     proof_with_pis: ProofWithPublicInputs<F, C, D>,
     verifier_data: &VerifierOnlyCircuitData<C, D>,
     common_data: &CommonCircuitData<F, D>,
 ) -> Result<()> {
-    validate_proof_with_pis_shape(&proof_with_pis, common_data)?;
+    verify_with_pow_context::<F, C, D>(proof_with_pis, verifier_data, common_data, &[])
+}
+
+/// Verifies a proof whose public inputs must be one of `allowed`, returning the index of the first
+/// match. This is the native counterpart of `CircuitBuilder::assert_public_inputs_in_set`.
+pub fn verify_proof_public_inputs_in_set<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof_with_pis: ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    allowed: &[Vec<F>],
+) -> Result<usize> {
+    let index = allowed
+        .iter()
+        .position(|pis| *pis == proof_with_pis.public_inputs)
+        .ok_or_else(|| anyhow!("The public inputs aren't in the allowed set"))?;
+    verify::<F, C, D>(proof_with_pis, verifier_data, common_data)?;
+    Ok(index)
+}
 
+/// Verifies a proof whose public inputs must be in the Merkle tree with root `root`, whose leaves
+/// are the elements of the allowed public inputs hashes, as in
+/// `CircuitBuilder::assert_public_inputs_in_merkle_set`.
+pub fn verify_proof_public_inputs_in_merkle_set<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof_with_pis: ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    index: usize,
+    merkle_proof: &MerkleProof<F, C::Hasher>,
+    root: <C::Hasher as Hasher<F>>::Hash,
+) -> Result<()> {
+    let leaf = proof_with_pis.get_public_inputs_hash().to_vec();
+    verify_merkle_proof(leaf, index, root, merkle_proof)?;
+    verify::<F, C, D>(proof_with_pis, verifier_data, common_data)
+}
+
+/// Verifies a proof whose FRI proof-of-work was bound to `pow_context`.
+pub(crate) fn verify_with_pow_context<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof_with_pis: ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    pow_context: &[F],
+) -> Result<()> {
+    let mut challenger = Challenger::with_transcript::<C::Transcript>();
+    challenger.set_pow_context(pow_context.to_vec());
+    verify_with_challenger::<F, C, D>(proof_with_pis, verifier_data, common_data, challenger)
+}
+
+/// Verifies a proof whose FRI query indices were derived with `query_index_sampler`.
+pub(crate) fn verify_with_query_index_sampler<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof_with_pis: ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    query_index_sampler: Arc<dyn QueryIndexSampler<F, C::Hasher>>,
+) -> Result<()> {
+    let mut challenger = Challenger::with_transcript::<C::Transcript>();
+    challenger.set_query_index_sampler(query_index_sampler);
+    verify_with_challenger::<F, C, D>(proof_with_pis, verifier_data, common_data, challenger)
+}
+
+/// Verifies a proof whose challenges are drawn from `challenger`, which must be configured like
+/// the prover's, for instance with the same PoW context and query index sampler.
+pub(crate) fn verify_with_challenger<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof_with_pis: ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    challenger: Challenger<F, C::Hasher>,
+) -> Result<()> {
     let public_inputs_hash = proof_with_pis.get_public_inputs_hash();
-    let challenges = proof_with_pis.get_challenges(
+    verify_with_public_inputs_hash::<F, C, D>(
+        proof_with_pis,
+        public_inputs_hash,
+        verifier_data,
+        common_data,
+        challenger,
+    )
+}
+
+/// Verifies a proof, taking the hash of its public inputs as given rather than recomputing it.
+pub(crate) fn verify_with_public_inputs_hash<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof_with_pis: ProofWithPublicInputs<F, C, D>,
+    public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    mut challenger: Challenger<F, C::Hasher>,
+) -> Result<()> {
+    validate_proof_with_pis_shape(&proof_with_pis, common_data)?;
+
+    let challenges = proof_with_pis.get_challenges_with_challenger(
+        &mut challenger,
         public_inputs_hash,
         &verifier_data.circuit_digest,
         common_data,
+        TRANSCRIPT_VERSION,
     )?;
 
     verify_with_challenges::<F, C, D>(
@@ -37,6 +178,94 @@ pub(crate) fn verify<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
     )
 }
 
+/// Verifies a proof, checking only as many FRI query rounds as `options` allows. The challenges
+/// are drawn from a copy of `challenger` for each of `transcript_versions` in turn, until one
+/// verifies, so `challenger` must be configured like the prover's.
+pub(crate) fn verify_with_options<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof_with_pis: ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    options: &VerifierOptions,
+    challenger: Challenger<F, C::Hasher>,
+    transcript_versions: &[u32],
+) -> Result<VerificationReport> {
+    validate_proof_with_pis_shape(&proof_with_pis, common_data)?;
+
+    let fri_config = &common_data.config.fri_config;
+    let query_rounds_checked = options
+        .max_queries_to_check
+        .map_or(fri_config.num_query_rounds, |max| {
+            max.min(fri_config.num_query_rounds)
+        });
+    if query_rounds_checked < fri_config.num_query_rounds {
+        warn!(
+            "Checking only {} of {} FRI query rounds; this verification is not fully sound",
+            query_rounds_checked, fri_config.num_query_rounds
+        );
+    }
+
+    let public_inputs_hash = proof_with_pis.get_public_inputs_hash();
+    let mut result = Err(anyhow!("No transcript version is accepted"));
+    for &version in transcript_versions {
+        let mut challenges = proof_with_pis.get_challenges_with_challenger(
+            &mut challenger.clone(),
+            public_inputs_hash,
+            &verifier_data.circuit_digest,
+            common_data,
+            version,
+        )?;
+        // The FRI verifier checks one query round per index.
+        challenges
+            .fri_challenges
+            .fri_query_indices
+            .truncate(query_rounds_checked);
+        result = verify_with_challenges::<F, C, D>(
+            proof_with_pis.proof.clone(),
+            public_inputs_hash,
+            challenges,
+            verifier_data,
+            common_data,
+        );
+        if result.is_ok() {
+            break;
+        }
+    }
+    result?;
+
+    let fri_field_bits = F::Extension::order().bits() as usize;
+    Ok(VerificationReport {
+        query_rounds_checked,
+        conjectured_security_bits: fri_field_bits
+            .min(fri_config.conjectured_query_security_bits_for_rounds(query_rounds_checked)),
+    })
+}
+
+/// Verifies a proof which may use any of the given transcript versions.
+pub(crate) fn verify_with_transcript_versions<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof_with_pis: ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    transcript_versions: &[u32],
+) -> Result<()> {
+    verify_with_options::<F, C, D>(
+        proof_with_pis,
+        verifier_data,
+        common_data,
+        &VerifierOptions::default(),
+        Challenger::with_transcript::<C::Transcript>(),
+        transcript_versions,
+    )?;
+    Ok(())
+}
+
 pub(crate) fn verify_with_challenges<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -53,6 +282,7 @@ pub(crate) fn verify_with_challenges<
     let vars = EvaluationVars {
         local_constants,
         local_wires,
+        next_wires: &proof.openings.wires_next,
         public_inputs_hash: &public_inputs_hash,
     };
     let local_zs = &proof.openings.plonk_zs;
@@ -71,19 +301,22 @@ pub(crate) fn verify_with_challenges<
         next_zs,
         local_lookup_zs,
         next_lookup_zs,
+        &proof.openings.challenge_dependent,
+        &proof.openings.challenge_dependent_next,
         partial_products,
         s_sigmas,
         &challenges.plonk_betas,
         &challenges.plonk_gammas,
         &challenges.plonk_alphas,
         &challenges.plonk_deltas,
+        &challenges.challenge_dependent_challenges,
     );
 
     // Check each polynomial identity, of the form `vanishing(x) = Z_H(x) quotient(x)`, at zeta.
     let quotient_polys_zeta = &proof.openings.quotient_polys;
     let zeta_pow_deg = challenges
         .plonk_zeta
-        .exp_power_of_2(common_data.degree_bits());
+        .exp_power_of_2(common_data.degree_bits().get());
     let z_h_zeta = zeta_pow_deg - F::Extension::ONE;
     // `quotient_polys_zeta` holds `num_challenges * quotient_degree_factor` evaluations.
     // Each chunk of `quotient_degree_factor` holds the evaluations of `t_0(zeta),...,t_{quotient_degree_factor-1}(zeta)`
@@ -97,22 +330,156 @@ pub(crate) fn verify_with_challenges<
         ensure!(vanishing_polys_zeta[i] == z_h_zeta * reduce_with_powers(chunk, zeta_pow_deg));
     }
 
-    let merkle_caps = &[
+    let mut merkle_caps = vec![
         verifier_data.constants_sigmas_cap.clone(),
         proof.wires_cap,
         // In the lookup case, `plonk_zs_partial_products_cap` should also include the lookup commitment.
         proof.plonk_zs_partial_products_cap,
         proof.quotient_polys_cap,
     ];
+    if common_data.has_challenge_dependent_columns() {
+        merkle_caps.push(proof.challenge_dependent_cap);
+    }
 
     verify_fri_proof::<F, C, D>(
         &common_data.get_fri_instance(challenges.plonk_zeta),
         &proof.openings.to_fri_openings(),
         &challenges.fri_challenges,
-        merkle_caps,
+        &merkle_caps,
         &proof.opening_proof,
         &common_data.fri_params,
     )?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iop::generator::generate_partial_witness;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::plonk::prover::{prove_with_transcript_version, ProverBuffers, ProverOptions};
+    use crate::plonk::transcript::{TranscriptVersionPolicy, LEGACY_TRANSCRIPT_VERSION};
+    use crate::util::timing::TimingTree;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_max_queries_to_check() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let num_query_rounds = config.fri_config.num_query_rounds;
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove(pw)?;
+
+        let full = data.verify_with_options(proof.clone(), &VerifierOptions::default())?;
+        assert_eq!(full.query_rounds_checked, num_query_rounds);
+        assert_eq!(
+            full.conjectured_security_bits,
+            config.conjectured_security_bits::<F, D>()
+        );
+
+        let options = VerifierOptions {
+            max_queries_to_check: Some(5),
+        };
+        let reduced = data.verify_with_options(proof.clone(), &options)?;
+        assert_eq!(reduced.query_rounds_checked, 5);
+        assert!(reduced.conjectured_security_bits < full.conjectured_security_bits);
+
+        // A tampered query round is only caught if it is checked.
+        let mut tampered = proof;
+        tampered.proof.opening_proof.query_round_proofs[num_query_rounds - 1]
+            .initial_trees_proof
+            .evals_proofs[0]
+            .0[0] += F::ONE;
+        data.verify_with_options(tampered.clone(), &options)?;
+        assert!(data
+            .verify_with_options(tampered.clone(), &VerifierOptions::default())
+            .is_err());
+        assert!(data.verify(tampered).is_err());
+        Ok(())
+    }
+
+    fn square_circuit() -> (CircuitData<F, C, D>, PartialWitness<F>) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        (builder.build::<C>(), pw)
+    }
+
+    #[test]
+    fn test_options_with_challenger() -> Result<()> {
+        let (data, pw) = square_circuit();
+        let pow_context = vec![F::from_canonical_u64(19_000_000)];
+        let prover_options = ProverOptions {
+            pow_context: pow_context.clone(),
+            ..Default::default()
+        };
+        let proof = data.prove_with_options(pw, &prover_options)?;
+        let options = VerifierOptions {
+            max_queries_to_check: Some(5),
+        };
+
+        let mut challenger = Challenger::with_transcript::<<C as GenericConfig<D>>::Transcript>();
+        challenger.set_pow_context(pow_context);
+        let report =
+            data.verify_with_options_and_challenger(proof.clone(), &options, challenger.clone())?;
+        assert_eq!(report.query_rounds_checked, 5);
+        data.verifier_data().verify_with_options_and_challenger(
+            proof.clone(),
+            &VerifierOptions::default(),
+            challenger,
+        )?;
+        // Without the PoW context, the verifier derives different challenges.
+        assert!(data.verify_with_options(proof.clone(), &options).is_err());
+        assert!(data
+            .verifier_data()
+            .verify_with_options(proof, &options)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_options_with_transcript_policy() -> Result<()> {
+        let (data, pw) = square_circuit();
+        let partition_witness = generate_partial_witness(pw, &data.prover_only, &data.common);
+        let proof = prove_with_transcript_version::<F, C, D>(
+            &data.prover_only,
+            &data.common,
+            partition_witness,
+            &ProverOptions::default(),
+            None,
+            &mut ProverBuffers::default(),
+            LEGACY_TRANSCRIPT_VERSION,
+            &mut TimingTree::default(),
+        )?;
+        let options = VerifierOptions {
+            max_queries_to_check: Some(5),
+        };
+
+        let mut verifier_data = data.verifier_data();
+        assert!(verifier_data
+            .verify_with_options(proof.clone(), &options)
+            .is_err());
+        verifier_data.accepted_transcript_versions = TranscriptVersionPolicy::AllKnown;
+        let report = verifier_data.verify_with_options(proof.clone(), &options)?;
+        assert_eq!(report.query_rounds_checked, 5);
+        verifier_data.verify_with_options(proof.clone(), &VerifierOptions::default())?;
+        // `CircuitData` only accepts the current transcript version, like `CircuitData::verify`.
+        assert!(data.verify_with_options(proof, &options).is_err());
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use alloc::vec::Vec;
 
+use anyhow::{ensure, Result};
 use itertools::Itertools;
 
 use crate::field::extension::Extendable;
@@ -12,7 +13,9 @@ use crate::hash::merkle_proofs::MerkleProofTarget;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
-use crate::plonk::circuit_data::{CommonCircuitData, VerifierCircuitTarget};
+use crate::plonk::circuit_data::{
+    CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
+};
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
 use crate::plonk::proof::{OpeningSetTarget, ProofTarget, ProofWithPublicInputsTarget};
 use crate::with_context;
@@ -73,6 +76,38 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         Ok(())
     }
 
+    /// Verifies `proof_with_pis` against the verifier data of one of `allowed_verifier_data`,
+    /// chosen by `selector`. The candidates are baked into the circuit as constants, and must all
+    /// have `inner_common_data` as their common data (see `check_compatible_shapes`). `selector` is
+    /// registered as a public input, so that the proof shows which circuit was verified.
+    ///
+    /// The candidate list is padded to a power of two by repeating its last element, so selectors
+    /// past the end select the last candidate.
+    pub fn verify_proof_with_selected_verifier_data<C: GenericConfig<D, F = F>>(
+        &mut self,
+        selector: Target,
+        proof_with_pis: &ProofWithPublicInputsTarget<D>,
+        allowed_verifier_data: &[VerifierOnlyCircuitData<C, D>],
+        inner_common_data: &CommonCircuitData<F, D>,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        assert!(
+            !allowed_verifier_data.is_empty(),
+            "At least one verifier data is required"
+        );
+        let mut candidates = allowed_verifier_data
+            .iter()
+            .map(|vd| self.constant_verifier_data(vd))
+            .collect::<Vec<_>>();
+        let last = candidates[candidates.len() - 1].clone();
+        candidates.resize(candidates.len().next_power_of_two(), last);
+
+        self.register_public_input(selector);
+        let selected_verifier_data = self.random_access_verifier_data(selector, candidates);
+        self.verify_proof::<C>(proof_with_pis, &selected_verifier_data, inner_common_data);
+    }
+
     /// Computes `if b { proof_with_pis0 } else { proof_with_pis1 }`.
     fn select_proof_with_pis(
         &mut self,
@@ -84,6 +119,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             proof:
                 ProofTarget {
                     wires_cap: wires_cap0,
+                    challenge_dependent_cap: challenge_dependent_cap0,
                     plonk_zs_partial_products_cap: plonk_zs_partial_products_cap0,
                     quotient_polys_cap: quotient_polys_cap0,
                     openings: openings0,
@@ -95,6 +131,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             proof:
                 ProofTarget {
                     wires_cap: wires_cap1,
+                    challenge_dependent_cap: challenge_dependent_cap1,
                     plonk_zs_partial_products_cap: plonk_zs_partial_products_cap1,
                     quotient_polys_cap: quotient_polys_cap1,
                     openings: openings1,
@@ -104,6 +141,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         } = proof_with_pis1;
         with_context!(self, "select proof", {
             let selected_wires_cap = self.select_cap(b, wires_cap0, wires_cap1);
+            let selected_challenge_dependent_cap =
+                self.select_cap(b, challenge_dependent_cap0, challenge_dependent_cap1);
             let selected_plonk_zs_partial_products_cap = self.select_cap(
                 b,
                 plonk_zs_partial_products_cap0,
@@ -118,6 +157,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             ProofWithPublicInputsTarget {
                 proof: ProofTarget {
                     wires_cap: selected_wires_cap,
+                    challenge_dependent_cap: selected_challenge_dependent_cap,
                     plonk_zs_partial_products_cap: selected_plonk_zs_partial_products_cap,
                     quotient_polys_cap: selected_quotient_polys_cap,
                     openings: selected_openings,
@@ -189,12 +229,23 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             constants: self.select_vec_ext(b, &os0.constants, &os1.constants),
             plonk_sigmas: self.select_vec_ext(b, &os0.plonk_sigmas, &os1.plonk_sigmas),
             wires: self.select_vec_ext(b, &os0.wires, &os1.wires),
+            wires_next: self.select_vec_ext(b, &os0.wires_next, &os1.wires_next),
             plonk_zs: self.select_vec_ext(b, &os0.plonk_zs, &os1.plonk_zs),
             plonk_zs_next: self.select_vec_ext(b, &os0.plonk_zs_next, &os1.plonk_zs_next),
             lookup_zs: self.select_vec_ext(b, &os0.lookup_zs, &os1.lookup_zs),
             next_lookup_zs: self.select_vec_ext(b, &os0.next_lookup_zs, &os1.next_lookup_zs),
             partial_products: self.select_vec_ext(b, &os0.partial_products, &os1.partial_products),
             quotient_polys: self.select_vec_ext(b, &os0.quotient_polys, &os1.quotient_polys),
+            challenge_dependent: self.select_vec_ext(
+                b,
+                &os0.challenge_dependent,
+                &os1.challenge_dependent,
+            ),
+            challenge_dependent_next: self.select_vec_ext(
+                b,
+                &os0.challenge_dependent_next,
+                &os1.challenge_dependent_next,
+            ),
         }
     }
 
@@ -235,6 +286,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 &proof1.final_poly.0,
             )),
             pow_witness: self.select(b, proof0.pow_witness, proof1.pow_witness),
+            round_pow_witnesses: self.select_vec(
+                b,
+                &proof0.round_pow_witnesses,
+                &proof1.round_pow_witnesses,
+            ),
         }
     }
 
@@ -334,28 +390,47 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 }
 
+/// Errors unless all of `common_data` are identical, as required to verify proofs of any of these
+/// circuits with a single recursive verifier.
+pub fn check_compatible_shapes<F: RichField + Extendable<D>, const D: usize>(
+    common_data: &[&CommonCircuitData<F, D>],
+) -> Result<()> {
+    for (i, cd) in common_data.iter().enumerate().skip(1) {
+        ensure!(
+            *cd == common_data[0],
+            "Circuit {} has a different common circuit data than circuit 0",
+            i
+        );
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(not(feature = "std"))]
-    use alloc::vec;
+    use alloc::{string::ToString, vec};
 
-    use anyhow::Result;
     use hashbrown::HashMap;
 
     use super::*;
-    use crate::field::types::Sample;
+    use crate::field::types::{Field, Sample};
     use crate::gates::noop::NoopGate;
     use crate::iop::witness::{PartialWitness, WitnessWrite};
-    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::generated_witness::GeneratedWitness;
+    use crate::plonk::proof::ProofWithPublicInputs;
     use crate::recursion::dummy_circuit::{dummy_circuit, dummy_proof};
 
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type Leaf = (CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>);
+    type Witness = GeneratedWitness<F, <C as GenericConfig<D>>::Hasher>;
+
     #[test]
     fn test_conditional_recursive_verifier() -> Result<()> {
         init_logger();
-        const D: usize = 2;
-        type C = PoseidonGoldilocksConfig;
-        type F = <C as GenericConfig<D>>::F;
         let config = CircuitConfig::standard_recursion_config();
 
         // Generate proof.
@@ -405,6 +480,112 @@ mod tests {
         data.verify(proof)
     }
 
+    /// Two different leaf circuits of the same shape, and a proof of each.
+    fn selectable_leaves() -> Result<[Leaf; 2]> {
+        let leaf = |multiply: bool| -> Result<_> {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let x = builder.add_virtual_public_input();
+            let y = builder.add_virtual_public_input();
+            let z = if multiply {
+                builder.mul(x, y)
+            } else {
+                builder.add(x, y)
+            };
+            builder.register_public_input(z);
+            for _ in 0..64 {
+                builder.add_gate(NoopGate, vec![]);
+            }
+            let data = builder.build::<C>();
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::rand());
+            pw.set_target(y, F::rand());
+            let proof = data.prove(pw)?;
+            data.verify(proof.clone())?;
+            Ok((data, proof))
+        };
+        let leaves = [leaf(true)?, leaf(false)?];
+        assert_ne!(
+            leaves[0].0.verifier_only.circuit_digest,
+            leaves[1].0.verifier_only.circuit_digest
+        );
+        check_compatible_shapes(&[&leaves[0].0.common, &leaves[1].0.common])?;
+        Ok(leaves)
+    }
+
+    /// A circuit verifying each of `proofs` against the leaf chosen by its selector, and a
+    /// witness for it. Returns the selector targets too.
+    fn selected_verifier_data_witness(
+        leaves: &[Leaf],
+        proofs: &[(usize, &ProofWithPublicInputs<F, C, D>)],
+    ) -> Result<(CircuitData<F, C, D>, Witness, Vec<Target>)> {
+        let common_data = &leaves[0].0.common;
+        let allowed = leaves
+            .iter()
+            .map(|(data, _)| data.verifier_only.clone())
+            .collect::<Vec<_>>();
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut pw = PartialWitness::new();
+        let mut selectors = Vec::new();
+        for &(selector_value, proof) in proofs {
+            let pt = builder.add_virtual_proof_with_pis(common_data);
+            pw.set_proof_with_pis_target(&pt, proof);
+            let selector = builder.add_virtual_target();
+            pw.set_target(selector, F::from_canonical_usize(selector_value));
+            builder.verify_proof_with_selected_verifier_data::<C>(
+                selector,
+                &pt,
+                &allowed,
+                common_data,
+            );
+            selectors.push(selector);
+        }
+        let data = builder.build::<C>();
+        let witness = data.generate_witness(pw)?;
+        Ok((data, witness, selectors))
+    }
+
+    #[test]
+    fn test_verify_proof_with_selected_verifier_data() -> Result<()> {
+        init_logger();
+        let leaves = selectable_leaves()?;
+        let (data, witness, _) =
+            selected_verifier_data_witness(&leaves, &[(0, &leaves[0].1), (1, &leaves[1].1)])?;
+        let proof = data.prove_from_witness(&witness)?;
+        // The selectors are the public inputs.
+        assert_eq!(proof.public_inputs, vec![F::ZERO, F::ONE]);
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_verify_proof_with_wrong_selector() -> Result<()> {
+        init_logger();
+        let leaves = selectable_leaves()?;
+        let (data, mut witness, selectors) =
+            selected_verifier_data_witness(&leaves, &[(0, &leaves[0].1)])?;
+        // Claim that the proof of the multiplication circuit is one of the addition circuit.
+        witness.overwrite_target(selectors[0], F::ONE, &data.prover_only.representative_map);
+        let proof = data.prove_from_witness(&witness)?;
+        assert_eq!(proof.public_inputs, vec![F::ONE]);
+        assert!(data.verify(proof).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_compatible_shapes_rejects_different_degrees() {
+        let circuit = |num_gates: usize| {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            for _ in 0..num_gates {
+                builder.add_gate(NoopGate, vec![]);
+            }
+            builder.build::<C>().common
+        };
+        let err = check_compatible_shapes(&[&circuit(10), &circuit(100)]).unwrap_err();
+        assert!(err.to_string().contains("different common circuit data"));
+    }
+
     fn init_logger() {
         let _ = env_logger::builder().format_timestamp(None).try_init();
     }
@@ -0,0 +1,277 @@
+//! Running-digest public inputs for chains of proofs.
+//!
+//! A proof in a chain takes the previous digest `h_i` as input and exposes
+//! `h_{i+1} = H(h_i || items_i)`. The input digest occupies the first [`NUM_HASH_OUT_ELTS`] public
+//! inputs and the output digest the next [`NUM_HASH_OUT_ELTS`], so that chains can be checked
+//! without knowing anything else about the circuits.
+
+use core::ops::Range;
+
+use anyhow::{ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+
+/// The public inputs holding the digest a proof starts from.
+pub const RUNNING_DIGEST_INPUT_RANGE: Range<usize> = 0..NUM_HASH_OUT_ELTS;
+
+/// The public inputs holding the digest a proof ends with.
+pub const RUNNING_DIGEST_OUTPUT_RANGE: Range<usize> = NUM_HASH_OUT_ELTS..2 * NUM_HASH_OUT_ELTS;
+
+/// Computes `H(prev || items)`, the native counterpart of `CircuitBuilder::chain_digest`.
+pub fn chain_digest<F: RichField, H: Hasher<F, Hash = HashOut<F>>>(
+    prev: HashOut<F>,
+    items: &[F],
+) -> HashOut<F> {
+    let mut inputs = prev.elements.to_vec();
+    inputs.extend_from_slice(items);
+    H::hash_no_pad(&inputs)
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds the digest this proof continues from, registered as the first public inputs.
+    ///
+    /// **WARNING**: This must be called before any other public input is registered.
+    pub fn add_running_digest_input(&mut self) -> HashOutTarget {
+        assert_eq!(
+            self.num_public_inputs(),
+            RUNNING_DIGEST_INPUT_RANGE.start,
+            "The running digest input must be the first public input"
+        );
+        let prev = self.add_virtual_hash();
+        self.register_public_inputs(&prev.elements);
+        prev
+    }
+
+    /// Computes `H(prev || items)` and registers it as the output digest, right after the input
+    /// digest added by `add_running_digest_input`.
+    pub fn chain_digest<H: AlgebraicHasher<F>>(
+        &mut self,
+        prev: HashOutTarget,
+        items: &[Target],
+    ) -> HashOutTarget {
+        assert_eq!(
+            self.num_public_inputs(),
+            RUNNING_DIGEST_OUTPUT_RANGE.start,
+            "The running digest output must directly follow the running digest input"
+        );
+        let mut inputs = prev.elements.to_vec();
+        inputs.extend_from_slice(items);
+        let next = self.hash_n_to_hash_no_pad::<H>(inputs);
+        self.register_public_inputs(&next.elements);
+        next
+    }
+
+    /// Constrains `children`, whose proofs are verified elsewhere in this circuit, to form a
+    /// chain, and exposes the first child's input digest and the last child's output digest as
+    /// this circuit's own running digest public inputs.
+    pub fn aggregate_digest_chain(&mut self, children: &[&ProofWithPublicInputsTarget<D>]) {
+        assert!(!children.is_empty(), "Cannot aggregate an empty chain");
+        for pair in children.windows(2) {
+            let prev_out = running_digest_output_target(pair[0]);
+            let next_in = running_digest_input_target(pair[1]);
+            self.connect_hashes(prev_out, next_in);
+        }
+
+        let input = self.add_running_digest_input();
+        self.connect_hashes(input, running_digest_input_target(children[0]));
+        let output = running_digest_output_target(children[children.len() - 1]);
+        self.register_public_inputs(&output.elements);
+    }
+}
+
+fn running_digest_input_target<const D: usize>(
+    proof: &ProofWithPublicInputsTarget<D>,
+) -> HashOutTarget {
+    HashOutTarget::from_vec(proof.public_inputs[RUNNING_DIGEST_INPUT_RANGE].to_vec())
+}
+
+fn running_digest_output_target<const D: usize>(
+    proof: &ProofWithPublicInputsTarget<D>,
+) -> HashOutTarget {
+    HashOutTarget::from_vec(proof.public_inputs[RUNNING_DIGEST_OUTPUT_RANGE].to_vec())
+}
+
+/// Checks natively that a sequence of proofs forms a chain of running digests.
+#[derive(Clone, Debug, Default)]
+pub struct DigestChain<F: RichField> {
+    initial: Option<HashOut<F>>,
+    current: Option<HashOut<F>>,
+}
+
+impl<F: RichField> DigestChain<F> {
+    pub fn new() -> Self {
+        Self {
+            initial: None,
+            current: None,
+        }
+    }
+
+    /// Starts a chain which must begin at `initial`.
+    pub fn starting_at(initial: HashOut<F>) -> Self {
+        Self {
+            initial: Some(initial),
+            current: None,
+        }
+    }
+
+    /// Appends the next proof of the chain, checking that it continues from the current digest.
+    pub fn push<C: GenericConfig<D, F = F>, const D: usize>(
+        &mut self,
+        proof: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<()>
+    where
+        F: Extendable<D>,
+    {
+        let (input, output) = running_digests(proof)?;
+        if let Some(expected) = self.current.or(self.initial) {
+            ensure!(input == expected, "Broken link in the digest chain");
+        }
+        self.current = Some(output);
+        Ok(())
+    }
+
+    /// The output digest of the last proof pushed so far.
+    pub fn final_digest(&self) -> Option<HashOut<F>> {
+        self.current
+    }
+
+    /// Checks that `proofs` form a chain and returns the final digest.
+    pub fn check<C: GenericConfig<D, F = F>, const D: usize>(
+        proofs: &[ProofWithPublicInputs<F, C, D>],
+    ) -> Result<HashOut<F>>
+    where
+        F: Extendable<D>,
+    {
+        let mut chain = Self::new();
+        for proof in proofs {
+            chain.push(proof)?;
+        }
+        chain
+            .final_digest()
+            .ok_or_else(|| anyhow::anyhow!("Empty digest chain"))
+    }
+}
+
+fn running_digests<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    proof: &ProofWithPublicInputs<F, C, D>,
+) -> Result<(HashOut<F>, HashOut<F>)> {
+    ensure!(
+        proof.public_inputs.len() >= RUNNING_DIGEST_OUTPUT_RANGE.end,
+        "Not enough public inputs for a running digest"
+    );
+    let pis = &proof.public_inputs;
+    let input = HashOut::from_partial(&pis[RUNNING_DIGEST_INPUT_RANGE]);
+    let output = HashOut::from_partial(&pis[RUNNING_DIGEST_OUTPUT_RANGE]);
+    Ok((input, output))
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{vec, vec::Vec};
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+    type Chain = (Vec<ProofWithPublicInputs<F, C, D>>, HashOut<F>);
+
+    struct ChainCircuit {
+        data: CircuitData<F, C, D>,
+        prev: HashOutTarget,
+        items: [Target; 2],
+    }
+
+    fn chain_circuit() -> ChainCircuit {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let prev = builder.add_running_digest_input();
+        let items = [builder.add_virtual_target(), builder.add_virtual_target()];
+        builder.chain_digest::<H>(prev, &items);
+        ChainCircuit {
+            data: builder.build::<C>(),
+            prev,
+            items,
+        }
+    }
+
+    fn prove_link(
+        circuit: &ChainCircuit,
+        prev: HashOut<F>,
+        items: [F; 2],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut pw = PartialWitness::new();
+        pw.set_hash_target(circuit.prev, prev);
+        pw.set_target_arr(&circuit.items, &items);
+        circuit.data.prove(pw)
+    }
+
+    fn prove_chain(circuit: &ChainCircuit, len: u64) -> Result<Chain> {
+        let mut digest = HashOut::ZERO;
+        let mut proofs = vec![];
+        for i in 0..len {
+            let items = [F::from_canonical_u64(i), F::from_canonical_u64(i * i)];
+            let proof = prove_link(circuit, digest, items)?;
+            circuit.data.verify(proof.clone())?;
+            proofs.push(proof);
+            digest = chain_digest::<F, H>(digest, &items);
+        }
+        Ok((proofs, digest))
+    }
+
+    #[test]
+    fn test_digest_chain() -> Result<()> {
+        let circuit = chain_circuit();
+        let (mut proofs, expected) = prove_chain(&circuit, 4)?;
+        assert_eq!(DigestChain::check(&proofs)?, expected);
+
+        let mut chain = DigestChain::starting_at(HashOut::ZERO);
+        for proof in &proofs {
+            chain.push(proof)?;
+        }
+        assert_eq!(chain.final_digest(), Some(expected));
+
+        // Dropping a proof breaks the link between its neighbours.
+        proofs.remove(2);
+        assert!(DigestChain::check(&proofs).is_err());
+        assert!(DigestChain::starting_at(expected).push(&proofs[0]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate_digest_chain() -> Result<()> {
+        let circuit = chain_circuit();
+        let (proofs, expected) = prove_chain(&circuit, 2)?;
+        let inner_cd = &circuit.data.common;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut pw = PartialWitness::new();
+        let inner_vd = builder.constant_verifier_data(&circuit.data.verifier_only);
+        let children = proofs
+            .iter()
+            .map(|proof| {
+                let pt = builder.add_virtual_proof_with_pis(inner_cd);
+                pw.set_proof_with_pis_target(&pt, proof);
+                builder.verify_proof::<C>(&pt, &inner_vd, inner_cd);
+                pt
+            })
+            .collect::<Vec<_>>();
+        builder.aggregate_digest_chain(&children.iter().collect::<Vec<_>>());
+        let data = builder.build::<C>();
+
+        let proof = data.prove(pw)?;
+        assert_eq!(DigestChain::check(core::slice::from_ref(&proof))?, expected);
+        data.verify(proof)
+    }
+}
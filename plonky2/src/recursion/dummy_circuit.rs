@@ -164,6 +164,7 @@ where
         let proof_with_pis_target = ProofWithPublicInputsTarget {
             proof: ProofTarget {
                 wires_cap: MerkleCapTarget(vec![]),
+                challenge_dependent_cap: MerkleCapTarget(vec![]),
                 plonk_zs_partial_products_cap: MerkleCapTarget(vec![]),
                 quotient_polys_cap: MerkleCapTarget(vec![]),
                 openings: OpeningSetTarget::default(),
@@ -172,6 +173,7 @@ where
                     query_round_proofs: vec![],
                     final_poly: PolynomialCoeffsExtTarget(vec![]),
                     pow_witness: Target::default(),
+                    round_pow_witnesses: vec![],
                 },
             },
             public_inputs: vec![],
@@ -180,6 +182,7 @@ where
         let proof_with_pis = ProofWithPublicInputs {
             proof: Proof {
                 wires_cap: MerkleCap(vec![]),
+                challenge_dependent_cap: MerkleCap(vec![]),
                 plonk_zs_partial_products_cap: MerkleCap(vec![]),
                 quotient_polys_cap: MerkleCap(vec![]),
                 openings: OpeningSet::default(),
@@ -188,6 +191,7 @@ where
                     query_round_proofs: vec![],
                     final_poly: PolynomialCoeffs { coeffs: vec![] },
                     pow_witness: F::ZERO,
+                    round_pow_witnesses: vec![],
                 },
             },
             public_inputs: vec![],
@@ -6,5 +6,9 @@
 
 pub mod conditional_recursive_verifier;
 pub mod cyclic_recursion;
+pub mod digest_chain;
 pub mod dummy_circuit;
 pub mod recursive_verifier;
+pub mod shared_public_inputs;
+pub mod variable_degree;
+pub mod wrap;
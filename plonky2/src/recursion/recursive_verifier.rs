@@ -1,9 +1,17 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
 use crate::field::extension::Extendable;
-use crate::hash::hash_types::{HashOutTarget, RichField};
+use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::iop::challenger::Transcript;
+use crate::iop::ext_target::ExtensionTarget;
 use crate::plonk::circuit_builder::CircuitBuilder;
-use crate::plonk::circuit_data::{CommonCircuitData, VerifierCircuitTarget};
+use crate::plonk::circuit_data::{
+    CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
+};
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
-use crate::plonk::plonk_common::salt_size;
+use crate::plonk::plonk_common::eval_l_0_circuit;
 use crate::plonk::proof::{
     OpeningSetTarget, ProofChallengesTarget, ProofTarget, ProofWithPublicInputsTarget,
 };
@@ -12,8 +20,22 @@ use crate::plonk::vars::EvaluationTargets;
 use crate::util::reducing::ReducingFactorTarget;
 use crate::with_context;
 
+/// Fails to compile, when `OK` is used, unless `T` is the sponge: the recursive verifier only
+/// replays the sponge's challenges, so proofs drawing them from another transcript cannot be
+/// verified recursively.
+pub(crate) struct AssertSpongeTranscript<F, T>(PhantomData<(F, T)>);
+
+impl<F: RichField, T: Transcript<F>> AssertSpongeTranscript<F, T> {
+    pub(crate) const OK: () = assert!(
+        T::SPONGE.is_some(),
+        "Proofs using a transcript other than the sponge cannot be verified recursively"
+    );
+}
+
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
-    /// Recursively verifies an inner proof.
+    /// Recursively verifies an inner proof. Every quantity derived from `inner_common_data` is a
+    /// constant of this circuit, while `inner_verifier_data` are targets; see
+    /// `verify_proof_with_verifier_data` to bake them in as constants as well.
     pub fn verify_proof<C: GenericConfig<D, F = F>>(
         &mut self,
         proof_with_pis: &ProofWithPublicInputsTarget<D>,
@@ -22,6 +44,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     ) where
         C::Hasher: AlgebraicHasher<F>,
     {
+        let () = AssertSpongeTranscript::<F, C::Transcript>::OK;
         assert_eq!(
             proof_with_pis.public_inputs.len(),
             inner_common_data.num_public_inputs
@@ -44,6 +67,32 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
     }
 
+    /// Recursively verifies an inner proof of a fixed circuit.
+    ///
+    /// Every quantity derived from `inner_common_data` is already a constant of this circuit. The
+    /// inner verifier data are baked in as constants too, unless `variable_verifier_data` is set,
+    /// as in the cyclic and multi-circuit cases. They are then virtual targets, which the caller
+    /// must set in the witness and constrain, e.g. by registering them as public inputs. Either
+    /// way, the returned targets hold them.
+    pub fn verify_proof_with_verifier_data<C: GenericConfig<D, F = F>>(
+        &mut self,
+        proof_with_pis: &ProofWithPublicInputsTarget<D>,
+        inner_verifier_data: &VerifierOnlyCircuitData<C, D>,
+        inner_common_data: &CommonCircuitData<F, D>,
+        variable_verifier_data: bool,
+    ) -> VerifierCircuitTarget
+    where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let verifier_data_target = if variable_verifier_data {
+            self.add_virtual_verifier_data(inner_common_data.config.fri_config.cap_height)
+        } else {
+            self.constant_verifier_data(inner_verifier_data)
+        };
+        self.verify_proof::<C>(proof_with_pis, &verifier_data_target, inner_common_data);
+        verifier_data_target
+    }
+
     /// Recursively verifies an inner proof.
     fn verify_proof_with_challenges<C: GenericConfig<D, F = F>>(
         &mut self,
@@ -55,6 +104,51 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     ) where
         C::Hasher: AlgebraicHasher<F>,
     {
+        let zeta_pow_deg = self
+            .exp_power_of_2_extension(challenges.plonk_zeta, inner_common_data.degree_bits().get());
+        let l_0_zeta = eval_l_0_circuit(
+            self,
+            inner_common_data.degree(),
+            challenges.plonk_zeta,
+            zeta_pow_deg,
+        );
+        self.check_vanishing_and_quotient(
+            proof,
+            public_inputs_hash,
+            &challenges,
+            inner_common_data,
+            zeta_pow_deg,
+            l_0_zeta,
+        );
+
+        let merkle_caps = Self::initial_merkle_caps(proof, inner_verifier_data, inner_common_data);
+        let fri_instance = inner_common_data.get_fri_instance_target(self, challenges.plonk_zeta);
+        with_context!(
+            self,
+            "verify FRI proof",
+            self.verify_fri_proof::<C>(
+                &fri_instance,
+                &proof.openings.to_fri_openings(),
+                &challenges.fri_challenges,
+                &merkle_caps,
+                &proof.opening_proof,
+                &inner_common_data.fri_params,
+            )
+        );
+    }
+
+    /// Checks that the vanishing polynomial, evaluated from the openings at `zeta`, equals
+    /// `Z_H(zeta)` times the opened quotient polynomials. `zeta_pow_deg` is `zeta^n` and `l_0_zeta`
+    /// is `L_0(zeta)`, for `n` the inner circuit's degree.
+    pub(crate) fn check_vanishing_and_quotient(
+        &mut self,
+        proof: &ProofTarget<D>,
+        public_inputs_hash: HashOutTarget,
+        challenges: &ProofChallengesTarget<D>,
+        inner_common_data: &CommonCircuitData<F, D>,
+        zeta_pow_deg: ExtensionTarget<D>,
+        l_0_zeta: ExtensionTarget<D>,
+    ) {
         let one = self.one_extension();
 
         let local_constants = &proof.openings.constants;
@@ -62,6 +156,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let vars = EvaluationTargets {
             local_constants,
             local_wires,
+            next_wires: &proof.openings.wires_next,
             public_inputs_hash: &public_inputs_hash,
         };
         let local_zs = &proof.openings.plonk_zs;
@@ -71,8 +166,6 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let s_sigmas = &proof.openings.plonk_sigmas;
         let partial_products = &proof.openings.partial_products;
 
-        let zeta_pow_deg =
-            self.exp_power_of_2_extension(challenges.plonk_zeta, inner_common_data.degree_bits());
         let vanishing_polys_zeta = with_context!(
             self,
             "evaluate the vanishing polynomial at our challenge point, zeta.",
@@ -80,18 +173,21 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 self,
                 inner_common_data,
                 challenges.plonk_zeta,
-                zeta_pow_deg,
+                l_0_zeta,
                 vars,
                 local_zs,
                 next_zs,
                 local_lookup_zs,
                 next_lookup_zs,
+                &proof.openings.challenge_dependent,
+                &proof.openings.challenge_dependent_next,
                 partial_products,
                 s_sigmas,
                 &challenges.plonk_betas,
                 &challenges.plonk_gammas,
                 &challenges.plonk_alphas,
                 &challenges.plonk_deltas,
+                &challenges.challenge_dependent_challenges,
             )
         );
 
@@ -108,27 +204,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 self.connect_extension(vanishing_polys_zeta[i], computed_vanishing_poly);
             }
         });
+    }
 
-        let merkle_caps = &[
+    /// The caps of the oracles opened by the FRI proof of an inner proof, in oracle order.
+    pub(crate) fn initial_merkle_caps(
+        proof: &ProofTarget<D>,
+        inner_verifier_data: &VerifierCircuitTarget,
+        inner_common_data: &CommonCircuitData<F, D>,
+    ) -> Vec<MerkleCapTarget> {
+        let mut merkle_caps = vec![
             inner_verifier_data.constants_sigmas_cap.clone(),
             proof.wires_cap.clone(),
             proof.plonk_zs_partial_products_cap.clone(),
             proof.quotient_polys_cap.clone(),
         ];
-
-        let fri_instance = inner_common_data.get_fri_instance_target(self, challenges.plonk_zeta);
-        with_context!(
-            self,
-            "verify FRI proof",
-            self.verify_fri_proof::<C>(
-                &fri_instance,
-                &proof.openings.to_fri_openings(),
-                &challenges.fri_challenges,
-                merkle_caps,
-                &proof.opening_proof,
-                &inner_common_data.fri_params,
-            )
-        );
+        if inner_common_data.has_challenge_dependent_columns() {
+            merkle_caps.push(proof.challenge_dependent_cap.clone());
+        }
+        merkle_caps
     }
 
     pub fn add_virtual_proof_with_pis(
@@ -144,24 +237,29 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     fn add_virtual_proof(&mut self, common_data: &CommonCircuitData<F, D>) -> ProofTarget<D> {
-        let config = &common_data.config;
         let fri_params = &common_data.fri_params;
         let cap_height = fri_params.config.cap_height;
 
-        let salt = salt_size(common_data.fri_params.hiding);
-        let num_leaves_per_oracle = &[
-            common_data.num_preprocessed_polys(),
-            config.num_wires + salt,
-            common_data.num_zs_partial_products_polys() + common_data.num_all_lookup_polys() + salt,
-            common_data.num_quotient_polys() + salt,
+        let mut num_leaves_per_oracle = vec![
+            common_data.salted_constants_len(),
+            common_data.salted_wires_len(),
+            common_data.salted_zs_len(),
+            common_data.salted_quotient_len(),
         ];
+        let challenge_dependent_cap = if common_data.has_challenge_dependent_columns() {
+            num_leaves_per_oracle.push(common_data.salted_challenge_dependent_len());
+            self.add_virtual_cap(cap_height)
+        } else {
+            MerkleCapTarget(vec![])
+        };
 
         ProofTarget {
             wires_cap: self.add_virtual_cap(cap_height),
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap: self.add_virtual_cap(cap_height),
             quotient_polys_cap: self.add_virtual_cap(cap_height),
             openings: self.add_opening_set(common_data),
-            opening_proof: self.add_virtual_fri_proof(num_leaves_per_oracle, fri_params),
+            opening_proof: self.add_virtual_fri_proof(&num_leaves_per_oracle, fri_params),
         }
     }
 
@@ -179,12 +277,17 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             constants: self.add_virtual_extension_targets(common_data.num_constants),
             plonk_sigmas: self.add_virtual_extension_targets(config.num_routed_wires),
             wires: self.add_virtual_extension_targets(config.num_wires),
+            wires_next: self.add_virtual_extension_targets(common_data.num_next_row_wires()),
             plonk_zs: self.add_virtual_extension_targets(num_challenges),
             plonk_zs_next: self.add_virtual_extension_targets(num_challenges),
             lookup_zs: self.add_virtual_extension_targets(num_lookups),
             next_lookup_zs: self.add_virtual_extension_targets(num_lookups),
             partial_products: self.add_virtual_extension_targets(total_partial_products),
             quotient_polys: self.add_virtual_extension_targets(common_data.num_quotient_polys()),
+            challenge_dependent: self
+                .add_virtual_extension_targets(common_data.num_challenge_dependent_columns()),
+            challenge_dependent_next: self
+                .add_virtual_extension_targets(common_data.num_challenge_dependent_columns()),
         }
     }
 }
@@ -201,16 +304,19 @@ mod tests {
     use log::{info, Level};
 
     use super::*;
+    use crate::field::types::{Field, Sample};
     use crate::fri::reduction_strategies::FriReductionStrategy;
-    use crate::fri::FriConfig;
+    use crate::fri::{FriConfig, FriPowStrategy};
     use crate::gadgets::lookup::{OTHER_TABLE, TIP5_TABLE};
     use crate::gates::lookup_table::LookupTable;
     use crate::gates::noop::NoopGate;
     use crate::iop::witness::{PartialWitness, WitnessWrite};
-    use crate::plonk::circuit_data::{CircuitConfig, VerifierOnlyCircuitData};
+    use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::{GenericConfig, KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
     use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
     use crate::plonk::prover::prove;
+    use crate::plonk::verifier::verify;
+    use crate::util::log_size::DegreeBits;
     use crate::util::timing::TimingTree;
 
     #[test]
@@ -288,23 +394,135 @@ mod tests {
 
         // Start with a degree 2^14 proof
         let (proof, vd, common_data) = dummy_proof::<F, C, D>(&config, 16_000)?;
-        assert_eq!(common_data.degree_bits(), 14);
+        assert_eq!(common_data.degree_bits(), DegreeBits::new(14));
 
         // Shrink it to 2^13.
         let (proof, vd, common_data) =
             recursive_proof::<F, C, C, D>(proof, vd, common_data, &config, Some(13), false, false)?;
-        assert_eq!(common_data.degree_bits(), 13);
+        assert_eq!(common_data.degree_bits(), DegreeBits::new(13));
 
         // Shrink it to 2^12.
         let (proof, vd, common_data) =
             recursive_proof::<F, C, C, D>(proof, vd, common_data, &config, None, true, true)?;
-        assert_eq!(common_data.degree_bits(), 12);
+        assert_eq!(common_data.degree_bits(), DegreeBits::new(12));
 
         test_serialization(&proof, &vd, &common_data)?;
 
         Ok(())
     }
 
+    #[test]
+    fn test_recursive_verifier_constant_verifier_data() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+
+        // Two different inner circuits with the same common data.
+        let inner = |multiply: bool| -> Result<_> {
+            let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+            let x = builder.add_virtual_public_input();
+            let y = builder.add_virtual_public_input();
+            let z = if multiply {
+                builder.mul(x, y)
+            } else {
+                builder.add(x, y)
+            };
+            builder.register_public_input(z);
+            let data = builder.build::<C>();
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::rand());
+            pw.set_target(y, F::rand());
+            let proof = data.prove(pw)?;
+            Ok((data, proof))
+        };
+        let (mul_data, mul_proof) = inner(true)?;
+        let (add_data, add_proof) = inner(false)?;
+        assert_eq!(mul_data.common, add_data.common);
+        let cd = &mul_data.common;
+
+        // A wrapper of proofs of the multiplication circuit, and its number of gates.
+        let wrapper = |variable_verifier_data: bool| {
+            let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+            let pt = builder.add_virtual_proof_with_pis(cd);
+            let inner_data = builder.verify_proof_with_verifier_data::<C>(
+                &pt,
+                &mul_data.verifier_only,
+                cd,
+                variable_verifier_data,
+            );
+            let num_gates = builder.num_gates() + builder.num_constant_gates();
+            (builder.build::<C>(), pt, inner_data, num_gates)
+        };
+        // The inputs of a wrapper: the inner proof, and the inner verifier data if they are
+        // witnessed. Witness generation fails unless every input is set, so there are no others.
+        let inputs = |pt: &ProofWithPublicInputsTarget<D>,
+                      inner_data: &Option<VerifierCircuitTarget>,
+                      proof: &ProofWithPublicInputs<F, C, D>,
+                      verifier_data: &VerifierOnlyCircuitData<C, D>| {
+            let mut pw = PartialWitness::new();
+            pw.set_proof_with_pis_target(pt, proof);
+            if let Some(inner_data) = inner_data {
+                pw.set_verifier_data_target(inner_data, verifier_data);
+            }
+            pw
+        };
+
+        let (variable, variable_pt, variable_vd, variable_gates) = wrapper(true);
+        let (constant, constant_pt, _, constant_gates) = wrapper(false);
+        info!(
+            "Wrapper gates: {variable_gates} with witnessed verifier data, {constant_gates} with constant verifier data"
+        );
+        // Nothing derived from the inner common data is witnessed, so baking in the verifier data
+        // only costs the `ConstantGate`s holding the constants which don't fit in spare constant
+        // wires.
+        let num_verifier_data_elements = 4 + 4 * cd.config.fri_config.num_cap_elements();
+        assert!(
+            constant_gates
+                <= variable_gates + num_verifier_data_elements.div_ceil(config.num_constants)
+        );
+
+        for (data, pt, vd) in [
+            (&variable, &variable_pt, Some(variable_vd)),
+            (&constant, &constant_pt, None),
+        ] {
+            let witness =
+                data.generate_witness(inputs(pt, &vd, &mul_proof, &mul_data.verifier_only))?;
+            data.verify(data.prove_from_witness(&witness)?)?;
+
+            // Checks whether the wrapper accepts the honest witness with the inputs of `proof`
+            // instead. Assigning those inputs directly would make witness generation panic on
+            // conflicting assignments, so only the wrapper's constraints can reject them.
+            let accepts = |proof: &ProofWithPublicInputs<F, C, D>,
+                           verifier_data: &VerifierOnlyCircuitData<C, D>|
+             -> Result<bool> {
+                let mut witness = witness.clone();
+                for (&target, &value) in &inputs(pt, &vd, proof, verifier_data).target_values {
+                    witness.overwrite_target(target, value, &data.prover_only.representative_map);
+                }
+                Ok(data.verify(data.prove_from_witness(&witness)?).is_ok())
+            };
+
+            // Corrupted inner proofs are rejected either way.
+            let mut corrupted = mul_proof.clone();
+            corrupted.public_inputs[2] += F::ONE;
+            assert!(!accepts(&corrupted, &mul_data.verifier_only)?);
+
+            // Witnessed verifier data which the caller doesn't constrain accept a proof of another
+            // circuit, while constant verifier data reject it.
+            if vd.is_some() {
+                let witness =
+                    data.generate_witness(inputs(pt, &vd, &add_proof, &add_data.verifier_only))?;
+                data.verify(data.prove_from_witness(&witness)?)?;
+            } else {
+                assert!(!accepts(&add_proof, &add_data.verifier_only)?);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Creates a chain of recursive proofs where the last proof is made as small as reasonably
     /// possible, using a high rate, high PoW bits, etc.
     #[test]
@@ -320,7 +538,7 @@ mod tests {
 
         // An initial dummy proof.
         let (proof, vd, common_data) = dummy_proof::<F, C, D>(&standard_config, 4_000)?;
-        assert_eq!(common_data.degree_bits(), 12);
+        assert_eq!(common_data.degree_bits(), DegreeBits::new(12));
 
         // A standard recursive proof.
         let (proof, vd, common_data) = recursive_proof::<F, C, C, D>(
@@ -332,18 +550,10 @@ mod tests {
             false,
             false,
         )?;
-        assert_eq!(common_data.degree_bits(), 12);
+        assert_eq!(common_data.degree_bits(), DegreeBits::new(12));
 
         // A high-rate recursive proof, designed to be verifiable with fewer routed wires.
-        let high_rate_config = CircuitConfig {
-            fri_config: FriConfig {
-                rate_bits: 7,
-                proof_of_work_bits: 16,
-                num_query_rounds: 12,
-                ..standard_config.fri_config.clone()
-            },
-            ..standard_config
-        };
+        let high_rate_config = CircuitConfig::high_rate_recursion_config();
         let (proof, vd, common_data) = recursive_proof::<F, C, C, D>(
             proof,
             vd,
@@ -353,7 +563,7 @@ mod tests {
             true,
             true,
         )?;
-        assert_eq!(common_data.degree_bits(), 12);
+        assert_eq!(common_data.degree_bits(), DegreeBits::new(12));
 
         // A final proof, optimized for size.
         let final_config = CircuitConfig {
@@ -361,9 +571,10 @@ mod tests {
             fri_config: FriConfig {
                 rate_bits: 8,
                 cap_height: 0,
-                proof_of_work_bits: 20,
+                pow_strategy: FriPowStrategy::Single(20),
                 reduction_strategy: FriReductionStrategy::MinSize(None),
                 num_query_rounds: 10,
+                commit_final_poly: false,
             },
             ..high_rate_config
         };
@@ -376,7 +587,11 @@ mod tests {
             true,
             true,
         )?;
-        assert_eq!(common_data.degree_bits(), 12, "final proof too large");
+        assert_eq!(
+            common_data.degree_bits(),
+            DegreeBits::new(12),
+            "final proof too large"
+        );
 
         test_serialization(&proof, &vd, &common_data)?;
 
@@ -405,6 +620,119 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_recursive_verifier_committed_final_poly() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        // A single 4-to-1 reduction leaves a long final polynomial.
+        let standard_config = CircuitConfig::standard_recursion_config();
+        let sent_config = CircuitConfig {
+            fri_config: FriConfig {
+                reduction_strategy: FriReductionStrategy::Fixed(vec![2]),
+                ..standard_config.fri_config.clone()
+            },
+            ..standard_config.clone()
+        };
+        let committed_config = CircuitConfig {
+            fri_config: FriConfig {
+                commit_final_poly: true,
+                ..sent_config.fri_config.clone()
+            },
+            ..sent_config.clone()
+        };
+
+        let (sent_proof, _, sent_cd) = dummy_proof::<F, C, D>(&sent_config, 4_000)?;
+        let (committed_proof, committed_vd, committed_cd) =
+            dummy_proof::<F, C, D>(&committed_config, 4_000)?;
+        assert!(sent_cd.fri_params.final_poly_len() >= 64);
+        assert!(committed_cd.fri_params.final_poly_len() < sent_cd.fri_params.final_poly_len());
+        assert_eq!(
+            committed_proof
+                .proof
+                .opening_proof
+                .commit_phase_merkle_caps
+                .len(),
+            sent_proof
+                .proof
+                .opening_proof
+                .commit_phase_merkle_caps
+                .len()
+                + 1
+        );
+
+        let verifier_gates = |cd: &CommonCircuitData<F, D>| {
+            let mut builder = CircuitBuilder::<F, D>::new(standard_config.clone());
+            let pt = builder.add_virtual_proof_with_pis(cd);
+            let inner_data = builder.add_virtual_verifier_data(cd.config.fri_config.cap_height);
+            builder.verify_proof::<C>(&pt, &inner_data, cd);
+            builder.num_gates()
+        };
+        assert!(verifier_gates(&committed_cd) < verifier_gates(&sent_cd));
+
+        recursive_proof::<F, C, C, D>(
+            committed_proof,
+            committed_vd,
+            committed_cd,
+            &standard_config,
+            None,
+            false,
+            false,
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_verifier_pow_strategies() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let standard_config = CircuitConfig::standard_recursion_config();
+        for pow_strategy in [
+            FriPowStrategy::Single(16),
+            FriPowStrategy::None,
+            FriPowStrategy::PerRound(vec![4, 0, 6]),
+        ] {
+            // Without a final grind, more queries are needed to reach the target security.
+            let num_query_rounds = if pow_strategy.final_bits().is_some() {
+                standard_config.fri_config.num_query_rounds
+            } else {
+                34
+            };
+            let config = CircuitConfig {
+                fri_config: FriConfig {
+                    pow_strategy: pow_strategy.clone(),
+                    num_query_rounds,
+                    ..standard_config.fri_config.clone()
+                },
+                ..standard_config.clone()
+            };
+
+            let (proof, vd, cd) = dummy_proof::<F, C, D>(&config, 4_000)?;
+            let opening_proof = &proof.proof.opening_proof;
+            assert_eq!(
+                opening_proof.round_pow_witnesses.len(),
+                cd.fri_params.round_pow_bits().len()
+            );
+            test_serialization(&proof, &vd, &cd)?;
+
+            if !opening_proof.round_pow_witnesses.is_empty() {
+                let mut bad_proof = proof.clone();
+                bad_proof.proof.opening_proof.round_pow_witnesses[0] += F::ONE;
+                assert!(verify(bad_proof, &vd, &cd).is_err());
+            }
+
+            recursive_proof::<F, C, C, D>(proof, vd, cd, &standard_config, None, false, false)?;
+        }
+
+        Ok(())
+    }
+
     type Proof<F, C, const D: usize> = (
         ProofWithPublicInputs<F, C, D>,
         VerifierOnlyCircuitData<C, D>,
@@ -642,14 +970,7 @@ mod tests {
         let pt = builder.add_virtual_proof_with_pis(&inner_cd);
         pw.set_proof_with_pis_target(&pt, &inner_proof);
 
-        let inner_data = builder.add_virtual_verifier_data(inner_cd.config.fri_config.cap_height);
-        pw.set_cap_target(
-            &inner_data.constants_sigmas_cap,
-            &inner_vd.constants_sigmas_cap,
-        );
-        pw.set_hash_target(inner_data.circuit_digest, inner_vd.circuit_digest);
-
-        builder.verify_proof::<InnerC>(&pt, &inner_data, &inner_cd);
+        builder.verify_proof_with_verifier_data::<InnerC>(&pt, &inner_vd, &inner_cd, false);
 
         if print_gate_counts {
             builder.print_gate_counts(0);
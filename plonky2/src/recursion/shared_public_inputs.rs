@@ -0,0 +1,226 @@
+//! Public inputs shared between inner proofs, without exposing them.
+//!
+//! An aggregating circuit can require two inner proofs to agree on some of their public inputs,
+//! for instance an intermediate value which one proof outputs and the other consumes. The shared
+//! values are connected inside the aggregating circuit and kept out of its own public inputs,
+//! which [`CircuitBuilder::build`] checks. Ranges index the `public_inputs` of the inner proofs.
+//!
+//! The aggregating circuit must use a zero-knowledge config, as its proof would otherwise reveal
+//! information about the shared values through the wire openings.
+
+use core::ops::Range;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+use crate::plonk::proof::ProofWithPublicInputsTarget;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Constrains the public inputs `range1` of `p1` to equal the public inputs `range2` of `p2`,
+    /// where both proofs are verified elsewhere in this circuit. The values must not become public
+    /// inputs of this circuit, which must be zero-knowledge.
+    pub fn connect_inner_public_inputs(
+        &mut self,
+        p1: &ProofWithPublicInputsTarget<D>,
+        range1: Range<usize>,
+        p2: &ProofWithPublicInputsTarget<D>,
+        range2: Range<usize>,
+    ) {
+        assert_eq!(
+            range1.len(),
+            range2.len(),
+            "Cannot connect public input ranges of different lengths"
+        );
+        let values = &p1.public_inputs[range1];
+        for (&x, &y) in values.iter().zip(&p2.public_inputs[range2]) {
+            self.connect(x, y);
+        }
+        self.keep_private(values);
+    }
+
+    /// Like `connect_inner_public_inputs`, but `p2` exposes `H(values)` in `digest_range` rather
+    /// than the values in `range` of `p1`. This spares `p2` a long range of public inputs, at the
+    /// cost of hashing the values here. Neither the values nor their digest may become public
+    /// inputs of this circuit, which must be zero-knowledge.
+    pub fn connect_inner_public_input_digest<H: AlgebraicHasher<F>>(
+        &mut self,
+        p1: &ProofWithPublicInputsTarget<D>,
+        range: Range<usize>,
+        p2: &ProofWithPublicInputsTarget<D>,
+        digest_range: Range<usize>,
+    ) {
+        assert_eq!(
+            digest_range.len(),
+            NUM_HASH_OUT_ELTS,
+            "A digest takes {} public inputs",
+            NUM_HASH_OUT_ELTS
+        );
+        let values = p1.public_inputs[range].to_vec();
+        self.keep_private(&values);
+        let digest = self.hash_n_to_hash_no_pad::<H>(values);
+        let exposed = HashOutTarget::from_vec(p2.public_inputs[digest_range].to_vec());
+        self.connect_hashes(digest, exposed);
+        self.keep_private(&exposed.elements);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::ProofWithPublicInputs;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    const NUM_SHARED: usize = 3;
+
+    /// A circuit exposing `x^2` and some shared values, or their digest if `hashed`.
+    struct InnerCircuit {
+        data: CircuitData<F, C, D>,
+        x: Target,
+        shared: [Target; NUM_SHARED],
+    }
+
+    fn inner_circuit(hashed: bool) -> InnerCircuit {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let x_squared = builder.square(x);
+        builder.register_public_input(x_squared);
+        let shared = builder.add_virtual_target_arr::<NUM_SHARED>();
+        if hashed {
+            let digest = builder.hash_n_to_hash_no_pad::<H>(shared.to_vec());
+            builder.register_public_inputs(&digest.elements);
+        } else {
+            builder.register_public_inputs(&shared);
+        }
+        InnerCircuit {
+            data: builder.build::<C>(),
+            x,
+            shared,
+        }
+    }
+
+    fn prove_inner(
+        circuit: &InnerCircuit,
+        x: u64,
+        shared: [u64; NUM_SHARED],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut pw = PartialWitness::new();
+        pw.set_target(circuit.x, F::from_canonical_u64(x));
+        pw.set_target_arr(&circuit.shared, &shared.map(F::from_canonical_u64));
+        circuit.data.prove(pw)
+    }
+
+    /// Aggregates `proof1` and `proof2`, connecting their shared values, and exposes the public
+    /// input `0` of both. Panics if they do not share the same values.
+    fn aggregate(
+        inner1: &InnerCircuit,
+        proof1: &ProofWithPublicInputs<F, C, D>,
+        inner2: &InnerCircuit,
+        proof2: &ProofWithPublicInputs<F, C, D>,
+        hashed: bool,
+    ) -> Result<()> {
+        let mut builder =
+            CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_zk_config());
+        let mut pw = PartialWitness::new();
+        let mut verify = |inner: &InnerCircuit, proof| {
+            let pt = builder.add_virtual_proof_with_pis(&inner.data.common);
+            pw.set_proof_with_pis_target(&pt, proof);
+            let vd = builder.constant_verifier_data(&inner.data.verifier_only);
+            builder.verify_proof::<C>(&pt, &vd, &inner.data.common);
+            pt
+        };
+        let pt1 = verify(inner1, proof1);
+        let pt2 = verify(inner2, proof2);
+
+        let shared = 1..1 + NUM_SHARED;
+        if hashed {
+            builder.connect_inner_public_input_digest::<H>(
+                &pt1,
+                shared,
+                &pt2,
+                1..1 + NUM_HASH_OUT_ELTS,
+            );
+        } else {
+            builder.connect_inner_public_inputs(&pt1, shared.clone(), &pt2, shared);
+        }
+        builder.register_public_input(pt1.public_inputs[0]);
+        builder.register_public_input(pt2.public_inputs[0]);
+        let data = builder.build::<C>();
+
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs.len(), 2);
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_connect_inner_public_inputs() -> Result<()> {
+        let inner = inner_circuit(false);
+        let proof1 = prove_inner(&inner, 2, [1, 2, 3])?;
+        let proof2 = prove_inner(&inner, 3, [1, 2, 3])?;
+        aggregate(&inner, &proof1, &inner, &proof2, false)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_connect_disagreeing_inner_public_inputs() {
+        let inner = inner_circuit(false);
+        let proof1 = prove_inner(&inner, 2, [1, 2, 3]).unwrap();
+        let proof2 = prove_inner(&inner, 3, [1, 2, 4]).unwrap();
+        let _ = aggregate(&inner, &proof1, &inner, &proof2, false);
+    }
+
+    #[test]
+    fn test_connect_inner_public_input_digest() -> Result<()> {
+        let inner = inner_circuit(false);
+        let hashed_inner = inner_circuit(true);
+        let proof1 = prove_inner(&inner, 2, [1, 2, 3])?;
+        let proof2 = prove_inner(&hashed_inner, 3, [1, 2, 3])?;
+        aggregate(&inner, &proof1, &hashed_inner, &proof2, true)
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_connect_disagreeing_inner_public_input_digest() {
+        let inner = inner_circuit(false);
+        let hashed_inner = inner_circuit(true);
+        let proof1 = prove_inner(&inner, 2, [1, 2, 3]).unwrap();
+        let proof2 = prove_inner(&hashed_inner, 3, [1, 2, 4]).unwrap();
+        let _ = aggregate(&inner, &proof1, &hashed_inner, &proof2, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "is connected to a public input")]
+    fn test_shared_public_inputs_stay_private() {
+        let inner = inner_circuit(false);
+        let mut builder =
+            CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_zk_config());
+        let pt1 = builder.add_virtual_proof_with_pis(&inner.data.common);
+        let pt2 = builder.add_virtual_proof_with_pis(&inner.data.common);
+        builder.connect_inner_public_inputs(&pt1, 1..2, &pt2, 1..2);
+        // A copy of the shared value on the other side must not be exposed either.
+        builder.register_public_input(pt2.public_inputs[1]);
+        builder.build::<C>();
+    }
+
+    #[test]
+    #[should_panic(expected = "require a zero-knowledge config")]
+    fn test_shared_public_inputs_require_zk() {
+        let inner = inner_circuit(false);
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let pt1 = builder.add_virtual_proof_with_pis(&inner.data.common);
+        let pt2 = builder.add_virtual_proof_with_pis(&inner.data.common);
+        builder.connect_inner_public_inputs(&pt1, 1..2, &pt2, 1..2);
+        builder.build::<C>();
+    }
+}
@@ -0,0 +1,343 @@
+//! Recursive verification of proofs whose degree is a witness.
+//!
+//! A single circuit can verify proofs of circuits which only differ by their number of rows, e.g.
+//! the same logic padded to different sizes. The inner `CommonCircuitData` is that of the largest
+//! circuit, and the inner `degree_bits` is a witness in a range fixed when building the circuit.
+//! All the inner circuits must use the same FRI reduction arities, e.g. with
+//! `FriReductionStrategy::Fixed`, and proofs of the smaller ones are padded to the shape of the
+//! largest with `ProofWithPublicInputs::padded_to`.
+
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CommonCircuitData, VerifierCircuitTarget};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig};
+use crate::plonk::proof::ProofWithPublicInputsTarget;
+use crate::recursion::recursive_verifier::AssertSpongeTranscript;
+use crate::with_context;
+
+/// A witnessed `degree_bits` in a range fixed when building the circuit, along with a flag for
+/// each value of the range, exactly one of which is set.
+pub(crate) struct VariableDegreeBits {
+    degree_bits: Target,
+    range: RangeInclusive<usize>,
+    /// `is_degree_bits[i]` is whether `degree_bits` is the `i`th value of `range`.
+    is_degree_bits: Vec<BoolTarget>,
+}
+
+impl VariableDegreeBits {
+    /// Constrains `degree_bits` to lie in `range`.
+    pub(crate) fn new<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilder<F, D>,
+        degree_bits: Target,
+        range: RangeInclusive<usize>,
+    ) -> Self {
+        assert!(!range.is_empty(), "Empty range of degrees");
+        let is_degree_bits = range
+            .clone()
+            .map(|d| {
+                let d = builder.constant(F::from_canonical_usize(d));
+                builder.is_equal(degree_bits, d)
+            })
+            .collect::<Vec<_>>();
+        let num_matches = builder.add_many(is_degree_bits.iter().map(|b| b.target));
+        builder.assert_one(num_matches);
+        Self {
+            degree_bits,
+            range,
+            is_degree_bits,
+        }
+    }
+
+    pub(crate) const fn target(&self) -> Target {
+        self.degree_bits
+    }
+
+    pub(crate) fn range(&self) -> RangeInclusive<usize> {
+        self.range.clone()
+    }
+
+    /// Returns the entry of `values`, which has one for each degree of the range, at the witnessed
+    /// degree.
+    pub(crate) fn select<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        values: &[Target],
+    ) -> Target {
+        assert_eq!(values.len(), self.is_degree_bits.len());
+        let mut result = builder.zero();
+        for (is_d, &value) in self.is_degree_bits.iter().zip(values) {
+            result = builder.mul_add(is_d.target, value, result);
+        }
+        result
+    }
+
+    /// Like `select`, for `ExtensionTarget`s.
+    pub(crate) fn select_extension<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        values: &[ExtensionTarget<D>],
+    ) -> ExtensionTarget<D> {
+        assert_eq!(values.len(), self.is_degree_bits.len());
+        let mut result = builder.zero_extension();
+        for (is_d, &value) in self.is_degree_bits.iter().zip(values) {
+            result = builder.scalar_mul_add_extension(is_d.target, value, result);
+        }
+        result
+    }
+
+    /// Returns `f(degree_bits)`.
+    pub(crate) fn select_constant<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        f: impl Fn(usize) -> F,
+    ) -> Target {
+        let terms = self
+            .range
+            .clone()
+            .zip(&self.is_degree_bits)
+            .map(|(d, is_d)| builder.mul_const(f(d), is_d.target))
+            .collect::<Vec<_>>();
+        builder.add_many(terms)
+    }
+
+    /// Returns whether `degree_bits` satisfies `predicate`.
+    pub(crate) fn satisfies<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        predicate: impl Fn(usize) -> bool,
+    ) -> BoolTarget {
+        let matches = self
+            .range
+            .clone()
+            .zip(&self.is_degree_bits)
+            .filter(|&(d, _)| predicate(d))
+            .map(|(_, is_d)| is_d.target)
+            .collect::<Vec<_>>();
+        if matches.len() == self.is_degree_bits.len() {
+            return builder._true();
+        }
+        // At most one of the flags is set, so their sum is boolean.
+        BoolTarget::new_unsafe(builder.add_many(matches))
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Recursively verifies a proof, padded with `ProofWithPublicInputs::padded_to`, of a circuit
+    /// described by `inner_common_data` except for its degree, which is `2^degree_bits` for a
+    /// witnessed `degree_bits` between `min_degree_bits` and that of `inner_common_data`.
+    ///
+    /// Nothing binds `degree_bits` to `inner_verifier_data`, whose circuit digest commits to the
+    /// degree, so callers must constrain both together, e.g. by registering them as public inputs
+    /// or by checking them against a list of known circuits.
+    pub fn verify_proof_variable_degree<C: GenericConfig<D, F = F>>(
+        &mut self,
+        proof_with_pis: &ProofWithPublicInputsTarget<D>,
+        inner_verifier_data: &VerifierCircuitTarget,
+        inner_common_data: &CommonCircuitData<F, D>,
+        degree_bits: Target,
+        min_degree_bits: usize,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let () = AssertSpongeTranscript::<F, C::Transcript>::OK;
+        assert_eq!(
+            proof_with_pis.public_inputs.len(),
+            inner_common_data.num_public_inputs
+        );
+        let max_degree_bits = inner_common_data.degree_bits().get();
+        assert!(
+            min_degree_bits <= max_degree_bits,
+            "The minimum degree exceeds that of the inner circuit"
+        );
+        let degree_bits =
+            VariableDegreeBits::new(self, degree_bits, min_degree_bits..=max_degree_bits);
+
+        let proof = &proof_with_pis.proof;
+        let public_inputs_hash =
+            self.hash_n_to_hash_no_pad::<C::InnerHasher>(proof_with_pis.public_inputs.clone());
+        let challenges = proof_with_pis.get_challenges_variable_degree::<F, C>(
+            self,
+            public_inputs_hash,
+            inner_verifier_data.circuit_digest,
+            inner_common_data,
+            &degree_bits,
+        );
+        let zeta = challenges.plonk_zeta;
+
+        // `zeta^n` for each possible degree `n`.
+        let mut zeta_pow_deg_candidates = Vec::with_capacity(degree_bits.range().count());
+        let mut zeta_pow_deg = self.exp_power_of_2_extension(zeta, min_degree_bits);
+        for d in degree_bits.range() {
+            if d > min_degree_bits {
+                zeta_pow_deg = self.square_extension(zeta_pow_deg);
+            }
+            zeta_pow_deg_candidates.push(zeta_pow_deg);
+        }
+        let zeta_pow_deg = degree_bits.select_extension(self, &zeta_pow_deg_candidates);
+
+        // L_0(zeta) = (zeta^n - 1) / (n * (zeta - 1)), as in `eval_l_0_circuit`.
+        let l_0_zeta = {
+            let one = self.one_extension();
+            let n = degree_bits.select_constant(self, |d| F::from_canonical_usize(1 << d));
+            let z_h_zeta = self.sub_extension(zeta_pow_deg, one);
+            let zeta_minus_one = self.sub_extension(zeta, one);
+            let denominator = self.scalar_mul_ext(n, zeta_minus_one);
+            self.div_extension(z_h_zeta, denominator)
+        };
+
+        self.check_vanishing_and_quotient(
+            proof,
+            public_inputs_hash,
+            &challenges,
+            inner_common_data,
+            zeta_pow_deg,
+            l_0_zeta,
+        );
+
+        // The Z polynomials are also opened at g * zeta, for `g` a generator of the subgroup of
+        // order `n`.
+        let g = degree_bits.select_constant(self, |d| F::primitive_root_of_unity(d));
+        let zeta_next = self.scalar_mul_ext(g, zeta);
+        let fri_instance = inner_common_data.get_fri_instance_target_at(zeta, zeta_next);
+
+        let merkle_caps = Self::initial_merkle_caps(proof, inner_verifier_data, inner_common_data);
+        with_context!(
+            self,
+            "verify FRI proof",
+            self.verify_fri_proof_variable_degree::<C>(
+                &fri_instance,
+                &proof.openings.to_fri_openings(),
+                &challenges.fri_challenges,
+                &merkle_caps,
+                &proof.opening_proof,
+                &inner_common_data.fri_params,
+                &degree_bits,
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::fri::reduction_strategies::FriReductionStrategy;
+    use crate::gates::noop::NoopGate;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::plonk::generated_witness::GeneratedWitness;
+    use crate::plonk::proof::ProofWithPublicInputs;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type Witness = GeneratedWitness<F, <C as GenericConfig<D>>::Hasher>;
+
+    const MIN_DEGREE_BITS: usize = 5;
+    const MAX_DEGREE_BITS: usize = 7;
+
+    /// Proves `x^2 = y` in a circuit padded to `2^degree_bits` rows. Whatever the degree, the
+    /// circuits have the same gates and FRI reduction arities.
+    fn inner_proof(
+        degree_bits: usize,
+    ) -> Result<(CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>)> {
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.reduction_strategy = FriReductionStrategy::Fixed(vec![2, 2]);
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        for _ in 0..1 << (degree_bits - 1) {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        assert_eq!(data.common.degree_bits().get(), degree_bits);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_usize(degree_bits));
+        let proof = data.prove(pw)?;
+        Ok((data, proof))
+    }
+
+    /// Generates, for a single wrapper circuit, a witness verifying each inner proof of
+    /// `2^degree_bits` rows. Returns the wrapper circuit and its degree target too.
+    fn variable_degree_witnesses(
+        degrees: &[usize],
+    ) -> Result<(CircuitData<F, C, D>, Target, Vec<Witness>)> {
+        let inner = (MIN_DEGREE_BITS..=MAX_DEGREE_BITS)
+            .map(inner_proof)
+            .collect::<Result<Vec<_>>>()?;
+        let max_common_data = &inner[MAX_DEGREE_BITS - MIN_DEGREE_BITS].0.common;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let proof_t = builder.add_virtual_proof_with_pis(max_common_data);
+        let verifier_data_t =
+            builder.add_virtual_verifier_data(max_common_data.config.fri_config.cap_height);
+        builder.register_public_inputs(&verifier_data_t.circuit_digest.elements);
+        let degree_bits_t = builder.add_virtual_public_input();
+        builder.verify_proof_variable_degree::<C>(
+            &proof_t,
+            &verifier_data_t,
+            max_common_data,
+            degree_bits_t,
+            MIN_DEGREE_BITS,
+        );
+        let data = builder.build::<C>();
+
+        let witnesses = degrees
+            .iter()
+            .map(|&degree_bits| {
+                let (inner_data, inner_proof) = &inner[degree_bits - MIN_DEGREE_BITS];
+                let mut pw = PartialWitness::new();
+                pw.set_proof_with_pis_target(&proof_t, &inner_proof.padded_to(max_common_data));
+                pw.set_verifier_data_target(&verifier_data_t, &inner_data.verifier_only);
+                pw.set_target(degree_bits_t, F::from_canonical_usize(degree_bits));
+                data.generate_witness(pw)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok((data, degree_bits_t, witnesses))
+    }
+
+    #[test]
+    fn test_verify_proof_variable_degree() -> Result<()> {
+        let (data, _, witnesses) = variable_degree_witnesses(&[5, 6, 7])?;
+        for witness in witnesses {
+            let proof = data.prove_from_witness(&witness)?;
+            data.verify(proof)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_proof_variable_degree_wrong_degree() -> Result<()> {
+        let (data, degree_bits_t, mut witnesses) = variable_degree_witnesses(&[6])?;
+        // Claim that the inner proof has 2^5 rows rather than 2^6. The witness is otherwise the
+        // honest one, so only the wrapper's constraints can catch the claim.
+        let witness = &mut witnesses[0];
+        witness.overwrite_target(
+            degree_bits_t,
+            F::from_canonical_usize(5),
+            &data.prover_only.representative_map,
+        );
+        let proof = data.prove_from_witness(witness)?;
+        assert_eq!(
+            proof.public_inputs.last(),
+            Some(&F::from_canonical_usize(5))
+        );
+        assert!(data.verify(proof).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+//! Wrapping proofs into small proofs of a fixed configuration.
+//!
+//! A [`ProofWrapper`] recursively verifies a proof in a circuit of its target configuration, and
+//! wraps the result again for as long as this shrinks the circuit. A high-rate target, such as
+//! [`CircuitConfig::high_rate_recursion_config`], thus turns any proof into a small one whose
+//! verifier data only depend on the inner circuit.
+//!
+//! The wrapper circuits are built deterministically from the inner verifier data, which they bind
+//! as constants, and cached so that wrapping further proofs of the same circuit only proves.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use anyhow::Result;
+use hashbrown::hash_map::Entry;
+use hashbrown::HashMap;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{
+    CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitData, VerifierOnlyCircuitData,
+};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig, GenericHashOut};
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+
+/// How a wrapped proof exposes the public inputs of the proof it wraps.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WrappedPublicInputs {
+    /// The wrapped proof has the same public inputs.
+    Preserve,
+    /// The wrapped proof exposes `C::InnerHasher::hash_no_pad` of the public inputs.
+    Hash,
+}
+
+/// A circuit verifying one proof of a given circuit.
+struct WrapStage<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    data: CircuitData<F, C, D>,
+    proof: ProofWithPublicInputsTarget<D>,
+}
+
+/// Wraps proofs into proofs of `target_config`, caching the wrapper circuits of each inner circuit.
+pub struct ProofWrapper<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    target_config: CircuitConfig,
+    public_inputs: WrappedPublicInputs,
+    /// The stages wrapping proofs of each inner circuit, keyed by the digests of its common data
+    /// and of its verifier data.
+    stages: HashMap<Vec<u8>, Vec<WrapStage<F, C, D>>>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> ProofWrapper<F, C, D>
+where
+    C::Hasher: AlgebraicHasher<F>,
+{
+    pub fn new(target_config: CircuitConfig, public_inputs: WrappedPublicInputs) -> Self {
+        Self {
+            target_config,
+            public_inputs,
+            stages: HashMap::new(),
+        }
+    }
+
+    /// The number of wrapper circuits built so far, over all inner circuits.
+    pub fn num_cached_circuits(&self) -> usize {
+        self.stages.values().map(Vec::len).sum()
+    }
+
+    /// Wraps `proof`, of the circuit with the given verifier data, into a proof of
+    /// `target_config`. Returns the wrapped proof along with the verifier data of the last
+    /// wrapper circuit.
+    pub fn wrap(
+        &mut self,
+        proof: ProofWithPublicInputs<F, C, D>,
+        inner_verifier_only: &VerifierOnlyCircuitData<C, D>,
+        inner_common: &CommonCircuitData<F, D>,
+    ) -> Result<(ProofWithPublicInputs<F, C, D>, VerifierCircuitData<F, C, D>)> {
+        let mut key = inner_common.digest::<C>().to_bytes();
+        key.extend(inner_verifier_only.circuit_digest.to_bytes());
+        let stages = match self.stages.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(wrap_stages(
+                inner_verifier_only,
+                inner_common,
+                &self.target_config,
+                self.public_inputs,
+            )),
+        };
+
+        let mut proof = proof;
+        for stage in stages.iter() {
+            let mut pw = PartialWitness::new();
+            pw.set_proof_with_pis_target(&stage.proof, &proof);
+            proof = stage.data.prove(pw)?;
+        }
+        let last = stages.last().expect("There is at least one stage");
+        Ok((proof, last.data.verifier_data()))
+    }
+}
+
+/// Wraps `proof` into a proof of `target_config`, preserving its public inputs. This builds the
+/// wrapper circuits on each call; a [`ProofWrapper`] reuses them across proofs of a circuit.
+pub fn wrap_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    proof: ProofWithPublicInputs<F, C, D>,
+    inner_verifier_only: &VerifierOnlyCircuitData<C, D>,
+    inner_common: &CommonCircuitData<F, D>,
+    target_config: &CircuitConfig,
+) -> Result<(ProofWithPublicInputs<F, C, D>, VerifierCircuitData<F, C, D>)>
+where
+    C::Hasher: AlgebraicHasher<F>,
+{
+    ProofWrapper::new(target_config.clone(), WrappedPublicInputs::Preserve).wrap(
+        proof,
+        inner_verifier_only,
+        inner_common,
+    )
+}
+
+/// Builds the stages wrapping proofs of the given circuit: a first stage in `config`, followed by
+/// as many as shrink the circuit further.
+fn wrap_stages<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    inner_verifier_only: &VerifierOnlyCircuitData<C, D>,
+    inner_common: &CommonCircuitData<F, D>,
+    config: &CircuitConfig,
+    public_inputs: WrappedPublicInputs,
+) -> Vec<WrapStage<F, C, D>>
+where
+    C::Hasher: AlgebraicHasher<F>,
+{
+    let mut stages = vec![wrap_stage(
+        inner_verifier_only,
+        inner_common,
+        config,
+        public_inputs,
+    )];
+    loop {
+        let last = &stages[stages.len() - 1].data;
+        // The public inputs are already in their final form.
+        let next = wrap_stage(
+            &last.verifier_only,
+            &last.common,
+            config,
+            WrappedPublicInputs::Preserve,
+        );
+        if next.data.common.degree_bits() >= last.common.degree_bits() {
+            return stages;
+        }
+        stages.push(next);
+    }
+}
+
+fn wrap_stage<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    inner_verifier_only: &VerifierOnlyCircuitData<C, D>,
+    inner_common: &CommonCircuitData<F, D>,
+    config: &CircuitConfig,
+    public_inputs: WrappedPublicInputs,
+) -> WrapStage<F, C, D>
+where
+    C::Hasher: AlgebraicHasher<F>,
+{
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+    let proof = builder.add_virtual_proof_with_pis(inner_common);
+    let inner_data = builder.constant_verifier_data(inner_verifier_only);
+    builder.verify_proof::<C>(&proof, &inner_data, inner_common);
+    match public_inputs {
+        WrappedPublicInputs::Preserve => builder.register_public_inputs(&proof.public_inputs),
+        WrappedPublicInputs::Hash => {
+            let hash = builder.hash_n_to_hash_no_pad::<C::InnerHasher>(proof.public_inputs.clone());
+            builder.register_public_inputs(&hash.elements);
+        }
+    }
+    WrapStage {
+        data: builder.build::<C>(),
+        proof,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Field;
+    use crate::gates::noop::NoopGate;
+    use crate::plonk::config::{Hasher, PoseidonGoldilocksConfig};
+    use crate::util::log_size::DegreeBits;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Proves `y = x^2` for a public `x` and `y`, in a circuit of at least `num_gates` gates.
+    fn square_circuit(num_gates: usize) -> (CircuitData<F, C, D>, ProofWithPublicInputs<F, C, D>) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        while builder.num_gates() < num_gates {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove(pw).unwrap();
+        (data, proof)
+    }
+
+    #[test]
+    fn test_wrap_proof() -> Result<()> {
+        let (inner, proof) = square_circuit(0);
+        let config = CircuitConfig::standard_recursion_config();
+
+        let mut wrapper =
+            ProofWrapper::<F, C, D>::new(config.clone(), WrappedPublicInputs::Preserve);
+        let (wrapped, verifier_data) =
+            wrapper.wrap(proof.clone(), &inner.verifier_only, &inner.common)?;
+        assert_eq!(verifier_data.common.config, config);
+        assert_eq!(wrapped.public_inputs, proof.public_inputs);
+        verifier_data.verify(wrapped)?;
+
+        // A second proof of the same circuit reuses the wrapper circuits.
+        let num_circuits = wrapper.num_cached_circuits();
+        assert!(num_circuits >= 1);
+        let (wrapped, again_verifier_data) =
+            wrapper.wrap(proof.clone(), &inner.verifier_only, &inner.common)?;
+        assert_eq!(wrapper.num_cached_circuits(), num_circuits);
+        assert_eq!(again_verifier_data, verifier_data);
+        verifier_data.verify(wrapped)?;
+
+        // The wrapper circuits are built deterministically.
+        let (wrapped, fresh_verifier_data) =
+            wrap_proof(proof.clone(), &inner.verifier_only, &inner.common, &config)?;
+        assert_eq!(fresh_verifier_data, verifier_data);
+        verifier_data.verify(wrapped)?;
+
+        // Hashing the public inputs leads to different wrapper circuits.
+        let mut wrapper = ProofWrapper::<F, C, D>::new(config, WrappedPublicInputs::Hash);
+        let (wrapped, hashed_verifier_data) =
+            wrapper.wrap(proof.clone(), &inner.verifier_only, &inner.common)?;
+        assert_eq!(
+            wrapped.public_inputs,
+            <C as GenericConfig<D>>::InnerHasher::hash_no_pad(&proof.public_inputs)
+                .elements
+                .to_vec()
+        );
+        assert_ne!(hashed_verifier_data, verifier_data);
+        hashed_verifier_data.verify(wrapped)
+    }
+
+    #[test]
+    fn test_wrap_reuses_cached_circuits() -> Result<()> {
+        let (inner, proof) = square_circuit(0);
+        let (other_inner, other_proof) = square_circuit(100);
+        assert_ne!(inner.common.degree_bits(), other_inner.common.degree_bits());
+        let config = CircuitConfig::standard_recursion_config();
+        let mut wrapper = ProofWrapper::<F, C, D>::new(config, WrappedPublicInputs::Preserve);
+
+        let (_, verifier_data) = wrapper.wrap(proof, &inner.verifier_only, &inner.common)?;
+        let num_circuits = wrapper.num_cached_circuits();
+
+        // Another proof of the same circuit hits the cache.
+        let mut pw = PartialWitness::new();
+        pw.set_target(inner.prover_only.public_inputs[0], F::from_canonical_u64(5));
+        let second_proof = inner.prove(pw)?;
+        let (wrapped, second_verifier_data) =
+            wrapper.wrap(second_proof, &inner.verifier_only, &inner.common)?;
+        assert_eq!(wrapper.num_cached_circuits(), num_circuits);
+        assert_eq!(second_verifier_data, verifier_data);
+        assert_eq!(wrapped.public_inputs[1], F::from_canonical_u64(25));
+        verifier_data.verify(wrapped)?;
+
+        // A different inner circuit misses it, and gets wrapper circuits of its own.
+        let (wrapped, other_verifier_data) =
+            wrapper.wrap(other_proof, &other_inner.verifier_only, &other_inner.common)?;
+        assert!(wrapper.num_cached_circuits() > num_circuits);
+        assert_ne!(other_verifier_data, verifier_data);
+        other_verifier_data.verify(wrapped)
+    }
+
+    /// Wraps a proof of `2^16` gates into a high-rate proof. This needs a lot of memory.
+    #[test]
+    #[ignore]
+    fn test_wrap_large_proof() -> Result<()> {
+        let (inner, proof) = square_circuit((1 << 15) + 1);
+        assert_eq!(inner.common.degree_bits(), DegreeBits::new(16));
+        let config = CircuitConfig::high_rate_recursion_config();
+
+        let mut wrapper =
+            ProofWrapper::<F, C, D>::new(config.clone(), WrappedPublicInputs::Preserve);
+        let (wrapped, verifier_data) =
+            wrapper.wrap(proof.clone(), &inner.verifier_only, &inner.common)?;
+        assert_eq!(verifier_data.common.config, config);
+        assert!(verifier_data.common.degree_bits() < inner.common.degree_bits());
+        assert_eq!(wrapped.public_inputs, proof.public_inputs);
+        verifier_data.verify(wrapped)?;
+
+        // A second call reuses the cached wrapper circuits.
+        let num_circuits = wrapper.num_cached_circuits();
+        let (wrapped, again_verifier_data) =
+            wrapper.wrap(proof, &inner.verifier_only, &inner.common)?;
+        assert_eq!(wrapper.num_cached_circuits(), num_circuits);
+        assert_eq!(again_verifier_data, verifier_data);
+        verifier_data.verify(wrapped)
+    }
+}
@@ -0,0 +1,201 @@
+//! Typed log2 sizes, so that the log of a polynomial's degree, the log of its low-degree extension
+//! and plain sizes cannot be mixed up.
+//!
+//! Converting from a `usize` is always explicit, through `new` or `From`. For example, a degree
+//! cannot be passed where its log is expected:
+//!
+//! ```compile_fail
+//! use plonky2::plonk::circuit_data::CircuitConfig;
+//!
+//! let config = CircuitConfig::standard_recursion_config().fri_config;
+//! let degree: usize = 1 << 10;
+//! let params = config.fri_params(degree, false);
+//! ```
+//!
+//! Nor can the log of an LDE size be passed where the log of a degree is expected:
+//!
+//! ```compile_fail
+//! use plonky2::plonk::circuit_data::CircuitConfig;
+//! use plonky2::util::log_size::DegreeBits;
+//!
+//! let config = CircuitConfig::standard_recursion_config().fri_config;
+//! let lde_bits = DegreeBits::new(10) + config.rate_bits;
+//! let params = config.fri_params(lde_bits, false);
+//! ```
+
+use core::fmt::{Display, Formatter};
+use core::ops::{Add, Shl, Sub, SubAssign};
+
+use serde::{Deserialize, Serialize};
+
+/// The log2 of a polynomial's degree bound, i.e. of the size of its evaluation domain before any
+/// low-degree extension.
+#[derive(
+    Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct DegreeBits(usize);
+
+/// The log2 of the size of a low-degree extension domain, i.e. `degree_bits + rate_bits`. This is
+/// also the height of the Merkle tree committing to the extension, and the number of bits in a
+/// query index.
+#[derive(
+    Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct LdeBits(usize);
+
+impl DegreeBits {
+    pub const fn new(bits: usize) -> Self {
+        Self(bits)
+    }
+
+    pub const fn get(self) -> usize {
+        self.0
+    }
+
+    /// The degree bound, i.e. `1 << self`.
+    pub const fn size(self) -> usize {
+        1 << self.0
+    }
+
+    /// The log of the low-degree extension with rate `2^{-rate_bits}`.
+    pub const fn lde_bits(self, rate_bits: usize) -> LdeBits {
+        LdeBits(self.0 + rate_bits)
+    }
+}
+
+impl LdeBits {
+    pub const fn new(bits: usize) -> Self {
+        Self(bits)
+    }
+
+    pub const fn get(self) -> usize {
+        self.0
+    }
+
+    /// The size of the domain, i.e. `1 << self`.
+    pub const fn size(self) -> usize {
+        1 << self.0
+    }
+
+    /// The length of a Merkle proof to a cap of height `cap_height`, in a tree of this height.
+    pub fn merkle_proof_len(self, cap_height: usize) -> usize {
+        assert!(
+            self.0 >= cap_height,
+            "A Merkle tree of height {} cannot have a cap of height {}",
+            self.0,
+            cap_height
+        );
+        self.0 - cap_height
+    }
+}
+
+impl From<usize> for DegreeBits {
+    fn from(bits: usize) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<DegreeBits> for usize {
+    fn from(bits: DegreeBits) -> Self {
+        bits.0
+    }
+}
+
+impl From<usize> for LdeBits {
+    fn from(bits: usize) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<LdeBits> for usize {
+    fn from(bits: LdeBits) -> Self {
+        bits.0
+    }
+}
+
+impl Display for DegreeBits {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl Display for LdeBits {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+/// Extends by `rate_bits`.
+impl Add<usize> for DegreeBits {
+    type Output = LdeBits;
+
+    fn add(self, rate_bits: usize) -> LdeBits {
+        self.lde_bits(rate_bits)
+    }
+}
+
+/// Folds by `arity_bits`.
+impl Sub<usize> for DegreeBits {
+    type Output = Self;
+
+    fn sub(self, arity_bits: usize) -> Self {
+        Self(self.0 - arity_bits)
+    }
+}
+
+/// Folds by `arity_bits`, or strips `cap_height` layers from a Merkle tree.
+impl Sub<usize> for LdeBits {
+    type Output = Self;
+
+    fn sub(self, bits: usize) -> Self {
+        Self(self.0 - bits)
+    }
+}
+
+impl SubAssign<usize> for DegreeBits {
+    fn sub_assign(&mut self, arity_bits: usize) {
+        *self = *self - arity_bits;
+    }
+}
+
+impl SubAssign<usize> for LdeBits {
+    fn sub_assign(&mut self, bits: usize) {
+        *self = *self - bits;
+    }
+}
+
+impl Shl<DegreeBits> for usize {
+    type Output = usize;
+
+    fn shl(self, bits: DegreeBits) -> usize {
+        self << bits.0
+    }
+}
+
+impl Shl<LdeBits> for usize {
+    type Output = usize;
+
+    fn shl(self, bits: LdeBits) -> usize {
+        self << bits.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_sizes() {
+        let degree_bits = DegreeBits::new(10);
+        let lde_bits = degree_bits + 3;
+        assert_eq!(lde_bits, LdeBits::new(13));
+        assert_eq!(1 << lde_bits, 1 << 13);
+        assert_eq!(degree_bits.size(), 1024);
+        assert_eq!((lde_bits - 4).get(), 9);
+        assert_eq!(usize::from(degree_bits - 4), 6);
+        assert!(DegreeBits::new(3) < degree_bits);
+        assert_eq!(lde_bits.merkle_proof_len(4), 9);
+    }
+}
@@ -11,10 +11,14 @@ use crate::field::polynomial::PolynomialValues;
 use crate::field::types::Field;
 
 pub(crate) mod context_tree;
+pub mod log_size;
 pub(crate) mod partial_products;
 pub mod reducing;
+pub mod sensitive;
 pub mod serialization;
 pub mod strided_view;
+#[cfg(test)]
+pub(crate) mod test_fixtures;
 pub mod timing;
 
 pub(crate) fn transpose_poly_values<F: Field>(polys: Vec<PolynomialValues<F>>) -> Vec<Vec<F>> {
@@ -30,6 +34,16 @@ pub fn transpose<T: Send + Sync + Copy>(matrix: &[Vec<T>]) -> Vec<Vec<T>> {
         .collect()
 }
 
+/// Like `transpose`, but writes the rows into `rows`, reusing its allocations.
+pub(crate) fn transpose_into<T: Send + Sync + Copy>(matrix: &[Vec<T>], rows: &mut Vec<Vec<T>>) {
+    let len = matrix[0].len();
+    rows.resize_with(len, Vec::new);
+    rows.par_iter_mut().enumerate().for_each(|(i, row)| {
+        row.clear();
+        row.extend(matrix.iter().map(|column| column[i]));
+    });
+}
+
 pub(crate) const fn reverse_bits(n: usize, num_bits: usize) -> usize {
     // NB: The only reason we need overflowing_shr() here as opposed
     // to plain '>>' is to accommodate the case n == num_bits == 0,
@@ -0,0 +1,200 @@
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use zeroize::Zeroize;
+
+use crate::hash::hash_types::RichField;
+
+/// Overwrites every element of `values`, along with the spare capacity which may still hold
+/// earlier elements, with `zero`. As with `zeroize`, the writes are volatile, so that they aren't
+/// optimized away although the memory is about to be freed.
+///
+/// Unlike `Vec::zeroize`, this keeps the length, and needs no `Zeroize` bound on the elements, so
+/// that the prover can wipe any field without `RichField` requiring it.
+pub(crate) fn wipe_vec<T: Copy>(values: &mut Vec<T>, zero: T) {
+    for value in values.iter_mut() {
+        // SAFETY: `value` is a valid, aligned reference.
+        unsafe { ptr::write_volatile(value, zero) };
+    }
+    for slot in values.spare_capacity_mut() {
+        // SAFETY: `slot` is valid and aligned, and writing to uninitialized memory is fine.
+        unsafe { ptr::write_volatile(slot.as_mut_ptr(), zero) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Data derived from the witness, which a [`SensitiveBuffers`] can wipe.
+pub trait SensitiveData: Zeroize {
+    type Field: RichField;
+
+    /// Calls `f` on every value held, e.g. to check that none is left after wiping.
+    fn for_each_value(&self, f: &mut dyn FnMut(Self::Field));
+}
+
+/// Holds data derived from the witness, such as the witness matrix or an oracle's LDEs and salts,
+/// and optionally overwrites it with zeros when dropped, see `ProverOptions::zeroize_witness`.
+/// Otherwise, the data is freed as usual, and may linger on the heap until the memory is reused.
+#[derive(Debug)]
+pub struct SensitiveBuffers<T: SensitiveData> {
+    inner: T,
+    zeroize: bool,
+}
+
+impl<T: SensitiveData> SensitiveBuffers<T> {
+    /// Wraps `inner`, which is wiped on drop if `zeroize` is set.
+    pub const fn new(inner: T, zeroize: bool) -> Self {
+        Self { inner, zeroize }
+    }
+
+    /// Whether the data is wiped on drop.
+    pub const fn zeroizes(&self) -> bool {
+        self.zeroize
+    }
+}
+
+impl<T: SensitiveData> Deref for SensitiveBuffers<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: SensitiveData> DerefMut for SensitiveBuffers<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T: SensitiveData> Drop for SensitiveBuffers<T> {
+    fn drop(&mut self) {
+        if self.zeroize {
+            self.inner.zeroize();
+        }
+        #[cfg(all(test, feature = "std"))]
+        inspection::inspect(&self.inner);
+    }
+}
+
+/// Lets tests look at the data of every [`SensitiveBuffers`] dropped on the current thread, once
+/// it has been wiped, if it was.
+#[cfg(all(test, feature = "std"))]
+pub(crate) mod inspection {
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    use super::SensitiveData;
+    use crate::field::types::{Field, PrimeField64};
+
+    /// What was left of the data of a dropped [`SensitiveBuffers`](super::SensitiveBuffers).
+    #[derive(Clone, Debug)]
+    pub(crate) struct Remains {
+        pub type_name: &'static str,
+        pub num_nonzero: usize,
+        pub contains_canary: bool,
+    }
+
+    std::thread_local! {
+        static RECORDING: RefCell<Option<(u64, Vec<Remains>)>> = const { RefCell::new(None) };
+    }
+
+    /// Runs `f`, and returns what was left of each buffer dropped meanwhile, noting whether it
+    /// still holds `canary`.
+    pub(crate) fn record<R>(canary: u64, f: impl FnOnce() -> R) -> (R, Vec<Remains>) {
+        RECORDING.with(|recording| *recording.borrow_mut() = Some((canary, Vec::new())));
+        let result = f();
+        let (_, remains) = RECORDING
+            .with(|recording| recording.borrow_mut().take())
+            .unwrap();
+        (result, remains)
+    }
+
+    pub(super) fn inspect<T: SensitiveData>(data: &T) {
+        RECORDING.with(|recording| {
+            if let Some((canary, remains)) = recording.borrow_mut().as_mut() {
+                let mut num_nonzero = 0;
+                let mut contains_canary = false;
+                data.for_each_value(&mut |x| {
+                    num_nonzero += usize::from(!x.is_zero());
+                    contains_canary |= x.to_canonical_u64() == *canary;
+                });
+                remains.push(Remains {
+                    type_name: core::any::type_name::<T>(),
+                    num_nonzero,
+                    contains_canary,
+                });
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use alloc::vec;
+    use core::cell::RefCell;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, Sample};
+
+    type F = GoldilocksField;
+
+    /// Records the contents it held when it was wiped.
+    struct Recorder {
+        data: Vec<F>,
+        wiped: Rc<RefCell<Option<Vec<F>>>>,
+    }
+
+    impl Zeroize for Recorder {
+        fn zeroize(&mut self) {
+            wipe_vec(&mut self.data, F::ZERO);
+            *self.wiped.borrow_mut() = Some(self.data.clone());
+        }
+    }
+
+    impl SensitiveData for Recorder {
+        type Field = F;
+
+        fn for_each_value(&self, f: &mut dyn FnMut(F)) {
+            self.data.iter().copied().for_each(f);
+        }
+    }
+
+    #[test]
+    fn test_sensitive_buffers_zeroize_on_drop() {
+        for zeroize in [false, true] {
+            let wiped = Rc::new(RefCell::new(None));
+            let mut buffers = SensitiveBuffers::new(
+                Recorder {
+                    data: F::rand_vec(3),
+                    wiped: wiped.clone(),
+                },
+                zeroize,
+            );
+            buffers.data.push(F::ONE);
+            assert_eq!(buffers.data.len(), 4);
+            drop(buffers);
+
+            // The length is kept, so the zeros can be checked.
+            let expected = zeroize.then(|| vec![F::ZERO; 4]);
+            assert_eq!(*wiped.borrow(), expected);
+        }
+    }
+
+    #[test]
+    fn test_wipe_vec_spare_capacity() {
+        let mut values = vec![1u64, 2, 3, 4];
+        values.truncate(1);
+        wipe_vec(&mut values, 0);
+        assert_eq!(values, [0]);
+        let spare = values.spare_capacity_mut()[..3]
+            .iter()
+            // SAFETY: `wipe_vec` initialized the spare capacity.
+            .map(|x| unsafe { x.assume_init() })
+            .collect::<Vec<_>>();
+        assert_eq!(spare, [0; 3]);
+    }
+}
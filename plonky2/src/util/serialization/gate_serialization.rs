@@ -100,6 +100,7 @@ pub mod default {
     use crate::gates::exponentiation::ExponentiationGate;
     use crate::gates::lookup::LookupGate;
     use crate::gates::lookup_table::LookupTableGate;
+    use crate::gates::mul_add_point::MulAddPointGate;
     use crate::gates::multiplication_extension::MulExtensionGate;
     use crate::gates::noop::NoopGate;
     use crate::gates::poseidon::PoseidonGate;
@@ -108,6 +109,7 @@ pub mod default {
     use crate::gates::random_access::RandomAccessGate;
     use crate::gates::reducing::ReducingGate;
     use crate::gates::reducing_extension::ReducingExtensionGate;
+    use crate::gates::running_sum::RunningSumGate;
     use crate::hash::hash_types::RichField;
     use crate::util::serialization::GateSerializer;
 
@@ -130,7 +132,9 @@ pub mod default {
             PublicInputGate,
             RandomAccessGate<F, D>,
             ReducingExtensionGate<D>,
-            ReducingGate<D>
+            ReducingGate<D>,
+            RunningSumGate,
+            MulAddPointGate<F, D>
         }
     }
 }
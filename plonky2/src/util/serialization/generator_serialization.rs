@@ -100,6 +100,10 @@ pub mod default {
 
     use crate::gadgets::arithmetic::EqualityGenerator;
     use crate::gadgets::arithmetic_extension::QuotientGeneratorExtension;
+    use crate::gadgets::poseidon_bn254::{
+        Bn254CarryGenerator, Bn254DigestGenerator, Bn254ReductionGenerator, Bn254SlackGenerator,
+    };
+    use crate::gadgets::public_input_set::PublicInputsSetIndexGenerator;
     use crate::gadgets::range_check::LowHighGenerator;
     use crate::gadgets::split_base::BaseSumGenerator;
     use crate::gadgets::split_join::{SplitGenerator, WireSplitGenerator};
@@ -110,12 +114,14 @@ pub mod default {
     use crate::gates::exponentiation::ExponentiationGenerator;
     use crate::gates::lookup::LookupGenerator;
     use crate::gates::lookup_table::LookupTableGenerator;
+    use crate::gates::mul_add_point::MulAddPointGenerator;
     use crate::gates::multiplication_extension::MulExtensionGenerator;
     use crate::gates::poseidon::PoseidonGenerator;
     use crate::gates::poseidon_mds::PoseidonMdsGenerator;
     use crate::gates::random_access::RandomAccessGenerator;
     use crate::gates::reducing::ReducingGenerator;
     use crate::gates::reducing_extension::ReducingGenerator as ReducingExtensionGenerator;
+    use crate::gates::running_sum::RunningSumGenerator;
     use crate::hash::hash_types::RichField;
     use crate::iop::generator::{
         ConstantGenerator, CopyGenerator, NonzeroTestGenerator, RandomValueGenerator,
@@ -140,6 +146,10 @@ pub mod default {
             ArithmeticExtensionGenerator<F, D>,
             BaseSplitGenerator<2>,
             BaseSumGenerator<2>,
+            Bn254CarryGenerator,
+            Bn254DigestGenerator,
+            Bn254ReductionGenerator,
+            Bn254SlackGenerator,
             ConstantGenerator<F>,
             CopyGenerator,
             DummyProofGenerator<F, C, D>,
@@ -158,8 +168,11 @@ pub mod default {
             RandomValueGenerator,
             ReducingGenerator<D>,
             ReducingExtensionGenerator<D>,
+            RunningSumGenerator,
             SplitGenerator,
-            WireSplitGenerator
+            WireSplitGenerator,
+            MulAddPointGenerator<F, D>,
+            PublicInputsSetIndexGenerator
         }
     }
 }
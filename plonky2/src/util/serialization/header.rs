@@ -0,0 +1,245 @@
+//! The header shared by all serialized artifacts: circuit data, proofs and witnesses.
+//!
+//! Each artifact is prefixed by [`ARTIFACT_MAGIC`], a byte naming its [`ArtifactKind`] and the
+//! version of that kind's encoding as a little-endian `u16`, so that passing the wrong blob, or a
+//! blob written by an incompatible release, is reported as such rather than as a failure to
+//! deserialize. Kinds may extend the header with fields of their own, such as the
+//! [`ProofHeader`](crate::plonk::proof_header::ProofHeader) of proofs.
+
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+/// The bytes every artifact starts with.
+pub const ARTIFACT_MAGIC: [u8; 4] = *b"PLK2";
+
+/// The length of the header: the magic, a kind byte and a little-endian `u16` format version.
+pub const ARTIFACT_HEADER_LEN: usize = ARTIFACT_MAGIC.len() + 1 + 2;
+
+/// The kinds of serialized artifacts.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ArtifactKind {
+    VerifierData,
+    CommonData,
+    Proof,
+    CompressedProof,
+    Witness,
+}
+
+impl ArtifactKind {
+    /// The version of the encoding of this kind written by this release. It must be bumped
+    /// whenever the byte format of the kind changes. Kinds first written by the
+    /// [`entrypoint`](crate::entrypoint) continue its numbering.
+    pub const fn format_version(self) -> u16 {
+        match self {
+            Self::VerifierData => 3,
+            Self::CommonData => 3,
+            Self::Proof => 4,
+            // Version 2 delta-encodes the query indices, see `CompressedFriProof::encode_indices`.
+            Self::CompressedProof => 2,
+            Self::Witness => 1,
+        }
+    }
+
+    pub(crate) const fn tag(self) -> u8 {
+        match self {
+            Self::VerifierData => 1,
+            Self::CommonData => 2,
+            Self::Proof => 3,
+            Self::CompressedProof => 4,
+            Self::Witness => 5,
+        }
+    }
+
+    pub(crate) const fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(Self::VerifierData),
+            2 => Some(Self::CommonData),
+            3 => Some(Self::Proof),
+            4 => Some(Self::CompressedProof),
+            5 => Some(Self::Witness),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ArtifactKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VerifierData => write!(f, "verifier data"),
+            Self::CommonData => write!(f, "common circuit data"),
+            Self::Proof => write!(f, "proof"),
+            Self::CompressedProof => write!(f, "compressed proof"),
+            Self::Witness => write!(f, "witness"),
+        }
+    }
+}
+
+/// The reasons an artifact header can be rejected.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HeaderError {
+    /// The artifact is too short to hold a header.
+    Missing,
+    /// The artifact doesn't start with [`ARTIFACT_MAGIC`]. It may have been serialized without a
+    /// header.
+    BadMagic,
+    /// The header names an unknown kind of artifact.
+    UnknownKind(u8),
+    /// The header names another kind of artifact.
+    WrongKind {
+        expected: ArtifactKind,
+        found: ArtifactKind,
+    },
+    /// The artifact was written with an encoding this release can't read.
+    UnsupportedFormatVersion { kind: ArtifactKind, version: u16 },
+}
+
+impl Display for HeaderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "The artifact is too short to hold a header"),
+            Self::BadMagic => write!(
+                f,
+                "The artifact doesn't start with the artifact magic; it may have been serialized \
+                 without a header"
+            ),
+            Self::UnknownKind(tag) => write!(f, "The artifact is of unknown kind {tag}"),
+            Self::WrongKind { expected, found } => {
+                write!(f, "Expected the {expected}, but got the {found}")
+            }
+            Self::UnsupportedFormatVersion {
+                kind: ArtifactKind::CompressedProof,
+                version: 1,
+            } => write!(
+                f,
+                "The compressed proof has format version 1, which stores its query indices \
+                 without delta encoding; only version {} is supported",
+                ArtifactKind::CompressedProof.format_version()
+            ),
+            Self::UnsupportedFormatVersion { kind, version } => write!(
+                f,
+                "The {kind} has format version {version}, but only version {} is supported",
+                kind.format_version()
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HeaderError {}
+
+/// The header of an artifact.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ArtifactHeader {
+    pub kind: ArtifactKind,
+    pub format_version: u16,
+}
+
+impl ArtifactHeader {
+    /// The header of an artifact of the given kind written by this release.
+    pub const fn current(kind: ArtifactKind) -> Self {
+        Self {
+            kind,
+            format_version: kind.format_version(),
+        }
+    }
+
+    /// Parses the header of an artifact of a kind and version this release can read, and returns
+    /// it along with the artifact's body.
+    pub fn split(bytes: &[u8]) -> Result<(Self, &[u8]), HeaderError> {
+        let (header, body) = Self::parse(bytes)?;
+        header.check_version()?;
+        Ok((header, body))
+    }
+
+    /// Parses the header of an artifact of the given kind, and returns the artifact's body.
+    pub fn split_kind(bytes: &[u8], kind: ArtifactKind) -> Result<&[u8], HeaderError> {
+        let (header, body) = Self::parse(bytes)?;
+        if header.kind != kind {
+            return Err(HeaderError::WrongKind {
+                expected: kind,
+                found: header.kind,
+            });
+        }
+        header.check_version()?;
+        Ok(body)
+    }
+
+    fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), HeaderError> {
+        if bytes.len() < ARTIFACT_HEADER_LEN {
+            return Err(if bytes.starts_with(&ARTIFACT_MAGIC[..bytes.len()]) {
+                HeaderError::Missing
+            } else {
+                HeaderError::BadMagic
+            });
+        }
+        let (header, body) = bytes.split_at(ARTIFACT_HEADER_LEN);
+        if header[..ARTIFACT_MAGIC.len()] != ARTIFACT_MAGIC {
+            return Err(HeaderError::BadMagic);
+        }
+        let tag = header[ARTIFACT_MAGIC.len()];
+        let kind = ArtifactKind::from_tag(tag).ok_or(HeaderError::UnknownKind(tag))?;
+        let format_version = u16::from_le_bytes([
+            header[ARTIFACT_HEADER_LEN - 2],
+            header[ARTIFACT_HEADER_LEN - 1],
+        ]);
+        Ok((
+            Self {
+                kind,
+                format_version,
+            },
+            body,
+        ))
+    }
+
+    fn check_version(&self) -> Result<(), HeaderError> {
+        if self.format_version != self.kind.format_version() {
+            return Err(HeaderError::UnsupportedFormatVersion {
+                kind: self.kind,
+                version: self.format_version,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&ARTIFACT_MAGIC);
+        buffer.push(self.kind.tag());
+        buffer.extend_from_slice(&self.format_version.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_round_trip() {
+        let mut bytes = Vec::new();
+        ArtifactHeader::current(ArtifactKind::Witness).write(&mut bytes);
+        assert_eq!(bytes.len(), ARTIFACT_HEADER_LEN);
+        bytes.push(42);
+
+        assert_eq!(
+            ArtifactHeader::split(&bytes),
+            Ok((ArtifactHeader::current(ArtifactKind::Witness), &[42][..]))
+        );
+        assert_eq!(
+            ArtifactHeader::split_kind(&bytes, ArtifactKind::Proof),
+            Err(HeaderError::WrongKind {
+                expected: ArtifactKind::Proof,
+                found: ArtifactKind::Witness,
+            })
+        );
+        assert_eq!(
+            ArtifactHeader::split(&bytes[..3]),
+            Err(HeaderError::Missing)
+        );
+        assert_eq!(ArtifactHeader::split(&[42]), Err(HeaderError::BadMagic));
+
+        bytes[ARTIFACT_MAGIC.len()] = 0;
+        assert_eq!(
+            ArtifactHeader::split(&bytes),
+            Err(HeaderError::UnknownKind(0))
+        );
+    }
+}
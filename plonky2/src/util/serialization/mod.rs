@@ -4,7 +4,10 @@ pub mod generator_serialization;
 #[macro_use]
 pub mod gate_serialization;
 
+pub mod header;
+
 use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
@@ -17,7 +20,6 @@ pub use gate_serialization::default::DefaultGateSerializer;
 pub use gate_serialization::GateSerializer;
 pub use generator_serialization::default::DefaultGeneratorSerializer;
 pub use generator_serialization::WitnessGeneratorSerializer;
-use hashbrown::HashMap;
 
 use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::polynomial::PolynomialCoeffs;
@@ -28,7 +30,7 @@ use crate::fri::proof::{
     FriProof, FriProofTarget, FriQueryRound, FriQueryRoundTarget, FriQueryStep, FriQueryStepTarget,
 };
 use crate::fri::reduction_strategies::FriReductionStrategy;
-use crate::fri::{FriConfig, FriParams};
+use crate::fri::{FriConfig, FriParams, FriPowStrategy};
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::gates::gate::GateRef;
 use crate::gates::lookup::Lookup;
@@ -46,11 +48,29 @@ use crate::plonk::circuit_data::{
     VerifierCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
 };
 use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
-use crate::plonk::plonk_common::salt_size;
 use crate::plonk::proof::{
     CompressedProof, CompressedProofWithPublicInputs, OpeningSet, OpeningSetTarget, Proof,
     ProofTarget, ProofWithPublicInputs, ProofWithPublicInputsTarget,
 };
+use crate::plonk::transcript::TranscriptVersionPolicy;
+use crate::util::log_size::DegreeBits;
+
+/// The layouts of [`CircuitConfig`]s written by past releases, and thus of the circuit data holding
+/// them, which can still be read.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ConfigLayout {
+    /// The layout of releases predating FRI proof-of-work strategies and the builder's
+    /// optimization passes. FRI configs hold a `u32` of proof-of-work bits and no
+    /// `commit_final_poly`, and circuit configs have neither `optimize_arithmetic` nor
+    /// `eliminate_dead_code`.
+    V1,
+    V2,
+}
+
+impl ConfigLayout {
+    /// The layout written by this release.
+    pub const CURRENT: Self = Self::V2;
+}
 
 /// A no_std compatible variant of `std::io::Error`
 #[derive(Debug)]
@@ -139,6 +159,65 @@ pub trait Read {
         Ok(u64::from_le_bytes(buf) as usize)
     }
 
+    /// Reads a `u64` value encoded as an unsigned LEB128 varint from `self`. Only the shortest
+    /// encoding of each value is accepted.
+    #[inline]
+    fn read_varint(&mut self) -> IoResult<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            let bits = u64::from(byte & 0x7f);
+            if shift == 63 && bits > 1 {
+                return Err(IoError);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                if byte == 0 && shift != 0 {
+                    return Err(IoError);
+                }
+                return Ok(value);
+            }
+        }
+        Err(IoError)
+    }
+
+    /// Reads FRI query indices written by `write_query_indices` from `self`.
+    #[inline]
+    fn read_query_indices(&mut self) -> IoResult<Vec<usize>> {
+        let len = self.read_varint()? as usize;
+        let num_distinct = self.read_varint()? as usize;
+        if num_distinct > len || (num_distinct == 0 && len != 0) {
+            return Err(IoError);
+        }
+
+        let mut distinct = Vec::new();
+        for _ in 0..num_distinct {
+            let delta = self.read_varint()? as usize;
+            let index = match distinct.last() {
+                None => delta,
+                // Distinct indices are strictly increasing.
+                Some(_) if delta == 0 => return Err(IoError),
+                Some(&prev) => usize::checked_add(prev, delta).ok_or(IoError)?,
+            };
+            distinct.push(index);
+        }
+
+        let mut used = vec![false; num_distinct];
+        let indices = (0..len)
+            .map(|_| {
+                let position = self.read_varint()? as usize;
+                let &index = distinct.get(position).ok_or(IoError)?;
+                used[position] = true;
+                Ok(index)
+            })
+            .collect::<IoResult<Vec<_>>>()?;
+        // Each distinct index must occur, so that the encoding is unique.
+        if used.contains(&false) {
+            return Err(IoError);
+        }
+        Ok(indices)
+    }
+
     /// Reads a vector of `usize` value from `self`.
     #[inline]
     fn read_usize_vec(&mut self) -> IoResult<Vec<usize>> {
@@ -358,19 +437,25 @@ pub trait Read {
         let lookup_zs_next = self.read_field_ext_vec::<F, D>(common_data.num_all_lookup_polys())?;
         let partial_products = self
             .read_field_ext_vec::<F, D>(common_data.num_partial_products * config.num_challenges)?;
-        let quotient_polys = self.read_field_ext_vec::<F, D>(
-            common_data.quotient_degree_factor * config.num_challenges,
-        )?;
+        let quotient_polys = self.read_field_ext_vec::<F, D>(common_data.num_quotient_polys())?;
+        let challenge_dependent =
+            self.read_field_ext_vec::<F, D>(common_data.num_challenge_dependent_columns())?;
+        let challenge_dependent_next =
+            self.read_field_ext_vec::<F, D>(common_data.num_challenge_dependent_columns())?;
+        let wires_next = self.read_field_ext_vec::<F, D>(common_data.num_next_row_wires())?;
         Ok(OpeningSet {
             constants,
             plonk_sigmas,
             wires,
+            wires_next,
             plonk_zs,
             plonk_zs_next,
             partial_products,
             quotient_polys,
             lookup_zs,
             lookup_zs_next,
+            challenge_dependent,
+            challenge_dependent_next,
         })
     }
 
@@ -386,17 +471,22 @@ pub trait Read {
         let next_lookup_zs = self.read_target_ext_vec::<D>()?;
         let partial_products = self.read_target_ext_vec::<D>()?;
         let quotient_polys = self.read_target_ext_vec::<D>()?;
+        let wires_next = self.read_target_ext_vec::<D>()?;
 
         Ok(OpeningSetTarget {
             constants,
             plonk_sigmas,
             wires,
+            wires_next,
             plonk_zs,
             plonk_zs_next,
             lookup_zs,
             next_lookup_zs,
             partial_products,
             quotient_polys,
+            // Challenge-dependent columns are not serializable, see `write_target_opening_set`.
+            challenge_dependent: vec![],
+            challenge_dependent_next: vec![],
         })
     }
 
@@ -436,32 +526,31 @@ pub trait Read {
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
     {
-        let config = &common_data.config;
-        let salt = salt_size(common_data.fri_params.hiding);
-        let mut evals_proofs = Vec::with_capacity(4);
+        let mut evals_proofs = Vec::with_capacity(5);
 
-        let constants_sigmas_v =
-            self.read_field_vec(common_data.num_constants + config.num_routed_wires)?;
+        let constants_sigmas_v = self.read_field_vec(common_data.salted_constants_len())?;
         let constants_sigmas_p = self.read_merkle_proof()?;
         evals_proofs.push((constants_sigmas_v, constants_sigmas_p));
 
-        let wires_v = self.read_field_vec(config.num_wires + salt)?;
+        let wires_v = self.read_field_vec(common_data.salted_wires_len())?;
         let wires_p = self.read_merkle_proof()?;
         evals_proofs.push((wires_v, wires_p));
 
-        let zs_partial_v = self.read_field_vec(
-            config.num_challenges
-                * (1 + common_data.num_partial_products + common_data.num_lookup_polys)
-                + salt,
-        )?;
+        let zs_partial_v = self.read_field_vec(common_data.salted_zs_len())?;
         let zs_partial_p = self.read_merkle_proof()?;
         evals_proofs.push((zs_partial_v, zs_partial_p));
 
-        let quotient_v =
-            self.read_field_vec(config.num_challenges * common_data.quotient_degree_factor + salt)?;
+        let quotient_v = self.read_field_vec(common_data.salted_quotient_len())?;
         let quotient_p = self.read_merkle_proof()?;
         evals_proofs.push((quotient_v, quotient_p));
 
+        if common_data.has_challenge_dependent_columns() {
+            let challenge_dependent_v =
+                self.read_field_vec(common_data.salted_challenge_dependent_len())?;
+            let challenge_dependent_p = self.read_merkle_proof()?;
+            evals_proofs.push((challenge_dependent_v, challenge_dependent_p));
+        }
+
         Ok(FriInitialTreeProof { evals_proofs })
     }
 
@@ -520,22 +609,32 @@ pub trait Read {
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
     {
-        let config = &common_data.config;
-        let mut fqrs = Vec::with_capacity(config.fri_config.num_query_rounds);
-        for _ in 0..config.fri_config.num_query_rounds {
-            let initial_trees_proof = self.read_fri_initial_proof::<F, C, D>(common_data)?;
-            let steps = common_data
-                .fri_params
-                .reduction_arity_bits
-                .iter()
-                .map(|&ar| self.read_fri_query_step::<F, C, D>(1 << ar, false))
-                .collect::<Result<_, _>>()?;
-            fqrs.push(FriQueryRound {
-                initial_trees_proof,
-                steps,
-            })
-        }
-        Ok(fqrs)
+        (0..common_data.config.fri_config.num_query_rounds)
+            .map(|_| self.read_fri_query_round::<F, C, D>(common_data))
+            .collect()
+    }
+
+    /// Reads a single [`FriQueryRound`] from `self` with `common_data`.
+    #[inline]
+    fn read_fri_query_round<F, C, const D: usize>(
+        &mut self,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<FriQueryRound<F, C::Hasher, D>>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let initial_trees_proof = self.read_fri_initial_proof::<F, C, D>(common_data)?;
+        let steps = common_data
+            .fri_params
+            .reduction_arity_bits
+            .iter()
+            .map(|&ar| self.read_fri_query_step::<F, C, D>(1 << ar, false))
+            .collect::<Result<_, _>>()?;
+        Ok(FriQueryRound {
+            initial_trees_proof,
+            steps,
+        })
     }
 
     /// Reads a vector of [`FriQueryRoundTarget`]s from `self`.
@@ -578,11 +677,14 @@ pub trait Read {
             self.read_field_ext_vec::<F, D>(common_data.fri_params.final_poly_len())?,
         );
         let pow_witness = self.read_field()?;
+        let round_pow_witnesses =
+            self.read_field_vec(common_data.fri_params.round_pow_bits().len())?;
         Ok(FriProof {
             commit_phase_merkle_caps,
             query_round_proofs,
             final_poly,
             pow_witness,
+            round_pow_witnesses,
         })
     }
 
@@ -596,12 +698,14 @@ pub trait Read {
         let query_round_proofs = self.read_target_fri_query_rounds::<D>()?;
         let final_poly = PolynomialCoeffsExtTarget(self.read_target_ext_vec::<D>()?);
         let pow_witness = self.read_target()?;
+        let round_pow_witnesses = self.read_target_vec()?;
 
         Ok(FriProofTarget {
             commit_phase_merkle_caps,
             query_round_proofs,
             final_poly,
             pow_witness,
+            round_pow_witnesses,
         })
     }
 
@@ -636,23 +740,57 @@ pub trait Read {
         }
     }
 
+    fn read_fri_pow_strategy(&mut self) -> IoResult<FriPowStrategy> {
+        match self.read_u8()? {
+            0 => Ok(FriPowStrategy::None),
+            1 => Ok(FriPowStrategy::Single(self.read_u32()?)),
+            2 => {
+                let len = self.read_usize()?;
+                let bits = (0..len)
+                    .map(|_| self.read_u32())
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(FriPowStrategy::PerRound(bits))
+            }
+            _ => Err(IoError),
+        }
+    }
+
     fn read_fri_config(&mut self) -> IoResult<FriConfig> {
+        self.read_fri_config_with_layout(ConfigLayout::CURRENT)
+    }
+
+    fn read_fri_config_with_layout(&mut self, layout: ConfigLayout) -> IoResult<FriConfig> {
         let rate_bits = self.read_usize()?;
         let cap_height = self.read_usize()?;
         let num_query_rounds = self.read_usize()?;
-        let proof_of_work_bits = self.read_u32()?;
-        let reduction_strategy = self.read_fri_reduction_strategy()?;
+        let (pow_strategy, reduction_strategy, commit_final_poly) = match layout {
+            ConfigLayout::V1 => {
+                // Such releases always ground once, right before the query phase.
+                let pow_strategy = FriPowStrategy::Single(self.read_u32()?);
+                (pow_strategy, self.read_fri_reduction_strategy()?, false)
+            }
+            ConfigLayout::V2 => (
+                self.read_fri_pow_strategy()?,
+                self.read_fri_reduction_strategy()?,
+                self.read_bool()?,
+            ),
+        };
 
         Ok(FriConfig {
             rate_bits,
             cap_height,
             num_query_rounds,
-            proof_of_work_bits,
+            pow_strategy,
             reduction_strategy,
+            commit_final_poly,
         })
     }
 
     fn read_circuit_config(&mut self) -> IoResult<CircuitConfig> {
+        self.read_circuit_config_with_layout(ConfigLayout::CURRENT)
+    }
+
+    fn read_circuit_config_with_layout(&mut self, layout: ConfigLayout) -> IoResult<CircuitConfig> {
         let num_wires = self.read_usize()?;
         let num_routed_wires = self.read_usize()?;
         let num_constants = self.read_usize()?;
@@ -660,8 +798,12 @@ pub trait Read {
         let num_challenges = self.read_usize()?;
         let max_quotient_degree_factor = self.read_usize()?;
         let use_base_arithmetic_gate = self.read_bool()?;
+        let (optimize_arithmetic, eliminate_dead_code) = match layout {
+            ConfigLayout::V1 => (false, false),
+            ConfigLayout::V2 => (self.read_bool()?, self.read_bool()?),
+        };
         let zero_knowledge = self.read_bool()?;
-        let fri_config = self.read_fri_config()?;
+        let fri_config = self.read_fri_config_with_layout(layout)?;
 
         Ok(CircuitConfig {
             num_wires,
@@ -671,15 +813,21 @@ pub trait Read {
             num_challenges,
             max_quotient_degree_factor,
             use_base_arithmetic_gate,
+            optimize_arithmetic,
+            eliminate_dead_code,
             zero_knowledge,
             fri_config,
         })
     }
 
     fn read_fri_params(&mut self) -> IoResult<FriParams> {
-        let config = self.read_fri_config()?;
+        self.read_fri_params_with_layout(ConfigLayout::CURRENT)
+    }
+
+    fn read_fri_params_with_layout(&mut self, layout: ConfigLayout) -> IoResult<FriParams> {
+        let config = self.read_fri_config_with_layout(layout)?;
         let reduction_arity_bits = self.read_usize_vec()?;
-        let degree_bits = self.read_usize()?;
+        let degree_bits = DegreeBits::new(self.read_usize()?);
         let hiding = self.read_bool()?;
 
         Ok(FriParams {
@@ -750,8 +898,16 @@ pub trait Read {
         &mut self,
         gate_serializer: &dyn GateSerializer<F, D>,
     ) -> IoResult<CommonCircuitData<F, D>> {
-        let config = self.read_circuit_config()?;
-        let fri_params = self.read_fri_params()?;
+        self.read_common_circuit_data_with_layout(gate_serializer, ConfigLayout::CURRENT)
+    }
+
+    fn read_common_circuit_data_with_layout<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        layout: ConfigLayout,
+    ) -> IoResult<CommonCircuitData<F, D>> {
+        let config = self.read_circuit_config_with_layout(layout)?;
+        let fri_params = self.read_fri_params_with_layout(layout)?;
 
         let selectors_info = self.read_selectors_info()?;
         let quotient_degree_factor = self.read_usize()?;
@@ -792,6 +948,7 @@ pub trait Read {
             num_lookup_polys,
             num_lookup_selectors,
             luts,
+            challenge_dependent_columns: vec![],
         };
 
         for _ in 0..gates_len {
@@ -844,6 +1001,22 @@ pub trait Read {
             generator_indices_by_watches.insert(k, self.read_usize_vec()?);
         }
 
+        let labels_len = self.read_usize()?;
+        let mut generator_group_labels = Vec::with_capacity(labels_len);
+        for _ in 0..labels_len {
+            let len = self.read_usize()?;
+            let mut bytes = vec![0; len];
+            self.read_exact(&mut bytes)?;
+            generator_group_labels.push(String::from_utf8(bytes).map_err(|_| IoError)?);
+        }
+        let mut generator_groups = Vec::with_capacity(gen_len);
+        for _ in 0..gen_len {
+            generator_groups.push(match self.read_bool()? {
+                true => Some(self.read_usize()?),
+                false => None,
+            });
+        }
+
         let constants_sigmas_commitment = self.read_polynomial_batch()?;
         let sigmas_len = self.read_usize()?;
         let mut sigmas = Vec::with_capacity(sigmas_len);
@@ -894,6 +1067,8 @@ pub trait Read {
         Ok(ProverOnlyCircuitData {
             generators,
             generator_indices_by_watches,
+            generator_group_labels,
+            generator_groups,
             constants_sigmas_commitment,
             sigmas,
             subgroup,
@@ -946,12 +1121,25 @@ pub trait Read {
     >(
         &mut self,
         gate_serializer: &dyn GateSerializer<F, D>,
+    ) -> IoResult<VerifierCircuitData<F, C, D>> {
+        self.read_verifier_circuit_data_with_layout(gate_serializer, ConfigLayout::CURRENT)
+    }
+
+    fn read_verifier_circuit_data_with_layout<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        &mut self,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        layout: ConfigLayout,
     ) -> IoResult<VerifierCircuitData<F, C, D>> {
         let verifier_only = self.read_verifier_only_circuit_data()?;
-        let common = self.read_common_circuit_data(gate_serializer)?;
+        let common = self.read_common_circuit_data_with_layout(gate_serializer, layout)?;
         Ok(VerifierCircuitData {
             verifier_only,
             common,
+            accepted_transcript_versions: TranscriptVersionPolicy::default(),
         })
     }
 
@@ -976,12 +1164,18 @@ pub trait Read {
     {
         let config = &common_data.config;
         let wires_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
+        let challenge_dependent_cap = if common_data.has_challenge_dependent_columns() {
+            self.read_merkle_cap(config.fri_config.cap_height)?
+        } else {
+            MerkleCap(vec![])
+        };
         let plonk_zs_partial_products_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
         let quotient_polys_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
         let openings = self.read_opening_set::<F, C, D>(common_data)?;
         let opening_proof = self.read_fri_proof::<F, C, D>(common_data)?;
         Ok(Proof {
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
@@ -999,6 +1193,7 @@ pub trait Read {
         let opening_proof = self.read_target_fri_proof::<D>()?;
         Ok(ProofTarget {
             wires_cap,
+            challenge_dependent_cap: MerkleCapTarget(vec![]),
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
@@ -1049,10 +1244,10 @@ pub trait Read {
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
     {
-        let config = &common_data.config;
-        let original_indices = (0..config.fri_config.num_query_rounds)
-            .map(|_| self.read_u32().map(|i| i as usize))
-            .collect::<Result<Vec<_>, _>>()?;
+        let original_indices = self.read_query_indices()?;
+        if original_indices.len() != common_data.config.fri_config.num_query_rounds {
+            return Err(IoError);
+        }
         let mut indices = original_indices.clone();
         indices.sort_unstable();
         indices.dedup();
@@ -1060,7 +1255,7 @@ pub trait Read {
         for &i in &indices {
             pairs.push((i, self.read_fri_initial_proof::<F, C, D>(common_data)?));
         }
-        let initial_trees_proofs = HashMap::from_iter(pairs);
+        let initial_trees_proofs = BTreeMap::from_iter(pairs);
 
         let mut steps = Vec::with_capacity(common_data.fri_params.reduction_arity_bits.len());
         for &a in &common_data.fri_params.reduction_arity_bits {
@@ -1076,7 +1271,7 @@ pub trait Read {
                     .iter()
                     .copied()
                     .zip(query_steps)
-                    .collect::<HashMap<_, _>>(),
+                    .collect::<BTreeMap<_, _>>(),
             );
         }
 
@@ -1106,11 +1301,14 @@ pub trait Read {
             self.read_field_ext_vec::<F, D>(common_data.fri_params.final_poly_len())?,
         );
         let pow_witness = self.read_field()?;
+        let round_pow_witnesses =
+            self.read_field_vec(common_data.fri_params.round_pow_bits().len())?;
         Ok(CompressedFriProof {
             commit_phase_merkle_caps,
             query_round_proofs,
             final_poly,
             pow_witness,
+            round_pow_witnesses,
         })
     }
 
@@ -1126,12 +1324,18 @@ pub trait Read {
     {
         let config = &common_data.config;
         let wires_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
+        let challenge_dependent_cap = if common_data.has_challenge_dependent_columns() {
+            self.read_merkle_cap(config.fri_config.cap_height)?
+        } else {
+            MerkleCap(vec![])
+        };
         let plonk_zs_partial_products_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
         let quotient_polys_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
         let openings = self.read_opening_set::<F, C, D>(common_data)?;
         let opening_proof = self.read_compressed_fri_proof::<F, C, D>(common_data)?;
         Ok(CompressedProof {
             wires_cap,
+            challenge_dependent_cap,
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
@@ -1238,6 +1442,41 @@ pub trait Write {
         self.write_all(&(x as u64).to_le_bytes())
     }
 
+    /// Writes `x` to `self` as an unsigned LEB128 varint, seven bits per byte.
+    #[inline]
+    fn write_varint(&mut self, mut x: u64) -> IoResult<()> {
+        while x >= 0x80 {
+            self.write_u8(x as u8 | 0x80)?;
+            x >>= 7;
+        }
+        self.write_u8(x as u8)
+    }
+
+    /// Writes FRI query indices `indices` to `self` compactly: the distinct indices in increasing
+    /// order, as varint deltas, then the position of each index among them. The order of
+    /// `indices` and any duplicates are thus preserved.
+    #[inline]
+    fn write_query_indices(&mut self, indices: &[usize]) -> IoResult<()> {
+        let mut distinct = indices.to_vec();
+        distinct.sort_unstable();
+        distinct.dedup();
+
+        self.write_varint(indices.len() as u64)?;
+        self.write_varint(distinct.len() as u64)?;
+        let mut prev = 0;
+        for &index in &distinct {
+            self.write_varint((index - prev) as u64)?;
+            prev = index;
+        }
+        for index in indices {
+            let position = distinct
+                .binary_search(index)
+                .expect("Index is among the distinct indices");
+            self.write_varint(position as u64)?;
+        }
+        Ok(())
+    }
+
     /// Writes a vector of words `v` to `self.`
     #[inline]
     fn write_usize_vec(&mut self, v: &[usize]) -> IoResult<()> {
@@ -1445,7 +1684,10 @@ pub trait Write {
         self.write_field_ext_vec::<F, D>(&os.lookup_zs)?;
         self.write_field_ext_vec::<F, D>(&os.lookup_zs_next)?;
         self.write_field_ext_vec::<F, D>(&os.partial_products)?;
-        self.write_field_ext_vec::<F, D>(&os.quotient_polys)
+        self.write_field_ext_vec::<F, D>(&os.quotient_polys)?;
+        self.write_field_ext_vec::<F, D>(&os.challenge_dependent)?;
+        self.write_field_ext_vec::<F, D>(&os.challenge_dependent_next)?;
+        self.write_field_ext_vec::<F, D>(&os.wires_next)
     }
 
     /// Writes a value `os` of type [`OpeningSet`] to `self.`
@@ -1454,6 +1696,11 @@ pub trait Write {
         &mut self,
         os: &OpeningSetTarget<D>,
     ) -> IoResult<()> {
+        // Challenge-dependent generators are not serializable, so neither are the openings of their
+        // columns. This keeps the layout of proofs without them.
+        if !os.challenge_dependent.is_empty() || !os.challenge_dependent_next.is_empty() {
+            return Err(IoError);
+        }
         self.write_target_ext_vec::<D>(&os.constants)?;
         self.write_target_ext_vec::<D>(&os.plonk_sigmas)?;
         self.write_target_ext_vec::<D>(&os.wires)?;
@@ -1462,7 +1709,8 @@ pub trait Write {
         self.write_target_ext_vec::<D>(&os.lookup_zs)?;
         self.write_target_ext_vec::<D>(&os.next_lookup_zs)?;
         self.write_target_ext_vec::<D>(&os.partial_products)?;
-        self.write_target_ext_vec::<D>(&os.quotient_polys)
+        self.write_target_ext_vec::<D>(&os.quotient_polys)?;
+        self.write_target_ext_vec::<D>(&os.wires_next)
     }
 
     /// Writes a value `p` of type [`MerkleProof`] to `self.`
@@ -1478,7 +1726,7 @@ pub trait Write {
                 .try_into()
                 .expect("Merkle proof length must fit in u8."),
         )?;
-        for &h in &p.siblings {
+        for &h in p.siblings.iter() {
             self.write_hash::<F, H>(h)?;
         }
         Ok(())
@@ -1605,7 +1853,8 @@ pub trait Write {
         }
         self.write_fri_query_rounds::<F, C, D>(&fp.query_round_proofs)?;
         self.write_field_ext_vec::<F, D>(&fp.final_poly.coeffs)?;
-        self.write_field(fp.pow_witness)
+        self.write_field(fp.pow_witness)?;
+        self.write_field_vec(&fp.round_pow_witnesses)
     }
 
     /// Writes a value `fpt` of type [`FriProofTarget`] to `self.`
@@ -1617,7 +1866,8 @@ pub trait Write {
         }
         self.write_target_fri_query_rounds::<D>(&fpt.query_round_proofs)?;
         self.write_target_ext_vec::<D>(&fpt.final_poly.0)?;
-        self.write_target(fpt.pow_witness)
+        self.write_target(fpt.pow_witness)?;
+        self.write_target_vec(&fpt.round_pow_witnesses)
     }
 
     fn write_fri_reduction_strategy(
@@ -1652,20 +1902,40 @@ pub trait Write {
         }
     }
 
+    fn write_fri_pow_strategy(&mut self, pow_strategy: &FriPowStrategy) -> IoResult<()> {
+        match pow_strategy {
+            FriPowStrategy::None => self.write_u8(0),
+            FriPowStrategy::Single(bits) => {
+                self.write_u8(1)?;
+                self.write_u32(*bits)
+            }
+            FriPowStrategy::PerRound(bits) => {
+                self.write_u8(2)?;
+                self.write_usize(bits.len())?;
+                for &b in bits {
+                    self.write_u32(b)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
     fn write_fri_config(&mut self, config: &FriConfig) -> IoResult<()> {
         let FriConfig {
             rate_bits,
             cap_height,
             num_query_rounds,
-            proof_of_work_bits,
+            pow_strategy,
             reduction_strategy,
+            commit_final_poly,
         } = &config;
 
         self.write_usize(*rate_bits)?;
         self.write_usize(*cap_height)?;
         self.write_usize(*num_query_rounds)?;
-        self.write_u32(*proof_of_work_bits)?;
+        self.write_fri_pow_strategy(pow_strategy)?;
         self.write_fri_reduction_strategy(reduction_strategy)?;
+        self.write_bool(*commit_final_poly)?;
 
         Ok(())
     }
@@ -1680,7 +1950,7 @@ pub trait Write {
 
         self.write_fri_config(config)?;
         self.write_usize_vec(reduction_arity_bits.as_slice())?;
-        self.write_usize(*degree_bits)?;
+        self.write_usize(degree_bits.get())?;
         self.write_bool(*hiding)?;
 
         Ok(())
@@ -1695,6 +1965,8 @@ pub trait Write {
             num_challenges,
             max_quotient_degree_factor,
             use_base_arithmetic_gate,
+            optimize_arithmetic,
+            eliminate_dead_code,
             zero_knowledge,
             fri_config,
         } = config;
@@ -1706,6 +1978,8 @@ pub trait Write {
         self.write_usize(*num_challenges)?;
         self.write_usize(*max_quotient_degree_factor)?;
         self.write_bool(*use_base_arithmetic_gate)?;
+        self.write_bool(*optimize_arithmetic)?;
+        self.write_bool(*eliminate_dead_code)?;
         self.write_bool(*zero_knowledge)?;
         self.write_fri_config(fri_config)?;
 
@@ -1781,8 +2055,14 @@ pub trait Write {
             num_lookup_polys,
             num_lookup_selectors,
             luts,
+            challenge_dependent_columns,
         } = common_data;
 
+        // Challenge-dependent generators are not serializable.
+        if !challenge_dependent_columns.is_empty() {
+            return Err(IoError);
+        }
+
         self.write_circuit_config(config)?;
         self.write_fri_params(fri_params)?;
 
@@ -1844,6 +2124,8 @@ pub trait Write {
         let ProverOnlyCircuitData {
             generators,
             generator_indices_by_watches,
+            generator_group_labels,
+            generator_groups,
             constants_sigmas_commitment,
             sigmas,
             subgroup,
@@ -1866,6 +2148,21 @@ pub trait Write {
             self.write_usize_vec(v)?;
         }
 
+        self.write_usize(generator_group_labels.len())?;
+        for label in generator_group_labels {
+            self.write_usize(label.len())?;
+            self.write_all(label.as_bytes())?;
+        }
+        for group in generator_groups {
+            match group {
+                Some(group) => {
+                    self.write_bool(true)?;
+                    self.write_usize(*group)?;
+                }
+                None => self.write_bool(false)?,
+            }
+        }
+
         self.write_polynomial_batch(constants_sigmas_commitment)?;
         self.write_usize(sigmas.len())?;
         for i in 0..sigmas.len() {
@@ -1980,6 +2277,8 @@ pub trait Write {
         C: GenericConfig<D, F = F>,
     {
         self.write_merkle_cap(&proof.wires_cap)?;
+        // Empty, and so not written, if the circuit has no challenge-dependent columns.
+        self.write_merkle_cap(&proof.challenge_dependent_cap)?;
         self.write_merkle_cap(&proof.plonk_zs_partial_products_cap)?;
         self.write_merkle_cap(&proof.quotient_polys_cap)?;
         self.write_opening_set(&proof.openings)?;
@@ -1989,6 +2288,10 @@ pub trait Write {
     /// Writes a value `proof` of type [`Proof`] to `self.`
     #[inline]
     fn write_target_proof<const D: usize>(&mut self, proof: &ProofTarget<D>) -> IoResult<()> {
+        // Challenge-dependent generators are not serializable, see `write_target_opening_set`.
+        if !proof.challenge_dependent_cap.0.is_empty() {
+            return Err(IoError);
+        }
         self.write_target_merkle_cap(&proof.wires_cap)?;
         self.write_target_merkle_cap(&proof.plonk_zs_partial_products_cap)?;
         self.write_target_merkle_cap(&proof.quotient_polys_cap)?;
@@ -2039,18 +2342,12 @@ pub trait Write {
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
     {
-        for &i in &cfqrs.indices {
-            self.write_u32(i as u32)?;
-        }
-        let mut initial_trees_proofs = cfqrs.initial_trees_proofs.iter().collect::<Vec<_>>();
-        initial_trees_proofs.sort_by_key(|&x| x.0);
-        for (_, itp) in initial_trees_proofs {
+        self.write_query_indices(&cfqrs.indices)?;
+        for itp in cfqrs.initial_trees_proofs.values() {
             self.write_fri_initial_proof::<F, C, D>(itp)?;
         }
         for h in &cfqrs.steps {
-            let mut fri_query_steps = h.iter().collect::<Vec<_>>();
-            fri_query_steps.sort_by_key(|&x| x.0);
-            for (_, fqs) in fri_query_steps {
+            for fqs in h.values() {
                 self.write_fri_query_step::<F, C, D>(fqs)?;
             }
         }
@@ -2072,7 +2369,8 @@ pub trait Write {
         }
         self.write_compressed_fri_query_rounds::<F, C, D>(&fp.query_round_proofs)?;
         self.write_field_ext_vec::<F, D>(&fp.final_poly.coeffs)?;
-        self.write_field(fp.pow_witness)
+        self.write_field(fp.pow_witness)?;
+        self.write_field_vec(&fp.round_pow_witnesses)
     }
 
     /// Writes a value `proof` of type [`CompressedProof`] to `self.`
@@ -2086,6 +2384,8 @@ pub trait Write {
         C: GenericConfig<D, F = F>,
     {
         self.write_merkle_cap(&proof.wires_cap)?;
+        // Empty, and so not written, if the circuit has no challenge-dependent columns.
+        self.write_merkle_cap(&proof.challenge_dependent_cap)?;
         self.write_merkle_cap(&proof.plonk_zs_partial_products_cap)?;
         self.write_merkle_cap(&proof.quotient_polys_cap)?;
         self.write_opening_set(&proof.openings)?;
@@ -0,0 +1,74 @@
+//! The circuit shared by the tests pinned by binary fixtures, and a helper to regenerate them.
+
+use anyhow::Result;
+
+use crate::field::types::Field;
+use crate::fri::reduction_strategies::FriReductionStrategy;
+use crate::fri::{FriConfig, FriPowStrategy};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use crate::plonk::proof::ProofWithPublicInputs;
+
+pub(crate) const D: usize = 2;
+pub(crate) type C = PoseidonGoldilocksConfig;
+pub(crate) type F = <C as GenericConfig<D>>::F;
+
+/// The public `x` the fixture proofs are generated for.
+pub(crate) const FIXTURE_X: u64 = 7;
+
+/// The FRI config of most fixtures. It only uses options which releases predating the FRI
+/// extensions could express, so that their archived proofs share it.
+pub(crate) fn fixture_fri_config() -> FriConfig {
+    FriConfig {
+        rate_bits: 3,
+        cap_height: 2,
+        pow_strategy: FriPowStrategy::Single(4),
+        reduction_strategy: FriReductionStrategy::ConstantArityBits(2, 3),
+        num_query_rounds: 2,
+        commit_final_poly: false,
+    }
+}
+
+pub(crate) fn fixture_config(zero_knowledge: bool, fri_config: FriConfig) -> CircuitConfig {
+    CircuitConfig {
+        // Few query rounds keep the fixtures small.
+        security_bits: 6,
+        zero_knowledge,
+        fri_config,
+        ..CircuitConfig::standard_recursion_config()
+    }
+}
+
+/// Proves that `y = x^2 + c` for a public `x` and `y`.
+pub(crate) fn fixture_circuit(config: CircuitConfig, c: u64) -> (CircuitData<F, C, D>, Target) {
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+    let x = builder.add_virtual_public_input();
+    let x_squared = builder.square(x);
+    let c = builder.constant(F::from_canonical_u64(c));
+    let y = builder.add(x_squared, c);
+    builder.register_public_input(y);
+    (builder.build::<C>(), x)
+}
+
+/// Proves the fixture circuit for `x = FIXTURE_X`.
+pub(crate) fn prove_fixture(
+    data: &CircuitData<F, C, D>,
+    x: Target,
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    let mut pw = PartialWitness::new();
+    pw.set_target(x, F::from_canonical_u64(FIXTURE_X));
+    data.prove(pw)
+}
+
+/// Overwrites the fixture at `path`, relative to the crate's `src` directory. Only the ignored
+/// tests regenerating fixtures after a deliberate format change call this.
+#[cfg(feature = "std")]
+pub(crate) fn write_fixture(path: &str, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join(path);
+    std::fs::write(path, contents)
+}
@@ -0,0 +1,72 @@
+//! A global allocator accounting for the allocations of the whole process. A test using it must
+//! live in its own test binary, which registers it with
+//! `#[global_allocator] static ALLOCATOR: TrackingAllocator = TrackingAllocator;`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Tracks the number of bytes allocated and its peak, as well as the number of allocations and the
+/// bytes they requested.
+pub struct TrackingAllocator;
+
+impl TrackingAllocator {
+    fn grow(size: usize) {
+        COUNT.fetch_add(1, Ordering::SeqCst);
+        BYTES.fetch_add(size, Ordering::SeqCst);
+        let live = LIVE.fetch_add(size, Ordering::SeqCst) + size;
+        PEAK.fetch_max(live, Ordering::SeqCst);
+    }
+
+    fn shrink(size: usize) {
+        LIVE.fetch_sub(size, Ordering::SeqCst);
+    }
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            Self::grow(new_size);
+            Self::shrink(layout.size());
+        }
+        new_ptr
+    }
+}
+
+/// The peak number of bytes allocated while running `f`, beyond those allocated beforehand.
+pub fn peak_allocation<T>(f: impl FnOnce() -> T) -> (usize, T) {
+    let base = LIVE.load(Ordering::SeqCst);
+    PEAK.store(base, Ordering::SeqCst);
+    let result = f();
+    (PEAK.load(Ordering::SeqCst) - base, result)
+}
+
+/// The number of allocations made while running `f`, and the bytes they allocated.
+pub fn count_allocations<T>(f: impl FnOnce() -> T) -> (usize, usize, T) {
+    let count = COUNT.load(Ordering::SeqCst);
+    let bytes = BYTES.load(Ordering::SeqCst);
+    let result = f();
+    (
+        COUNT.load(Ordering::SeqCst) - count,
+        BYTES.load(Ordering::SeqCst) - bytes,
+        result,
+    )
+}
@@ -0,0 +1,88 @@
+//! Inputs to `QuotientComputation` over random oracles.
+
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Sample;
+use plonky2::fri::oracle::PolynomialBatch;
+use plonky2::gates::noop::NoopGate;
+use plonky2::hash::hash_types::HashOut;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::prover::QuotientComputation;
+use plonky2::util::timing::TimingTree;
+
+pub const D: usize = 2;
+pub type C = PoseidonGoldilocksConfig;
+pub type F = <C as GenericConfig<D>>::F;
+
+/// A circuit computing `x^2 + x`, padded with no-ops to `2^degree_bits` gates.
+pub fn padded_circuit(degree_bits: usize) -> CircuitData<F, C, D> {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    let x = builder.add_virtual_public_input();
+    let y = builder.mul_add(x, x, x);
+    builder.register_public_input(y);
+    while builder.num_gates() < 1 << degree_bits {
+        builder.add_gate(NoopGate, vec![]);
+    }
+    builder.build::<C>()
+}
+
+/// The oracles and challenges the quotients of a circuit are computed from.
+pub struct QuotientInputs {
+    pub data: CircuitData<F, C, D>,
+    wires_commitment: PolynomialBatch<F, C, D>,
+    zs_commitment: PolynomialBatch<F, C, D>,
+    public_inputs_hash: HashOut<F>,
+    betas: Vec<F>,
+    gammas: Vec<F>,
+    alphas: Vec<F>,
+}
+
+impl QuotientInputs {
+    pub fn new(data: CircuitData<F, C, D>) -> Self {
+        let common_data = &data.common;
+        let num_challenges = common_data.config.num_challenges;
+        // The quotients are computed from whatever was committed, so random oracles will do.
+        let commit = |num_polys: usize| {
+            PolynomialBatch::<F, C, D>::from_values(
+                (0..num_polys)
+                    .map(|_| PolynomialValues::new(F::rand_vec(common_data.degree())))
+                    .collect(),
+                common_data.config.fri_config.rate_bits,
+                false,
+                common_data.config.fri_config.cap_height,
+                &mut TimingTree::default(),
+                data.prover_only.fft_root_table.as_ref(),
+            )
+        };
+        let wires_commitment = commit(common_data.config.num_wires);
+        let zs_commitment = commit(num_challenges * (1 + common_data.num_partial_products));
+        Self {
+            wires_commitment,
+            zs_commitment,
+            public_inputs_hash: HashOut::rand(),
+            betas: F::rand_vec(num_challenges),
+            gammas: F::rand_vec(num_challenges),
+            alphas: F::rand_vec(num_challenges),
+            data,
+        }
+    }
+
+    /// The computation of the quotients in blocks of `2^block_bits` rows.
+    pub fn computation(&self, block_bits: usize) -> QuotientComputation<'_, F, C, D> {
+        QuotientComputation {
+            common_data: &self.data.common,
+            prover_data: &self.data.prover_only,
+            public_inputs_hash: &self.public_inputs_hash,
+            wires_commitment: &self.wires_commitment,
+            zs_partial_products_and_lookup_commitment: &self.zs_commitment,
+            challenge_dependent_commitment: None,
+            betas: &self.betas,
+            gammas: &self.gammas,
+            deltas: &[],
+            challenge_dependent_challenges: &[],
+            alphas: &self.alphas,
+            block_bits,
+        }
+    }
+}
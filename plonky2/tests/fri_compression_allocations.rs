@@ -0,0 +1,79 @@
+//! Allocations of FRI proof compression and decompression.
+
+mod common;
+
+use common::allocator::{count_allocations, TrackingAllocator};
+use plonky2::gates::noop::NoopGate;
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+#[test]
+fn test_fri_compression_allocations() {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    for _ in 0..100 {
+        builder.add_gate(NoopGate, vec![]);
+    }
+    let data = builder.build::<C>();
+    let proof = data.prove(PartialWitness::new()).unwrap();
+    let digest = &data.verifier_only.circuit_digest;
+    let challenges = proof
+        .get_challenges(proof.get_public_inputs_hash(), digest, &data.common)
+        .unwrap();
+    let indices = &challenges.fri_challenges.fri_query_indices;
+    let params = &data.common.fri_params;
+
+    let num_rounds = indices.len();
+    let num_trees = proof.proof.opening_proof.query_round_proofs[0]
+        .initial_trees_proof
+        .evals_proofs
+        .len();
+    let num_steps = params.reduction_arity_bits.len();
+    let num_openings = num_rounds * (num_trees + num_steps);
+
+    // The baseline is a deep copy of the opening proof, which allocates each opened leaf and each
+    // Merkle path.
+    let (clone_count, _, fri_proof) = count_allocations(|| proof.proof.opening_proof.clone());
+    assert!(
+        clone_count >= 2 * num_openings,
+        "{clone_count} allocations to clone {num_openings} openings"
+    );
+
+    // Compression allocates each opening's compressed Merkle path, rather than cloning the query
+    // rounds.
+    let (compress_count, _, compressed) =
+        count_allocations(|| proof.proof.clone().compress(indices, params));
+    let (proof_clone_count, _, _) = count_allocations(|| proof.proof.clone());
+    let compress_count = compress_count - proof_clone_count;
+    assert!(
+        compress_count <= (2 * num_openings).min(clone_count),
+        "{compress_count} allocations to compress {num_openings} openings, {clone_count} to clone them"
+    );
+    assert_eq!(
+        compressed.opening_proof,
+        fri_proof.compress(indices, params)
+    );
+
+    // Decompression builds the same owned proof as a clone, and beyond that hashes each opened leaf
+    // and infers the folded evaluations. It starts by deriving the challenges, which is not counted.
+    let compressed = proof.clone().compress(digest, &data.common).unwrap();
+    let (challenges_count, _, _) = count_allocations(|| {
+        proof.get_challenges(proof.get_public_inputs_hash(), digest, &data.common)
+    });
+    let (decompress_count, _, decompressed) =
+        count_allocations(|| compressed.decompress(digest, &data.common));
+    assert_eq!(decompressed.unwrap(), proof);
+    let decompress_count = decompress_count - challenges_count;
+    assert!(
+        decompress_count <= clone_count + 6 * num_openings,
+        "{decompress_count} allocations to decompress {num_openings} openings, {clone_count} to clone them"
+    );
+}
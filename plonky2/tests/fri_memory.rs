@@ -0,0 +1,103 @@
+//! Memory accounting of the FRI commit phase.
+
+mod common;
+
+use common::allocator::{peak_allocation, TrackingAllocator};
+use plonky2::field::extension::Extendable;
+use plonky2::field::polynomial::{FlatExtensionPolynomial, PolynomialCoeffs};
+use plonky2::field::types::{Field, Sample};
+use plonky2::fri::folding::fold_polynomial_coeffs;
+use plonky2::fri::prover::fri_proof_from_values;
+use plonky2::fri::reduction_strategies::FriReductionStrategy;
+use plonky2::fri::{FriConfig, FriParams, FriPowStrategy};
+use plonky2::hash::merkle_tree::MerkleTree;
+use plonky2::iop::challenger::Challenger;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::util::reverse_index_bits_in_place;
+use plonky2::util::timing::TimingTree;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+type FE = <F as Extendable<D>>::Extension;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+/// The commit phase folding the coefficients, and evaluating each folded polynomial afresh, so that
+/// both forms of the current polynomial are alive while its Merkle tree is built.
+fn commit_folding_coefficients(mut coeffs: PolynomialCoeffs<FE>, fri_params: &FriParams) {
+    let mut challenger = Challenger::<F, <C as GenericConfig<D>>::Hasher>::new();
+    let mut trees = Vec::new();
+    let mut shift = F::MULTIPLICATIVE_GROUP_GENERATOR;
+    let mut values = coeffs.coset_fft(shift.into());
+    for &arity_bits in &fri_params.reduction_arity_bits {
+        reverse_index_bits_in_place(&mut values.values);
+        let leaves = values
+            .values
+            .chunks(1 << arity_bits)
+            .map(|chunk| chunk.iter().flat_map(|x| x.0).collect())
+            .collect();
+        let tree = MerkleTree::<F, <C as GenericConfig<D>>::Hasher>::new(
+            leaves,
+            fri_params.config.cap_height,
+        );
+        challenger.observe_cap(&tree.cap);
+        trees.push(tree);
+        let beta = challenger.get_extension_challenge::<D>();
+        coeffs = fold_polynomial_coeffs(&coeffs, beta, arity_bits);
+        shift = shift.exp_power_of_2(arity_bits);
+        values = coeffs.coset_fft(shift.into());
+    }
+}
+
+#[test]
+fn test_fri_commit_phase_peak_memory() {
+    let degree_bits = 20;
+    let reduction_arity_bits = vec![4, 4, 4, 4];
+    let fri_params = FriParams {
+        config: FriConfig {
+            rate_bits: 1,
+            cap_height: 4,
+            pow_strategy: FriPowStrategy::None,
+            reduction_strategy: FriReductionStrategy::Fixed(reduction_arity_bits.clone()),
+            num_query_rounds: 2,
+            commit_final_poly: false,
+        },
+        hiding: false,
+        degree_bits: degree_bits.into(),
+        reduction_arity_bits,
+    };
+    let rate_bits = fri_params.config.rate_bits;
+    let coeffs = PolynomialCoeffs::new(FE::rand_vec(1 << degree_bits)).lde(rate_bits);
+    // The size of the codeword, which both peaks below include once as their input.
+    let codeword_bytes = coeffs.len() * std::mem::size_of::<FE>();
+
+    let flat = FlatExtensionPolynomial::<F, D>::from(coeffs.clone());
+    let (folding_coefficients, ()) =
+        peak_allocation(|| commit_folding_coefficients(coeffs, &fri_params));
+    let (folding_values, ()) = peak_allocation(|| {
+        let mut values = flat;
+        values.coset_fft_in_place(F::MULTIPLICATIVE_GROUP_GENERATOR);
+        fri_proof_from_values::<F, C, D>(
+            &[],
+            values,
+            &mut Challenger::new(),
+            &fri_params,
+            &mut TimingTree::default(),
+        );
+    });
+
+    // Folding the coefficients keeps them alongside the values and the leaves, so about three
+    // codewords, while folding the values in place keeps about two.
+    let folding_coefficients = folding_coefficients + codeword_bytes;
+    let folding_values = folding_values + codeword_bytes;
+    assert!(
+        folding_coefficients >= 3 * codeword_bytes,
+        "{folding_coefficients} bytes folding the coefficients"
+    );
+    assert!(
+        4 * folding_values < 3 * folding_coefficients,
+        "{folding_values} bytes folding the values, {folding_coefficients} folding the coefficients"
+    );
+}
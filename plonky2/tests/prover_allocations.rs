@@ -0,0 +1,59 @@
+//! Allocations of repeated proofs reusing `ProverBuffers`.
+
+mod common;
+
+use std::mem::size_of;
+
+use common::allocator::{count_allocations, TrackingAllocator};
+use plonky2::field::types::Field;
+use plonky2::gates::noop::NoopGate;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::prover::ProverBuffers;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+#[test]
+fn test_prover_buffers_allocations() {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    let x = builder.add_virtual_public_input();
+    let y = builder.mul(x, x);
+    builder.register_public_input(y);
+    for _ in 0..(1 << 12) {
+        builder.add_gate(NoopGate, vec![]);
+    }
+    let data = builder.build::<C>();
+
+    let prove = |buffers: &mut ProverBuffers<F, _>, x_value: u64| {
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(x_value));
+        let (count, bytes, proof) = count_allocations(|| data.prove_with_buffers(pw, buffers));
+        data.verify(proof.unwrap()).unwrap();
+        (count, bytes)
+    };
+
+    let (fresh_count, fresh_bytes) = prove(&mut ProverBuffers::default(), 3);
+    let mut buffers = ProverBuffers::new(&data.common);
+    prove(&mut buffers, 4);
+    let (reused_count, reused_bytes) = prove(&mut buffers, 5);
+
+    // Each oracle committed by the prover reuses its LDEs, the rows of its Merkle tree and its
+    // digests. The wires alone take up `num_wires` columns of LDEs, and as many in the rows.
+    let lde_size = data.common.lde_size();
+    let wire_ldes_bytes = data.common.config.num_wires * lde_size * size_of::<F>();
+    assert!(
+        fresh_count - reused_count >= 3 * lde_size,
+        "{reused_count} allocations with reused buffers, {fresh_count} without"
+    );
+    assert!(
+        fresh_bytes - reused_bytes >= 2 * wire_ldes_bytes,
+        "{reused_bytes} bytes allocated with reused buffers, {fresh_bytes} without"
+    );
+}
@@ -0,0 +1,23 @@
+//! The quotient polynomials don't depend on the blocks they are computed in.
+
+mod common;
+
+use common::quotient::{padded_circuit, QuotientInputs};
+use plonky2::plonk::prover::QUOTIENT_BLOCK_BITS;
+
+#[test]
+fn test_quotient_block_size_independence() {
+    let inputs = QuotientInputs::new(padded_circuit(8));
+    let num_challenges = inputs.data.common.config.num_challenges;
+
+    // Blocks smaller than a batch, a few batches, and the whole domain.
+    let expected = inputs.computation(QUOTIENT_BLOCK_BITS).compute();
+    assert_eq!(expected.len(), num_challenges);
+    for block_bits in [0, 3, 7, usize::MAX] {
+        assert_eq!(
+            inputs.computation(block_bits).compute(),
+            expected,
+            "block_bits = {block_bits}"
+        );
+    }
+}
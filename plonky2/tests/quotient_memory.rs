@@ -0,0 +1,34 @@
+//! Memory accounting of the quotient polynomial computation.
+
+mod common;
+
+use std::mem::size_of;
+
+use common::allocator::{peak_allocation, TrackingAllocator};
+use common::quotient::{padded_circuit, QuotientInputs, F};
+use plonky2::plonk::prover::QUOTIENT_BLOCK_BITS;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+#[test]
+fn test_quotient_peak_memory() {
+    let inputs = QuotientInputs::new(padded_circuit(14));
+    let num_challenges = inputs.data.common.config.num_challenges;
+
+    let expected = inputs.computation(QUOTIENT_BLOCK_BITS).compute();
+    let output_bytes = expected.iter().map(|p| p.len()).sum::<usize>() * size_of::<F>();
+    let column_bytes = output_bytes / num_challenges;
+    for block_bits in [QUOTIENT_BLOCK_BITS, usize::MAX] {
+        let (peak, polys) = peak_allocation(|| inputs.computation(block_bits).compute());
+        assert_eq!(polys, expected);
+        // Besides the output, the peak holds the FFT root table of the interpolation, as large as
+        // one output column, and the workers' buffers for a batch of rows each. Keeping a vector
+        // of quotient values per row and a transposed copy of them took about four times the
+        // output on top.
+        assert!(
+            peak < output_bytes + 2 * column_bytes,
+            "{peak} bytes with {block_bits} block bits, for {output_bytes} bytes of quotients"
+        );
+    }
+}
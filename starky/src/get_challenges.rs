@@ -10,6 +10,7 @@ use plonky2::iop::challenger::{Challenger, RecursiveChallenger};
 use plonky2::iop::target::Target;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::util::log_size::DegreeBits;
 
 use crate::config::StarkConfig;
 use crate::lookup::{get_grand_product_challenge_set, get_grand_product_challenge_set_target};
@@ -24,6 +25,7 @@ fn get_challenges<F, C, const D: usize>(
     commit_phase_merkle_caps: &[MerkleCap<F, C::Hasher>],
     final_poly: &PolynomialCoeffs<F::Extension>,
     pow_witness: F,
+    round_pow_witnesses: &[F],
     config: &StarkConfig,
     degree_bits: usize,
 ) -> StarkProofChallenges<F, D>
@@ -58,7 +60,8 @@ where
             commit_phase_merkle_caps,
             final_poly,
             pow_witness,
-            degree_bits,
+            round_pow_witnesses,
+            DegreeBits::new(degree_bits),
             &config.fri_config,
         ),
     }
@@ -93,6 +96,7 @@ where
                     commit_phase_merkle_caps,
                     final_poly,
                     pow_witness,
+                    round_pow_witnesses,
                     ..
                 },
         } = &self.proof;
@@ -105,6 +109,7 @@ where
             commit_phase_merkle_caps,
             final_poly,
             *pow_witness,
+            round_pow_witnesses,
             config,
             degree_bits,
         )
@@ -125,6 +130,7 @@ pub(crate) fn get_challenges_target<
     commit_phase_merkle_caps: &[MerkleCapTarget],
     final_poly: &PolynomialCoeffsExtTarget<D>,
     pow_witness: Target,
+    round_pow_witnesses: &[Target],
     config: &StarkConfig,
 ) -> StarkProofChallengesTarget<D>
 where
@@ -158,6 +164,7 @@ where
             commit_phase_merkle_caps,
             final_poly,
             pow_witness,
+            round_pow_witnesses,
             &config.fri_config,
         ),
     }
@@ -184,6 +191,7 @@ impl<const D: usize> StarkProofWithPublicInputsTarget<D> {
                     commit_phase_merkle_caps,
                     final_poly,
                     pow_witness,
+                    round_pow_witnesses,
                     ..
                 },
         } = &self.proof;
@@ -197,6 +205,7 @@ impl<const D: usize> StarkProofWithPublicInputsTarget<D> {
             commit_phase_merkle_caps,
             final_poly,
             *pow_witness,
+            round_pow_witnesses,
             config,
         )
     }